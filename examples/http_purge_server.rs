@@ -4,7 +4,12 @@
 //! PURGE requests for cache invalidation.
 //!
 //! Usage:
-//!   cargo run --example http_purge_server
+//!   cargo run --example http_purge_server [-- /path/to/config.yaml]
+//!
+//! The configuration (default `pingora_slice.yaml`) sets the origin, slice
+//! size and retries the cache warmer fetches with. To warm the cache from a
+//! file of URLs at startup, run the server binary instead:
+//!   cargo run -- pingora_slice.yaml --warm urls.txt
 //!
 //! Then test with curl:
 //!   # Purge specific URL
@@ -13,16 +18,41 @@
 //!   # Purge all cache
 //!   curl -X PURGE http://localhost:8080/* -H "X-Purge-All: true"
 //!
+//!   # Purge a list of URLs
+//!   curl -X PURGE http://localhost:8080/purge/batch -d '["/test.dat", "/video.mp4"]'
+//!
 //!   # Purge with authentication
 //!   curl -X PURGE http://localhost:8080/test.dat -H "Authorization: Bearer secret-token"
+//!
+//!   # Inspect a cache entry
+//!   curl "http://localhost:8080/admin/cache/entry?key=http://localhost:8080/test.dat:slice:0:1023"
+//!
+//!   # List cached keys
+//!   curl "http://localhost:8080/admin/cache/keys?prefix=http://localhost:8080/test.dat&limit=10"
+//!
+//!   # Remove a single cache entry
+//!   curl -X DELETE "http://localhost:8080/admin/cache/entry?key=http://localhost:8080/test.dat:slice:0:1023"
+//!
+//!   # Warm the cache for a list of origin URLs
+//!   curl -X POST http://localhost:8080/admin/warm -d '["http://origin.example.com/video.mp4"]'
+//!
+//!   # Check warm-up progress
+//!   curl http://localhost:8080/admin/warm/status
+//!
+//!   # Prometheus metrics, served by `MetricsEndpoint` on its own port
+//!   curl http://localhost:9090/metrics
 
 use bytes::Bytes;
 use http::{Request, Response, StatusCode};
-use http_body_util::{BodyExt, Empty, Full};
+use http_body_util::Full;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper_util::rt::TokioIo;
+use pingora_slice::cache_admin::CacheAdminHandler;
+use pingora_slice::cache_warmer::{CacheWarmer, WarmHandler};
 use pingora_slice::config::SliceConfig;
+use pingora_slice::metrics::SliceMetrics;
+use pingora_slice::metrics_endpoint::MetricsEndpoint;
 use pingora_slice::models::ByteRange;
 use pingora_slice::purge_handler::PurgeHandler;
 use pingora_slice::purge_metrics::PurgeMetrics;
@@ -37,11 +67,14 @@ use tracing::{error, info};
 struct ServerState {
     cache: Arc<TieredCache>,
     purge_handler: Arc<PurgeHandler>,
-    purge_metrics: Option<Arc<PurgeMetrics>>,
+    cache_admin: Arc<CacheAdminHandler>,
+    warm_handler: Arc<WarmHandler>,
+    /// Registry the PURGE metrics are collected in, served by the metrics endpoint
+    registry: prometheus::Registry,
 }
 
 impl ServerState {
-    async fn new() -> Result<Self, Box<dyn std::error::Error>> {
+    async fn new(config: Arc<SliceConfig>) -> Result<Self, Box<dyn std::error::Error>> {
         // Create cache directory
         let cache_dir = tempfile::tempdir()?;
         info!("Cache directory: {:?}", cache_dir.path());
@@ -57,22 +90,34 @@ impl ServerState {
         );
 
         // Create PURGE metrics
-        let purge_metrics = Arc::new(PurgeMetrics::new().expect("Failed to create purge metrics"));
+        let registry = prometheus::Registry::new();
+        let purge_metrics = Arc::new(PurgeMetrics::with_registry(&registry)?);
         info!("PURGE metrics enabled");
 
-        // Create PURGE handler (with optional authentication)
-        let purge_handler = if std::env::var("PURGE_TOKEN").is_ok() {
+        // Create the cache warmer, fetching with the loaded configuration
+        let warmer = Arc::new(CacheWarmer::new(config, cache.clone()));
+
+        // Create PURGE and cache admin handlers (with optional authentication)
+        let (purge_handler, cache_admin, warm_handler) = if std::env::var("PURGE_TOKEN").is_ok() {
             let token = std::env::var("PURGE_TOKEN").unwrap();
             info!("PURGE authentication enabled");
-            Arc::new(
-                PurgeHandler::with_auth(cache.clone(), token)
-                    .with_metrics(purge_metrics.clone())
+            (
+                Arc::new(
+                    PurgeHandler::with_auth(cache.clone(), token.clone())
+                        .with_metrics(purge_metrics.clone())
+                ),
+                Arc::new(CacheAdminHandler::with_auth(cache.clone(), token.clone())),
+                Arc::new(WarmHandler::with_auth(warmer.clone(), token)),
             )
         } else {
             info!("PURGE authentication disabled (set PURGE_TOKEN env var to enable)");
-            Arc::new(
-                PurgeHandler::new(cache.clone())
-                    .with_metrics(purge_metrics.clone())
+            (
+                Arc::new(
+                    PurgeHandler::new(cache.clone())
+                        .with_metrics(purge_metrics.clone())
+                ),
+                Arc::new(CacheAdminHandler::new(cache.clone())),
+                Arc::new(WarmHandler::new(warmer.clone())),
             )
         };
 
@@ -85,7 +130,9 @@ impl ServerState {
         Ok(Self {
             cache,
             purge_handler,
-            purge_metrics: Some(purge_metrics),
+            cache_admin,
+            warm_handler,
+            registry,
         })
     }
 
@@ -129,8 +176,19 @@ async fn handle_request(
 
     info!("{} {}", method, uri);
 
-    // Check if this is a PURGE request
-    if method.as_str() == "PURGE" {
+    // Check if this is a batch PURGE request
+    if PurgeHandler::is_batch_request(&req) {
+        match state.purge_handler.handle_batch_purge(req).await {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                error!("Batch PURGE request failed: {}", e);
+                Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Full::new(Bytes::from(format!("Error: {}", e))))
+                    .unwrap())
+            }
+        }
+    } else if method.as_str() == "PURGE" {
         // Handle PURGE request
         match state.purge_handler.handle_purge(req).await {
             Ok(response) => Ok(response),
@@ -142,36 +200,39 @@ async fn handle_request(
                     .unwrap())
             }
         }
+    } else if CacheAdminHandler::matches(uri.path()) {
+        // Cache admin endpoints
+        match state.cache_admin.handle_request(req).await {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                error!("Cache admin request failed: {}", e);
+                Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Full::new(Bytes::from(format!("Error: {}", e))))
+                    .unwrap())
+            }
+        }
+    } else if WarmHandler::matches(uri.path()) {
+        // Cache warm-up endpoints
+        match state.warm_handler.handle_request(req).await {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                error!("Warm request failed: {}", e);
+                Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Full::new(Bytes::from(format!("Error: {}", e))))
+                    .unwrap())
+            }
+        }
     } else if method == hyper::Method::GET && uri.path() == "/stats" {
         // Return cache statistics
         let stats = state.cache.get_stats();
-        let json = serde_json::json!({
-            "l1_entries": stats.l1_entries,
-            "l1_bytes": stats.l1_bytes,
-            "l1_hits": stats.l1_hits,
-            "l2_hits": stats.l2_hits,
-            "misses": stats.misses,
-            "disk_writes": stats.disk_writes,
-            "disk_errors": stats.disk_errors,
-        });
+        let json = serde_json::to_string(&stats).unwrap();
 
         Ok(Response::builder()
             .status(StatusCode::OK)
             .header("content-type", "application/json")
-            .body(Full::new(Bytes::from(json.to_string())))
-            .unwrap())
-    } else if method == hyper::Method::GET && uri.path() == "/metrics" {
-        // Return Prometheus metrics
-        use prometheus::Encoder;
-        let encoder = prometheus::TextEncoder::new();
-        let metric_families = prometheus::gather();
-        let mut buffer = vec![];
-        encoder.encode(&metric_families, &mut buffer).unwrap();
-
-        Ok(Response::builder()
-            .status(StatusCode::OK)
-            .header("content-type", encoder.format_type())
-            .body(Full::new(Bytes::from(buffer)))
+            .body(Full::new(Bytes::from(json)))
             .unwrap())
     } else if method == hyper::Method::GET {
         // Simple GET handler (for testing)
@@ -223,8 +284,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("Starting HTTP PURGE server...");
 
+    // Load the configuration the cache warmer fetches with
+    let config_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "pingora_slice.yaml".to_string());
+    info!("Loading configuration from: {}", config_path);
+    let config = Arc::new(SliceConfig::load(&config_path)?);
+
     // Create server state
-    let state = Arc::new(ServerState::new().await?);
+    let state = Arc::new(ServerState::new(config).await?);
+
+    // Serve Prometheus metrics on a separate port
+    let metrics_addr: SocketAddr = "127.0.0.1:9090".parse()?;
+    let metrics_endpoint = MetricsEndpoint::new(Arc::new(SliceMetrics::new()), metrics_addr)
+        .with_registry(state.registry.clone());
+    tokio::spawn(async move {
+        if let Err(e) = metrics_endpoint.start().await {
+            error!("Metrics endpoint failed: {}", e);
+        }
+    });
 
     // Bind to address
     let addr: SocketAddr = "127.0.0.1:8080".parse()?;
@@ -237,7 +315,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("  curl http://localhost:8080/stats");
     info!("");
     info!("  # Get Prometheus metrics");
-    info!("  curl http://localhost:8080/metrics");
+    info!("  curl http://localhost:9090/metrics");
     info!("");
     info!("  # Get cached file (should HIT)");
     info!("  curl http://localhost:8080/test.dat");
@@ -248,9 +326,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("  # Verify it's purged (should MISS)");
     info!("  curl http://localhost:8080/test.dat");
     info!("");
+    info!("  # Inspect a cache entry");
+    info!("  curl 'http://localhost:8080/admin/cache/entry?key=http://localhost:8080/test.dat:slice:0:1023'");
+    info!("");
+    info!("  # List cached keys");
+    info!("  curl 'http://localhost:8080/admin/cache/keys?prefix=http://localhost:8080/test.dat'");
+    info!("");
+    info!("  # Remove a single cache entry");
+    info!("  curl -X DELETE 'http://localhost:8080/admin/cache/entry?key=http://localhost:8080/test.dat:slice:0:1023'");
+    info!("");
     info!("  # Purge all cache");
     info!("  curl -X PURGE http://localhost:8080/* -H 'X-Purge-All: true'");
     info!("");
+    info!("  # Warm the cache and check progress");
+    info!("  curl -X POST http://localhost:8080/admin/warm -d '[\"http://origin.example.com/video.mp4\"]'");
+    info!("  curl http://localhost:8080/admin/warm/status");
+    info!("");
     if std::env::var("PURGE_TOKEN").is_ok() {
         info!("  # Purge with authentication");
         info!(
@@ -0,0 +1,37 @@
+//! Example: Slice Proxy Server
+//!
+//! Runs the slice proxy as a standalone HTTP server using
+//! `run_slice_server`, with the listen address, worker threads and PID file
+//! taken from the configuration file.
+//!
+//! # Usage
+//! ```bash
+//! cargo run --example slice_server -- examples/pingora_slice.yaml
+//! ```
+//!
+//! Then fetch a file through the proxy:
+//! ```bash
+//! curl -o /dev/null http://127.0.0.1:8080/large-files/video.mp4
+//! ```
+//!
+//! Stop it with Ctrl-C; in-flight requests are allowed to finish.
+
+use pingora_slice::run_slice_server;
+use std::env;
+use tracing::{error, Level};
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .with_target(false)
+        .init();
+
+    let config_path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "pingora_slice.yaml".to_string());
+
+    if let Err(e) = run_slice_server(&config_path) {
+        error!("Server failed: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,517 @@
+//! Structured per-request access log
+//!
+//! Records are handed to a dedicated writer task over a bounded channel so
+//! that logging never blocks the request path. When the channel is full the
+//! record is dropped and counted instead of waiting for the writer.
+
+use crate::config::{AccessLogConfig, AccessLogFormat};
+use crate::error::{Result, SliceError};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tracing::{debug, warn};
+
+/// How the response was served with respect to the slice cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CacheStatus {
+    /// Every slice was served from cache
+    Hit,
+    /// No slice was served from cache
+    Miss,
+    /// Some slices were served from cache
+    Partial,
+    /// Served from an expired cache entry
+    Stale,
+    /// Refetched because the cached copy had expired
+    Expired,
+    /// Proxied without the slice cache
+    Bypass,
+}
+
+impl CacheStatus {
+    /// Derive the cache status from slice counts
+    pub fn from_counts(total: usize, cached: usize) -> Self {
+        if total > 0 && cached == total {
+            CacheStatus::Hit
+        } else if cached > 0 {
+            CacheStatus::Partial
+        } else {
+            CacheStatus::Miss
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Miss => "MISS",
+            CacheStatus::Partial => "PARTIAL",
+            CacheStatus::Stale => "STALE",
+            CacheStatus::Expired => "EXPIRED",
+            CacheStatus::Bypass => "BYPASS",
+        }
+    }
+
+    /// Value of the `X-Cache-Status` response header
+    ///
+    /// Uses the values CDN tooling knows; a partial hit still went to the
+    /// origin, so it is reported as `MISS`.
+    pub fn header_value(&self) -> &'static str {
+        match self {
+            CacheStatus::Partial => "MISS",
+            status => status.as_str(),
+        }
+    }
+}
+
+/// One access log record per completed request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessRecord {
+    /// Completion time, RFC 3339 in UTC
+    pub timestamp: String,
+    /// Client address, if known
+    pub client_addr: Option<String>,
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub bytes_sent: u64,
+    /// Cache status (absent for requests that were not sliced)
+    pub cache_status: Option<CacheStatus>,
+    pub slices_total: usize,
+    pub slices_cached: usize,
+    /// Time spent waiting on the origin, in milliseconds
+    pub upstream_time_ms: Option<u64>,
+    pub total_time_ms: u64,
+    /// Error type if the request failed (see `SliceError::error_type`)
+    pub error_type: Option<String>,
+}
+
+impl AccessRecord {
+    /// Format the record as a single line (without trailing newline)
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Json => {
+                serde_json::to_string(self).unwrap_or_else(|e| {
+                    format!("{{\"error\":\"failed to serialize access record: {}\"}}", e)
+                })
+            }
+            AccessLogFormat::Combined => self.format_combined(),
+        }
+    }
+
+    /// Combined log format followed by the slice-specific fields
+    fn format_combined(&self) -> String {
+        let bytes = if self.bytes_sent == 0 {
+            "-".to_string()
+        } else {
+            self.bytes_sent.to_string()
+        };
+        format!(
+            "{} - - [{}] \"{} {} HTTP/1.1\" {} {} \"-\" \"-\" cache={} slices={}/{} upstream_ms={} total_ms={} error={}",
+            self.client_addr.as_deref().unwrap_or("-"),
+            clf_timestamp(&self.timestamp),
+            self.method,
+            self.url,
+            self.status,
+            bytes,
+            self.cache_status.map(|s| s.as_str()).unwrap_or("-"),
+            self.slices_cached,
+            self.slices_total,
+            self.upstream_time_ms
+                .map(|ms| ms.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            self.total_time_ms,
+            self.error_type.as_deref().unwrap_or("-"),
+        )
+    }
+}
+
+/// Message sent to the writer task
+enum Command {
+    Record(String),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Destination of the access log
+#[derive(Debug, Clone)]
+enum Sink {
+    Stdout,
+    File(PathBuf),
+}
+
+impl Sink {
+    fn open(&self) -> Result<Box<dyn AsyncWrite + Send + Unpin>> {
+        match self {
+            Sink::Stdout => Ok(Box::new(tokio::io::stdout())),
+            Sink::File(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| {
+                        SliceError::IoError(format!(
+                            "Failed to open access log {}: {}",
+                            path.display(),
+                            e
+                        ))
+                    })?;
+                Ok(Box::new(tokio::fs::File::from_std(file)))
+            }
+        }
+    }
+}
+
+/// Asynchronous, buffered access logger
+pub struct AccessLogger {
+    sender: mpsc::Sender<Command>,
+    format: AccessLogFormat,
+    reopen: Arc<Notify>,
+    dropped: AtomicU64,
+}
+
+impl AccessLogger {
+    /// Create an access logger from configuration
+    ///
+    /// Opens the log file (if any) and spawns the writer task, so this must be
+    /// called from within a Tokio runtime.
+    ///
+    /// # Returns
+    /// * `Ok(AccessLogger)` if the destination could be opened
+    /// * `Err(SliceError)` otherwise
+    pub fn from_config(config: &AccessLogConfig) -> Result<Self> {
+        let sink = match &config.path {
+            Some(path) => Sink::File(PathBuf::from(path)),
+            None => Sink::Stdout,
+        };
+        let writer = sink.open()?;
+        let (sender, receiver) = mpsc::channel(config.buffer_size.max(1));
+        let reopen = Arc::new(Notify::new());
+
+        tokio::spawn(run_writer(sink, writer, receiver, reopen.clone()));
+
+        Ok(Self {
+            sender,
+            format: config.format,
+            reopen,
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Configured record format
+    pub fn format(&self) -> AccessLogFormat {
+        self.format
+    }
+
+    /// Queue a record for writing without blocking
+    ///
+    /// If the writer has fallen behind and the buffer is full, the record is
+    /// dropped and counted in `dropped_count`.
+    pub fn log(&self, record: AccessRecord) {
+        let mut line = record.format(self.format);
+        line.push('\n');
+        if self.sender.try_send(Command::Record(line)).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Number of records dropped because the buffer was full
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Wait until all records queued so far have been written and flushed
+    pub async fn flush(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.sender.send(Command::Flush(tx)).await.is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    /// Reopen the log file, e.g. after it has been rotated
+    pub fn reopen(&self) {
+        self.reopen.notify_one();
+    }
+
+    /// Reopen the log file whenever the process receives SIGUSR1
+    ///
+    /// Must be called from within a Tokio runtime.
+    #[cfg(unix)]
+    pub fn reopen_on_sigusr1(self: &Arc<Self>) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut signals = signal(SignalKind::user_defined1())?;
+        let reopen = self.reopen.clone();
+        tokio::spawn(async move {
+            while signals.recv().await.is_some() {
+                debug!("Received SIGUSR1, reopening access log");
+                reopen.notify_one();
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Writer task: drains the channel into the sink, reopening it on request
+async fn run_writer(
+    sink: Sink,
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    mut receiver: mpsc::Receiver<Command>,
+    reopen: Arc<Notify>,
+) {
+    let mut writer = BufWriter::new(writer);
+
+    loop {
+        tokio::select! {
+            command = receiver.recv() => {
+                let Some(command) = command else { break };
+                handle_command(&mut writer, command).await;
+                // Write everything already queued before flushing once
+                while let Ok(command) = receiver.try_recv() {
+                    handle_command(&mut writer, command).await;
+                }
+                if let Err(e) = writer.flush().await {
+                    warn!("Failed to flush access log: {}", e);
+                }
+            }
+            _ = reopen.notified() => {
+                let _ = writer.flush().await;
+                match sink.open() {
+                    Ok(new_writer) => {
+                        writer = BufWriter::new(new_writer);
+                        debug!("Reopened access log: {:?}", sink);
+                    }
+                    Err(e) => warn!("Failed to reopen access log: {}", e),
+                }
+            }
+        }
+    }
+
+    let _ = writer.flush().await;
+}
+
+async fn handle_command(writer: &mut BufWriter<Box<dyn AsyncWrite + Send + Unpin>>, command: Command) {
+    match command {
+        Command::Record(line) => {
+            if let Err(e) = writer.write_all(line.as_bytes()).await {
+                warn!("Failed to write access log record: {}", e);
+            }
+        }
+        Command::Flush(done) => {
+            if let Err(e) = writer.flush().await {
+                warn!("Failed to flush access log: {}", e);
+            }
+            let _ = done.send(());
+        }
+    }
+}
+
+/// Current time as an RFC 3339 UTC timestamp with millisecond precision
+pub fn rfc3339_now() -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = elapsed.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+/// Convert an RFC 3339 UTC timestamp to the common log format
+/// (`16/Oct/2026:12:00:00 +0000`)
+fn clf_timestamp(rfc3339: &str) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let parsed = (|| {
+        let year = rfc3339.get(0..4)?;
+        let month: usize = rfc3339.get(5..7)?.parse().ok()?;
+        let day = rfc3339.get(8..10)?;
+        let time = rfc3339.get(11..19)?;
+        Some(format!(
+            "{}/{}/{}:{} +0000",
+            day,
+            MONTHS.get(month.checked_sub(1)?)?,
+            year,
+            time
+        ))
+    })();
+    parsed.unwrap_or_else(|| rfc3339.to_string())
+}
+
+/// Days since 1970-01-01 to (year, month, day) in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record(url: &str, cached: usize) -> AccessRecord {
+        AccessRecord {
+            timestamp: rfc3339_now(),
+            client_addr: Some("192.0.2.10".to_string()),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            status: 200,
+            bytes_sent: 4096,
+            cache_status: Some(CacheStatus::from_counts(4, cached)),
+            slices_total: 4,
+            slices_cached: cached,
+            upstream_time_ms: Some(12),
+            total_time_ms: 30,
+            error_type: None,
+        }
+    }
+
+    fn file_config(dir: &TempDir, format: AccessLogFormat, buffer_size: usize) -> AccessLogConfig {
+        AccessLogConfig {
+            enabled: true,
+            path: Some(dir.path().join("access.log").to_string_lossy().into_owned()),
+            format,
+            buffer_size,
+        }
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20742), (2026, 10, 16));
+    }
+
+    #[test]
+    fn test_cache_status_from_counts() {
+        assert_eq!(CacheStatus::from_counts(4, 4), CacheStatus::Hit);
+        assert_eq!(CacheStatus::from_counts(4, 1), CacheStatus::Partial);
+        assert_eq!(CacheStatus::from_counts(4, 0), CacheStatus::Miss);
+        assert_eq!(CacheStatus::from_counts(0, 0), CacheStatus::Miss);
+        assert_eq!(CacheStatus::Partial.header_value(), "MISS");
+        assert_eq!(CacheStatus::Expired.header_value(), "EXPIRED");
+    }
+
+    #[test]
+    fn test_combined_format() {
+        let mut rec = record("/video.mp4", 1);
+        rec.timestamp = "2026-10-16T08:05:09.123Z".to_string();
+        assert_eq!(
+            rec.format(AccessLogFormat::Combined),
+            "192.0.2.10 - - [16/Oct/2026:08:05:09 +0000] \"GET /video.mp4 HTTP/1.1\" 200 4096 \"-\" \"-\" cache=PARTIAL slices=1/4 upstream_ms=12 total_ms=30 error=-"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_lines_written() {
+        let dir = TempDir::new().unwrap();
+        let config = file_config(&dir, AccessLogFormat::Json, 16);
+        let logger = AccessLogger::from_config(&config).unwrap();
+
+        logger.log(record("/a.bin", 4));
+        logger.log(record("/b.bin", 0));
+        let mut failed = record("/c.bin", 0);
+        failed.status = 502;
+        failed.cache_status = None;
+        failed.error_type = Some("subrequest_failed".to_string());
+        logger.log(failed);
+        logger.flush().await;
+
+        let content = std::fs::read_to_string(config.path.unwrap()).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+
+        for line in &lines {
+            for field in [
+                "timestamp",
+                "client_addr",
+                "method",
+                "url",
+                "status",
+                "bytes_sent",
+                "cache_status",
+                "slices_total",
+                "slices_cached",
+                "upstream_time_ms",
+                "total_time_ms",
+                "error_type",
+            ] {
+                assert!(line.get(field).is_some(), "missing field {}", field);
+            }
+        }
+        assert_eq!(lines[0]["cache_status"], "HIT");
+        assert_eq!(lines[1]["cache_status"], "MISS");
+        assert_eq!(lines[2]["status"], 502);
+        assert!(lines[2]["cache_status"].is_null());
+        assert_eq!(lines[2]["error_type"], "subrequest_failed");
+        assert!(lines[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[tokio::test]
+    async fn test_full_buffer_drops_without_blocking() {
+        let dir = TempDir::new().unwrap();
+        let config = file_config(&dir, AccessLogFormat::Json, 4);
+        let logger = AccessLogger::from_config(&config).unwrap();
+
+        // The single-threaded test runtime does not run the writer task until
+        // we yield, so the buffer fills up after four records
+        let start = std::time::Instant::now();
+        for i in 0..100 {
+            logger.log(record(&format!("/{}.bin", i), 0));
+        }
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+        assert_eq!(logger.dropped_count(), 96);
+
+        logger.flush().await;
+        let content = std::fs::read_to_string(config.path.unwrap()).unwrap();
+        assert_eq!(content.lines().count(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_reopen_after_rotation() {
+        let dir = TempDir::new().unwrap();
+        let config = file_config(&dir, AccessLogFormat::Json, 16);
+        let path = PathBuf::from(config.path.clone().unwrap());
+        let rotated = dir.path().join("access.log.1");
+        let logger = AccessLogger::from_config(&config).unwrap();
+
+        logger.log(record("/before.bin", 0));
+        logger.flush().await;
+        std::fs::rename(&path, &rotated).unwrap();
+
+        logger.reopen();
+        tokio::task::yield_now().await;
+        logger.log(record("/after.bin", 0));
+        logger.flush().await;
+
+        assert!(std::fs::read_to_string(&rotated).unwrap().contains("/before.bin"));
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("/after.bin"));
+        assert!(!content.contains("/before.bin"));
+    }
+}
@@ -0,0 +1,16 @@
+//! Offline inspection and repair of an L2 cache directory
+//!
+//! See [`pingora_slice::cachectl`] for the commands and exit codes.
+
+use std::io::Write;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut out = std::io::stdout().lock();
+    let mut err = std::io::stderr().lock();
+    let code = pingora_slice::cachectl::run(&args, &mut out, &mut err).await;
+    // exit skips destructors, so nothing else flushes stdout
+    let _ = out.flush();
+    std::process::exit(code);
+}
@@ -7,42 +7,128 @@
 //! The cache automatically promotes frequently accessed items to L1
 //! and persists all items to L2 asynchronously.
 
+use crate::config::OriginQuotaConfig;
 use crate::error::Result;
-use crate::models::ByteRange;
+use crate::models::{ByteRange, SliceKey};
 use bytes::Bytes;
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
 /// Cached slice entry with expiration and access tracking
+///
+/// Times are monotonic, so a wall clock step (NTP correction, VM resume)
+/// neither expires every entry at once nor keeps them past their TTL.
 #[derive(Clone)]
 struct CacheEntry {
     data: Bytes,
-    expires_at: SystemTime,
-    last_accessed: SystemTime,
+    host: String,
+    stored_at: Instant,
+    expires_at: Instant,
+    last_accessed: Instant,
     access_count: u64,
 }
 
 /// Cache statistics for monitoring
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheStats {
     pub total_entries: usize,
     pub total_bytes: usize,
     pub hits: u64,
     pub misses: u64,
+    /// Fraction of lookups that were hits (0.0 to 1.0)
+    pub hit_ratio: f64,
+    /// Bytes cached per origin host
+    pub host_bytes: HashMap<String, usize>,
+}
+
+/// Cache key for one variant of a URL whose response has a Vary header
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheVariant {
+    /// URL the variant's slices are cached under
+    pub key: String,
+    /// Request headers selecting the variant, forwarded to the origin
+    pub headers: HeaderMap,
+}
+
+impl CacheVariant {
+    /// Select the variant of `url` that `request_headers` asks for
+    ///
+    /// Each header named in `vary` is added to the key with the request's
+    /// value, or an empty value if the request does not send it.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the file
+    /// * `vary` - Lowercased header names from the origin's Vary header
+    /// * `allowed` - Header names the cache may vary on
+    /// * `request_headers` - Headers of the client request
+    ///
+    /// # Returns
+    /// `None` if the response cannot be cached: `Vary: *`, or a header
+    /// outside `allowed`
+    pub fn from_vary(
+        url: &str,
+        vary: &[String],
+        allowed: &[String],
+        request_headers: &HeaderMap,
+    ) -> Option<Self> {
+        let mut key = SliceKey::from_object_key(url);
+        let mut headers = HeaderMap::new();
+
+        for name in vary {
+            if !allowed.iter().any(|a| a.eq_ignore_ascii_case(name)) {
+                return None;
+            }
+            let header = http::header::HeaderName::from_bytes(name.as_bytes()).ok()?;
+            let value = request_headers.get(&header);
+
+            // Format: {url}|vary:{name}={value}|vary:...
+            key = key.with_variant(
+                name,
+                value.and_then(|v| v.to_str().ok()).unwrap_or("").trim(),
+            );
+            if let Some(value) = value {
+                headers.insert(header, value.clone());
+            }
+        }
+
+        Some(CacheVariant {
+            key: key.object_key(),
+            headers,
+        })
+    }
 }
 
 /// Cache manager for storing and retrieving slices
+///
+/// A standalone, memory-only cache kept as a public API for embedders that
+/// do not need a disk tier. The proxy and the server use
+/// [`TieredCache`](crate::TieredCache), which names slices the same way.
 pub struct SliceCache {
     storage: Arc<RwLock<HashMap<String, CacheEntry>>>,
     ttl: Duration,
     max_size_bytes: Option<usize>,
     current_size_bytes: Arc<RwLock<usize>>,
+    host_bytes: Arc<RwLock<HashMap<String, usize>>>,
+    origin_quotas: Option<OriginQuotaConfig>,
     hits: Arc<RwLock<u64>>,
     misses: Arc<RwLock<u64>>,
 }
 
+/// Origin host a cached URL belongs to, as `host[:port]`
+///
+/// URLs without a scheme and authority (such as `/file.bin`) share the
+/// empty host.
+pub(crate) fn origin_host(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.split(['/', '?', '|']).next().unwrap_or(""),
+        None => "",
+    }
+}
+
 impl SliceCache {
     /// Create a new SliceCache
     ///
@@ -54,6 +140,8 @@ impl SliceCache {
             ttl,
             max_size_bytes: None,
             current_size_bytes: Arc::new(RwLock::new(0)),
+            host_bytes: Arc::new(RwLock::new(HashMap::new())),
+            origin_quotas: None,
             hits: Arc::new(RwLock::new(0)),
             misses: Arc::new(RwLock::new(0)),
         }
@@ -70,11 +158,25 @@ impl SliceCache {
             ttl,
             max_size_bytes: Some(max_size_bytes),
             current_size_bytes: Arc::new(RwLock::new(0)),
+            host_bytes: Arc::new(RwLock::new(HashMap::new())),
+            origin_quotas: None,
             hits: Arc::new(RwLock::new(0)),
             misses: Arc::new(RwLock::new(0)),
         }
     }
 
+    /// Limit how many bytes each origin host may occupy
+    ///
+    /// A store that takes a host over its quota evicts that host's least
+    /// recently used entries, leaving other hosts' entries alone.
+    ///
+    /// # Arguments
+    /// * `quotas` - Per-host and default quotas in bytes
+    pub fn with_origin_quotas(mut self, quotas: OriginQuotaConfig) -> Self {
+        self.origin_quotas = Some(quotas);
+        self
+    }
+
     /// Get cache statistics
     pub fn get_stats(&self) -> CacheStats {
         let storage = self.storage.read().unwrap();
@@ -82,11 +184,46 @@ impl SliceCache {
         let hits = *self.hits.read().unwrap();
         let misses = *self.misses.read().unwrap();
 
+        let lookups = hits + misses;
         CacheStats {
             total_entries: storage.len(),
             total_bytes: current_size,
             hits,
             misses,
+            hit_ratio: if lookups == 0 { 0.0 } else { hits as f64 / lookups as f64 },
+            host_bytes: self.host_bytes.read().unwrap().clone(),
+        }
+    }
+
+    /// Check that the cache's locks can still be taken
+    ///
+    /// # Returns
+    /// `false` if a thread panicked while holding one, leaving the cache
+    /// unusable
+    pub fn is_accessible(&self) -> bool {
+        self.storage.read().is_ok()
+            && self.current_size_bytes.read().is_ok()
+            && self.host_bytes.read().is_ok()
+    }
+
+    /// Byte quota for an origin host, if it has one
+    fn origin_quota(&self, host: &str) -> Option<usize> {
+        let quotas = self.origin_quotas.as_ref()?;
+        quotas.hosts.get(host).copied().or(quotas.default_bytes)
+    }
+
+    /// Remove a dropped entry's bytes from the size accounting
+    fn release(&self, entry: &CacheEntry) {
+        if let Ok(mut current_size) = self.current_size_bytes.write() {
+            *current_size = current_size.saturating_sub(entry.data.len());
+        }
+        if let Ok(mut host_bytes) = self.host_bytes.write() {
+            if let Some(used) = host_bytes.get_mut(&entry.host) {
+                *used = used.saturating_sub(entry.data.len());
+                if *used == 0 {
+                    host_bytes.remove(&entry.host);
+                }
+            }
         }
     }
 
@@ -95,44 +232,52 @@ impl SliceCache {
     /// The cache key includes the URL and byte range to ensure uniqueness.
     ///
     /// # Arguments
-    /// * `url` - The URL of the file
+    /// * `url` - The URL of the file, or an object key from [`SliceKey`]
     /// * `range` - The byte range of the slice
     ///
     /// # Returns
     /// A String that uniquely identifies this slice
     pub fn generate_cache_key(&self, url: &str, range: &ByteRange) -> String {
         // Format: {url}:slice:{start}:{end}
-        format!("{}:slice:{}:{}", url, range.start, range.end)
+        SliceKey::from_object_key(url).with_range(*range).slice_key()
     }
 
-    /// Clean up expired entries from the cache
-    fn cleanup_expired(&self) {
-        let now = SystemTime::now();
-        if let Ok(mut storage) = self.storage.write() {
-            let mut removed_bytes = 0;
-            storage.retain(|_, entry| {
-                if entry.expires_at <= now {
-                    removed_bytes += entry.data.len();
-                    false
-                } else {
-                    true
-                }
-            });
-
-            if removed_bytes > 0 {
-                if let Ok(mut current_size) = self.current_size_bytes.write() {
-                    *current_size = current_size.saturating_sub(removed_bytes);
-                }
+    /// Remove every expired entry from the cache
+    ///
+    /// Lookups skip expired entries but leave them in place, so they keep
+    /// their bytes until removed here or evicted.
+    ///
+    /// # Returns
+    /// The number of entries removed
+    pub fn cleanup_expired(&self) -> usize {
+        let now = Instant::now();
+        let Ok(mut storage) = self.storage.write() else {
+            return 0;
+        };
+        let before = storage.len();
+        storage.retain(|_, entry| {
+            if entry.expires_at <= now {
+                self.release(entry);
+                false
+            } else {
+                true
             }
-        }
+        });
+        before - storage.len()
     }
 
     /// Evict least recently used entries to make room for new data
     /// Uses LRU (Least Recently Used) eviction policy
     fn evict_lru(&self, needed_bytes: usize) {
+        self.evict_lru_matching(needed_bytes, None);
+    }
+
+    /// Evict least recently used entries, only those of `host` if given
+    fn evict_lru_matching(&self, needed_bytes: usize, host: Option<&str>) {
         if let Ok(mut storage) = self.storage.write() {
             // Collect entries sorted by last access time
             let mut entries: Vec<_> = storage.iter()
+                .filter(|(_, v)| host.is_none_or(|host| v.host == host))
                 .map(|(k, v)| (k.clone(), v.last_accessed, v.data.len()))
                 .collect();
             
@@ -149,15 +294,17 @@ impl SliceCache {
                 freed_bytes += size;
             }
 
+            let removed = keys_to_remove.len();
             for key in keys_to_remove {
                 if let Some(entry) = storage.remove(&key) {
-                    if let Ok(mut current_size) = self.current_size_bytes.write() {
-                        *current_size = current_size.saturating_sub(entry.data.len());
-                    }
+                    self.release(&entry);
                 }
             }
 
-            debug!("LRU eviction: freed {} bytes by removing {} entries", freed_bytes, storage.len());
+            debug!(
+                "LRU eviction: freed {} bytes by removing {} entries (host={:?})",
+                freed_bytes, removed, host
+            );
         }
     }
 
@@ -172,8 +319,25 @@ impl SliceCache {
     /// * `Ok(None)` if the slice is not in cache
     /// * `Err(SliceError)` if a cache error occurs
     pub async fn lookup_slice(&self, url: &str, range: &ByteRange) -> Result<Option<Bytes>> {
+        Ok(self.lookup_slice_with_age(url, range).await?.map(|(data, _)| data))
+    }
+
+    /// Look up a single cached slice along with its age
+    ///
+    /// The age is the time since the slice was stored, for the `Age`
+    /// response header.
+    ///
+    /// # Returns
+    /// * `Ok(Some((Bytes, Duration)))` if the slice is found in cache
+    /// * `Ok(None)` if the slice is not in cache
+    /// * `Err(SliceError)` if a cache error occurs
+    pub async fn lookup_slice_with_age(
+        &self,
+        url: &str,
+        range: &ByteRange,
+    ) -> Result<Option<(Bytes, Duration)>> {
         let key = self.generate_cache_key(url, range);
-        let now = SystemTime::now();
+        let now = Instant::now();
         
         debug!(
             "Looking up cached slice: url={}, range={}-{}",
@@ -190,7 +354,8 @@ impl SliceCache {
                             "Cache hit for slice: url={}, range={}-{}, size={}",
                             url, range.start, range.end, entry.data.len()
                         );
-                        Some(entry.data.clone())
+                        let age = now.saturating_duration_since(entry.stored_at);
+                        Some((entry.data.clone(), age))
                     } else {
                         debug!(
                             "Cache entry expired for slice: url={}, range={}-{}",
@@ -254,17 +419,47 @@ impl SliceCache {
         url: &str,
         range: &ByteRange,
         data: Bytes,
+    ) -> Result<()> {
+        self.store_slice_with_ttl(url, range, data, self.ttl).await
+    }
+
+    /// Store a slice in the cache with a per-entry TTL
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the file
+    /// * `range` - The byte range of the slice
+    /// * `data` - The slice data to store
+    /// * `ttl` - Time-to-live for this entry, overriding the cache default
+    pub async fn store_slice_with_ttl(
+        &self,
+        url: &str,
+        range: &ByteRange,
+        data: Bytes,
+        ttl: Duration,
     ) -> Result<()> {
         let key = self.generate_cache_key(url, range);
-        let now = SystemTime::now();
-        let expires_at = now + self.ttl;
+        let now = Instant::now();
+        let expires_at = now + ttl;
         let data_size = data.len();
+        let host = origin_host(url).to_string();
         
         debug!(
             "Storing slice in cache: url={}, range={}-{}, size={}",
             url, range.start, range.end, data_size
         );
 
+        // Keep the origin within its quota by evicting its own entries
+        if let Some(quota) = self.origin_quota(&host) {
+            let used = self.host_bytes.read().unwrap().get(&host).copied().unwrap_or(0);
+            if used + data_size > quota {
+                debug!(
+                    "Origin quota reached for host={} ({}/{}), evicting its LRU entries",
+                    host, used, quota
+                );
+                self.evict_lru_matching(used + data_size - quota, Some(&host));
+            }
+        }
+
         // Check if we need to evict entries to make room
         if let Some(max_size) = self.max_size_bytes {
             let current_size = *self.current_size_bytes.read().unwrap();
@@ -281,23 +476,26 @@ impl SliceCache {
             Ok(mut storage) => {
                 // Remove old entry if it exists and update size
                 if let Some(old_entry) = storage.get(&key) {
-                    if let Ok(mut current_size) = self.current_size_bytes.write() {
-                        *current_size = current_size.saturating_sub(old_entry.data.len());
-                    }
+                    self.release(old_entry);
+                }
+
+                // Update current size
+                if let Ok(mut current_size) = self.current_size_bytes.write() {
+                    *current_size += data_size;
+                }
+                if let Ok(mut host_bytes) = self.host_bytes.write() {
+                    *host_bytes.entry(host.clone()).or_default() += data_size;
                 }
 
                 storage.insert(key, CacheEntry {
                     data,
+                    host,
+                    stored_at: now,
                     expires_at,
                     last_accessed: now,
                     access_count: 0,
                 });
 
-                // Update current size
-                if let Ok(mut current_size) = self.current_size_bytes.write() {
-                    *current_size += data_size;
-                }
-
                 debug!(
                     "Successfully stored slice in cache: url={}, range={}-{}",
                     url, range.start, range.end
@@ -323,6 +521,19 @@ impl SliceCache {
         }
     }
 
+    /// Whether the cache holds an expired copy of a slice
+    ///
+    /// Expired entries stay in place until swept or overwritten, which
+    /// tells a refetch of a known slice apart from a first fetch.
+    pub fn is_expired(&self, url: &str, range: &ByteRange) -> bool {
+        let key = self.generate_cache_key(url, range);
+        let now = Instant::now();
+        self.storage
+            .read()
+            .map(|storage| storage.get(&key).is_some_and(|entry| entry.expires_at <= now))
+            .unwrap_or(false)
+    }
+
     /// Batch lookup multiple slices
     ///
     /// This method looks up multiple slices and returns
@@ -494,6 +705,61 @@ mod tests {
         assert!(result2.is_none());
     }
 
+    #[tokio::test]
+    async fn test_lookup_reports_age_and_expiry() {
+        let cache = SliceCache::new(Duration::from_secs(3600));
+        let range = ByteRange::new(0, 1023).unwrap();
+        let url = "http://example.com/file.bin";
+
+        assert!(!cache.is_expired(url, &range));
+        cache.store_slice_with_ttl(url, &range, Bytes::from(vec![1; 10]), Duration::from_millis(200)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let (data, age) = cache.lookup_slice_with_age(url, &range).await.unwrap().unwrap();
+        assert_eq!(data.len(), 10);
+        assert!(age >= Duration::from_millis(100) && age < Duration::from_millis(200), "{:?}", age);
+        assert!(!cache.is_expired(url, &range));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(cache.is_expired(url, &range));
+        assert!(cache.lookup_slice_with_age(url, &range).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_expired_reclaims_bytes() {
+        let cache = SliceCache::new(Duration::from_secs(3600));
+        let range = ByteRange::new(0, 1023).unwrap();
+        
+        cache.store_slice_with_ttl("http://example.com/a", &range, Bytes::from(vec![0; 100]), Duration::from_millis(50)).await.unwrap();
+        cache.store_slice_with_ttl("http://example.com/b", &range, Bytes::from(vec![0; 100]), Duration::from_millis(50)).await.unwrap();
+        cache.store_slice("http://example.com/c", &range, Bytes::from(vec![0; 10])).await.unwrap();
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // Expired entries still count until they are cleaned up
+        assert_eq!(cache.get_stats().total_bytes, 210);
+        assert_eq!(cache.cleanup_expired(), 2);
+        let stats = cache.get_stats();
+        assert_eq!((stats.total_entries, stats.total_bytes), (1, 10));
+        assert_eq!(cache.cleanup_expired(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_store_slice_with_ttl() {
+        let cache = SliceCache::new(Duration::from_secs(3600));
+        let range = ByteRange::new(0, 1023).unwrap();
+        let data = Bytes::from(vec![1, 2, 3]);
+        
+        cache.store_slice_with_ttl("http://example.com/short", &range, data.clone(), Duration::from_millis(100)).await.unwrap();
+        cache.store_slice("http://example.com/long", &range, data).await.unwrap();
+        
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        
+        // The short-TTL entry expires while the default-TTL entry is still cached
+        assert!(cache.lookup_slice("http://example.com/short", &range).await.unwrap().is_none());
+        assert!(cache.lookup_slice("http://example.com/long", &range).await.unwrap().is_some());
+    }
+
     #[tokio::test]
     async fn test_cache_with_max_size() {
         // Create cache with 1KB limit
@@ -530,6 +796,47 @@ mod tests {
         assert!(result3.is_some());
     }
 
+    #[tokio::test]
+    async fn test_origin_quota_evicts_only_that_origin() {
+        let quotas = OriginQuotaConfig {
+            default_bytes: None,
+            hosts: HashMap::from([("noisy.example.com".to_string(), 1024)]),
+        };
+        let cache = SliceCache::new(Duration::from_secs(3600)).with_origin_quotas(quotas);
+
+        let noisy = "http://noisy.example.com/big.bin";
+        let quiet = "http://quiet.example.com:8080/small.bin";
+        let ranges: Vec<_> = (0..3)
+            .map(|i| ByteRange::new(i * 512, i * 512 + 511).unwrap())
+            .collect();
+
+        cache.store_slice(quiet, &ranges[0], Bytes::from(vec![9u8; 512])).await.unwrap();
+        cache.store_slice(quiet, &ranges[1], Bytes::from(vec![9u8; 512])).await.unwrap();
+        for range in &ranges {
+            cache.store_slice(noisy, range, Bytes::from(vec![1u8; 512])).await.unwrap();
+        }
+
+        // The noisy origin's oldest slice made room for its newest
+        assert!(cache.lookup_slice(noisy, &ranges[0]).await.unwrap().is_none());
+        assert!(cache.lookup_slice(noisy, &ranges[1]).await.unwrap().is_some());
+        assert!(cache.lookup_slice(noisy, &ranges[2]).await.unwrap().is_some());
+        assert!(cache.lookup_slice(quiet, &ranges[0]).await.unwrap().is_some());
+        assert!(cache.lookup_slice(quiet, &ranges[1]).await.unwrap().is_some());
+
+        let stats = cache.get_stats();
+        assert_eq!(stats.host_bytes["noisy.example.com"], 1024);
+        assert_eq!(stats.host_bytes["quiet.example.com:8080"], 1024);
+        assert_eq!(stats.total_bytes, 2048);
+    }
+
+    #[test]
+    fn test_origin_host() {
+        assert_eq!(origin_host("http://example.com/file.bin"), "example.com");
+        assert_eq!(origin_host("https://example.com:8443?x=1"), "example.com:8443");
+        assert_eq!(origin_host("http://example.com|vary:accept-encoding=gzip"), "example.com");
+        assert_eq!(origin_host("/file.bin"), "");
+    }
+
     #[tokio::test]
     async fn test_cache_stats() {
         let cache = SliceCache::new(Duration::from_secs(3600));
@@ -546,5 +853,48 @@ mod tests {
         assert_eq!(stats.total_entries, 1);
         assert_eq!(stats.hits, 1);
         assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hit_ratio, 0.5);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        let mut fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            ["hit_ratio", "hits", "host_bytes", "misses", "total_bytes", "total_entries"]
+        );
+    }
+
+    #[test]
+    fn test_cache_variant_keys() {
+        let allowed = vec!["accept-encoding".to_string()];
+        let vary = vec!["accept-encoding".to_string()];
+        let url = "http://example.com/file.bin";
+
+        let mut gzip = HeaderMap::new();
+        gzip.insert("accept-encoding", "gzip".parse().unwrap());
+        let mut br = HeaderMap::new();
+        br.insert("accept-encoding", "br".parse().unwrap());
+
+        let gzip_variant = CacheVariant::from_vary(url, &vary, &allowed, &gzip).unwrap();
+        let br_variant = CacheVariant::from_vary(url, &vary, &allowed, &br).unwrap();
+        assert_ne!(gzip_variant.key, br_variant.key);
+        assert_eq!(gzip_variant.headers.get("accept-encoding").unwrap(), "gzip");
+
+        // No Vary header: the URL alone is the key
+        let plain = CacheVariant::from_vary(url, &[], &allowed, &gzip).unwrap();
+        assert_eq!(plain.key, url);
+        assert!(plain.headers.is_empty());
+    }
+
+    #[test]
+    fn test_cache_variant_rejects_unlisted_headers() {
+        let allowed = vec!["accept-encoding".to_string()];
+        let headers = HeaderMap::new();
+
+        let vary = vec!["cookie".to_string()];
+        assert!(CacheVariant::from_vary("http://example.com/a", &vary, &allowed, &headers).is_none());
+
+        let vary = vec!["*".to_string()];
+        assert!(CacheVariant::from_vary("http://example.com/a", &vary, &allowed, &headers).is_none());
     }
 }
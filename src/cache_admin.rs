@@ -0,0 +1,717 @@
+//! Admin endpoints for inspecting the cache
+//!
+//! Supported endpoints:
+//! - GET /admin/cache/entry?key=<cache key> - Inspect a single entry without its body
+//! - DELETE /admin/cache/entry?key=<cache key> - Remove a single entry from all tiers
+//! - GET /admin/cache/keys?prefix=<prefix>&limit=<n>&after=<key> - List keys a page at a time
+//!   (`cursor` is accepted as another name for `after`)
+//! - GET /admin/cache/stats?section=<l1|l2|disk> - Cache statistics, optionally one section
+//! - GET /admin/cache/top?by=<size|age|hits>&limit=<n> - Largest, oldest or most hit entries
+//! - POST /admin/cache/shrink?l1_bytes=<n> - Evict LRU entries until L1 holds at most n bytes
+//! - GET /admin/config - Effective configuration as YAML, secrets redacted
+//!   (when a configuration is attached)
+
+use crate::config::SliceConfig;
+use crate::error::{Result, SliceError};
+use crate::purge_handler::has_valid_token;
+use crate::tiered_cache::{CacheEntryInfo, CacheTier, TieredCache, TopBy};
+use bytes::Bytes;
+use http::{Method, Request, Response, StatusCode};
+use http_body_util::Full;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Default page size for `GET /admin/cache/keys` and `GET /admin/cache/top`
+const DEFAULT_KEYS_LIMIT: usize = 100;
+
+/// Maximum page size for `GET /admin/cache/keys` and `GET /admin/cache/top`
+const MAX_KEYS_LIMIT: usize = 1000;
+
+/// Sections of `GET /admin/cache/stats`: each selects the fields named
+/// `<section>_*`
+const STATS_SECTIONS: [&str; 3] = ["l1", "l2", "disk"];
+
+/// Cache admin request handler
+pub struct CacheAdminHandler {
+    cache: Arc<TieredCache>,
+    /// Optional auth token (shared with PURGE)
+    auth_token: Option<String>,
+    /// Configuration served by `GET /admin/config`
+    config: Option<Arc<SliceConfig>>,
+}
+
+/// Response body for `GET /admin/cache/entry`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheEntryResponse {
+    pub key: String,
+    pub present: bool,
+    #[serde(flatten)]
+    pub entry: Option<CacheEntryInfo>,
+}
+
+/// Response body for `DELETE /admin/cache/entry`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteEntryResponse {
+    pub success: bool,
+    pub key: String,
+    pub removed_from: Vec<CacheTier>,
+}
+
+/// Response body for `GET /admin/cache/keys`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheKeysResponse {
+    pub prefix: String,
+    pub keys: Vec<String>,
+    /// Pass as `after` to fetch the next page (absent on the last page)
+    pub next: Option<String>,
+}
+
+/// Response body for `POST /admin/cache/shrink`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShrinkResponse {
+    pub success: bool,
+    pub evicted: usize,
+    /// L1 size after shrinking
+    pub l1_bytes: usize,
+}
+
+/// Error response body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+impl CacheAdminHandler {
+    /// Create a new cache admin handler
+    pub fn new(cache: Arc<TieredCache>) -> Self {
+        Self {
+            cache,
+            auth_token: None,
+            config: None,
+        }
+    }
+
+    /// Create a new cache admin handler with authentication
+    pub fn with_auth(cache: Arc<TieredCache>, auth_token: String) -> Self {
+        Self {
+            cache,
+            auth_token: Some(auth_token),
+            config: None,
+        }
+    }
+
+    /// Serve `config` from `GET /admin/config`
+    pub fn with_config(mut self, config: Arc<SliceConfig>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Whether the request path is served by this handler
+    pub fn matches(path: &str) -> bool {
+        path == "/admin/cache" || path.starts_with("/admin/cache/") || path == "/admin/config"
+    }
+
+    /// Handle a cache admin request
+    pub async fn handle_request<B>(&self, req: Request<B>) -> Result<Response<Full<Bytes>>> {
+        if let Some(expected_token) = &self.auth_token {
+            if !has_valid_token(&req, expected_token) {
+                return self.error_response(
+                    StatusCode::UNAUTHORIZED,
+                    "Invalid or missing authentication token",
+                );
+            }
+        }
+
+        let query = req.uri().query();
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, "/admin/cache/entry") | (&Method::DELETE, "/admin/cache/entry") => {
+                let key = match query_param(query, "key") {
+                    Some(key) if !key.is_empty() => key,
+                    _ => {
+                        return self.error_response(
+                            StatusCode::BAD_REQUEST,
+                            "Missing required query parameter: key",
+                        );
+                    }
+                };
+                if req.method() == Method::GET {
+                    self.handle_entry(key).await
+                } else {
+                    self.handle_delete_entry(key).await
+                }
+            }
+            (&Method::GET, "/admin/cache/keys") => {
+                let prefix = query_param(query, "prefix").unwrap_or_default();
+                let after = query_param(query, "after").or_else(|| query_param(query, "cursor"));
+                let Some(limit) = limit_param(query) else {
+                    return self.error_response(
+                        StatusCode::BAD_REQUEST,
+                        "limit must be a positive integer",
+                    );
+                };
+                self.handle_keys(prefix, after, limit)
+            }
+            (&Method::GET, "/admin/cache/top") => {
+                let by = match query_param(query, "by").as_deref() {
+                    None | Some("size") => TopBy::Size,
+                    Some("age") => TopBy::Age,
+                    Some("hits") => TopBy::Hits,
+                    Some(_) => {
+                        return self.error_response(
+                            StatusCode::BAD_REQUEST,
+                            "by must be one of: size, age, hits",
+                        );
+                    }
+                };
+                let Some(limit) = limit_param(query) else {
+                    return self.error_response(
+                        StatusCode::BAD_REQUEST,
+                        "limit must be a positive integer",
+                    );
+                };
+                self.handle_top(by, limit).await
+            }
+            (&Method::GET, "/admin/cache/stats") => self.handle_stats(query_param(query, "section")),
+            (&Method::POST, "/admin/cache/shrink") => {
+                match query_param(query, "l1_bytes").map(|bytes| bytes.parse::<usize>()) {
+                    Some(Ok(target)) => self.handle_shrink(target),
+                    _ => self.error_response(
+                        StatusCode::BAD_REQUEST,
+                        "l1_bytes must be a non-negative integer",
+                    ),
+                }
+            }
+            (&Method::GET, "/admin/config") => self.handle_config(),
+            (_, "/admin/cache/entry")
+            | (_, "/admin/cache/keys")
+            | (_, "/admin/cache/stats")
+            | (_, "/admin/cache/top")
+            | (_, "/admin/cache/shrink")
+            | (_, "/admin/config") => {
+                self.error_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+            }
+            _ => self.error_response(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    /// Inspect a single cache entry
+    async fn handle_entry(&self, key: String) -> Result<Response<Full<Bytes>>> {
+        let entry = self.cache.inspect(&key).await;
+        debug!("Inspected cache entry: {} (present: {})", key, entry.is_some());
+
+        let status = if entry.is_some() {
+            StatusCode::OK
+        } else {
+            StatusCode::NOT_FOUND
+        };
+        let response = CacheEntryResponse {
+            key,
+            present: entry.is_some(),
+            entry,
+        };
+
+        self.json_response(status, &response)
+    }
+
+    /// Remove a single cache entry from every tier
+    async fn handle_delete_entry(&self, key: String) -> Result<Response<Full<Bytes>>> {
+        let removed = match self.cache.remove_entry(&key).await {
+            Ok(removed) => removed,
+            Err(e) => {
+                return self.error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string());
+            }
+        };
+
+        let mut removed_from = Vec::new();
+        if removed.l1 {
+            removed_from.push(CacheTier::L1);
+        }
+        if removed.l2 {
+            removed_from.push(CacheTier::L2);
+        }
+        let status = if removed.any() {
+            StatusCode::OK
+        } else {
+            StatusCode::NOT_FOUND
+        };
+        let response = DeleteEntryResponse {
+            success: removed.any(),
+            key,
+            removed_from,
+        };
+
+        self.json_response(status, &response)
+    }
+
+    /// List cache keys a page at a time
+    fn handle_keys(
+        &self,
+        prefix: String,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Response<Full<Bytes>>> {
+        let page = self.cache.list_keys(&prefix, after.as_deref(), limit);
+        let response = CacheKeysResponse {
+            prefix,
+            keys: page.keys,
+            next: page.next,
+        };
+
+        self.json_response(StatusCode::OK, &response)
+    }
+
+    /// Report cache statistics, or only the fields of one section
+    fn handle_stats(&self, section: Option<String>) -> Result<Response<Full<Bytes>>> {
+        let stats = self.cache.get_stats();
+        let Some(section) = section else {
+            return self.json_response(StatusCode::OK, &stats);
+        };
+        if !STATS_SECTIONS.contains(&section.as_str()) {
+            return self.error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("section must be one of: {}", STATS_SECTIONS.join(", ")),
+            );
+        }
+
+        let serde_json::Value::Object(fields) = serde_json::to_value(&stats)
+            .map_err(|e| SliceError::CacheError(format!("Failed to serialize stats: {}", e)))?
+        else {
+            return Err(SliceError::CacheError("Stats did not serialize to an object".to_string()));
+        };
+        let prefix = format!("{}_", section);
+        let fields: serde_json::Map<String, serde_json::Value> = fields
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(&prefix))
+            .collect();
+        self.json_response(StatusCode::OK, &fields)
+    }
+
+    /// Report the top entries of each tier
+    async fn handle_top(&self, by: TopBy, limit: usize) -> Result<Response<Full<Bytes>>> {
+        match self.cache.top_entries(by, limit).await {
+            Ok(top) => self.json_response(StatusCode::OK, &top),
+            Err(e) => self.error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        }
+    }
+
+    /// Evict LRU entries until L1 holds at most `target` bytes
+    fn handle_shrink(&self, target: usize) -> Result<Response<Full<Bytes>>> {
+        let evicted = self.cache.shrink_l1_to(target);
+        let response = ShrinkResponse {
+            success: true,
+            evicted,
+            l1_bytes: self.cache.get_stats().l1_bytes,
+        };
+
+        self.json_response(StatusCode::OK, &response)
+    }
+
+    /// Dump the effective configuration with secrets redacted
+    fn handle_config(&self) -> Result<Response<Full<Bytes>>> {
+        let Some(config) = &self.config else {
+            return self.error_response(StatusCode::NOT_FOUND, "No configuration attached");
+        };
+        let yaml = config.to_yaml()?;
+
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/yaml")
+            .header("cache-control", "no-cache, no-store, must-revalidate")
+            .body(Full::new(Bytes::from(yaml)))
+            .map_err(|e| SliceError::CacheError(format!("Failed to build response: {}", e)))
+    }
+
+    /// Build JSON response
+    fn json_response<T: Serialize>(
+        &self,
+        status: StatusCode,
+        body: &T,
+    ) -> Result<Response<Full<Bytes>>> {
+        let json = serde_json::to_string(body)
+            .map_err(|e| SliceError::CacheError(format!("Failed to serialize response: {}", e)))?;
+
+        Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .header("cache-control", "no-cache, no-store, must-revalidate")
+            .body(Full::new(Bytes::from(json)))
+            .map_err(|e| SliceError::CacheError(format!("Failed to build response: {}", e)))
+    }
+
+    /// Build error response
+    fn error_response(&self, status: StatusCode, message: &str) -> Result<Response<Full<Bytes>>> {
+        let response = AdminErrorResponse {
+            success: false,
+            message: message.to_string(),
+        };
+
+        self.json_response(status, &response)
+    }
+}
+
+/// The `limit` query parameter, capped at [`MAX_KEYS_LIMIT`]
+///
+/// # Returns
+/// `None` if it is present but not a positive integer
+fn limit_param(query: Option<&str>) -> Option<usize> {
+    match query_param(query, "limit") {
+        None => Some(DEFAULT_KEYS_LIMIT),
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 => Some(limit.min(MAX_KEYS_LIMIT)),
+            _ => None,
+        },
+    }
+}
+
+/// Extract and percent-decode a query parameter
+fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    form_urlencoded::parse(query?.as_bytes())
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ByteRange;
+    use http_body_util::BodyExt;
+    use std::time::Duration;
+
+    async fn body_json(response: Response<Full<Bytes>>) -> serde_json::Value {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_inspect_entry() {
+        let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+        let range = ByteRange::new(0, 1023).unwrap();
+        cache
+            .store("http://example.com/test.dat", &range, Bytes::from(vec![1u8; 1024]))
+            .unwrap();
+        let handler = CacheAdminHandler::new(cache);
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/admin/cache/entry?key=http%3A%2F%2Fexample.com%2Ftest.dat%3Aslice%3A0%3A1023")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let json = body_json(response).await;
+        assert_eq!(json["key"], "http://example.com/test.dat:slice:0:1023");
+        assert_eq!(json["present"], true);
+        assert_eq!(json["tier"], "l1");
+        assert_eq!(json["size_bytes"], 1024);
+        assert_eq!(json["compressed"], false);
+        assert!(json["ttl_remaining_secs"].as_u64().unwrap() <= 60);
+        assert!(json.get("data").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_inspect_missing_entry() {
+        let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+        let handler = CacheAdminHandler::new(cache);
+
+        let req = Request::builder()
+            .uri("/admin/cache/entry?key=missing")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await["present"], false);
+    }
+
+    #[tokio::test]
+    async fn test_inspect_requires_token() {
+        let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+        let handler = CacheAdminHandler::with_auth(cache, "secret-token".to_string());
+
+        let req = Request::builder()
+            .uri("/admin/cache/entry?key=missing")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let req = Request::builder()
+            .uri("/admin/cache/entry?key=missing")
+            .header("authorization", "Bearer secret-token")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn populated_cache() -> (tempfile::TempDir, Arc<TieredCache>) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = Arc::new(
+            TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+                .await
+                .unwrap(),
+        );
+        for i in 0..3u64 {
+            let range = ByteRange::new(i * 1024, i * 1024 + 1023).unwrap();
+            cache
+                .store("http://example.com/video.mp4", &range, Bytes::from(vec![i as u8; 1024]))
+                .unwrap();
+        }
+        let range = ByteRange::new(0, 1023).unwrap();
+        cache
+            .store("http://example.com/other.bin", &range, Bytes::from(vec![9u8; 1024]))
+            .unwrap();
+        // Let the async L2 writes land
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        (temp_dir, cache)
+    }
+
+    #[tokio::test]
+    async fn test_list_keys() {
+        let (_dir, cache) = populated_cache().await;
+        let handler = CacheAdminHandler::new(cache);
+
+        let req = Request::builder()
+            .uri("/admin/cache/keys?prefix=http%3A%2F%2Fexample.com%2Fvideo.mp4&limit=2")
+            .body(())
+            .unwrap();
+        let json = body_json(handler.handle_request(req).await.unwrap()).await;
+        let keys = json["keys"].as_array().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], "http://example.com/video.mp4:slice:0:1023");
+        let next = json["next"].as_str().unwrap().to_string();
+
+        let uri = format!(
+            "/admin/cache/keys?prefix=http%3A%2F%2Fexample.com%2Fvideo.mp4&limit=2&after={}",
+            form_urlencoded::byte_serialize(next.as_bytes()).collect::<String>()
+        );
+        let req = Request::builder().uri(&uri).body(()).unwrap();
+        let json = body_json(handler.handle_request(req).await.unwrap()).await;
+        assert_eq!(json["keys"].as_array().unwrap().len(), 1);
+        assert!(json["next"].is_null());
+
+        // `cursor` is the same as `after`
+        let req = Request::builder().uri(uri.replace("&after=", "&cursor=")).body(()).unwrap();
+        let cursor_json = body_json(handler.handle_request(req).await.unwrap()).await;
+        assert_eq!(cursor_json, json);
+
+        let req = Request::builder()
+            .uri("/admin/cache/keys?limit=0")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_delete_entry() {
+        let (_dir, cache) = populated_cache().await;
+        let handler = CacheAdminHandler::with_auth(cache.clone(), "secret-token".to_string());
+        let uri = "/admin/cache/entry?key=http%3A%2F%2Fexample.com%2Fother.bin%3Aslice%3A0%3A1023";
+
+        let req = Request::builder()
+            .method(Method::DELETE)
+            .uri(uri)
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let req = Request::builder()
+            .method(Method::DELETE)
+            .uri(uri)
+            .header("authorization", "Bearer secret-token")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body_json(response).await;
+        assert_eq!(json["removed_from"], serde_json::json!(["l1", "l2"]));
+        assert!(cache.inspect("http://example.com/other.bin:slice:0:1023").await.is_none());
+
+        // The remaining entries are untouched
+        assert_eq!(cache.list_keys("", None, 10).keys.len(), 3);
+
+        let req = Request::builder()
+            .method(Method::DELETE)
+            .uri(uri)
+            .header("authorization", "Bearer secret-token")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await["removed_from"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_stats_schema() {
+        let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+        let range = ByteRange::new(0, 1023).unwrap();
+        cache.store("http://example.com/a", &range, Bytes::from(vec![1u8; 1024])).unwrap();
+        cache.lookup("http://example.com/a", &range).await.unwrap();
+        cache.lookup("http://example.com/b", &range).await.unwrap();
+        let handler = CacheAdminHandler::new(cache);
+
+        let req = Request::builder().uri("/admin/cache/stats").body(()).unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body_json(response).await;
+
+        // Dashboards depend on these names; change them only deliberately
+        let mut fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            [
+                "disk_errors",
+                "disk_writes",
+                "l1_admission_rejected",
+                "l1_bytes",
+                "l1_entries",
+                "l1_eviction_policy",
+                "l1_evictions_shrink",
+                "l1_evictions_size",
+                "l1_evictions_ttl",
+                "l1_hit_ratio",
+                "l1_hits",
+                "l1_sweep_running",
+                "l1_sweeps_coalesced",
+                "l2_backend",
+                "l2_degraded",
+                "l2_hits",
+                "misses",
+                "oldest_pending_write_ms",
+                "overall_hit_ratio",
+                "pending_write_bytes",
+                "pending_writes",
+            ]
+        );
+        assert_eq!(json["l1_hits"], 1);
+        assert_eq!(json["misses"], 1);
+        assert_eq!(json["l1_hit_ratio"], 0.5);
+        assert_eq!(json["overall_hit_ratio"], 0.5);
+        assert!(json["l2_backend"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_stats_section() {
+        let (_dir, cache) = populated_cache().await;
+        let handler = CacheAdminHandler::new(cache);
+
+        let req = Request::builder().uri("/admin/cache/stats?section=l2").body(()).unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body_json(response).await;
+        let mut fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(fields, ["l2_backend", "l2_degraded", "l2_hits"]);
+        assert_eq!(json["l2_backend"]["name"], "file");
+
+        let req = Request::builder().uri("/admin/cache/stats?section=gc").body(()).unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_top_entries() {
+        let (_dir, cache) = populated_cache().await;
+        let range = ByteRange::new(1024, 2047).unwrap();
+        cache.lookup("http://example.com/video.mp4", &range).await.unwrap();
+        let handler = CacheAdminHandler::new(cache);
+
+        let req = Request::builder().uri("/admin/cache/top?by=hits&limit=1").body(()).unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body_json(response).await;
+        assert_eq!(json["by"], "hits");
+        assert_eq!(json["l1"]["total_entries"], 4);
+        assert_eq!(json["l1"]["total_bytes"], 4096);
+        assert_eq!(json["l1"]["entries"][0]["key"], "http://example.com/video.mp4:slice:1024:2047");
+        assert_eq!(json["l1"]["entries"][0]["access_count"], 1);
+        assert_eq!(json["l2"]["total_entries"], 4);
+
+        let req = Request::builder().uri("/admin/cache/top").body(()).unwrap();
+        let json = body_json(handler.handle_request(req).await.unwrap()).await;
+        assert_eq!(json["by"], "size");
+        assert_eq!(json["l2"]["entries"].as_array().unwrap().len(), 4);
+
+        let req = Request::builder().uri("/admin/cache/top?by=name").body(()).unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_shrink_l1() {
+        let (_dir, cache) = populated_cache().await;
+        let before = cache.get_stats().l1_entries;
+        let handler = CacheAdminHandler::new(cache.clone());
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/cache/shrink?l1_bytes=0")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body_json(response).await;
+        assert_eq!(json["evicted"], before);
+        assert_eq!(json["l1_bytes"], 0);
+        assert_eq!(cache.get_stats().l1_evictions_shrink, before as u64);
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/cache/shrink")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let req = Request::builder().uri("/admin/cache/shrink?l1_bytes=0").body(()).unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_config_dump() {
+        let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+        let handler = CacheAdminHandler::with_auth(cache.clone(), "secret-token".to_string());
+        let req = Request::builder()
+            .uri("/admin/config")
+            .header("authorization", "Bearer secret-token")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let config = SliceConfig {
+            purge: Some(crate::config::PurgeConfig {
+                enabled: true,
+                auth_token: Some("secret-token".to_string()),
+                enable_metrics: true,
+            }),
+            ..Default::default()
+        };
+        let handler = CacheAdminHandler::with_auth(cache, "secret-token".to_string())
+            .with_config(Arc::new(config));
+        assert!(CacheAdminHandler::matches("/admin/config"));
+
+        let req = Request::builder().uri("/admin/config").body(()).unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let req = Request::builder()
+            .uri("/admin/config")
+            .header("authorization", "Bearer secret-token")
+            .body(())
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/yaml");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let yaml = String::from_utf8(body.to_vec()).unwrap();
+        assert!(yaml.contains("slice_size: 1048576"));
+        assert!(!yaml.contains("secret-token"));
+    }
+}
@@ -0,0 +1,228 @@
+//! Which origin responses are cached
+//!
+//! [`CachePolicy`] decides from the request method, the origin's status and
+//! its response headers whether fetched slices are stored, and for how
+//! long. Statuses come from `cache_admission`; per-user responses are never
+//! cached, whatever their status.
+
+use crate::config::SliceConfig;
+use crate::subrequest_manager::SubrequestResult;
+use http::{HeaderMap, HeaderName, Method};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Response headers that always keep a response out of the cache
+const UNCACHEABLE_RESPONSE_HEADERS: [&str; 2] = ["set-cookie", "authorization"];
+
+/// Outcome of [`CachePolicy::admit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// Cache the response, for the TTL configured for its status if any
+    Cache(Option<Duration>),
+    /// Only responses to GET are cached
+    SkipMethod,
+    /// The status is not in `cache_admission.statuses`
+    SkipStatus,
+    /// The response carries per-user headers
+    SkipPrivate,
+}
+
+impl Admission {
+    /// Whether the response is cached
+    pub fn is_cached(&self) -> bool {
+        matches!(self, Admission::Cache(_))
+    }
+
+    /// TTL configured for the response's status, if it is cached with one
+    pub fn ttl(&self) -> Option<Duration> {
+        match self {
+            Admission::Cache(ttl) => *ttl,
+            _ => None,
+        }
+    }
+}
+
+/// Decides which origin responses are cached
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    statuses: Vec<u16>,
+    status_ttls: BTreeMap<u16, Duration>,
+    uncacheable_headers: Vec<HeaderName>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self::from_config(&SliceConfig::default())
+    }
+}
+
+impl CachePolicy {
+    /// Create a policy from `cache_admission` and
+    /// `uncacheable_response_headers`
+    pub fn from_config(config: &SliceConfig) -> Self {
+        let uncacheable_headers = UNCACHEABLE_RESPONSE_HEADERS
+            .iter()
+            .map(|name| HeaderName::from_static(name))
+            .chain(
+                config
+                    .uncacheable_response_headers
+                    .iter()
+                    .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok()),
+            )
+            .collect();
+        CachePolicy {
+            statuses: config.cache_admission.statuses.clone(),
+            status_ttls: config
+                .cache_admission
+                .status_ttls
+                .iter()
+                .map(|(&status, &secs)| (status, Duration::from_secs(secs)))
+                .collect(),
+            uncacheable_headers,
+        }
+    }
+
+    /// Whether a response to a `method` request may be cached
+    ///
+    /// # Arguments
+    /// * `method` - Method of the request
+    /// * `status` - Status the origin answered with
+    /// * `headers` - Headers of the origin's response
+    pub fn admit(&self, method: &Method, status: u16, headers: &HeaderMap) -> Admission {
+        if method != Method::GET {
+            return Admission::SkipMethod;
+        }
+        if !self.statuses.contains(&status) {
+            return Admission::SkipStatus;
+        }
+        if is_private_response(headers, &self.uncacheable_headers) {
+            return Admission::SkipPrivate;
+        }
+        Admission::Cache(self.status_ttls.get(&status).copied())
+    }
+
+    /// Whether the slices of one fill may be cached
+    ///
+    /// A fill is cached only if every response in it is, for the shortest
+    /// of their configured TTLs.
+    pub fn admit_fill(&self, results: &[SubrequestResult]) -> Admission {
+        let mut fill_ttl = None;
+        for result in results {
+            match self.admit(&Method::GET, result.status, &result.headers) {
+                Admission::Cache(ttl) => {
+                    fill_ttl = match (fill_ttl, ttl) {
+                        (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+                        (a, b) => a.or(b),
+                    };
+                }
+                skip => return skip,
+            }
+        }
+        Admission::Cache(fill_ttl)
+    }
+}
+
+/// Whether a response carries per-user headers and must not be cached
+///
+/// True for `Cache-Control: private` or `no-store`, or when any header in
+/// `names` is present.
+fn is_private_response(headers: &HeaderMap, names: &[HeaderName]) -> bool {
+    let private_directive = headers
+        .get_all(http::header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|directive| directive.split('=').next().unwrap_or("").trim())
+        .any(|directive| directive.eq_ignore_ascii_case("private") || directive.eq_ignore_ascii_case("no-store"));
+    private_directive || names.iter().any(|name| headers.contains_key(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CacheAdmissionConfig;
+    use bytes::Bytes;
+    use http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn test_is_private_response() {
+        let names = [HeaderName::from_static("set-cookie"), HeaderName::from_static("x-user-id")];
+        assert!(!is_private_response(&headers(&[("cache-control", "public, max-age=60")]), &names));
+        assert!(is_private_response(&headers(&[("set-cookie", "session=abc")]), &names));
+        assert!(is_private_response(&headers(&[("x-user-id", "42")]), &names));
+        assert!(is_private_response(&headers(&[("cache-control", "max-age=60, Private")]), &names));
+        assert!(is_private_response(&headers(&[("cache-control", "private=\"set-cookie\"")]), &names));
+        assert!(is_private_response(
+            &headers(&[("cache-control", "max-age=60"), ("cache-control", "no-store")]),
+            &names
+        ));
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = CachePolicy::default();
+        assert_eq!(policy.admit(&Method::GET, 206, &HeaderMap::new()), Admission::Cache(None));
+        assert_eq!(policy.admit(&Method::GET, 200, &HeaderMap::new()), Admission::Cache(None));
+        assert_eq!(policy.admit(&Method::GET, 301, &HeaderMap::new()), Admission::SkipStatus);
+        assert_eq!(policy.admit(&Method::POST, 200, &HeaderMap::new()), Admission::SkipMethod);
+        assert_eq!(
+            policy.admit(&Method::GET, 206, &headers(&[("set-cookie", "session=abc")])),
+            Admission::SkipPrivate
+        );
+    }
+
+    #[test]
+    fn test_statuses_and_ttls_from_config() {
+        let policy = CachePolicy::from_config(&SliceConfig {
+            cache_admission: CacheAdmissionConfig {
+                statuses: vec![200],
+                status_ttls: BTreeMap::from([(200, 60)]),
+            },
+            uncacheable_response_headers: vec!["x-user-id".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(policy.admit(&Method::GET, 206, &HeaderMap::new()), Admission::SkipStatus);
+        assert_eq!(
+            policy.admit(&Method::GET, 200, &HeaderMap::new()),
+            Admission::Cache(Some(Duration::from_secs(60)))
+        );
+        assert_eq!(
+            policy.admit(&Method::GET, 200, &headers(&[("x-user-id", "42")])),
+            Admission::SkipPrivate
+        );
+    }
+
+    #[test]
+    fn test_fill_takes_shortest_ttl_and_any_skip() {
+        let policy = CachePolicy::from_config(&SliceConfig {
+            cache_admission: CacheAdmissionConfig {
+                statuses: vec![200, 206],
+                status_ttls: BTreeMap::from([(200, 60), (206, 30)]),
+            },
+            ..Default::default()
+        });
+        let result = |status: u16, pairs: &[(&'static str, &'static str)]| SubrequestResult {
+            slice_index: 0,
+            data: Bytes::new(),
+            status,
+            headers: headers(pairs),
+        };
+        assert_eq!(policy.admit_fill(&[]), Admission::Cache(None));
+        assert_eq!(
+            policy.admit_fill(&[result(200, &[]), result(206, &[])]),
+            Admission::Cache(Some(Duration::from_secs(30)))
+        );
+        assert_eq!(
+            policy.admit_fill(&[result(206, &[]), result(206, &[("cache-control", "no-store")])]),
+            Admission::SkipPrivate
+        );
+    }
+}
@@ -0,0 +1,2947 @@
+//! Pluggable L2 storage for [`TieredCache`](crate::TieredCache)
+//!
+//! The L2 tier of the tiered cache talks to its storage only through the
+//! [`CacheBackend`] trait. [`FileBackend`] is the built-in disk store; other
+//! stores (S3, Redis, memcached, ...) can be plugged in by implementing the
+//! trait and passing the backend to
+//! [`TieredCache::with_backend`](crate::TieredCache::with_backend).
+//!
+//! Backends are shared between request handling and the cache's background
+//! writer, so every method takes `&self` and implementations must be
+//! `Send + Sync`. The tiered cache tracks hits, writes and errors itself and
+//! bypasses a backend whose calls keep failing, so implementations only
+//! need to report failures through their `Result`s.
+
+use crate::error::{Result, SliceError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{debug, info, warn};
+
+/// Statistics reported by an L2 backend
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheBackendStats {
+    /// Short name of the backend, e.g. `file`
+    pub name: String,
+    /// Number of stored entries, if the backend tracks it
+    pub entries: Option<u64>,
+    /// Bytes of stored entry data, if the backend tracks it
+    pub bytes: Option<u64>,
+    /// What loading the entry index found at startup, for backends that
+    /// keep one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<IndexStats>,
+}
+
+/// Metadata about a single L2 entry, without its body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendEntry {
+    pub size_bytes: usize,
+    /// When the entry was stored, in seconds since the Unix epoch
+    pub stored_at_secs: u64,
+    pub ttl_remaining: Duration,
+    /// Offset of the entry data in the backing store, if meaningful
+    pub offset: Option<u64>,
+}
+
+/// Storage behind the L2 tier of the tiered cache
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Store `data` under `key`, replacing any existing entry
+    ///
+    /// The entry must not be returned by [`lookup`](Self::lookup) once
+    /// `ttl` has elapsed.
+    async fn store(&self, key: &str, data: Bytes, ttl: Duration) -> Result<()>;
+
+    /// Look up the unexpired entry stored under `key`
+    async fn lookup(&self, key: &str) -> Result<Option<Bytes>>;
+
+    /// Remove the entry stored under `key`
+    ///
+    /// Returns whether an entry was removed.
+    async fn remove(&self, key: &str) -> Result<bool>;
+
+    /// Remove every entry, returning how many were removed
+    async fn purge_all(&self) -> Result<usize>;
+
+    /// Current backend statistics
+    fn stats(&self) -> CacheBackendStats;
+
+    /// Check that the backend can accept writes
+    ///
+    /// Called periodically while the tiered cache bypasses the backend after
+    /// repeated errors; the backend is used again once this succeeds.
+    async fn health(&self) -> Result<()>;
+
+    /// Metadata of the unexpired entry under `key`, for cache inspection
+    ///
+    /// Backends that cannot report it without reading the body may keep the
+    /// default, which reports no entry.
+    async fn entry(&self, key: &str) -> Result<Option<BackendEntry>> {
+        let _ = key;
+        Ok(None)
+    }
+
+    /// Call `visit` with the name and metadata of every unexpired entry
+    ///
+    /// Names only need to identify entries within the backend: backends
+    /// that store entries under a hash of the key cannot report the key.
+    /// Meant for occasional reports, so implementations may walk the whole
+    /// store.
+    ///
+    /// # Returns
+    /// `false` if the backend cannot enumerate its entries (the default)
+    async fn visit_entries(&self, visit: &mut (dyn FnMut(String, BackendEntry) + Send)) -> Result<bool> {
+        let _ = visit;
+        Ok(false)
+    }
+
+    /// Make every stored entry durable
+    ///
+    /// Called by [`TieredCache::flush`](crate::TieredCache::flush) after
+    /// the queued writes. Backends that persist each write before `store`
+    /// returns may keep the default, which does nothing.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// When [`FileBackend`] flushes written entries to stable storage
+///
+/// Syncing only matters for crashes and power loss: entries handed to the
+/// OS survive the process exiting under every policy. An entry lost in a
+/// crash is a cache miss, but one left partly written is read back short
+/// until it expires or is purged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Never sync; the OS writes entries back on its own schedule
+    ///
+    /// Highest write throughput. Any entry written since the OS last flushed
+    /// its page cache (typically the last 30 seconds) can be lost or left
+    /// partly written in a crash.
+    Never,
+    /// Sync every entry before its write completes (default)
+    ///
+    /// No completed write is lost in a crash, at the cost of one fsync per
+    /// entry.
+    #[default]
+    PerWrite,
+    /// Sync the entries written since the last sync at most once per interval
+    ///
+    /// Syncing happens on the first write after the interval elapses, or
+    /// from a timer when no write comes, so entries written within about
+    /// the last interval can be lost or left partly written in a crash.
+    /// [`FileBackend::with_sync_thresholds`] also syncs early once enough
+    /// files or bytes are waiting. Call [`FileBackend::sync`] before
+    /// shutdown to close the gap.
+    Interval(Duration),
+}
+
+/// L2 backend storing one file per entry under a base directory
+///
+/// Each file starts with the entry's expiry time (8 bytes, little-endian
+/// seconds since the Unix epoch) followed by the data. Files are spread over
+/// two levels of subdirectories derived from a hash of the key.
+///
+/// With [`with_dedup`](Self::with_dedup), entry data is content-addressed:
+/// each distinct body is stored once under `objects/`, named by its blake3
+/// hash and prefixed with a reference count (8 bytes, little-endian), and
+/// the entry file holds the expiry time followed by the 32-byte hash.
+/// Storing a body that is already present only bumps its count; removing
+/// an entry decrements it, and the body is deleted when it reaches zero.
+/// The two layouts are not compatible: [`verify_layout`](Self::verify_layout)
+/// records the layout in a `.layout` file and refuses to open a directory
+/// written in the other one, unless
+/// [`with_layout_migration`](Self::with_layout_migration) is set, in which
+/// case it rewrites the existing entries.
+///
+/// Files are written under a temporary name and renamed into place, so a
+/// failed or interrupted write never leaves a partial entry behind. A
+/// crash can still leave temporary files or, in dedup mode, reference
+/// counts that disagree with the entries; [`recover`](Self::recover)
+/// cleans both up, and [`check_consistency`](Self::check_consistency)
+/// reports such disagreements without touching anything.
+#[derive(Debug, Clone)]
+pub struct FileBackend {
+    base_path: PathBuf,
+    fsync_policy: FsyncPolicy,
+    /// Files and bytes awaiting an interval sync that trigger it early
+    sync_max_files: Option<usize>,
+    sync_max_bytes: Option<u64>,
+    sync_state: Arc<SyncState>,
+    dedup: bool,
+    /// Rewrite entries stored in the other layout instead of refusing them
+    migrate_layout: bool,
+    /// Serialize reference count updates in dedup mode
+    ref_locks: Arc<RefLocks>,
+    /// Discrepancies found by consistency checks so far
+    consistency_errors: Arc<AtomicU64>,
+    index: Arc<EntryIndex>,
+    /// Files the index does not list are kept while younger than this
+    orphan_grace: Duration,
+    /// Refuse every write, see [`open_readonly`](FileBackend::open_readonly)
+    read_only: bool,
+}
+
+/// Default number of lock stripes of [`FileBackend`]'s dedup mode
+const DEFAULT_LOCK_SHARDS: usize = 16;
+
+/// Striped locks serializing [`FileBackend`]'s dedup reference updates
+///
+/// An update holds the stripe of its key, then the stripes of the bodies
+/// it references, in index order, so updates to unrelated keys and bodies
+/// run side by side. Passes over the whole directory hold every stripe.
+#[derive(Debug)]
+struct RefLocks {
+    keys: Vec<tokio::sync::Mutex<()>>,
+    objects: Vec<tokio::sync::Mutex<()>>,
+}
+
+impl RefLocks {
+    fn new(shards: usize) -> Self {
+        let stripes = || (0..shards.max(1)).map(|_| tokio::sync::Mutex::new(())).collect();
+        RefLocks {
+            keys: stripes(),
+            objects: stripes(),
+        }
+    }
+
+    fn key_shard(&self, key: &str) -> usize {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.keys.len() as u64) as usize
+    }
+
+    fn object_shard(&self, hash: &[u8; 32]) -> usize {
+        let prefix = u64::from_le_bytes(hash[..8].try_into().unwrap());
+        (prefix % self.objects.len() as u64) as usize
+    }
+
+    /// Lock the stripe of `key`
+    async fn lock_key(&self, key: &str) -> tokio::sync::MutexGuard<'_, ()> {
+        self.keys[self.key_shard(key)].lock().await
+    }
+
+    /// Lock the stripes of the bodies with `hashes`
+    ///
+    /// Call with the key's stripe held, so a whole-directory pass cannot
+    /// slip in between.
+    async fn lock_objects(&self, hashes: &[[u8; 32]]) -> Vec<tokio::sync::MutexGuard<'_, ()>> {
+        let mut shards: Vec<usize> = hashes.iter().map(|hash| self.object_shard(hash)).collect();
+        shards.sort_unstable();
+        shards.dedup();
+        let mut guards = Vec::with_capacity(shards.len());
+        for shard in shards {
+            guards.push(self.objects[shard].lock().await);
+        }
+        guards
+    }
+
+    /// Lock every stripe
+    async fn lock_all(&self) -> Vec<tokio::sync::MutexGuard<'_, ()>> {
+        let mut guards = Vec::with_capacity(self.keys.len() + self.objects.len());
+        for lock in self.keys.iter().chain(&self.objects) {
+            guards.push(lock.lock().await);
+        }
+        guards
+    }
+}
+
+/// Space saved by [`FileBackend`]'s dedup mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DedupStats {
+    /// Distinct bodies stored
+    pub objects: u64,
+    /// Bytes of entry data as seen by callers, counting every reference
+    pub logical_bytes: u64,
+    /// Bytes of entry data actually stored
+    pub physical_bytes: u64,
+}
+
+/// State of [`FileBackend`]'s interval syncing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStats {
+    /// Files written but not yet synced
+    pub pending_files: usize,
+    /// Bytes of the files not yet synced
+    pub pending_bytes: u64,
+    /// Batches of files synced
+    pub syncs: u64,
+    /// Files synced
+    pub fsyncs: u64,
+    /// Syncs due on a write that were left to the running one
+    pub coalesced: u64,
+}
+
+/// What [`FileBackend::recover`] cleaned up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryStats {
+    /// Temporary files left by interrupted writes
+    pub temp_files_removed: u64,
+    /// Shared bodies no entry referenced any more (dedup mode)
+    pub objects_reclaimed: u64,
+    /// Shared bodies whose reference count was corrected (dedup mode)
+    pub refcounts_fixed: u64,
+}
+
+/// What [`FileBackend::load_index`] found reconciling the index with the
+/// directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStats {
+    /// Entries listed in the index whose file was found
+    pub recovered_entries: u64,
+    /// Bytes of entry data of the recovered entries
+    pub recovered_bytes: u64,
+    /// Files the index did not list that were added to it: written just
+    /// before a crash, or found in a directory without an index
+    pub adopted_entries: u64,
+    /// Entries listed in the index whose file was gone
+    pub missing_entries: u64,
+    /// Files the index did not list that were deleted
+    pub orphans_removed: u64,
+    /// Bytes of the deleted files
+    pub orphan_bytes: u64,
+}
+
+/// Discrepancies found by [`FileBackend::check_consistency`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    /// Entry files checked
+    pub entries: u64,
+    /// Entry files too short to hold an entry
+    pub corrupt_entries: u64,
+    /// Entries referencing a shared body that does not exist (dedup mode)
+    pub dangling_entries: u64,
+    /// Shared bodies no entry references (dedup mode)
+    pub unreferenced_objects: u64,
+    /// Shared bodies whose reference count differs from the entries
+    /// pointing to them (dedup mode)
+    pub refcount_mismatches: u64,
+}
+
+impl ConsistencyReport {
+    /// Total number of discrepancies
+    pub fn problems(&self) -> u64 {
+        self.corrupt_entries
+            + self.dangling_entries
+            + self.unreferenced_objects
+            + self.refcount_mismatches
+    }
+}
+
+/// An entry recorded in [`FileBackend`]'s index file, read by
+/// [`FileBackend::read_index`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexRecord {
+    /// Entry file path relative to the base directory
+    pub file: String,
+    /// Key the entry was stored under; empty for files adopted without a
+    /// record
+    pub key: String,
+    pub size_bytes: u64,
+    pub stored_at_secs: u64,
+    pub expires_at_secs: u64,
+}
+
+/// Space used by a [`FileBackend`] directory, from
+/// [`FileBackend::disk_usage`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskUsage {
+    /// Entry files
+    pub entries: u64,
+    /// Entry files past their expiry that lookups no longer return
+    pub expired_entries: u64,
+    /// Length of every file in the directory
+    pub file_bytes: u64,
+    /// Disk space allocated to those files, in whole filesystem blocks
+    pub allocated_bytes: u64,
+    /// Temporary files left by interrupted writes
+    pub temp_files: u64,
+}
+
+impl DiskUsage {
+    /// Allocated space beyond the files' lengths, lost to partly used
+    /// blocks
+    pub fn slack_bytes(&self) -> u64 {
+        self.allocated_bytes.saturating_sub(self.file_bytes)
+    }
+}
+
+/// Layout of [`FileBackend`] entries found by [`FileBackend::verify_layout`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileLayout {
+    /// Expiry followed by the data
+    Plain,
+    /// Expiry followed by the hash of a shared body
+    Dedup,
+}
+
+impl FileLayout {
+    fn as_str(self) -> &'static str {
+        match self {
+            FileLayout::Plain => "plain",
+            FileLayout::Dedup => "dedup",
+        }
+    }
+}
+
+/// How the data of [`FileBackend`] entries is encoded, checked by
+/// [`FileBackend::verify_entry_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryFormat {
+    /// The slice data as stored
+    Raw,
+    /// Records of a [`DeltaBackend`](crate::DeltaBackend): a type byte
+    /// followed by the data or a diff
+    Delta,
+}
+
+impl EntryFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            EntryFormat::Raw => "raw",
+            EntryFormat::Delta => "delta",
+        }
+    }
+}
+
+/// File recording the layout of a [`FileBackend`] directory
+const LAYOUT_FILE: &str = ".layout";
+
+/// File recording the cache key format of a [`FileBackend`] directory
+const KEY_VERSION_FILE: &str = ".key-version";
+
+/// File recording the entry format of a [`FileBackend`] directory
+const ENTRY_FORMAT_FILE: &str = ".entry-format";
+
+/// Marks the temporary files writes go through before they are renamed
+const TEMP_MARKER: &str = ".~partial.";
+
+/// Distinguishes concurrent temporary files for the same path
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// File holding the entry index of a [`FileBackend`] directory
+const INDEX_FILE: &str = ".index";
+
+/// First bytes of the index file, naming its format
+const INDEX_MAGIC: &[u8; 8] = b"SLIDX001";
+
+/// Records appended to the index file before it is rewritten as a
+/// snapshot, provided they also outnumber the entries two to one
+const INDEX_COMPACT_RECORDS: usize = 4096;
+
+/// Age past which [`FileBackend::load_index`] deletes files the index
+/// does not list (default)
+const DEFAULT_ORPHAN_GRACE: Duration = Duration::from_secs(600);
+
+/// An entry listed in [`FileBackend`]'s index
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexedEntry {
+    /// Key the entry was stored under; empty for files adopted without a
+    /// record
+    key: String,
+    size: u64,
+    stored_at_secs: u64,
+    expires_at_secs: u64,
+}
+
+/// Entries of the index by file path relative to the base directory
+#[derive(Debug, Default)]
+struct IndexedEntries {
+    entries: HashMap<String, IndexedEntry>,
+    bytes: u64,
+}
+
+impl IndexedEntries {
+    fn insert(&mut self, name: String, entry: IndexedEntry) {
+        self.bytes += entry.size;
+        if let Some(old) = self.entries.insert(name, entry) {
+            self.bytes -= old.size;
+        }
+    }
+
+    fn remove(&mut self, name: &str) {
+        if let Some(old) = self.entries.remove(name) {
+            self.bytes -= old.size;
+        }
+    }
+
+    /// Contents of an index file listing exactly these entries
+    fn snapshot(&self) -> Vec<u8> {
+        let mut data = INDEX_MAGIC.to_vec();
+        for (name, entry) in &self.entries {
+            encode_index_record(&mut data, name, Some(entry));
+        }
+        data
+    }
+}
+
+/// [`FileBackend`]'s entry index, shared by its clones
+#[derive(Debug, Default)]
+struct EntryIndex {
+    /// `None` until [`FileBackend::load_index`] has run
+    entries: Mutex<Option<IndexedEntries>>,
+    /// Index file open for appending, and the records it holds
+    ///
+    /// Held while a change is applied and appended, so the file lists
+    /// changes in the order they were made.
+    log: tokio::sync::Mutex<Option<(fs::File, usize)>>,
+    /// What loading the index found
+    loaded: Mutex<Option<IndexStats>>,
+}
+
+/// Files awaiting a sync under [`FsyncPolicy::Interval`], and fsync counts
+#[derive(Debug)]
+struct SyncState {
+    pending: Mutex<PendingSync>,
+    fsyncs: AtomicU64,
+    /// Batches synced
+    syncs: AtomicU64,
+    /// Held while files are synced, so syncs never overlap
+    syncing: tokio::sync::Mutex<()>,
+    /// Syncs due on a write that were left to the timer because another
+    /// sync was running
+    coalesced: AtomicU64,
+    /// Syncs running now and the most ever seen at once
+    #[cfg(test)]
+    active: (AtomicU64, AtomicU64),
+}
+
+#[derive(Debug)]
+struct PendingSync {
+    files: Vec<PathBuf>,
+    /// Bytes written to `files`
+    bytes: u64,
+    last_sync: Instant,
+    /// Whether the timer task syncing idle writes is running; at most one
+    /// runs, for as long as writes keep coming
+    timer_running: bool,
+}
+
+impl PendingSync {
+    /// Take the files waiting for a sync and their bytes
+    fn take(&mut self) -> (Vec<PathBuf>, u64) {
+        (std::mem::take(&mut self.files), std::mem::take(&mut self.bytes))
+    }
+}
+
+impl FileBackend {
+    /// Create a backend under `base_path`, creating the directory if needed
+    pub async fn new(base_path: impl AsRef<Path>) -> Result<Self> {
+        let base_path = base_path.as_ref().to_path_buf();
+        fs::create_dir_all(&base_path).await.map_err(|e| {
+            SliceError::CacheError(format!("Failed to create L2 cache directory: {}", e))
+        })?;
+        Ok(FileBackend {
+            base_path,
+            fsync_policy: FsyncPolicy::default(),
+            sync_max_files: None,
+            sync_max_bytes: None,
+            sync_state: Arc::new(SyncState {
+                pending: Mutex::new(PendingSync {
+                    files: Vec::new(),
+                    bytes: 0,
+                    last_sync: Instant::now(),
+                    timer_running: false,
+                }),
+                fsyncs: AtomicU64::new(0),
+                syncs: AtomicU64::new(0),
+                syncing: tokio::sync::Mutex::new(()),
+                coalesced: AtomicU64::new(0),
+                #[cfg(test)]
+                active: Default::default(),
+            }),
+            dedup: false,
+            migrate_layout: false,
+            ref_locks: Arc::new(RefLocks::new(DEFAULT_LOCK_SHARDS)),
+            consistency_errors: Arc::new(AtomicU64::new(0)),
+            index: Arc::new(EntryIndex::default()),
+            orphan_grace: DEFAULT_ORPHAN_GRACE,
+            read_only: false,
+        })
+    }
+
+    /// Open an existing directory without ever writing to it
+    ///
+    /// Meant for inspecting a cache offline, possibly while a proxy still
+    /// uses it. The layout is read from the directory instead of being
+    /// configured. Lookups leave expired entries in place, and stores,
+    /// removals, [`recover`](Self::recover), [`load_index`](Self::load_index)
+    /// and [`verify_layout`](Self::verify_layout) fail with
+    /// [`SliceError::CacheError`].
+    pub async fn open_readonly(base_path: impl AsRef<Path>) -> Result<Self> {
+        let base_path = base_path.as_ref();
+        if !fs::metadata(base_path).await.is_ok_and(|m| m.is_dir()) {
+            return Err(SliceError::CacheError(format!(
+                "L2 cache directory {} does not exist",
+                base_path.display()
+            )));
+        }
+        let mut backend = Self::new(base_path).await?;
+        backend.read_only = true;
+        backend.dedup = backend.stored_layout().await? == Some(FileLayout::Dedup);
+        Ok(backend)
+    }
+
+    /// Whether the backend was opened with [`open_readonly`](Self::open_readonly)
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail if the backend was opened read-only
+    fn writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(SliceError::CacheError(format!(
+                "L2 cache directory {} is opened read-only",
+                self.base_path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Let [`verify_layout`](Self::verify_layout) rewrite entries stored in
+    /// the other layout (default: off, the directory is refused)
+    pub fn with_layout_migration(mut self, enabled: bool) -> Self {
+        self.migrate_layout = enabled;
+        self
+    }
+
+    /// Layout this backend writes
+    pub fn layout(&self) -> FileLayout {
+        if self.dedup {
+            FileLayout::Dedup
+        } else {
+            FileLayout::Plain
+        }
+    }
+
+    /// Store identical entry bodies once, shared between keys (default: off)
+    pub fn with_dedup(mut self, enabled: bool) -> Self {
+        self.dedup = enabled;
+        self
+    }
+
+    /// Split the locks guarding dedup reference counts into `shards`
+    /// stripes (default: 16)
+    ///
+    /// Stores and removals of keys and bodies in different stripes do not
+    /// wait for each other; `1` serializes them all.
+    pub fn with_lock_shards(mut self, shards: usize) -> Self {
+        self.ref_locks = Arc::new(RefLocks::new(shards));
+        self
+    }
+
+    /// Keep files the index does not list while they are younger than
+    /// `grace` (default: 10 minutes)
+    ///
+    /// See [`load_index`](Self::load_index).
+    pub fn with_orphan_grace(mut self, grace: Duration) -> Self {
+        self.orphan_grace = grace;
+        self
+    }
+
+    /// Set when written entries are synced to stable storage
+    /// (default: [`FsyncPolicy::PerWrite`])
+    pub fn with_fsync_policy(mut self, policy: FsyncPolicy) -> Self {
+        self.fsync_policy = policy;
+        self
+    }
+
+    /// Under [`FsyncPolicy::Interval`], also sync as soon as `max_files`
+    /// files or `max_bytes` bytes are waiting (default: only the interval)
+    pub fn with_sync_thresholds(mut self, max_files: Option<usize>, max_bytes: Option<u64>) -> Self {
+        self.sync_max_files = max_files;
+        self.sync_max_bytes = max_bytes;
+        self
+    }
+
+    /// Files and bytes waiting for a sync, and syncs done so far
+    pub fn sync_stats(&self) -> SyncStats {
+        let pending = self.sync_state.pending.lock().unwrap();
+        SyncStats {
+            pending_files: pending.files.len(),
+            pending_bytes: pending.bytes,
+            syncs: self.sync_state.syncs.load(Ordering::Relaxed),
+            fsyncs: self.fsync_count(),
+            coalesced: self.syncs_coalesced(),
+        }
+    }
+
+    /// Number of files synced to stable storage so far
+    pub fn fsync_count(&self) -> u64 {
+        self.sync_state.fsyncs.load(Ordering::Relaxed)
+    }
+
+    /// Number of syncs due on a write that were coalesced into the sync
+    /// already running
+    pub fn syncs_coalesced(&self) -> u64 {
+        self.sync_state.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// Sync every entry written but not yet synced
+    ///
+    /// Only [`FsyncPolicy::Interval`] leaves entries waiting for a sync.
+    /// Waits for a sync already running to finish first.
+    pub async fn sync(&self) -> Result<()> {
+        let syncing = self.sync_state.syncing.lock().await;
+        let files = self.sync_state.pending.lock().unwrap().take();
+        self.sync_files(&syncing, files).await
+    }
+
+    /// Queue `files` holding `bytes` for the next sync, starting the sync
+    /// timer if it is not running
+    ///
+    /// # Returns
+    /// Every queued file and its bytes if `take_due` is set and `interval`
+    /// has passed since the last sync or a threshold is reached, for the
+    /// caller to sync now
+    fn queue_sync(
+        &self,
+        (files, bytes): (Vec<PathBuf>, u64),
+        interval: Duration,
+        take_due: bool,
+    ) -> (Vec<PathBuf>, u64) {
+        let (due, start_timer) = {
+            let mut pending = self.sync_state.pending.lock().unwrap();
+            pending.files.extend(files);
+            pending.bytes += bytes;
+            let start_timer = !pending.timer_running;
+            pending.timer_running = true;
+            let due = pending.last_sync.elapsed() >= interval
+                || self.sync_max_files.is_some_and(|max| pending.files.len() >= max)
+                || self.sync_max_bytes.is_some_and(|max| pending.bytes >= max);
+            if take_due && due {
+                pending.last_sync = Instant::now();
+                (pending.take(), start_timer)
+            } else {
+                ((Vec::new(), 0), start_timer)
+            }
+        };
+        if start_timer {
+            tokio::spawn(self.clone().sync_timer(interval));
+        }
+        due
+    }
+
+    /// Sync queued writes every `interval` until an interval passes
+    /// without any
+    async fn sync_timer(self, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let syncing = self.sync_state.syncing.lock().await;
+            let files = {
+                let mut pending = self.sync_state.pending.lock().unwrap();
+                pending.last_sync = Instant::now();
+                if pending.files.is_empty() {
+                    // Cleared under the lock writes queue under, so a write
+                    // either sees the timer running or starts a new one
+                    pending.timer_running = false;
+                    return;
+                }
+                pending.take()
+            };
+            if let Err(e) = self.sync_files(&syncing, files).await {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    /// Sync `files`, skipping any that were removed since they were written
+    ///
+    /// Takes the `syncing` guard so that syncs never run concurrently.
+    async fn sync_files(
+        &self,
+        _syncing: &tokio::sync::MutexGuard<'_, ()>,
+        (files, _): (Vec<PathBuf>, u64),
+    ) -> Result<()> {
+        if files.is_empty() {
+            return Ok(());
+        }
+        self.sync_state.syncs.fetch_add(1, Ordering::Relaxed);
+        #[cfg(test)]
+        {
+            let (active, peak) = &self.sync_state.active;
+            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+        }
+        let result = self.sync_each(files).await;
+        #[cfg(test)]
+        self.sync_state.active.0.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    async fn sync_each(&self, files: Vec<PathBuf>) -> Result<()> {
+        for path in files {
+            let file = match fs::File::open(&path).await {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(SliceError::CacheError(format!("Failed to open file for sync: {}", e)));
+                }
+            };
+            file.sync_all().await.map_err(|e| {
+                SliceError::CacheError(format!("Failed to sync file: {}", e))
+            })?;
+            self.sync_state.fsyncs.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Base directory of the backend
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
+    /// Path of the file holding `key`
+    fn file_path(&self, key: &str) -> PathBuf {
+        // Use hash to create subdirectories (avoid too many files in one dir)
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        // Create 2-level directory structure: XX/YY/filename
+        let dir1 = format!("{:02x}", (hash >> 8) & 0xFF);
+        let dir2 = format!("{:02x}", hash & 0xFF);
+
+        // Sanitize key for filename
+        let filename = key
+            .replace('/', "_")
+            .replace(':', "_")
+            .replace('?', "_")
+            .replace('&', "_");
+
+        self.base_path.join(dir1).join(dir2).join(filename)
+    }
+
+    /// Remove every file below `dir`, returning how many were removed
+    async fn remove_files(dir: &Path) -> std::io::Result<usize> {
+        let mut removed = 0;
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    pending.push(entry.path());
+                } else if ![LAYOUT_FILE, INDEX_FILE, KEY_VERSION_FILE, ENTRY_FORMAT_FILE]
+                    .iter()
+                    .any(|name| entry.file_name() == *name)
+                {
+                    fs::remove_file(entry.path()).await?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Sync or flush a freshly written file according to the fsync policy,
+    /// moving it from `written` to `path` once its data is handed off
+    async fn finish_write(
+        &self,
+        mut file: fs::File,
+        written: &Path,
+        path: &Path,
+        len: u64,
+    ) -> Result<()> {
+        let commit = || async {
+            if written == path {
+                return Ok(());
+            }
+            fs::rename(written, path).await.map_err(|e| {
+                SliceError::CacheError(format!("Failed to commit cache file: {}", e))
+            })
+        };
+        match self.fsync_policy {
+            FsyncPolicy::Never => {
+                // Hand buffered data to the OS before the file is closed
+                file.flush().await.map_err(|e| {
+                    SliceError::CacheError(format!("Failed to flush file: {}", e))
+                })?;
+                commit().await?;
+            }
+            FsyncPolicy::PerWrite => {
+                file.sync_all().await.map_err(|e| {
+                    SliceError::CacheError(format!("Failed to sync file: {}", e))
+                })?;
+                self.sync_state.fsyncs.fetch_add(1, Ordering::Relaxed);
+                commit().await?;
+            }
+            FsyncPolicy::Interval(interval) => {
+                file.flush().await.map_err(|e| {
+                    SliceError::CacheError(format!("Failed to flush file: {}", e))
+                })?;
+                commit().await?;
+                let due = self.queue_sync((vec![path.to_path_buf()], len), interval, true);
+                if !due.0.is_empty() {
+                    match self.sync_state.syncing.try_lock() {
+                        Ok(syncing) => self.sync_files(&syncing, due).await?,
+                        Err(_) => {
+                            // The running sync or the timer gets to them
+                            self.sync_state.coalesced.fetch_add(1, Ordering::Relaxed);
+                            self.queue_sync(due, interval, false);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Create `path` holding `parts` back to back
+    ///
+    /// The data goes to a temporary file that is renamed over `path` once
+    /// it is complete, so on error `path` is left as it was.
+    async fn write_file(&self, path: &Path, parts: &[&[u8]]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                SliceError::CacheError(format!("Failed to create cache directory: {}", e))
+            })?;
+        }
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(format!(
+            "{}{}",
+            TEMP_MARKER,
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp_path = path.with_file_name(temp_name);
+
+        let write = async {
+            let mut file = fs::File::create(&temp_path).await.map_err(|e| {
+                SliceError::CacheError(format!("Failed to create cache file: {}", e))
+            })?;
+            for part in parts {
+                file.write_all(part).await.map_err(|e| {
+                    SliceError::CacheError(format!("Failed to write cache file: {}", e))
+                })?;
+            }
+            let len = parts.iter().map(|part| part.len() as u64).sum();
+            self.finish_write(file, &temp_path, path, len).await
+        };
+        let result = write.await;
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path).await;
+        }
+        result
+    }
+
+    /// Name of the entry file at `path` in the index
+    fn index_name(&self, path: &Path) -> String {
+        path.strip_prefix(&self.base_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Whether the index is loaded and lists no entry at `path`
+    fn unindexed(&self, path: &Path) -> bool {
+        let entries = self.index.entries.lock().unwrap();
+        entries
+            .as_ref()
+            .is_some_and(|indexed| !indexed.entries.contains_key(&self.index_name(path)))
+    }
+
+    /// Record in the index that `path` now holds `entry`, or nothing
+    ///
+    /// Does nothing until the index is loaded. The record is appended
+    /// without a sync, and a failure to append is only logged: the entry
+    /// itself is already written or removed, and
+    /// [`load_index`](Self::load_index) reconciles the two.
+    async fn index_update(&self, path: &Path, entry: Option<IndexedEntry>) {
+        let mut log = self.index.log.lock().await;
+        let Some((file, records)) = log.as_mut() else {
+            return;
+        };
+        let name = self.index_name(path);
+        let mut record = Vec::new();
+        encode_index_record(&mut record, &name, entry.as_ref());
+        let live = {
+            let mut entries = self.index.entries.lock().unwrap();
+            let Some(indexed) = entries.as_mut() else {
+                return;
+            };
+            match entry {
+                Some(entry) => indexed.insert(name, entry),
+                None => indexed.remove(&name),
+            }
+            indexed.entries.len()
+        };
+
+        let appended = async {
+            file.write_all(&record).await?;
+            file.flush().await
+        };
+        if let Err(e) = appended.await {
+            warn!("Failed to append to L2 index: {}", e);
+        }
+        *records += 1;
+        if *records > INDEX_COMPACT_RECORDS && *records > 2 * live {
+            if let Err(e) = self.write_index(&mut log).await {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    /// Rewrite the index file as a snapshot of the entries and reopen it
+    /// for appending
+    ///
+    /// On failure the index is dropped, and lookups go to the directory as
+    /// they do before it is loaded.
+    async fn write_index(&self, log: &mut Option<(fs::File, usize)>) -> Result<()> {
+        let snapshot = self.index.entries.lock().unwrap().as_ref().map(|indexed| {
+            (indexed.snapshot(), indexed.entries.len())
+        });
+        let Some((snapshot, live)) = snapshot else {
+            return Ok(());
+        };
+        let path = self.base_path.join(INDEX_FILE);
+        let reopened = async {
+            self.write_file(&path, &[&snapshot]).await?;
+            fs::OpenOptions::new().append(true).open(&path).await.map_err(|e| {
+                SliceError::CacheError(format!("Failed to open L2 index: {}", e))
+            })
+        };
+        match reopened.await {
+            Ok(file) => {
+                *log = Some((file, live));
+                Ok(())
+            }
+            Err(e) => {
+                *log = None;
+                *self.index.entries.lock().unwrap() = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// Load the entry index and reconcile it with the directory
+    ///
+    /// The index lists every entry with its key, size, store time and
+    /// expiry. It lives in the `.index` file as a snapshot followed by a
+    /// log of the stores and removals since. Once loaded it is kept up to
+    /// date by every write, lookups of entries it does not list are
+    /// answered without touching the disk, and
+    /// [`stats`](CacheBackend::stats) reports entry and byte counts.
+    ///
+    /// Index entries whose file is gone are dropped. Files the index does
+    /// not list are deleted once they are older than the grace period
+    /// ([`with_orphan_grace`](Self::with_orphan_grace)); younger ones, whose
+    /// records an unsynced log lost in a crash, are added to the index, as
+    /// is every file of a directory without an index. The index is then
+    /// rewritten as a snapshot. In dedup mode, deleted files keep their
+    /// bodies referenced until [`recover`](Self::recover) runs. Walks the
+    /// whole directory, so run it at startup before serving traffic, after
+    /// [`verify_layout`](Self::verify_layout).
+    pub async fn load_index(&self) -> Result<IndexStats> {
+        self.writable()?;
+        let _refs = self.ref_locks.lock_all().await;
+        let mut log = self.index.log.lock().await;
+        let index_path = self.base_path.join(INDEX_FILE);
+        let mut listed = match fs::read(&index_path).await {
+            Ok(data) => {
+                let listed = decode_index(&data);
+                if listed.is_none() {
+                    warn!("Unreadable L2 index {}; rebuilding it from the directory", index_path.display());
+                }
+                listed
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(SliceError::CacheError(format!("Failed to read L2 index: {}", e)));
+            }
+        };
+        let adopt_all = listed.is_none();
+
+        let mut stats = IndexStats::default();
+        let mut indexed = IndexedEntries::default();
+        let now = SystemTime::now();
+        for path in self.entry_files().await? {
+            let Ok(metadata) = fs::metadata(&path).await else {
+                continue;
+            };
+            let name = self.index_name(&path);
+            if let Some(mut entry) = listed.as_mut().and_then(|listed| listed.entries.remove(&name)) {
+                if !self.dedup {
+                    // The file is the truth if a record was lost
+                    entry.size = metadata.len().saturating_sub(8);
+                }
+                stats.recovered_entries += 1;
+                stats.recovered_bytes += entry.size;
+                indexed.insert(name, entry);
+                continue;
+            }
+
+            let young = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_none_or(|age| age < self.orphan_grace);
+            if adopt_all || young {
+                if let Some(entry) = self.read_unindexed(&path, &metadata).await {
+                    stats.adopted_entries += 1;
+                    indexed.insert(name, entry);
+                    continue;
+                }
+            }
+            match fs::remove_file(&path).await {
+                Ok(()) => {
+                    stats.orphans_removed += 1;
+                    stats.orphan_bytes += metadata.len();
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(SliceError::CacheError(format!("Failed to delete orphan L2 file: {}", e)));
+                }
+            }
+        }
+        stats.missing_entries = listed.map_or(0, |listed| listed.entries.len() as u64);
+
+        *self.index.entries.lock().unwrap() = Some(indexed);
+        self.write_index(&mut log).await?;
+        *self.index.loaded.lock().unwrap() = Some(stats);
+        info!(
+            "Loaded L2 index: {} entries ({} bytes) recovered, {} adopted, {} missing, {} orphan files ({} bytes) removed",
+            stats.recovered_entries,
+            stats.recovered_bytes,
+            stats.adopted_entries,
+            stats.missing_entries,
+            stats.orphans_removed,
+            stats.orphan_bytes
+        );
+        Ok(stats)
+    }
+
+    /// Rebuild the index from a scan of the directory
+    ///
+    /// The index file is discarded and every readable entry file is adopted,
+    /// as by [`load_index`](Self::load_index) in a directory without an
+    /// index. Entries adopted this way have no key. Run it while no proxy
+    /// uses the directory.
+    pub async fn rebuild_index(&self) -> Result<IndexStats> {
+        self.writable()?;
+        match fs::remove_file(self.base_path.join(INDEX_FILE)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(SliceError::CacheError(format!("Failed to remove L2 index: {}", e)));
+            }
+        }
+        *self.index.log.lock().await = None;
+        self.load_index().await
+    }
+
+    /// Index entry for a file found without a record, or `None` if it
+    /// holds no readable entry
+    async fn read_unindexed(&self, path: &Path, metadata: &std::fs::Metadata) -> Option<IndexedEntry> {
+        let (expires_at_secs, size) = if self.dedup {
+            let (expires_at, hash) = Self::read_ref_at(path).await.ok()??;
+            let object = fs::metadata(self.object_path(&hash)).await.ok()?;
+            (unix_secs(expires_at), object.len().saturating_sub(8))
+        } else {
+            let mut file = fs::File::open(path).await.ok()?;
+            let mut timestamp_bytes = [0u8; 8];
+            file.read_exact(&mut timestamp_bytes).await.ok()?;
+            (u64::from_le_bytes(timestamp_bytes), metadata.len() - 8)
+        };
+        Some(IndexedEntry {
+            key: String::new(),
+            size,
+            stored_at_secs: metadata.modified().map(unix_secs).unwrap_or(0),
+            expires_at_secs,
+        })
+    }
+
+    /// Directory holding shared bodies in dedup mode
+    fn objects_dir(&self) -> PathBuf {
+        self.base_path.join("objects")
+    }
+
+    /// Path of the shared body with `hash`
+    fn object_path(&self, hash: &[u8; 32]) -> PathBuf {
+        let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+        self.objects_dir().join(&hex[..2]).join(hex)
+    }
+
+    /// Expiry and body hash of the dedup entry for `key`
+    async fn read_ref(&self, key: &str) -> Result<Option<(SystemTime, [u8; 32])>> {
+        Self::read_ref_at(&self.file_path(key)).await
+    }
+
+    /// Expiry and body hash of the dedup entry in the file at `path`
+    async fn read_ref_at(path: &Path) -> Result<Option<(SystemTime, [u8; 32])>> {
+        let data = match fs::read(path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(SliceError::CacheError(format!(
+                    "Failed to read L2 cache file {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+        if data.len() != 40 {
+            let _ = fs::remove_file(path).await;
+            return Ok(None);
+        }
+        let expires_at_secs = u64::from_le_bytes(data[0..8].try_into().unwrap());
+        let hash: [u8; 32] = data[8..40].try_into().unwrap();
+        Ok(Some((UNIX_EPOCH + Duration::from_secs(expires_at_secs), hash)))
+    }
+
+    /// Reference count of the shared body at `path`, if it exists
+    async fn read_refcount(path: &Path) -> Result<Option<u64>> {
+        let mut file = match fs::File::open(path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(SliceError::CacheError(format!("Failed to open cache object: {}", e)));
+            }
+        };
+        let mut count = [0u8; 8];
+        file.read_exact(&mut count).await.map_err(|e| {
+            SliceError::CacheError(format!("Failed to read cache object: {}", e))
+        })?;
+        Ok(Some(u64::from_le_bytes(count)))
+    }
+
+    /// Overwrite the reference count of the shared body at `path`
+    async fn write_refcount(&self, path: &Path, count: u64) -> Result<()> {
+        let mut file = fs::OpenOptions::new().write(true).open(path).await.map_err(|e| {
+            SliceError::CacheError(format!("Failed to open cache object: {}", e))
+        })?;
+        file.write_all(&count.to_le_bytes()).await.map_err(|e| {
+            SliceError::CacheError(format!("Failed to write cache object: {}", e))
+        })?;
+        self.finish_write(file, path, path, 8).await
+    }
+
+    /// Drop one reference to the body with `hash`, deleting it at zero
+    ///
+    /// Must be called with the stripe of `hash` held.
+    async fn release_object(&self, hash: &[u8; 32]) -> Result<()> {
+        let path = self.object_path(hash);
+        match Self::read_refcount(&path).await? {
+            Some(count) if count > 1 => self.write_refcount(&path, count - 1).await,
+            Some(_) => {
+                fs::remove_file(&path).await.map_err(|e| {
+                    SliceError::CacheError(format!("Failed to delete cache object: {}", e))
+                })?;
+                debug!("Deleted unreferenced L2 object: {}", path.display());
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Store `data` under `key` in dedup mode
+    async fn store_shared(&self, key: &str, data: Bytes, ttl: Duration) -> Result<()> {
+        let hash: [u8; 32] = *blake3::hash(&data).as_bytes();
+        let object = self.object_path(&hash);
+        let _key = self.ref_locks.lock_key(key).await;
+
+        // Same body again: only the expiry changes
+        let previous = self.read_ref(key).await?.map(|(_, old)| old);
+        let changed = previous != Some(hash);
+        let _objects = match previous {
+            Some(old) => self.ref_locks.lock_objects(&[hash, old]).await,
+            None => self.ref_locks.lock_objects(&[hash]).await,
+        };
+
+        // Reference the new body, then commit the entry, then let go of the
+        // old body, so a failure at any step leaves no reference behind
+        if changed {
+            match Self::read_refcount(&object).await? {
+                Some(count) => self.write_refcount(&object, count + 1).await?,
+                None => self.write_file(&object, &[&1u64.to_le_bytes(), &data]).await?,
+            }
+        }
+
+        let expires_at_secs = unix_secs(SystemTime::now() + ttl);
+        let committed = self
+            .write_file(&self.file_path(key), &[&expires_at_secs.to_le_bytes(), &hash])
+            .await;
+        if let Err(e) = committed {
+            if changed {
+                self.release_object(&hash).await?;
+            }
+            return Err(e);
+        }
+
+        if let (true, Some(old)) = (changed, previous) {
+            self.release_object(&old).await?;
+        }
+        let entry = IndexedEntry {
+            key: key.to_string(),
+            size: data.len() as u64,
+            stored_at_secs: unix_secs(SystemTime::now()),
+            expires_at_secs,
+        };
+        self.index_update(&self.file_path(key), Some(entry)).await;
+        debug!("Wrote to L2: {} ({} bytes, shared)", key, data.len());
+        Ok(())
+    }
+
+    /// Look up `key` in dedup mode
+    async fn lookup_shared(&self, key: &str) -> Result<Option<Bytes>> {
+        if self.unindexed(&self.file_path(key)) {
+            return Ok(None);
+        }
+        let Some((expires_at, hash)) = self.read_ref(key).await? else {
+            return Ok(None);
+        };
+        if expires_at <= SystemTime::now() {
+            if !self.read_only {
+                self.remove_shared(key).await?;
+            }
+            return Ok(None);
+        }
+        match fs::read(self.object_path(&hash)).await {
+            Ok(data) if data.len() >= 8 => Ok(Some(Bytes::from(data[8..].to_vec()))),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // The body is gone; drop the dangling entry
+                if !self.read_only {
+                    let _ = fs::remove_file(self.file_path(key)).await;
+                    self.index_update(&self.file_path(key), None).await;
+                }
+                Ok(None)
+            }
+            Err(e) => Err(SliceError::CacheError(format!("Failed to read cache object: {}", e))),
+        }
+    }
+
+    /// Remove `key` in dedup mode
+    async fn remove_shared(&self, key: &str) -> Result<bool> {
+        let _key = self.ref_locks.lock_key(key).await;
+        let Some((_, hash)) = self.read_ref(key).await? else {
+            return Ok(false);
+        };
+        let _objects = self.ref_locks.lock_objects(&[hash]).await;
+        fs::remove_file(self.file_path(key)).await.map_err(|e| {
+            SliceError::CacheError(format!("Failed to delete L2 cache file: {}", e))
+        })?;
+        self.index_update(&self.file_path(key), None).await;
+        self.release_object(&hash).await?;
+        debug!("Deleted from L2: {}", key);
+        Ok(true)
+    }
+
+    /// Metadata of the unexpired entry in the file at `path`
+    async fn entry_at(&self, path: &Path) -> Result<Option<BackendEntry>> {
+        if self.dedup {
+            return self.entry_shared(path).await;
+        }
+        let Ok(mut file) = fs::File::open(path).await else {
+            return Ok(None);
+        };
+        let read = async {
+            let metadata = file.metadata().await?;
+            let mut timestamp_bytes = [0u8; 8];
+            file.read_exact(&mut timestamp_bytes).await?;
+            Ok::<_, std::io::Error>((metadata, u64::from_le_bytes(timestamp_bytes)))
+        };
+        let Ok((metadata, expires_at_secs)) = read.await else {
+            return Ok(None);
+        };
+
+        let expires_at = UNIX_EPOCH + Duration::from_secs(expires_at_secs);
+        let Ok(ttl_remaining) = expires_at.duration_since(SystemTime::now()) else {
+            return Ok(None);
+        };
+        Ok(Some(BackendEntry {
+            size_bytes: metadata.len().saturating_sub(8) as usize,
+            stored_at_secs: metadata.modified().map(unix_secs).unwrap_or(0),
+            ttl_remaining,
+            offset: Some(8),
+        }))
+    }
+
+    /// Metadata of the dedup entry in the file at `path`
+    async fn entry_shared(&self, path: &Path) -> Result<Option<BackendEntry>> {
+        let Some((expires_at, hash)) = Self::read_ref_at(path).await? else {
+            return Ok(None);
+        };
+        let Ok(ttl_remaining) = expires_at.duration_since(SystemTime::now()) else {
+            return Ok(None);
+        };
+        let (Ok(entry), Ok(object)) = (
+            fs::metadata(path).await,
+            fs::metadata(self.object_path(&hash)).await,
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(BackendEntry {
+            size_bytes: object.len().saturating_sub(8) as usize,
+            stored_at_secs: entry.modified().map(unix_secs).unwrap_or(0),
+            ttl_remaining,
+            offset: Some(8),
+        }))
+    }
+
+    /// Logical and physical bytes of the bodies stored in dedup mode
+    ///
+    /// Walks the object directory, so this is meant for occasional
+    /// reporting rather than every request.
+    pub async fn dedup_stats(&self) -> Result<DedupStats> {
+        let mut stats = DedupStats::default();
+        for path in self.object_files().await? {
+            let Ok(metadata) = fs::metadata(&path).await else {
+                continue;
+            };
+            let size = metadata.len().saturating_sub(8);
+            let count = Self::read_refcount(&path).await.ok().flatten().unwrap_or(0);
+            stats.objects += 1;
+            stats.physical_bytes += size;
+            stats.logical_bytes += size * count;
+        }
+        Ok(stats)
+    }
+
+    /// Read the entries recorded in the index file, without loading it
+    ///
+    /// # Returns
+    /// `None` if the directory has no index, or `Err(SliceError::CacheError)`
+    /// if it is unreadable
+    pub async fn read_index(&self) -> Result<Option<Vec<IndexRecord>>> {
+        let data = match fs::read(self.base_path.join(INDEX_FILE)).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(SliceError::CacheError(format!("Failed to read L2 index: {}", e)));
+            }
+        };
+        let indexed = decode_index(&data)
+            .ok_or_else(|| SliceError::CacheError("L2 index has an unknown format".to_string()))?;
+        let mut records: Vec<IndexRecord> = indexed
+            .entries
+            .into_iter()
+            .map(|(file, entry)| IndexRecord {
+                file,
+                key: entry.key,
+                size_bytes: entry.size,
+                stored_at_secs: entry.stored_at_secs,
+                expires_at_secs: entry.expires_at_secs,
+            })
+            .collect();
+        records.sort_by(|a, b| a.key.cmp(&b.key).then_with(|| a.file.cmp(&b.file)));
+        Ok(Some(records))
+    }
+
+    /// Count the entries and the space used by the directory
+    ///
+    /// Walks the whole directory, so this is meant for offline inspection
+    /// and occasional reporting.
+    pub async fn disk_usage(&self) -> Result<DiskUsage> {
+        let mut usage = DiskUsage::default();
+        let mut pending = vec![self.base_path.clone()];
+        let walk = async {
+            while let Some(dir) = pending.pop() {
+                let mut entries = fs::read_dir(&dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    let metadata = entry.metadata().await?;
+                    if metadata.is_dir() {
+                        pending.push(entry.path());
+                        continue;
+                    }
+                    usage.file_bytes += metadata.len();
+                    usage.allocated_bytes += allocated_bytes(&metadata);
+                    if entry.file_name().to_string_lossy().contains(TEMP_MARKER) {
+                        usage.temp_files += 1;
+                    }
+                }
+            }
+            Ok(())
+        };
+        walk.await.map_err(|e: std::io::Error| {
+            SliceError::CacheError(format!("Failed to scan L2 cache directory: {}", e))
+        })?;
+
+        let now = SystemTime::now();
+        for path in self.entry_files().await? {
+            usage.entries += 1;
+            let expires_at = if self.dedup {
+                Self::read_ref_at(&path).await.ok().flatten().map(|(expires_at, _)| expires_at)
+            } else {
+                let mut timestamp_bytes = [0u8; 8];
+                let read = async { fs::File::open(&path).await?.read_exact(&mut timestamp_bytes).await };
+                read.await
+                    .ok()
+                    .map(|_| UNIX_EPOCH + Duration::from_secs(u64::from_le_bytes(timestamp_bytes)))
+            };
+            if expires_at.is_some_and(|expires_at| expires_at <= now) {
+                usage.expired_entries += 1;
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Paths of every shared body, leaving out temporary files
+    async fn object_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut pending = vec![self.objects_dir()];
+        let walk = async {
+            while let Some(dir) = pending.pop() {
+                let mut entries = match fs::read_dir(&dir).await {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e),
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    if entry.file_type().await?.is_dir() {
+                        pending.push(entry.path());
+                    } else if !entry.file_name().to_string_lossy().contains(TEMP_MARKER) {
+                        files.push(entry.path());
+                    }
+                }
+            }
+            Ok(())
+        };
+        walk.await.map_err(|e: std::io::Error| {
+            SliceError::CacheError(format!("Failed to read L2 object directory: {}", e))
+        })?;
+        Ok(files)
+    }
+
+    /// Number of discrepancies found by
+    /// [`check_consistency`](Self::check_consistency) so far
+    pub fn consistency_errors(&self) -> u64 {
+        self.consistency_errors.load(Ordering::Relaxed)
+    }
+
+    /// Check that the entries agree with each other and the shared bodies
+    ///
+    /// The read-only counterpart of [`recover`](Self::recover): entry files
+    /// too short to be read are reported, and in dedup mode the references
+    /// to each shared body are recounted from the entries and compared with
+    /// the bodies on disk and their stored counts. Nothing is fixed;
+    /// discrepancies are logged and added to
+    /// [`consistency_errors`](Self::consistency_errors). Walks the whole
+    /// directory and holds up dedup writes while it runs.
+    pub async fn check_consistency(&self) -> Result<ConsistencyReport> {
+        let _refs = self.ref_locks.lock_all().await;
+        let mut report = ConsistencyReport::default();
+        let mut refs: HashMap<[u8; 32], u64> = HashMap::new();
+
+        for path in self.entry_files().await? {
+            // Plain entries can be removed while the walk runs
+            let Ok(metadata) = fs::metadata(&path).await else {
+                continue;
+            };
+            report.entries += 1;
+            if !self.dedup {
+                if metadata.len() < 8 {
+                    report.corrupt_entries += 1;
+                }
+                continue;
+            }
+            match fs::read(&path).await {
+                Ok(data) if data.len() == 40 => {
+                    *refs.entry(data[8..40].try_into().unwrap()).or_default() += 1;
+                }
+                _ => report.corrupt_entries += 1,
+            }
+        }
+
+        if self.dedup {
+            for path in self.object_files().await? {
+                let hash = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(parse_hash);
+                let expected = hash.and_then(|hash| refs.remove(&hash)).unwrap_or(0);
+                if expected == 0 {
+                    report.unreferenced_objects += 1;
+                } else if Self::read_refcount(&path).await.ok().flatten() != Some(expected) {
+                    report.refcount_mismatches += 1;
+                }
+            }
+            report.dangling_entries = refs.values().sum();
+        }
+
+        if report.problems() > 0 {
+            warn!(
+                "L2 cache inconsistent: {} corrupt entries, {} dangling entries, {} unreferenced objects, {} wrong reference counts",
+                report.corrupt_entries,
+                report.dangling_entries,
+                report.unreferenced_objects,
+                report.refcount_mismatches
+            );
+            self.consistency_errors.fetch_add(report.problems(), Ordering::Relaxed);
+        }
+        Ok(report)
+    }
+
+    /// Delete the entry files [`check_consistency`](Self::check_consistency)
+    /// reports as corrupt
+    ///
+    /// Run [`recover`](Self::recover) and [`load_index`](Self::load_index)
+    /// afterwards to fix the reference counts and drop the index records.
+    ///
+    /// # Returns
+    /// The number of files removed
+    pub async fn remove_corrupt_entries(&self) -> Result<u64> {
+        self.writable()?;
+        let _refs = self.ref_locks.lock_all().await;
+        let mut removed = 0;
+        for path in self.entry_files().await? {
+            let Ok(metadata) = fs::metadata(&path).await else {
+                continue;
+            };
+            let corrupt = if self.dedup { metadata.len() != 40 } else { metadata.len() < 8 };
+            if !corrupt {
+                continue;
+            }
+            match fs::remove_file(&path).await {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(SliceError::CacheError(format!("Failed to delete corrupt L2 entry: {}", e)));
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Run [`check_consistency`](Self::check_consistency) every `interval`
+    /// in the background
+    ///
+    /// Must be called from within a Tokio runtime. The checks stop when the
+    /// returned handle is aborted.
+    pub fn spawn_consistency_checks(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = backend.check_consistency().await {
+                    warn!("L2 consistency check failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Check that the directory holds entries in this backend's layout
+    ///
+    /// The layout is read from the `.layout` file, or guessed from the
+    /// contents of directories written before it existed: an `objects/`
+    /// directory means dedup, any other entry means plain. A directory in
+    /// the other layout is refused with [`SliceError::ConfigError`], or
+    /// with [`with_layout_migration`](Self::with_layout_migration) every
+    /// entry is rewritten. Migration walks and rewrites the whole
+    /// directory, so run it at startup before serving traffic; if it is
+    /// interrupted, purge the directory.
+    ///
+    /// # Returns
+    /// The number of entries migrated
+    pub async fn verify_layout(&self) -> Result<usize> {
+        self.writable()?;
+        let wanted = self.layout();
+        let marker = self.base_path.join(LAYOUT_FILE);
+        let (found, recorded) = self.read_layout().await?;
+
+        let mut migrated = 0;
+        if let Some(found) = found.filter(|&found| found != wanted) {
+            if !self.migrate_layout {
+                return Err(SliceError::ConfigError(format!(
+                    "L2 cache directory {} uses the {} layout but the backend is configured for {}; purge it or enable layout migration",
+                    self.base_path.display(),
+                    found.as_str(),
+                    wanted.as_str()
+                )));
+            }
+            migrated = self.migrate().await?;
+            info!(
+                "Migrated {} L2 entries from the {} to the {} layout",
+                migrated,
+                found.as_str(),
+                wanted.as_str()
+            );
+        }
+        if !recorded || found != Some(wanted) {
+            self.write_file(&marker, &[wanted.as_str().as_bytes()]).await?;
+        }
+        Ok(migrated)
+    }
+
+    /// Check that the entries were stored under keys in format `version`
+    ///
+    /// The format is read from the `.key-version` file. Entries in a
+    /// directory recording another format, or none at all, were stored
+    /// under keys that lookups no longer build and would only take up space
+    /// until they expire, so they are purged and the directory is stamped
+    /// with `version`. Run it at startup before
+    /// [`load_index`](Self::load_index).
+    ///
+    /// # Returns
+    /// The number of entries purged
+    pub async fn verify_key_version(&self, version: u32) -> Result<usize> {
+        self.writable()?;
+        let path = self.base_path.join(KEY_VERSION_FILE);
+        let recorded = match fs::read_to_string(&path).await {
+            Ok(text) => text.trim().parse::<u32>().ok(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(SliceError::CacheError(format!(
+                    "Failed to read L2 key version file: {}",
+                    e
+                )));
+            }
+        };
+        if recorded == Some(version) {
+            return Ok(0);
+        }
+
+        let purged = self.purge_unindexed().await?;
+        if purged > 0 {
+            info!(
+                "Purged {} L2 entries stored under key format {}, expected {}",
+                purged,
+                recorded.map_or_else(|| "unrecorded".to_string(), |v| v.to_string()),
+                version
+            );
+        }
+        self.write_file(&path, &[version.to_string().as_bytes()]).await?;
+        Ok(purged)
+    }
+
+    /// Check that the entries are encoded in `format`
+    ///
+    /// The format is read from the `.entry-format` file; a directory
+    /// without one holds raw entries. Entries in another format would be
+    /// misread, so they are purged and the directory is stamped with
+    /// `format`. Run it at startup before [`load_index`](Self::load_index).
+    ///
+    /// # Returns
+    /// The number of entries purged
+    pub async fn verify_entry_format(&self, format: EntryFormat) -> Result<usize> {
+        self.writable()?;
+        let path = self.base_path.join(ENTRY_FORMAT_FILE);
+        let recorded = match fs::read_to_string(&path).await {
+            Ok(text) => Some(text.trim().to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(SliceError::CacheError(format!(
+                    "Failed to read L2 entry format file: {}",
+                    e
+                )));
+            }
+        };
+        let found = recorded.as_deref().unwrap_or(EntryFormat::Raw.as_str());
+        if found == format.as_str() {
+            if recorded.is_none() {
+                self.write_file(&path, &[format.as_str().as_bytes()]).await?;
+            }
+            return Ok(0);
+        }
+
+        let purged = self.purge_unindexed().await?;
+        if purged > 0 {
+            info!(
+                "Purged {} L2 entries stored in the {} entry format, expected {}",
+                purged,
+                found,
+                format.as_str()
+            );
+        }
+        self.write_file(&path, &[format.as_str().as_bytes()]).await?;
+        Ok(purged)
+    }
+
+    /// Purge every entry, along with the index file if it is not loaded
+    /// yet
+    async fn purge_unindexed(&self) -> Result<usize> {
+        let purged = self.purge_all().await?;
+        // Before the index is loaded the purge leaves its file alone, and
+        // it would list the purged entries
+        if self.index.entries.lock().unwrap().is_none() {
+            match fs::remove_file(self.base_path.join(INDEX_FILE)).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(SliceError::CacheError(format!(
+                        "Failed to remove L2 index: {}",
+                        e
+                    )));
+                }
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Layout of the entries in the directory, whatever this backend is
+    /// configured for
+    ///
+    /// # Returns
+    /// `None` if the directory records no layout and holds no entries
+    pub async fn stored_layout(&self) -> Result<Option<FileLayout>> {
+        Ok(self.read_layout().await?.0)
+    }
+
+    /// Layout from the `.layout` file, or guessed from the contents, and
+    /// whether the file recorded it
+    async fn read_layout(&self) -> Result<(Option<FileLayout>, bool)> {
+        match fs::read_to_string(self.base_path.join(LAYOUT_FILE)).await {
+            Ok(name) => match name.trim() {
+                "plain" => Ok((Some(FileLayout::Plain), true)),
+                "dedup" => Ok((Some(FileLayout::Dedup), true)),
+                other => Err(SliceError::ConfigError(format!(
+                    "L2 cache directory {} has unknown layout '{}'",
+                    self.base_path.display(),
+                    other
+                ))),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok((self.detect_layout().await?, false))
+            }
+            Err(e) => Err(SliceError::CacheError(format!("Failed to read L2 layout file: {}", e))),
+        }
+    }
+
+    /// Guess the layout of a directory without a `.layout` file
+    ///
+    /// # Returns
+    /// `None` if the directory holds no entries
+    async fn detect_layout(&self) -> Result<Option<FileLayout>> {
+        if fs::metadata(self.objects_dir()).await.is_ok() {
+            return Ok(Some(FileLayout::Dedup));
+        }
+        let entries = self.entry_files().await?;
+        Ok((!entries.is_empty()).then_some(FileLayout::Plain))
+    }
+
+    /// Paths of every entry file, leaving out shared bodies and bookkeeping
+    async fn entry_files(&self) -> Result<Vec<PathBuf>> {
+        let objects_dir = self.objects_dir();
+        let mut files = Vec::new();
+        let mut pending = vec![self.base_path.clone()];
+        let walk = async {
+            while let Some(dir) = pending.pop() {
+                let mut entries = fs::read_dir(&dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    let path = entry.path();
+                    if entry.file_type().await?.is_dir() {
+                        if path != objects_dir {
+                            pending.push(path);
+                        }
+                        continue;
+                    }
+                    // Skip `.layout` and `.probe`, which keys never map to
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    let bookkeeping = dir == self.base_path && name.starts_with('.');
+                    if !bookkeeping && !name.contains(TEMP_MARKER) {
+                        files.push(path);
+                    }
+                }
+            }
+            Ok(())
+        };
+        walk.await.map_err(|e: std::io::Error| {
+            SliceError::CacheError(format!("Failed to scan L2 cache directory: {}", e))
+        })?;
+        Ok(files)
+    }
+
+    /// Rewrite every entry from the other layout into this backend's
+    async fn migrate(&self) -> Result<usize> {
+        let _refs = self.ref_locks.lock_all().await;
+        let read_error =
+            |e: std::io::Error| SliceError::CacheError(format!("Failed to read L2 cache file: {}", e));
+        let mut migrated = 0;
+        for path in self.entry_files().await? {
+            let data = fs::read(&path).await.map_err(read_error)?;
+            if self.dedup {
+                // Plain to dedup: move the data into a shared body
+                if data.len() < 8 {
+                    let _ = fs::remove_file(&path).await;
+                    continue;
+                }
+                let hash: [u8; 32] = *blake3::hash(&data[8..]).as_bytes();
+                let object = self.object_path(&hash);
+                match Self::read_refcount(&object).await? {
+                    Some(count) => self.write_refcount(&object, count + 1).await?,
+                    None => self.write_file(&object, &[&1u64.to_le_bytes(), &data[8..]]).await?,
+                }
+                self.write_file(&path, &[&data[..8], &hash]).await?;
+            } else {
+                // Dedup to plain: copy the shared body into the entry
+                let body = match data.get(8..40).map(|hash| self.object_path(hash.try_into().unwrap())) {
+                    Some(object) => fs::read(&object).await.ok().filter(|body| body.len() >= 8),
+                    None => None,
+                };
+                let Some(body) = body else {
+                    let _ = fs::remove_file(&path).await;
+                    continue;
+                };
+                self.write_file(&path, &[&data[..8], &body[8..]]).await?;
+            }
+            migrated += 1;
+        }
+        if !self.dedup {
+            match fs::remove_dir_all(self.objects_dir()).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(SliceError::CacheError(format!(
+                        "Failed to remove L2 object directory: {}",
+                        e
+                    )));
+                }
+            }
+        }
+        Ok(migrated)
+    }
+
+    /// Clean up after writes a crash interrupted
+    ///
+    /// Removes leftover temporary files and, in dedup mode, recounts the
+    /// references to each shared body from the entries: bodies no entry
+    /// points to are deleted and wrong counts are rewritten. Walks the whole
+    /// directory, so run it at startup before serving traffic.
+    pub async fn recover(&self) -> Result<RecoveryStats> {
+        self.writable()?;
+        let _refs = self.ref_locks.lock_all().await;
+        let mut stats = RecoveryStats::default();
+        let mut refs: HashMap<[u8; 32], u64> = HashMap::new();
+        let mut objects = Vec::new();
+
+        let objects_dir = self.objects_dir();
+        let mut pending = vec![self.base_path.clone()];
+        let walk = async {
+            while let Some(dir) = pending.pop() {
+                let mut entries = fs::read_dir(&dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    let path = entry.path();
+                    if entry.file_type().await?.is_dir() {
+                        pending.push(path);
+                        continue;
+                    }
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if name.contains(TEMP_MARKER) {
+                        fs::remove_file(&path).await?;
+                        stats.temp_files_removed += 1;
+                    } else if !self.dedup || (dir == self.base_path && name.starts_with('.')) {
+                        continue;
+                    } else if path.starts_with(&objects_dir) {
+                        objects.push(path);
+                    } else if entry.metadata().await?.len() == 40 {
+                        let data = fs::read(&path).await?;
+                        let hash: [u8; 32] = data[8..40].try_into().unwrap();
+                        *refs.entry(hash).or_default() += 1;
+                    }
+                }
+            }
+            Ok(())
+        };
+        walk.await.map_err(|e: std::io::Error| {
+            SliceError::CacheError(format!("Failed to scan L2 cache directory: {}", e))
+        })?;
+
+        for path in objects {
+            let hash = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parse_hash);
+            let expected = hash.and_then(|hash| refs.get(&hash)).copied().unwrap_or(0);
+            if expected == 0 {
+                fs::remove_file(&path).await.map_err(|e| {
+                    SliceError::CacheError(format!("Failed to delete cache object: {}", e))
+                })?;
+                stats.objects_reclaimed += 1;
+            } else if Self::read_refcount(&path).await.ok().flatten() != Some(expected) {
+                self.write_refcount(&path, expected).await?;
+                stats.refcounts_fixed += 1;
+            }
+        }
+
+        if stats != RecoveryStats::default() {
+            info!(
+                "Recovered L2 cache: removed {} temporary files, reclaimed {} objects, fixed {} reference counts",
+                stats.temp_files_removed, stats.objects_reclaimed, stats.refcounts_fixed
+            );
+        }
+        Ok(stats)
+    }
+}
+
+#[async_trait]
+impl CacheBackend for FileBackend {
+    async fn store(&self, key: &str, data: Bytes, ttl: Duration) -> Result<()> {
+        self.writable()?;
+        if self.dedup {
+            return self.store_shared(key, data, ttl).await;
+        }
+
+        // Write timestamp + data
+        let now = SystemTime::now();
+        let expires_at_secs = unix_secs(now + ttl);
+        let file_path = self.file_path(key);
+        self.write_file(&file_path, &[&expires_at_secs.to_le_bytes(), &data])
+            .await?;
+        let entry = IndexedEntry {
+            key: key.to_string(),
+            size: data.len() as u64,
+            stored_at_secs: unix_secs(now),
+            expires_at_secs,
+        };
+        self.index_update(&file_path, Some(entry)).await;
+
+        debug!("Wrote to L2: {} ({} bytes)", key, data.len());
+        Ok(())
+    }
+
+    async fn lookup(&self, key: &str) -> Result<Option<Bytes>> {
+        if self.dedup {
+            return self.lookup_shared(key).await;
+        }
+        let file_path = self.file_path(key);
+        if self.unindexed(&file_path) {
+            return Ok(None);
+        }
+
+        match fs::read(&file_path).await {
+            Ok(data) => {
+                // Check if file is expired (first 8 bytes = timestamp)
+                if data.len() < 8 {
+                    if !self.read_only {
+                        let _ = fs::remove_file(&file_path).await;
+                        self.index_update(&file_path, None).await;
+                    }
+                    return Ok(None);
+                }
+
+                let timestamp_bytes: [u8; 8] = data[0..8].try_into().unwrap();
+                let expires_at_secs = u64::from_le_bytes(timestamp_bytes);
+                let expires_at = UNIX_EPOCH + Duration::from_secs(expires_at_secs);
+
+                if expires_at <= SystemTime::now() {
+                    // Expired, delete file
+                    if !self.read_only {
+                        let _ = fs::remove_file(&file_path).await;
+                        self.index_update(&file_path, None).await;
+                    }
+                    return Ok(None);
+                }
+
+                // Return data (skip first 8 bytes)
+                Ok(Some(Bytes::from(data[8..].to_vec())))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SliceError::CacheError(format!(
+                "Failed to read L2 cache file {}: {}",
+                file_path.display(),
+                e
+            ))),
+        }
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool> {
+        self.writable()?;
+        if self.dedup {
+            return self.remove_shared(key).await;
+        }
+        let file_path = self.file_path(key);
+        match fs::remove_file(&file_path).await {
+            Ok(()) => {
+                self.index_update(&file_path, None).await;
+                debug!("Deleted from L2: {}", key);
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(SliceError::CacheError(format!(
+                "Failed to delete L2 cache file {}: {}",
+                file_path.display(),
+                e
+            ))),
+        }
+    }
+
+    async fn purge_all(&self) -> Result<usize> {
+        self.writable()?;
+        // Shared bodies are not entries of their own
+        let _refs = self.ref_locks.lock_all().await;
+        match fs::remove_dir_all(self.objects_dir()).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(SliceError::CacheError(format!(
+                    "Failed to purge L2 object directory: {}",
+                    e
+                )));
+            }
+        }
+        let removed = Self::remove_files(&self.base_path).await.map_err(|e| {
+            SliceError::CacheError(format!("Failed to purge L2 cache directory: {}", e))
+        })?;
+        let mut log = self.index.log.lock().await;
+        if let Some(indexed) = self.index.entries.lock().unwrap().as_mut() {
+            *indexed = IndexedEntries::default();
+        }
+        self.write_index(&mut log).await?;
+        Ok(removed)
+    }
+
+    fn stats(&self) -> CacheBackendStats {
+        // Without the index, counting entries would mean walking the whole
+        // directory tree
+        let entries = self.index.entries.lock().unwrap();
+        CacheBackendStats {
+            name: "file".to_string(),
+            entries: entries.as_ref().map(|indexed| indexed.entries.len() as u64),
+            bytes: entries.as_ref().map(|indexed| indexed.bytes),
+            index: *self.index.loaded.lock().unwrap(),
+        }
+    }
+
+    async fn health(&self) -> Result<()> {
+        self.writable()?;
+        let probe_path = self.base_path.join(".probe");
+        async {
+            fs::create_dir_all(&self.base_path).await?;
+            fs::write(&probe_path, b"probe").await?;
+            fs::remove_file(&probe_path).await
+        }
+        .await
+        .map_err(|e| SliceError::CacheError(format!("L2 disk probe failed: {}", e)))
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.sync().await?;
+        if let Some((file, _)) = self.index.log.lock().await.as_ref() {
+            file.sync_data().await.map_err(|e| {
+                SliceError::CacheError(format!("Failed to sync L2 index: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn entry(&self, key: &str) -> Result<Option<BackendEntry>> {
+        self.entry_at(&self.file_path(key)).await
+    }
+
+    /// Walks the directory; entries are named by their file's path
+    /// relative to the base directory
+    async fn visit_entries(&self, visit: &mut (dyn FnMut(String, BackendEntry) + Send)) -> Result<bool> {
+        for path in self.entry_files().await? {
+            // Entries can expire or be removed while the walk runs
+            let Ok(Some(entry)) = self.entry_at(&path).await else {
+                continue;
+            };
+            let name = path.strip_prefix(&self.base_path).unwrap_or(&path);
+            visit(name.to_string_lossy().into_owned(), entry);
+        }
+        Ok(true)
+    }
+}
+
+/// Append the index record for `name` to `out`: a store if it holds
+/// `entry`, a removal otherwise
+///
+/// A record is `+` or `-`, the name (2-byte length, then the bytes) and,
+/// for stores, the size, store time and expiry (8 bytes each) and the key
+/// (4-byte length, then the bytes). Numbers are little-endian.
+fn encode_index_record(out: &mut Vec<u8>, name: &str, entry: Option<&IndexedEntry>) {
+    out.push(if entry.is_some() { b'+' } else { b'-' });
+    out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    out.extend_from_slice(name.as_bytes());
+    if let Some(entry) = entry {
+        out.extend_from_slice(&entry.size.to_le_bytes());
+        out.extend_from_slice(&entry.stored_at_secs.to_le_bytes());
+        out.extend_from_slice(&entry.expires_at_secs.to_le_bytes());
+        out.extend_from_slice(&(entry.key.len() as u32).to_le_bytes());
+        out.extend_from_slice(entry.key.as_bytes());
+    }
+}
+
+/// Replay the records of an index file
+///
+/// Replay stops at the first incomplete record, as a crash in the middle
+/// of an append leaves behind.
+///
+/// # Returns
+/// `None` if the data is not an index file
+fn decode_index(data: &[u8]) -> Option<IndexedEntries> {
+    let mut rest = data.strip_prefix(INDEX_MAGIC.as_slice())?;
+    let mut indexed = IndexedEntries::default();
+    while let Some((name, entry)) = decode_index_record(&mut rest) {
+        match entry {
+            Some(entry) => indexed.insert(name, entry),
+            None => indexed.remove(&name),
+        }
+    }
+    Some(indexed)
+}
+
+/// Read one index record off the front of `data`
+fn decode_index_record(data: &mut &[u8]) -> Option<(String, Option<IndexedEntry>)> {
+    fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        if data.len() < len {
+            return None;
+        }
+        let (head, tail) = data.split_at(len);
+        *data = tail;
+        Some(head)
+    }
+    fn take_u64(data: &mut &[u8]) -> Option<u64> {
+        take(data, 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    let op = take(data, 1)?[0];
+    let name_len = u16::from_le_bytes(take(data, 2)?.try_into().unwrap()) as usize;
+    let name = String::from_utf8(take(data, name_len)?.to_vec()).ok()?;
+    let entry = match op {
+        b'+' => {
+            let size = take_u64(data)?;
+            let stored_at_secs = take_u64(data)?;
+            let expires_at_secs = take_u64(data)?;
+            let key_len = u32::from_le_bytes(take(data, 4)?.try_into().unwrap()) as usize;
+            let key = String::from_utf8(take(data, key_len)?.to_vec()).ok()?;
+            Some(IndexedEntry {
+                key,
+                size,
+                stored_at_secs,
+                expires_at_secs,
+            })
+        }
+        b'-' => None,
+        _ => return None,
+    };
+    Some((name, entry))
+}
+
+/// Disk space allocated to a file
+#[cfg(unix)]
+fn allocated_bytes(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+/// Disk space allocated to a file
+#[cfg(not(unix))]
+fn allocated_bytes(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Hash named by a shared body's 64-digit hex file name
+fn parse_hash(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(hash)
+}
+
+/// Seconds since the Unix epoch
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_backend_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+
+        let data = Bytes::from(vec![7u8; 100]);
+        backend.store("http://a/file:0:99", data.clone(), Duration::from_secs(60)).await.unwrap();
+        assert_eq!(backend.lookup("http://a/file:0:99").await.unwrap(), Some(data));
+
+        let entry = backend.entry("http://a/file:0:99").await.unwrap().unwrap();
+        assert_eq!(entry.size_bytes, 100);
+        assert_eq!(entry.offset, Some(8));
+
+        assert!(backend.remove("http://a/file:0:99").await.unwrap());
+        assert!(!backend.remove("http://a/file:0:99").await.unwrap());
+        assert_eq!(backend.lookup("http://a/file:0:99").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_file_backend_expired_entry() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+
+        backend.store("key", Bytes::from_static(b"data"), Duration::ZERO).await.unwrap();
+        assert_eq!(backend.lookup("key").await.unwrap(), None);
+        assert_eq!(backend.entry("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_file_backend_purge_all() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+
+        for i in 0..5 {
+            let key = format!("http://a/file:{}:{}", i * 10, i * 10 + 9);
+            backend.store(&key, Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        }
+        assert_eq!(backend.purge_all().await.unwrap(), 5);
+        assert_eq!(backend.lookup("http://a/file:0:9").await.unwrap(), None);
+        assert!(backend.health().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_per_write_fsync_survives_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        assert_eq!(backend.fsync_policy, FsyncPolicy::PerWrite);
+
+        let data = Bytes::from(vec![3u8; 4096]);
+        backend.store("key", data.clone(), Duration::from_secs(60)).await.unwrap();
+        // The entry is on stable storage once the write returns
+        assert_eq!(backend.fsync_count(), 1);
+
+        // Simulate a crash: nothing else runs before the backend goes away
+        drop(backend);
+        let restarted = FileBackend::new(temp_dir.path()).await.unwrap();
+        assert_eq!(restarted.lookup("key").await.unwrap(), Some(data));
+    }
+
+    #[tokio::test]
+    async fn test_fsync_never_skips_syncs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let never = FileBackend::new(temp_dir.path().join("never"))
+            .await
+            .unwrap()
+            .with_fsync_policy(FsyncPolicy::Never);
+        let per_write = FileBackend::new(temp_dir.path().join("per_write")).await.unwrap();
+
+        for i in 0..10 {
+            let key = format!("key{}", i);
+            never.store(&key, Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+            per_write.store(&key, Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        }
+        assert_eq!(never.fsync_count(), 0);
+        assert_eq!(per_write.fsync_count(), 10);
+        assert_eq!(never.lookup("key9").await.unwrap(), Some(Bytes::from_static(b"data")));
+    }
+
+    #[tokio::test]
+    async fn test_fsync_interval_batches_syncs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path())
+            .await
+            .unwrap()
+            .with_fsync_policy(FsyncPolicy::Interval(Duration::from_millis(100)));
+
+        for i in 0..3 {
+            let key = format!("key{}", i);
+            backend.store(&key, Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        }
+        assert_eq!(backend.fsync_count(), 0);
+
+        // Idle writes are synced once the interval passes
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(backend.fsync_count(), 3);
+        backend.store("key3", Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        assert_eq!(backend.fsync_count(), 3);
+
+        backend.store("key4", Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        assert!(backend.remove("key4").await.unwrap());
+        backend.store("key5", Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        backend.sync().await.unwrap();
+        assert_eq!(backend.fsync_count(), 5);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_interval_syncs_never_overlap() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path())
+            .await
+            .unwrap()
+            .with_fsync_policy(FsyncPolicy::Interval(Duration::from_millis(1)));
+
+        let tasks: Vec<_> = (0..2000)
+            .map(|i| {
+                let backend = backend.clone();
+                tokio::spawn(async move {
+                    let key = format!("key{}", i % 200);
+                    backend.store(&key, Bytes::from_static(b"data"), Duration::from_secs(60)).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        backend.sync().await.unwrap();
+
+        // Syncs due while another ran were folded into later ones
+        let (active, peak) = &backend.sync_state.active;
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert_eq!(active.load(Ordering::SeqCst), 0);
+        assert!(backend.fsync_count() >= 200);
+        assert!(backend.sync_state.pending.lock().unwrap().files.is_empty());
+
+        // The timer stops once writes stop
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!backend.sync_state.pending.lock().unwrap().timer_running);
+    }
+
+    /// A backend syncing every minute unless a threshold is reached
+    async fn threshold_backend(
+        dir: &Path,
+        max_files: Option<usize>,
+        max_bytes: Option<u64>,
+    ) -> FileBackend {
+        FileBackend::new(dir)
+            .await
+            .unwrap()
+            .with_fsync_policy(FsyncPolicy::Interval(Duration::from_secs(60)))
+            .with_sync_thresholds(max_files, max_bytes)
+    }
+
+    #[tokio::test]
+    async fn test_sync_on_file_count() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = threshold_backend(temp_dir.path(), Some(3), None).await;
+        let ttl = Duration::from_secs(60);
+
+        backend.store("key0", Bytes::from_static(b"data"), ttl).await.unwrap();
+        backend.store("key1", Bytes::from_static(b"data"), ttl).await.unwrap();
+        let stats = backend.sync_stats();
+        assert_eq!((stats.pending_files, stats.pending_bytes), (2, 24));
+        assert_eq!(stats.syncs, 0);
+
+        backend.store("key2", Bytes::from_static(b"data"), ttl).await.unwrap();
+        let stats = backend.sync_stats();
+        assert_eq!((stats.pending_files, stats.pending_bytes), (0, 0));
+        assert_eq!((stats.syncs, stats.fsyncs), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_sync_on_bytes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = threshold_backend(temp_dir.path(), None, Some(3000)).await;
+        let data = Bytes::from(vec![1u8; 1000]);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("key0", data.clone(), ttl).await.unwrap();
+        backend.store("key1", data.clone(), ttl).await.unwrap();
+        assert_eq!(backend.sync_stats().pending_bytes, 2016);
+        assert_eq!(backend.fsync_count(), 0);
+
+        // Expiry header included, the third entry crosses the limit
+        backend.store("key2", data, ttl).await.unwrap();
+        let stats = backend.sync_stats();
+        assert_eq!((stats.pending_files, stats.syncs, stats.fsyncs), (0, 1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_sync_on_timer_below_thresholds() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path())
+            .await
+            .unwrap()
+            .with_fsync_policy(FsyncPolicy::Interval(Duration::from_millis(100)))
+            .with_sync_thresholds(Some(100), Some(1 << 20));
+
+        backend.store("key0", Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        assert_eq!(backend.sync_stats().pending_files, 1);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let stats = backend.sync_stats();
+        assert_eq!((stats.pending_files, stats.pending_bytes), (0, 0));
+        assert_eq!((stats.syncs, stats.fsyncs), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_dedup_stores_identical_bodies_once() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let data = Bytes::from(vec![9u8; 4096]);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("http://mirror-a/os.img:0:4095", data.clone(), ttl).await.unwrap();
+        backend.store("http://mirror-b/os.img:0:4095", data.clone(), ttl).await.unwrap();
+        backend.store("http://mirror-b/os.img:0:4095", data.clone(), ttl).await.unwrap();
+
+        let stats = backend.dedup_stats().await.unwrap();
+        assert_eq!(stats.objects, 1);
+        assert_eq!(stats.physical_bytes, 4096);
+        assert_eq!(stats.logical_bytes, 8192);
+        let entry = backend.entry("http://mirror-a/os.img:0:4095").await.unwrap().unwrap();
+        assert_eq!(entry.size_bytes, 4096);
+
+        // The body outlives the first key and goes with the last
+        assert!(backend.remove("http://mirror-a/os.img:0:4095").await.unwrap());
+        assert_eq!(backend.lookup("http://mirror-b/os.img:0:4095").await.unwrap(), Some(data));
+        assert!(backend.remove("http://mirror-b/os.img:0:4095").await.unwrap());
+        assert_eq!(backend.dedup_stats().await.unwrap(), DedupStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_dedup_overwrite_releases_old_body() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("key", Bytes::from_static(b"old"), ttl).await.unwrap();
+        backend.store("key", Bytes::from_static(b"new"), ttl).await.unwrap();
+        assert_eq!(backend.lookup("key").await.unwrap(), Some(Bytes::from_static(b"new")));
+        assert_eq!(backend.dedup_stats().await.unwrap().objects, 1);
+
+        // Expired entries release their body on lookup
+        backend.store("gone", Bytes::from_static(b"new"), Duration::ZERO).await.unwrap();
+        assert_eq!(backend.lookup("gone").await.unwrap(), None);
+        assert_eq!(backend.dedup_stats().await.unwrap().logical_bytes, 3);
+
+        backend.store("other", Bytes::from_static(b"data"), ttl).await.unwrap();
+        assert_eq!(backend.purge_all().await.unwrap(), 2);
+        assert_eq!(backend.dedup_stats().await.unwrap(), DedupStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_lock_shards_let_distinct_keys_proceed() {
+        let ttl = Duration::from_secs(60);
+        for (shards, blocked) in [(1, true), (16, false)] {
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            let backend = FileBackend::new(temp_dir.path())
+                .await
+                .unwrap()
+                .with_dedup(true)
+                .with_lock_shards(shards);
+            let locks = backend.ref_locks.clone();
+            let other = (0..)
+                .map(|i| format!("key{}", i))
+                .find(|key| shards == 1 || locks.key_shard(key) != locks.key_shard("held"))
+                .unwrap();
+
+            // A store stuck in another key's stripe only waits when there
+            // is a single stripe
+            let held = locks.lock_key("held").await;
+            let store = backend.store(&other, Bytes::from_static(b"data"), ttl);
+            let stored = tokio::time::timeout(Duration::from_millis(200), store).await;
+            assert_eq!(stored.is_err(), blocked);
+            drop(held);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_dedup_updates_stay_consistent() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let ttl = Duration::from_secs(60);
+
+        // Sixty-four keys over eight bodies, each stored, overwritten and
+        // half of them removed at once
+        let mut tasks = Vec::new();
+        for i in 0..64u8 {
+            let backend = backend.clone();
+            tasks.push(tokio::spawn(async move {
+                let key = format!("key{}", i);
+                backend.store(&key, Bytes::from(vec![i % 8; 256]), ttl).await.unwrap();
+                backend.store(&key, Bytes::from(vec![(i + 1) % 8; 256]), ttl).await.unwrap();
+                if i % 2 == 0 {
+                    assert!(backend.remove(&key).await.unwrap());
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let report = backend.check_consistency().await.unwrap();
+        assert_eq!(report.problems(), 0);
+        assert_eq!(report.entries, 32);
+        let stats = backend.dedup_stats().await.unwrap();
+        assert_eq!(stats.objects, 4);
+        assert_eq!(stats.logical_bytes, 32 * 256);
+    }
+
+    /// Make the entry file for `key` impossible to replace
+    async fn block_entry(backend: &FileBackend, key: &str) {
+        let path = backend.file_path(key);
+        fs::create_dir_all(&path).await.unwrap();
+        fs::write(path.join("occupied"), b"x").await.unwrap();
+    }
+
+    /// Temporary files anywhere under `dir`
+    fn temp_files(dir: &Path) -> usize {
+        let mut count = 0;
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    pending.push(entry.path());
+                } else if entry.file_name().to_string_lossy().contains(TEMP_MARKER) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    #[tokio::test]
+    async fn test_failed_store_leaks_nothing() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("kept", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        let baseline = backend.dedup_stats().await.unwrap();
+
+        // The body is referenced before the entry write fails
+        block_entry(&backend, "broken").await;
+        assert!(backend.store("broken", Bytes::from_static(b"fresh"), ttl).await.is_err());
+        assert!(backend.store("broken", Bytes::from_static(b"shared"), ttl).await.is_err());
+        assert_eq!(backend.dedup_stats().await.unwrap(), baseline);
+        assert_eq!(temp_files(temp_dir.path()), 0);
+
+        // Same for a plain backend: the failed write leaves no file behind
+        let plain = FileBackend::new(temp_dir.path().join("plain")).await.unwrap();
+        block_entry(&plain, "broken").await;
+        assert!(plain.store("broken", Bytes::from_static(b"data"), ttl).await.is_err());
+        assert_eq!(temp_files(temp_dir.path()), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_overwrite_keeps_old_body() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("key", Bytes::from_static(b"old"), ttl).await.unwrap();
+        let entry = backend.file_path("key");
+        fs::rename(&entry, temp_dir.path().join("saved")).await.unwrap();
+        block_entry(&backend, "key").await;
+        assert!(backend.store("key", Bytes::from_static(b"new"), ttl).await.is_err());
+
+        // The old entry still has its body
+        fs::remove_dir_all(&entry).await.unwrap();
+        fs::rename(temp_dir.path().join("saved"), &entry).await.unwrap();
+        assert_eq!(backend.lookup("key").await.unwrap(), Some(Bytes::from_static(b"old")));
+        assert_eq!(backend.dedup_stats().await.unwrap().objects, 1);
+    }
+
+    #[tokio::test]
+    async fn test_recover_reclaims_unreferenced_objects() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("a", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        backend.store("b", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        backend.store("c", Bytes::from_static(b"orphan"), ttl).await.unwrap();
+
+        // Crash leftovers: a half-written file, an entry lost after its body
+        // was referenced, and a reference count bumped for an entry that
+        // never got written
+        fs::write(temp_dir.path().join(format!("x{}0", TEMP_MARKER)), b"partial")
+            .await
+            .unwrap();
+        fs::remove_file(backend.file_path("b")).await.unwrap();
+        fs::remove_file(backend.file_path("c")).await.unwrap();
+
+        let stats = backend.recover().await.unwrap();
+        assert_eq!(
+            stats,
+            RecoveryStats {
+                temp_files_removed: 1,
+                objects_reclaimed: 1,
+                refcounts_fixed: 1,
+            }
+        );
+        assert_eq!(
+            backend.dedup_stats().await.unwrap(),
+            DedupStats {
+                objects: 1,
+                logical_bytes: 6,
+                physical_bytes: 6,
+            }
+        );
+        assert_eq!(backend.recover().await.unwrap(), RecoveryStats::default());
+
+        // The corrected count goes to zero with the last entry
+        assert!(backend.remove("a").await.unwrap());
+        assert_eq!(backend.dedup_stats().await.unwrap(), DedupStats::default());
+    }
+
+    /// Run stores, overwrites, removals and expiries, checking after each
+    async fn assert_stays_consistent(backend: &FileBackend) {
+        let ttl = Duration::from_secs(60);
+        let clean = |report: ConsistencyReport| report.problems() == 0;
+
+        for i in 0..8 {
+            let body = Bytes::from(vec![(i % 3) as u8; 512]);
+            backend.store(&format!("key{}", i), body, ttl).await.unwrap();
+        }
+        assert!(clean(backend.check_consistency().await.unwrap()));
+
+        backend.store("key0", Bytes::from_static(b"new"), ttl).await.unwrap();
+        backend.store("key1", Bytes::from(vec![1u8; 512]), ttl).await.unwrap();
+        assert!(clean(backend.check_consistency().await.unwrap()));
+
+        assert!(backend.remove("key2").await.unwrap());
+        assert!(backend.remove("key5").await.unwrap());
+        assert!(clean(backend.check_consistency().await.unwrap()));
+
+        backend.store("short", Bytes::from(vec![1u8; 512]), Duration::ZERO).await.unwrap();
+        assert_eq!(backend.lookup("short").await.unwrap(), None);
+        let report = backend.check_consistency().await.unwrap();
+        assert!(clean(report));
+        assert_eq!(report.entries, 6);
+
+        backend.purge_all().await.unwrap();
+        assert_eq!(backend.check_consistency().await.unwrap(), ConsistencyReport::default());
+        assert_eq!(backend.consistency_errors(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_plain_layout_stays_consistent() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        assert_stays_consistent(&backend).await;
+    }
+
+    #[tokio::test]
+    async fn test_dedup_layout_stays_consistent() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        assert_stays_consistent(&backend).await;
+    }
+
+    #[tokio::test]
+    async fn test_consistency_check_reports_without_fixing() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("a", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        backend.store("b", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        backend.store("c", Bytes::from_static(b"orphan"), ttl).await.unwrap();
+        backend.store("d", Bytes::from_static(b"lost"), ttl).await.unwrap();
+        backend.store("e", Bytes::from_static(b"torn"), ttl).await.unwrap();
+
+        fs::remove_file(backend.file_path("b")).await.unwrap();
+        fs::remove_file(backend.file_path("c")).await.unwrap();
+        let lost: [u8; 32] = *blake3::hash(b"lost").as_bytes();
+        fs::remove_file(backend.object_path(&lost)).await.unwrap();
+        fs::write(backend.file_path("e"), b"torn").await.unwrap();
+
+        let expected = ConsistencyReport {
+            entries: 3,
+            corrupt_entries: 1,
+            dangling_entries: 1,
+            unreferenced_objects: 2,
+            refcount_mismatches: 1,
+        };
+        assert_eq!(backend.check_consistency().await.unwrap(), expected);
+        assert_eq!(backend.check_consistency().await.unwrap(), expected);
+        assert_eq!(backend.consistency_errors(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_periodic_consistency_checks() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        backend.store("a", Bytes::from_static(b"data"), Duration::from_secs(60)).await.unwrap();
+        fs::write(backend.file_path("a"), b"torn").await.unwrap();
+
+        let checks = backend.spawn_consistency_checks(Duration::from_millis(20));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        checks.abort();
+        assert!(backend.consistency_errors() >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_layout_mismatch_refused() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ttl = Duration::from_secs(60);
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        backend.verify_layout().await.unwrap();
+        backend.store("a", Bytes::from_static(b"plain"), ttl).await.unwrap();
+
+        let dedup = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        assert!(matches!(dedup.verify_layout().await, Err(SliceError::ConfigError(_))));
+
+        // Directories written before the layout was recorded are refused too
+        fs::remove_file(temp_dir.path().join(LAYOUT_FILE)).await.unwrap();
+        assert!(matches!(dedup.verify_layout().await, Err(SliceError::ConfigError(_))));
+        assert_eq!(backend.lookup("a").await.unwrap(), Some(Bytes::from_static(b"plain")));
+    }
+
+    #[tokio::test]
+    async fn test_same_layout_reopen() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ttl = Duration::from_secs(60);
+        {
+            let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+            assert_eq!(backend.verify_layout().await.unwrap(), 0);
+            backend.store("a", Bytes::from_static(b"body"), ttl).await.unwrap();
+        }
+
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        assert_eq!(backend.verify_layout().await.unwrap(), 0);
+        assert_eq!(backend.lookup("a").await.unwrap(), Some(Bytes::from_static(b"body")));
+
+        // The layout record is not an entry and survives a purge
+        assert_eq!(backend.purge_all().await.unwrap(), 1);
+        let plain = FileBackend::new(temp_dir.path()).await.unwrap();
+        assert!(plain.verify_layout().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_layout_migration() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ttl = Duration::from_secs(60);
+        let plain = FileBackend::new(temp_dir.path()).await.unwrap();
+        plain.verify_layout().await.unwrap();
+        plain.store("a", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        plain.store("b", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        plain.store("c", Bytes::from_static(b"other"), ttl).await.unwrap();
+
+        let dedup = FileBackend::new(temp_dir.path())
+            .await
+            .unwrap()
+            .with_dedup(true)
+            .with_layout_migration(true);
+        assert_eq!(dedup.verify_layout().await.unwrap(), 3);
+        assert_eq!(dedup.lookup("a").await.unwrap(), Some(Bytes::from_static(b"shared")));
+        assert_eq!(dedup.dedup_stats().await.unwrap().objects, 2);
+        assert_eq!(dedup.verify_layout().await.unwrap(), 0);
+
+        let plain = plain.with_layout_migration(true);
+        assert_eq!(plain.verify_layout().await.unwrap(), 3);
+        assert_eq!(plain.lookup("b").await.unwrap(), Some(Bytes::from_static(b"shared")));
+        assert_eq!(plain.lookup("c").await.unwrap(), Some(Bytes::from_static(b"other")));
+        assert!(fs::metadata(temp_dir.path().join("objects")).await.is_err());
+    }
+
+    /// Make the file at `path` look `age` old
+    fn age_file(path: &Path, age: Duration) {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_survives_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ttl = Duration::from_secs(60);
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        assert_eq!(backend.stats().entries, None);
+        assert_eq!(backend.load_index().await.unwrap(), IndexStats::default());
+
+        backend.store("a", Bytes::from(vec![1u8; 100]), ttl).await.unwrap();
+        backend.store("b", Bytes::from(vec![2u8; 50]), ttl).await.unwrap();
+        backend.store("b", Bytes::from(vec![2u8; 60]), ttl).await.unwrap();
+        backend.store("c", Bytes::from(vec![3u8; 10]), ttl).await.unwrap();
+        assert!(backend.remove("c").await.unwrap());
+        let stats = backend.stats();
+        assert_eq!((stats.entries, stats.bytes), (Some(2), Some(160)));
+
+        let restarted = FileBackend::new(temp_dir.path()).await.unwrap();
+        let loaded = restarted.load_index().await.unwrap();
+        assert_eq!((loaded.recovered_entries, loaded.recovered_bytes), (2, 160));
+        assert_eq!(loaded.orphans_removed + loaded.adopted_entries + loaded.missing_entries, 0);
+        assert_eq!(restarted.lookup("b").await.unwrap(), Some(Bytes::from(vec![2u8; 60])));
+        assert_eq!(restarted.stats().index, Some(loaded));
+
+        // A key the index does not list is a miss without reading the disk
+        let expires_at_secs = unix_secs(SystemTime::now() + ttl);
+        restarted.write_file(&restarted.file_path("ghost"), &[&expires_at_secs.to_le_bytes(), b"x"]).await.unwrap();
+        assert_eq!(restarted.lookup("ghost").await.unwrap(), None);
+        assert_eq!(FileBackend::new(temp_dir.path()).await.unwrap().lookup("ghost").await.unwrap(), Some(Bytes::from_static(b"x")));
+    }
+
+    #[tokio::test]
+    async fn test_index_reconciles_with_directory() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ttl = Duration::from_secs(60);
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        backend.load_index().await.unwrap();
+        backend.store("kept", Bytes::from(vec![0u8; 10]), ttl).await.unwrap();
+        backend.store("lost", Bytes::from(vec![0u8; 10]), ttl).await.unwrap();
+
+        // Written behind the index's back: one just now, one long ago
+        let unindexed = FileBackend::new(temp_dir.path()).await.unwrap();
+        unindexed.store("recent", Bytes::from(vec![0u8; 20]), ttl).await.unwrap();
+        unindexed.store("stale", Bytes::from(vec![0u8; 30]), ttl).await.unwrap();
+        age_file(&unindexed.file_path("stale"), Duration::from_secs(3600));
+        fs::remove_file(backend.file_path("lost")).await.unwrap();
+
+        let restarted = FileBackend::new(temp_dir.path()).await.unwrap();
+        let loaded = restarted.load_index().await.unwrap();
+        assert_eq!(
+            loaded,
+            IndexStats {
+                recovered_entries: 1,
+                recovered_bytes: 10,
+                adopted_entries: 1,
+                missing_entries: 1,
+                orphans_removed: 1,
+                orphan_bytes: 38,
+            }
+        );
+        assert!(fs::metadata(restarted.file_path("stale")).await.is_err());
+        assert!(restarted.lookup("recent").await.unwrap().is_some());
+        assert_eq!(restarted.stats().entries, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_index_rebuilt_from_directory() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        backend.store("old", Bytes::from(vec![0u8; 10]), Duration::from_secs(60)).await.unwrap();
+        age_file(&backend.file_path("old"), Duration::from_secs(3600));
+
+        // Without an index every file is kept, however old
+        let loaded = backend.load_index().await.unwrap();
+        assert_eq!((loaded.adopted_entries, loaded.orphans_removed), (1, 0));
+
+        // A log cut off in the middle of a record keeps the records before it
+        backend.store("new", Bytes::from(vec![0u8; 5]), Duration::from_secs(60)).await.unwrap();
+        let index_path = temp_dir.path().join(INDEX_FILE);
+        let mut data = std::fs::read(&index_path).unwrap();
+        data.extend_from_slice(b"+\x05");
+        std::fs::write(&index_path, &data).unwrap();
+        let loaded = FileBackend::new(temp_dir.path()).await.unwrap().load_index().await.unwrap();
+        assert_eq!((loaded.recovered_entries, loaded.recovered_bytes), (2, 15));
+
+        // An unreadable index is rebuilt the same way
+        std::fs::write(&index_path, b"garbage").unwrap();
+        let loaded = FileBackend::new(temp_dir.path()).await.unwrap().load_index().await.unwrap();
+        assert_eq!((loaded.adopted_entries, loaded.recovered_entries), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_index_with_dedup() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ttl = Duration::from_secs(60);
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        backend.load_index().await.unwrap();
+        backend.store("a", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        backend.store("b", Bytes::from_static(b"shared"), ttl).await.unwrap();
+        assert_eq!(backend.stats().bytes, Some(12));
+
+        let stray = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        stray.store("c", Bytes::from_static(b"stray"), ttl).await.unwrap();
+        age_file(&stray.file_path("c"), Duration::from_secs(3600));
+
+        // The orphan entry goes, and recovery then reclaims its body
+        let restarted = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(true);
+        let loaded = restarted.load_index().await.unwrap();
+        assert_eq!((loaded.recovered_entries, loaded.orphans_removed), (2, 1));
+        assert_eq!(restarted.recover().await.unwrap().objects_reclaimed, 1);
+        assert_eq!(restarted.lookup("a").await.unwrap(), Some(Bytes::from_static(b"shared")));
+        assert_eq!(restarted.purge_all().await.unwrap(), 2);
+        assert_eq!(restarted.stats().entries, Some(0));
+    }
+}
@@ -0,0 +1,153 @@
+//! Cache key normalization
+//!
+//! Origins often serve the same object under URLs that differ only in
+//! tracking query parameters or path case. [`CacheKeyBuilder`] maps such
+//! URLs onto one cache key according to the configured [`CacheKeyPolicy`].
+//! The proxy, the cache warmer and the PURGE handler all build keys through
+//! it, so a purge reaches the same entries that clients hit.
+
+use crate::config::{CacheKeyPolicy, SliceConfig};
+
+/// Builds cache keys from request URLs
+#[derive(Debug, Clone, Default)]
+pub struct CacheKeyBuilder {
+    policy: CacheKeyPolicy,
+}
+
+impl CacheKeyBuilder {
+    /// Create a builder applying `policy`
+    pub fn new(policy: CacheKeyPolicy) -> Self {
+        CacheKeyBuilder { policy }
+    }
+
+    /// Create a builder from `cache_key_policy`
+    pub fn from_config(config: &SliceConfig) -> Self {
+        Self::new(config.cache_key_policy.clone())
+    }
+
+    /// Cache key for `url`
+    ///
+    /// Works on absolute URLs and on paths. With the default policy the URL
+    /// is returned unchanged.
+    pub fn build(&self, url: &str) -> String {
+        let (rest, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+        let (base, query) = match rest.split_once('?') {
+            Some((base, query)) => (base, Some(query)),
+            None => (rest, None),
+        };
+
+        let mut key = if self.policy.lowercase_path {
+            let path_start = match base.split_once("://") {
+                Some((scheme, after)) => scheme.len() + 3 + after.find('/').unwrap_or(after.len()),
+                None => 0,
+            };
+            format!("{}{}", &base[..path_start], base[path_start..].to_lowercase())
+        } else {
+            base.to_string()
+        };
+
+        if let Some(query) = query {
+            if !self.policy.strip_query {
+                key.push('?');
+                key.push_str(query);
+            } else {
+                let mut kept: Vec<&str> = query
+                    .split('&')
+                    .filter(|param| {
+                        let name = param.split_once('=').map_or(*param, |(name, _)| name);
+                        self.policy.keep_query_params.iter().any(|keep| keep == name)
+                    })
+                    .collect();
+                // Stable sort keeps repeated parameters in request order
+                kept.sort_by_key(|param| param.split_once('=').map_or(*param, |(name, _)| name));
+                if !kept.is_empty() {
+                    key.push('?');
+                    key.push_str(&kept.join("&"));
+                }
+            }
+        }
+
+        if let Some(fragment) = fragment {
+            if !self.policy.strip_fragment {
+                key.push('#');
+                key.push_str(fragment);
+            }
+        }
+
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(policy: CacheKeyPolicy) -> CacheKeyBuilder {
+        CacheKeyBuilder::new(policy)
+    }
+
+    #[test]
+    fn test_default_policy_keeps_url() {
+        let url = "http://Example.com/Videos/A.mp4?utm_source=x&b=1#t=10";
+        assert_eq!(CacheKeyBuilder::default().build(url), url);
+    }
+
+    #[test]
+    fn test_strip_query() {
+        let keys = builder(CacheKeyPolicy {
+            strip_query: true,
+            ..Default::default()
+        });
+        assert_eq!(keys.build("http://h/a.mp4?utm_source=x&v=2"), "http://h/a.mp4");
+        assert_eq!(keys.build("http://h/a.mp4"), "http://h/a.mp4");
+        assert_eq!(keys.build("/a.mp4?"), "/a.mp4");
+    }
+
+    #[test]
+    fn test_keep_query_params_sorted() {
+        let keys = builder(CacheKeyPolicy {
+            strip_query: true,
+            keep_query_params: vec!["v".to_string(), "lang".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            keys.build("http://h/a.mp4?v=2&utm_source=x&lang=en"),
+            "http://h/a.mp4?lang=en&v=2"
+        );
+        assert_eq!(
+            keys.build("http://h/a.mp4?lang=en&v=2"),
+            keys.build("http://h/a.mp4?utm_medium=y&v=2&lang=en")
+        );
+        assert_eq!(keys.build("http://h/a.mp4?v=2&v=1&flag"), "http://h/a.mp4?v=2&v=1");
+    }
+
+    #[test]
+    fn test_lowercase_path() {
+        let keys = builder(CacheKeyPolicy {
+            lowercase_path: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            keys.build("http://Origin.Example.com:8080/Videos/A.MP4?Token=AbC"),
+            "http://Origin.Example.com:8080/videos/a.mp4?Token=AbC"
+        );
+        assert_eq!(keys.build("/Videos/A.MP4"), "/videos/a.mp4");
+        assert_eq!(keys.build("http://Host"), "http://Host");
+    }
+
+    #[test]
+    fn test_strip_fragment() {
+        let keys = builder(CacheKeyPolicy {
+            strip_fragment: true,
+            ..Default::default()
+        });
+        assert_eq!(keys.build("http://h/a.mp4?v=1#t=10"), "http://h/a.mp4?v=1");
+        assert_eq!(
+            CacheKeyBuilder::default().build("http://h/a.mp4#t=10"),
+            "http://h/a.mp4#t=10"
+        );
+    }
+}
@@ -0,0 +1,644 @@
+//! Cache warming for pre-populating slices before traffic arrives
+//!
+//! A [`CacheWarmer`] runs the slicing pipeline (metadata fetch, slice
+//! calculation, concurrent slice fetches) for a list of URLs and stores the
+//! results in a [`TieredCache`]. It uses its own, small concurrency limit so
+//! warming does not starve live traffic.
+//!
+//! Supported admin endpoints (see [`WarmHandler`]):
+//! - POST /admin/warm - Start warming the URLs listed in the body
+//! - GET /admin/warm/status - Per-URL progress of the current or last job
+//! - DELETE /admin/warm - Cancel the running job
+
+use crate::cache_key::CacheKeyBuilder;
+use crate::config::SliceConfig;
+use crate::error::{Result, SliceError};
+use crate::header_rules::HeaderRewriter;
+use crate::metadata_fetcher::MetadataFetcher;
+use crate::metrics::SliceMetrics;
+use crate::models::SliceKey;
+use crate::origin_signing::RequestSigner;
+use crate::purge_handler::{has_valid_token, parse_batch_urls};
+use crate::slice_calculator::SliceCalculator;
+use crate::rate_limiter::OriginRateLimiter;
+use crate::request_analyzer::RequestAnalyzer;
+use crate::subrequest_manager::SubrequestManager;
+use crate::tiered_cache::TieredCache;
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Body;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+/// Default number of concurrent slice fetches while warming
+const DEFAULT_WARM_CONCURRENCY: usize = 2;
+
+/// Maximum warm request body size
+const MAX_WARM_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// State of a single URL in a warm job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmUrlState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// Progress of a single URL in a warm job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmUrlStatus {
+    pub url: String,
+    pub state: WarmUrlState,
+    pub slices_total: usize,
+    /// Slices that were already cached and skipped
+    pub slices_cached: usize,
+    pub slices_fetched: usize,
+    pub bytes_fetched: u64,
+    pub error: Option<String>,
+}
+
+/// Progress of the current (or last) warm job
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarmStatus {
+    pub running: bool,
+    pub cancelled: bool,
+    pub bytes_fetched: u64,
+    pub urls: Vec<WarmUrlStatus>,
+}
+
+impl WarmStatus {
+    fn new(urls: Vec<String>) -> Self {
+        WarmStatus {
+            running: true,
+            cancelled: false,
+            bytes_fetched: 0,
+            urls: urls
+                .into_iter()
+                .map(|url| WarmUrlStatus {
+                    url,
+                    state: WarmUrlState::Pending,
+                    slices_total: 0,
+                    slices_cached: 0,
+                    slices_fetched: 0,
+                    bytes_fetched: 0,
+                    error: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Number of URLs in the given state
+    pub fn count(&self, state: WarmUrlState) -> usize {
+        self.urls.iter().filter(|u| u.state == state).count()
+    }
+}
+
+/// Pre-populates the cache for a list of URLs
+pub struct CacheWarmer {
+    config: Arc<SliceConfig>,
+    /// Compiled once, for the TTL of each warmed URL
+    analyzer: RequestAnalyzer,
+    cache_keys: CacheKeyBuilder,
+    header_rewriter: HeaderRewriter,
+    cache: Arc<TieredCache>,
+    concurrency: usize,
+    metrics: Option<Arc<SliceMetrics>>,
+    rate_limiter: Option<Arc<OriginRateLimiter>>,
+    status: RwLock<WarmStatus>,
+    cancelled: AtomicBool,
+}
+
+impl CacheWarmer {
+    /// Create a new cache warmer
+    ///
+    /// # Arguments
+    /// * `config` - Slice configuration (slice size, retries, metadata probe)
+    /// * `cache` - Cache that warmed slices are stored in
+    pub fn new(config: Arc<SliceConfig>, cache: Arc<TieredCache>) -> Self {
+        let rate_limiter = OriginRateLimiter::from_config(&config).map(Arc::new);
+        Self {
+            analyzer: RequestAnalyzer::new(config.clone()),
+            cache_keys: CacheKeyBuilder::from_config(&config),
+            header_rewriter: HeaderRewriter::from_config(&config),
+            config,
+            cache,
+            concurrency: DEFAULT_WARM_CONCURRENCY,
+            metrics: None,
+            rate_limiter,
+            status: RwLock::new(WarmStatus::default()),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Set the maximum number of concurrent slice fetches (default: 2)
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Record warmed slices and bytes in the given metrics
+    pub fn with_metrics(mut self, metrics: Arc<SliceMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Share the origin rate limiter used by the proxy
+    pub fn with_rate_limiter(mut self, limiter: Arc<OriginRateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Progress of the current (or last) warm job
+    pub fn status(&self) -> WarmStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Whether a warm job is running
+    pub fn is_running(&self) -> bool {
+        self.status.read().unwrap().running
+    }
+
+    /// Cancel the running warm job
+    ///
+    /// Slices already being fetched finish; remaining slices and URLs are skipped.
+    pub fn cancel(&self) {
+        if self.is_running() {
+            info!("Cancelling cache warm-up");
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Start warming `urls` in the background
+    ///
+    /// # Returns
+    /// * `Ok(())` if the job was started
+    /// * `Err(SliceError)` if a job is already running
+    pub fn start(self: &Arc<Self>, urls: Vec<String>) -> Result<()> {
+        self.begin(urls)?;
+        let warmer = self.clone();
+        tokio::spawn(async move { warmer.run().await });
+        Ok(())
+    }
+
+    /// Warm `urls` and wait for the job to finish
+    ///
+    /// # Returns
+    /// * `Ok(WarmStatus)` with the final per-URL results
+    /// * `Err(SliceError)` if a job is already running
+    pub async fn warm(&self, urls: Vec<String>) -> Result<WarmStatus> {
+        self.begin(urls)?;
+        self.run().await;
+        Ok(self.status())
+    }
+
+    /// Reset the status for a new job, failing if one is running
+    fn begin(&self, urls: Vec<String>) -> Result<()> {
+        let mut status = self.status.write().unwrap();
+        if status.running {
+            return Err(SliceError::InternalError(
+                "A cache warm-up job is already running".to_string(),
+            ));
+        }
+        info!("Starting cache warm-up for {} URLs", urls.len());
+        *status = WarmStatus::new(urls);
+        self.cancelled.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Warm every URL of the current job in order
+    async fn run(&self) {
+        let urls: Vec<String> = self.status().urls.into_iter().map(|u| u.url).collect();
+
+        for (idx, url) in urls.iter().enumerate() {
+            if self.cancelled.load(Ordering::Relaxed) {
+                self.update(idx, |u| u.state = WarmUrlState::Cancelled);
+                continue;
+            }
+
+            self.update(idx, |u| u.state = WarmUrlState::Running);
+            let state = match self.warm_url(idx, url).await {
+                Ok(()) if self.cancelled.load(Ordering::Relaxed) => WarmUrlState::Cancelled,
+                Ok(()) => WarmUrlState::Done,
+                Err(e) => {
+                    warn!("Failed to warm {}: {}", url, e);
+                    self.update(idx, |u| u.error = Some(e.to_string()));
+                    WarmUrlState::Failed
+                }
+            };
+            self.update(idx, |u| u.state = state);
+        }
+
+        let mut status = self.status.write().unwrap();
+        status.running = false;
+        status.cancelled = self.cancelled.load(Ordering::Relaxed);
+        info!(
+            "Cache warm-up finished: {} done, {} failed, {} cancelled, {} bytes fetched",
+            status.count(WarmUrlState::Done),
+            status.count(WarmUrlState::Failed),
+            status.count(WarmUrlState::Cancelled),
+            status.bytes_fetched
+        );
+    }
+
+    /// Fetch and store every slice of `url` that is not already cached
+    async fn warm_url(&self, idx: usize, url: &str) -> Result<()> {
+        let origin_headers = self.header_rewriter.request_headers(HeaderMap::new());
+        let fetcher = MetadataFetcher::from_config(&self.config)?
+            .with_request_headers(origin_headers.clone());
+        let metadata = fetcher.fetch_metadata(url).await?;
+        if !metadata.supports_range {
+            return Err(SliceError::RangeNotSupported);
+        }
+
+        let slices = SliceCalculator::new(self.config.slice_size)
+            .calculate_slices(metadata.content_length, None)?;
+        let slices_total = slices.len();
+
+        // Store under the same key and with the same TTL as the proxy would
+        let cache_url = SliceKey::new(&self.cache_keys.build(url)).object_key();
+        let ttl = self.analyzer.cache_ttl_for(url);
+        let mut missing = Vec::new();
+        for slice in slices {
+            let key = self.cache.generate_cache_key(&cache_url, &slice.range);
+            if self.cache.inspect(&key).await.is_none() {
+                missing.push(slice);
+            }
+        }
+        let slices_cached = slices_total - missing.len();
+        self.update(idx, |u| {
+            u.slices_total = slices_total;
+            u.slices_cached = slices_cached;
+        });
+
+        let mut manager = SubrequestManager::new(self.concurrency, self.config.max_retries)
+            .with_expected_size(metadata.content_length)
+            .with_request_headers(origin_headers);
+        if let Some(limiter) = &self.rate_limiter {
+            manager = manager.with_rate_limiter(limiter.clone());
+        }
+        if let Some(signer) = RequestSigner::from_config(&self.config) {
+            manager = manager.with_signer(Arc::new(signer));
+        }
+        for chunk in missing.chunks(self.concurrency) {
+            if self.cancelled.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            for result in manager.fetch_slices(chunk.to_vec(), url).await? {
+                let Some(slice) = chunk.iter().find(|s| s.index == result.slice_index) else {
+                    continue;
+                };
+                let bytes = result.data.len() as u64;
+                self.cache.store_with_ttl(&cache_url, &slice.range, result.data, ttl)?;
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_bytes_from_origin(bytes);
+                    metrics.record_warmed_slice(bytes);
+                }
+                self.update(idx, |u| {
+                    u.slices_fetched += 1;
+                    u.bytes_fetched += bytes;
+                });
+                self.status.write().unwrap().bytes_fetched += bytes;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update(&self, idx: usize, f: impl FnOnce(&mut WarmUrlStatus)) {
+        if let Some(url) = self.status.write().unwrap().urls.get_mut(idx) {
+            f(url);
+        }
+    }
+}
+
+/// Admin endpoints for starting, inspecting and cancelling cache warm-up
+pub struct WarmHandler {
+    warmer: Arc<CacheWarmer>,
+    /// Optional auth token (shared with PURGE)
+    auth_token: Option<String>,
+}
+
+/// Error response body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WarmErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+impl WarmHandler {
+    /// Create a new warm handler
+    pub fn new(warmer: Arc<CacheWarmer>) -> Self {
+        Self {
+            warmer,
+            auth_token: None,
+        }
+    }
+
+    /// Create a new warm handler with authentication
+    pub fn with_auth(warmer: Arc<CacheWarmer>, auth_token: String) -> Self {
+        Self {
+            warmer,
+            auth_token: Some(auth_token),
+        }
+    }
+
+    /// Whether the request path is served by this handler
+    pub fn matches(path: &str) -> bool {
+        path == "/admin/warm" || path.starts_with("/admin/warm/")
+    }
+
+    /// Handle a warm admin request
+    pub async fn handle_request<B>(&self, req: Request<B>) -> Result<Response<Full<Bytes>>>
+    where
+        B: Body,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if let Some(expected_token) = &self.auth_token {
+            if !has_valid_token(&req, expected_token) {
+                return self.error_response(
+                    StatusCode::UNAUTHORIZED,
+                    "Invalid or missing authentication token",
+                );
+            }
+        }
+
+        match (req.method().clone(), req.uri().path()) {
+            (Method::POST, "/admin/warm") => {
+                let body = match Limited::new(req.into_body(), MAX_WARM_BODY_BYTES)
+                    .collect()
+                    .await
+                {
+                    Ok(body) => body.to_bytes(),
+                    Err(e) => {
+                        return self.error_response(
+                            StatusCode::PAYLOAD_TOO_LARGE,
+                            &format!("Failed to read request body: {}", e),
+                        );
+                    }
+                };
+                let urls = match parse_batch_urls(&body) {
+                    Ok(urls) if !urls.is_empty() => urls,
+                    Ok(_) => return self.error_response(StatusCode::BAD_REQUEST, "No URLs to warm"),
+                    Err(e) => return self.error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+                };
+                match self.warmer.start(urls) {
+                    Ok(()) => self.json_response(StatusCode::ACCEPTED, &self.warmer.status()),
+                    Err(e) => self.error_response(StatusCode::CONFLICT, &e.to_string()),
+                }
+            }
+            (Method::DELETE, "/admin/warm") => {
+                self.warmer.cancel();
+                self.json_response(StatusCode::OK, &self.warmer.status())
+            }
+            (Method::GET, "/admin/warm/status") => {
+                self.json_response(StatusCode::OK, &self.warmer.status())
+            }
+            (_, "/admin/warm") | (_, "/admin/warm/status") => {
+                self.error_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+            }
+            _ => self.error_response(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    /// Build JSON response
+    fn json_response<T: Serialize>(
+        &self,
+        status: StatusCode,
+        body: &T,
+    ) -> Result<Response<Full<Bytes>>> {
+        let json = serde_json::to_string(body)
+            .map_err(|e| SliceError::CacheError(format!("Failed to serialize response: {}", e)))?;
+
+        Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .header("cache-control", "no-cache, no-store, must-revalidate")
+            .body(Full::new(Bytes::from(json)))
+            .map_err(|e| SliceError::CacheError(format!("Failed to build response: {}", e)))
+    }
+
+    /// Build error response
+    fn error_response(&self, status: StatusCode, message: &str) -> Result<Response<Full<Bytes>>> {
+        let response = WarmErrorResponse {
+            success: false,
+            message: message.to_string(),
+        };
+
+        self.json_response(status, &response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ByteRange;
+    use std::time::Duration;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mount_file(server: &MockServer, file: &str, expected_gets: u64, head_delay: Duration) {
+        Mock::given(method("HEAD"))
+            .and(path(file))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Length", "2048")
+                    .insert_header("Accept-Ranges", "bytes")
+                    .set_delay(head_delay),
+            )
+            .mount(server)
+            .await;
+        for (range, content_range) in [
+            ("bytes=0-1023", "bytes 0-1023/2048"),
+            ("bytes=1024-2047", "bytes 1024-2047/2048"),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(file))
+                .and(header("range", range))
+                .respond_with(
+                    ResponseTemplate::new(206)
+                        .insert_header("Content-Range", content_range)
+                        .set_body_bytes(vec![7u8; 1024]),
+                )
+                .expect(expected_gets)
+                .mount(server)
+                .await;
+        }
+    }
+
+    fn warmer() -> (Arc<TieredCache>, Arc<SliceMetrics>, Arc<CacheWarmer>) {
+        let config = Arc::new(SliceConfig {
+            slice_size: 1024,
+            max_retries: 0,
+            ..Default::default()
+        });
+        let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+        let metrics = Arc::new(SliceMetrics::new());
+        let warmer = Arc::new(
+            CacheWarmer::new(config, cache.clone()).with_metrics(metrics.clone()),
+        );
+        (cache, metrics, warmer)
+    }
+
+    #[tokio::test]
+    async fn test_warm_urls_then_cache_hits() {
+        let server = MockServer::start().await;
+        // Each slice is fetched from origin exactly once across both warm runs
+        mount_file(&server, "/a.bin", 1, Duration::ZERO).await;
+        mount_file(&server, "/b.bin", 1, Duration::ZERO).await;
+        let urls = vec![
+            format!("{}/a.bin", server.uri()),
+            format!("{}/b.bin", server.uri()),
+        ];
+        let (cache, metrics, warmer) = warmer();
+
+        let status = warmer.warm(urls.clone()).await.unwrap();
+        assert!(!status.running);
+        assert_eq!(status.count(WarmUrlState::Done), 2);
+        assert_eq!(status.bytes_fetched, 4096);
+        assert_eq!(status.urls[0].slices_fetched, 2);
+        assert_eq!(metrics.get_stats().warmed_bytes, 4096);
+        assert_eq!(metrics.get_stats().warmed_slices, 4);
+
+        for url in &urls {
+            for range in [ByteRange::new(0, 1023).unwrap(), ByteRange::new(1024, 2047).unwrap()] {
+                assert!(cache.lookup(url, &range).await.unwrap().is_some());
+            }
+        }
+        assert_eq!(cache.get_stats().l1_hits, 4);
+
+        // Warming again skips the cached slices
+        let status = warmer.warm(urls).await.unwrap();
+        assert_eq!(status.urls[1].slices_cached, 2);
+        assert_eq!(status.urls[1].slices_fetched, 0);
+        assert_eq!(status.bytes_fetched, 0);
+    }
+
+    #[tokio::test]
+    async fn test_warm_uses_pattern_ttl() {
+        let server = MockServer::start().await;
+        mount_file(&server, "/live.bin", 1, Duration::ZERO).await;
+        mount_file(&server, "/vod.bin", 1, Duration::ZERO).await;
+        let config = Arc::new(SliceConfig {
+            slice_size: 1024,
+            max_retries: 0,
+            cache_ttl: 3600,
+            pattern_rules: vec![crate::config::PatternRule {
+                pattern: "*/live.bin".to_string(),
+                name: None,
+                cache_ttl: Some(30),
+            }],
+            ..Default::default()
+        });
+        let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+        let warmer = CacheWarmer::new(config, cache.clone());
+
+        let live = format!("{}/live.bin", server.uri());
+        let vod = format!("{}/vod.bin", server.uri());
+        warmer.warm(vec![live.clone(), vod.clone()]).await.unwrap();
+
+        let range = ByteRange::new(0, 1023).unwrap();
+        let ttl = |url: &str| {
+            let key = cache.generate_cache_key(url, &range);
+            let cache = cache.clone();
+            async move { cache.inspect(&key).await.unwrap().ttl_remaining_secs }
+        };
+        assert!((29..=30).contains(&ttl(&live).await));
+        assert!((3599..=3600).contains(&ttl(&vod).await));
+    }
+
+    #[tokio::test]
+    async fn test_warm_failure_reported_per_url() {
+        let server = MockServer::start().await;
+        mount_file(&server, "/a.bin", 1, Duration::ZERO).await;
+        Mock::given(method("HEAD"))
+            .and(path("/missing.bin"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let (_cache, _metrics, warmer) = warmer();
+
+        let status = warmer
+            .warm(vec![
+                format!("{}/missing.bin", server.uri()),
+                format!("{}/a.bin", server.uri()),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(status.urls[0].state, WarmUrlState::Failed);
+        assert!(status.urls[0].error.is_some());
+        assert_eq!(status.urls[1].state, WarmUrlState::Done);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_warm_job() {
+        let server = MockServer::start().await;
+        mount_file(&server, "/a.bin", 0, Duration::from_millis(200)).await;
+        mount_file(&server, "/b.bin", 0, Duration::ZERO).await;
+        let (_cache, _metrics, warmer) = warmer();
+
+        warmer
+            .start(vec![
+                format!("{}/a.bin", server.uri()),
+                format!("{}/b.bin", server.uri()),
+            ])
+            .unwrap();
+        assert!(warmer.start(vec!["http://example.com/x".to_string()]).is_err());
+        warmer.cancel();
+
+        while warmer.is_running() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let status = warmer.status();
+        assert!(status.cancelled);
+        assert_eq!(status.count(WarmUrlState::Cancelled), 2);
+    }
+
+    #[tokio::test]
+    async fn test_warm_endpoints() {
+        let server = MockServer::start().await;
+        mount_file(&server, "/a.bin", 1, Duration::ZERO).await;
+        let (_cache, _metrics, warmer) = warmer();
+        let handler = WarmHandler::with_auth(warmer.clone(), "secret-token".to_string());
+
+        let body = format!(r#"["{}/a.bin"]"#, server.uri());
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/warm")
+            .body(Full::new(Bytes::from(body.clone())))
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/warm")
+            .header("authorization", "Bearer secret-token")
+            .body(Full::new(Bytes::from(body)))
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        while warmer.is_running() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let req = Request::builder()
+            .uri("/admin/warm/status")
+            .header("authorization", "Bearer secret-token")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let response = handler.handle_request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let status: WarmStatus = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status.urls[0].state, WarmUrlState::Done);
+        assert_eq!(status.bytes_fetched, 2048);
+    }
+}
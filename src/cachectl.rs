@@ -0,0 +1,394 @@
+//! Offline inspection and repair of a file-backed L2 cache directory
+//!
+//! Backs the `slice-cachectl` binary, for examining the cache of a node
+//! without starting the proxy:
+//!
+//! ```text
+//! slice-cachectl <dir> info
+//! slice-cachectl <dir> ls [--prefix <prefix>]
+//! slice-cachectl <dir> get <key> [-o <file>]
+//! slice-cachectl <dir> verify [--repair]
+//! slice-cachectl <dir> rebuild-metadata
+//! slice-cachectl <dir> stats [--json]
+//! ```
+//!
+//! Every command except `verify --repair` and `rebuild-metadata` opens the
+//! directory with [`FileBackend::open_readonly`], so it cannot change a
+//! cache a running proxy still uses.
+
+use crate::cache_backend::{CacheBackend, FileBackend, FileLayout};
+use crate::error::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Exit code: the command succeeded and the cache is healthy
+pub const EXIT_HEALTHY: i32 = 0;
+/// Exit code: `verify` found problems it did not (or could not) repair
+pub const EXIT_CORRUPT: i32 = 1;
+/// Exit code: the directory is missing or not a cache the tool can read
+pub const EXIT_UNUSABLE: i32 = 2;
+/// Exit code: bad arguments, a key that is not cached, or a failed write
+pub const EXIT_FAILED: i32 = 3;
+
+/// Command line help
+pub const USAGE: &str = "\
+usage: slice-cachectl <dir> <command>
+
+commands:
+  info                      layout, index and entry counts
+  ls [--prefix <prefix>]    keys recorded in the index
+  get <key> [-o <file>]     write an entry's data to stdout or <file>
+  verify [--repair]         check entries, index and shared bodies
+  rebuild-metadata          rebuild the index from a directory scan
+  stats [--json]            entry counts and disk usage
+
+exit codes: 0 healthy, 1 corruption found, 2 cache unusable, 3 command failed
+";
+
+/// A parsed command line
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Info,
+    Ls { prefix: Option<String> },
+    Get { key: String, output: Option<PathBuf> },
+    Verify { repair: bool },
+    RebuildMetadata,
+    Stats { json: bool },
+}
+
+/// `stats --json` output
+#[derive(Debug, Serialize)]
+struct StatsReport {
+    layout: Option<FileLayout>,
+    entries: u64,
+    expired_entries: u64,
+    index_records: Option<u64>,
+    indexed_bytes: Option<u64>,
+    file_bytes: u64,
+    allocated_bytes: u64,
+    slack_bytes: u64,
+    temp_files: u64,
+    logical_bytes: Option<u64>,
+    physical_bytes: Option<u64>,
+}
+
+/// Run the command in `args` (without the program name)
+///
+/// # Returns
+/// The process exit code, one of the `EXIT_*` constants
+pub async fn run(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let (dir, command) = match parse(args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            let _ = write!(err, "{}\n\n{}", message, USAGE);
+            return EXIT_FAILED;
+        }
+    };
+    let backend = match FileBackend::open_readonly(&dir).await {
+        Ok(backend) => backend,
+        Err(e) => {
+            let _ = writeln!(err, "cannot open cache: {}", e);
+            return EXIT_UNUSABLE;
+        }
+    };
+
+    let result = match command {
+        Command::Info => info(&backend, out).await,
+        Command::Ls { prefix } => ls(&backend, prefix.as_deref(), out, err).await,
+        Command::Get { key, output } => get(&backend, &key, output.as_deref(), out, err).await,
+        Command::Verify { repair } => verify(&backend, repair, out).await,
+        Command::RebuildMetadata => rebuild_metadata(&backend, out).await,
+        Command::Stats { json } => stats(&backend, json, out).await,
+    };
+    result.unwrap_or_else(|e| {
+        let _ = writeln!(err, "{}", e);
+        EXIT_UNUSABLE
+    })
+}
+
+fn parse(args: &[String]) -> std::result::Result<(PathBuf, Command), String> {
+    let mut args = args.iter().map(String::as_str);
+    let dir = args.next().ok_or("missing cache directory")?;
+    let name = args.next().ok_or("missing command")?;
+    let rest: Vec<&str> = args.collect();
+    let command = match (name, rest.as_slice()) {
+        ("info", []) => Command::Info,
+        ("ls", []) => Command::Ls { prefix: None },
+        ("ls", ["--prefix", prefix]) => Command::Ls {
+            prefix: Some(prefix.to_string()),
+        },
+        ("get", [key]) => Command::Get {
+            key: key.to_string(),
+            output: None,
+        },
+        ("get", [key, "-o", file]) => Command::Get {
+            key: key.to_string(),
+            output: Some(PathBuf::from(file)),
+        },
+        ("verify", []) => Command::Verify { repair: false },
+        ("verify", ["--repair"]) => Command::Verify { repair: true },
+        ("rebuild-metadata", []) => Command::RebuildMetadata,
+        ("stats", []) => Command::Stats { json: false },
+        ("stats", ["--json"]) => Command::Stats { json: true },
+        _ => return Err(format!("invalid command: {} {}", name, rest.join(" "))),
+    };
+    Ok((PathBuf::from(dir), command))
+}
+
+fn layout_name(layout: Option<FileLayout>) -> &'static str {
+    match layout {
+        Some(FileLayout::Plain) => "plain",
+        Some(FileLayout::Dedup) => "dedup",
+        None => "empty",
+    }
+}
+
+async fn info(backend: &FileBackend, out: &mut dyn Write) -> Result<i32> {
+    let layout = backend.stored_layout().await?;
+    let usage = backend.disk_usage().await?;
+    writeln!(out, "directory: {}", backend.base_path().display())?;
+    writeln!(out, "layout: {}", layout_name(layout))?;
+    match backend.read_index().await {
+        Ok(Some(records)) => writeln!(out, "index: {} records", records.len())?,
+        Ok(None) => writeln!(out, "index: missing")?,
+        Err(e) => writeln!(out, "index: unreadable ({})", e)?,
+    }
+    writeln!(out, "entries: {} ({} expired)", usage.entries, usage.expired_entries)?;
+    writeln!(out, "bytes: {}", usage.file_bytes)?;
+    Ok(EXIT_HEALTHY)
+}
+
+async fn ls(
+    backend: &FileBackend,
+    prefix: Option<&str>,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    let now = unix_now();
+    let matches = |name: &str| prefix.is_none_or(|prefix| name.starts_with(prefix));
+    match backend.read_index().await? {
+        Some(records) => {
+            for record in records {
+                // Adopted entries have no key, only their file
+                let name = if record.key.is_empty() { &record.file } else { &record.key };
+                if matches(name) {
+                    let ttl = match record.expires_at_secs.checked_sub(now) {
+                        Some(secs) if secs > 0 => format!("{}s", secs),
+                        _ => "expired".to_string(),
+                    };
+                    writeln!(out, "{}\t{}\t{}", name, record.size_bytes, ttl)?;
+                }
+            }
+        }
+        None => {
+            writeln!(err, "no index; listing entry files instead of keys")?;
+            let mut entries = Vec::new();
+            backend
+                .visit_entries(&mut |name, entry| entries.push((name, entry)))
+                .await?;
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (name, entry) in entries.into_iter().filter(|(name, _)| matches(name)) {
+                writeln!(out, "{}\t{}\t{}s", name, entry.size_bytes, entry.ttl_remaining.as_secs())?;
+            }
+        }
+    }
+    Ok(EXIT_HEALTHY)
+}
+
+async fn get(
+    backend: &FileBackend,
+    key: &str,
+    output: Option<&Path>,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32> {
+    let Some(data) = backend.lookup(key).await? else {
+        writeln!(err, "not cached: {}", key)?;
+        return Ok(EXIT_FAILED);
+    };
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, &data) {
+                writeln!(err, "cannot write {}: {}", path.display(), e)?;
+                return Ok(EXIT_FAILED);
+            }
+            writeln!(out, "wrote {} bytes to {}", data.len(), path.display())?;
+        }
+        None => out.write_all(&data)?,
+    }
+    Ok(EXIT_HEALTHY)
+}
+
+/// Problems found by `verify`, one line each
+async fn problems(backend: &FileBackend) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    match backend.read_index().await {
+        Ok(Some(records)) => {
+            let missing = records
+                .iter()
+                .filter(|record| !backend.base_path().join(&record.file).is_file())
+                .count();
+            if missing > 0 {
+                problems.push(format!("{} index records without an entry file", missing));
+            }
+        }
+        Ok(None) => {}
+        Err(e) => problems.push(format!("index unreadable: {}", e)),
+    }
+
+    let report = backend.check_consistency().await?;
+    for (count, what) in [
+        (report.corrupt_entries, "corrupt entries"),
+        (report.dangling_entries, "entries without their shared body"),
+        (report.unreferenced_objects, "unreferenced shared bodies"),
+        (report.refcount_mismatches, "wrong reference counts"),
+        (backend.disk_usage().await?.temp_files, "temporary files"),
+    ] {
+        if count > 0 {
+            problems.push(format!("{} {}", count, what));
+        }
+    }
+    Ok(problems)
+}
+
+async fn verify(backend: &FileBackend, repair: bool, out: &mut dyn Write) -> Result<i32> {
+    let found = problems(backend).await?;
+    for problem in &found {
+        writeln!(out, "problem: {}", problem)?;
+    }
+    if found.is_empty() {
+        writeln!(out, "healthy")?;
+        return Ok(EXIT_HEALTHY);
+    }
+    if !repair {
+        return Ok(EXIT_CORRUPT);
+    }
+
+    let writer = writable(backend).await?;
+    let removed = writer.remove_corrupt_entries().await?;
+    let recovered = writer.recover().await?;
+    let indexed = writer.load_index().await?;
+    writeln!(
+        out,
+        "repaired: {} corrupt entries removed, {} temporary files removed, {} shared bodies reclaimed, {} reference counts fixed, {} orphan files removed, {} missing entries dropped",
+        removed,
+        recovered.temp_files_removed,
+        recovered.objects_reclaimed,
+        recovered.refcounts_fixed,
+        indexed.orphans_removed,
+        indexed.missing_entries
+    )?;
+
+    let left = problems(backend).await?;
+    for problem in &left {
+        writeln!(out, "unrepaired: {}", problem)?;
+    }
+    if left.is_empty() {
+        writeln!(out, "healthy")?;
+        Ok(EXIT_HEALTHY)
+    } else {
+        Ok(EXIT_CORRUPT)
+    }
+}
+
+async fn rebuild_metadata(backend: &FileBackend, out: &mut dyn Write) -> Result<i32> {
+    let stats = writable(backend).await?.rebuild_index().await?;
+    writeln!(
+        out,
+        "rebuilt index: {} entries adopted, {} unreadable files removed",
+        stats.adopted_entries, stats.orphans_removed
+    )?;
+    Ok(EXIT_HEALTHY)
+}
+
+async fn stats(backend: &FileBackend, json: bool, out: &mut dyn Write) -> Result<i32> {
+    let layout = backend.stored_layout().await?;
+    let usage = backend.disk_usage().await?;
+    let records = backend.read_index().await.ok().flatten();
+    let dedup = match layout {
+        Some(FileLayout::Dedup) => Some(backend.dedup_stats().await?),
+        _ => None,
+    };
+    let report = StatsReport {
+        layout,
+        entries: usage.entries,
+        expired_entries: usage.expired_entries,
+        index_records: records.as_ref().map(|records| records.len() as u64),
+        indexed_bytes: records
+            .as_ref()
+            .map(|records| records.iter().map(|record| record.size_bytes).sum()),
+        file_bytes: usage.file_bytes,
+        allocated_bytes: usage.allocated_bytes,
+        slack_bytes: usage.slack_bytes(),
+        temp_files: usage.temp_files,
+        logical_bytes: dedup.map(|dedup| dedup.logical_bytes),
+        physical_bytes: dedup.map(|dedup| dedup.physical_bytes),
+    };
+
+    if json {
+        let text = serde_json::to_string_pretty(&report)
+            .map_err(|e| crate::error::SliceError::InternalError(e.to_string()))?;
+        writeln!(out, "{}", text)?;
+        return Ok(EXIT_HEALTHY);
+    }
+    writeln!(out, "layout: {}", layout_name(report.layout))?;
+    writeln!(out, "entries: {} ({} expired)", report.entries, report.expired_entries)?;
+    if let (Some(count), Some(bytes)) = (report.index_records, report.indexed_bytes) {
+        writeln!(out, "indexed: {} records, {} bytes", count, bytes)?;
+    }
+    writeln!(out, "file bytes: {}", report.file_bytes)?;
+    writeln!(out, "allocated bytes: {}", report.allocated_bytes)?;
+    let slack_ratio = match report.allocated_bytes {
+        0 => 0.0,
+        allocated => report.slack_bytes as f64 / allocated as f64,
+    };
+    writeln!(out, "slack bytes: {} ({:.1}%)", report.slack_bytes, slack_ratio * 100.0)?;
+    writeln!(out, "temporary files: {}", report.temp_files)?;
+    if let (Some(logical), Some(physical)) = (report.logical_bytes, report.physical_bytes) {
+        writeln!(out, "dedup: {} logical bytes in {} physical bytes", logical, physical)?;
+    }
+    Ok(EXIT_HEALTHY)
+}
+
+/// A backend that may write to the directory `backend` opened read-only
+async fn writable(backend: &FileBackend) -> Result<FileBackend> {
+    let dedup = backend.stored_layout().await? == Some(FileLayout::Dedup);
+    Ok(FileBackend::new(backend.base_path()).await?.with_dedup(dedup))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let (dir, command) = parse(&args("/cache ls --prefix http://h/")).unwrap();
+        assert_eq!(dir, PathBuf::from("/cache"));
+        assert_eq!(command, Command::Ls { prefix: Some("http://h/".to_string()) });
+        assert_eq!(
+            parse(&args("/cache get k -o out.bin")).unwrap().1,
+            Command::Get {
+                key: "k".to_string(),
+                output: Some(PathBuf::from("out.bin"))
+            }
+        );
+        assert_eq!(parse(&args("/cache verify --repair")).unwrap().1, Command::Verify { repair: true });
+        assert_eq!(parse(&args("/cache stats --json")).unwrap().1, Command::Stats { json: true });
+        assert!(parse(&args("/cache")).is_err());
+        assert!(parse(&args("/cache verify --force")).is_err());
+        assert!(parse(&args("/cache get")).is_err());
+    }
+}
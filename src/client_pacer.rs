@@ -0,0 +1,169 @@
+//! Per-connection download bandwidth shaping
+//!
+//! [`ClientPacer`] tracks how many body bytes have been forwarded to a client
+//! and returns the delay needed before the next chunk to stay at the target
+//! rate. The first `burst` bytes are sent without delay so time to first byte
+//! is unaffected.
+
+use bytes::Bytes;
+use http::HeaderMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Request header carrying a per-request rate override, in bytes per second
+pub const CLIENT_RATE_LIMIT_HEADER: &str = "x-ratelimit-bps";
+
+/// Minimum number of bytes sent before pacing starts
+const MIN_BURST_BYTES: u64 = 64 * 1024;
+
+/// Largest chunk handed to the pacer when splitting cached data
+pub const PACED_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Paces body chunks sent to one client
+#[derive(Debug, Clone)]
+pub struct ClientPacer {
+    bytes_per_sec: u64,
+    burst: u64,
+    started: Option<Instant>,
+    sent: u64,
+}
+
+impl ClientPacer {
+    /// Create a pacer for the given rate
+    ///
+    /// The burst allowance is a tenth of a second's worth of data, and at
+    /// least 64KB.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        ClientPacer {
+            bytes_per_sec: bytes_per_sec.max(1),
+            burst: (bytes_per_sec / 10).max(MIN_BURST_BYTES),
+            started: None,
+            sent: 0,
+        }
+    }
+
+    /// Resolve the pacer for a request
+    ///
+    /// When `trust_header` is set, a numeric `X-RateLimit-Bps` header
+    /// overrides `default_rate`; a value of 0 disables pacing.
+    ///
+    /// # Returns
+    /// `None` if the request should not be paced
+    pub fn for_request(
+        default_rate: Option<u64>,
+        trust_header: bool,
+        headers: &HeaderMap,
+    ) -> Option<Self> {
+        let override_rate = trust_header
+            .then(|| headers.get(CLIENT_RATE_LIMIT_HEADER))
+            .flatten()
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+
+        match override_rate.or(default_rate) {
+            Some(rate) if rate > 0 => Some(Self::new(rate)),
+            _ => None,
+        }
+    }
+
+    /// Target rate in bytes per second
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Split `data` into pieces of at most `PACED_CHUNK_SIZE` bytes
+    ///
+    /// The pieces share `data`'s buffer, so nothing is copied.
+    pub fn split(data: &Bytes) -> impl Iterator<Item = Bytes> + '_ {
+        (0..data.len())
+            .step_by(PACED_CHUNK_SIZE)
+            .map(move |start| data.slice(start..(start + PACED_CHUNK_SIZE).min(data.len())))
+    }
+
+    /// Account for a chunk about to be forwarded
+    ///
+    /// # Returns
+    /// How long to wait before sending the next chunk, if at all
+    pub fn on_chunk(&mut self, len: usize) -> Option<Duration> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        self.sent += len as u64;
+
+        let paced = self.sent.saturating_sub(self.burst);
+        let due = Duration::from_secs_f64(paced as f64 / self.bytes_per_sec as f64);
+        due.checked_sub(started.elapsed()).filter(|d| !d.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    const MB: u64 = 1024 * 1024;
+
+    /// Send `total` bytes in 64KB chunks, sleeping as the pacer asks
+    async fn transfer(pacer: &mut ClientPacer, total: u64) -> Duration {
+        let start = Instant::now();
+        let mut remaining = total;
+        while remaining > 0 {
+            let chunk = remaining.min(64 * 1024);
+            remaining -= chunk;
+            if let Some(delay) = pacer.on_chunk(chunk as usize) {
+                tokio::time::sleep(delay).await;
+            }
+        }
+        start.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paces_to_target_rate() {
+        let mut pacer = ClientPacer::new(MB);
+        let elapsed = transfer(&mut pacer, 10 * MB).await;
+
+        // 10MB at 1MB/s, less the 100KB burst
+        assert!(
+            elapsed >= Duration::from_millis(9800) && elapsed <= Duration::from_millis(10100),
+            "elapsed {:?}",
+            elapsed
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_not_delayed() {
+        let mut pacer = ClientPacer::new(MB);
+        assert_eq!(pacer.on_chunk(64 * 1024), None);
+        assert!(pacer.on_chunk(256 * 1024).is_some());
+    }
+
+    #[test]
+    fn test_split_bounds_chunks() {
+        let data = Bytes::from(vec![7u8; PACED_CHUNK_SIZE * 2 + 10]);
+        let pieces: Vec<Bytes> = ClientPacer::split(&data).collect();
+        assert_eq!(
+            pieces.iter().map(Bytes::len).collect::<Vec<_>>(),
+            vec![PACED_CHUNK_SIZE, PACED_CHUNK_SIZE, 10]
+        );
+        assert_eq!(ClientPacer::split(&Bytes::new()).count(), 0);
+    }
+
+    #[test]
+    fn test_unlimited_has_no_pacer() {
+        assert!(ClientPacer::for_request(None, true, &HeaderMap::new()).is_none());
+        assert!(ClientPacer::for_request(Some(0), false, &HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_header_override_requires_trust() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CLIENT_RATE_LIMIT_HEADER, HeaderValue::from_static("2048"));
+
+        let pacer = ClientPacer::for_request(Some(MB), true, &headers).unwrap();
+        assert_eq!(pacer.bytes_per_sec(), 2048);
+
+        let pacer = ClientPacer::for_request(Some(MB), false, &headers).unwrap();
+        assert_eq!(pacer.bytes_per_sec(), MB);
+
+        headers.insert(CLIENT_RATE_LIMIT_HEADER, HeaderValue::from_static("0"));
+        assert!(ClientPacer::for_request(Some(MB), true, &headers).is_none());
+    }
+}
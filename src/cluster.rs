@@ -0,0 +1,214 @@
+//! Consistent-hash routing between pingora-slice nodes
+//!
+//! In cluster mode every node hashes the request URL onto a ring of peers.
+//! The owning peer fetches and caches the object; the other nodes forward
+//! requests for it to the owner, so each object is cached once across the
+//! cluster instead of once per node. With `local_copy` the other nodes
+//! keep a copy too: they slice the request themselves and fill the slices
+//! they miss from the owner rather than the origin.
+
+use crate::config::{SliceConfig, UpstreamPolicy};
+use crate::upstream::UpstreamPool;
+use http::HeaderMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Header marking a request that was already forwarded by a cluster node
+///
+/// A request carrying it is never forwarded again, so a key that the nodes
+/// disagree on bounces at most once before going to the origin.
+pub const CLUSTER_HOP_HEADER: &str = "x-slice-cluster-hop";
+
+/// Points placed on the ring per peer, to spread keys evenly
+const VIRTUAL_NODES: usize = 128;
+
+/// Consistent-hash ring over a set of peers
+#[derive(Debug, Clone)]
+pub struct HashRing {
+    ring: BTreeMap<u64, usize>,
+    peers: Vec<String>,
+}
+
+impl HashRing {
+    /// Build a ring over `peers`
+    ///
+    /// The hash is fixed (FNV-1a), so every node builds the same ring from
+    /// the same peer list regardless of build or platform.
+    pub fn new(peers: &[String]) -> Self {
+        let mut ring = BTreeMap::new();
+        for (idx, peer) in peers.iter().enumerate() {
+            for vnode in 0..VIRTUAL_NODES {
+                ring.insert(hash(format!("{}#{}", peer, vnode).as_bytes()), idx);
+            }
+        }
+        HashRing {
+            ring,
+            peers: peers.to_vec(),
+        }
+    }
+
+    /// Peer that owns `key`, or `None` if the ring is empty
+    pub fn owner(&self, key: &str) -> Option<&str> {
+        let point = hash(key.as_bytes());
+        self.ring
+            .range(point..)
+            .next()
+            .or_else(|| self.ring.iter().next())
+            .map(|(_, &idx)| self.peers[idx].as_str())
+    }
+
+    /// Peers on the ring
+    pub fn peers(&self) -> &[String] {
+        &self.peers
+    }
+}
+
+/// FNV-1a followed by a 64-bit finalizer so similar keys spread out
+fn hash(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+/// Routing decisions for one node of the cluster
+#[derive(Debug, Clone)]
+pub struct ClusterRouter {
+    ring: HashRing,
+    self_peer: String,
+    local_copy: bool,
+    /// With `local_copy`, the pool each other peer is filled from
+    fill_pools: HashMap<String, Arc<UpstreamPool>>,
+}
+
+impl ClusterRouter {
+    /// Create a router from the `cluster` section
+    ///
+    /// # Returns
+    /// `None` if cluster mode is not configured
+    pub fn from_config(config: &SliceConfig) -> Option<Self> {
+        let cluster = config.cluster.as_ref()?;
+        // A pool of one peer never takes it out of rotation
+        let fill_pools = cluster
+            .peers
+            .iter()
+            .filter(|peer| cluster.local_copy && **peer != cluster.self_peer)
+            .map(|peer| {
+                let pool = UpstreamPool::new(
+                    std::slice::from_ref(peer),
+                    UpstreamPolicy::default(),
+                    u32::MAX,
+                    Duration::ZERO,
+                );
+                (peer.clone(), Arc::new(pool))
+            })
+            .collect();
+        Some(ClusterRouter {
+            ring: HashRing::new(&cluster.peers),
+            self_peer: cluster.self_peer.clone(),
+            local_copy: cluster.local_copy,
+            fill_pools,
+        })
+    }
+
+    /// Peer the request for `key` should be forwarded to
+    ///
+    /// # Returns
+    /// `None` if this node owns the key or the request was already forwarded
+    pub fn route(&self, key: &str, headers: &HeaderMap) -> Option<&str> {
+        if headers.contains_key(CLUSTER_HOP_HEADER) {
+            return None;
+        }
+        self.ring.owner(key).filter(|owner| *owner != self.self_peer)
+    }
+
+    /// This node's address on the ring
+    pub fn self_peer(&self) -> &str {
+        &self.self_peer
+    }
+
+    /// Whether objects owned by other peers are also cached here, filled
+    /// from their owner, instead of being forwarded
+    pub fn local_copy(&self) -> bool {
+        self.local_copy
+    }
+
+    /// Pool to fill objects owned by `peer` from, with `local_copy`
+    pub fn fill_pool(&self, peer: &str) -> Option<&Arc<UpstreamPool>> {
+        self.fill_pools.get(peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    use std::collections::HashMap;
+
+    fn peers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_keys_spread_across_peers() {
+        let ring = HashRing::new(&peers(&["10.0.0.1:8080", "10.0.0.2:8080", "10.0.0.3:8080"]));
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for i in 0..3000 {
+            *counts.entry(ring.owner(&format!("http://origin/video/{}.mp4", i)).unwrap()).or_default() += 1;
+        }
+
+        assert_eq!(counts.len(), 3);
+        for count in counts.values() {
+            assert!(*count > 700 && *count < 1300, "uneven distribution: {:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_removing_peer_only_moves_its_keys() {
+        let full = HashRing::new(&peers(&["a", "b", "c"]));
+        let reduced = HashRing::new(&peers(&["a", "b"]));
+
+        for i in 0..1000 {
+            let key = format!("/file/{}", i);
+            let before = full.owner(&key).unwrap();
+            if before != "c" {
+                assert_eq!(reduced.owner(&key).unwrap(), before);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_ring_has_no_owner() {
+        assert!(HashRing::new(&[]).owner("/file").is_none());
+    }
+
+    #[test]
+    fn test_hop_header_prevents_forwarding() {
+        let config = SliceConfig {
+            cluster: Some(crate::config::ClusterConfig {
+                peers: peers(&["a", "b"]),
+                self_peer: "b".to_string(),
+                local_copy: false,
+            }),
+            ..Default::default()
+        };
+        let router = ClusterRouter::from_config(&config).unwrap();
+        let key = (0..)
+            .map(|i| format!("/file/{}", i))
+            .find(|key| router.ring.owner(key) == Some("a"))
+            .unwrap();
+
+        assert_eq!(router.route(&key, &HeaderMap::new()), Some("a"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CLUSTER_HOP_HEADER, HeaderValue::from_static("b"));
+        assert_eq!(router.route(&key, &headers), None);
+    }
+}
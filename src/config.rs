@@ -2,17 +2,31 @@
 
 use crate::error::{Result, SliceError};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
 /// Configuration for the Slice module
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SliceConfig {
     /// Size of each slice in bytes (default: 1MB)
     /// Valid range: 64KB to 10MB
     #[serde(default = "default_slice_size")]
     pub slice_size: usize,
 
+    /// Files smaller than this many bytes are proxied without slicing
+    /// (default: 0, slice every file)
+    #[serde(default)]
+    pub min_slice_file_size: u64,
+
+    /// Slice client requests carrying a single `Range` (default: true)
+    ///
+    /// Only the slices overlapping the range are fetched and the response is
+    /// trimmed to the requested bytes. Multi-range requests are always proxied.
+    #[serde(default = "default_true")]
+    pub slice_client_range_requests: bool,
+
     /// Maximum number of concurrent subrequests (default: 4)
     #[serde(default = "default_max_concurrent")]
     pub max_concurrent_subrequests: usize,
@@ -21,10 +35,64 @@ pub struct SliceConfig {
     #[serde(default = "default_max_retries")]
     pub max_retries: usize,
 
+    /// HTTP version of slice subrequests (default: `auto`)
+    #[serde(default)]
+    pub origin_protocol: OriginProtocol,
+
+    /// Idle connections kept open per origin host for slice subrequests
+    /// (default: 10)
+    #[serde(default = "default_origin_pool_size")]
+    pub origin_pool_size: usize,
+
+    /// Retries shared by all slices of a request; a request needing more
+    /// fails with a 504 (default: only `max_retries` per slice)
+    #[serde(default)]
+    pub request_retry_budget: Option<usize>,
+
+    /// Failed slice fetch attempts after which a request stops using range
+    /// requests and fetches the whole object in one GET (default: never)
+    #[serde(default)]
+    pub whole_object_fallback_after: Option<usize>,
+
+    /// Most bytes the whole-object fallback may read from the origin; a
+    /// request needing more fails instead (default: unlimited)
+    #[serde(default)]
+    pub whole_object_fallback_max_bytes: Option<u64>,
+
+    /// Milliseconds a request may spend fetching slices from the origin,
+    /// retries included, before failing with a 504 (default: unlimited)
+    #[serde(default)]
+    pub request_deadline_ms: Option<u64>,
+
+    /// Seconds a client request may take from arrival until its response
+    /// is ready, and until a proxied body has been relayed; after that it
+    /// is answered with a 504 or its body is cut off (default: unlimited)
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+
     /// URL patterns that should enable slicing (regex patterns)
     #[serde(default)]
     pub slice_patterns: Vec<String>,
 
+    /// URL patterns that enable slicing with per-pattern overrides
+    ///
+    /// Rules are matched in order and the first match wins. A URL matching a
+    /// rule is eligible for slicing just like one matching `slice_patterns`.
+    #[serde(default)]
+    pub pattern_rules: Vec<PatternRule>,
+
+    /// Per-pattern choice between slicing, whole-object caching and no cache
+    ///
+    /// Rules are matched in order and the first match wins. URLs matching no
+    /// rule are handled as before: sliced when eligible, proxied otherwise.
+    #[serde(default)]
+    pub cache_mode_rules: Vec<CacheModeRule>,
+
+    /// Request header that forces a fresh origin fetch for one request
+    /// (optional)
+    #[serde(default)]
+    pub cache_bypass: Option<CacheBypassConfig>,
+
     /// Whether to enable caching (default: true)
     #[serde(default = "default_true")]
     pub enable_cache: bool,
@@ -33,6 +101,22 @@ pub struct SliceConfig {
     #[serde(default = "default_cache_ttl")]
     pub cache_ttl: u64,
 
+    /// Remove expired cache entries every this many seconds (default: none)
+    ///
+    /// Without a sweep, expired entries keep their memory until they are
+    /// evicted to make room for new ones.
+    #[serde(default)]
+    pub cache_sweep_interval_secs: Option<u64>,
+
+    /// Serve cached slices up to this many seconds past their TTL when the
+    /// origin fails (default: 0, never)
+    ///
+    /// Like `Cache-Control: stale-if-error`: a sliced request whose metadata
+    /// or slices fail with a 5xx, timeout or connection error is answered
+    /// from the expired copies instead, reported as `STALE`.
+    #[serde(default)]
+    pub stale_if_error_secs: u64,
+
     /// L1 (memory) cache size in bytes (default: 100MB)
     #[serde(default = "default_l1_cache_size")]
     pub l1_cache_size_bytes: usize,
@@ -45,10 +129,50 @@ pub struct SliceConfig {
     #[serde(default = "default_true")]
     pub enable_l2_cache: bool,
 
+    /// When a failing L2 disk is bypassed and probed for recovery
+    #[serde(default)]
+    pub l2_health: L2HealthConfig,
+
+    /// Store updated versions of L2 entries as diffs
+    #[serde(default)]
+    pub l2_delta: L2DeltaConfig,
+
     /// Upstream server address
     #[serde(default = "default_upstream")]
     pub upstream_address: String,
 
+    /// Address the proxy server listens on (default: "0.0.0.0:8080")
+    #[serde(default = "default_listen_address")]
+    pub listen_address: String,
+
+    /// Worker threads of the proxy server (default: one per CPU)
+    #[serde(default)]
+    pub threads: Option<usize>,
+
+    /// File the proxy server writes its process ID to while running
+    /// (optional)
+    #[serde(default)]
+    pub pid_file: Option<String>,
+
+    /// Detach from the terminal and run the proxy server in the background
+    /// (default: false)
+    #[serde(default)]
+    pub daemon: bool,
+
+    /// Socket options applied to accepted client connections
+    #[serde(default)]
+    pub socket: SocketConfig,
+
+    /// Maximum bytes of request header lines the standalone server accepts;
+    /// larger requests are answered with a 431 (default: unlimited)
+    #[serde(default)]
+    pub max_request_header_bytes: Option<usize>,
+
+    /// Maximum length in bytes of the request URI the standalone server
+    /// accepts; longer URIs are answered with a 414 (default: unlimited)
+    #[serde(default)]
+    pub max_uri_bytes: Option<usize>,
+
     /// Metrics endpoint configuration (optional)
     #[serde(default)]
     pub metrics_endpoint: Option<MetricsEndpointConfig>,
@@ -56,10 +180,315 @@ pub struct SliceConfig {
     /// Purge configuration (optional)
     #[serde(default)]
     pub purge: Option<PurgeConfig>,
+
+    /// How file metadata is probed on the origin (default: auto)
+    #[serde(default)]
+    pub metadata_probe: MetadataProbe,
+
+    /// HEAD response statuses that trigger the ranged GET fallback in `auto` mode
+    /// (default: 403, 405, 501)
+    #[serde(default = "default_metadata_probe_fallback_statuses")]
+    pub metadata_probe_fallback_statuses: Vec<u16>,
+
+    /// How long fetched file metadata is cached, in seconds
+    /// (default: same as cache_ttl, 0 disables metadata caching)
+    #[serde(default)]
+    pub metadata_cache_ttl: Option<u64>,
+
+    /// Maximum number of URLs to cache metadata for (default: 10000)
+    #[serde(default = "default_metadata_cache_max_entries")]
+    pub metadata_cache_max_entries: usize,
+
+    /// Access log configuration (optional)
+    #[serde(default)]
+    pub access_log: Option<AccessLogConfig>,
+
+    /// Maximum bytes per second read from the origin by slice subrequests
+    /// (default: unlimited)
+    #[serde(default)]
+    pub origin_max_bytes_per_sec: Option<u64>,
+
+    /// Maximum slice subrequests per second sent to the origin
+    /// (default: unlimited)
+    #[serde(default)]
+    pub origin_max_requests_per_sec: Option<u64>,
+
+    /// Subrequest concurrency slow-start configuration (optional)
+    #[serde(default)]
+    pub slow_start: Option<SlowStartConfig>,
+
+    /// Maximum download speed per client connection in bytes per second
+    /// (default: unlimited)
+    #[serde(default)]
+    pub client_max_bytes_per_sec: Option<u64>,
+
+    /// Whether to honor a per-request `X-RateLimit-Bps` header set by a
+    /// trusted front end (default: false)
+    #[serde(default)]
+    pub trust_rate_limit_header: bool,
+
+    /// Per-client-IP request and bandwidth limits (optional)
+    #[serde(default)]
+    pub client_rate_limit: Option<ClientRateLimitConfig>,
+
+    /// Join proxied response chunks into client writes of up to this many
+    /// bytes (default: chunks are written as received)
+    #[serde(default)]
+    pub response_buffer_size: Option<usize>,
+
+    /// Longest time in milliseconds buffered response bytes wait for more
+    /// data before being written (default: 10)
+    #[serde(default = "default_response_buffer_flush_ms")]
+    pub response_buffer_flush_ms: u64,
+
+    /// What to do with outstanding slice fetches when the client disconnects
+    /// (default: abort)
+    #[serde(default)]
+    pub on_client_abort: ClientAbortPolicy,
+
+    /// Concurrent subrequests used by each background cache fill (default: 2)
+    #[serde(default = "default_background_fill_concurrency")]
+    pub background_fill_concurrency: usize,
+
+    /// Maximum number of background cache fills running at once (default: 4)
+    #[serde(default = "default_max_background_fills")]
+    pub max_background_fills: usize,
+
+    /// Slices past the end of a range request from the start of an uncached
+    /// file to fetch and cache in the background (default: 0, disabled)
+    #[serde(default)]
+    pub prefetch_slices: usize,
+
+    /// Maximum number of requests filling the cache from the origin at once;
+    /// further misses are proxied without caching (default: unlimited)
+    #[serde(default)]
+    pub max_concurrent_fills: Option<usize>,
+
+    /// Maximum bytes buffered by in-flight cache fills across all requests;
+    /// a miss that would exceed it is proxied without caching
+    /// (default: unlimited)
+    #[serde(default)]
+    pub max_fill_buffer_bytes: Option<u64>,
+
+    /// Maximum bytes a single miss may buffer; a larger miss is proxied
+    /// without caching (default: unlimited)
+    #[serde(default)]
+    pub max_request_fill_bytes: Option<u64>,
+
+    /// Order in which slice subrequests are started (default: parallel)
+    #[serde(default)]
+    pub fetch_order: FetchOrder,
+
+    /// Request headers an origin's Vary header may name for the response to
+    /// be cached (default: accept-encoding, accept-language)
+    #[serde(default = "default_vary_headers")]
+    pub vary_headers: Vec<String>,
+
+    /// Response headers that keep a response out of the cache, on top of
+    /// `Set-Cookie`, `Authorization` and `Cache-Control: private`/`no-store`
+    #[serde(default)]
+    pub uncacheable_response_headers: Vec<String>,
+
+    /// Origin statuses whose slices are cached, and their TTLs (default:
+    /// 200 and 206, for `cache_ttl`)
+    #[serde(default)]
+    pub cache_admission: CacheAdmissionConfig,
+
+    /// Cluster mode configuration for sharing one cache tier (optional)
+    #[serde(default)]
+    pub cluster: Option<ClusterConfig>,
+
+    /// Per-origin-host byte quotas for the slice cache (optional)
+    #[serde(default)]
+    pub origin_quotas: Option<OriginQuotaConfig>,
+
+    /// Thresholds for the `/health` readiness check
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    /// Several origins to spread requests over, used instead of
+    /// `upstream_address` when set (optional)
+    #[serde(default)]
+    pub upstream_pool: Option<UpstreamPoolConfig>,
+
+    /// OpenTelemetry trace export, used with the `otel` feature (optional)
+    #[serde(default)]
+    pub tracing: Option<TracingConfig>,
+
+    /// How request URLs are normalized into cache keys (default: as-is)
+    #[serde(default)]
+    pub cache_key_policy: CacheKeyPolicy,
+
+    /// Header rewrite rules for origin requests and client responses,
+    /// applied in order (default: none)
+    #[serde(default)]
+    pub header_rules: Vec<HeaderRule>,
+
+    /// `User-Agent` sent with origin requests, replacing the client's
+    /// (default: the client's, none for subrequests)
+    #[serde(default)]
+    pub upstream_user_agent: Option<String>,
+
+    /// Forwarding headers added to origin requests (default: none)
+    #[serde(default)]
+    pub forwarded_headers: ForwardedHeadersConfig,
+
+    /// `Host` header of origin requests (default: `upstream`)
+    #[serde(default)]
+    pub host_header: HostHeaderMode,
+
+    /// HMAC signature added to metadata probes and slice subrequests
+    /// (optional)
+    #[serde(default)]
+    pub origin_signing: Option<OriginSigningConfig>,
+
+    /// Static bodies sent to the client when a request fails with a server
+    /// error, e.g. the origin is down (default: none)
+    #[serde(default)]
+    pub error_pages: Vec<ErrorPageConfig>,
+}
+
+/// Prefix of environment variables that override configuration values
+pub const ENV_PREFIX: &str = "PINGORA_SLICE_";
+
+/// Replaces secrets in [`SliceConfig::to_yaml`]
+const REDACTED: &str = "<redacted>";
+
+/// Headers whose values in `header_rules` are treated as secrets
+const SECRET_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+    "x-auth-token",
+];
+
+/// Maximum number of entries in `vary_headers`
+pub const MAX_VARY_HEADERS: usize = 4;
+
+/// Fields that only take effect when the proxy starts
+///
+/// They size the caches, bind sockets or configure state shared by every
+/// request, so a configuration reload keeps their running values.
+pub const RESTART_REQUIRED_FIELDS: [&str; 31] = [
+    "slice_size",
+    "origin_protocol",
+    "origin_pool_size",
+    "cache_sweep_interval_secs",
+    "stale_if_error_secs",
+    "l1_cache_size_bytes",
+    "l2_cache_dir",
+    "enable_l2_cache",
+    "l2_health",
+    "l2_delta",
+    "listen_address",
+    "threads",
+    "pid_file",
+    "daemon",
+    "socket",
+    "metrics_endpoint",
+    "metadata_cache_ttl",
+    "metadata_cache_max_entries",
+    "access_log",
+    "origin_max_bytes_per_sec",
+    "origin_max_requests_per_sec",
+    "slow_start",
+    "client_rate_limit",
+    "max_background_fills",
+    "max_concurrent_fills",
+    "max_fill_buffer_bytes",
+    "cluster",
+    "origin_quotas",
+    "health",
+    "upstream_pool",
+    "tracing",
+];
+
+/// Per-URL-pattern settings that override the global configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternRule {
+    /// URL pattern (same syntax as `slice_patterns`)
+    pub pattern: String,
+
+    /// Name used as the `pattern` label of per-pattern metrics
+    /// (default: the pattern itself)
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Cache TTL in seconds for slices of matching URLs (default: global cache_ttl)
+    #[serde(default)]
+    pub cache_ttl: Option<u64>,
+}
+
+/// How requests matching a cache mode rule use the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    /// Fetch the object in slices and cache each slice
+    #[default]
+    Slice,
+    /// Fetch the object with one plain GET, never a range request, and cache it
+    Whole,
+    /// Proxy to the origin without looking up or storing anything in the cache
+    Bypass,
+}
+
+/// Cache mode for URLs matching a pattern
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheModeRule {
+    /// URL pattern (same syntax as `slice_patterns`)
+    pub pattern: String,
+
+    /// How matching requests use the cache
+    pub mode: CacheMode,
+}
+
+/// Request header letting a client skip the cache lookup
+///
+/// The object is fetched from the origin and stored again, so later
+/// requests see the fresh copy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheBypassConfig {
+    /// Header name; a value of `1` or `true` bypasses the cache
+    /// (default: x-cache-bypass)
+    #[serde(default = "default_cache_bypass_header")]
+    pub header: String,
+
+    /// Only honor the header on requests carrying the purge token
+    /// (default: true). Without it any client can force origin fetches, so
+    /// turning it off is an explicit opt-out
+    #[serde(default = "default_true")]
+    pub require_token: bool,
+}
+
+impl Default for CacheBypassConfig {
+    fn default() -> Self {
+        Self {
+            header: default_cache_bypass_header(),
+            require_token: true,
+        }
+    }
+}
+
+/// Strategy used to fetch file metadata from the origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataProbe {
+    /// Only send HEAD requests
+    Head,
+    /// Only send `GET` with `Range: bytes=0-0`
+    GetRange,
+    /// Send HEAD, falling back to a ranged GET when the origin rejects HEAD
+    #[default]
+    Auto,
 }
 
 /// Configuration for the metrics HTTP endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MetricsEndpointConfig {
     /// Whether to enable the metrics endpoint (default: false)
     #[serde(default)]
@@ -68,218 +497,2329 @@ pub struct MetricsEndpointConfig {
     /// Address to bind the metrics endpoint to (default: "127.0.0.1:9090")
     #[serde(default = "default_metrics_address")]
     pub address: String,
+
+    /// Also export request, cache and byte counters labeled by the matched
+    /// pattern rule or slice pattern (default: false)
+    #[serde(default)]
+    pub pattern_labels: bool,
+
+    /// Path the Prometheus metrics are served at (default: "/metrics")
+    #[serde(default = "default_metrics_path")]
+    pub path: String,
+
+    /// Require HTTP basic auth for the metrics path (default: none)
+    #[serde(default)]
+    pub basic_auth: Option<BasicAuthConfig>,
 }
 
-/// Configuration for cache purge functionality
+/// Credentials for HTTP basic auth
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BasicAuthConfig {
+    /// User name; may not contain ':'
+    pub username: String,
+
+    /// Password
+    pub password: String,
+}
+
+/// Configuration for the per-request access log
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PurgeConfig {
-    /// Whether to enable purge functionality (default: false)
+#[serde(deny_unknown_fields)]
+pub struct AccessLogConfig {
+    /// Whether to write an access log (default: false)
     #[serde(default)]
     pub enabled: bool,
 
-    /// Authentication token for purge requests (optional)
-    /// If not set, purge requests will not require authentication
-    pub auth_token: Option<String>,
+    /// File to append records to (default: stdout)
+    #[serde(default)]
+    pub path: Option<String>,
 
-    /// Whether to enable Prometheus metrics for purge operations (default: true)
-    #[serde(default = "default_true")]
-    pub enable_metrics: bool,
-}
+    /// Record format (default: json)
+    #[serde(default)]
+    pub format: AccessLogFormat,
 
-impl Default for MetricsEndpointConfig {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            address: default_metrics_address(),
-        }
-    }
+    /// Number of records buffered before new ones are dropped (default: 8192)
+    #[serde(default = "default_access_log_buffer_size")]
+    pub buffer_size: usize,
 }
 
-// Default value functions for serde
-fn default_slice_size() -> usize {
-    1024 * 1024 // 1MB
+/// What happens to outstanding slice fetches when the client disconnects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAbortPolicy {
+    /// Cancel every outstanding subrequest
+    #[default]
+    Abort,
+    /// Fetch and cache the remaining slices in a background task
+    CompleteFill,
+    /// Let in-flight subrequests finish and cache them, start no new ones
+    CompleteCurrentSlice,
 }
 
-fn default_max_concurrent() -> usize {
-    4
+/// Order in which the slices of a request are fetched from the origin
+///
+/// Earlier slices are what a progressive client needs first, so fetching
+/// them ahead of the rest shortens the time until playback can start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchOrder {
+    /// Start every slice at once, up to `max_concurrent_subrequests`
+    #[default]
+    Parallel,
+    /// Fetch the first N slices one at a time, then the rest in parallel
+    SequentialFirstN(usize),
+    /// Start slices strictly in order as concurrency permits free up
+    Priority,
 }
 
-fn default_max_retries() -> usize {
-    3
-}
+/// Configuration for running several nodes as one consistent-hash cache tier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClusterConfig {
+    /// Addresses of all nodes in the cluster, including this one
+    pub peers: Vec<String>,
 
-fn default_true() -> bool {
-    true
+    /// This node's address as it appears in `peers`
+    #[serde(rename = "self")]
+    pub self_peer: String,
+
+    /// Also cache objects owned by other peers here, filling them from their
+    /// owner instead of forwarding the request (default: false)
+    #[serde(default)]
+    pub local_copy: bool,
 }
 
-fn default_cache_ttl() -> u64 {
-    3600 // 1 hour
+/// Cache byte quotas per origin host
+///
+/// Hosts are matched against the authority of the request URL, including
+/// the port when the URL has one (e.g. `videos.example.com` or
+/// `10.0.0.5:8080`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OriginQuotaConfig {
+    /// Quota in bytes for hosts not listed in `hosts` (default: unlimited)
+    #[serde(default)]
+    pub default_bytes: Option<usize>,
+
+    /// Quota in bytes for individual origin hosts
+    #[serde(default)]
+    pub hosts: HashMap<String, usize>,
 }
 
-fn default_l1_cache_size() -> usize {
-    100 * 1024 * 1024 // 100MB
+/// How the next origin is picked from an upstream pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamPolicy {
+    /// Rotate through the peers in order
+    #[default]
+    RoundRobin,
+    /// Pick the peer with the fewest requests in flight
+    LeastConnections,
+    /// Always use the first healthy peer in list order
+    Failover,
 }
 
-fn default_l2_cache_dir() -> String {
-    "/var/cache/pingora-slice".to_string()
+/// Configuration for a pool of origin servers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamPoolConfig {
+    /// Origin addresses (`host:port`); for `failover` the first is the primary
+    pub peers: Vec<String>,
+
+    /// Selection policy (default: round_robin)
+    #[serde(default)]
+    pub policy: UpstreamPolicy,
+
+    /// Consecutive failures before a peer is taken out of rotation
+    /// (default: 3)
+    #[serde(default = "default_upstream_max_failures")]
+    pub max_failures: u32,
+
+    /// Seconds a failed peer stays out of rotation before it is tried
+    /// again (default: 30)
+    #[serde(default = "default_upstream_cooldown_secs")]
+    pub cooldown_secs: u64,
 }
 
-fn default_upstream() -> String {
-    "127.0.0.1:8080".to_string()
+/// Normalization applied to request URLs before they are used as cache keys
+///
+/// Only cache keys are affected; the origin always receives the URL the
+/// client sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheKeyPolicy {
+    /// Drop query parameters, except those in `keep_query_params`
+    /// (default: false)
+    #[serde(default)]
+    pub strip_query: bool,
+
+    /// Query parameters kept by `strip_query`, sorted by name in the key
+    #[serde(default)]
+    pub keep_query_params: Vec<String>,
+
+    /// Lowercase the URL path (default: false)
+    #[serde(default)]
+    pub lowercase_path: bool,
+
+    /// Drop a `#fragment` (default: false)
+    #[serde(default)]
+    pub strip_fragment: bool,
 }
 
-fn default_metrics_address() -> String {
-    "127.0.0.1:9090".to_string()
+/// Which origin responses are cached, by status
+///
+/// Slice subrequests see `206 Partial Content`, or `200 OK` from an origin
+/// answering with the whole object; anything else fails the fill, so only
+/// those two can be admitted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheAdmissionConfig {
+    /// Statuses whose responses are cached (default: 200, 206)
+    #[serde(default = "default_admitted_statuses")]
+    pub statuses: Vec<u16>,
+
+    /// Cache TTL in seconds per status, overriding `cache_ttl` and pattern
+    /// rule TTLs (default: none)
+    #[serde(default)]
+    pub status_ttls: BTreeMap<u16, u64>,
 }
 
-impl Default for SliceConfig {
+impl Default for CacheAdmissionConfig {
     fn default() -> Self {
-        SliceConfig {
-            slice_size: default_slice_size(),
-            max_concurrent_subrequests: default_max_concurrent(),
-            max_retries: default_max_retries(),
-            slice_patterns: Vec::new(),
-            enable_cache: default_true(),
-            cache_ttl: default_cache_ttl(),
-            l1_cache_size_bytes: default_l1_cache_size(),
-            l2_cache_dir: default_l2_cache_dir(),
-            enable_l2_cache: default_true(),
-            upstream_address: default_upstream(),
-            metrics_endpoint: None,
-            purge: None,
+        CacheAdmissionConfig {
+            statuses: default_admitted_statuses(),
+            status_ttls: BTreeMap::new(),
         }
     }
 }
 
-impl SliceConfig {
-    /// Load configuration from a YAML file
-    ///
-    /// # Arguments
-    /// * `path` - Path to the YAML configuration file
-    ///
-    /// # Returns
-    /// * `Ok(SliceConfig)` if loading and validation succeed
-    /// * `Err(SliceError)` if file cannot be read or config is invalid
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let content = fs::read_to_string(path.as_ref()).map_err(|e| {
-            SliceError::ConfigError(format!("Failed to read config file: {}", e))
-        })?;
+/// Change a header rule makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderAction {
+    /// Append a value, keeping existing ones
+    Add,
+    /// Replace all values with one
+    Set,
+    /// Drop all values
+    Remove,
+}
 
-        let config: SliceConfig = serde_yaml::from_str(&content).map_err(|e| {
-            SliceError::ConfigError(format!("Failed to parse config file: {}", e))
-        })?;
+/// Headers a header rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderTarget {
+    /// Requests sent to the origin, including slice subrequests and
+    /// metadata probes
+    UpstreamRequest,
+    /// Responses sent to the client
+    Response,
+    /// Both of the above
+    Both,
+}
 
-        config.validate()?;
-        Ok(config)
-    }
+/// A single header rewrite rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderRule {
+    /// Headers the rule applies to
+    pub apply_to: HeaderTarget,
 
-    /// Validate the configuration
-    ///
-    /// # Returns
-    /// * `Ok(())` if configuration is valid
-    /// * `Err(SliceError)` if any validation fails
-    ///
-    /// # Validation Rules
-    /// - slice_size must be between 64KB and 10MB
-    /// - max_concurrent_subrequests must be > 0
-    /// - max_retries must be >= 0
-    /// - cache_ttl must be > 0
-    pub fn validate(&self) -> Result<()> {
-        const MIN_SLICE_SIZE: usize = 64 * 1024; // 64KB
-        const MAX_SLICE_SIZE: usize = 10 * 1024 * 1024; // 10MB
+    pub action: HeaderAction,
+
+    /// Header name
+    pub name: String,
+
+    /// Header value, required for `add` and `set`
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// How forwarding headers combine with values sent by the client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForwardedMode {
+    /// Extend the values of a downstream proxy, for chained proxies
+    #[default]
+    Append,
+    /// Replace any values sent by the client
+    Set,
+}
+
+/// HTTP version slice subrequests use
+///
+/// Connections are pooled per origin host and reused across slices and
+/// requests whatever the version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OriginProtocol {
+    /// HTTP/2 when a TLS origin offers it, HTTP/1.1 keep-alive otherwise
+    #[default]
+    Auto,
+    /// HTTP/1.1 keep-alive only
+    Http1,
+    /// HTTP/2 only, also over plain HTTP (h2c with prior knowledge)
+    Http2,
+}
+
+/// Which `Host` header origin requests carry
+///
+/// Applies to the metadata probe, the slice subrequests and proxied
+/// requests alike, so they all reach the same virtual host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostHeaderMode {
+    /// The host of the upstream address the request is sent to
+    #[default]
+    Upstream,
+    /// The `Host` the client sent, for origins serving several virtual
+    /// hosts from one address; cache keys then include that host
+    Preserve,
+}
+
+/// Keyed hash an origin request signature is computed with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningAlgorithm {
+    /// HMAC-SHA256
+    #[default]
+    HmacSha256,
+    /// HMAC-SHA512
+    HmacSha512,
+}
+
+/// Part of an origin request covered by its signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignedComponent {
+    /// Request method, e.g. `GET`
+    Method,
+    /// `Host` header, or the host and port of the URL without one
+    Host,
+    /// URL path, as sent
+    Path,
+    /// URL query string without the `?`, empty without one
+    Query,
+    /// `Range` header, empty without one
+    Range,
+}
+
+/// HMAC signing of requests sent to the origin for sliced files
+///
+/// The signed components are joined with newlines, in the order listed,
+/// and the lowercase hex HMAC of the result is sent in `header`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OriginSigningConfig {
+    /// Shared secret the signature is keyed with
+    pub secret: String,
+
+    /// Header carrying the signature (default: x-signature)
+    #[serde(default = "default_signing_header")]
+    pub header: String,
+
+    /// Keyed hash used (default: hmac_sha256)
+    #[serde(default)]
+    pub algorithm: SigningAlgorithm,
+
+    /// Request parts signed, in order (default: method, path)
+    #[serde(default = "default_signed_components")]
+    pub components: Vec<SignedComponent>,
+}
+
+/// Forwarding headers describing the client to the origin
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForwardedHeadersConfig {
+    /// Send the client IP in `X-Forwarded-For` (default: false)
+    #[serde(default)]
+    pub x_forwarded_for: bool,
+
+    /// Send the client scheme in `X-Forwarded-Proto` (default: false)
+    #[serde(default)]
+    pub x_forwarded_proto: bool,
+
+    /// Send the client IP and scheme in `Forwarded` (RFC 7239) (default: false)
+    #[serde(default)]
+    pub forwarded: bool,
+
+    /// Whether values sent by the client are kept (default: append)
+    #[serde(default)]
+    pub mode: ForwardedMode,
+}
+
+impl ForwardedHeadersConfig {
+    /// Whether any header is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.x_forwarded_for || self.x_forwarded_proto || self.forwarded
+    }
+}
+
+/// Token-bucket limits applied to each client IP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientRateLimitConfig {
+    /// Requests per second each client may make
+    pub requests_per_sec: u64,
+
+    /// Requests a client may make at once (default: requests_per_sec)
+    #[serde(default)]
+    pub burst: Option<u64>,
+
+    /// Response bytes per second each client may download (default: unlimited)
+    #[serde(default)]
+    pub bytes_per_sec: Option<u64>,
+
+    /// Client IPs tracked at once; the least recently seen are forgotten
+    /// (default: 10000)
+    #[serde(default = "default_client_rate_limit_max_clients")]
+    pub max_clients: usize,
+}
+
+/// A static response body for requests failing with one status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ErrorPageConfig {
+    /// Status the page is served with, 500-599
+    pub status: u16,
+
+    /// File holding the body, read once at startup
+    pub path: String,
+
+    /// Content-Type of the body (default: text/html; charset=utf-8)
+    #[serde(default = "default_error_page_content_type")]
+    pub content_type: String,
+}
+
+/// OpenTelemetry trace export settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TracingConfig {
+    /// OTLP/gRPC collector endpoint (default: http://localhost:4317)
+    #[serde(default = "default_otlp_endpoint")]
+    pub otlp_endpoint: String,
+
+    /// Fraction of new traces to sample; requests continuing a trace
+    /// follow the caller's decision (default: 1.0)
+    #[serde(default = "default_sampling_ratio")]
+    pub sampling_ratio: f64,
+
+    /// Service name reported with every span (default: pingora-slice)
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        TracingConfig {
+            otlp_endpoint: default_otlp_endpoint(),
+            sampling_ratio: default_sampling_ratio(),
+            service_name: default_service_name(),
+        }
+    }
+}
+
+/// Socket options of accepted client connections
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SocketConfig {
+    /// Send small responses without waiting to fill a segment (default: true)
+    #[serde(default = "default_true")]
+    pub tcp_nodelay: bool,
+
+    /// Allow rebinding the listen address while old connections linger
+    /// (default: true)
+    #[serde(default = "default_true")]
+    pub so_reuseaddr: bool,
+
+    /// SO_SNDBUF in bytes (default: the system's)
+    #[serde(default)]
+    pub send_buffer_size: Option<usize>,
+
+    /// SO_RCVBUF in bytes (default: the system's)
+    #[serde(default)]
+    pub recv_buffer_size: Option<usize>,
+
+    /// Idle seconds before TCP keepalive probes are sent (default: off)
+    #[serde(default)]
+    pub keepalive_secs: Option<u64>,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        SocketConfig {
+            tcp_nodelay: true,
+            so_reuseaddr: true,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            keepalive_secs: None,
+        }
+    }
+}
+
+/// Thresholds used by the health checker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthConfig {
+    /// Seconds between upstream reachability probes (default: 10)
+    #[serde(default = "default_upstream_probe_interval_secs")]
+    pub upstream_probe_interval_secs: u64,
+
+    /// Milliseconds allowed for the upstream to accept a connection
+    /// (default: 1000)
+    #[serde(default = "default_upstream_probe_timeout_ms")]
+    pub upstream_probe_timeout_ms: u64,
+
+    /// Seconds of subrequest history the failure ratio is computed over
+    /// (default: 60)
+    #[serde(default = "default_error_rate_window_secs")]
+    pub error_rate_window_secs: u64,
+
+    /// Fraction of failed subrequests above which the proxy reports itself
+    /// unhealthy (default: 0.5)
+    #[serde(default = "default_max_subrequest_failure_ratio")]
+    pub max_subrequest_failure_ratio: f64,
+
+    /// Subrequests needed in the window before the failure ratio is judged
+    /// (default: 10)
+    #[serde(default = "default_min_subrequests_for_error_rate")]
+    pub min_subrequests_for_error_rate: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            upstream_probe_interval_secs: default_upstream_probe_interval_secs(),
+            upstream_probe_timeout_ms: default_upstream_probe_timeout_ms(),
+            error_rate_window_secs: default_error_rate_window_secs(),
+            max_subrequest_failure_ratio: default_max_subrequest_failure_ratio(),
+            min_subrequests_for_error_rate: default_min_subrequests_for_error_rate(),
+        }
+    }
+}
+
+/// Failure handling for the L2 disk cache
+///
+/// After `error_threshold` consecutive disk errors the cache serves from L1
+/// only, with misses going to the origin, and probes the disk every
+/// `probe_interval_secs` until it is writable again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct L2HealthConfig {
+    /// Consecutive disk errors before L2 is bypassed (default: 5)
+    #[serde(default = "default_l2_error_threshold")]
+    pub error_threshold: u64,
+
+    /// Seconds between recovery probes of a bypassed L2 (default: 30)
+    #[serde(default = "default_l2_probe_interval_secs")]
+    pub probe_interval_secs: u64,
+}
+
+impl Default for L2HealthConfig {
+    fn default() -> Self {
+        L2HealthConfig {
+            error_threshold: default_l2_error_threshold(),
+            probe_interval_secs: default_l2_probe_interval_secs(),
+        }
+    }
+}
+
+/// Diff storage for updated L2 entries
+///
+/// When enabled, L2 goes through a [`DeltaBackend`](crate::DeltaBackend):
+/// an entry of at least `min_size_bytes` stored again is written as a diff
+/// against its previous version, if the diff is at most `max_delta_ratio`
+/// times the new version's size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct L2DeltaConfig {
+    /// Whether to store updates as diffs (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Smallest entry that is diffed (default: 65536)
+    #[serde(default = "default_l2_delta_min_size_bytes")]
+    pub min_size_bytes: usize,
+
+    /// Largest diff worth keeping, as a fraction of the new version
+    /// (default: 0.5)
+    #[serde(default = "default_l2_delta_max_delta_ratio")]
+    pub max_delta_ratio: f64,
+}
+
+impl Default for L2DeltaConfig {
+    fn default() -> Self {
+        L2DeltaConfig {
+            enabled: false,
+            min_size_bytes: default_l2_delta_min_size_bytes(),
+            max_delta_ratio: default_l2_delta_max_delta_ratio(),
+        }
+    }
+}
+
+/// Configuration for ramping up subrequest concurrency on cold start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlowStartConfig {
+    /// Whether to ramp up concurrency (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Concurrent subrequests allowed before any have succeeded (default: 1)
+    #[serde(default = "default_slow_start_initial_concurrency")]
+    pub initial_concurrency: usize,
+
+    /// Seconds after which the full `max_concurrent_subrequests` is allowed
+    /// even without enough successes (default: 30, 0 ramps on successes only)
+    #[serde(default = "default_slow_start_warmup_secs")]
+    pub warmup_secs: u64,
+}
+
+/// Access log record format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLogFormat {
+    /// One JSON object per line
+    #[default]
+    Json,
+    /// Apache/nginx combined log format with slice fields appended
+    Combined,
+}
+
+/// Configuration for cache purge functionality
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PurgeConfig {
+    /// Whether to enable purge functionality (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Authentication token for purge requests (optional)
+    /// If not set, purge requests will not require authentication
+    pub auth_token: Option<String>,
+
+    /// Whether to enable Prometheus metrics for purge operations (default: true)
+    #[serde(default = "default_true")]
+    pub enable_metrics: bool,
+}
+
+impl Default for MetricsEndpointConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: default_metrics_address(),
+            pattern_labels: false,
+            path: default_metrics_path(),
+            basic_auth: None,
+        }
+    }
+}
+
+// Default value functions for serde
+fn default_slice_size() -> usize {
+    1024 * 1024 // 1MB
+}
+
+fn default_max_concurrent() -> usize {
+    4
+}
+
+fn default_origin_pool_size() -> usize {
+    10
+}
+
+fn default_response_buffer_flush_ms() -> u64 {
+    10
+}
+
+fn default_max_retries() -> usize {
+    3
+}
+
+fn default_signing_header() -> String {
+    "x-signature".to_string()
+}
+
+fn default_signed_components() -> Vec<SignedComponent> {
+    vec![SignedComponent::Method, SignedComponent::Path]
+}
+
+fn default_client_rate_limit_max_clients() -> usize {
+    10_000
+}
+
+fn default_error_page_content_type() -> String {
+    "text/html; charset=utf-8".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_cache_ttl() -> u64 {
+    3600 // 1 hour
+}
+
+fn default_l1_cache_size() -> usize {
+    100 * 1024 * 1024 // 100MB
+}
+
+fn default_l2_cache_dir() -> String {
+    "/var/cache/pingora-slice".to_string()
+}
+
+fn default_upstream() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+fn default_listen_address() -> String {
+    "0.0.0.0:8080".to_string()
+}
+
+fn default_l2_error_threshold() -> u64 {
+    crate::tiered_cache::DEFAULT_DISK_ERROR_THRESHOLD
+}
+
+fn default_l2_probe_interval_secs() -> u64 {
+    crate::tiered_cache::DEFAULT_DISK_PROBE_INTERVAL.as_secs()
+}
+
+fn default_l2_delta_min_size_bytes() -> usize {
+    crate::delta_backend::DEFAULT_MIN_SIZE
+}
+
+fn default_l2_delta_max_delta_ratio() -> f64 {
+    crate::delta_backend::DEFAULT_MAX_DELTA_RATIO
+}
+
+fn default_upstream_probe_interval_secs() -> u64 {
+    10
+}
+
+fn default_upstream_probe_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_error_rate_window_secs() -> u64 {
+    60
+}
+
+fn default_max_subrequest_failure_ratio() -> f64 {
+    0.5
+}
+
+fn default_min_subrequests_for_error_rate() -> u64 {
+    10
+}
+
+fn default_upstream_max_failures() -> u32 {
+    3
+}
+
+fn default_upstream_cooldown_secs() -> u64 {
+    30
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://localhost:4317".to_string()
+}
+
+fn default_sampling_ratio() -> f64 {
+    1.0
+}
+
+fn default_service_name() -> String {
+    "pingora-slice".to_string()
+}
+
+fn default_metrics_address() -> String {
+    "127.0.0.1:9090".to_string()
+}
+
+fn default_metrics_path() -> String {
+    "/metrics".to_string()
+}
+
+fn default_metadata_probe_fallback_statuses() -> Vec<u16> {
+    vec![403, 405, 501]
+}
+
+fn default_metadata_cache_max_entries() -> usize {
+    10000
+}
+
+fn default_access_log_buffer_size() -> usize {
+    8192
+}
+
+fn default_background_fill_concurrency() -> usize {
+    2
+}
+
+fn default_max_background_fills() -> usize {
+    4
+}
+
+fn default_vary_headers() -> Vec<String> {
+    vec!["accept-encoding".to_string(), "accept-language".to_string()]
+}
+
+fn default_admitted_statuses() -> Vec<u16> {
+    vec![200, 206]
+}
+
+fn default_cache_bypass_header() -> String {
+    "x-cache-bypass".to_string()
+}
+
+fn default_slow_start_initial_concurrency() -> usize {
+    1
+}
+
+fn default_slow_start_warmup_secs() -> u64 {
+    30
+}
+
+impl Default for SliceConfig {
+    fn default() -> Self {
+        SliceConfig {
+            slice_size: default_slice_size(),
+            min_slice_file_size: 0,
+            slice_client_range_requests: default_true(),
+            max_concurrent_subrequests: default_max_concurrent(),
+            max_retries: default_max_retries(),
+            origin_protocol: OriginProtocol::default(),
+            origin_pool_size: default_origin_pool_size(),
+            request_retry_budget: None,
+            whole_object_fallback_after: None,
+            whole_object_fallback_max_bytes: None,
+            request_deadline_ms: None,
+            request_timeout_secs: None,
+            slice_patterns: Vec::new(),
+            pattern_rules: Vec::new(),
+            cache_mode_rules: Vec::new(),
+            cache_bypass: None,
+            enable_cache: default_true(),
+            cache_ttl: default_cache_ttl(),
+            cache_sweep_interval_secs: None,
+            stale_if_error_secs: 0,
+            l1_cache_size_bytes: default_l1_cache_size(),
+            l2_cache_dir: default_l2_cache_dir(),
+            enable_l2_cache: default_true(),
+            l2_health: L2HealthConfig::default(),
+            l2_delta: L2DeltaConfig::default(),
+            upstream_address: default_upstream(),
+            listen_address: default_listen_address(),
+            socket: SocketConfig::default(),
+            max_request_header_bytes: None,
+            max_uri_bytes: None,
+            threads: None,
+            pid_file: None,
+            daemon: false,
+            metrics_endpoint: None,
+            purge: None,
+            metadata_probe: MetadataProbe::default(),
+            metadata_probe_fallback_statuses: default_metadata_probe_fallback_statuses(),
+            metadata_cache_ttl: None,
+            metadata_cache_max_entries: default_metadata_cache_max_entries(),
+            access_log: None,
+            origin_max_bytes_per_sec: None,
+            origin_max_requests_per_sec: None,
+            slow_start: None,
+            client_max_bytes_per_sec: None,
+            trust_rate_limit_header: false,
+            client_rate_limit: None,
+            response_buffer_size: None,
+            response_buffer_flush_ms: default_response_buffer_flush_ms(),
+            on_client_abort: ClientAbortPolicy::default(),
+            prefetch_slices: 0,
+            background_fill_concurrency: default_background_fill_concurrency(),
+            max_background_fills: default_max_background_fills(),
+            max_concurrent_fills: None,
+            max_fill_buffer_bytes: None,
+            max_request_fill_bytes: None,
+            fetch_order: FetchOrder::default(),
+            vary_headers: default_vary_headers(),
+            uncacheable_response_headers: Vec::new(),
+            cache_admission: CacheAdmissionConfig::default(),
+            cluster: None,
+            origin_quotas: None,
+            health: HealthConfig::default(),
+            upstream_pool: None,
+            tracing: None,
+            cache_key_policy: CacheKeyPolicy::default(),
+            header_rules: Vec::new(),
+            upstream_user_agent: None,
+            forwarded_headers: ForwardedHeadersConfig::default(),
+            host_header: HostHeaderMode::default(),
+            origin_signing: None,
+            error_pages: Vec::new(),
+        }
+    }
+}
+
+impl SliceConfig {
+    /// Load configuration from a YAML file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the YAML configuration file
+    ///
+    /// # Returns
+    /// * `Ok(SliceConfig)` if loading and validation succeed
+    /// * `Err(SliceError)` if file cannot be read or config is invalid
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config = Self::parse_file(path.as_ref())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Load configuration from a YAML file with environment overrides
+    ///
+    /// Like [`from_file`](Self::from_file), but `PINGORA_SLICE_*` environment
+    /// variables override values from the file before validation (see
+    /// [`with_env_overrides`](Self::with_env_overrides)).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config = Self::parse_file(path.as_ref())?.with_env_overrides(std::env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read and parse a YAML file, rejecting unknown keys
+    fn parse_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            SliceError::ConfigError(format!("Failed to read config file: {}", e))
+        })?;
+
+        // serde_yaml reports the offending key with its line and column
+        serde_yaml::from_str(&content).map_err(|e| {
+            SliceError::ConfigError(format!(
+                "Failed to parse config file {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Apply `PINGORA_SLICE_*` overrides from `vars`
+    ///
+    /// `PINGORA_SLICE_<FIELD>` sets a top-level field, e.g.
+    /// `PINGORA_SLICE_SLICE_SIZE=2097152`, and `__` separates nested fields,
+    /// e.g. `PINGORA_SLICE_METRICS_ENDPOINT__ADDRESS=0.0.0.0:9090`. Values
+    /// are parsed as YAML, so lists can be written as `[a, b]`. Variables
+    /// naming an unknown field or holding a value of the wrong type are
+    /// errors naming the variable. The result is not validated.
+    pub fn with_env_overrides<I>(self, vars: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut overrides: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        if overrides.is_empty() {
+            return Ok(self);
+        }
+        overrides.sort();
+
+        let mut tree = serde_yaml::to_value(&self).map_err(|e| {
+            SliceError::ConfigError(format!("Failed to serialize config: {}", e))
+        })?;
+        let mut config = self;
+        for (name, raw) in overrides {
+            let path: Vec<String> = name[ENV_PREFIX.len()..]
+                .to_ascii_lowercase()
+                .split("__")
+                .map(str::to_string)
+                .collect();
+            if path.iter().any(String::is_empty) {
+                return Err(SliceError::ConfigError(format!(
+                    "{}: invalid configuration field name",
+                    name
+                )));
+            }
+
+            // Values that don't fit as YAML (e.g. a numeric-looking string
+            // for a string field) are retried as plain strings
+            let parsed = serde_yaml::from_str(&raw)
+                .unwrap_or_else(|_| serde_yaml::Value::String(raw.clone()));
+            let mut candidate = tree.clone();
+            set_yaml_path(&mut candidate, &path, parsed);
+            config = match serde_yaml::from_value(candidate.clone()) {
+                Ok(config) => config,
+                Err(_) => {
+                    candidate = tree.clone();
+                    set_yaml_path(&mut candidate, &path, serde_yaml::Value::String(raw.clone()));
+                    serde_yaml::from_value(candidate.clone()).map_err(|e| {
+                        SliceError::ConfigError(format!("{}: invalid value {:?}: {}", name, raw, e))
+                    })?
+                }
+            };
+            tree = candidate;
+        }
+        Ok(config)
+    }
+
+    /// Keep the values of [`RESTART_REQUIRED_FIELDS`] from `running`
+    ///
+    /// Used when reloading: every other field is taken from `self`. Returns
+    /// the merged configuration, validated, and the names of the fields
+    /// whose new value was dropped.
+    pub fn with_restart_fields_from(self, running: &SliceConfig) -> Result<(Self, Vec<&'static str>)> {
+        let to_value = |config: &SliceConfig| {
+            serde_yaml::to_value(config).map_err(|e| {
+                SliceError::ConfigError(format!("Failed to serialize config: {}", e))
+            })
+        };
+        let mut tree = to_value(&self)?;
+        let running_tree = to_value(running)?;
+        let mut ignored = Vec::new();
+        for field in RESTART_REQUIRED_FIELDS {
+            let running_value = running_tree.get(field).cloned().unwrap_or(serde_yaml::Value::Null);
+            if tree.get(field) != Some(&running_value) {
+                ignored.push(field);
+                set_yaml_path(&mut tree, &[field.to_string()], running_value);
+            }
+        }
+        if ignored.is_empty() {
+            self.validate()?;
+            return Ok((self, ignored));
+        }
+        let config: SliceConfig = serde_yaml::from_value(tree).map_err(|e| {
+            SliceError::ConfigError(format!("Failed to merge config: {}", e))
+        })?;
+        config.validate()?;
+        Ok((config, ignored))
+    }
+
+    /// Effective configuration as YAML, with secrets redacted
+    ///
+    /// `purge.auth_token`, `origin_signing.secret`,
+    /// `metrics_endpoint.basic_auth.password` and `header_rules` values for
+    /// credential headers (`authorization`, `cookie`, ...) are replaced with
+    /// `<redacted>`.
+    pub fn to_yaml(&self) -> Result<String> {
+        let mut config = self.clone();
+        if let Some(token) = config.purge.as_mut().and_then(|purge| purge.auth_token.as_mut()) {
+            *token = REDACTED.to_string();
+        }
+        if let Some(signing) = config.origin_signing.as_mut() {
+            signing.secret = REDACTED.to_string();
+        }
+        if let Some(auth) = config.metrics_endpoint.as_mut().and_then(|m| m.basic_auth.as_mut()) {
+            auth.password = REDACTED.to_string();
+        }
+        for rule in &mut config.header_rules {
+            if SECRET_HEADERS.contains(&rule.name.to_ascii_lowercase().as_str()) {
+                if let Some(value) = rule.value.as_mut() {
+                    *value = REDACTED.to_string();
+                }
+            }
+        }
+
+        serde_yaml::to_string(&config)
+            .map_err(|e| SliceError::ConfigError(format!("Failed to serialize config: {}", e)))
+    }
+
+    /// Validate the configuration
+    ///
+    /// # Returns
+    /// * `Ok(())` if configuration is valid
+    /// * `Err(SliceError)` if any validation fails
+    ///
+    /// # Validation Rules
+    /// - slice_size must be between 64KB and 10MB
+    /// - max_concurrent_subrequests must be > 0
+    /// - max_retries must be >= 0
+    /// - request_deadline_ms and request_timeout_secs must be > 0 if set
+    /// - whole_object_fallback_after and whole_object_fallback_max_bytes
+    ///   must be > 0 if set
+    /// - cache_ttl must be > 0
+    /// - cache_sweep_interval_secs must be > 0 if set
+    /// - pattern_rules must have non-empty patterns and non-zero TTLs
+    /// - cache_mode_rules must have non-empty patterns, each listed once
+    /// - cache_bypass.header must be a valid header name, and purge.auth_token
+    ///   must be set unless require_token is turned off
+    /// - access_log.buffer_size must be > 0 when the access log is enabled
+    /// - origin_max_bytes_per_sec and origin_max_requests_per_sec must be > 0 when set
+    /// - client_rate_limit rates, burst and max_clients must be > 0
+    /// - slow_start.initial_concurrency must be > 0 when slow start is enabled
+    /// - response_buffer_size and response_buffer_flush_ms must be > 0 when
+    ///   buffering is enabled
+    /// - background_fill_concurrency and max_background_fills must be > 0 for complete_fill
+    ///   or prefetch_slices
+    /// - max_concurrent_fills, max_fill_buffer_bytes and max_request_fill_bytes
+    ///   must be > 0 if set
+    /// - listen_address must be a socket address and threads > 0 if set
+    /// - socket buffer sizes and keepalive_secs must be > 0 if set
+    /// - max_request_header_bytes and max_uri_bytes must be > 0 if set
+    /// - vary_headers must have at most MAX_VARY_HEADERS valid header names
+    /// - uncacheable_response_headers must be valid header names
+    /// - cache_admission.statuses may only list 200 and 206, and
+    ///   status_ttls only admitted statuses with a TTL > 0
+    /// - cluster.peers must be non-empty and contain cluster.self
+    /// - origin_quotas must be > 0
+    /// - l2_health.error_threshold and probe_interval_secs must be > 0
+    /// - l2_delta.max_delta_ratio must be > 0 and <= 1
+    /// - health probe interval, timeout and window must be > 0 and the
+    ///   failure ratio between 0 and 1
+    /// - upstream_pool.peers must be non-empty and max_failures must be > 0
+    /// - tracing.otlp_endpoint must be non-empty and sampling_ratio between 0 and 1
+    /// - header_rules must name valid headers and give `add`/`set` a valid value
+    /// - upstream_user_agent must be a valid header value
+    /// - origin_signing needs a secret, a valid header name and components
+    /// - error_pages must use 5xx statuses, valid content types and readable files
+    /// - metrics_endpoint.path must start with '/' and basic_auth needs a
+    ///   username without ':'
+    pub fn validate(&self) -> Result<()> {
+        const MIN_SLICE_SIZE: usize = 64 * 1024; // 64KB
+        const MAX_SLICE_SIZE: usize = 10 * 1024 * 1024; // 10MB
 
         // Validate slice size
         if self.slice_size < MIN_SLICE_SIZE || self.slice_size > MAX_SLICE_SIZE {
             return Err(SliceError::ConfigError(format!(
-                "slice_size must be between {}KB and {}MB, got {} bytes",
-                MIN_SLICE_SIZE / 1024,
-                MAX_SLICE_SIZE / (1024 * 1024),
-                self.slice_size
+                "slice_size must be between {}KB and {}MB, got {} bytes",
+                MIN_SLICE_SIZE / 1024,
+                MAX_SLICE_SIZE / (1024 * 1024),
+                self.slice_size
+            )));
+        }
+
+        // Validate max concurrent subrequests
+        if self.max_concurrent_subrequests == 0 {
+            return Err(SliceError::ConfigError(
+                "max_concurrent_subrequests must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate request deadlines
+        if self.request_deadline_ms == Some(0) {
+            return Err(SliceError::ConfigError(
+                "request_deadline_ms must be greater than 0".to_string(),
+            ));
+        }
+        if self.request_timeout_secs == Some(0) {
+            return Err(SliceError::ConfigError(
+                "request_timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.whole_object_fallback_after == Some(0) {
+            return Err(SliceError::ConfigError(
+                "whole_object_fallback_after must be greater than 0".to_string(),
+            ));
+        }
+        if self.whole_object_fallback_max_bytes == Some(0) {
+            return Err(SliceError::ConfigError(
+                "whole_object_fallback_max_bytes must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate cache TTL
+        if self.enable_cache && self.cache_ttl == 0 {
+            return Err(SliceError::ConfigError(
+                "cache_ttl must be greater than 0 when caching is enabled".to_string(),
+            ));
+        }
+        if self.cache_sweep_interval_secs == Some(0) {
+            return Err(SliceError::ConfigError(
+                "cache_sweep_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate access log buffer
+        if let Some(access_log) = &self.access_log {
+            if access_log.enabled && access_log.buffer_size == 0 {
+                return Err(SliceError::ConfigError(
+                    "access_log.buffer_size must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        // Validate origin rate limits
+        if self.origin_max_bytes_per_sec == Some(0) {
+            return Err(SliceError::ConfigError(
+                "origin_max_bytes_per_sec must be greater than 0".to_string(),
+            ));
+        }
+        if self.origin_max_requests_per_sec == Some(0) {
+            return Err(SliceError::ConfigError(
+                "origin_max_requests_per_sec must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate per-client rate limits
+        if let Some(limit) = &self.client_rate_limit {
+            if limit.requests_per_sec == 0 || limit.burst == Some(0) {
+                return Err(SliceError::ConfigError(
+                    "client_rate_limit.requests_per_sec and burst must be greater than 0".to_string(),
+                ));
+            }
+            if limit.bytes_per_sec == Some(0) {
+                return Err(SliceError::ConfigError(
+                    "client_rate_limit.bytes_per_sec must be greater than 0".to_string(),
+                ));
+            }
+            if limit.max_clients == 0 {
+                return Err(SliceError::ConfigError(
+                    "client_rate_limit.max_clients must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        // Validate slow start
+        if let Some(slow_start) = &self.slow_start {
+            if slow_start.enabled && slow_start.initial_concurrency == 0 {
+                return Err(SliceError::ConfigError(
+                    "slow_start.initial_concurrency must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        // Validate response buffering
+        if self.response_buffer_size.is_some_and(|size| size == 0 || self.response_buffer_flush_ms == 0) {
+            return Err(SliceError::ConfigError(
+                "response_buffer_size and response_buffer_flush_ms must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate background fills
+        if (self.on_client_abort == ClientAbortPolicy::CompleteFill || self.prefetch_slices > 0)
+            && (self.background_fill_concurrency == 0 || self.max_background_fills == 0)
+        {
+            return Err(SliceError::ConfigError(
+                "background_fill_concurrency and max_background_fills must be greater than 0 \
+                 when on_client_abort is complete_fill or prefetch_slices is set"
+                    .to_string(),
+            ));
+        }
+
+        if self.listen_address.parse::<std::net::SocketAddr>().is_err() {
+            return Err(SliceError::ConfigError(format!(
+                "listen_address must be an ip:port socket address, got {:?}",
+                self.listen_address
+            )));
+        }
+        if self.threads == Some(0) {
+            return Err(SliceError::ConfigError(
+                "threads must be greater than 0".to_string(),
+            ));
+        }
+        if self.socket.send_buffer_size == Some(0)
+            || self.socket.recv_buffer_size == Some(0)
+            || self.socket.keepalive_secs == Some(0)
+        {
+            return Err(SliceError::ConfigError(
+                "socket buffer sizes and keepalive_secs must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_request_header_bytes == Some(0) || self.max_uri_bytes == Some(0) {
+            return Err(SliceError::ConfigError(
+                "max_request_header_bytes and max_uri_bytes must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_concurrent_fills == Some(0)
+            || self.max_fill_buffer_bytes == Some(0)
+            || self.max_request_fill_bytes == Some(0)
+        {
+            return Err(SliceError::ConfigError(
+                "max_concurrent_fills, max_fill_buffer_bytes and max_request_fill_bytes must be greater than 0"
+                    .to_string(),
+            ));
+        }
+
+        // Validate varyable headers
+        if self.vary_headers.len() > MAX_VARY_HEADERS {
+            return Err(SliceError::ConfigError(format!(
+                "vary_headers must have at most {} entries, got {}",
+                MAX_VARY_HEADERS,
+                self.vary_headers.len()
             )));
         }
+        if let Some(name) = self
+            .vary_headers
+            .iter()
+            .find(|name| http::header::HeaderName::from_bytes(name.as_bytes()).is_err())
+        {
+            return Err(SliceError::ConfigError(format!(
+                "vary_headers contains an invalid header name: {:?}",
+                name
+            )));
+        }
+        if let Some(name) = self
+            .uncacheable_response_headers
+            .iter()
+            .find(|name| http::header::HeaderName::from_bytes(name.as_bytes()).is_err())
+        {
+            return Err(SliceError::ConfigError(format!(
+                "uncacheable_response_headers contains an invalid header name: {:?}",
+                name
+            )));
+        }
+        if let Some(status) = self
+            .cache_admission
+            .statuses
+            .iter()
+            .find(|status| !matches!(status, 200 | 206))
+        {
+            return Err(SliceError::ConfigError(format!(
+                "cache_admission.statuses may only list 200 and 206, not {}",
+                status
+            )));
+        }
+        if let Some((status, _)) = self
+            .cache_admission
+            .status_ttls
+            .iter()
+            .find(|(status, &ttl)| ttl == 0 || !self.cache_admission.statuses.contains(status))
+        {
+            return Err(SliceError::ConfigError(format!(
+                "cache_admission.status_ttls needs an admitted status and a TTL > 0, not {}",
+                status
+            )));
+        }
+
+        // Validate cluster mode
+        if let Some(cluster) = &self.cluster {
+            if cluster.peers.is_empty() {
+                return Err(SliceError::ConfigError(
+                    "cluster.peers must not be empty".to_string(),
+                ));
+            }
+            if !cluster.peers.contains(&cluster.self_peer) {
+                return Err(SliceError::ConfigError(format!(
+                    "cluster.self {:?} must be listed in cluster.peers",
+                    cluster.self_peer
+                )));
+            }
+        }
+
+        // Validate origin quotas
+        if let Some(quotas) = &self.origin_quotas {
+            if quotas.default_bytes == Some(0) {
+                return Err(SliceError::ConfigError(
+                    "origin_quotas.default_bytes must be greater than 0".to_string(),
+                ));
+            }
+            if let Some(host) = quotas.hosts.iter().find(|(_, &quota)| quota == 0).map(|(host, _)| host) {
+                return Err(SliceError::ConfigError(format!(
+                    "origin_quotas.hosts quota for {:?} must be greater than 0",
+                    host
+                )));
+            }
+        }
+
+        // Validate L2 failure handling
+        if self.l2_health.error_threshold == 0 || self.l2_health.probe_interval_secs == 0 {
+            return Err(SliceError::ConfigError(
+                "l2_health.error_threshold and probe_interval_secs must be greater than 0"
+                    .to_string(),
+            ));
+        }
+        let ratio = self.l2_delta.max_delta_ratio;
+        if !(ratio > 0.0 && ratio <= 1.0) {
+            return Err(SliceError::ConfigError(format!(
+                "l2_delta.max_delta_ratio must be greater than 0 and at most 1, got {}",
+                ratio
+            )));
+        }
+
+        // Validate health check thresholds
+        let health = &self.health;
+        if health.upstream_probe_interval_secs == 0
+            || health.upstream_probe_timeout_ms == 0
+            || health.error_rate_window_secs == 0
+        {
+            return Err(SliceError::ConfigError(
+                "health probe interval, timeout and error rate window must be greater than 0"
+                    .to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&health.max_subrequest_failure_ratio) {
+            return Err(SliceError::ConfigError(format!(
+                "health.max_subrequest_failure_ratio must be between 0 and 1, got {}",
+                health.max_subrequest_failure_ratio
+            )));
+        }
+
+        // Validate upstream pool
+        if let Some(pool) = &self.upstream_pool {
+            if pool.peers.is_empty() {
+                return Err(SliceError::ConfigError(
+                    "upstream_pool.peers must not be empty".to_string(),
+                ));
+            }
+            if pool.max_failures == 0 {
+                return Err(SliceError::ConfigError(
+                    "upstream_pool.max_failures must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        // Validate trace export
+        if let Some(tracing) = &self.tracing {
+            if tracing.otlp_endpoint.is_empty() {
+                return Err(SliceError::ConfigError(
+                    "tracing.otlp_endpoint must not be empty".to_string(),
+                ));
+            }
+            if !(0.0..=1.0).contains(&tracing.sampling_ratio) {
+                return Err(SliceError::ConfigError(format!(
+                    "tracing.sampling_ratio must be between 0 and 1, got {}",
+                    tracing.sampling_ratio
+                )));
+            }
+        }
+
+        // Validate header rewrite rules
+        for rule in &self.header_rules {
+            if http::header::HeaderName::from_bytes(rule.name.as_bytes()).is_err() {
+                return Err(SliceError::ConfigError(format!(
+                    "header_rules contains an invalid header name: {:?}",
+                    rule.name
+                )));
+            }
+            match (&rule.action, &rule.value) {
+                (HeaderAction::Remove, _) => {}
+                (_, None) => {
+                    return Err(SliceError::ConfigError(format!(
+                        "header_rules entry for {:?} needs a value",
+                        rule.name
+                    )));
+                }
+                (_, Some(value)) if http::HeaderValue::from_str(value).is_err() => {
+                    return Err(SliceError::ConfigError(format!(
+                        "header_rules entry for {:?} has an invalid value",
+                        rule.name
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(user_agent) = &self.upstream_user_agent {
+            if http::HeaderValue::from_str(user_agent).is_err() {
+                return Err(SliceError::ConfigError(format!(
+                    "upstream_user_agent is not a valid header value: {:?}",
+                    user_agent
+                )));
+            }
+        }
+
+        if let Some(signing) = &self.origin_signing {
+            if signing.secret.is_empty() || signing.components.is_empty() {
+                return Err(SliceError::ConfigError(
+                    "origin_signing needs a secret and at least one component".to_string(),
+                ));
+            }
+            if http::header::HeaderName::from_bytes(signing.header.as_bytes()).is_err() {
+                return Err(SliceError::ConfigError(format!(
+                    "origin_signing.header is not a valid header name: {:?}",
+                    signing.header
+                )));
+            }
+        }
+
+        // Validate the metrics endpoint
+        if let Some(endpoint) = &self.metrics_endpoint {
+            if !endpoint.path.starts_with('/') {
+                return Err(SliceError::ConfigError(format!(
+                    "metrics_endpoint.path must start with '/', got {:?}",
+                    endpoint.path
+                )));
+            }
+            if let Some(auth) = &endpoint.basic_auth {
+                if auth.username.is_empty() || auth.username.contains(':') {
+                    return Err(SliceError::ConfigError(
+                        "metrics_endpoint.basic_auth.username must be non-empty and not contain ':'"
+                            .to_string(),
+                    ));
+                }
+            }
+        }
+
+        // Validate error pages
+        for page in &self.error_pages {
+            if !(500..=599).contains(&page.status) {
+                return Err(SliceError::ConfigError(format!(
+                    "error_pages status must be between 500 and 599, got {}",
+                    page.status
+                )));
+            }
+            if http::HeaderValue::from_str(&page.content_type).is_err() {
+                return Err(SliceError::ConfigError(format!(
+                    "error_pages entry for {} has an invalid content_type",
+                    page.status
+                )));
+            }
+            if !Path::new(&page.path).is_file() {
+                return Err(SliceError::ConfigError(format!(
+                    "error_pages entry for {} names a missing file: {}",
+                    page.status, page.path
+                )));
+            }
+        }
+
+        // Validate per-pattern rules
+        for rule in &self.pattern_rules {
+            if rule.pattern.is_empty() {
+                return Err(SliceError::ConfigError(
+                    "pattern_rules entries must have a non-empty pattern".to_string(),
+                ));
+            }
+            if self.enable_cache && rule.cache_ttl == Some(0) {
+                return Err(SliceError::ConfigError(format!(
+                    "cache_ttl for pattern '{}' must be greater than 0 when caching is enabled",
+                    rule.pattern
+                )));
+            }
+        }
+
+        // Validate cache mode rules
+        let mut seen = HashSet::new();
+        for rule in &self.cache_mode_rules {
+            if rule.pattern.is_empty() {
+                return Err(SliceError::ConfigError(
+                    "cache_mode_rules entries must have a non-empty pattern".to_string(),
+                ));
+            }
+            if !seen.insert(rule.pattern.as_str()) {
+                return Err(SliceError::ConfigError(format!(
+                    "cache_mode_rules lists pattern '{}' more than once",
+                    rule.pattern
+                )));
+            }
+        }
+
+        if let Some(bypass) = &self.cache_bypass {
+            if http::header::HeaderName::from_bytes(bypass.header.as_bytes()).is_err() {
+                return Err(SliceError::ConfigError(format!(
+                    "cache_bypass.header is not a valid header name: {:?}",
+                    bypass.header
+                )));
+            }
+            let has_token = self.purge.as_ref().is_some_and(|p| p.auth_token.is_some());
+            if bypass.require_token && !has_token {
+                return Err(SliceError::ConfigError(
+                    "cache_bypass needs purge.auth_token, or require_token: false to let any client bypass the cache".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Effective metadata cache TTL in seconds
+    ///
+    /// Falls back to `cache_ttl` when `metadata_cache_ttl` is not set.
+    pub fn metadata_ttl(&self) -> u64 {
+        self.metadata_cache_ttl.unwrap_or(self.cache_ttl)
+    }
+
+    /// Create a new SliceConfig with custom values
+    pub fn new(
+        slice_size: usize,
+        max_concurrent_subrequests: usize,
+        max_retries: usize,
+    ) -> Result<Self> {
+        let config = SliceConfig {
+            slice_size,
+            max_concurrent_subrequests,
+            max_retries,
+            ..Default::default()
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Start building a configuration from the defaults
+    ///
+    /// See [`SliceConfigBuilder`].
+    pub fn builder() -> SliceConfigBuilder {
+        SliceConfigBuilder::default()
+    }
+}
+
+/// Setters of [`SliceConfigBuilder`], one per field
+///
+/// Fields listed under `optional` are set to `Some(value)`. Values are
+/// converted with `Into`, so string fields take `&str` as well.
+macro_rules! config_setters {
+    (
+        plain { $($field:ident: $ty:ty;)* }
+        optional { $($opt_field:ident: $opt_ty:ty;)* }
+    ) => {
+        $(
+            #[doc = concat!("Set `", stringify!($field), "`")]
+            pub fn $field(mut self, value: $ty) -> Self {
+                self.config.$field = value.into();
+                self
+            }
+        )*
+        $(
+            #[doc = concat!("Set `", stringify!($opt_field), "`")]
+            pub fn $opt_field(mut self, value: $opt_ty) -> Self {
+                self.config.$opt_field = Some(value.into());
+                self
+            }
+        )*
+    };
+}
+
+/// Builds a [`SliceConfig`] field by field
+///
+/// Fields that are not set keep their defaults, and [`build`](Self::build)
+/// validates the result like [`SliceConfig::from_file`] does. Optional
+/// fields are set with their value rather than an `Option`.
+///
+/// # Example
+/// ```
+/// use pingora_slice::SliceConfig;
+///
+/// let config = SliceConfig::builder()
+///     .slice_size(256 * 1024)
+///     .upstream_address("origin.example.com:80")
+///     .request_timeout_secs(30)
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.request_timeout_secs, Some(30));
+///
+/// assert!(SliceConfig::builder().slice_size(0).build().is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SliceConfigBuilder {
+    config: SliceConfig,
+}
+
+impl SliceConfigBuilder {
+    config_setters! {
+        plain {
+            slice_size: usize;
+            min_slice_file_size: u64;
+            slice_client_range_requests: bool;
+            max_concurrent_subrequests: usize;
+            max_retries: usize;
+            origin_protocol: OriginProtocol;
+            origin_pool_size: usize;
+            slice_patterns: Vec<String>;
+            pattern_rules: Vec<PatternRule>;
+            cache_mode_rules: Vec<CacheModeRule>;
+            enable_cache: bool;
+            cache_ttl: u64;
+            stale_if_error_secs: u64;
+            l1_cache_size_bytes: usize;
+            l2_cache_dir: impl Into<String>;
+            enable_l2_cache: bool;
+            l2_health: L2HealthConfig;
+            l2_delta: L2DeltaConfig;
+            upstream_address: impl Into<String>;
+            listen_address: impl Into<String>;
+            daemon: bool;
+            socket: SocketConfig;
+            metadata_probe: MetadataProbe;
+            metadata_probe_fallback_statuses: Vec<u16>;
+            metadata_cache_max_entries: usize;
+            trust_rate_limit_header: bool;
+            response_buffer_flush_ms: u64;
+            on_client_abort: ClientAbortPolicy;
+            background_fill_concurrency: usize;
+            prefetch_slices: usize;
+            max_background_fills: usize;
+            fetch_order: FetchOrder;
+            vary_headers: Vec<String>;
+            uncacheable_response_headers: Vec<String>;
+            cache_admission: CacheAdmissionConfig;
+            health: HealthConfig;
+            cache_key_policy: CacheKeyPolicy;
+            header_rules: Vec<HeaderRule>;
+            forwarded_headers: ForwardedHeadersConfig;
+            host_header: HostHeaderMode;
+            error_pages: Vec<ErrorPageConfig>;
+        }
+        optional {
+            request_retry_budget: usize;
+            whole_object_fallback_after: usize;
+            whole_object_fallback_max_bytes: u64;
+            request_deadline_ms: u64;
+            request_timeout_secs: u64;
+            cache_sweep_interval_secs: u64;
+            threads: usize;
+            pid_file: impl Into<String>;
+            cache_bypass: CacheBypassConfig;
+            max_request_header_bytes: usize;
+            max_uri_bytes: usize;
+            metrics_endpoint: MetricsEndpointConfig;
+            purge: PurgeConfig;
+            metadata_cache_ttl: u64;
+            access_log: AccessLogConfig;
+            origin_max_bytes_per_sec: u64;
+            origin_max_requests_per_sec: u64;
+            slow_start: SlowStartConfig;
+            client_max_bytes_per_sec: u64;
+            client_rate_limit: ClientRateLimitConfig;
+            response_buffer_size: usize;
+            max_concurrent_fills: usize;
+            max_fill_buffer_bytes: u64;
+            max_request_fill_bytes: u64;
+            cluster: ClusterConfig;
+            origin_quotas: OriginQuotaConfig;
+            upstream_pool: UpstreamPoolConfig;
+            tracing: TracingConfig;
+            upstream_user_agent: impl Into<String>;
+            origin_signing: OriginSigningConfig;
+        }
+    }
+
+    /// Validate the configuration and return it
+    ///
+    /// # Returns
+    /// * `Ok(SliceConfig)` if validation succeeds
+    /// * `Err(SliceError::ConfigError)` naming the first invalid setting
+    pub fn build(self) -> Result<SliceConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Set the value at `path` in a YAML mapping, creating mappings as needed
+fn set_yaml_path(tree: &mut serde_yaml::Value, path: &[String], value: serde_yaml::Value) {
+    let Some((key, rest)) = path.split_first() else {
+        *tree = value;
+        return;
+    };
+    if !tree.is_mapping() {
+        *tree = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    }
+    if let serde_yaml::Value::Mapping(mapping) = tree {
+        let child = mapping
+            .entry(serde_yaml::Value::String(key.clone()))
+            .or_insert(serde_yaml::Value::Null);
+        set_yaml_path(child, rest, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = SliceConfig::default();
+        assert_eq!(config.slice_size, 1024 * 1024);
+        assert_eq!(config.max_concurrent_subrequests, 4);
+        assert_eq!(config.max_retries, 3);
+        assert!(config.enable_cache);
+        assert_eq!(config.cache_ttl, 3600);
+        assert_eq!(config.metadata_probe, MetadataProbe::Auto);
+        assert_eq!(config.metadata_probe_fallback_statuses, vec![403, 405, 501]);
+    }
+
+    #[test]
+    fn test_metadata_ttl_defaults_to_cache_ttl() {
+        let mut config = SliceConfig::default();
+        assert_eq!(config.metadata_ttl(), config.cache_ttl);
+
+        config.metadata_cache_ttl = Some(30);
+        assert_eq!(config.metadata_ttl(), 30);
+    }
+
+    #[test]
+    fn test_metadata_probe_from_yaml() {
+        let yaml = "metadata_probe: get_range\nmetadata_probe_fallback_statuses: [403]\n";
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.metadata_probe, MetadataProbe::GetRange);
+        assert_eq!(config.metadata_probe_fallback_statuses, vec![403]);
+    }
+
+    #[test]
+    fn test_validate_valid_config() {
+        let config = SliceConfig::default();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_slice_size_too_small() {
+        let mut config = SliceConfig::default();
+        config.slice_size = 1024; // 1KB, too small
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_slice_size_too_large() {
+        let mut config = SliceConfig::default();
+        config.slice_size = 20 * 1024 * 1024; // 20MB, too large
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_zero_concurrent() {
+        let mut config = SliceConfig::default();
+        config.max_concurrent_subrequests = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_zero_cache_ttl() {
+        let mut config = SliceConfig::default();
+        config.cache_ttl = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_pattern_rule_zero_ttl() {
+        let mut config = SliceConfig::default();
+        config.pattern_rules.push(PatternRule {
+            pattern: "/api/*".to_string(),
+            name: None,
+            cache_ttl: Some(0),
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_pattern_rules_from_yaml() {
+        let yaml = "pattern_rules:\n  - pattern: \"/api/*\"\n    cache_ttl: 60\n  - pattern: \"*.mp4\"\n";
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.pattern_rules.len(), 2);
+        assert_eq!(config.pattern_rules[0].cache_ttl, Some(60));
+        assert_eq!(config.pattern_rules[1].cache_ttl, None);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_uncacheable_response_headers() {
+        let mut config = SliceConfig {
+            uncacheable_response_headers: vec!["x-user-id".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        config.uncacheable_response_headers.push("bad header".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_cache_admission() {
+        let yaml = "cache_admission:\n  statuses: [200]\n  status_ttls:\n    200: 60\n";
+        let mut config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.cache_admission.status_ttls.get(&200), Some(&60));
+        assert!(config.validate().is_ok());
+
+        // Only admitted statuses take a TTL
+        config.cache_admission.status_ttls.insert(206, 60);
+        assert!(config.validate().is_err());
+
+        // Redirects never reach the slice cache
+        config.cache_admission = CacheAdmissionConfig {
+            statuses: vec![200, 206, 301],
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("not 301"));
+    }
+
+    #[test]
+    fn test_cache_mode_rules_from_yaml() {
+        let yaml = "cache_mode_rules:\n  - pattern: \"/reports/*\"\n    mode: whole\n  - pattern: \"/live/\"\n    mode: bypass\n";
+        let mut config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.cache_mode_rules[0].mode, CacheMode::Whole);
+        assert_eq!(config.cache_mode_rules[1].mode, CacheMode::Bypass);
+        assert!(config.validate().is_ok());
+
+        config.cache_mode_rules.push(CacheModeRule {
+            pattern: "/live/".to_string(),
+            mode: CacheMode::Slice,
+        });
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("'/live/' more than once"), "{}", err);
+    }
+
+    #[test]
+    fn test_cache_bypass_from_yaml() {
+        let yaml = "cache_bypass: {}\n";
+        let mut config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        let bypass = config.cache_bypass.clone().unwrap();
+        assert_eq!(bypass.header, "x-cache-bypass");
+        assert!(bypass.require_token);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("purge.auth_token"), "{}", err);
+
+        config.purge = Some(PurgeConfig {
+            enabled: true,
+            auth_token: Some("secret".to_string()),
+            enable_metrics: true,
+        });
+        assert!(config.validate().is_ok());
+
+        config.cache_bypass = Some(CacheBypassConfig {
+            header: "bad header".to_string(),
+            require_token: false,
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_anonymous_cache_bypass_is_an_explicit_opt_out() {
+        let mut config = SliceConfig {
+            cache_bypass: Some(CacheBypassConfig::default()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let yaml = "cache_bypass:\n  require_token: false\n";
+        config.cache_bypass = serde_yaml::from_str::<SliceConfig>(yaml).unwrap().cache_bypass;
+        assert!(!config.cache_bypass.as_ref().unwrap().require_token);
+        assert!(config.validate().is_ok());
+    }
 
-        // Validate max concurrent subrequests
-        if self.max_concurrent_subrequests == 0 {
-            return Err(SliceError::ConfigError(
-                "max_concurrent_subrequests must be greater than 0".to_string(),
-            ));
+    #[test]
+    fn test_access_log_from_yaml() {
+        let yaml = "access_log:\n  enabled: true\n  path: /var/log/slice/access.log\n  format: combined\n";
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        let access_log = config.access_log.unwrap();
+        assert!(access_log.enabled);
+        assert_eq!(access_log.path.as_deref(), Some("/var/log/slice/access.log"));
+        assert_eq!(access_log.format, AccessLogFormat::Combined);
+        assert_eq!(access_log.buffer_size, 8192);
+    }
+
+    #[test]
+    fn test_validate_zero_origin_rate_limit() {
+        let config = SliceConfig {
+            origin_max_bytes_per_sec: Some(0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = SliceConfig {
+            origin_max_requests_per_sec: Some(0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_slow_start_from_yaml() {
+        let yaml = "slow_start:\n  enabled: true\n  initial_concurrency: 2\n";
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        let slow_start = config.slow_start.as_ref().unwrap();
+        assert!(slow_start.enabled);
+        assert_eq!(slow_start.initial_concurrency, 2);
+        assert_eq!(slow_start.warmup_secs, 30);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_on_client_abort_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("on_client_abort: complete_fill\n").unwrap();
+        assert_eq!(config.on_client_abort, ClientAbortPolicy::CompleteFill);
+        assert!(config.validate().is_ok());
+
+        let config = SliceConfig {
+            on_client_abort: ClientAbortPolicy::CompleteFill,
+            max_background_fills: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert_eq!(SliceConfig::default().on_client_abort, ClientAbortPolicy::Abort);
+    }
+
+    #[test]
+    fn test_fetch_order_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("fetch_order: priority\n").unwrap();
+        assert_eq!(config.fetch_order, FetchOrder::Priority);
+
+        let config: SliceConfig =
+            serde_yaml::from_str("fetch_order: !sequential_first_n 2\n").unwrap();
+        assert_eq!(config.fetch_order, FetchOrder::SequentialFirstN(2));
+        assert_eq!(SliceConfig::default().fetch_order, FetchOrder::Parallel);
+    }
+
+    #[test]
+    fn test_cluster_from_yaml() {
+        let yaml = "cluster:\n  peers: [\"10.0.0.1:8080\", \"10.0.0.2:8080\"]\n  self: \"10.0.0.2:8080\"\n";
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        let cluster = config.cluster.as_ref().unwrap();
+        assert_eq!(cluster.self_peer, "10.0.0.2:8080");
+        assert!(!cluster.local_copy);
+        assert!(config.validate().is_ok());
+
+        let config = SliceConfig {
+            cluster: Some(ClusterConfig {
+                self_peer: "10.0.0.3:8080".to_string(),
+                ..cluster.clone()
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_origin_quotas_from_yaml() {
+        let yaml = "origin_quotas:\n  default_bytes: 1048576\n  hosts:\n    videos.example.com: 4096\n";
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        let quotas = config.origin_quotas.as_ref().unwrap();
+        assert_eq!(quotas.default_bytes, Some(1048576));
+        assert_eq!(quotas.hosts["videos.example.com"], 4096);
+        assert!(config.validate().is_ok());
+
+        let config = SliceConfig {
+            origin_quotas: Some(OriginQuotaConfig {
+                default_bytes: None,
+                hosts: HashMap::from([("videos.example.com".to_string(), 0)]),
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_l2_health_validation() {
+        let config: SliceConfig =
+            serde_yaml::from_str("l2_health:\n  error_threshold: 3\n").unwrap();
+        assert_eq!(config.l2_health.error_threshold, 3);
+        assert_eq!(config.l2_health.probe_interval_secs, 30);
+        assert!(config.validate().is_ok());
+
+        for l2_health in [
+            L2HealthConfig { error_threshold: 0, ..Default::default() },
+            L2HealthConfig { probe_interval_secs: 0, ..Default::default() },
+        ] {
+            let config = SliceConfig { l2_health, ..Default::default() };
+            assert!(config.validate().is_err());
         }
+    }
 
-        // Validate cache TTL
-        if self.enable_cache && self.cache_ttl == 0 {
-            return Err(SliceError::ConfigError(
-                "cache_ttl must be greater than 0 when caching is enabled".to_string(),
-            ));
+    #[test]
+    fn test_l2_delta_validation() {
+        let config: SliceConfig =
+            serde_yaml::from_str("l2_delta:\n  enabled: true\n  max_delta_ratio: 0.25\n").unwrap();
+        assert!(config.l2_delta.enabled);
+        assert_eq!(config.l2_delta.min_size_bytes, 64 * 1024);
+        assert_eq!(config.l2_delta.max_delta_ratio, 0.25);
+        assert!(config.validate().is_ok());
+        assert!(!SliceConfig::default().l2_delta.enabled);
+
+        for max_delta_ratio in [0.0, 1.5, f64::NAN] {
+            let l2_delta = L2DeltaConfig { max_delta_ratio, ..Default::default() };
+            let config = SliceConfig { l2_delta, ..Default::default() };
+            assert!(config.validate().is_err());
         }
+    }
 
-        Ok(())
+    #[test]
+    fn test_health_validation() {
+        let config: SliceConfig =
+            serde_yaml::from_str("health:\n  max_subrequest_failure_ratio: 0.25\n").unwrap();
+        assert_eq!(config.health.max_subrequest_failure_ratio, 0.25);
+        assert_eq!(config.health.upstream_probe_interval_secs, 10);
+        assert!(config.validate().is_ok());
+
+        let config = SliceConfig {
+            health: HealthConfig {
+                max_subrequest_failure_ratio: 1.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = SliceConfig {
+            health: HealthConfig {
+                upstream_probe_interval_secs: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
     }
 
-    /// Create a new SliceConfig with custom values
-    pub fn new(
-        slice_size: usize,
-        max_concurrent_subrequests: usize,
-        max_retries: usize,
-    ) -> Result<Self> {
+    #[test]
+    fn test_upstream_pool_from_yaml() {
+        let yaml = "upstream_pool:\n  peers: [\"10.0.0.1:80\", \"10.0.0.2:80\"]\n  policy: failover\n";
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        let pool = config.upstream_pool.as_ref().unwrap();
+        assert_eq!(pool.policy, UpstreamPolicy::Failover);
+        assert_eq!(pool.max_failures, 3);
+        assert_eq!(pool.cooldown_secs, 30);
+        assert!(config.validate().is_ok());
+
         let config = SliceConfig {
-            slice_size,
-            max_concurrent_subrequests,
-            max_retries,
+            upstream_pool: Some(UpstreamPoolConfig {
+                peers: Vec::new(),
+                ..pool.clone()
+            }),
             ..Default::default()
         };
-        config.validate()?;
-        Ok(config)
+        assert!(config.validate().is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_tracing_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("tracing:\n  sampling_ratio: 0.1\n").unwrap();
+        let tracing = config.tracing.as_ref().unwrap();
+        assert_eq!(tracing.sampling_ratio, 0.1);
+        assert_eq!(tracing.otlp_endpoint, "http://localhost:4317");
+        assert_eq!(tracing.service_name, "pingora-slice");
+        assert!(config.validate().is_ok());
+
+        let config = SliceConfig {
+            tracing: Some(TracingConfig {
+                sampling_ratio: 2.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
 
     #[test]
-    fn test_default_config() {
-        let config = SliceConfig::default();
-        assert_eq!(config.slice_size, 1024 * 1024);
-        assert_eq!(config.max_concurrent_subrequests, 4);
-        assert_eq!(config.max_retries, 3);
-        assert!(config.enable_cache);
-        assert_eq!(config.cache_ttl, 3600);
+    fn test_vary_headers_validation() {
+        assert!(SliceConfig::default().validate().is_ok());
+
+        let config = SliceConfig {
+            vary_headers: vec!["bad header".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = SliceConfig {
+            vary_headers: (0..=MAX_VARY_HEADERS).map(|i| format!("x-h{}", i)).collect(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_validate_valid_config() {
-        let config = SliceConfig::default();
+    fn test_header_rules_from_yaml() {
+        let yaml = r#"
+header_rules:
+  - apply_to: upstream_request
+    action: set
+    name: authorization
+    value: "Bearer secret"
+  - apply_to: response
+    action: remove
+    name: set-cookie
+"#;
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.header_rules.len(), 2);
+        assert_eq!(config.header_rules[0].apply_to, HeaderTarget::UpstreamRequest);
+        assert_eq!(config.header_rules[1].action, HeaderAction::Remove);
+        assert!(config.header_rules[1].value.is_none());
         assert!(config.validate().is_ok());
     }
 
     #[test]
-    fn test_validate_slice_size_too_small() {
-        let mut config = SliceConfig::default();
-        config.slice_size = 1024; // 1KB, too small
+    fn test_header_rules_validation() {
+        let rule = |name: &str, action, value: Option<&str>| SliceConfig {
+            header_rules: vec![HeaderRule {
+                apply_to: HeaderTarget::Both,
+                action,
+                name: name.to_string(),
+                value: value.map(str::to_string),
+            }],
+            ..Default::default()
+        };
+        assert!(rule("x-token", HeaderAction::Add, Some("abc")).validate().is_ok());
+        assert!(rule("bad header", HeaderAction::Remove, None).validate().is_err());
+        assert!(rule("x-token", HeaderAction::Set, None).validate().is_err());
+        assert!(rule("x-token", HeaderAction::Set, Some("a\nb")).validate().is_err());
+    }
+
+    #[test]
+    fn test_forwarded_headers_from_yaml() {
+        let yaml = r#"
+upstream_user_agent: "pingora-slice/1.0"
+forwarded_headers:
+  x_forwarded_for: true
+  forwarded: true
+  mode: set
+"#;
+        let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.upstream_user_agent.as_deref(), Some("pingora-slice/1.0"));
+        assert!(config.forwarded_headers.x_forwarded_for);
+        assert!(!config.forwarded_headers.x_forwarded_proto);
+        assert_eq!(config.forwarded_headers.mode, ForwardedMode::Set);
+        assert!(config.validate().is_ok());
+
+        let config = SliceConfig {
+            upstream_user_agent: Some("bad\nagent".to_string()),
+            ..Default::default()
+        };
         assert!(config.validate().is_err());
+        assert!(!SliceConfig::default().forwarded_headers.is_enabled());
     }
 
     #[test]
-    fn test_validate_slice_size_too_large() {
-        let mut config = SliceConfig::default();
-        config.slice_size = 20 * 1024 * 1024; // 20MB, too large
+    fn test_origin_protocol_from_yaml() {
+        let config: SliceConfig =
+            serde_yaml::from_str("origin_protocol: http2\norigin_pool_size: 32").unwrap();
+        assert_eq!(config.origin_protocol, OriginProtocol::Http2);
+        assert_eq!(config.origin_pool_size, 32);
+        assert_eq!(SliceConfig::default().origin_protocol, OriginProtocol::Auto);
+        assert!(serde_yaml::from_str::<SliceConfig>("origin_protocol: h3").is_err());
+    }
+
+    #[test]
+    fn test_response_buffer_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("response_buffer_size: 16384").unwrap();
+        assert_eq!(config.response_buffer_size, Some(16384));
+        assert_eq!(config.response_buffer_flush_ms, 10);
+        assert!(config.validate().is_ok());
+
+        let config: SliceConfig =
+            serde_yaml::from_str("response_buffer_size: 16384\nresponse_buffer_flush_ms: 0").unwrap();
         assert!(config.validate().is_err());
+        assert!(SliceConfig::default().response_buffer_size.is_none());
     }
 
     #[test]
-    fn test_validate_zero_concurrent() {
-        let mut config = SliceConfig::default();
-        config.max_concurrent_subrequests = 0;
+    fn test_request_limits_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str(
+            "request_retry_budget: 8\nrequest_deadline_ms: 10000\nrequest_timeout_secs: 30",
+        )
+        .unwrap();
+        assert_eq!(config.request_retry_budget, Some(8));
+        assert_eq!(config.request_deadline_ms, Some(10000));
+        assert_eq!(config.request_timeout_secs, Some(30));
+        assert!(config.validate().is_ok());
+
+        let config = SliceConfig {
+            request_deadline_ms: Some(0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config = SliceConfig {
+            request_timeout_secs: Some(0),
+            ..Default::default()
+        };
         assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_validate_zero_cache_ttl() {
-        let mut config = SliceConfig::default();
-        config.cache_ttl = 0;
+    fn test_whole_object_fallback_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("whole_object_fallback_after: 3").unwrap();
+        assert_eq!(config.whole_object_fallback_after, Some(3));
+        assert!(config.validate().is_ok());
+        assert!(SliceConfig::default().whole_object_fallback_after.is_none());
+
+        let config = SliceConfig {
+            whole_object_fallback_after: Some(0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config: SliceConfig = serde_yaml::from_str("whole_object_fallback_max_bytes: 1048576").unwrap();
+        assert_eq!(config.whole_object_fallback_max_bytes, Some(1048576));
+        let config = SliceConfig {
+            whole_object_fallback_max_bytes: Some(0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_cache_sweep_interval_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("cache_sweep_interval_secs: 300").unwrap();
+        assert_eq!(config.cache_sweep_interval_secs, Some(300));
+        assert!(config.validate().is_ok());
+        assert!(SliceConfig::default().cache_sweep_interval_secs.is_none());
+
+        let config = SliceConfig {
+            cache_sweep_interval_secs: Some(0),
+            ..Default::default()
+        };
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_stale_if_error_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("stale_if_error_secs: 600").unwrap();
+        assert_eq!(config.stale_if_error_secs, 600);
+        assert!(config.validate().is_ok());
+        assert_eq!(SliceConfig::default().stale_if_error_secs, 0);
+    }
+
+    #[test]
+    fn test_metrics_endpoint_auth_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str(
+            "metrics_endpoint:\n  enabled: true\n  path: /internal/metrics\n  basic_auth:\n    username: prom\n    password: pw",
+        )
+        .unwrap();
+        let endpoint = config.metrics_endpoint.clone().unwrap();
+        assert_eq!(endpoint.path, "/internal/metrics");
+        assert_eq!(endpoint.basic_auth.unwrap().username, "prom");
+        assert!(config.validate().is_ok());
+
+        let config: SliceConfig = serde_yaml::from_str("metrics_endpoint:\n  enabled: true").unwrap();
+        let endpoint = config.metrics_endpoint.unwrap();
+        assert_eq!(endpoint.path, "/metrics");
+        assert!(endpoint.basic_auth.is_none());
+
+        for yaml in [
+            "metrics_endpoint:\n  path: metrics",
+            "metrics_endpoint:\n  basic_auth:\n    username: ''\n    password: pw",
+            "metrics_endpoint:\n  basic_auth:\n    username: 'a:b'\n    password: pw",
+        ] {
+            let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+            assert!(config.validate().is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_origin_signing_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str(
+            "origin_signing:\n  secret: s3cret\n  algorithm: hmac_sha512\n  components: [method, host, path, range]",
+        )
+        .unwrap();
+        let signing = config.origin_signing.clone().unwrap();
+        assert_eq!(signing.header, "x-signature");
+        assert_eq!(signing.algorithm, SigningAlgorithm::HmacSha512);
+        assert_eq!(signing.components.len(), 4);
+        assert!(config.validate().is_ok());
+
+        let config: SliceConfig = serde_yaml::from_str("origin_signing:\n  secret: s3cret").unwrap();
+        assert_eq!(
+            config.origin_signing.unwrap().components,
+            [SignedComponent::Method, SignedComponent::Path]
+        );
+
+        for yaml in [
+            "origin_signing:\n  secret: ''",
+            "origin_signing:\n  secret: s3cret\n  components: []",
+            "origin_signing:\n  secret: s3cret\n  header: 'bad header'",
+        ] {
+            let config: SliceConfig = serde_yaml::from_str(yaml).unwrap();
+            assert!(config.validate().is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_host_header_from_yaml() {
+        let config: SliceConfig = serde_yaml::from_str("host_header: preserve").unwrap();
+        assert_eq!(config.host_header, HostHeaderMode::Preserve);
+        assert_eq!(SliceConfig::default().host_header, HostHeaderMode::Upstream);
+        assert!(serde_yaml::from_str::<SliceConfig>("host_header: client").is_err());
+    }
+
+    #[test]
+    fn test_error_pages_validation() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let page = |status, path: &str| SliceConfig {
+            error_pages: vec![ErrorPageConfig {
+                status,
+                path: path.to_string(),
+                content_type: default_error_page_content_type(),
+            }],
+            ..Default::default()
+        };
+        let path = file.path().to_str().unwrap();
+        assert!(page(503, path).validate().is_ok());
+        assert!(page(404, path).validate().is_err());
+        assert!(page(503, "/nonexistent/503.html").validate().is_err());
+    }
+
     #[test]
     fn test_new_config() {
         let config = SliceConfig::new(512 * 1024, 8, 5).unwrap();
@@ -293,4 +2833,186 @@ mod tests {
         let result = SliceConfig::new(1024, 8, 5); // Too small
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_builder_valid_config() {
+        let config = SliceConfig::builder()
+            .slice_size(512 * 1024)
+            .max_concurrent_subrequests(8)
+            .upstream_address("origin:8080")
+            .slice_patterns(vec!["/videos/*".to_string()])
+            .request_timeout_secs(30)
+            .origin_protocol(OriginProtocol::Http2)
+            .build()
+            .unwrap();
+        assert_eq!(config.slice_size, 512 * 1024);
+        assert_eq!(config.max_concurrent_subrequests, 8);
+        assert_eq!(config.upstream_address, "origin:8080");
+        assert_eq!(config.request_timeout_secs, Some(30));
+        assert_eq!(config.origin_protocol, OriginProtocol::Http2);
+        // Fields not set keep their defaults
+        assert_eq!(config.max_retries, SliceConfig::default().max_retries);
+    }
+
+    #[test]
+    fn test_builder_runs_validation() {
+        let err = SliceConfig::builder().slice_size(1024).build().unwrap_err();
+        assert!(err.to_string().contains("slice_size"), "{}", err);
+
+        let err = SliceConfig::builder().request_timeout_secs(0).build().unwrap_err();
+        assert!(err.to_string().contains("request_timeout_secs"), "{}", err);
+
+        let err = SliceConfig::builder()
+            .enable_cache(true)
+            .cache_ttl(0)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("cache_ttl"), "{}", err);
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        let err = serde_yaml::from_str::<SliceConfig>("slice_size: 1048576\nslice_sise: 2\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown field `slice_sise`"), "{}", err);
+        assert!(err.contains("line 2"), "{}", err);
+
+        let err = serde_yaml::from_str::<SliceConfig>("metrics_endpoint:\n  enabled: true\n  adress: x\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown field `adress`"), "{}", err);
+        assert!(err.contains("line 3"), "{}", err);
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let config: SliceConfig = serde_yaml::from_str("slice_size: 524288\nmax_retries: 5\n").unwrap();
+        let config = config
+            .with_env_overrides(env(&[
+                ("PINGORA_SLICE_SLICE_SIZE", "2097152"),
+                ("PINGORA_SLICE_UPSTREAM_ADDRESS", "origin:8080"),
+                ("PINGORA_SLICE_SLICE_PATTERNS", "[/a/*, /b/*]"),
+                ("PINGORA_SLICE_METRICS_ENDPOINT__ADDRESS", "0.0.0.0:9090"),
+                ("PINGORA_SLICE_L2_CACHE_DIR", "1234"),
+                ("HOME", "/root"),
+            ]))
+            .unwrap();
+
+        // Environment beats the file, the file beats defaults
+        assert_eq!(config.slice_size, 2097152);
+        assert_eq!(config.max_retries, 5);
+        assert_eq!(config.max_concurrent_subrequests, 4);
+        assert_eq!(config.upstream_address, "origin:8080");
+        assert_eq!(config.slice_patterns, ["/a/*", "/b/*"]);
+        assert_eq!(config.metrics_endpoint.unwrap().address, "0.0.0.0:9090");
+        assert_eq!(config.l2_cache_dir, "1234");
+    }
+
+    #[test]
+    fn test_env_override_errors_name_variable() {
+        let err = SliceConfig::default()
+            .with_env_overrides(env(&[("PINGORA_SLICE_SLICE_SIZE", "large")]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("PINGORA_SLICE_SLICE_SIZE"), "{}", err);
+        assert!(err.contains("\"large\""), "{}", err);
+
+        let err = SliceConfig::default()
+            .with_env_overrides(env(&[("PINGORA_SLICE_SLICE_SISE", "1")]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("PINGORA_SLICE_SLICE_SISE"), "{}", err);
+        assert!(err.contains("unknown field `slice_sise`"), "{}", err);
+
+        assert!(SliceConfig::default()
+            .with_env_overrides(env(&[("PINGORA_SLICE_CLUSTER__", "x")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_to_yaml_redacts_secrets() {
+        let config = SliceConfig {
+            purge: Some(PurgeConfig {
+                enabled: true,
+                auth_token: Some("purge-secret".to_string()),
+                enable_metrics: true,
+            }),
+            header_rules: vec![
+                HeaderRule {
+                    apply_to: HeaderTarget::UpstreamRequest,
+                    action: HeaderAction::Set,
+                    name: "Authorization".to_string(),
+                    value: Some("Bearer origin-secret".to_string()),
+                },
+                HeaderRule {
+                    apply_to: HeaderTarget::Response,
+                    action: HeaderAction::Set,
+                    name: "x-served-by".to_string(),
+                    value: Some("edge-1".to_string()),
+                },
+            ],
+            origin_signing: Some(OriginSigningConfig {
+                secret: "signing-secret".to_string(),
+                header: default_signing_header(),
+                algorithm: SigningAlgorithm::default(),
+                components: default_signed_components(),
+            }),
+            metrics_endpoint: Some(MetricsEndpointConfig {
+                basic_auth: Some(BasicAuthConfig {
+                    username: "prometheus".to_string(),
+                    password: "scrape-secret".to_string(),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let yaml = config.to_yaml().unwrap();
+        assert!(!yaml.contains("scrape-secret"));
+        assert!(yaml.contains("prometheus"));
+        assert!(!yaml.contains("purge-secret"));
+        assert!(!yaml.contains("origin-secret"));
+        assert!(!yaml.contains("signing-secret"));
+        assert!(yaml.contains("edge-1"));
+
+        // The dump loads back as the same configuration, minus secrets
+        let dumped: SliceConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(dumped.slice_size, config.slice_size);
+        assert_eq!(dumped.purge.unwrap().auth_token.as_deref(), Some("<redacted>"));
+    }
+
+    #[test]
+    fn test_reload_keeps_restart_required_fields() {
+        let running = SliceConfig {
+            l2_cache_dir: "/var/cache/slice".to_string(),
+            ..Default::default()
+        };
+        let reloaded = SliceConfig {
+            slice_size: running.slice_size * 2,
+            l2_cache_dir: "/mnt/cache".to_string(),
+            cache_ttl: 60,
+            slice_patterns: vec!["^/video/".to_string()],
+            ..Default::default()
+        };
+        let (merged, ignored) = reloaded.with_restart_fields_from(&running).unwrap();
+        assert_eq!(ignored, ["slice_size", "l2_cache_dir"]);
+        assert_eq!(merged.slice_size, running.slice_size);
+        assert_eq!(merged.l2_cache_dir, "/var/cache/slice");
+        assert_eq!(merged.cache_ttl, 60);
+        assert_eq!(merged.slice_patterns, ["^/video/"]);
+
+        let (_, ignored) = SliceConfig::default().with_restart_fields_from(&SliceConfig::default()).unwrap();
+        assert!(ignored.is_empty());
+
+        let invalid = SliceConfig {
+            max_concurrent_subrequests: 0,
+            ..Default::default()
+        };
+        assert!(invalid.with_restart_fields_from(&running).is_err());
+    }
 }
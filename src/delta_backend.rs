@@ -0,0 +1,515 @@
+//! Delta storage for updated versions of the same L2 entry
+//!
+//! Some objects change only slightly between versions, like incremental
+//! backups or appended logs. [`DeltaBackend`] wraps another
+//! [`CacheBackend`] and, when a key is stored again, keeps the previous
+//! version as a base and writes only a binary diff against it. Lookups apply
+//! the diff to the base.
+//!
+//! Every later version is diffed against the same base, so a lookup applies
+//! at most one diff. A version is stored whole, and becomes the base for the
+//! next update, when it is smaller than the minimum size, when its diff is
+//! not small enough compared to the version itself, or when the base would
+//! expire before it.
+//!
+//! Stores of the same key must not run concurrently, which holds for the
+//! tiered cache: it writes to L2 from a single task.
+//!
+//! Stored records start with a type byte, so a directory must always be
+//! read through a `DeltaBackend` or never. [`TieredCache::from_config`]
+//! enables it with `l2_delta` and records the choice with
+//! [`FileBackend::verify_entry_format`](crate::FileBackend::verify_entry_format),
+//! which purges entries written the other way.
+//!
+//! [`TieredCache::from_config`]: crate::TieredCache::from_config
+
+use crate::cache_backend::{BackendEntry, CacheBackend, CacheBackendStats};
+use crate::error::{Result, SliceError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Suffix of the inner key holding the base version of a key
+const BASE_SUFFIX: &str = "#delta-base";
+
+/// First byte of a stored record: the rest is the entry itself
+const FULL_RECORD: u8 = 0;
+/// First byte of a stored record: the rest is a diff against the base
+const DELTA_RECORD: u8 = 1;
+
+/// Diff operations
+const COPY_OP: u8 = 0;
+const INSERT_OP: u8 = 1;
+
+/// Length of the base blocks matched when diffing
+const BLOCK_SIZE: usize = 32;
+
+/// Default smallest entry that is diffed
+pub const DEFAULT_MIN_SIZE: usize = 64 * 1024;
+/// Default largest diff, as a fraction of the new version, worth storing
+pub const DEFAULT_MAX_DELTA_RATIO: f64 = 0.5;
+
+/// Counts of the versions [`DeltaBackend`] stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeltaStats {
+    /// Versions stored whole
+    pub full_stores: u64,
+    /// Versions stored as a diff
+    pub delta_stores: u64,
+    /// Bytes not written thanks to diffs
+    pub bytes_saved: u64,
+}
+
+/// L2 backend storing updated versions of a key as diffs
+pub struct DeltaBackend {
+    inner: Arc<dyn CacheBackend>,
+    min_size: usize,
+    max_delta_ratio: f64,
+    full_stores: AtomicU64,
+    delta_stores: AtomicU64,
+    bytes_saved: AtomicU64,
+}
+
+impl DeltaBackend {
+    /// Store entries in `inner`, diffing updates of 64KB or more
+    pub fn new(inner: Arc<dyn CacheBackend>) -> Self {
+        DeltaBackend {
+            inner,
+            min_size: DEFAULT_MIN_SIZE,
+            max_delta_ratio: DEFAULT_MAX_DELTA_RATIO,
+            full_stores: AtomicU64::new(0),
+            delta_stores: AtomicU64::new(0),
+            bytes_saved: AtomicU64::new(0),
+        }
+    }
+
+    /// Store entries smaller than `bytes` whole (default: 64KB)
+    pub fn with_min_size(mut self, bytes: usize) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    /// Store a version whole when its diff is larger than `ratio` times its
+    /// own size (default: 0.5)
+    ///
+    /// Lower ratios only keep diffs of versions that are very similar to
+    /// their base. Ratios above 1.0 are treated as 1.0: a diff larger than
+    /// the version itself is never kept.
+    pub fn with_max_delta_ratio(mut self, ratio: f64) -> Self {
+        self.max_delta_ratio = ratio.min(1.0);
+        self
+    }
+
+    /// Versions stored whole and as diffs so far
+    pub fn delta_stats(&self) -> DeltaStats {
+        DeltaStats {
+            full_stores: self.full_stores.load(Ordering::Relaxed),
+            delta_stores: self.delta_stores.load(Ordering::Relaxed),
+            bytes_saved: self.bytes_saved.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Version the next update of `key` is diffed against
+    ///
+    /// # Returns
+    /// The base, and whether it still has to be written under `base_key`
+    /// because it is the whole version currently stored under `key`
+    async fn base_for(&self, key: &str, base_key: &str, ttl: Duration) -> Result<Option<(Bytes, bool)>> {
+        if let Some(base) = self.inner.lookup(base_key).await? {
+            let outlives = match self.inner.entry(base_key).await? {
+                Some(entry) => entry.ttl_remaining >= ttl,
+                None => true,
+            };
+            return Ok(outlives.then_some((base, false)));
+        }
+
+        let current = self.inner.lookup(key).await?;
+        Ok(current
+            .filter(|record| record.first() == Some(&FULL_RECORD) && record.len() > self.min_size)
+            .map(|record| (record.slice(1..), true)))
+    }
+}
+
+/// Inner key holding the base version of `key`
+fn base_key(key: &str) -> String {
+    format!("{}{}", key, BASE_SUFFIX)
+}
+
+/// `payload` prefixed with the record type
+fn record(kind: u8, payload: &[u8]) -> Bytes {
+    let mut out = Vec::with_capacity(payload.len() + 1);
+    out.push(kind);
+    out.extend_from_slice(payload);
+    Bytes::from(out)
+}
+
+#[async_trait]
+impl CacheBackend for DeltaBackend {
+    async fn store(&self, key: &str, data: Bytes, ttl: Duration) -> Result<()> {
+        let base_key = base_key(key);
+        if data.len() >= self.min_size {
+            if let Some((base, promote)) = self.base_for(key, &base_key, ttl).await? {
+                let delta = encode_delta(&base, &data);
+                if delta.len() as f64 <= data.len() as f64 * self.max_delta_ratio {
+                    if promote {
+                        self.inner.store(&base_key, base, ttl).await?;
+                    }
+                    self.inner.store(key, record(DELTA_RECORD, &delta), ttl).await?;
+                    self.delta_stores.fetch_add(1, Ordering::Relaxed);
+                    self.bytes_saved
+                        .fetch_add(data.len().saturating_sub(delta.len()) as u64, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+        }
+
+        // Written before dropping the base so a failed store leaves the
+        // previous version readable
+        self.inner.store(key, record(FULL_RECORD, &data), ttl).await?;
+        self.inner.remove(&base_key).await?;
+        self.full_stores.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn lookup(&self, key: &str) -> Result<Option<Bytes>> {
+        let Some(stored) = self.inner.lookup(key).await? else {
+            return Ok(None);
+        };
+        match stored.first() {
+            Some(&FULL_RECORD) => Ok(Some(stored.slice(1..))),
+            Some(&DELTA_RECORD) => {
+                // A diff whose base expired or was evicted is a miss
+                let Some(base) = self.inner.lookup(&base_key(key)).await? else {
+                    return Ok(None);
+                };
+                let data = apply_delta(&base, &stored[1..]).ok_or_else(|| {
+                    SliceError::CacheError(format!("Corrupt delta entry for {}", key))
+                })?;
+                Ok(Some(Bytes::from(data)))
+            }
+            _ => Err(SliceError::CacheError(format!("Unknown delta record for {}", key))),
+        }
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool> {
+        let removed = self.inner.remove(key).await?;
+        self.inner.remove(&base_key(key)).await?;
+        Ok(removed)
+    }
+
+    async fn purge_all(&self) -> Result<usize> {
+        self.inner.purge_all().await
+    }
+
+    fn stats(&self) -> CacheBackendStats {
+        self.inner.stats()
+    }
+
+    async fn health(&self) -> Result<()> {
+        self.inner.health().await
+    }
+
+    /// Reports the stored record, so the size is that of the diff for
+    /// entries stored as one
+    async fn entry(&self, key: &str) -> Result<Option<BackendEntry>> {
+        self.inner.entry(key).await
+    }
+
+    /// Leaves out the bases, which are not entries of their own
+    async fn visit_entries(&self, visit: &mut (dyn FnMut(String, BackendEntry) + Send)) -> Result<bool> {
+        self.inner
+            .visit_entries(&mut |name: String, entry| {
+                if !name.ends_with(BASE_SUFFIX) {
+                    visit(name, entry);
+                }
+            })
+            .await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// FNV-1a hash identifying the base a diff was taken against
+fn fingerprint(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Multiplier of the rolling block hash
+const HASH_BASE: u64 = 0x100_0000_01b3;
+
+/// Hash of a `BLOCK_SIZE` window, updatable one byte at a time
+fn block_hash(block: &[u8]) -> u64 {
+    block
+        .iter()
+        .fold(0u64, |hash, &byte| hash.wrapping_mul(HASH_BASE).wrapping_add(byte as u64))
+}
+
+/// Diff turning `base` into `target`
+///
+/// Header: base length, base fingerprint and target length. Then a list of
+/// operations: copy a range of the base, or insert literal bytes. Matches
+/// are found by hashing the base in aligned blocks and rolling a window of
+/// the same size over the target.
+pub(crate) fn encode_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(base.len() as u64).to_le_bytes());
+    out.extend_from_slice(&fingerprint(base).to_le_bytes());
+    out.extend_from_slice(&(target.len() as u64).to_le_bytes());
+
+    let mut blocks: HashMap<u64, usize> = HashMap::new();
+    for offset in (0..base.len().saturating_sub(BLOCK_SIZE - 1)).step_by(BLOCK_SIZE) {
+        blocks
+            .entry(block_hash(&base[offset..offset + BLOCK_SIZE]))
+            .or_insert(offset);
+    }
+
+    // Weight of the byte leaving the window
+    let top = (1..BLOCK_SIZE).fold(1u64, |weight, _| weight.wrapping_mul(HASH_BASE));
+
+    let mut literal_start = 0;
+    let mut pos = 0;
+    let mut hash = None;
+    while pos + BLOCK_SIZE <= target.len() {
+        let window = *hash.get_or_insert_with(|| block_hash(&target[pos..pos + BLOCK_SIZE]));
+        let matched = blocks
+            .get(&window)
+            .copied()
+            .filter(|&offset| base[offset..offset + BLOCK_SIZE] == target[pos..pos + BLOCK_SIZE]);
+
+        let Some(mut offset) = matched else {
+            if pos + BLOCK_SIZE < target.len() {
+                let leaving = (target[pos] as u64).wrapping_mul(top);
+                hash = Some(
+                    window
+                        .wrapping_sub(leaving)
+                        .wrapping_mul(HASH_BASE)
+                        .wrapping_add(target[pos + BLOCK_SIZE] as u64),
+                );
+            }
+            pos += 1;
+            continue;
+        };
+
+        // Grow the match into the pending literal and past the block
+        let mut start = pos;
+        while start > literal_start && offset > 0 && base[offset - 1] == target[start - 1] {
+            start -= 1;
+            offset -= 1;
+        }
+        let len = base[offset..]
+            .iter()
+            .zip(&target[start..])
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        push_insert(&mut out, &target[literal_start..start]);
+        out.push(COPY_OP);
+        out.extend_from_slice(&(offset as u64).to_le_bytes());
+        out.extend_from_slice(&(len as u64).to_le_bytes());
+
+        pos = start + len;
+        literal_start = pos;
+        hash = None;
+    }
+    push_insert(&mut out, &target[literal_start..]);
+    out
+}
+
+fn push_insert(out: &mut Vec<u8>, literal: &[u8]) {
+    if literal.is_empty() {
+        return;
+    }
+    out.push(INSERT_OP);
+    out.extend_from_slice(&(literal.len() as u64).to_le_bytes());
+    out.extend_from_slice(literal);
+}
+
+/// Rebuild the target of `delta` from `base`
+///
+/// # Returns
+/// `None` if the diff is malformed or was not taken against `base`
+pub(crate) fn apply_delta(base: &[u8], mut delta: &[u8]) -> Option<Vec<u8>> {
+    fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        if data.len() < len {
+            return None;
+        }
+        let (head, rest) = data.split_at(len);
+        *data = rest;
+        Some(head)
+    }
+    fn take_u64(data: &mut &[u8]) -> Option<u64> {
+        Some(u64::from_le_bytes(take(data, 8)?.try_into().ok()?))
+    }
+
+    let base_len = take_u64(&mut delta)?;
+    let base_hash = take_u64(&mut delta)?;
+    if base_len != base.len() as u64 || base_hash != fingerprint(base) {
+        return None;
+    }
+    let target_len = usize::try_from(take_u64(&mut delta)?).ok()?;
+
+    let mut out = Vec::with_capacity(target_len);
+    while let Some(&op) = delta.first() {
+        delta = &delta[1..];
+        match op {
+            COPY_OP => {
+                let offset = usize::try_from(take_u64(&mut delta)?).ok()?;
+                let len = usize::try_from(take_u64(&mut delta)?).ok()?;
+                out.extend_from_slice(base.get(offset..offset.checked_add(len)?)?);
+            }
+            INSERT_OP => {
+                let len = usize::try_from(take_u64(&mut delta)?).ok()?;
+                out.extend_from_slice(take(&mut delta, len)?);
+            }
+            _ => return None,
+        }
+        if out.len() > target_len {
+            return None;
+        }
+    }
+    (out.len() == target_len).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache_backend::FileBackend;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// Bytes that do not compress or repeat
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    fn disk_usage(dir: &Path) -> u64 {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let metadata = entry.metadata().unwrap();
+                if metadata.is_dir() {
+                    disk_usage(&entry.path())
+                } else {
+                    metadata.len()
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let base = noise(10_000, 1);
+        let mut target = base.clone();
+        target[5000..5010].copy_from_slice(b"0123456789");
+        target.splice(100..100, b"inserted".iter().copied());
+        target.truncate(9000);
+        target.extend_from_slice(&base[..500]);
+
+        let delta = encode_delta(&base, &target);
+        assert!(delta.len() < 200, "{} bytes", delta.len());
+        assert_eq!(apply_delta(&base, &delta).unwrap(), target);
+
+        // Unrelated and empty inputs still round trip
+        let other = noise(3000, 2);
+        assert_eq!(apply_delta(&base, &encode_delta(&base, &other)).unwrap(), other);
+        assert_eq!(apply_delta(&[], &encode_delta(&[], &other)).unwrap(), other);
+        assert_eq!(apply_delta(&base, &encode_delta(&base, &[])).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_delta_refuses_other_base() {
+        let base = noise(4096, 1);
+        let delta = encode_delta(&base, &noise(4096, 2));
+        assert!(apply_delta(&noise(4096, 3), &delta).is_none());
+        assert!(apply_delta(&base, &delta[..delta.len() - 1]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_max_delta_ratio_is_capped() {
+        let temp_dir = TempDir::new().unwrap();
+        let inner = Arc::new(FileBackend::new(temp_dir.path()).await.unwrap());
+        let backend = DeltaBackend::new(inner).with_max_delta_ratio(2.0);
+        let ttl = Duration::from_secs(60);
+        let key = "http://example.com/backup.tar:0:131071";
+
+        // An unrelated version's diff is larger than the version itself
+        backend.store(key, Bytes::from(noise(128 * 1024, 1)), ttl).await.unwrap();
+        let v2 = noise(128 * 1024, 2);
+        backend.store(key, Bytes::from(v2.clone()), ttl).await.unwrap();
+        assert_eq!(backend.lookup(key).await.unwrap().unwrap(), v2);
+        let stats = backend.delta_stats();
+        assert_eq!((stats.full_stores, stats.delta_stores, stats.bytes_saved), (2, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_updated_version_stored_as_delta() {
+        let temp_dir = TempDir::new().unwrap();
+        let inner = Arc::new(FileBackend::new(temp_dir.path()).await.unwrap());
+        let backend = DeltaBackend::new(inner);
+        let ttl = Duration::from_secs(60);
+        let key = "http://example.com/backup.tar:0:262143";
+
+        let v1 = noise(256 * 1024, 1);
+        backend.store(key, Bytes::from(v1.clone()), ttl).await.unwrap();
+        let after_v1 = disk_usage(temp_dir.path());
+
+        let mut v2 = v1.clone();
+        v2[1000..1100].copy_from_slice(&noise(100, 2));
+        v2[200_000..200_050].copy_from_slice(&noise(50, 3));
+        backend.store(key, Bytes::from(v2.clone()), ttl).await.unwrap();
+        let after_v2 = disk_usage(temp_dir.path());
+
+        // v1 moved to the base, v2 only added its diff
+        assert!(after_v2 - after_v1 < 2048, "{} extra bytes", after_v2 - after_v1);
+        assert_eq!(backend.lookup(key).await.unwrap().unwrap(), v2);
+        assert_eq!(backend.delta_stats().delta_stores, 1);
+
+        // Later versions are diffed against the same base
+        let mut v3 = v1.clone();
+        v3[50_000..50_010].copy_from_slice(b"0123456789");
+        backend.store(key, Bytes::from(v3.clone()), ttl).await.unwrap();
+        assert_eq!(backend.lookup(key).await.unwrap().unwrap(), v3);
+
+        let mut names = Vec::new();
+        backend.visit_entries(&mut |name, _| names.push(name)).await.unwrap();
+        assert_eq!(names.len(), 1);
+
+        assert!(backend.remove(key).await.unwrap());
+        assert!(backend.lookup(key).await.unwrap().is_none());
+        assert_eq!(disk_usage(temp_dir.path()), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dissimilar_and_small_versions_stored_whole() {
+        let temp_dir = TempDir::new().unwrap();
+        let inner = Arc::new(FileBackend::new(temp_dir.path()).await.unwrap());
+        let backend = DeltaBackend::new(inner).with_min_size(1024);
+        let ttl = Duration::from_secs(60);
+
+        backend.store("a", Bytes::from(noise(4096, 1)), ttl).await.unwrap();
+        backend.store("a", Bytes::from(noise(4096, 2)), ttl).await.unwrap();
+        assert_eq!(backend.lookup("a").await.unwrap().unwrap(), noise(4096, 2));
+
+        backend.store("b", Bytes::from(vec![1u8; 512]), ttl).await.unwrap();
+        backend.store("b", Bytes::from(vec![2u8; 512]), ttl).await.unwrap();
+        assert_eq!(backend.lookup("b").await.unwrap().unwrap(), vec![2u8; 512]);
+
+        let stats = backend.delta_stats();
+        assert_eq!(stats.delta_stores, 0);
+        assert_eq!(stats.full_stores, 4);
+    }
+}
@@ -20,6 +20,9 @@ pub enum SliceError {
     #[error("Subrequest failed for slice {slice_index} after {attempts} attempts")]
     SubrequestFailed { slice_index: usize, attempts: usize },
 
+    #[error("Gave up on range requests after {failures} failed slice fetches")]
+    SliceFailureLimit { failures: usize },
+
     #[error("Cache error: {0}")]
     CacheError(String),
 
@@ -50,11 +53,23 @@ pub enum SliceError {
     #[error("Unsatisfiable range: {0}")]
     UnsatisfiableRange(String),
 
+    #[error("Range not satisfiable: the file is {total_size} bytes")]
+    RangeNotSatisfiable { total_size: u64 },
+
     #[error("Network timeout: {0}")]
     Timeout(String),
 
+    #[error("Request deadline exceeded: {0}")]
+    DeadlineExceeded(String),
+
     #[error("Internal error: {0}")]
     InternalError(String),
+
+    #[error("Client disconnected before the response completed")]
+    ClientAborted,
+
+    #[error("Client rate limit exceeded, retry after {retry_after_secs}s")]
+    RateLimited { retry_after_secs: u64 },
 }
 
 impl From<std::io::Error> for SliceError {
@@ -88,6 +103,9 @@ impl SliceError {
             // Network and IO errors should be retried
             SliceError::Timeout(_) => true,
             SliceError::IoError(_) => true,
+
+            // The request as a whole ran out of time or retries
+            SliceError::DeadlineExceeded(_) => false,
             
             // Content-Range mismatch might be transient
             SliceError::ContentRangeMismatch { .. } => true,
@@ -102,18 +120,41 @@ impl SliceError {
             SliceError::ConfigError(_) => false,
             SliceError::InvalidRange(_) => false,
             SliceError::UnsatisfiableRange(_) => false,
+            SliceError::RangeNotSatisfiable { .. } => false,
             SliceError::ParseError(_) => false,
             SliceError::RangeNotSupported => false,
             
             // Other errors
             SliceError::MetadataFetchError(_) => true,
             SliceError::SubrequestFailed { .. } => false, // Already exhausted retries
+            SliceError::SliceFailureLimit { .. } => false,
             SliceError::CacheError(_) => false, // Cache errors shouldn't block request
             SliceError::AssemblyError(_) => false,
             SliceError::InternalError(_) => false,
+            SliceError::ClientAborted => false,
+            SliceError::RateLimited { .. } => false,
         }
     }
 
+    /// Whether the error means the origin is failing, so that an expired
+    /// cached copy may be served in its place (`stale_if_error_secs`)
+    ///
+    /// True for 5xx responses, timeouts and connection errors, including
+    /// subrequests that failed every retry; false for 4xx responses and
+    /// for errors on this side.
+    pub fn is_origin_failure(&self) -> bool {
+        matches!(
+            self,
+            SliceError::OriginServerError { .. }
+                | SliceError::Timeout(_)
+                | SliceError::IoError(_)
+                | SliceError::HttpError(_)
+                | SliceError::MetadataFetchError(_)
+                | SliceError::SubrequestFailed { .. }
+                | SliceError::SliceFailureLimit { .. }
+        )
+    }
+
     /// Convert error to HTTP status code
     /// 
     /// Maps internal errors to appropriate HTTP status codes:
@@ -134,6 +175,7 @@ impl SliceError {
             // Invalid range errors return 416 (Requirement 10.5)
             SliceError::InvalidRange(_) => 416,
             SliceError::UnsatisfiableRange(_) => 416,
+            SliceError::RangeNotSatisfiable { .. } => 416,
             
             // Parse errors are client errors
             SliceError::ParseError(_) => 400,
@@ -141,8 +183,10 @@ impl SliceError {
             // Network and subrequest errors become 502 Bad Gateway
             SliceError::MetadataFetchError(_) => 502,
             SliceError::SubrequestFailed { .. } => 502,
+            SliceError::SliceFailureLimit { .. } => 502,
             SliceError::HttpError(_) => 502,
             SliceError::Timeout(_) => 504, // Gateway Timeout
+            SliceError::DeadlineExceeded(_) => 504,
             SliceError::ContentRangeMismatch { .. } => 502,
             
             // Internal errors return 500
@@ -152,6 +196,36 @@ impl SliceError {
             SliceError::AssemblyError(_) => 500,
             SliceError::IoError(_) => 500,
             SliceError::InternalError(_) => 500,
+            // Nobody is left to receive it, but logs follow the nginx convention
+            SliceError::ClientAborted => 499,
+            SliceError::RateLimited { .. } => 429,
+        }
+    }
+
+    /// Short name of the error variant, used in access logs
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            SliceError::ConfigError(_) => "config_error",
+            SliceError::MetadataFetchError(_) => "metadata_fetch_error",
+            SliceError::RangeNotSupported => "range_not_supported",
+            SliceError::SubrequestFailed { .. } => "subrequest_failed",
+            SliceError::SliceFailureLimit { .. } => "slice_failure_limit",
+            SliceError::CacheError(_) => "cache_error",
+            SliceError::AssemblyError(_) => "assembly_error",
+            SliceError::InvalidRange(_) => "invalid_range",
+            SliceError::IoError(_) => "io_error",
+            SliceError::HttpError(_) => "http_error",
+            SliceError::ParseError(_) => "parse_error",
+            SliceError::OriginClientError { .. } => "origin_client_error",
+            SliceError::OriginServerError { .. } => "origin_server_error",
+            SliceError::ContentRangeMismatch { .. } => "content_range_mismatch",
+            SliceError::UnsatisfiableRange(_) => "unsatisfiable_range",
+            SliceError::RangeNotSatisfiable { .. } => "range_not_satisfiable",
+            SliceError::Timeout(_) => "timeout",
+            SliceError::DeadlineExceeded(_) => "deadline_exceeded",
+            SliceError::InternalError(_) => "internal_error",
+            SliceError::ClientAborted => "client_aborted",
+            SliceError::RateLimited { .. } => "rate_limited",
         }
     }
 
@@ -0,0 +1,139 @@
+//! Static error pages for failed requests
+//!
+//! [`ErrorPages`] holds the bodies configured in `error_pages`, read once
+//! when the proxy is created, and builds the response sent to the client
+//! when a request fails with one of their statuses, e.g. because the origin
+//! is down and nothing is cached.
+
+use crate::config::{ErrorPageConfig, SliceConfig};
+use crate::error::{Result, SliceError};
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderValue};
+use http::StatusCode;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// A loaded error page
+#[derive(Debug, Clone)]
+struct ErrorPage {
+    content_type: HeaderValue,
+    body: Bytes,
+}
+
+/// Error page bodies by response status
+#[derive(Debug, Clone, Default)]
+pub struct ErrorPages {
+    pages: HashMap<u16, ErrorPage>,
+}
+
+impl ErrorPages {
+    /// Read the body of every page
+    ///
+    /// # Returns
+    /// * `Err(SliceError)` if a status or content type is invalid or a file
+    ///   cannot be read
+    pub fn load(configs: &[ErrorPageConfig]) -> Result<Self> {
+        let mut pages = HashMap::new();
+        for config in configs {
+            StatusCode::from_u16(config.status).map_err(|_| {
+                SliceError::ConfigError(format!("Invalid error page status: {}", config.status))
+            })?;
+            let content_type = HeaderValue::from_str(&config.content_type).map_err(|_| {
+                SliceError::ConfigError(format!(
+                    "Invalid content_type for error page {}: {:?}",
+                    config.status, config.content_type
+                ))
+            })?;
+            let body = std::fs::read(&config.path).map_err(|e| {
+                SliceError::ConfigError(format!(
+                    "Failed to read error page {} from {}: {}",
+                    config.status, config.path, e
+                ))
+            })?;
+            pages.insert(
+                config.status,
+                ErrorPage {
+                    content_type,
+                    body: Bytes::from(body),
+                },
+            );
+        }
+        Ok(ErrorPages { pages })
+    }
+
+    /// Load the `error_pages` of the configuration
+    ///
+    /// Pages that fail to load are logged and skipped;
+    /// [`SliceConfig::validate`] rejects them at load time.
+    pub fn from_config(config: &SliceConfig) -> Self {
+        let mut pages = ErrorPages::default();
+        for page in &config.error_pages {
+            match Self::load(std::slice::from_ref(page)) {
+                Ok(loaded) => pages.pages.extend(loaded.pages),
+                Err(e) => warn!("Skipping error page: {}", e),
+            }
+        }
+        pages
+    }
+
+    /// Whether no pages are configured
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Response for a request failing with `status`, if a page is configured
+    pub fn response(&self, status: u16) -> Option<(StatusCode, HeaderMap, Bytes)> {
+        let page = self.pages.get(&status)?;
+        let status = StatusCode::from_u16(status).ok()?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", page.content_type.clone());
+        headers.insert("content-length", HeaderValue::from(page.body.len()));
+        headers.insert("cache-control", HeaderValue::from_static("no-store"));
+        Some((status, headers, page.body.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn page_file(body: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(body.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_response_for_configured_status() {
+        let file = page_file("<h1>Down for maintenance</h1>");
+        let pages = ErrorPages::load(&[ErrorPageConfig {
+            status: 503,
+            path: file.path().to_string_lossy().into_owned(),
+            content_type: "text/html".to_string(),
+        }])
+        .unwrap();
+
+        let (status, headers, body) = pages.response(503).unwrap();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(headers.get("content-type").unwrap(), "text/html");
+        assert_eq!(headers.get("content-length").unwrap(), "29");
+        assert_eq!(body, Bytes::from("<h1>Down for maintenance</h1>"));
+        assert!(pages.response(502).is_none());
+    }
+
+    #[test]
+    fn test_missing_file_is_skipped() {
+        let config = SliceConfig {
+            error_pages: vec![ErrorPageConfig {
+                status: 502,
+                path: "/nonexistent/502.html".to_string(),
+                content_type: "text/html".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(ErrorPages::load(&config.error_pages).is_err());
+        assert!(ErrorPages::from_config(&config).is_empty());
+    }
+}
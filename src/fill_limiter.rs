@@ -0,0 +1,181 @@
+//! Limits on in-flight cache fills
+//!
+//! A sliced miss buffers every slice it fetches until the response is
+//! assembled, so a flash crowd for many distinct uncached files can hold a
+//! lot of memory and open as many fills against the origin.
+//! [`FillLimiter`] caps the number of fills running at once
+//! (`max_concurrent_fills`) and the bytes they buffer together
+//! (`max_fill_buffer_bytes`). A miss that does not fit is proxied in normal
+//! mode, uncached, instead of waiting.
+//!
+//! The limiter also tracks the bytes reserved when no limit is set, so the
+//! total can be exported as a gauge.
+
+use crate::config::SliceConfig;
+use crate::metrics::SliceMetrics;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Process-wide budget for cache fills
+#[derive(Debug)]
+pub struct FillLimiter {
+    /// One permit per concurrent fill (unlimited if not set)
+    fills: Option<Arc<Semaphore>>,
+    /// Permits `fills` started with
+    max_fills: usize,
+    /// Maximum bytes buffered across fills (unlimited if not set)
+    max_bytes: Option<u64>,
+    /// Bytes reserved by the fills in flight
+    buffered: Arc<AtomicU64>,
+    /// Metrics to mirror `buffered` into
+    metrics: Option<Arc<SliceMetrics>>,
+}
+
+/// One admitted fill; returns its budget on drop
+#[derive(Debug)]
+pub struct FillGuard {
+    _permit: Option<OwnedSemaphorePermit>,
+    bytes: u64,
+    buffered: Arc<AtomicU64>,
+    metrics: Option<Arc<SliceMetrics>>,
+}
+
+impl FillLimiter {
+    /// Create a limiter
+    ///
+    /// # Arguments
+    /// * `max_fills` - Maximum concurrent fills (unlimited if `None`)
+    /// * `max_bytes` - Maximum bytes buffered across fills (unlimited if `None`)
+    pub fn new(max_fills: Option<usize>, max_bytes: Option<u64>) -> Self {
+        FillLimiter {
+            fills: max_fills.map(|n| Arc::new(Semaphore::new(n))),
+            max_fills: max_fills.unwrap_or(0),
+            max_bytes,
+            buffered: Arc::new(AtomicU64::new(0)),
+            metrics: None,
+        }
+    }
+
+    /// Create a limiter from `max_concurrent_fills` and `max_fill_buffer_bytes`
+    ///
+    /// With neither set every fill is admitted, but its bytes are still
+    /// counted.
+    pub fn from_config(config: &SliceConfig) -> Self {
+        Self::new(config.max_concurrent_fills, config.max_fill_buffer_bytes)
+    }
+
+    /// Report the bytes reserved by fills as `fill_buffered_bytes`
+    pub fn with_metrics(mut self, metrics: Arc<SliceMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Admit a fill that will buffer `bytes`, without waiting
+    ///
+    /// # Returns
+    /// `None` if all fill permits are taken or the bytes do not fit in the
+    /// remaining buffer budget
+    pub fn try_acquire(&self, bytes: u64) -> Option<FillGuard> {
+        let permit = match &self.fills {
+            Some(fills) => Some(fills.clone().try_acquire_owned().ok()?),
+            None => None,
+        };
+
+        let max_bytes = self.max_bytes.unwrap_or(u64::MAX);
+        self.buffered
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |buffered| {
+                buffered.checked_add(bytes).filter(|&total| total <= max_bytes)
+            })
+            .ok()?;
+        if let Some(metrics) = &self.metrics {
+            metrics.add_fill_buffered_bytes(bytes);
+        }
+
+        Some(FillGuard {
+            _permit: permit,
+            bytes,
+            buffered: self.buffered.clone(),
+            metrics: self.metrics.clone(),
+        })
+    }
+
+    /// Bytes reserved by the fills in flight
+    pub fn buffered_bytes(&self) -> u64 {
+        self.buffered.load(Ordering::Acquire)
+    }
+
+    /// Number of fills in flight, if the count is limited
+    pub fn active_fills(&self) -> Option<usize> {
+        let fills = self.fills.as_ref()?;
+        Some(self.max_fills - fills.available_permits())
+    }
+}
+
+impl FillGuard {
+    /// Bytes this fill reserved
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for FillGuard {
+    fn drop(&mut self) {
+        self.buffered.fetch_sub(self.bytes, Ordering::AcqRel);
+        if let Some(metrics) = &self.metrics {
+            metrics.release_fill_buffered_bytes(self.bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_fill_limit() {
+        let limiter = FillLimiter::new(Some(2), None);
+        let a = limiter.try_acquire(100).unwrap();
+        let _b = limiter.try_acquire(100).unwrap();
+        assert!(limiter.try_acquire(100).is_none());
+        assert_eq!(limiter.active_fills(), Some(2));
+
+        drop(a);
+        assert!(limiter.try_acquire(100).is_some());
+    }
+
+    #[test]
+    fn test_buffer_budget() {
+        let limiter = FillLimiter::new(None, Some(1000));
+        let a = limiter.try_acquire(600).unwrap();
+        assert!(limiter.try_acquire(600).is_none());
+        let b = limiter.try_acquire(400).unwrap();
+        assert_eq!(limiter.buffered_bytes(), 1000);
+
+        drop(a);
+        drop(b);
+        assert_eq!(limiter.buffered_bytes(), 0);
+        assert!(limiter.try_acquire(2000).is_none());
+    }
+
+    #[test]
+    fn test_rejected_bytes_release_permit() {
+        let limiter = FillLimiter::new(Some(1), Some(100));
+        assert!(limiter.try_acquire(200).is_none());
+        assert!(limiter.try_acquire(50).is_some());
+    }
+
+    #[test]
+    fn test_buffered_bytes_gauge() {
+        let metrics = Arc::new(SliceMetrics::new());
+        let limiter = FillLimiter::new(None, None).with_metrics(metrics.clone());
+        let a = limiter.try_acquire(300).unwrap();
+        let b = limiter.try_acquire(200).unwrap();
+        assert_eq!(metrics.get_stats().fill_buffered_bytes, 500);
+
+        drop(a);
+        assert_eq!(metrics.get_stats().fill_buffered_bytes, 200);
+        drop(b);
+        assert_eq!(metrics.get_stats().fill_buffered_bytes, 0);
+    }
+}
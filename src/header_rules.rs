@@ -0,0 +1,358 @@
+//! Configurable header rewriting
+//!
+//! [`HeaderRewriter`] applies the `header_rules` of the configuration to
+//! origin requests (slice subrequests, metadata probes and proxied requests)
+//! and to responses sent to the client, e.g. to add an auth token for the
+//! origin or strip `Set-Cookie` from responses.
+//!
+//! [`ForwardedHeaders`] builds the `X-Forwarded-*` and `Forwarded` headers
+//! describing the client of each request.
+
+use crate::config::{
+    ForwardedHeadersConfig, ForwardedMode, HeaderAction, HeaderRule, HeaderTarget, SliceConfig,
+};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use std::net::{IpAddr, SocketAddr};
+
+/// A header rule with its name and value parsed
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    action: HeaderAction,
+    name: HeaderName,
+    value: Option<HeaderValue>,
+}
+
+impl CompiledRule {
+    fn compile(rule: &HeaderRule) -> Option<Self> {
+        let name = HeaderName::from_bytes(rule.name.as_bytes()).ok()?;
+        let value = match (&rule.action, &rule.value) {
+            (HeaderAction::Remove, _) => None,
+            (_, value) => Some(HeaderValue::from_str(value.as_deref()?).ok()?),
+        };
+        Some(CompiledRule {
+            action: rule.action,
+            name,
+            value,
+        })
+    }
+
+    fn apply(&self, headers: &mut HeaderMap) {
+        match (&self.action, &self.value) {
+            (HeaderAction::Add, Some(value)) => {
+                headers.append(self.name.clone(), value.clone());
+            }
+            (HeaderAction::Set, Some(value)) => {
+                headers.insert(self.name.clone(), value.clone());
+            }
+            (HeaderAction::Remove, _) => {
+                headers.remove(&self.name);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Applies header rewrite rules to origin requests and client responses
+#[derive(Debug, Clone, Default)]
+pub struct HeaderRewriter {
+    request: Vec<CompiledRule>,
+    response: Vec<CompiledRule>,
+}
+
+impl HeaderRewriter {
+    /// Create a rewriter applying `rules` in order
+    ///
+    /// Rules with an invalid name or value are skipped;
+    /// [`SliceConfig::validate`] rejects them at load time.
+    pub fn new(rules: &[HeaderRule]) -> Self {
+        let mut rewriter = HeaderRewriter::default();
+        for rule in rules {
+            let Some(compiled) = CompiledRule::compile(rule) else {
+                continue;
+            };
+            match rule.apply_to {
+                HeaderTarget::UpstreamRequest => rewriter.request.push(compiled),
+                HeaderTarget::Response => rewriter.response.push(compiled),
+                HeaderTarget::Both => {
+                    rewriter.request.push(compiled.clone());
+                    rewriter.response.push(compiled);
+                }
+            }
+        }
+        rewriter
+    }
+
+    /// Create a rewriter from `upstream_user_agent` and `header_rules`
+    ///
+    /// The user agent is set before the rules run, so a rule can still
+    /// override it.
+    pub fn from_config(config: &SliceConfig) -> Self {
+        let mut rules = Vec::with_capacity(config.header_rules.len() + 1);
+        if let Some(user_agent) = &config.upstream_user_agent {
+            rules.push(HeaderRule {
+                apply_to: HeaderTarget::UpstreamRequest,
+                action: HeaderAction::Set,
+                name: "user-agent".to_string(),
+                value: Some(user_agent.clone()),
+            });
+        }
+        rules.extend(config.header_rules.iter().cloned());
+        Self::new(&rules)
+    }
+
+    /// Whether no rules are configured
+    pub fn is_empty(&self) -> bool {
+        self.request.is_empty() && self.response.is_empty()
+    }
+
+    /// Rewrite the headers of a request to the origin
+    pub fn rewrite_request(&self, headers: &mut HeaderMap) {
+        for rule in &self.request {
+            rule.apply(headers);
+        }
+    }
+
+    /// Rewrite the headers of a response to the client
+    pub fn rewrite_response(&self, headers: &mut HeaderMap) {
+        for rule in &self.response {
+            rule.apply(headers);
+        }
+    }
+
+    /// Headers to send with every origin request: `base` after the request rules
+    pub fn request_headers(&self, base: HeaderMap) -> HeaderMap {
+        let mut headers = base;
+        self.rewrite_request(&mut headers);
+        headers
+    }
+}
+
+/// Builds the forwarding headers for a request to the origin
+#[derive(Debug, Clone, Default)]
+pub struct ForwardedHeaders {
+    config: ForwardedHeadersConfig,
+}
+
+impl ForwardedHeaders {
+    /// Create a builder for the enabled headers
+    pub fn new(config: ForwardedHeadersConfig) -> Self {
+        ForwardedHeaders { config }
+    }
+
+    /// Create a builder from `forwarded_headers`
+    pub fn from_config(config: &SliceConfig) -> Self {
+        Self::new(config.forwarded_headers.clone())
+    }
+
+    /// Whether no headers are enabled
+    pub fn is_empty(&self) -> bool {
+        !self.config.is_enabled()
+    }
+
+    /// Forwarding headers for a request from `client_addr` over `scheme`
+    ///
+    /// In append mode the values in `request`, set by a proxy in front of
+    /// this one, are extended with this hop and an incoming
+    /// `X-Forwarded-Proto` is kept; in set mode they are replaced.
+    /// `X-Forwarded-For` is left out when the client address is unknown or
+    /// not an IP.
+    pub fn build(&self, request: &HeaderMap, client_addr: Option<&str>, scheme: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let ip = client_addr.and_then(client_ip);
+        let append = self.config.mode == ForwardedMode::Append;
+
+        if self.config.x_forwarded_for {
+            if let Some(ip) = &ip {
+                let value = match joined(request, "x-forwarded-for") {
+                    Some(previous) if append => format!("{}, {}", previous, ip),
+                    _ => ip.to_string(),
+                };
+                insert(&mut headers, "x-forwarded-for", &value);
+            }
+        }
+
+        if self.config.x_forwarded_proto {
+            let value = match request.get("x-forwarded-proto") {
+                Some(previous) if append => previous.clone(),
+                _ => HeaderValue::from_str(scheme).unwrap_or(HeaderValue::from_static("http")),
+            };
+            headers.insert("x-forwarded-proto", value);
+        }
+
+        if self.config.forwarded {
+            let node = match &ip {
+                Some(IpAddr::V6(ip)) => format!("\"[{}]\"", ip),
+                Some(ip) => ip.to_string(),
+                None => "unknown".to_string(),
+            };
+            let element = format!("for={};proto={}", node, scheme);
+            let value = match joined(request, "forwarded") {
+                Some(previous) if append => format!("{}, {}", previous, element),
+                _ => element,
+            };
+            insert(&mut headers, "forwarded", &value);
+        }
+
+        headers
+    }
+}
+
+/// IP of a client address given as `ip` or `ip:port`
+fn client_ip(addr: &str) -> Option<IpAddr> {
+    addr.parse::<SocketAddr>()
+        .map(|addr| addr.ip())
+        .or_else(|_| addr.parse::<IpAddr>())
+        .ok()
+}
+
+/// All values of a header, comma-joined
+fn joined(headers: &HeaderMap, name: &str) -> Option<String> {
+    let values: Vec<&str> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+fn insert(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(apply_to: HeaderTarget, action: HeaderAction, name: &str, value: Option<&str>) -> HeaderRule {
+        HeaderRule {
+            apply_to,
+            action,
+            name: name.to_string(),
+            value: value.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_add_set_remove() {
+        let rewriter = HeaderRewriter::new(&[
+            rule(HeaderTarget::Response, HeaderAction::Add, "x-tag", Some("b")),
+            rule(HeaderTarget::Response, HeaderAction::Set, "cache-control", Some("public")),
+            rule(HeaderTarget::Response, HeaderAction::Remove, "set-cookie", None),
+        ]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tag", HeaderValue::from_static("a"));
+        headers.append("cache-control", HeaderValue::from_static("private"));
+        headers.append("cache-control", HeaderValue::from_static("no-store"));
+        headers.append("set-cookie", HeaderValue::from_static("a=1"));
+        headers.append("set-cookie", HeaderValue::from_static("b=2"));
+        rewriter.rewrite_response(&mut headers);
+
+        let tags: Vec<_> = headers.get_all("x-tag").iter().collect();
+        assert_eq!(tags, ["a", "b"]);
+        let cache_control: Vec<_> = headers.get_all("cache-control").iter().collect();
+        assert_eq!(cache_control, ["public"]);
+        assert!(!headers.contains_key("set-cookie"));
+    }
+
+    #[test]
+    fn test_rules_apply_to_their_target() {
+        let rewriter = HeaderRewriter::new(&[
+            rule(HeaderTarget::UpstreamRequest, HeaderAction::Set, "authorization", Some("Bearer t")),
+            rule(HeaderTarget::Response, HeaderAction::Set, "x-served-by", Some("slice")),
+            rule(HeaderTarget::Both, HeaderAction::Set, "x-env", Some("prod")),
+        ]);
+
+        let request = rewriter.request_headers(HeaderMap::new());
+        assert_eq!(request.get("authorization").unwrap(), "Bearer t");
+        assert_eq!(request.get("x-env").unwrap(), "prod");
+        assert!(!request.contains_key("x-served-by"));
+
+        let mut response = HeaderMap::new();
+        rewriter.rewrite_response(&mut response);
+        assert_eq!(response.get("x-served-by").unwrap(), "slice");
+        assert_eq!(response.get("x-env").unwrap(), "prod");
+        assert!(!response.contains_key("authorization"));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_skipped() {
+        let rewriter = HeaderRewriter::new(&[
+            rule(HeaderTarget::Both, HeaderAction::Set, "bad header", Some("x")),
+            rule(HeaderTarget::Both, HeaderAction::Add, "x-missing-value", None),
+        ]);
+        assert!(rewriter.is_empty());
+    }
+
+    fn forwarded(mode: ForwardedMode) -> ForwardedHeaders {
+        ForwardedHeaders::new(ForwardedHeadersConfig {
+            x_forwarded_for: true,
+            x_forwarded_proto: true,
+            forwarded: true,
+            mode,
+        })
+    }
+
+    #[test]
+    fn test_forwarded_headers_for_direct_client() {
+        let headers = forwarded(ForwardedMode::Append).build(&HeaderMap::new(), Some("192.0.2.10:51234"), "https");
+        assert_eq!(headers.get("x-forwarded-for").unwrap(), "192.0.2.10");
+        assert_eq!(headers.get("x-forwarded-proto").unwrap(), "https");
+        assert_eq!(headers.get("forwarded").unwrap(), "for=192.0.2.10;proto=https");
+
+        let headers = forwarded(ForwardedMode::Append).build(&HeaderMap::new(), Some("[2001:db8::1]:443"), "http");
+        assert_eq!(headers.get("x-forwarded-for").unwrap(), "2001:db8::1");
+        assert_eq!(headers.get("forwarded").unwrap(), "for=\"[2001:db8::1]\";proto=http");
+    }
+
+    #[test]
+    fn test_forwarded_headers_append_or_set() {
+        let mut request = HeaderMap::new();
+        request.append("x-forwarded-for", HeaderValue::from_static("203.0.113.1"));
+        request.append("x-forwarded-for", HeaderValue::from_static("203.0.113.2"));
+        request.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        request.insert("forwarded", HeaderValue::from_static("for=203.0.113.1;proto=https"));
+
+        let headers = forwarded(ForwardedMode::Append).build(&request, Some("10.0.0.5"), "http");
+        assert_eq!(
+            headers.get("x-forwarded-for").unwrap(),
+            "203.0.113.1, 203.0.113.2, 10.0.0.5"
+        );
+        assert_eq!(headers.get("x-forwarded-proto").unwrap(), "https");
+        assert_eq!(
+            headers.get("forwarded").unwrap(),
+            "for=203.0.113.1;proto=https, for=10.0.0.5;proto=http"
+        );
+
+        let headers = forwarded(ForwardedMode::Set).build(&request, Some("10.0.0.5"), "http");
+        assert_eq!(headers.get("x-forwarded-for").unwrap(), "10.0.0.5");
+        assert_eq!(headers.get("x-forwarded-proto").unwrap(), "http");
+        assert_eq!(headers.get("forwarded").unwrap(), "for=10.0.0.5;proto=http");
+    }
+
+    #[test]
+    fn test_unknown_client() {
+        let headers = forwarded(ForwardedMode::Append).build(&HeaderMap::new(), None, "http");
+        assert!(!headers.contains_key("x-forwarded-for"));
+        assert_eq!(headers.get("forwarded").unwrap(), "for=unknown;proto=http");
+        assert!(ForwardedHeaders::default().build(&HeaderMap::new(), Some("10.0.0.5"), "http").is_empty());
+    }
+
+    #[test]
+    fn test_upstream_user_agent() {
+        let config = SliceConfig {
+            upstream_user_agent: Some("pingora-slice/1.0".to_string()),
+            ..Default::default()
+        };
+        let mut request = HeaderMap::new();
+        request.insert("user-agent", HeaderValue::from_static("curl/8.0"));
+        HeaderRewriter::from_config(&config).rewrite_request(&mut request);
+        assert_eq!(request.get("user-agent").unwrap(), "pingora-slice/1.0");
+
+        let mut response = HeaderMap::new();
+        HeaderRewriter::from_config(&config).rewrite_response(&mut response);
+        assert!(!response.contains_key("user-agent"));
+    }
+}
@@ -0,0 +1,428 @@
+//! Component health for readiness and liveness probes
+//!
+//! [`HealthChecker`] aggregates the state of what requests depend on: the
+//! disk cache directory, the proxy's tiered cache, the upstream and the
+//! recent subrequest failure rate. Upstream reachability is probed by a
+//! background task and the result cached, so health requests never reach
+//! the origin themselves.
+
+use crate::config::{HealthConfig, SliceConfig};
+use crate::metrics::SliceMetrics;
+use crate::tiered_cache::TieredCache;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+use tokio::fs;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Missed probe intervals after which the process is considered wedged
+const LIVENESS_MISSED_PROBES: u32 = 3;
+
+/// State of one component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentHealth {
+    pub healthy: bool,
+    pub detail: String,
+}
+
+impl ComponentHealth {
+    fn ok(detail: impl Into<String>) -> Self {
+        ComponentHealth {
+            healthy: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn failed(detail: impl Into<String>) -> Self {
+        ComponentHealth {
+            healthy: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Result of a readiness check
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// `healthy` if every component is healthy, `unhealthy` otherwise
+    pub status: &'static str,
+    pub components: BTreeMap<&'static str, ComponentHealth>,
+}
+
+impl HealthReport {
+    fn new(components: BTreeMap<&'static str, ComponentHealth>) -> Self {
+        let healthy = components.values().all(|c| c.healthy);
+        HealthReport {
+            status: if healthy { "healthy" } else { "unhealthy" },
+            components,
+        }
+    }
+
+    /// Whether every component is healthy
+    pub fn is_healthy(&self) -> bool {
+        self.status == "healthy"
+    }
+
+    /// Serialize the report as a JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| format!(r#"{{"status":"{}"}}"#, self.status))
+    }
+}
+
+/// Subrequest counters observed at one point in time
+#[derive(Debug, Clone, Copy)]
+struct ErrorSample {
+    at: Instant,
+    total: u64,
+    failed: u64,
+}
+
+/// Aggregates component health for the `/health` endpoints
+pub struct HealthChecker {
+    config: HealthConfig,
+    upstream_address: String,
+    metrics: Arc<SliceMetrics>,
+    cache: Option<Arc<TieredCache>>,
+    disk_dir: Option<PathBuf>,
+    /// Result of the last upstream probe, `None` until one has run
+    upstream: RwLock<Option<ComponentHealth>>,
+    samples: Mutex<VecDeque<ErrorSample>>,
+    /// When the probe task last ran, `None` if it was never started
+    heartbeat: Mutex<Option<Instant>>,
+}
+
+impl HealthChecker {
+    /// Create a health checker for the configured upstream
+    ///
+    /// # Arguments
+    /// * `config` - Configuration providing `upstream_address` and `health`
+    /// * `metrics` - Metrics the subrequest failure rate is read from
+    pub fn new(config: &SliceConfig, metrics: Arc<SliceMetrics>) -> Self {
+        let stats = metrics.get_stats();
+        let first = ErrorSample {
+            at: Instant::now(),
+            total: stats.total_subrequests,
+            failed: stats.failed_subrequests,
+        };
+        HealthChecker {
+            config: config.health.clone(),
+            upstream_address: config.upstream_address.clone(),
+            metrics,
+            cache: None,
+            disk_dir: None,
+            upstream: RwLock::new(None),
+            samples: Mutex::new(VecDeque::from([first])),
+            heartbeat: Mutex::new(None),
+        }
+    }
+
+    /// Also check the proxy's cache: that L1 is usable and, when it has
+    /// one, that L2 is not bypassed after repeated disk errors
+    pub fn with_cache(mut self, cache: Arc<TieredCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Also check that the disk cache directory is writable
+    pub fn with_disk_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.disk_dir = Some(dir.into());
+        self
+    }
+
+    /// Probe the upstream once and cache the result
+    ///
+    /// A probe is a TCP connect to `upstream_address`, which is enough to
+    /// tell a down origin from a slow one without sending a request.
+    pub async fn probe_upstream(&self) {
+        let timeout = Duration::from_millis(self.config.upstream_probe_timeout_ms);
+        let result = match tokio::time::timeout(timeout, TcpStream::connect(&self.upstream_address)).await {
+            Ok(Ok(_)) => ComponentHealth::ok(format!("{} reachable", self.upstream_address)),
+            Ok(Err(e)) => ComponentHealth::failed(format!("{} unreachable: {}", self.upstream_address, e)),
+            Err(_) => ComponentHealth::failed(format!(
+                "{} did not accept a connection within {:?}",
+                self.upstream_address, timeout
+            )),
+        };
+        if !result.healthy {
+            warn!("Upstream health probe failed: {}", result.detail);
+        }
+        *self.upstream.write().unwrap() = Some(result);
+        *self.heartbeat.lock().unwrap() = Some(Instant::now());
+        self.sample_errors();
+    }
+
+    /// Start probing the upstream every `upstream_probe_interval_secs`
+    ///
+    /// The first probe runs immediately. The task exits when the checker
+    /// is dropped.
+    pub fn spawn_probe(self: &Arc<Self>) -> JoinHandle<()> {
+        let checker: Weak<Self> = Arc::downgrade(self);
+        let interval = Duration::from_secs(self.config.upstream_probe_interval_secs);
+        tokio::spawn(async move {
+            loop {
+                let Some(checker) = checker.upgrade() else {
+                    break;
+                };
+                checker.probe_upstream().await;
+                drop(checker);
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Whether the process is still making progress
+    ///
+    /// Only fails when the probe task has stopped running for several
+    /// intervals, so a down origin or full disk does not get the process
+    /// restarted.
+    pub fn is_live(&self) -> bool {
+        let interval = Duration::from_secs(self.config.upstream_probe_interval_secs);
+        match *self.heartbeat.lock().unwrap() {
+            Some(last) => last.elapsed() < interval * LIVENESS_MISSED_PROBES,
+            None => true,
+        }
+    }
+
+    /// Check every component
+    ///
+    /// The upstream is reported unhealthy until the first probe has run.
+    pub async fn check(&self) -> HealthReport {
+        let mut components = BTreeMap::new();
+
+        if let Some(dir) = &self.disk_dir {
+            components.insert("disk_cache", Self::check_disk(dir).await);
+        }
+        if let Some(cache) = &self.cache {
+            if cache.is_accessible() {
+                components.insert(
+                    "l1_cache",
+                    ComponentHealth::ok(format!("{} entries", cache.get_stats().l1_entries)),
+                );
+            } else {
+                components.insert("l1_cache", ComponentHealth::failed("cache lock poisoned"));
+            }
+            if cache.has_l2() {
+                let health = if cache.is_l2_degraded() {
+                    ComponentHealth::failed("bypassed after repeated disk errors")
+                } else {
+                    ComponentHealth::ok("in use")
+                };
+                components.insert("l2_cache", health);
+            }
+        }
+        components.insert(
+            "upstream",
+            self.upstream
+                .read()
+                .unwrap()
+                .clone()
+                .unwrap_or_else(|| ComponentHealth::failed("not probed yet")),
+        );
+        components.insert("subrequest_errors", self.check_error_rate());
+
+        let report = HealthReport::new(components);
+        debug!("Health check: {}", report.status);
+        report
+    }
+
+    /// Write and remove a probe file in the disk cache directory
+    async fn check_disk(dir: &Path) -> ComponentHealth {
+        let probe_path = dir.join(".health-probe");
+        let result = async {
+            fs::write(&probe_path, b"probe").await?;
+            fs::remove_file(&probe_path).await
+        }
+        .await;
+        match result {
+            Ok(()) => ComponentHealth::ok(format!("{} writable", dir.display())),
+            Err(e) => ComponentHealth::failed(format!("{} not writable: {}", dir.display(), e)),
+        }
+    }
+
+    /// Record the current subrequest counters, dropping samples outside the window
+    fn sample_errors(&self) -> (ErrorSample, ErrorSample) {
+        let stats = self.metrics.get_stats();
+        let now = ErrorSample {
+            at: Instant::now(),
+            total: stats.total_subrequests,
+            failed: stats.failed_subrequests,
+        };
+        let window = Duration::from_secs(self.config.error_rate_window_secs);
+
+        let mut samples = self.samples.lock().unwrap();
+        while samples.len() > 1 && samples[1].at.elapsed() >= window {
+            samples.pop_front();
+        }
+        let oldest = samples.front().copied().unwrap_or(now);
+        samples.push_back(now);
+        (oldest, now)
+    }
+
+    /// Compare the failure ratio over the window against the threshold
+    fn check_error_rate(&self) -> ComponentHealth {
+        let (oldest, now) = self.sample_errors();
+        let total = now.total.saturating_sub(oldest.total);
+        let failed = now.failed.saturating_sub(oldest.failed);
+        let detail = format!(
+            "{} of {} subrequests failed in the last {}s",
+            failed,
+            total,
+            oldest.at.elapsed().as_secs()
+        );
+
+        if total < self.config.min_subrequests_for_error_rate {
+            return ComponentHealth::ok(detail);
+        }
+        if failed as f64 / total as f64 > self.config.max_subrequest_failure_ratio {
+            ComponentHealth::failed(detail)
+        } else {
+            ComponentHealth::ok(detail)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn checker(upstream: String, metrics: Arc<SliceMetrics>) -> HealthChecker {
+        let config = SliceConfig {
+            upstream_address: upstream,
+            ..Default::default()
+        };
+        HealthChecker::new(&config, metrics)
+    }
+
+    #[tokio::test]
+    async fn test_healthy_components() {
+        let origin = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let checker = checker(origin.local_addr().unwrap().to_string(), Arc::new(SliceMetrics::new()))
+            .with_disk_dir(dir.path())
+            .with_cache(Arc::new(
+                TieredCache::new(Duration::from_secs(60), 1024 * 1024, dir.path()).await.unwrap(),
+            ));
+
+        // Unknown until the first probe
+        assert!(!checker.check().await.is_healthy());
+
+        checker.probe_upstream().await;
+        let report = checker.check().await;
+        assert!(report.is_healthy(), "{}", report.to_json());
+        assert_eq!(
+            report.components.keys().copied().collect::<Vec<_>>(),
+            vec!["disk_cache", "l1_cache", "l2_cache", "subrequest_errors", "upstream"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_removed_disk_dir_is_unhealthy() {
+        let origin = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().to_path_buf();
+        let checker = checker(origin.local_addr().unwrap().to_string(), Arc::new(SliceMetrics::new()))
+            .with_disk_dir(&path);
+        checker.probe_upstream().await;
+        assert!(checker.check().await.is_healthy());
+
+        drop(dir);
+        let report = checker.check().await;
+        assert!(!report.is_healthy());
+        assert!(!report.components["disk_cache"].healthy);
+        assert!(report.components["upstream"].healthy);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["status"], "unhealthy");
+        assert_eq!(json["components"]["disk_cache"]["healthy"], false);
+    }
+
+    #[tokio::test]
+    async fn test_degraded_l2_is_unhealthy() {
+        let origin = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let l2_path = dir.path().join("l2");
+        let cache = Arc::new(
+            TieredCache::with_disk_health(Duration::from_secs(60), 1024 * 1024, &l2_path, 1, Duration::from_secs(60))
+                .await
+                .unwrap(),
+        );
+        let checker = checker(origin.local_addr().unwrap().to_string(), Arc::new(SliceMetrics::new()))
+            .with_cache(cache.clone());
+        checker.probe_upstream().await;
+        assert!(checker.check().await.is_healthy());
+
+        // Every disk write fails once the directory is a regular file
+        std::fs::remove_dir_all(&l2_path).unwrap();
+        std::fs::write(&l2_path, b"not a directory").unwrap();
+        let range = crate::models::ByteRange::new(0, 99).unwrap();
+        cache.store("http://example.com/file", &range, bytes::Bytes::from(vec![0; 100])).unwrap();
+        for _ in 0..50 {
+            if cache.is_l2_degraded() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let report = checker.check().await;
+        assert!(!report.components["l2_cache"].healthy);
+        assert!(report.components["l1_cache"].healthy);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_upstream_is_unhealthy() {
+        let origin = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = origin.local_addr().unwrap().to_string();
+        let checker = checker(addr, Arc::new(SliceMetrics::new()));
+        checker.probe_upstream().await;
+        assert!(checker.check().await.is_healthy());
+
+        // Origin goes down: the cached result only changes on the next probe
+        drop(origin);
+        assert!(checker.check().await.is_healthy());
+        checker.probe_upstream().await;
+        let report = checker.check().await;
+        assert!(!report.components["upstream"].healthy);
+        assert!(report.components["upstream"].detail.contains("unreachable"));
+    }
+
+    #[tokio::test]
+    async fn test_failure_ratio_threshold() {
+        let origin = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let metrics = Arc::new(SliceMetrics::new());
+        let checker = checker(origin.local_addr().unwrap().to_string(), metrics.clone());
+        checker.probe_upstream().await;
+
+        // Too few subrequests to judge
+        for _ in 0..5 {
+            metrics.record_subrequest(false);
+        }
+        assert!(checker.check().await.is_healthy());
+
+        for _ in 0..10 {
+            metrics.record_subrequest(false);
+        }
+        metrics.record_subrequest(true);
+        let report = checker.check().await;
+        assert!(!report.components["subrequest_errors"].healthy);
+        assert!(report.components["subrequest_errors"].detail.starts_with("15 of 16"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_liveness_follows_probe_heartbeat() {
+        let checker = checker("127.0.0.1:1".to_string(), Arc::new(SliceMetrics::new()));
+        assert!(checker.is_live());
+
+        *checker.heartbeat.lock().unwrap() = Some(Instant::now());
+        tokio::time::advance(Duration::from_secs(20)).await;
+        assert!(checker.is_live());
+
+        tokio::time::advance(Duration::from_secs(20)).await;
+        assert!(!checker.is_live());
+    }
+}
@@ -180,28 +180,83 @@ pub mod models;
 pub mod error;
 pub mod request_analyzer;
 pub mod metadata_fetcher;
+pub mod metadata_cache;
 pub mod slice_calculator;
 pub mod cache;
+pub mod cache_key;  // Cache key normalization shared by proxy and purge
+pub mod cache_admission;  // Which origin responses are cached
+pub mod header_rules;  // Configurable request/response header rewriting
+pub mod error_pages;  // Static bodies for failed requests
 pub mod tiered_cache;  // New two-tier cache implementation
+pub mod cache_backend;  // Pluggable L2 storage for the tiered cache
+pub mod delta_backend;  // Diff storage for updated L2 entries
 pub mod purge_handler;  // HTTP PURGE method handler
 pub mod purge_metrics;  // Prometheus metrics for purge operations
+pub mod cache_admin;  // Cache inspection admin endpoints
+pub mod cache_warmer;  // Cache pre-population for lists of URLs
+pub mod cachectl;  // Offline inspection and repair of the L2 cache directory
+pub mod cluster;  // Consistent-hash routing between nodes
+pub mod upstream;  // Origin pool with health-aware selection
+pub mod origin_client;  // Pooled HTTP client for slice subrequests
+pub mod origin_signing;  // HMAC signatures on origin requests
 pub mod subrequest_manager;
+pub mod rate_limiter;
+pub mod slow_start;
+pub mod fill_limiter;  // Caps on concurrent cache fills and their buffers
+pub mod client_pacer;
 pub mod response_assembler;
 pub mod metrics;
 pub mod metrics_endpoint;
+pub mod health;  // Component health for readiness/liveness probes
+pub mod access_log;
+#[cfg(feature = "otel")]
+pub mod telemetry;  // OTLP export of request spans
 pub mod proxy;
+pub mod server;  // Standalone HTTP server running the proxy
 
 // Re-export commonly used types
-pub use config::SliceConfig;
-pub use models::{ByteRange, SliceSpec, FileMetadata};
+pub use config::{
+    AccessLogConfig, AccessLogFormat, BasicAuthConfig, CacheAdmissionConfig, CacheBypassConfig, CacheKeyPolicy, CacheMode, CacheModeRule, ClientAbortPolicy, ClientRateLimitConfig,
+    ClusterConfig, ErrorPageConfig, FetchOrder, ForwardedHeadersConfig, ForwardedMode,
+    HeaderAction, HeaderRule, HeaderTarget, HealthConfig, HostHeaderMode, L2DeltaConfig, L2HealthConfig, MetadataProbe,
+    MetricsEndpointConfig, OriginProtocol, OriginQuotaConfig, OriginSigningConfig, PatternRule,
+    SignedComponent, SigningAlgorithm, SliceConfig, SliceConfigBuilder, SlowStartConfig, SocketConfig,
+    TracingConfig, UpstreamPolicy, UpstreamPoolConfig,
+};
+pub use models::{ByteRange, SliceSpec, FileMetadata, SliceKey};
 pub use error::{SliceError, Result};
 pub use request_analyzer::RequestAnalyzer;
 pub use metadata_fetcher::MetadataFetcher;
+pub use metadata_cache::MetadataCache;
 pub use slice_calculator::SliceCalculator;
-pub use cache::SliceCache;
-pub use tiered_cache::{TieredCache, TieredCacheStats};  // Export new cache
-pub use subrequest_manager::{SubrequestManager, SubrequestResult, RetryPolicy};
+pub use cache::{CacheVariant, SliceCache};
+pub use cache_admission::{Admission, CachePolicy};
+pub use cache_key::CacheKeyBuilder;
+pub use header_rules::{ForwardedHeaders, HeaderRewriter};
+pub use error_pages::ErrorPages;
+pub use tiered_cache::{
+    CacheKeys, L1AdmissionPolicy, L1EvictionPolicy, TieredCache, TieredCacheStats, TopBy, TopEntries, TopEntry,
+    TopTier,
+};  // Export new cache
+pub use cache_backend::{
+    BackendEntry, CacheBackend, CacheBackendStats, ConsistencyReport, DedupStats, DiskUsage,
+    EntryFormat, FileBackend, FileLayout, FsyncPolicy, IndexRecord, IndexStats, RecoveryStats, SyncStats,
+};
+pub use delta_backend::{DeltaBackend, DeltaStats};
+pub use origin_client::origin_client;
+pub use origin_signing::RequestSigner;
+pub use subrequest_manager::{AbortSignal, InterruptedFetch, SubrequestManager, SubrequestResult, RetryPolicy};
+pub use rate_limiter::{ClientRateLimiter, OriginRateLimiter};
+pub use slow_start::ConcurrencyRamp;
+pub use fill_limiter::{FillGuard, FillLimiter};
+pub use client_pacer::ClientPacer;
 pub use response_assembler::ResponseAssembler;
-pub use metrics::{SliceMetrics, MetricsSnapshot};
+pub use metrics::{SliceMetrics, MetricsSnapshot, HistogramSnapshot, PatternStats};
 pub use metrics_endpoint::MetricsEndpoint;
-pub use proxy::{SliceProxy, SliceContext};
+pub use health::{ComponentHealth, HealthChecker, HealthReport};
+pub use access_log::{AccessLogger, AccessRecord, CacheStatus};
+pub use cache_warmer::{CacheWarmer, WarmStatus};
+pub use cluster::{ClusterRouter, HashRing, CLUSTER_HOP_HEADER};
+pub use upstream::{UpstreamLease, UpstreamPool};
+pub use proxy::{SliceBody, SliceProxy, SliceContext};
+pub use server::{run_slice_server, run_slice_server_warming, ReloadHandle, SliceServer};
@@ -1,13 +1,12 @@
 //! Pingora Slice Module Server
 //!
 //! This is the main entry point for the Pingora Slice proxy server.
-//! It loads configuration, sets up logging, and starts the HTTP proxy service.
+//! It loads configuration, sets up logging, and starts the HTTP proxy service
+//! together with the metrics endpoint.
 
-use pingora_slice::{SliceConfig, SliceProxy};
+use pingora_slice::{run_slice_server_warming, SliceConfig};
 use std::env;
-use std::sync::Arc;
 use tracing::{info, error};
-use tracing_subscriber;
 
 /// Main entry point for the Pingora Slice server
 ///
@@ -18,6 +17,9 @@ use tracing_subscriber;
 ///
 /// # Start with custom config
 /// cargo run -- /path/to/config.yaml
+///
+/// # Warm the cache with the URLs listed in urls.txt (one per line)
+/// cargo run -- /path/to/config.yaml --warm urls.txt
 /// ```
 ///
 /// # Requirements
@@ -33,15 +35,41 @@ fn main() {
 
     info!("Starting Pingora Slice Module Server");
 
-    // Get config file path from command line or use default
-    let config_path = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "pingora_slice.yaml".to_string());
+    // Get config file path from command line or use default, and the
+    // optional `--warm <file>` list of URLs to pre-populate the cache with
+    let mut config_path = None;
+    let mut warm_file = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--warm" {
+            match args.next() {
+                Some(file) => warm_file = Some(file),
+                None => {
+                    error!("--warm requires a file argument");
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            config_path = Some(arg);
+        }
+    }
+    let config_path = config_path.unwrap_or_else(|| "pingora_slice.yaml".to_string());
+
+    let warm_urls = match &warm_file {
+        Some(file) => match read_url_list(file) {
+            Ok(urls) => urls,
+            Err(e) => {
+                error!("Failed to read warm URL list {}: {}", file, e);
+                std::process::exit(1);
+            }
+        },
+        None => Vec::new(),
+    };
 
     info!("Loading configuration from: {}", config_path);
 
-    // Load configuration from file
-    let config = match SliceConfig::from_file(&config_path) {
+    // Load configuration from file, with PINGORA_SLICE_* environment overrides
+    match SliceConfig::load(&config_path) {
         Ok(cfg) => {
             info!("Configuration loaded successfully");
             info!("  - Slice size: {} bytes ({} KB)", cfg.slice_size, cfg.slice_size / 1024);
@@ -51,63 +79,29 @@ fn main() {
             info!("  - Cache TTL: {} seconds", cfg.cache_ttl);
             info!("  - Upstream address: {}", cfg.upstream_address);
             info!("  - Slice patterns: {:?}", cfg.slice_patterns);
-            cfg
         }
         Err(e) => {
             error!("Failed to load configuration: {}", e);
             error!("Please ensure the configuration file exists and is valid");
             std::process::exit(1);
         }
-    };
+    }
 
-    // Create SliceProxy instance
-    info!("Creating SliceProxy instance");
-    let _proxy = SliceProxy::new(Arc::new(config.clone()));
-
-    info!("SliceProxy created successfully");
-    info!("Metrics initialized");
-
-    // In a real Pingora integration, we would:
-    // 1. Create a Pingora Server instance
-    // 2. Create an HTTP proxy service with our SliceProxy
-    // 3. Configure listening address and port
-    // 4. Start the server
-    //
-    // Example (pseudo-code for actual Pingora integration):
-    // ```
-    // let mut server = pingora::Server::new(None).unwrap();
-    // server.bootstrap();
-    //
-    // let mut proxy_service = pingora::services::http_proxy_service(
-    //     &server.configuration,
-    //     proxy
-    // );
-    // proxy_service.add_tcp("0.0.0.0:8080");
-    //
-    // server.add_service(proxy_service);
-    // server.run_forever();
-    // ```
-
-    info!("=== Pingora Slice Module Server ===");
-    info!("Server would start here in a full Pingora integration");
-    info!("This is a demonstration of the startup code structure");
-    info!("");
-    info!("To integrate with Pingora:");
-    info!("1. Implement ProxyHttp trait for SliceProxy");
-    info!("2. Create Pingora Server instance");
-    info!("3. Create HTTP proxy service with SliceProxy");
-    info!("4. Configure listening address (e.g., 0.0.0.0:8080)");
-    info!("5. Start the server with server.run_forever()");
-    info!("");
-    info!("Current configuration:");
-    info!("  Upstream: {}", config.upstream_address);
-    info!("  Slice size: {} KB", config.slice_size / 1024);
-    info!("  Max concurrent: {}", config.max_concurrent_subrequests);
-    info!("");
-    info!("Server initialization complete");
-    info!("In production, the server would now be listening for requests");
+    // Serve the proxy, and the metrics endpoint when `metrics_endpoint` is
+    // enabled, until SIGINT or SIGTERM
+    if let Err(e) = run_slice_server_warming(&config_path, warm_urls) {
+        error!("Server failed: {}", e);
+        std::process::exit(1);
+    }
+}
 
-    // For demonstration purposes, we'll just show that everything initialized correctly
-    // In a real deployment, this would call server.run_forever() and never return
-    info!("Demonstration complete - server would run indefinitely in production");
+/// Read the URLs listed in `path`, one per line, skipping blank lines and
+/// `#` comments
+fn read_url_list(path: &str) -> std::io::Result<Vec<String>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
 }
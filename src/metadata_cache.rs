@@ -0,0 +1,385 @@
+//! In-memory cache for origin file metadata
+//!
+//! Caching metadata avoids a HEAD request to the origin for every sliced
+//! request. Concurrent misses for the same URL are coalesced so that only one
+//! metadata fetch is in flight per URL at a time.
+
+use crate::error::Result;
+use crate::models::{FileMetadata, SliceKey};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+/// Cached metadata entry
+#[derive(Debug, Clone)]
+struct MetadataEntry {
+    metadata: FileMetadata,
+    fetched_at: Instant,
+}
+
+/// Shared in-flight metadata fetch
+type InflightFetch = Arc<OnceCell<Result<FileMetadata>>>;
+
+/// URL → FileMetadata cache with TTL, size limit and singleflight fetches
+pub struct MetadataCache {
+    entries: RwLock<HashMap<String, MetadataEntry>>,
+    inflight: Mutex<HashMap<String, InflightFetch>>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl MetadataCache {
+    /// Create a new metadata cache
+    ///
+    /// # Arguments
+    /// * `ttl` - How long fetched metadata stays valid (zero disables caching)
+    /// * `max_entries` - Maximum number of URLs to keep metadata for
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        MetadataCache {
+            entries: RwLock::new(HashMap::new()),
+            inflight: Mutex::new(HashMap::new()),
+            ttl,
+            max_entries,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    /// Get cached metadata for a URL if present and not expired
+    pub fn get(&self, url: &str) -> Option<FileMetadata> {
+        self.get_with_age(url).map(|(metadata, _)| metadata)
+    }
+
+    /// Get cached metadata for a URL with the time since it was fetched
+    pub fn get_with_age(&self, url: &str) -> Option<(FileMetadata, Duration)> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(url)
+            .map(|entry| (entry, entry.fetched_at.elapsed()))
+            .filter(|(_, age)| *age < self.ttl)
+            .map(|(entry, age)| (entry.metadata.clone(), age))
+    }
+
+    /// Get cached metadata for a URL that expired less than `max_stale` ago
+    ///
+    /// For serving a request while the origin is failing; fresh entries are
+    /// returned as well.
+    pub fn get_stale(&self, url: &str, max_stale: Duration) -> Option<FileMetadata> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(url)
+            .filter(|entry| entry.fetched_at.elapsed() < self.ttl + max_stale)
+            .map(|entry| entry.metadata.clone())
+    }
+
+    /// Store metadata for a URL, evicting the oldest entry when full
+    pub fn insert(&self, url: &str, metadata: FileMetadata) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut entries = self.entries.write().unwrap();
+        if !entries.contains_key(url) && entries.len() >= self.max_entries {
+            // Drop expired entries first, then the oldest one if still full
+            entries.retain(|_, entry| entry.fetched_at.elapsed() < self.ttl);
+            if entries.len() >= self.max_entries {
+                if let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.fetched_at)
+                    .map(|(k, _)| k.clone())
+                {
+                    entries.remove(&oldest);
+                    debug!("Evicted metadata cache entry: {}", oldest);
+                }
+            }
+        }
+
+        entries.insert(
+            url.to_string(),
+            MetadataEntry {
+                metadata,
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Remove cached metadata for a URL
+    ///
+    /// # Returns
+    /// `true` if an entry was removed
+    pub fn invalidate(&self, url: &str) -> bool {
+        self.take(url).is_some()
+    }
+
+    /// Mark cached metadata for a URL expired
+    ///
+    /// The next request fetches it again, while
+    /// [`get_stale`](Self::get_stale) can still find it.
+    pub fn expire(&self, url: &str) {
+        let mut entries = self.entries.write().unwrap();
+        let Some(entry) = entries.get_mut(url) else {
+            return;
+        };
+        match Instant::now().checked_sub(self.ttl) {
+            Some(expired) => entry.fetched_at = entry.fetched_at.min(expired),
+            None => {
+                entries.remove(url);
+            }
+        }
+        debug!("Expired metadata cache entry: {}", url);
+    }
+
+    /// Remove cached metadata for a URL and return it, even if expired
+    ///
+    /// Lets a purge find the slices of a file whose metadata outlived its TTL.
+    pub fn take(&self, url: &str) -> Option<FileMetadata> {
+        let removed = self.entries.write().unwrap().remove(url)?;
+        debug!("Invalidated metadata cache entry: {}", url);
+        Some(removed.metadata)
+    }
+
+    /// Remove cached metadata for `url` and every variant of it
+    ///
+    /// # Returns
+    /// The metadata keys removed, with their metadata
+    pub fn take_variants(&self, url: &str) -> Vec<(String, FileMetadata)> {
+        let target = SliceKey::from_object_key(url);
+        let mut entries = self.entries.write().unwrap();
+        let keys: Vec<String> = entries
+            .keys()
+            .filter(|key| SliceKey::parse(key).is_ok_and(|key| key.same_url(&target)))
+            .cloned()
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let removed = entries.remove(&key)?;
+                debug!("Invalidated metadata cache entry: {}", key);
+                Some((key, removed.metadata))
+            })
+            .collect()
+    }
+
+    /// Remove cached metadata for all URLs starting with `prefix`
+    ///
+    /// # Returns
+    /// The number of entries removed
+    pub fn invalidate_prefix(&self, prefix: &str) -> usize {
+        let mut entries = self.entries.write().unwrap();
+        let before = entries.len();
+        entries.retain(|url, _| !url.starts_with(prefix));
+        before - entries.len()
+    }
+
+    /// Remove all cached metadata
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    /// Number of cached entries (including expired ones not yet evicted)
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// Whether the cache holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get cached metadata, or fetch it with `fetch` on a miss
+    ///
+    /// Concurrent callers that miss on the same URL share a single call to
+    /// `fetch` and all receive its result. Only successful results are cached.
+    pub async fn get_or_fetch<F, Fut>(&self, url: &str, fetch: F) -> Result<FileMetadata>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<FileMetadata>>,
+    {
+        if let Some(metadata) = self.get(url) {
+            debug!("Metadata cache hit: {}", url);
+            return Ok(metadata);
+        }
+
+        let cell = {
+            let mut inflight = self.inflight.lock().unwrap();
+            inflight
+                .entry(url.to_string())
+                .or_insert_with(|| Arc::new(OnceCell::new()))
+                .clone()
+        };
+
+        let result = cell
+            .get_or_init(|| async {
+                // A previous fetch may have completed since the lookup above
+                if let Some(metadata) = self.get(url) {
+                    return Ok(metadata);
+                }
+                debug!("Metadata cache miss, fetching: {}", url);
+                let result = fetch().await;
+                if let Ok(metadata) = &result {
+                    self.insert(url, metadata.clone());
+                }
+                result
+            })
+            .await
+            .clone();
+
+        // The first caller to get here retires the in-flight entry so later
+        // misses start a fresh fetch
+        let mut inflight = self.inflight.lock().unwrap();
+        if inflight.get(url).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+            inflight.remove(url);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SliceError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn metadata(size: u64) -> FileMetadata {
+        FileMetadata::new(size, true)
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let cache = MetadataCache::new(Duration::from_secs(60), 10);
+        assert!(cache.get("http://example.com/a").is_none());
+
+        cache.insert("http://example.com/a", metadata(100));
+        assert_eq!(cache.get("http://example.com/a").unwrap().content_length, 100);
+    }
+
+    #[test]
+    fn test_get_with_age() {
+        let cache = MetadataCache::new(Duration::from_secs(60), 10);
+        cache.insert("http://example.com/a", metadata(100));
+        std::thread::sleep(Duration::from_millis(20));
+
+        let (found, age) = cache.get_with_age("http://example.com/a").unwrap();
+        assert_eq!(found.content_length, 100);
+        assert!(age >= Duration::from_millis(20));
+        assert!(cache.get_with_age("http://example.com/b").is_none());
+    }
+
+    #[test]
+    fn test_expired_entry_not_returned() {
+        let cache = MetadataCache::new(Duration::from_millis(10), 10);
+        cache.insert("http://example.com/a", metadata(100));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(cache.get("http://example.com/a").is_none());
+    }
+
+    #[test]
+    fn test_stale_entry_within_window() {
+        let cache = MetadataCache::new(Duration::from_millis(10), 10);
+        cache.insert("http://example.com/a", metadata(100));
+        std::thread::sleep(Duration::from_millis(20));
+
+        let found = cache.get_stale("http://example.com/a", Duration::from_secs(60)).unwrap();
+        assert_eq!(found.content_length, 100);
+        assert!(cache.get_stale("http://example.com/a", Duration::from_millis(1)).is_none());
+        assert!(cache.get_stale("http://example.com/b", Duration::from_secs(60)).is_none());
+    }
+
+    #[test]
+    fn test_expire_keeps_stale_entry() {
+        let cache = MetadataCache::new(Duration::from_secs(60), 10);
+        cache.insert("http://example.com/a", metadata(100));
+        cache.expire("http://example.com/a");
+
+        assert!(cache.get("http://example.com/a").is_none());
+        assert!(cache.get_stale("http://example.com/a", Duration::from_secs(60)).is_some());
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_cache() {
+        let cache = MetadataCache::new(Duration::ZERO, 10);
+        cache.insert("http://example.com/a", metadata(100));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_evicts_oldest_when_full() {
+        let cache = MetadataCache::new(Duration::from_secs(60), 2);
+        cache.insert("http://example.com/a", metadata(1));
+        cache.insert("http://example.com/b", metadata(2));
+        cache.insert("http://example.com/c", metadata(3));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("http://example.com/a").is_none());
+        assert!(cache.get("http://example.com/c").is_some());
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let cache = MetadataCache::new(Duration::from_secs(60), 10);
+        cache.insert("http://example.com/dir/a", metadata(1));
+        cache.insert("http://example.com/dir/b", metadata(2));
+        cache.insert("http://example.com/other", metadata(3));
+
+        assert!(cache.invalidate("http://example.com/other"));
+        assert!(!cache.invalidate("http://example.com/other"));
+        assert_eq!(cache.invalidate_prefix("http://example.com/dir/"), 2);
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_share_one_fetch() {
+        let cache = Arc::new(MetadataCache::new(Duration::from_secs(60), 10));
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..100 {
+            let cache = cache.clone();
+            let fetches = fetches.clone();
+            handles.push(tokio::spawn(async move {
+                cache
+                    .get_or_fetch("http://example.com/a", || async {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok(metadata(42))
+                    })
+                    .await
+            }));
+        }
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap().content_length, 42);
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_fetch_not_cached() {
+        let cache = MetadataCache::new(Duration::from_secs(60), 10);
+
+        let result = cache
+            .get_or_fetch("http://example.com/a", || async {
+                Err(SliceError::MetadataFetchError("boom".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+
+        let result = cache
+            .get_or_fetch("http://example.com/a", || async { Ok(metadata(7)) })
+            .await;
+        assert_eq!(result.unwrap().content_length, 7);
+    }
+}
@@ -1,15 +1,26 @@
 //! Metadata fetcher for retrieving file information from origin servers
 
+use crate::config::{MetadataProbe, SliceConfig};
 use crate::error::{Result, SliceError};
 use crate::models::FileMetadata;
-use reqwest::Client;
+use crate::origin_signing::{send_signed, RequestSigner};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode};
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
 /// MetadataFetcher is responsible for fetching file metadata from origin servers
-/// using HEAD requests
+/// using HEAD requests, or a `GET` with `Range: bytes=0-0` for origins that
+/// reject HEAD
+#[derive(Clone)]
 pub struct MetadataFetcher {
     client: Client,
+    timeout: Duration,
+    probe: MetadataProbe,
+    fallback_statuses: Vec<u16>,
+    request_headers: HeaderMap,
+    signer: Option<Arc<RequestSigner>>,
 }
 
 impl MetadataFetcher {
@@ -20,7 +31,7 @@ impl MetadataFetcher {
             .build()
             .map_err(|e| SliceError::HttpError(format!("Failed to create HTTP client: {}", e)))?;
         
-        Ok(MetadataFetcher { client })
+        Ok(Self::with_client(client, Duration::from_secs(10)))
     }
 
     /// Create a new MetadataFetcher with a custom timeout
@@ -30,7 +41,64 @@ impl MetadataFetcher {
             .build()
             .map_err(|e| SliceError::HttpError(format!("Failed to create HTTP client: {}", e)))?;
         
-        Ok(MetadataFetcher { client })
+        Ok(Self::with_client(client, timeout))
+    }
+
+    /// Create a new MetadataFetcher using the probe and signing settings
+    /// from `config`
+    pub fn from_config(config: &SliceConfig) -> Result<Self> {
+        let mut fetcher = Self::new()?
+            .with_probe(config.metadata_probe)
+            .with_fallback_statuses(config.metadata_probe_fallback_statuses.clone());
+        if let Some(signer) = RequestSigner::from_config(config) {
+            fetcher = fetcher.with_signer(Arc::new(signer));
+        }
+        Ok(fetcher)
+    }
+
+    fn with_client(client: Client, timeout: Duration) -> Self {
+        MetadataFetcher {
+            client,
+            timeout,
+            probe: MetadataProbe::default(),
+            fallback_statuses: vec![403, 405, 501],
+            request_headers: HeaderMap::new(),
+            signer: None,
+        }
+    }
+
+    /// Send probes with `client`, sharing its connection pool
+    ///
+    /// Probes keep this fetcher's timeout. See
+    /// [`origin_client`](crate::origin_client::origin_client).
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sign every probe with `signer`, after all other headers are set
+    pub fn with_signer(mut self, signer: Arc<RequestSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Set the metadata probe strategy
+    pub fn with_probe(mut self, probe: MetadataProbe) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// Set the HEAD statuses that trigger the ranged GET fallback in `auto` mode
+    pub fn with_fallback_statuses(mut self, statuses: Vec<u16>) -> Self {
+        self.fallback_statuses = statuses;
+        self
+    }
+
+    /// Send `headers` with every probe, so the origin picks the same
+    /// variant as for the client
+    pub fn with_request_headers(mut self, headers: HeaderMap) -> Self {
+        self.request_headers = headers;
+        self
     }
 
     /// Fetch metadata for a file from the origin server
@@ -42,6 +110,9 @@ impl MetadataFetcher {
     /// - ETag: Entity tag for cache validation
     /// - Last-Modified: Last modification timestamp
     ///
+    /// Depending on the configured [`MetadataProbe`], a `GET` with
+    /// `Range: bytes=0-0` is used instead of, or as a fallback for, HEAD.
+    ///
     /// # Arguments
     /// * `url` - The URL of the file to fetch metadata for
     ///
@@ -51,14 +122,40 @@ impl MetadataFetcher {
     ///
     /// # Requirements
     /// Validates: Requirements 3.1, 3.2, 3.3, 3.4, 3.5
+    #[tracing::instrument(name = "fetch_metadata", skip(self), fields(url = %url))]
     pub async fn fetch_metadata(&self, url: &str) -> Result<FileMetadata> {
-        debug!("Fetching metadata for url={}", url);
-        
+        debug!("Fetching metadata for url={}, probe={:?}", url, self.probe);
+
+        match self.probe {
+            MetadataProbe::Head => self.fetch_with_head(url).await,
+            MetadataProbe::GetRange => self.fetch_with_range_probe(url).await,
+            MetadataProbe::Auto => match self.fetch_with_head(url).await {
+                Err(SliceError::OriginClientError { status, .. })
+                | Err(SliceError::OriginServerError { status, .. })
+                    if self.fallback_statuses.contains(&status) =>
+                {
+                    info!(
+                        "Origin rejected HEAD for url={} with status={}, retrying with ranged GET",
+                        url, status
+                    );
+                    // The GET's answer, e.g. a 404, says more than the
+                    // HEAD the origin does not allow
+                    self.fetch_with_range_probe(url).await.inspect_err(|e| {
+                        warn!("Ranged GET fallback failed for url={}: {}", url, e);
+                    })
+                }
+                result => result,
+            },
+        }
+    }
+
+    async fn fetch_with_head(&self, url: &str) -> Result<FileMetadata> {
         // Send HEAD request to origin server (Requirement 3.1)
-        let response = self
-            .client
-            .head(url)
-            .send()
+        let headers = self.request_headers.clone();
+        #[cfg(feature = "otel")]
+        let headers = crate::telemetry::with_traceparent(headers);
+        let request = self.client.head(url).timeout(self.timeout).headers(headers);
+        let response = send_signed(request, self.signer.as_deref())
             .await
             .map_err(|e| {
                 warn!("HEAD request failed for url={}: {}", url, e);
@@ -160,8 +257,135 @@ impl MetadataFetcher {
             content_type,
             etag,
             last_modified,
-        ))
+        )
+        .with_vary(parse_vary(headers)))
     }
+
+    /// Fetch metadata using `GET` with `Range: bytes=0-0`
+    ///
+    /// A 206 response yields the total size from `Content-Range: bytes 0-0/N`
+    /// and marks the file as range-capable. A 200 response means the origin
+    /// ignored the Range header, so the file is reported as not supporting
+    /// ranges and its size is taken from Content-Length.
+    async fn fetch_with_range_probe(&self, url: &str) -> Result<FileMetadata> {
+        let headers = self.request_headers.clone();
+        #[cfg(feature = "otel")]
+        let headers = crate::telemetry::with_traceparent(headers);
+        let request = self
+            .client
+            .get(url)
+            .timeout(self.timeout)
+            .headers(headers)
+            .header("range", "bytes=0-0");
+        let response = send_signed(request, self.signer.as_deref())
+            .await
+            .map_err(|e| {
+                warn!("Ranged GET probe failed for url={}: {}", url, e);
+                SliceError::MetadataFetchError(format!("Ranged GET probe failed: {}", e))
+            })?;
+
+        let status = response.status();
+        debug!("Received ranged GET probe response for url={}, status={}", url, status);
+
+        if status.is_client_error() {
+            return Err(SliceError::origin_client_error(
+                status.as_u16(),
+                format!("Origin server returned client error: {}", status),
+            ));
+        }
+
+        if status.is_server_error() {
+            return Err(SliceError::origin_server_error(
+                status.as_u16(),
+                format!("Origin server returned server error: {}", status),
+            ));
+        }
+
+        let headers = response.headers();
+        let (content_length, supports_range) = match status {
+            StatusCode::PARTIAL_CONTENT => {
+                let total = headers
+                    .get("content-range")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_content_range_total)
+                    .ok_or_else(|| {
+                        warn!("Content-Range total missing or invalid for url={}", url);
+                        SliceError::MetadataFetchError(
+                            "Content-Range total missing or invalid in probe response".to_string(),
+                        )
+                    })?;
+                (total, true)
+            }
+            StatusCode::OK => {
+                // The origin ignored the Range header and is sending the full body
+                let length = parse_content_length(headers).ok_or_else(|| {
+                    SliceError::MetadataFetchError(
+                        "Content-Length header missing or invalid".to_string(),
+                    )
+                })?;
+                (length, false)
+            }
+            _ => {
+                return Err(SliceError::HttpError(format!(
+                    "Unexpected status code: {}",
+                    status
+                )));
+            }
+        };
+
+        let content_type = header_string(headers, "content-type");
+        let etag = header_string(headers, "etag");
+        let last_modified = header_string(headers, "last-modified");
+
+        info!(
+            "Fetched metadata via ranged GET for url={}: size={}, supports_range={}, content_type={:?}",
+            url, content_length, supports_range, content_type
+        );
+
+        Ok(FileMetadata::with_headers(
+            content_length,
+            supports_range,
+            content_type,
+            etag,
+            last_modified,
+        )
+        .with_vary(parse_vary(headers)))
+    }
+}
+
+/// Collect the lowercased header names from all Vary headers
+fn parse_vary(headers: &HeaderMap) -> Vec<String> {
+    let mut vary: Vec<String> = headers
+        .get_all("vary")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    vary.sort();
+    vary.dedup();
+    vary
+}
+
+fn parse_content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+}
+
+fn header_string(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+/// Parse the total size from a `Content-Range: bytes start-end/total` value
+fn parse_content_range_total(value: &str) -> Option<u64> {
+    let (_, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    total.trim().parse().ok()
 }
 
 impl Default for MetadataFetcher {
@@ -185,4 +409,11 @@ mod tests {
         let fetcher = MetadataFetcher::with_timeout(Duration::from_secs(5));
         assert!(fetcher.is_ok());
     }
+
+    #[test]
+    fn test_parse_content_range_total() {
+        assert_eq!(parse_content_range_total("bytes 0-0/1024"), Some(1024));
+        assert_eq!(parse_content_range_total("bytes 0-0/*"), None);
+        assert_eq!(parse_content_range_total("0-0/1024"), None);
+    }
 }
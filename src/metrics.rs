@@ -2,10 +2,154 @@
 //!
 //! This module provides thread-safe metrics collection using atomic operations.
 //! It tracks requests, cache hits/misses, subrequests, and latencies.
+//! Latencies are also recorded into fixed-bucket histograms so percentiles
+//! can be reported without locking.
 
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 
+/// Upper bounds (in milliseconds) of the latency histogram buckets
+///
+/// Buckets are roughly log-scaled from 1ms to 60s. Observations above the
+/// last bound fall into an implicit `+Inf` bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 15] = [
+    1, 2, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000,
+];
+
+/// Number of histogram buckets, including the `+Inf` bucket
+const LATENCY_BUCKET_COUNT: usize = LATENCY_BUCKETS_MS.len() + 1;
+
+/// Lock-free latency histogram with fixed buckets
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKET_COUNT],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+/// Snapshot of a latency histogram
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// Per-bucket (non-cumulative) counts; the last entry is the `+Inf` bucket
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencyHistogram {
+    /// Record an observation
+    pub fn observe(&self, duration: Duration) {
+        let us = duration.as_micros() as u64;
+        let index = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound_ms| us <= bound_ms * 1000)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    /// Get a snapshot of the histogram
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|b| b.load(Ordering::Relaxed))
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum_us: self.sum_us.load(Ordering::Relaxed),
+            max_us: self.max_us.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reset all buckets to zero
+    pub fn reset(&self) {
+        self.take();
+    }
+
+    /// Get a snapshot of the histogram and reset it
+    ///
+    /// Every observation lands either in the snapshot or in the histogram
+    /// afterwards, though one racing with the reset may have its bucket and
+    /// its count split between the two.
+    pub fn take(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|b| b.swap(0, Ordering::Relaxed))
+                .collect(),
+            count: self.count.swap(0, Ordering::Relaxed),
+            sum_us: self.sum_us.swap(0, Ordering::Relaxed),
+            max_us: self.max_us.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+impl HistogramSnapshot {
+    /// Estimate a percentile (0.0 to 1.0) in milliseconds
+    ///
+    /// Returns the upper bound of the bucket containing the requested rank,
+    /// capped at the largest observed value. Observations in the `+Inf`
+    /// bucket report the maximum.
+    pub fn percentile_ms(&self, quantile: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let max_ms = self.max_ms();
+        let mut cumulative = 0;
+        for (index, &bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket;
+            if cumulative >= rank {
+                return match LATENCY_BUCKETS_MS.get(index) {
+                    Some(&bound_ms) => (bound_ms as f64).min(max_ms),
+                    None => max_ms,
+                };
+            }
+        }
+
+        max_ms
+    }
+
+    /// Median latency in milliseconds
+    pub fn p50_ms(&self) -> f64 {
+        self.percentile_ms(0.50)
+    }
+
+    /// 90th percentile latency in milliseconds
+    pub fn p90_ms(&self) -> f64 {
+        self.percentile_ms(0.90)
+    }
+
+    /// 99th percentile latency in milliseconds
+    pub fn p99_ms(&self) -> f64 {
+        self.percentile_ms(0.99)
+    }
+
+    /// Largest observed latency in milliseconds
+    pub fn max_ms(&self) -> f64 {
+        self.max_us as f64 / 1000.0
+    }
+}
+
+/// Request, cache and byte counters for one configured URL pattern
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatternStats {
+    pub requests: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub bytes_to_client: u64,
+}
+
 /// Metrics collector for the Slice Module
 ///
 /// All operations are thread-safe using atomic operations.
@@ -20,21 +164,75 @@ pub struct SliceMetrics {
     cache_hits: AtomicU64,
     cache_misses: AtomicU64,
     cache_errors: AtomicU64,
+    expiry_sweeps: AtomicU64,
+    expired_entries_swept: AtomicU64,
+    last_sweep_entries: AtomicU64,
+    
+    // Sliced requests by how much of them the cache served
+    full_hit_requests: AtomicU64,
+    partial_hit_requests: AtomicU64,
+    full_miss_requests: AtomicU64,
     
     // Subrequest statistics
     total_subrequests: AtomicU64,
     failed_subrequests: AtomicU64,
     retried_subrequests: AtomicU64,
+    origin_connections: AtomicU64,
+    http1_subrequests: AtomicU64,
+    http2_subrequests: AtomicU64,
+    whole_object_fallbacks: AtomicU64,
     
     // Byte statistics
     bytes_from_origin: AtomicU64,
     bytes_from_cache: AtomicU64,
     bytes_to_client: AtomicU64,
     
+    // Cache warming statistics
+    warmed_slices: AtomicU64,
+    warmed_bytes: AtomicU64,
+    
+    // Origin rate limiting statistics (utilization stored as a percentage)
+    origin_throttle_wait_us: AtomicU64,
+    origin_bandwidth_utilization: AtomicU64,
+    origin_request_utilization: AtomicU64,
+    effective_concurrency: AtomicU64,
+    
+    // Client disconnect statistics
+    client_aborts: AtomicU64,
+    background_fills: AtomicU64,
+    prefetched_slices: AtomicU64,
+    deadline_aborts: AtomicU64,
+    
+    // Client rate limiting statistics
+    throttled_requests: AtomicU64,
+    
+    // Cache fill limit statistics
+    fill_bypasses: AtomicU64,
+    fill_buffered_bytes: AtomicU64,
+    private_skips: AtomicU64,
+    
+    // Cluster statistics: requests forwarded to each peer
+    cluster_routed: Mutex<BTreeMap<String, u64>>,
+    
+    // Upstream statistics: subrequests and failures per upstream origin
+    upstream_requests: Mutex<BTreeMap<String, u64>>,
+    upstream_failures: Mutex<BTreeMap<String, u64>>,
+    upstream_failovers: Mutex<BTreeMap<(String, String), u64>>,
+    
+    // Per-pattern statistics, keyed by pattern label
+    pattern_stats: Mutex<BTreeMap<String, PatternStats>>,
+    
     // Latency statistics (stored as microseconds)
     total_request_duration_us: AtomicU64,
     total_subrequest_duration_us: AtomicU64,
     total_assembly_duration_us: AtomicU64,
+    
+    // Latency histograms
+    request_latency: LatencyHistogram,
+    subrequest_latency: LatencyHistogram,
+    assembly_latency: LatencyHistogram,
+    ttfb_latency: LatencyHistogram,
+    first_slice_latency: LatencyHistogram,
 }
 
 /// Snapshot of metrics at a point in time
@@ -49,21 +247,87 @@ pub struct MetricsSnapshot {
     pub cache_hits: u64,
     pub cache_misses: u64,
     pub cache_errors: u64,
+    /// Runs of the periodic expiry sweep
+    pub expiry_sweeps: u64,
+    /// Expired entries removed by all sweeps
+    pub expired_entries_swept: u64,
+    /// Expired entries removed by the latest sweep
+    pub last_sweep_entries: u64,
+    
+    // Sliced requests by how much of them the cache served
+    pub full_hit_requests: u64,
+    pub partial_hit_requests: u64,
+    pub full_miss_requests: u64,
     
     // Subrequest statistics
     pub total_subrequests: u64,
     pub failed_subrequests: u64,
     pub retried_subrequests: u64,
+    pub origin_connections: u64,
+    /// Subrequests answered over HTTP/1.x
+    pub http1_subrequests: u64,
+    /// Subrequests answered over HTTP/2
+    pub http2_subrequests: u64,
+    /// Requests that gave up on range requests and fetched the whole object
+    pub whole_object_fallbacks: u64,
     
     // Byte statistics
     pub bytes_from_origin: u64,
     pub bytes_from_cache: u64,
     pub bytes_to_client: u64,
     
+    // Cache warming statistics
+    pub warmed_slices: u64,
+    pub warmed_bytes: u64,
+    
+    // Origin rate limiting statistics
+    pub origin_throttle_wait_us: u64,
+    pub origin_bandwidth_utilization: u64,
+    pub origin_request_utilization: u64,
+    pub effective_concurrency: u64,
+    
+    // Client disconnect statistics
+    pub client_aborts: u64,
+    pub background_fills: u64,
+    /// Slices fetched ahead of sequential range requests
+    pub prefetched_slices: u64,
+    /// Requests cut short because their deadline passed
+    pub deadline_aborts: u64,
+    
+    // Client rate limiting statistics
+    pub throttled_requests: u64,
+    
+    // Cache fill limit statistics
+    pub fill_bypasses: u64,
+    /// Bytes reserved by the cache fills in flight
+    pub fill_buffered_bytes: u64,
+    /// Responses not cached because they carried per-user headers
+    pub private_skips: u64,
+    
+    // Cluster statistics
+    pub cluster_routed: BTreeMap<String, u64>,
+    
+    // Upstream statistics
+    pub upstream_requests: BTreeMap<String, u64>,
+    pub upstream_failures: BTreeMap<String, u64>,
+    /// Retries moved from the first upstream to the second after a failure
+    pub upstream_failovers: BTreeMap<(String, String), u64>,
+    
+    // Per-pattern statistics
+    pub pattern_stats: BTreeMap<String, PatternStats>,
+    
     // Latency statistics
     pub total_request_duration_us: u64,
     pub total_subrequest_duration_us: u64,
     pub total_assembly_duration_us: u64,
+    
+    // Latency histograms
+    pub request_latency: HistogramSnapshot,
+    pub subrequest_latency: HistogramSnapshot,
+    pub assembly_latency: HistogramSnapshot,
+    pub ttfb_latency: HistogramSnapshot,
+    /// Time from dispatching a request's slice fetches until its first slice arrived
+    pub first_slice_latency: HistogramSnapshot,
 }
 
 impl SliceMetrics {
@@ -109,6 +373,32 @@ impl SliceMetrics {
         self.cache_errors.fetch_add(1, Ordering::Relaxed);
     }
     
+    /// Record a run of the periodic expiry sweep
+    ///
+    /// # Arguments
+    /// * `entries` - Expired entries the run removed
+    pub fn record_expiry_sweep(&self, entries: u64) {
+        self.expiry_sweeps.fetch_add(1, Ordering::Relaxed);
+        self.expired_entries_swept.fetch_add(entries, Ordering::Relaxed);
+        self.last_sweep_entries.store(entries, Ordering::Relaxed);
+    }
+    
+    /// Record how a sliced request was served: all slices from cache, all
+    /// from origin, or a mix of both
+    ///
+    /// # Arguments
+    /// * `cached` - Slices of the request found in cache
+    /// * `uncached` - Slices of the request fetched from origin
+    pub fn record_slice_hit_mix(&self, cached: usize, uncached: usize) {
+        let counter = match (cached, uncached) {
+            (0, 0) => return,
+            (_, 0) => &self.full_hit_requests,
+            (0, _) => &self.full_miss_requests,
+            _ => &self.partial_hit_requests,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    
     /// Record a subrequest
     ///
     /// # Arguments
@@ -131,6 +421,24 @@ impl SliceMetrics {
         self.retried_subrequests.fetch_add(1, Ordering::Relaxed);
     }
     
+    /// Record a connection opened to the origin
+    pub fn record_origin_connection(&self) {
+        self.origin_connections.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record the HTTP version a subrequest was answered over
+    ///
+    /// # Arguments
+    /// * `version` - Version of the origin's response
+    pub fn record_subrequest_version(&self, version: http::Version) {
+        let counter = if version == http::Version::HTTP_2 {
+            &self.http2_subrequests
+        } else {
+            &self.http1_subrequests
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    
     /// Record bytes received from origin
     ///
     /// # Arguments
@@ -155,6 +463,162 @@ impl SliceMetrics {
         self.bytes_to_client.fetch_add(bytes, Ordering::Relaxed);
     }
     
+    /// Record a slice fetched and stored by the cache warmer
+    ///
+    /// # Arguments
+    /// * `bytes` - Size of the warmed slice
+    pub fn record_warmed_slice(&self, bytes: u64) {
+        self.warmed_slices.fetch_add(1, Ordering::Relaxed);
+        self.warmed_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+    
+    /// Record time spent waiting on the origin rate limiter
+    ///
+    /// # Arguments
+    /// * `duration` - How long a subrequest was delayed
+    pub fn record_origin_throttle(&self, duration: Duration) {
+        self.origin_throttle_wait_us
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+    
+    /// Record current origin rate limiter utilization
+    ///
+    /// # Arguments
+    /// * `bandwidth` - Share of the bandwidth cap in use (1.0 = at the cap)
+    /// * `requests` - Share of the request rate cap in use (1.0 = at the cap)
+    pub fn set_origin_utilization(&self, bandwidth: f64, requests: f64) {
+        self.origin_bandwidth_utilization
+            .store((bandwidth * 100.0).round() as u64, Ordering::Relaxed);
+        self.origin_request_utilization
+            .store((requests * 100.0).round() as u64, Ordering::Relaxed);
+    }
+    
+    /// Record the subrequest concurrency currently allowed by slow start
+    ///
+    /// # Arguments
+    /// * `limit` - Number of subrequests allowed in flight
+    pub fn set_effective_concurrency(&self, limit: usize) {
+        self.effective_concurrency.store(limit as u64, Ordering::Relaxed);
+    }
+    
+    /// Record a request whose client disconnected before the response completed
+    pub fn record_client_abort(&self) {
+        self.client_aborts.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a request that fell back to fetching the whole object after
+    /// its slice fetches kept failing
+    pub fn record_whole_object_fallback(&self) {
+        self.whole_object_fallbacks.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a request cut short because its deadline passed
+    pub fn record_deadline_abort(&self) {
+        self.deadline_aborts.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a cache fill completed in the background after a client disconnect
+    pub fn record_background_fill(&self) {
+        self.background_fills.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record slices fetched and cached ahead of a sequential download
+    pub fn record_prefetched_slices(&self, slices: u64) {
+        self.prefetched_slices.fetch_add(slices, Ordering::Relaxed);
+    }
+    
+    /// Record a request rejected by the per-client rate limit
+    pub fn record_throttled_request(&self) {
+        self.throttled_requests.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a cache miss proxied without caching because the fill limits
+    /// were reached
+    pub fn record_fill_bypass(&self) {
+        self.fill_bypasses.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record bytes reserved by a cache fill that started
+    pub fn add_fill_buffered_bytes(&self, bytes: u64) {
+        self.fill_buffered_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+    
+    /// Record bytes released by a cache fill that finished or was aborted
+    pub fn release_fill_buffered_bytes(&self, bytes: u64) {
+        self.fill_buffered_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+    
+    /// Record a response not cached because it carried per-user headers
+    pub fn record_private_skip(&self) {
+        self.private_skips.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a request forwarded to the cluster peer that owns it
+    ///
+    /// # Arguments
+    /// * `peer` - Address of the peer the request was routed to
+    pub fn record_cluster_route(&self, peer: &str) {
+        let mut routed = self.cluster_routed.lock().unwrap();
+        *routed.entry(peer.to_string()).or_default() += 1;
+    }
+    
+    /// Record an origin request sent to one upstream of the pool
+    ///
+    /// # Arguments
+    /// * `upstream` - Address of the upstream the request went to
+    /// * `success` - Whether the upstream answered without a retryable error
+    pub fn record_upstream_request(&self, upstream: &str, success: bool) {
+        *self.upstream_requests.lock().unwrap().entry(upstream.to_string()).or_default() += 1;
+        if !success {
+            *self.upstream_failures.lock().unwrap().entry(upstream.to_string()).or_default() += 1;
+        }
+    }
+    
+    /// Record a retry sent to another upstream after a failure
+    ///
+    /// # Arguments
+    /// * `from` - Address of the upstream the previous attempt failed on
+    /// * `to` - Address of the upstream the retry went to
+    pub fn record_upstream_failover(&self, from: &str, to: &str) {
+        *self
+            .upstream_failovers
+            .lock()
+            .unwrap()
+            .entry((from.to_string(), to.to_string()))
+            .or_default() += 1;
+    }
+    
+    /// Record a sliceable request matching a configured URL pattern
+    ///
+    /// # Arguments
+    /// * `pattern` - Label of the matched pattern (see
+    ///   [`RequestAnalyzer::pattern_label`](crate::RequestAnalyzer::pattern_label))
+    pub fn record_pattern_request(&self, pattern: &str) {
+        self.pattern_stats.lock().unwrap().entry(pattern.to_string()).or_default().requests += 1;
+    }
+    
+    /// Record slice cache hits and misses of a request matching a URL pattern
+    ///
+    /// # Arguments
+    /// * `pattern` - Label of the matched pattern
+    /// * `hits` - Number of slices served from cache
+    /// * `misses` - Number of slices fetched from the origin
+    pub fn record_pattern_cache(&self, pattern: &str, hits: u64, misses: u64) {
+        let mut patterns = self.pattern_stats.lock().unwrap();
+        let stats = patterns.entry(pattern.to_string()).or_default();
+        stats.cache_hits += hits;
+        stats.cache_misses += misses;
+    }
+    
+    /// Record bytes sent to the client for a request matching a URL pattern
+    ///
+    /// # Arguments
+    /// * `pattern` - Label of the matched pattern
+    /// * `bytes` - Number of bytes sent
+    pub fn record_pattern_bytes_to_client(&self, pattern: &str, bytes: u64) {
+        self.pattern_stats.lock().unwrap().entry(pattern.to_string()).or_default().bytes_to_client += bytes;
+    }
+    
     /// Record request duration
     ///
     /// # Arguments
@@ -165,6 +629,7 @@ impl SliceMetrics {
     pub fn record_request_duration(&self, duration: Duration) {
         self.total_request_duration_us
             .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.request_latency.observe(duration);
     }
     
     /// Record subrequest duration
@@ -179,6 +644,35 @@ impl SliceMetrics {
             .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
     }
     
+    /// Record the latency of a single slice fetch
+    ///
+    /// Unlike [`record_subrequest_duration`](Self::record_subrequest_duration),
+    /// which accumulates the time spent fetching all slices of a request, this
+    /// feeds the per-slice subrequest latency histogram.
+    ///
+    /// # Arguments
+    /// * `duration` - Duration of the slice fetch, including retries
+    pub fn record_subrequest_latency(&self, duration: Duration) {
+        self.subrequest_latency.observe(duration);
+    }
+    
+    /// Record time to first byte
+    ///
+    /// # Arguments
+    /// * `duration` - Time from the start of the request until the first slice was available
+    pub fn record_ttfb(&self, duration: Duration) {
+        self.ttfb_latency.observe(duration);
+    }
+    
+    /// Record time to first slice
+    ///
+    /// # Arguments
+    /// * `duration` - Time from dispatching a request's slice fetches until
+    ///   the lowest-index slice was fetched
+    pub fn record_first_slice(&self, duration: Duration) {
+        self.first_slice_latency.observe(duration);
+    }
+    
     /// Record assembly duration
     ///
     /// # Arguments
@@ -186,6 +680,7 @@ impl SliceMetrics {
     pub fn record_assembly_duration(&self, duration: Duration) {
         self.total_assembly_duration_us
             .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.assembly_latency.observe(duration);
     }
     
     /// Get a snapshot of current metrics
@@ -204,37 +699,125 @@ impl SliceMetrics {
             cache_hits: self.cache_hits.load(Ordering::Relaxed),
             cache_misses: self.cache_misses.load(Ordering::Relaxed),
             cache_errors: self.cache_errors.load(Ordering::Relaxed),
+            expiry_sweeps: self.expiry_sweeps.load(Ordering::Relaxed),
+            expired_entries_swept: self.expired_entries_swept.load(Ordering::Relaxed),
+            last_sweep_entries: self.last_sweep_entries.load(Ordering::Relaxed),
+            full_hit_requests: self.full_hit_requests.load(Ordering::Relaxed),
+            partial_hit_requests: self.partial_hit_requests.load(Ordering::Relaxed),
+            full_miss_requests: self.full_miss_requests.load(Ordering::Relaxed),
             total_subrequests: self.total_subrequests.load(Ordering::Relaxed),
             failed_subrequests: self.failed_subrequests.load(Ordering::Relaxed),
             retried_subrequests: self.retried_subrequests.load(Ordering::Relaxed),
+            origin_connections: self.origin_connections.load(Ordering::Relaxed),
+            http1_subrequests: self.http1_subrequests.load(Ordering::Relaxed),
+            http2_subrequests: self.http2_subrequests.load(Ordering::Relaxed),
             bytes_from_origin: self.bytes_from_origin.load(Ordering::Relaxed),
             bytes_from_cache: self.bytes_from_cache.load(Ordering::Relaxed),
             bytes_to_client: self.bytes_to_client.load(Ordering::Relaxed),
+            warmed_slices: self.warmed_slices.load(Ordering::Relaxed),
+            warmed_bytes: self.warmed_bytes.load(Ordering::Relaxed),
+            origin_throttle_wait_us: self.origin_throttle_wait_us.load(Ordering::Relaxed),
+            origin_bandwidth_utilization: self.origin_bandwidth_utilization.load(Ordering::Relaxed),
+            origin_request_utilization: self.origin_request_utilization.load(Ordering::Relaxed),
+            effective_concurrency: self.effective_concurrency.load(Ordering::Relaxed),
+            client_aborts: self.client_aborts.load(Ordering::Relaxed),
+            deadline_aborts: self.deadline_aborts.load(Ordering::Relaxed),
+            whole_object_fallbacks: self.whole_object_fallbacks.load(Ordering::Relaxed),
+            background_fills: self.background_fills.load(Ordering::Relaxed),
+            prefetched_slices: self.prefetched_slices.load(Ordering::Relaxed),
+            throttled_requests: self.throttled_requests.load(Ordering::Relaxed),
+            fill_bypasses: self.fill_bypasses.load(Ordering::Relaxed),
+            fill_buffered_bytes: self.fill_buffered_bytes.load(Ordering::Relaxed),
+            private_skips: self.private_skips.load(Ordering::Relaxed),
+            cluster_routed: self.cluster_routed.lock().unwrap().clone(),
+            upstream_requests: self.upstream_requests.lock().unwrap().clone(),
+            upstream_failures: self.upstream_failures.lock().unwrap().clone(),
+            upstream_failovers: self.upstream_failovers.lock().unwrap().clone(),
+            pattern_stats: self.pattern_stats.lock().unwrap().clone(),
             total_request_duration_us: self.total_request_duration_us.load(Ordering::Relaxed),
             total_subrequest_duration_us: self.total_subrequest_duration_us.load(Ordering::Relaxed),
             total_assembly_duration_us: self.total_assembly_duration_us.load(Ordering::Relaxed),
+            request_latency: self.request_latency.snapshot(),
+            subrequest_latency: self.subrequest_latency.snapshot(),
+            assembly_latency: self.assembly_latency.snapshot(),
+            ttfb_latency: self.ttfb_latency.snapshot(),
+            first_slice_latency: self.first_slice_latency.snapshot(),
         }
     }
     
-    /// Reset all metrics to zero
+    /// Reset all counters and latency histograms to zero
     ///
-    /// This is primarily useful for testing.
+    /// Same as [`snapshot_and_reset`](Self::snapshot_and_reset) without the
+    /// snapshot.
     pub fn reset(&self) {
-        self.total_requests.store(0, Ordering::Relaxed);
-        self.sliced_requests.store(0, Ordering::Relaxed);
-        self.passthrough_requests.store(0, Ordering::Relaxed);
-        self.cache_hits.store(0, Ordering::Relaxed);
-        self.cache_misses.store(0, Ordering::Relaxed);
-        self.cache_errors.store(0, Ordering::Relaxed);
-        self.total_subrequests.store(0, Ordering::Relaxed);
-        self.failed_subrequests.store(0, Ordering::Relaxed);
-        self.retried_subrequests.store(0, Ordering::Relaxed);
-        self.bytes_from_origin.store(0, Ordering::Relaxed);
-        self.bytes_from_cache.store(0, Ordering::Relaxed);
-        self.bytes_to_client.store(0, Ordering::Relaxed);
-        self.total_request_duration_us.store(0, Ordering::Relaxed);
-        self.total_subrequest_duration_us.store(0, Ordering::Relaxed);
-        self.total_assembly_duration_us.store(0, Ordering::Relaxed);
+        self.snapshot_and_reset();
+    }
+
+    /// Get a snapshot of the metrics and reset them for the next interval
+    ///
+    /// Counters, the per-peer, per-upstream and per-pattern maps, and the
+    /// latency histograms are reset. Gauges describe current state rather
+    /// than an interval, so `last_sweep_entries`, the origin utilizations,
+    /// `effective_concurrency` and `fill_buffered_bytes` keep their values.
+    ///
+    /// Each value is swapped for zero atomically, so with recording running
+    /// concurrently every event is counted in exactly one interval: either
+    /// in the returned snapshot or in the live metrics afterwards. As with
+    /// [`get_stats`](Self::get_stats), related fields of the snapshot may
+    /// disagree by the events recorded while it was taken.
+    pub fn snapshot_and_reset(&self) -> MetricsSnapshot {
+        let take = |counter: &AtomicU64| counter.swap(0, Ordering::Relaxed);
+        MetricsSnapshot {
+            total_requests: take(&self.total_requests),
+            sliced_requests: take(&self.sliced_requests),
+            passthrough_requests: take(&self.passthrough_requests),
+            cache_hits: take(&self.cache_hits),
+            cache_misses: take(&self.cache_misses),
+            cache_errors: take(&self.cache_errors),
+            expiry_sweeps: take(&self.expiry_sweeps),
+            expired_entries_swept: take(&self.expired_entries_swept),
+            last_sweep_entries: self.last_sweep_entries.load(Ordering::Relaxed),
+            full_hit_requests: take(&self.full_hit_requests),
+            partial_hit_requests: take(&self.partial_hit_requests),
+            full_miss_requests: take(&self.full_miss_requests),
+            total_subrequests: take(&self.total_subrequests),
+            failed_subrequests: take(&self.failed_subrequests),
+            retried_subrequests: take(&self.retried_subrequests),
+            origin_connections: take(&self.origin_connections),
+            http1_subrequests: take(&self.http1_subrequests),
+            http2_subrequests: take(&self.http2_subrequests),
+            bytes_from_origin: take(&self.bytes_from_origin),
+            bytes_from_cache: take(&self.bytes_from_cache),
+            bytes_to_client: take(&self.bytes_to_client),
+            warmed_slices: take(&self.warmed_slices),
+            warmed_bytes: take(&self.warmed_bytes),
+            origin_throttle_wait_us: take(&self.origin_throttle_wait_us),
+            origin_bandwidth_utilization: self.origin_bandwidth_utilization.load(Ordering::Relaxed),
+            origin_request_utilization: self.origin_request_utilization.load(Ordering::Relaxed),
+            effective_concurrency: self.effective_concurrency.load(Ordering::Relaxed),
+            client_aborts: take(&self.client_aborts),
+            deadline_aborts: take(&self.deadline_aborts),
+            whole_object_fallbacks: take(&self.whole_object_fallbacks),
+            background_fills: take(&self.background_fills),
+            prefetched_slices: take(&self.prefetched_slices),
+            throttled_requests: take(&self.throttled_requests),
+            fill_bypasses: take(&self.fill_bypasses),
+            fill_buffered_bytes: self.fill_buffered_bytes.load(Ordering::Relaxed),
+            private_skips: take(&self.private_skips),
+            cluster_routed: std::mem::take(&mut *self.cluster_routed.lock().unwrap()),
+            upstream_requests: std::mem::take(&mut *self.upstream_requests.lock().unwrap()),
+            upstream_failures: std::mem::take(&mut *self.upstream_failures.lock().unwrap()),
+            upstream_failovers: std::mem::take(&mut *self.upstream_failovers.lock().unwrap()),
+            pattern_stats: std::mem::take(&mut *self.pattern_stats.lock().unwrap()),
+            total_request_duration_us: take(&self.total_request_duration_us),
+            total_subrequest_duration_us: take(&self.total_subrequest_duration_us),
+            total_assembly_duration_us: take(&self.total_assembly_duration_us),
+            request_latency: self.request_latency.take(),
+            subrequest_latency: self.subrequest_latency.take(),
+            assembly_latency: self.assembly_latency.take(),
+            ttfb_latency: self.ttfb_latency.take(),
+            first_slice_latency: self.first_slice_latency.take(),
+        }
     }
 }
 
@@ -248,6 +831,26 @@ impl MetricsSnapshot {
             (self.cache_hits as f64 / total as f64) * 100.0
         }
     }
+
+    /// Fraction of slice lookups served from cache (0.0 to 1.0)
+    pub fn cache_hit_ratio(&self) -> f64 {
+        let total = self.cache_hits + self.cache_misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.cache_hits as f64 / total as f64
+        }
+    }
+
+    /// Fraction of slice bytes served from cache rather than the origin (0.0 to 1.0)
+    pub fn byte_hit_ratio(&self) -> f64 {
+        let total = self.bytes_from_cache + self.bytes_from_origin;
+        if total == 0 {
+            0.0
+        } else {
+            self.bytes_from_cache as f64 / total as f64
+        }
+    }
     
     /// Calculate average request duration in milliseconds
     pub fn avg_request_duration_ms(&self) -> f64 {
@@ -276,6 +879,17 @@ impl MetricsSnapshot {
         }
     }
     
+    /// Percentage of subrequests sent over an already open connection
+    /// (0.0 to 100.0)
+    pub fn origin_connection_reuse_rate(&self) -> f64 {
+        if self.total_subrequests == 0 {
+            0.0
+        } else {
+            let reused = self.total_subrequests.saturating_sub(self.origin_connections);
+            (reused as f64 / self.total_subrequests as f64) * 100.0
+        }
+    }
+    
     /// Calculate subrequest failure rate as a percentage (0.0 to 100.0)
     pub fn subrequest_failure_rate(&self) -> f64 {
         if self.total_subrequests == 0 {
@@ -383,6 +997,36 @@ mod tests {
         let stats = metrics.get_stats();
         assert_eq!(stats.cache_hit_rate(), 0.0);
     }
+
+    #[test]
+    fn test_cache_hit_ratio() {
+        let metrics = SliceMetrics::new();
+        assert_eq!(metrics.get_stats().cache_hit_ratio(), 0.0);
+
+        metrics.record_cache_hit();
+        metrics.record_cache_miss();
+        metrics.record_cache_miss();
+        metrics.record_cache_miss();
+
+        let stats = metrics.get_stats();
+        assert_eq!(stats.cache_hit_ratio(), 0.25);
+    }
+
+    #[test]
+    fn test_byte_hit_ratio() {
+        let metrics = SliceMetrics::new();
+        assert_eq!(metrics.get_stats().byte_hit_ratio(), 0.0);
+
+        metrics.record_bytes_from_cache(3000);
+        metrics.record_bytes_from_origin(1000);
+
+        let stats = metrics.get_stats();
+        assert_eq!(stats.byte_hit_ratio(), 0.75);
+
+        metrics.reset();
+        metrics.record_bytes_from_origin(1000);
+        assert_eq!(metrics.get_stats().byte_hit_ratio(), 0.0);
+    }
     
     #[test]
     fn test_avg_request_duration() {
@@ -397,6 +1041,31 @@ mod tests {
         assert_eq!(stats.avg_request_duration_ms(), 150.0);
     }
     
+    #[test]
+    fn test_origin_connection_reuse_rate() {
+        let metrics = SliceMetrics::new();
+        assert_eq!(metrics.get_stats().origin_connection_reuse_rate(), 0.0);
+        
+        metrics.record_origin_connection();
+        for _ in 0..4 {
+            metrics.record_subrequest(true);
+        }
+        assert_eq!(metrics.get_stats().origin_connection_reuse_rate(), 75.0);
+    }
+    
+    #[test]
+    fn test_subrequest_versions() {
+        let metrics = SliceMetrics::new();
+        metrics.record_subrequest_version(http::Version::HTTP_11);
+        metrics.record_subrequest_version(http::Version::HTTP_2);
+        metrics.record_subrequest_version(http::Version::HTTP_2);
+        
+        let stats = metrics.get_stats();
+        assert_eq!((stats.http1_subrequests, stats.http2_subrequests), (1, 2));
+        metrics.reset();
+        assert_eq!(metrics.get_stats().http2_subrequests, 0);
+    }
+    
     #[test]
     fn test_subrequest_failure_rate() {
         let metrics = SliceMetrics::new();
@@ -426,6 +1095,110 @@ mod tests {
         assert_eq!(stats.total_subrequests, 0);
     }
     
+    #[test]
+    fn test_histogram_bucket_boundaries() {
+        let histogram = LatencyHistogram::default();
+        
+        histogram.observe(Duration::from_micros(1_000)); // exactly 1ms -> le=1
+        histogram.observe(Duration::from_micros(1_001)); // just above -> le=2
+        histogram.observe(Duration::from_millis(60_000)); // exactly 60s -> le=60000
+        histogram.observe(Duration::from_millis(60_001)); // -> +Inf
+        
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.buckets.len(), LATENCY_BUCKETS_MS.len() + 1);
+        assert_eq!(snapshot.buckets[0], 1);
+        assert_eq!(snapshot.buckets[1], 1);
+        assert_eq!(snapshot.buckets[LATENCY_BUCKETS_MS.len() - 1], 1);
+        assert_eq!(snapshot.buckets[LATENCY_BUCKETS_MS.len()], 1);
+        assert_eq!(snapshot.count, 4);
+        assert_eq!(snapshot.max_us, 60_001_000);
+    }
+    
+    #[test]
+    fn test_histogram_percentiles() {
+        let metrics = SliceMetrics::new();
+        
+        // 90 fast requests (<= 10ms), 9 at 200ms, 1 at 3s
+        for _ in 0..90 {
+            metrics.record_request_duration(Duration::from_millis(8));
+        }
+        for _ in 0..9 {
+            metrics.record_request_duration(Duration::from_millis(200));
+        }
+        metrics.record_request_duration(Duration::from_millis(3_000));
+        
+        let latency = metrics.get_stats().request_latency;
+        assert_eq!(latency.count, 100);
+        assert_eq!(latency.p50_ms(), 10.0);
+        assert_eq!(latency.p90_ms(), 10.0);
+        assert_eq!(latency.p99_ms(), 250.0);
+        assert_eq!(latency.percentile_ms(1.0), 3_000.0);
+        assert_eq!(latency.max_ms(), 3_000.0);
+    }
+    
+    #[test]
+    fn test_histogram_percentile_capped_at_max() {
+        let metrics = SliceMetrics::new();
+        metrics.record_ttfb(Duration::from_millis(30));
+        metrics.record_ttfb(Duration::from_secs(120));
+        
+        let ttfb = metrics.get_stats().ttfb_latency;
+        // 30ms falls in the 50ms bucket but the max observed is higher
+        assert_eq!(ttfb.p50_ms(), 50.0);
+        // +Inf bucket reports the max
+        assert_eq!(ttfb.p99_ms(), 120_000.0);
+    }
+    
+    #[test]
+    fn test_histogram_empty_and_reset() {
+        let metrics = SliceMetrics::new();
+        assert_eq!(metrics.get_stats().subrequest_latency.p99_ms(), 0.0);
+        
+        metrics.record_subrequest_latency(Duration::from_millis(5));
+        metrics.record_assembly_duration(Duration::from_millis(1));
+        metrics.reset();
+        
+        let stats = metrics.get_stats();
+        assert_eq!(stats.subrequest_latency.count, 0);
+        assert_eq!(stats.assembly_latency.count, 0);
+        assert_eq!(stats.assembly_latency.max_us, 0);
+    }
+    
+    #[test]
+    fn test_cluster_routes_per_peer() {
+        let metrics = SliceMetrics::new();
+        metrics.record_cluster_route("10.0.0.1:8080");
+        metrics.record_cluster_route("10.0.0.1:8080");
+        metrics.record_cluster_route("10.0.0.2:8080");
+        
+        let stats = metrics.get_stats();
+        assert_eq!(stats.cluster_routed.get("10.0.0.1:8080"), Some(&2));
+        assert_eq!(stats.cluster_routed.get("10.0.0.2:8080"), Some(&1));
+        
+        metrics.reset();
+        assert!(metrics.get_stats().cluster_routed.is_empty());
+    }
+    
+    #[test]
+    fn test_upstream_requests_per_upstream() {
+        let metrics = SliceMetrics::new();
+        metrics.record_upstream_request("10.0.0.1:80", true);
+        metrics.record_upstream_request("10.0.0.1:80", false);
+        metrics.record_upstream_request("10.0.0.2:80", true);
+        
+        let stats = metrics.get_stats();
+        assert_eq!(stats.upstream_requests.get("10.0.0.1:80"), Some(&2));
+        assert_eq!(stats.upstream_requests.get("10.0.0.2:80"), Some(&1));
+        assert_eq!(stats.upstream_failures.get("10.0.0.1:80"), Some(&1));
+        assert_eq!(stats.upstream_failures.get("10.0.0.2:80"), None);
+        
+        metrics.record_upstream_failover("10.0.0.1:80", "10.0.0.2:80");
+        let pair = ("10.0.0.1:80".to_string(), "10.0.0.2:80".to_string());
+        assert_eq!(metrics.get_stats().upstream_failovers.get(&pair), Some(&1));
+        metrics.reset();
+        assert!(metrics.get_stats().upstream_failovers.is_empty());
+    }
+    
     #[test]
     fn test_thread_safety() {
         let metrics = Arc::new(SliceMetrics::new());
@@ -454,4 +1227,65 @@ mod tests {
         assert_eq!(stats.cache_hits, 1000);
         assert_eq!(stats.total_subrequests, 1000);
     }
+    
+    #[test]
+    fn test_snapshot_and_reset() {
+        let metrics = SliceMetrics::new();
+        metrics.record_request(true);
+        metrics.record_cache_hit();
+        metrics.record_bytes_to_client(4096);
+        metrics.record_cluster_route("10.0.0.1:8080");
+        metrics.record_request_duration(Duration::from_millis(20));
+        metrics.set_effective_concurrency(8);
+
+        let interval = metrics.snapshot_and_reset();
+        assert_eq!(interval.total_requests, 1);
+        assert_eq!(interval.cache_hits, 1);
+        assert_eq!(interval.bytes_to_client, 4096);
+        assert_eq!(interval.cluster_routed.get("10.0.0.1:8080"), Some(&1));
+        assert_eq!(interval.request_latency.count, 1);
+        assert_eq!(interval.total_request_duration_us, 20_000);
+
+        let live = metrics.get_stats();
+        assert_eq!(live.total_requests, 0);
+        assert_eq!(live.cache_hits, 0);
+        assert_eq!(live.bytes_to_client, 0);
+        assert!(live.cluster_routed.is_empty());
+        assert_eq!(live.request_latency, HistogramSnapshot {
+            buckets: vec![0; LATENCY_BUCKET_COUNT],
+            ..HistogramSnapshot::default()
+        });
+        // Gauges keep describing the current state
+        assert_eq!(live.effective_concurrency, 8);
+    }
+    
+    #[test]
+    fn test_snapshot_and_reset_loses_nothing_under_concurrency() {
+        let metrics = Arc::new(SliceMetrics::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let metrics = Arc::clone(&metrics);
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        metrics.record_request(true);
+                        metrics.record_subrequest_latency(Duration::from_millis(1));
+                    }
+                })
+            })
+            .collect();
+
+        let mut requests = 0;
+        let mut observations = 0;
+        while !handles.iter().all(|handle| handle.is_finished()) {
+            let interval = metrics.snapshot_and_reset();
+            requests += interval.total_requests;
+            observations += interval.subrequest_latency.count;
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let rest = metrics.snapshot_and_reset();
+        assert_eq!(requests + rest.total_requests, 40_000);
+        assert_eq!(observations + rest.subrequest_latency.count, 40_000);
+    }
 }
@@ -7,24 +7,40 @@
 //! # Requirements
 //! Validates: Requirements 9.5
 
-use crate::metrics::{SliceMetrics, MetricsSnapshot};
+use crate::health::HealthChecker;
+use crate::metrics::{HistogramSnapshot, MetricsSnapshot, PatternStats, SliceMetrics, LATENCY_BUCKETS_MS};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use http_body_util::Full;
 use hyper::body::Bytes;
+use hyper::header::{ACCEPT_ENCODING, AUTHORIZATION};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Request, Response, StatusCode};
 use hyper_util::rt::TokioIo;
+use prometheus::{Registry, TextEncoder};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::TcpListener;
 use tracing::{error, info};
 
+/// Compression level for gzipped scrapes (zlib's default)
+const GZIP_LEVEL: u8 = 6;
+
 /// Metrics endpoint server
 ///
 /// Provides an HTTP server that exposes metrics in Prometheus format.
 pub struct MetricsEndpoint {
     metrics: Arc<SliceMetrics>,
     addr: SocketAddr,
+    health: Option<Arc<HealthChecker>>,
+    /// Collectors served after the slice metrics
+    registry: Option<Registry>,
+    path: String,
+    /// `user:password` scrapers must present
+    credentials: Option<String>,
+    started: Instant,
 }
 
 impl MetricsEndpoint {
@@ -41,16 +57,59 @@ impl MetricsEndpoint {
     /// use std::sync::Arc;
     ///
     /// let metrics = Arc::new(SliceMetrics::new());
-    /// let endpoint = MetricsEndpoint::new(metrics, "127.0.0.1:9090".parse().unwrap());
+    /// let endpoint = MetricsEndpoint::new(metrics, "127.0.0.1:9090".parse().unwrap())
+    ///     .with_path("/internal/metrics")
+    ///     .with_basic_auth("prometheus", "s3cret");
     /// ```
     pub fn new(metrics: Arc<SliceMetrics>, addr: SocketAddr) -> Self {
-        Self { metrics, addr }
+        Self {
+            metrics,
+            addr,
+            health: None,
+            registry: None,
+            path: "/metrics".to_string(),
+            credentials: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Serve `/health` and `/health/live` from a health checker
+    ///
+    /// Without one, `/health` always reports healthy.
+    pub fn with_health_checker(mut self, health: Arc<HealthChecker>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Append the collectors of a Prometheus registry to each scrape
+    ///
+    /// For metrics kept outside [`SliceMetrics`], such as
+    /// [`PurgeMetrics::with_registry`](crate::purge_metrics::PurgeMetrics::with_registry).
+    pub fn with_registry(mut self, registry: Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Serve the metrics at `path` instead of `/metrics`
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Require HTTP basic auth for the metrics path
+    ///
+    /// Scrapes without matching credentials get 401. The health paths and
+    /// the index stay open so load balancer probes need no credentials.
+    pub fn with_basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some(format!("{}:{}", username, password));
+        self
     }
 
     /// Start the metrics endpoint server
     ///
     /// This method starts an HTTP server that listens on the configured address
-    /// and serves metrics in Prometheus format at the `/metrics` endpoint.
+    /// and serves metrics in Prometheus format at the configured path
+    /// (`/metrics` by default).
     ///
     /// The server runs indefinitely until the process is terminated.
     ///
@@ -73,17 +132,18 @@ impl MetricsEndpoint {
     pub async fn start(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let listener = TcpListener::bind(self.addr).await?;
         info!("Metrics endpoint listening on http://{}", self.addr);
-        info!("Metrics available at http://{}/metrics", self.addr);
+        info!("Metrics available at http://{}{}", self.addr, self.path);
 
+        let endpoint = Arc::new(self);
         loop {
             let (stream, _) = listener.accept().await?;
             let io = TokioIo::new(stream);
-            let metrics = Arc::clone(&self.metrics);
+            let endpoint = Arc::clone(&endpoint);
 
             tokio::task::spawn(async move {
                 let service = service_fn(move |req| {
-                    let metrics = Arc::clone(&metrics);
-                    async move { handle_request(req, metrics).await }
+                    let endpoint = Arc::clone(&endpoint);
+                    async move { handle_request(req, &endpoint).await }
                 });
 
                 if let Err(err) = http1::Builder::new().serve_connection(io, service).await {
@@ -92,34 +152,157 @@ impl MetricsEndpoint {
             });
         }
     }
+
+    /// Whether a request carries the configured basic auth credentials
+    fn is_authorized<B>(&self, req: &Request<B>) -> bool {
+        let Some(expected) = &self.credentials else {
+            return true;
+        };
+        req.headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+            .and_then(|(_, encoded)| BASE64.decode(encoded.trim()).ok())
+            .is_some_and(|decoded| decoded == expected.as_bytes())
+    }
 }
 
 /// Handle incoming HTTP requests
 async fn handle_request(
     req: Request<hyper::body::Incoming>,
-    metrics: Arc<SliceMetrics>,
+    endpoint: &MetricsEndpoint,
 ) -> Result<Response<Full<Bytes>>, hyper::Error> {
-    match req.uri().path() {
-        "/metrics" => Ok(metrics_response(metrics)),
-        "/health" => Ok(health_response()),
-        "/" => Ok(index_response()),
+    let path = req.uri().path();
+    if path == endpoint.path {
+        if !endpoint.is_authorized(&req) {
+            return Ok(unauthorized_response());
+        }
+        return Ok(metrics_response(endpoint, accepts_gzip(&req)));
+    }
+
+    match (path, endpoint.health.as_deref()) {
+        ("/health", Some(health)) => Ok(readiness_response(health).await),
+        ("/health", None) => Ok(health_response()),
+        ("/health/live", health) => Ok(liveness_response(health)),
+        ("/", _) => Ok(index_response(&endpoint.path)),
         _ => Ok(not_found_response()),
     }
 }
 
+/// Whether the client listed gzip in `Accept-Encoding` without `q=0`
+fn accepts_gzip<B>(req: &Request<B>) -> bool {
+    req.headers()
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let refused = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+        })
+}
+
 /// Generate the metrics response in Prometheus format
 ///
 /// # Requirements
 /// Validates: Requirements 9.5
-fn metrics_response(metrics: Arc<SliceMetrics>) -> Response<Full<Bytes>> {
-    let snapshot = metrics.get_stats();
-    let body = format_prometheus_metrics(&snapshot);
+fn metrics_response(endpoint: &MetricsEndpoint, gzip: bool) -> Response<Full<Bytes>> {
+    let snapshot = endpoint.metrics.get_stats();
+    let mut body = format_prometheus_metrics(&snapshot);
+    format_process_metrics(&mut body, &ProcessStats::collect(endpoint.started));
+    if let Some(registry) = &endpoint.registry {
+        if let Err(e) = TextEncoder::new().encode_utf8(&registry.gather(), &mut body) {
+            error!("Failed to encode registry metrics: {}", e);
+        }
+    }
 
-    Response::builder()
+    let response = Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
-        .body(Full::new(Bytes::from(body)))
-        .unwrap()
+        .header("Vary", "Accept-Encoding");
+    if gzip {
+        response
+            .header("Content-Encoding", "gzip")
+            .body(Full::new(Bytes::from(gzip_encode(body.as_bytes()))))
+            .unwrap()
+    } else {
+        response.body(Full::new(Bytes::from(body))).unwrap()
+    }
+}
+
+/// Compress a scrape body as a gzip member
+///
+/// Deflates with miniz_oxide directly: pingora-core switches flate2 to
+/// zlib-ng, whose encoder can fail to initialise, and a scrape must not
+/// depend on it. Encoding into memory cannot fail.
+fn gzip_encode(data: &[u8]) -> Vec<u8> {
+    let deflated = miniz_oxide::deflate::compress_to_vec(data, GZIP_LEVEL);
+    // Header: magic, deflate, no flags, no mtime, no extra flags, unknown OS
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.reserve(deflated.len() + 8);
+    out.extend_from_slice(&deflated);
+    out.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Resource usage of this process
+///
+/// Memory and descriptor counts come from `/proc` and are `None` where it
+/// is unavailable.
+struct ProcessStats {
+    resident_memory_bytes: Option<u64>,
+    open_fds: Option<usize>,
+    uptime_secs: f64,
+}
+
+impl ProcessStats {
+    fn collect(started: Instant) -> Self {
+        let resident_memory_bytes = std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| {
+                let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+                let kb = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+                Some(kb * 1024)
+            });
+        let open_fds = std::fs::read_dir("/proc/self/fd").ok().map(|dir| dir.count());
+
+        Self {
+            resident_memory_bytes,
+            open_fds,
+            uptime_secs: started.elapsed().as_secs_f64(),
+        }
+    }
+}
+
+/// Append process resource metrics in Prometheus text format
+fn format_process_metrics(output: &mut String, stats: &ProcessStats) {
+    if let Some(bytes) = stats.resident_memory_bytes {
+        output.push_str("# HELP process_resident_memory_bytes Resident memory size in bytes\n");
+        output.push_str("# TYPE process_resident_memory_bytes gauge\n");
+        output.push_str(&format!("process_resident_memory_bytes {}\n", bytes));
+        output.push('\n');
+    }
+
+    if let Some(fds) = stats.open_fds {
+        output.push_str("# HELP process_open_fds Number of open file descriptors\n");
+        output.push_str("# TYPE process_open_fds gauge\n");
+        output.push_str(&format!("process_open_fds {}\n", fds));
+        output.push('\n');
+    }
+
+    output.push_str("# HELP pingora_slice_uptime_seconds Seconds since the metrics endpoint was created\n");
+    output.push_str("# TYPE pingora_slice_uptime_seconds gauge\n");
+    output.push_str(&format!("pingora_slice_uptime_seconds {:.3}\n", stats.uptime_secs));
+    output.push('\n');
 }
 
 /// Format metrics in Prometheus exposition format
@@ -165,11 +348,51 @@ fn format_prometheus_metrics(snapshot: &MetricsSnapshot) -> String {
     output.push_str(&format!("pingora_slice_cache_errors_total {}\n", snapshot.cache_errors));
     output.push_str("\n");
 
+    output.push_str("# HELP pingora_slice_expiry_sweeps_total Runs of the periodic cache expiry sweep\n");
+    output.push_str("# TYPE pingora_slice_expiry_sweeps_total counter\n");
+    output.push_str(&format!("pingora_slice_expiry_sweeps_total {}\n", snapshot.expiry_sweeps));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_expired_entries_swept_total Expired cache entries removed by the expiry sweep\n");
+    output.push_str("# TYPE pingora_slice_expired_entries_swept_total counter\n");
+    output.push_str(&format!("pingora_slice_expired_entries_swept_total {}\n", snapshot.expired_entries_swept));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_last_sweep_entries Expired cache entries removed by the latest expiry sweep\n");
+    output.push_str("# TYPE pingora_slice_last_sweep_entries gauge\n");
+    output.push_str(&format!("pingora_slice_last_sweep_entries {}\n", snapshot.last_sweep_entries));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_full_hit_requests_total Sliced requests served entirely from cache\n");
+    output.push_str("# TYPE pingora_slice_full_hit_requests_total counter\n");
+    output.push_str(&format!("pingora_slice_full_hit_requests_total {}\n", snapshot.full_hit_requests));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_partial_hit_requests_total Sliced requests served partly from cache and partly from origin\n");
+    output.push_str("# TYPE pingora_slice_partial_hit_requests_total counter\n");
+    output.push_str(&format!("pingora_slice_partial_hit_requests_total {}\n", snapshot.partial_hit_requests));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_full_miss_requests_total Sliced requests served entirely from origin\n");
+    output.push_str("# TYPE pingora_slice_full_miss_requests_total counter\n");
+    output.push_str(&format!("pingora_slice_full_miss_requests_total {}\n", snapshot.full_miss_requests));
+    output.push('\n');
+
     output.push_str("# HELP pingora_slice_cache_hit_rate Cache hit rate percentage\n");
     output.push_str("# TYPE pingora_slice_cache_hit_rate gauge\n");
     output.push_str(&format!("pingora_slice_cache_hit_rate {:.2}\n", snapshot.cache_hit_rate()));
     output.push_str("\n");
 
+    output.push_str("# HELP pingora_slice_cache_hit_ratio Fraction of slice lookups served from cache\n");
+    output.push_str("# TYPE pingora_slice_cache_hit_ratio gauge\n");
+    output.push_str(&format!("pingora_slice_cache_hit_ratio {:.4}\n", snapshot.cache_hit_ratio()));
+    output.push_str("\n");
+
+    output.push_str("# HELP pingora_slice_byte_hit_ratio Fraction of slice bytes served from cache\n");
+    output.push_str("# TYPE pingora_slice_byte_hit_ratio gauge\n");
+    output.push_str(&format!("pingora_slice_byte_hit_ratio {:.4}\n", snapshot.byte_hit_ratio()));
+    output.push_str("\n");
+
     // Subrequest metrics
     output.push_str("# HELP pingora_slice_subrequests_total Total number of subrequests sent\n");
     output.push_str("# TYPE pingora_slice_subrequests_total counter\n");
@@ -191,6 +414,27 @@ fn format_prometheus_metrics(snapshot: &MetricsSnapshot) -> String {
     output.push_str(&format!("pingora_slice_subrequest_failure_rate {:.2}\n", snapshot.subrequest_failure_rate()));
     output.push_str("\n");
 
+    output.push_str("# HELP pingora_slice_origin_connections_total Connections opened to origins for subrequests and metadata probes\n");
+    output.push_str("# TYPE pingora_slice_origin_connections_total counter\n");
+    output.push_str(&format!("pingora_slice_origin_connections_total {}\n", snapshot.origin_connections));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_subrequests_by_protocol_total Subrequests answered by origins, by HTTP version\n");
+    output.push_str("# TYPE pingora_slice_subrequests_by_protocol_total counter\n");
+    output.push_str(&format!("pingora_slice_subrequests_by_protocol_total{{protocol=\"http1\"}} {}\n", snapshot.http1_subrequests));
+    output.push_str(&format!("pingora_slice_subrequests_by_protocol_total{{protocol=\"http2\"}} {}\n", snapshot.http2_subrequests));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_whole_object_fallbacks_total Requests that fetched the whole object after repeated slice fetch failures\n");
+    output.push_str("# TYPE pingora_slice_whole_object_fallbacks_total counter\n");
+    output.push_str(&format!("pingora_slice_whole_object_fallbacks_total {}\n", snapshot.whole_object_fallbacks));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_origin_connection_reuse_rate Percentage of subrequests sent over an already open connection\n");
+    output.push_str("# TYPE pingora_slice_origin_connection_reuse_rate gauge\n");
+    output.push_str(&format!("pingora_slice_origin_connection_reuse_rate {:.2}\n", snapshot.origin_connection_reuse_rate()));
+    output.push('\n');
+
     // Byte metrics
     output.push_str("# HELP pingora_slice_bytes_from_origin_total Total bytes received from origin\n");
     output.push_str("# TYPE pingora_slice_bytes_from_origin_total counter\n");
@@ -207,6 +451,148 @@ fn format_prometheus_metrics(snapshot: &MetricsSnapshot) -> String {
     output.push_str(&format!("pingora_slice_bytes_to_client_total {}\n", snapshot.bytes_to_client));
     output.push_str("\n");
 
+    // Cache warming metrics
+    output.push_str("# HELP pingora_slice_warmed_slices_total Slices fetched and stored by the cache warmer\n");
+    output.push_str("# TYPE pingora_slice_warmed_slices_total counter\n");
+    output.push_str(&format!("pingora_slice_warmed_slices_total {}\n", snapshot.warmed_slices));
+    output.push_str("\n");
+
+    output.push_str("# HELP pingora_slice_warmed_bytes_total Bytes fetched and stored by the cache warmer\n");
+    output.push_str("# TYPE pingora_slice_warmed_bytes_total counter\n");
+    output.push_str(&format!("pingora_slice_warmed_bytes_total {}\n", snapshot.warmed_bytes));
+    output.push_str("\n");
+
+    // Origin rate limiting metrics
+    output.push_str("# HELP pingora_slice_origin_throttle_wait_seconds_total Time subrequests spent waiting on the origin rate limiter\n");
+    output.push_str("# TYPE pingora_slice_origin_throttle_wait_seconds_total counter\n");
+    output.push_str(&format!("pingora_slice_origin_throttle_wait_seconds_total {:.6}\n", snapshot.origin_throttle_wait_us as f64 / 1_000_000.0));
+    output.push_str("\n");
+
+    output.push_str("# HELP pingora_slice_origin_bandwidth_utilization Origin bandwidth cap utilization percentage\n");
+    output.push_str("# TYPE pingora_slice_origin_bandwidth_utilization gauge\n");
+    output.push_str(&format!("pingora_slice_origin_bandwidth_utilization {}\n", snapshot.origin_bandwidth_utilization));
+    output.push_str("\n");
+
+    output.push_str("# HELP pingora_slice_origin_request_utilization Origin request rate cap utilization percentage\n");
+    output.push_str("# TYPE pingora_slice_origin_request_utilization gauge\n");
+    output.push_str(&format!("pingora_slice_origin_request_utilization {}\n", snapshot.origin_request_utilization));
+    output.push_str("\n");
+
+    output.push_str("# HELP pingora_slice_effective_concurrency Subrequests currently allowed in flight by slow start\n");
+    output.push_str("# TYPE pingora_slice_effective_concurrency gauge\n");
+    output.push_str(&format!("pingora_slice_effective_concurrency {}\n", snapshot.effective_concurrency));
+    output.push_str("\n");
+
+    // Client disconnect metrics
+    output.push_str("# HELP pingora_slice_client_aborts_total Requests whose client disconnected before the response completed\n");
+    output.push_str("# TYPE pingora_slice_client_aborts_total counter\n");
+    output.push_str(&format!("pingora_slice_client_aborts_total {}\n", snapshot.client_aborts));
+    output.push_str("\n");
+
+    output.push_str("# HELP pingora_slice_background_fills_total Cache fills completed in the background after a client disconnect\n");
+    output.push_str("# TYPE pingora_slice_background_fills_total counter\n");
+    output.push_str(&format!("pingora_slice_background_fills_total {}\n", snapshot.background_fills));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_prefetched_slices_total Slices fetched and cached ahead of sequential range requests\n");
+    output.push_str("# TYPE pingora_slice_prefetched_slices_total counter\n");
+    output.push_str(&format!("pingora_slice_prefetched_slices_total {}\n", snapshot.prefetched_slices));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_deadline_aborts_total Requests cut short because their deadline passed\n");
+    output.push_str("# TYPE pingora_slice_deadline_aborts_total counter\n");
+    output.push_str(&format!("pingora_slice_deadline_aborts_total {}\n", snapshot.deadline_aborts));
+    output.push_str("\n");
+
+    output.push_str("# HELP pingora_slice_throttled_requests_total Requests rejected with 429 by the per-client rate limit\n");
+    output.push_str("# TYPE pingora_slice_throttled_requests_total counter\n");
+    output.push_str(&format!("pingora_slice_throttled_requests_total {}\n", snapshot.throttled_requests));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_fill_bypasses_total Cache misses proxied without caching because the fill limits were reached\n");
+    output.push_str("# TYPE pingora_slice_fill_bypasses_total counter\n");
+    output.push_str(&format!("pingora_slice_fill_bypasses_total {}\n", snapshot.fill_bypasses));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_fill_buffered_bytes Bytes reserved by the cache fills in flight\n");
+    output.push_str("# TYPE pingora_slice_fill_buffered_bytes gauge\n");
+    output.push_str(&format!("pingora_slice_fill_buffered_bytes {}\n", snapshot.fill_buffered_bytes));
+    output.push('\n');
+
+    output.push_str("# HELP pingora_slice_private_skips_total Responses not cached because they carried Set-Cookie or other per-user headers\n");
+    output.push_str("# TYPE pingora_slice_private_skips_total counter\n");
+    output.push_str(&format!("pingora_slice_private_skips_total {}\n", snapshot.private_skips));
+    output.push('\n');
+
+    // Cluster routing metrics
+    if !snapshot.cluster_routed.is_empty() {
+        output.push_str("# HELP pingora_slice_cluster_routed_requests_total Requests forwarded to the cluster peer owning the object\n");
+        output.push_str("# TYPE pingora_slice_cluster_routed_requests_total counter\n");
+        for (peer, count) in &snapshot.cluster_routed {
+            output.push_str(&format!("pingora_slice_cluster_routed_requests_total{{peer=\"{}\"}} {}\n", peer, count));
+        }
+        output.push_str("\n");
+    }
+
+    // Upstream pool metrics
+    if !snapshot.upstream_requests.is_empty() {
+        output.push_str("# HELP pingora_slice_upstream_requests_total Origin requests sent to each upstream\n");
+        output.push_str("# TYPE pingora_slice_upstream_requests_total counter\n");
+        for (upstream, count) in &snapshot.upstream_requests {
+            output.push_str(&format!("pingora_slice_upstream_requests_total{{upstream=\"{}\"}} {}\n", upstream, count));
+        }
+        output.push_str("\n");
+
+        output.push_str("# HELP pingora_slice_upstream_failures_total Origin requests to each upstream that failed with a retryable error\n");
+        output.push_str("# TYPE pingora_slice_upstream_failures_total counter\n");
+        for upstream in snapshot.upstream_requests.keys() {
+            let failures = snapshot.upstream_failures.get(upstream).copied().unwrap_or(0);
+            output.push_str(&format!("pingora_slice_upstream_failures_total{{upstream=\"{}\"}} {}\n", upstream, failures));
+        }
+        output.push_str("\n");
+    }
+
+    if !snapshot.upstream_failovers.is_empty() {
+        output.push_str("# HELP pingora_slice_upstream_failovers_total Retries sent to another upstream after a failure\n");
+        output.push_str("# TYPE pingora_slice_upstream_failovers_total counter\n");
+        for ((from, to), count) in &snapshot.upstream_failovers {
+            output.push_str(&format!("pingora_slice_upstream_failovers_total{{from=\"{}\",to=\"{}\"}} {}\n", from, to, count));
+        }
+        output.push('\n');
+    }
+
+    // Per-pattern metrics
+    if !snapshot.pattern_stats.is_empty() {
+        format_pattern_counter(
+            &mut output,
+            "pingora_slice_pattern_requests_total",
+            "Sliceable requests per matched URL pattern",
+            snapshot,
+            |stats| stats.requests,
+        );
+        format_pattern_counter(
+            &mut output,
+            "pingora_slice_pattern_cache_hits_total",
+            "Slice cache hits per matched URL pattern",
+            snapshot,
+            |stats| stats.cache_hits,
+        );
+        format_pattern_counter(
+            &mut output,
+            "pingora_slice_pattern_cache_misses_total",
+            "Slice cache misses per matched URL pattern",
+            snapshot,
+            |stats| stats.cache_misses,
+        );
+        format_pattern_counter(
+            &mut output,
+            "pingora_slice_pattern_bytes_to_client_total",
+            "Bytes sent to clients per matched URL pattern",
+            snapshot,
+            |stats| stats.bytes_to_client,
+        );
+    }
+
     // Latency metrics (in milliseconds)
     output.push_str("# HELP pingora_slice_request_duration_ms_avg Average request duration in milliseconds\n");
     output.push_str("# TYPE pingora_slice_request_duration_ms_avg gauge\n");
@@ -223,9 +609,94 @@ fn format_prometheus_metrics(snapshot: &MetricsSnapshot) -> String {
     output.push_str(&format!("pingora_slice_assembly_duration_ms_avg {:.2}\n", snapshot.avg_assembly_duration_ms()));
     output.push_str("\n");
 
+    // Latency histograms (in seconds)
+    format_histogram(
+        &mut output,
+        "pingora_slice_request_duration_seconds",
+        "Sliced request duration in seconds",
+        &snapshot.request_latency,
+    );
+    format_histogram(
+        &mut output,
+        "pingora_slice_subrequest_duration_seconds",
+        "Per-slice subrequest duration in seconds",
+        &snapshot.subrequest_latency,
+    );
+    format_histogram(
+        &mut output,
+        "pingora_slice_assembly_duration_seconds",
+        "Response assembly duration in seconds",
+        &snapshot.assembly_latency,
+    );
+    format_histogram(
+        &mut output,
+        "pingora_slice_ttfb_seconds",
+        "Time to first byte of sliced responses in seconds",
+        &snapshot.ttfb_latency,
+    );
+    format_histogram(
+        &mut output,
+        "pingora_slice_first_slice_seconds",
+        "Time from dispatching a request's slice fetches until its first slice arrived",
+        &snapshot.first_slice_latency,
+    );
+
     output
 }
 
+/// Append a latency histogram in Prometheus histogram format
+///
+/// Bucket counts are emitted cumulatively with `le` bounds in seconds, so the
+/// series work with `histogram_quantile` and Grafana heatmaps.
+fn format_histogram(output: &mut String, name: &str, help: &str, histogram: &HistogramSnapshot) {
+    output.push_str(&format!("# HELP {} {}\n", name, help));
+    output.push_str(&format!("# TYPE {} histogram\n", name));
+
+    let mut cumulative = 0;
+    for (index, bound_ms) in LATENCY_BUCKETS_MS.iter().enumerate() {
+        cumulative += histogram.buckets.get(index).copied().unwrap_or(0);
+        output.push_str(&format!(
+            "{}_bucket{{le=\"{}\"}} {}\n",
+            name,
+            *bound_ms as f64 / 1000.0,
+            cumulative
+        ));
+    }
+    output.push_str(&format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, histogram.count));
+    output.push_str(&format!("{}_sum {}\n", name, histogram.sum_us as f64 / 1_000_000.0));
+    output.push_str(&format!("{}_count {}\n", name, histogram.count));
+    output.push('\n');
+}
+
+/// Append a counter labeled by URL pattern in Prometheus text format
+fn format_pattern_counter(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    snapshot: &MetricsSnapshot,
+    value: impl Fn(&PatternStats) -> u64,
+) {
+    output.push_str(&format!("# HELP {} {}\n", name, help));
+    output.push_str(&format!("# TYPE {} counter\n", name));
+    for (pattern, stats) in &snapshot.pattern_stats {
+        output.push_str(&format!(
+            "{}{{pattern=\"{}\"}} {}\n",
+            name,
+            escape_label_value(pattern),
+            value(stats)
+        ));
+    }
+    output.push('\n');
+}
+
+/// Escape a Prometheus label value
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 /// Generate health check response
 fn health_response() -> Response<Full<Bytes>> {
     Response::builder()
@@ -235,8 +706,40 @@ fn health_response() -> Response<Full<Bytes>> {
         .unwrap()
 }
 
-/// Generate index page response
-fn index_response() -> Response<Full<Bytes>> {
+/// Generate the readiness response: 200 if every component is healthy,
+/// 503 otherwise, with per-component detail
+async fn readiness_response(health: &HealthChecker) -> Response<Full<Bytes>> {
+    let report = health.check().await;
+    let status = if report.is_healthy() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(report.to_json())))
+        .unwrap()
+}
+
+/// Generate the liveness response: 200 unless the process is wedged
+fn liveness_response(health: Option<&HealthChecker>) -> Response<Full<Bytes>> {
+    let (status, body) = if health.is_none_or(HealthChecker::is_live) {
+        (StatusCode::OK, r#"{"status":"alive"}"#)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, r#"{"status":"wedged"}"#)
+    };
+
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+/// Generate index page response, linking the metrics at `metrics_path`
+fn index_response(metrics_path: &str) -> Response<Full<Bytes>> {
     let body = r#"<!DOCTYPE html>
 <html>
 <head>
@@ -253,13 +756,17 @@ fn index_response() -> Response<Full<Bytes>> {
     <h1>Pingora Slice Metrics Endpoint</h1>
     <p>Available endpoints:</p>
     <div class="endpoint">
-        <strong><a href="/metrics">/metrics</a></strong> - Prometheus format metrics
+        <strong><a href="{metrics_path}">{metrics_path}</a></strong> - Prometheus format metrics
     </div>
     <div class="endpoint">
         <strong><a href="/health">/health</a></strong> - Health check endpoint
     </div>
+    <div class="endpoint">
+        <strong><a href="/health/live">/health/live</a></strong> - Liveness check endpoint
+    </div>
 </body>
-</html>"#;
+</html>"#
+        .replace("{metrics_path}", &escape_html(metrics_path));
 
     Response::builder()
         .status(StatusCode::OK)
@@ -268,6 +775,25 @@ fn index_response() -> Response<Full<Bytes>> {
         .unwrap()
 }
 
+/// Escape text for inclusion in HTML
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Generate 401 response asking for basic auth
+fn unauthorized_response() -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("WWW-Authenticate", "Basic realm=\"metrics\"")
+        .header("Content-Type", "text/plain")
+        .body(Full::new(Bytes::from("401 Unauthorized")))
+        .unwrap()
+}
+
 /// Generate 404 response
 fn not_found_response() -> Response<Full<Bytes>> {
     Response::builder()
@@ -291,11 +817,17 @@ mod tests {
         metrics.record_request(false);
         metrics.record_cache_hit();
         metrics.record_cache_miss();
+        metrics.record_slice_hit_mix(1, 1);
         metrics.record_subrequest(true);
         metrics.record_subrequest(false);
+        metrics.record_origin_connection();
+        metrics.record_subrequest_version(http::Version::HTTP_2);
         metrics.record_bytes_from_origin(1000);
         metrics.record_bytes_from_cache(500);
         metrics.record_bytes_to_client(1500);
+        metrics.record_deadline_abort();
+        metrics.record_expiry_sweep(3);
+        metrics.record_expiry_sweep(2);
 
         let snapshot = metrics.get_stats();
         let output = format_prometheus_metrics(&snapshot);
@@ -306,11 +838,21 @@ mod tests {
         assert!(output.contains("pingora_slice_passthrough_requests_total 1"));
         assert!(output.contains("pingora_slice_cache_hits_total 1"));
         assert!(output.contains("pingora_slice_cache_misses_total 1"));
+        assert!(output.contains("pingora_slice_partial_hit_requests_total 1"));
+        assert!(output.contains("pingora_slice_full_hit_requests_total 0"));
         assert!(output.contains("pingora_slice_subrequests_total 2"));
         assert!(output.contains("pingora_slice_failed_subrequests_total 1"));
+        assert!(output.contains("pingora_slice_origin_connections_total 1"));
+        assert!(output.contains("pingora_slice_origin_connection_reuse_rate 50.00"));
+        assert!(output.contains("pingora_slice_subrequests_by_protocol_total{protocol=\"http1\"} 0"));
+        assert!(output.contains("pingora_slice_subrequests_by_protocol_total{protocol=\"http2\"} 1"));
         assert!(output.contains("pingora_slice_bytes_from_origin_total 1000"));
         assert!(output.contains("pingora_slice_bytes_from_cache_total 500"));
         assert!(output.contains("pingora_slice_bytes_to_client_total 1500"));
+        assert!(output.contains("pingora_slice_deadline_aborts_total 1"));
+        assert!(output.contains("pingora_slice_expiry_sweeps_total 2"));
+        assert!(output.contains("pingora_slice_expired_entries_swept_total 5"));
+        assert!(output.contains("pingora_slice_last_sweep_entries 2"));
         
         // Verify HELP and TYPE comments are present
         assert!(output.contains("# HELP pingora_slice_requests_total"));
@@ -319,6 +861,26 @@ mod tests {
         assert!(output.contains("# TYPE pingora_slice_cache_hit_rate gauge"));
     }
 
+    #[test]
+    fn test_format_pattern_metrics() {
+        let metrics = SliceMetrics::new();
+        assert!(!format_prometheus_metrics(&metrics.get_stats()).contains("pattern="));
+
+        metrics.record_pattern_request("live");
+        metrics.record_pattern_cache("live", 1, 3);
+        metrics.record_pattern_bytes_to_client("live", 4096);
+        metrics.record_pattern_request("*.mp4");
+
+        let output = format_prometheus_metrics(&metrics.get_stats());
+        assert!(output.contains("# TYPE pingora_slice_pattern_requests_total counter"));
+        assert!(output.contains("pingora_slice_pattern_requests_total{pattern=\"live\"} 1"));
+        assert!(output.contains("pingora_slice_pattern_requests_total{pattern=\"*.mp4\"} 1"));
+        assert!(output.contains("pingora_slice_pattern_cache_hits_total{pattern=\"live\"} 1"));
+        assert!(output.contains("pingora_slice_pattern_cache_misses_total{pattern=\"live\"} 3"));
+        assert!(output.contains("pingora_slice_pattern_bytes_to_client_total{pattern=\"live\"} 4096"));
+        assert!(output.contains("pingora_slice_pattern_bytes_to_client_total{pattern=\"*.mp4\"} 0"));
+    }
+
     #[test]
     fn test_format_prometheus_metrics_empty() {
         let metrics = SliceMetrics::new();
@@ -347,6 +909,27 @@ mod tests {
 
         // Cache hit rate should be 75%
         assert!(output.contains("pingora_slice_cache_hit_rate 75.00"));
+        assert!(output.contains("pingora_slice_cache_hit_ratio 0.7500"));
+        assert!(output.contains("pingora_slice_byte_hit_ratio 0.0000"));
+    }
+
+    #[test]
+    fn test_format_prometheus_histograms() {
+        let metrics = SliceMetrics::new();
+        metrics.record_request_duration(std::time::Duration::from_millis(3));
+        metrics.record_request_duration(std::time::Duration::from_millis(700));
+
+        let output = format_prometheus_metrics(&metrics.get_stats());
+
+        assert!(output.contains("# TYPE pingora_slice_request_duration_seconds histogram"));
+        assert!(output.contains("pingora_slice_request_duration_seconds_bucket{le=\"0.001\"} 0"));
+        assert!(output.contains("pingora_slice_request_duration_seconds_bucket{le=\"0.005\"} 1"));
+        assert!(output.contains("pingora_slice_request_duration_seconds_bucket{le=\"0.5\"} 1"));
+        assert!(output.contains("pingora_slice_request_duration_seconds_bucket{le=\"1\"} 2"));
+        assert!(output.contains("pingora_slice_request_duration_seconds_bucket{le=\"+Inf\"} 2"));
+        assert!(output.contains("pingora_slice_request_duration_seconds_sum 0.703"));
+        assert!(output.contains("pingora_slice_request_duration_seconds_count 2"));
+        assert!(output.contains("# TYPE pingora_slice_ttfb_seconds histogram"));
     }
 
     #[test]
@@ -358,15 +941,84 @@ mod tests {
         assert_eq!(content_type, "application/json");
     }
 
+    #[tokio::test]
+    async fn test_readiness_response_reports_components() {
+        let config = crate::config::SliceConfig {
+            upstream_address: "127.0.0.1:1".to_string(),
+            ..Default::default()
+        };
+        let health = HealthChecker::new(&config, Arc::new(SliceMetrics::new()));
+        health.probe_upstream().await;
+
+        let response = readiness_response(&health).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = http_body_util::BodyExt::collect(response.into_body()).await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["status"], "unhealthy");
+        assert_eq!(json["components"]["upstream"]["healthy"], false);
+        assert_eq!(json["components"]["subrequest_errors"]["healthy"], true);
+
+        assert_eq!(liveness_response(Some(&health)).status(), StatusCode::OK);
+        assert_eq!(liveness_response(None).status(), StatusCode::OK);
+    }
+
     #[test]
     fn test_index_response() {
-        let response = index_response();
+        let response = index_response("/internal/metrics");
         assert_eq!(response.status(), StatusCode::OK);
         
         let content_type = response.headers().get("Content-Type").unwrap();
         assert_eq!(content_type, "text/html; charset=utf-8");
     }
 
+    #[test]
+    fn test_format_process_metrics() {
+        let mut output = String::new();
+        format_process_metrics(
+            &mut output,
+            &ProcessStats { resident_memory_bytes: Some(4096), open_fds: Some(12), uptime_secs: 1.5 },
+        );
+        assert!(output.contains("process_resident_memory_bytes 4096\n"));
+        assert!(output.contains("process_open_fds 12\n"));
+        assert!(output.contains("pingora_slice_uptime_seconds 1.500\n"));
+
+        let mut output = String::new();
+        format_process_metrics(
+            &mut output,
+            &ProcessStats { resident_memory_bytes: None, open_fds: None, uptime_secs: 0.0 },
+        );
+        assert!(!output.contains("process_"));
+    }
+
+    #[test]
+    fn test_accepts_gzip() {
+        let accepts = |value: &str| {
+            accepts_gzip(&Request::builder().header(ACCEPT_ENCODING, value).body(()).unwrap())
+        };
+        assert!(accepts("gzip"));
+        assert!(accepts("deflate, GZIP;q=0.5"));
+        assert!(accepts("*"));
+        assert!(!accepts("br, deflate"));
+        assert!(!accepts("gzip;q=0"));
+        assert!(!accepts_gzip(&Request::builder().body(()).unwrap()));
+    }
+
+    #[test]
+    fn test_gzip_encode_round_trip() {
+        let body = format_prometheus_metrics(&SliceMetrics::new().get_stats());
+        for data in [body.as_bytes(), b"", b"x"] {
+            let compressed = gzip_encode(data);
+            assert_eq!(compressed[..3], [0x1f, 0x8b, 8]);
+            let (member, trailer) = compressed[10..].split_at(compressed.len() - 18);
+            let decoded = miniz_oxide::inflate::decompress_to_vec(member).unwrap();
+            assert_eq!(decoded, data);
+            assert_eq!(trailer[..4], crc32fast::hash(data).to_le_bytes());
+            assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes());
+        }
+        assert!(gzip_encode(body.as_bytes()).len() < body.len() / 3);
+    }
+
     #[test]
     fn test_not_found_response() {
         let response = not_found_response();
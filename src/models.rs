@@ -127,6 +127,8 @@ pub struct FileMetadata {
     pub etag: Option<String>,
     /// Last modified timestamp
     pub last_modified: Option<String>,
+    /// Lowercased request header names listed in the origin's Vary header
+    pub vary: Vec<String>,
 }
 
 impl FileMetadata {
@@ -138,6 +140,7 @@ impl FileMetadata {
             content_type: None,
             etag: None,
             last_modified: None,
+            vary: Vec::new(),
         }
     }
 
@@ -155,10 +158,269 @@ impl FileMetadata {
             content_type,
             etag,
             last_modified,
+            vary: Vec::new(),
+        }
+    }
+
+    /// Set the request headers the origin varies its response on
+    pub fn with_vary(mut self, vary: Vec<String>) -> Self {
+        self.vary = vary;
+        self
+    }
+
+    /// Check whether an `If-Range` validator matches this file
+    ///
+    /// An entity tag must match the stored ETag using strong comparison, so
+    /// weak tags (`W/"..."`) never match. Any other value is treated as an
+    /// HTTP-date and must equal the stored Last-Modified exactly.
+    pub fn matches_if_range(&self, if_range: &str) -> bool {
+        let if_range = if_range.trim();
+
+        if if_range.starts_with('"') || if_range.starts_with("W/") {
+            match &self.etag {
+                Some(etag) => !if_range.starts_with("W/") && !etag.starts_with("W/") && etag == if_range,
+                None => false,
+            }
+        } else {
+            self.last_modified.as_deref() == Some(if_range)
+        }
+    }
+
+    /// Check whether an `If-None-Match` header matches this file
+    ///
+    /// Uses weak comparison, so `W/"v1"` matches `"v1"`. `*` matches any
+    /// existing file.
+    pub fn matches_if_none_match(&self, if_none_match: &str) -> bool {
+        if if_none_match.trim() == "*" {
+            return true;
+        }
+        let Some(etag) = &self.etag else {
+            return false;
+        };
+        let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+        let etag = opaque(etag);
+        if_none_match.split(',').any(|tag| opaque(tag) == etag)
+    }
+
+    /// Check whether the file is unchanged since an `If-Modified-Since` date
+    ///
+    /// # Returns
+    /// `false` if either date is missing or not a valid HTTP-date
+    pub fn unmodified_since(&self, if_modified_since: &str) -> bool {
+        let last_modified = self.last_modified.as_deref().and_then(parse_http_date);
+        match (last_modified, parse_http_date(if_modified_since)) {
+            (Some(last_modified), Some(since)) => last_modified <= since,
+            _ => false,
+        }
+    }
+
+    /// Evaluate a request's `If-None-Match` and `If-Modified-Since` headers
+    ///
+    /// `If-Modified-Since` is only considered when there is no
+    /// `If-None-Match`, as required by RFC 7232.
+    ///
+    /// # Returns
+    /// `true` if the client's copy is current and a 304 should be sent
+    pub fn is_not_modified(
+        &self,
+        if_none_match: Option<&str>,
+        if_modified_since: Option<&str>,
+    ) -> bool {
+        match (if_none_match, if_modified_since) {
+            (Some(if_none_match), _) => self.matches_if_none_match(if_none_match),
+            (None, Some(since)) => self.unmodified_since(since),
+            (None, None) => false,
         }
     }
 }
 
+/// Cache key of an object, one of its variants, or one of its slices
+///
+/// Every cache key is built through this type so the slice cache, the
+/// tiered cache, the metadata cache and purges all agree on the format:
+///
+/// * object: `{url}` or `{url}|vary:{name}={value}|vary:...`
+/// * slice: `{object}:slice:{start}:{end}`
+///
+/// `|` in the URL and variant values is escaped as `%7C`, and `:slice:` as
+/// `%3Aslice:`, so every rendering parses back unambiguously.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SliceKey {
+    url: String,
+    #[serde(default)]
+    variant: Vec<(String, String)>,
+    #[serde(default)]
+    range: Option<ByteRange>,
+}
+
+impl SliceKey {
+    /// Version of the key format, recorded next to persisted entries
+    ///
+    /// Version 1 was `{url}:{start}:{end}`, before keys carried variants.
+    pub const FORMAT_VERSION: u32 = 2;
+
+    const VARY: &'static str = "|vary:";
+    const SLICE: &'static str = ":slice:";
+
+    /// Key for the whole object at `url`
+    ///
+    /// `url` should already be normalized, e.g. by
+    /// [`CacheKeyBuilder`](crate::CacheKeyBuilder).
+    pub fn new(url: &str) -> Self {
+        SliceKey {
+            url: Self::escape(url),
+            variant: Vec::new(),
+            range: None,
+        }
+    }
+
+    /// Key for an object key rendered by [`object_key`](Self::object_key),
+    /// or for a plain URL that is not one
+    pub fn from_object_key(key: &str) -> Self {
+        match Self::parse(key) {
+            Ok(parsed) if parsed.range.is_none() => parsed,
+            _ => Self::new(key),
+        }
+    }
+
+    /// Select a variant by the value of a request header the origin varies on
+    pub fn with_variant(mut self, name: &str, value: &str) -> Self {
+        self.variant
+            .push((Self::escape(name).replace('=', "%3D"), Self::escape(value)));
+        self
+    }
+
+    /// Narrow the key to one slice of the object
+    pub fn with_range(mut self, range: ByteRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Normalized URL, with delimiters escaped
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Header name/value pairs selecting the variant, in key order
+    pub fn variant(&self) -> &[(String, String)] {
+        &self.variant
+    }
+
+    /// Byte range of the slice, if the key names one
+    pub fn range(&self) -> Option<ByteRange> {
+        self.range
+    }
+
+    /// Whether `other` names the same URL, whatever its variant or range
+    pub fn same_url(&self, other: &SliceKey) -> bool {
+        self.url == other.url
+    }
+
+    /// Key of the object (or variant) without the range
+    pub fn object_key(&self) -> String {
+        let mut key = self.url.clone();
+        for (name, value) in &self.variant {
+            key.push_str(Self::VARY);
+            key.push_str(name);
+            key.push('=');
+            key.push_str(value);
+        }
+        key
+    }
+
+    /// Key the object's file metadata is cached under
+    pub fn metadata_key(&self) -> String {
+        self.object_key()
+    }
+
+    /// Key of the slice, or the object key if no range is set
+    pub fn slice_key(&self) -> String {
+        match self.range {
+            Some(range) => format!("{}{}{}:{}", self.object_key(), Self::SLICE, range.start, range.end),
+            None => self.object_key(),
+        }
+    }
+
+    /// Parse a key rendered by [`object_key`](Self::object_key) or
+    /// [`slice_key`](Self::slice_key)
+    ///
+    /// # Returns
+    /// `Err(SliceError::ParseError)` if the key has a malformed slice range
+    /// or variant
+    pub fn parse(key: &str) -> Result<Self> {
+        let (object, range) = match key.rsplit_once(Self::SLICE) {
+            Some((object, range)) => {
+                let bounds = range.split_once(':').and_then(|(start, end)| {
+                    Some((start.parse::<u64>().ok()?, end.parse::<u64>().ok()?))
+                });
+                let (start, end) = bounds.ok_or_else(|| {
+                    SliceError::ParseError(format!("invalid slice range in cache key: {}", key))
+                })?;
+                (object, Some(ByteRange::new(start, end)?))
+            }
+            None => (key, None),
+        };
+
+        let mut parts = object.split(Self::VARY);
+        let url = parts.next().unwrap_or_default().to_string();
+        let variant = parts
+            .map(|part| {
+                part.split_once('=')
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .ok_or_else(|| {
+                        SliceError::ParseError(format!("invalid variant in cache key: {}", key))
+                    })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(SliceKey { url, variant, range })
+    }
+
+    fn escape(value: &str) -> String {
+        let mut escaped = value.replace('|', "%7C");
+        // Escaping can expose another match, as in `:slice:slice:`
+        while escaped.contains(Self::SLICE) {
+            escaped = escaped.replace(Self::SLICE, "%3Aslice:");
+        }
+        escaped
+    }
+}
+
+impl std::fmt::Display for SliceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.slice_key())
+    }
+}
+
+/// Parse an IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) into seconds
+/// since the Unix epoch
+fn parse_http_date(value: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let mut parts = value.split_whitespace();
+    let _weekday = parts.next()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month_name = parts.next()?;
+    let month = MONTHS.iter().position(|m| *m == month_name)? as i64 + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':').map(|t| t.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" {
+        return None;
+    }
+
+    // Days since the epoch for a proleptic Gregorian date
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let days = era * 146_097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719_468;
+
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,6 +462,80 @@ mod tests {
         assert!(!spec.cached);
     }
 
+    #[test]
+    fn test_matches_if_range_etag() {
+        let metadata = FileMetadata::with_headers(
+            1024,
+            true,
+            None,
+            Some("\"v1\"".to_string()),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        );
+        assert!(metadata.matches_if_range("\"v1\""));
+        assert!(!metadata.matches_if_range("\"v2\""));
+        // Weak validators never match
+        assert!(!metadata.matches_if_range("W/\"v1\""));
+    }
+
+    #[test]
+    fn test_matches_if_range_date() {
+        let metadata = FileMetadata::with_headers(
+            1024,
+            true,
+            None,
+            None,
+            Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        );
+        assert!(metadata.matches_if_range("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert!(!metadata.matches_if_range("Thu, 22 Oct 2015 07:28:00 GMT"));
+        assert!(!metadata.matches_if_range("\"v1\""));
+        assert!(!FileMetadata::new(1024, true).matches_if_range("Wed, 21 Oct 2015 07:28:00 GMT"));
+    }
+
+    #[test]
+    fn test_matches_if_none_match() {
+        let metadata = FileMetadata::with_headers(1024, true, None, Some("\"v1\"".to_string()), None);
+        assert!(metadata.matches_if_none_match("\"v1\""));
+        assert!(metadata.matches_if_none_match("\"v0\", \"v1\""));
+        assert!(!metadata.matches_if_none_match("\"v2\""));
+        // Weak comparison ignores the W/ prefix on either side
+        assert!(metadata.matches_if_none_match("W/\"v1\""));
+        assert!(metadata.matches_if_none_match("*"));
+        assert!(!FileMetadata::new(1024, true).matches_if_none_match("\"v1\""));
+    }
+
+    #[test]
+    fn test_unmodified_since() {
+        let metadata = FileMetadata::with_headers(
+            1024,
+            true,
+            None,
+            None,
+            Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        );
+        assert!(metadata.unmodified_since("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert!(metadata.unmodified_since("Thu, 22 Oct 2015 00:00:00 GMT"));
+        assert!(!metadata.unmodified_since("Wed, 21 Oct 2015 07:27:59 GMT"));
+        assert!(!metadata.unmodified_since("not a date"));
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784_111_777));
+    }
+
+    #[test]
+    fn test_if_none_match_takes_precedence() {
+        let metadata = FileMetadata::with_headers(
+            1024,
+            true,
+            None,
+            Some("\"v1\"".to_string()),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        );
+        let since = Some("Wed, 21 Oct 2015 07:28:00 GMT");
+        assert!(metadata.is_not_modified(None, since));
+        assert!(!metadata.is_not_modified(Some("\"v2\""), since));
+        assert!(!metadata.is_not_modified(None, None));
+    }
+
     #[test]
     fn test_file_metadata_new() {
         let metadata = FileMetadata::new(1024000, true);
@@ -207,4 +543,98 @@ mod tests {
         assert!(metadata.supports_range);
         assert!(metadata.content_type.is_none());
     }
+
+    #[test]
+    fn test_slice_key_renderings() {
+        let range = ByteRange::new(0, 1023).unwrap();
+        let key = SliceKey::new("http://h/a.mp4").with_range(range);
+        assert_eq!(key.object_key(), "http://h/a.mp4");
+        assert_eq!(key.metadata_key(), "http://h/a.mp4");
+        assert_eq!(key.slice_key(), "http://h/a.mp4:slice:0:1023");
+        assert_eq!(key.to_string(), key.slice_key());
+
+        let variant = SliceKey::new("http://h/a.mp4")
+            .with_variant("accept-encoding", "gzip")
+            .with_variant("accept-language", "");
+        assert_eq!(
+            variant.object_key(),
+            "http://h/a.mp4|vary:accept-encoding=gzip|vary:accept-language="
+        );
+        assert_eq!(variant.slice_key(), variant.object_key());
+        assert_eq!(
+            variant.with_range(range).slice_key(),
+            "http://h/a.mp4|vary:accept-encoding=gzip|vary:accept-language=:slice:0:1023"
+        );
+    }
+
+    #[test]
+    fn test_slice_key_parse_round_trip() {
+        let range = ByteRange::new(5, 9).unwrap();
+        let keys = [
+            SliceKey::new("http://h/a.mp4"),
+            SliceKey::new("http://h/a.mp4").with_range(range),
+            SliceKey::new("http://h:8080/a.mp4?x=1&y=2").with_variant("accept-encoding", "br"),
+            SliceKey::new("/a.mp4")
+                .with_variant("accept-encoding", "gzip")
+                .with_variant("x-device", "tv")
+                .with_range(range),
+        ];
+        for key in keys {
+            assert_eq!(SliceKey::parse(&key.object_key()).unwrap(), SliceKey { range: None, ..key.clone() });
+            assert_eq!(SliceKey::parse(&key.slice_key()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_slice_key_escapes_delimiters() {
+        let range = ByteRange::new(0, 1).unwrap();
+        let urls = [
+            "http://h/a|vary:x=1",
+            "http://h/a:slice:0:1",
+            "http://h/a:slice:slice:2:3",
+            "http://h/a:slice",
+            "http://h/a=b:c|d",
+            "http://h/%7C",
+        ];
+        for url in urls {
+            let key = SliceKey::new(url).with_variant("v", "x|vary:y:slice:1:2").with_range(range);
+            let parsed = SliceKey::parse(&key.slice_key()).unwrap();
+            assert_eq!(parsed, key);
+            assert_eq!(parsed.range(), Some(range));
+            assert_eq!(parsed.variant().len(), 1);
+            assert!(!parsed.url().contains('|'));
+
+            let object = SliceKey::parse(&SliceKey::new(url).object_key()).unwrap();
+            assert_eq!(object.range(), None);
+            assert!(object.variant().is_empty());
+        }
+        assert_eq!(SliceKey::new("http://h/a|b").url(), "http://h/a%7Cb");
+        assert_eq!(SliceKey::new("http://h/a:slice:1").url(), "http://h/a%3Aslice:1");
+    }
+
+    #[test]
+    fn test_slice_key_parse_errors() {
+        assert!(SliceKey::parse("http://h/a:slice:x:1").is_err());
+        assert!(SliceKey::parse("http://h/a:slice:9:1").is_err());
+        assert!(SliceKey::parse("http://h/a:slice:1").is_err());
+        assert!(SliceKey::parse("http://h/a|vary:novalue").is_err());
+    }
+
+    #[test]
+    fn test_slice_key_from_object_key() {
+        let variant = SliceKey::new("http://h/a").with_variant("accept-encoding", "gzip");
+        assert_eq!(SliceKey::from_object_key(&variant.object_key()), variant);
+        // Not an object key: treated as a plain URL
+        assert_eq!(SliceKey::from_object_key("http://h/a:slice:0:1"), SliceKey::new("http://h/a:slice:0:1"));
+        assert!(SliceKey::from_object_key("http://h/a").same_url(&variant));
+    }
+
+    #[test]
+    fn test_slice_key_serialize() {
+        let key = SliceKey::new("http://h/a")
+            .with_variant("accept-encoding", "gzip")
+            .with_range(ByteRange::new(0, 1).unwrap());
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(serde_json::from_str::<SliceKey>(&json).unwrap(), key);
+    }
 }
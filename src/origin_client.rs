@@ -0,0 +1,83 @@
+//! Connection pool for slice subrequests
+//!
+//! One client is shared by every subrequest a proxy sends, so connections
+//! to an origin host stay open and are reused across slices and requests.
+//! With `origin_protocol: auto`, a TLS origin offering HTTP/2 is spoken to
+//! over one multiplexed connection and everything else over pooled
+//! HTTP/1.1 keep-alive connections. Plain-HTTP origins only get HTTP/2
+//! with `origin_protocol: http2`, as there is no negotiation without TLS.
+
+use crate::config::{OriginProtocol, SliceConfig};
+use crate::error::{Result, SliceError};
+use crate::metrics::SliceMetrics;
+use reqwest::Client;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+
+/// Build the client slice subrequests are sent with
+///
+/// # Arguments
+/// * `config` - Supplies `origin_protocol` and `origin_pool_size`
+/// * `metrics` - Counts the connections the client opens (optional)
+pub fn origin_client(config: &SliceConfig, metrics: Option<Arc<SliceMetrics>>) -> Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .pool_max_idle_per_host(config.origin_pool_size)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_nodelay(true)
+        .http2_adaptive_window(true);
+    builder = match config.origin_protocol {
+        OriginProtocol::Auto => builder,
+        OriginProtocol::Http1 => builder.http1_only(),
+        OriginProtocol::Http2 => builder.http2_prior_knowledge(),
+    };
+    if let Some(metrics) = metrics {
+        builder = builder.connector_layer(CountConnections { metrics });
+    }
+    builder
+        .build()
+        .map_err(|e| SliceError::ConfigError(format!("Failed to create origin HTTP client: {}", e)))
+}
+
+/// Records every connection the client opens
+#[derive(Clone)]
+struct CountConnections {
+    metrics: Arc<SliceMetrics>,
+}
+
+impl<S> Layer<S> for CountConnections {
+    type Service = CountedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountedConnector {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CountedConnector<S> {
+    inner: S,
+    metrics: Arc<SliceMetrics>,
+}
+
+impl<S, R> Service<R> for CountedConnector<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.metrics.record_origin_connection();
+        self.inner.call(request)
+    }
+}
@@ -5,14 +5,41 @@
 
 use crate::{
     SliceConfig, SliceMetrics, FileMetadata, SliceSpec, ByteRange,
-    RequestAnalyzer, MetadataFetcher, SliceCalculator, SliceCache,
+    RequestAnalyzer, MetadataFetcher, MetadataCache, SliceCalculator, TieredCache, CacheVariant, SliceKey,
 };
+use crate::access_log::{rfc3339_now, AccessLogger, AccessRecord, CacheStatus};
+use crate::cache_admission::{Admission, CachePolicy};
+use crate::cache_key::CacheKeyBuilder;
+use crate::header_rules::{ForwardedHeaders, HeaderRewriter};
+use crate::error_pages::ErrorPages;
+use crate::fill_limiter::{FillGuard, FillLimiter};
+use crate::origin_client::origin_client;
+use crate::origin_signing::RequestSigner;
+use crate::purge_handler::headers_have_token;
+use crate::client_pacer::ClientPacer;
+use crate::cluster::{ClusterRouter, CLUSTER_HOP_HEADER};
+use crate::config::{CacheMode, ClientAbortPolicy, HostHeaderMode};
 use crate::error::{Result, SliceError};
+use crate::rate_limiter::{ClientRateLimiter, OriginRateLimiter};
+use crate::slow_start::ConcurrencyRamp;
+use crate::subrequest_manager::{AbortSignal, InterruptedFetch, SubrequestManager, SubrequestResult};
+use crate::upstream::{rewrite_authority, UpstreamPool};
 use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use http::{Method, HeaderMap, HeaderValue};
-use tracing::{debug, info, warn};
+use tokio::sync::{mpsc, Semaphore};
+use tracing::{debug, info, warn, Instrument};
+
+/// Why a background fill was started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BackgroundFill {
+    /// Slices the disconnected client no longer waits for
+    ClientAbort,
+    /// Slices after a sequential download's current range
+    Prefetch,
+}
 
 /// Main proxy structure that integrates all slice module components
 ///
@@ -22,6 +49,9 @@ use tracing::{debug, info, warn};
 /// # Fields
 /// * `config` - Shared configuration for the slice module
 /// * `metrics` - Thread-safe metrics collector
+/// * `metadata_cache` - File metadata shared across requests
+/// * `cache` - Slice cache shared across requests
+/// * `access_logger` - Optional structured access log
 ///
 /// # Requirements
 /// Validates: All requirements (1.1-10.5)
@@ -32,6 +62,66 @@ pub struct SliceProxy {
     
     /// Metrics collector for monitoring
     metrics: Arc<SliceMetrics>,
+
+    /// Cached file metadata, shared across requests
+    metadata_cache: Arc<MetadataCache>,
+
+    /// Slice cache, shared across requests
+    cache: Arc<TieredCache>,
+
+    /// Access log written on request completion
+    access_logger: Option<Arc<AccessLogger>>,
+
+    /// Origin rate limiter shared by every subrequest (if configured)
+    origin_limiter: Option<Arc<OriginRateLimiter>>,
+
+    /// Subrequest concurrency slow-start shared by every request (if enabled)
+    concurrency_ramp: Option<Arc<ConcurrencyRamp>>,
+
+    /// Permits for cache fills continued after a client disconnect
+    background_fills: Arc<Semaphore>,
+
+    /// Consistent-hash routing to other nodes in cluster mode (optional)
+    cluster: Option<ClusterRouter>,
+
+    /// Origins requests are spread over (if `upstream_pool` is configured)
+    upstreams: Option<Arc<UpstreamPool>>,
+
+    /// Normalizes request URLs into cache keys
+    cache_keys: CacheKeyBuilder,
+
+    /// Applies `header_rules` to origin requests and client responses
+    header_rewriter: HeaderRewriter,
+
+    /// Builds the `forwarded_headers` of each request
+    forwarded: ForwardedHeaders,
+    
+    /// Decides which origin responses are cached
+    cache_policy: Arc<CachePolicy>,
+
+    /// Bodies of `error_pages`, loaded at startup
+    error_pages: ErrorPages,
+
+    /// Per-client-IP limits (if `client_rate_limit` is configured)
+    client_limiter: Option<Arc<ClientRateLimiter>>,
+
+    /// Caps on cache fills in flight and the bytes they buffer
+    fill_limiter: Arc<FillLimiter>,
+
+    /// Connection pool shared by every slice subrequest
+    origin_client: reqwest::Client,
+
+    /// Signs metadata probes and slice subrequests (optional)
+    signer: Option<Arc<RequestSigner>>,
+
+    /// Matches requests against the configured patterns, compiled once
+    analyzer: RequestAnalyzer,
+
+    /// Splits files into `slice_size` slices
+    calculator: SliceCalculator,
+
+    /// Probe settings and connection pool of metadata requests
+    metadata_fetcher: MetadataFetcher,
 }
 
 /// Per-request context for slice processing
@@ -45,6 +135,32 @@ pub struct SliceProxy {
 /// * `metadata` - File metadata from the origin server (if fetched)
 /// * `client_range` - Client's requested byte range (if any)
 /// * `slices` - Calculated slice specifications for this request
+/// * `cache_ttl` - Cache TTL from the matched pattern rule (if any)
+/// * `client_addr`, `response_status`, `bytes_sent`, `upstream_time` - Response
+///   details recorded for the access log
+/// * `pacer` - Download bandwidth shaping for this client (if limited)
+/// * `client_abort` - Raised when the client disconnects mid-response
+/// * `cache_variant` - Cache key and origin headers for a Vary response (if any)
+/// * `cluster_peer` - Cluster peer the request is forwarded to, or filled from with `local_copy` (if any)
+/// * `not_modified` - Whether the client's conditional headers call for a 304
+/// * `trace_span` - Request-level span the request's work is recorded under
+/// * `normalized_key` - Request URL after `cache_key_policy` normalization
+/// * `pattern_label` - Configured URL pattern the request matched, for metrics
+/// * `client_scheme` - Scheme the client connected with (default: http)
+/// * `forwarded_headers` - Forwarding headers, and the client's Host when
+///   preserved, sent with this request's origin requests
+/// * `fill_guard` - Budget held by this request's cache fill
+/// * `fill_bypassed` - Whether the fill limits sent this miss to normal proxy
+///   mode
+/// * `fill_too_large` - Whether `max_request_fill_bytes` sent this miss to
+///   normal proxy mode
+/// * `cache_expired` - Whether a slice to fetch had an expired cached copy
+/// * `served_stale` - Whether expired cached copies stood in for a failing
+///   origin
+/// * `head_age` - Age of the cached metadata a HEAD request is answered from
+/// * `cache_mode` - Cache mode from the matched cache mode rule (default: slice)
+/// * `cache_bypassed` - Whether the client's cache bypass header skipped the
+///   cache lookup
 #[derive(Debug, Clone, Default)]
 pub struct SliceContext {
     /// Whether slicing is enabled for this request
@@ -58,11 +174,98 @@ pub struct SliceContext {
     
     /// Calculated slices for this request
     pub slices: Vec<SliceSpec>,
+    
+    /// Cache TTL for slices of this request (overrides the global cache_ttl)
+    pub cache_ttl: Option<Duration>,
+    
+    /// Address of the client that sent the request
+    pub client_addr: Option<String>,
+    
+    /// Status code sent to the client
+    pub response_status: Option<u16>,
+    
+    /// Number of body bytes sent to the client
+    pub bytes_sent: u64,
+    
+    /// Time spent waiting on the origin
+    pub upstream_time: Option<Duration>,
+    
+    /// Paces the response body to the client's download limit
+    pub pacer: Option<ClientPacer>,
+    
+    /// Raised by the response writer when the client goes away
+    pub client_abort: AbortSignal,
+    
+    /// Variant of the URL selected by the origin's Vary header
+    pub cache_variant: Option<CacheVariant>,
+    
+    /// Cluster peer that owns the requested object, when it is not this node
+    ///
+    /// With `local_copy` the request is sliced here and its metadata and
+    /// missing slices are fetched from this peer instead of the origin.
+    pub cluster_peer: Option<String>,
+    
+    /// Set when the client's cached copy is current and a 304 is sent
+    pub not_modified: bool,
+    
+    /// `slice_request` span opened by `request_filter`
+    pub trace_span: Option<tracing::Span>,
+    
+    /// Cache key of the request URL before any Vary variant is applied
+    pub normalized_key: Option<String>,
+    
+    /// Label of the matched URL pattern, set when per-pattern metrics are enabled
+    pub pattern_label: Option<String>,
+    
+    /// Scheme the client connected with, `http` when not set
+    pub client_scheme: Option<String>,
+    
+    /// `X-Forwarded-*` and `Forwarded` headers for the origin requests, plus
+    /// the client's `Host` with `host_header: preserve`
+    pub forwarded_headers: HeaderMap,
+    
+    /// Fill budget reserved for the uncached slices, released when the
+    /// context is dropped
+    pub fill_guard: Option<Arc<FillGuard>>,
+    
+    /// Set when a miss is proxied uncached because the fill limits were reached
+    pub fill_bypassed: bool,
+    
+    /// Set when a miss is proxied uncached because it would buffer more than
+    /// `max_request_fill_bytes`
+    pub fill_too_large: bool,
+    
+    /// When the request must be answered by, set by `request_filter` from
+    /// `request_timeout_secs` unless already set
+    pub deadline: Option<Instant>,
+    
+    /// Set when a slice is refetched because its cached copy expired
+    pub cache_expired: bool,
+    
+    /// Set when the response is served from expired cached copies because
+    /// the origin failed, as `stale_if_error_secs` allows
+    pub served_stale: bool,
+    
+    /// Set when a HEAD request is answered from cached metadata, to the
+    /// metadata's age
+    pub head_age: Option<Duration>,
+    
+    /// How this request uses the cache, set by `request_filter` from
+    /// `cache_mode_rules`
+    pub cache_mode: CacheMode,
+    
+    /// Set when the `cache_bypass` header asked for a fresh origin fetch;
+    /// the fetched slices are still cached
+    pub cache_bypassed: bool,
 }
 
 impl SliceProxy {
     /// Create a new SliceProxy instance
     ///
+    /// The proxy caches slices in memory only; pass a cache built with
+    /// [`TieredCache::from_config`] to [`with_cache`](Self::with_cache) to
+    /// keep them on disk as well.
+    ///
     /// # Arguments
     /// * `config` - Configuration for the slice module
     ///
@@ -81,12 +284,156 @@ impl SliceProxy {
     /// # Requirements
     /// Validates: Requirements 1.1, 1.2, 1.3, 1.4
     pub fn new(config: Arc<SliceConfig>) -> Self {
+        let mut cache = TieredCache::memory_only(
+            Duration::from_secs(config.cache_ttl),
+            config.l1_cache_size_bytes,
+        )
+        .with_stale_window(Duration::from_secs(config.stale_if_error_secs));
+        if let Some(quotas) = &config.origin_quotas {
+            cache = cache.with_origin_quotas(quotas.clone());
+        }
+        Self::with_cache(config, Arc::new(cache))
+    }
+    
+    /// Create a new SliceProxy that uses the given slice cache
+    ///
+    /// The cache is shared by every request handled through this proxy, so
+    /// slices stored by one request are served as cache hits to later ones.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration for the slice module
+    /// * `cache` - Slice cache to use for lookups and stores
+    ///
+    /// # Example
+    /// ```
+    /// use pingora_slice::{SliceConfig, SliceProxy, TieredCache};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 64 * 1024 * 1024));
+    /// let proxy = SliceProxy::with_cache(Arc::new(SliceConfig::default()), cache);
+    /// ```
+    pub fn with_cache(config: Arc<SliceConfig>, cache: Arc<TieredCache>) -> Self {
+        let metadata_cache = Arc::new(MetadataCache::new(
+            Duration::from_secs(config.metadata_ttl()),
+            config.metadata_cache_max_entries,
+        ));
+        let origin_limiter = OriginRateLimiter::from_config(&config).map(Arc::new);
+        let concurrency_ramp = ConcurrencyRamp::from_config(&config).map(Arc::new);
+        let background_fills = Arc::new(Semaphore::new(config.max_background_fills));
+        let cluster = ClusterRouter::from_config(&config);
+        let upstreams = UpstreamPool::from_config(&config).map(Arc::new);
+        let cache_keys = CacheKeyBuilder::from_config(&config);
+        let header_rewriter = HeaderRewriter::from_config(&config);
+        let forwarded = ForwardedHeaders::from_config(&config);
+        let cache_policy = Arc::new(CachePolicy::from_config(&config));
+        let error_pages = ErrorPages::from_config(&config);
+        let client_limiter = ClientRateLimiter::from_config(&config).map(Arc::new);
+        let metrics = Arc::new(SliceMetrics::new());
+        let fill_limiter = Arc::new(FillLimiter::from_config(&config).with_metrics(metrics.clone()));
+        let origin_client = origin_client(&config, Some(metrics.clone()))
+            .expect("Failed to create origin HTTP client");
+        let signer = RequestSigner::from_config(&config).map(Arc::new);
+        let analyzer = RequestAnalyzer::new(config.clone());
+        let calculator = SliceCalculator::new(config.slice_size);
+        let metadata_fetcher = MetadataFetcher::from_config(&config)
+            .expect("Failed to create metadata fetcher")
+            .with_http_client(origin_client.clone());
         SliceProxy {
             config,
-            metrics: Arc::new(SliceMetrics::new()),
+            metrics,
+            metadata_cache,
+            cache,
+            access_logger: None,
+            origin_limiter,
+            concurrency_ramp,
+            background_fills,
+            cluster,
+            upstreams,
+            cache_keys,
+            header_rewriter,
+            forwarded,
+            cache_policy,
+            error_pages,
+            client_limiter,
+            fill_limiter,
+            origin_client,
+            signer,
+            analyzer,
+            calculator,
+            metadata_fetcher,
         }
     }
     
+    /// Proxy answering new requests with `config`
+    ///
+    /// The caches, metrics, limiters and origin connections are shared with
+    /// this proxy; only what is read per request is rebuilt, so requests in
+    /// flight finish on this proxy's configuration. Fields listed in
+    /// [`RESTART_REQUIRED_FIELDS`](crate::config::RESTART_REQUIRED_FIELDS)
+    /// keep their running values and are returned when they differ.
+    ///
+    /// # Errors
+    /// Returns a configuration error if the merged configuration is invalid
+    pub fn reloaded(&self, config: SliceConfig) -> Result<(SliceProxy, Vec<&'static str>)> {
+        let (config, ignored) = config.with_restart_fields_from(&self.config)?;
+        let config = Arc::new(config);
+        let metadata_fetcher = MetadataFetcher::from_config(&config)?.with_http_client(self.origin_client.clone());
+        let proxy = SliceProxy {
+            analyzer: RequestAnalyzer::new(config.clone()),
+            cache_keys: CacheKeyBuilder::from_config(&config),
+            header_rewriter: HeaderRewriter::from_config(&config),
+            forwarded: ForwardedHeaders::from_config(&config),
+            cache_policy: Arc::new(CachePolicy::from_config(&config)),
+            error_pages: ErrorPages::from_config(&config),
+            signer: RequestSigner::from_config(&config).map(Arc::new),
+            metadata_fetcher,
+            config,
+            ..self.clone()
+        };
+        Ok((proxy, ignored))
+    }
+
+    /// Write an access log record for every request passed to `logging`
+    ///
+    /// # Arguments
+    /// * `logger` - Access logger to record completed requests with
+    pub fn with_access_logger(mut self, logger: Arc<AccessLogger>) -> Self {
+        self.access_logger = Some(logger);
+        self
+    }
+    
+    /// Share an origin rate limiter with other proxies or the cache warmer
+    ///
+    /// Replaces the limiter built from `origin_max_bytes_per_sec` and
+    /// `origin_max_requests_per_sec`, so that the caps apply process-wide.
+    ///
+    /// # Arguments
+    /// * `limiter` - Rate limiter applied to every slice subrequest
+    pub fn with_origin_limiter(mut self, limiter: Arc<OriginRateLimiter>) -> Self {
+        self.origin_limiter = Some(limiter);
+        self
+    }
+    
+    /// Get the origin rate limiter used by this proxy (if any)
+    pub fn origin_limiter(&self) -> Option<Arc<OriginRateLimiter>> {
+        self.origin_limiter.clone()
+    }
+    
+    /// Share a concurrency slow-start with other proxies
+    ///
+    /// # Arguments
+    /// * `ramp` - Slow-start gate applied to every slice subrequest
+    pub fn with_concurrency_ramp(mut self, ramp: Arc<ConcurrencyRamp>) -> Self {
+        self.concurrency_ramp = Some(ramp);
+        self
+    }
+    
+    /// Get the concurrency slow-start used by this proxy (if enabled)
+    pub fn concurrency_ramp(&self) -> Option<Arc<ConcurrencyRamp>> {
+        self.concurrency_ramp.clone()
+    }
+    
     /// Create a new request context
     ///
     /// This method creates a fresh SliceContext for each incoming request.
@@ -150,6 +497,64 @@ impl SliceProxy {
         Arc::clone(&self.metrics)
     }
     
+    /// Get a cloned Arc to the slice cache
+    ///
+    /// # Returns
+    /// An Arc clone of the shared slice cache
+    pub fn cache_arc(&self) -> Arc<TieredCache> {
+        Arc::clone(&self.cache)
+    }
+    
+    /// Remove expired cache entries every `cache_sweep_interval_secs` in
+    /// the background
+    ///
+    /// Each run is recorded in the metrics. Must be called from within a
+    /// Tokio runtime; the sweep stops when the returned handle is aborted.
+    ///
+    /// # Returns
+    /// The sweep task, or `None` if no interval is configured
+    pub fn spawn_expiry_sweep(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let interval = Duration::from_secs(self.config.cache_sweep_interval_secs?);
+        let cache = self.cache_arc();
+        let metrics = self.metrics_arc();
+        Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let swept = cache.evict_expired_l1();
+                metrics.record_expiry_sweep(swept as u64);
+                if swept > 0 {
+                    debug!("Expiry sweep removed {} cache entries", swept);
+                }
+            }
+        }))
+    }
+    
+    /// Origins requests are spread over (if `upstream_pool` is configured)
+    pub fn upstream_pool(&self) -> Option<&UpstreamPool> {
+        self.upstreams.as_deref()
+    }
+    
+    /// Policy deciding which origin responses are cached
+    pub fn cache_policy(&self) -> &CachePolicy {
+        &self.cache_policy
+    }
+    
+    /// Cache fill limits shared by every request
+    pub fn fill_limiter(&self) -> &FillLimiter {
+        &self.fill_limiter
+    }
+    
+    /// Get a cloned Arc to the metadata cache
+    ///
+    /// This is useful for components that invalidate metadata, such as the
+    /// PURGE handler.
+    ///
+    /// # Returns
+    /// An Arc clone of the metadata cache
+    pub fn metadata_cache_arc(&self) -> Arc<MetadataCache> {
+        Arc::clone(&self.metadata_cache)
+    }
+    
     /// Handle a slice request - core logic for fetching and streaming slices
     ///
     /// This method implements the complete slice request handling flow:
@@ -160,6 +565,10 @@ impl SliceProxy {
     /// 5. Stream all slices in order to the client
     /// 6. Record metrics
     ///
+    /// The whole body is collected before returning; see
+    /// [`stream_slice_request`](Self::stream_slice_request) to send it as it
+    /// is assembled.
+    ///
     /// # Arguments
     /// * `url` - The URL being requested
     /// * `ctx` - The request context containing metadata and slices
@@ -175,9 +584,61 @@ impl SliceProxy {
         url: &str,
         ctx: &SliceContext,
     ) -> Result<(http::StatusCode, HeaderMap, Vec<Bytes>)> {
-        use crate::{ResponseAssembler, SubrequestManager};
-        use std::collections::BTreeMap;
-        use std::time::Instant;
+        let (status, headers, mut body) = self.stream_slice_request(url, ctx).await?;
+        let mut slices = Vec::new();
+        while let Some(slice) = body.next_slice().await {
+            match slice {
+                Ok(slice) => slices.push(slice),
+                Err(SliceError::ClientAborted) => return Err(self.abort_slice_body(url, ctx, body).await),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((status, headers, slices))
+    }
+    
+    /// Handle a slice request, returning as soon as the response headers
+    /// are known
+    ///
+    /// The headers wait for the first slice fetched from origin, if any, which
+    /// decides whether the response is cached. The returned [`SliceBody`]
+    /// produces the slices in index order as they become available, so the
+    /// client receives the start of the response while later slices are
+    /// still being fetched. Errors after the headers end the body early.
+    ///
+    /// # Arguments
+    /// * `url` - The URL being requested
+    /// * `ctx` - The request context containing metadata and slices
+    pub async fn stream_slice_request(
+        &self,
+        url: &str,
+        ctx: &SliceContext,
+    ) -> Result<(http::StatusCode, HeaderMap, SliceBody)> {
+        let span = ctx.trace_span.clone().unwrap_or_else(tracing::Span::current);
+        self.serve_slices(url, ctx, span.clone()).instrument(span).await
+    }
+    
+    /// Apply the `on_client_abort` policy to a body whose client went away
+    ///
+    /// # Returns
+    /// The error to report for the aborted request
+    pub async fn abort_slice_body(&self, url: &str, ctx: &SliceContext, body: SliceBody) -> SliceError {
+        let span = body.span.clone();
+        async {
+            let fetch = body.interrupt().await;
+            self.handle_client_abort(url, ctx, fetch).await
+        }
+        .instrument(span)
+        .await
+    }
+    
+    /// Body of `stream_slice_request`, run inside the request span
+    async fn serve_slices(
+        &self,
+        url: &str,
+        ctx: &SliceContext,
+        span: tracing::Span,
+    ) -> Result<(http::StatusCode, HeaderMap, SliceBody)> {
+        use crate::ResponseAssembler;
         
         let start_time = Instant::now();
         
@@ -196,7 +657,36 @@ impl SliceProxy {
         
         // Step 1: Build response headers (Requirement 6.5)
         let assembler = ResponseAssembler::new();
-        let (status, headers) = assembler.build_response_header(metadata, ctx.client_range())?;
+        
+        // The client's copy is current: validators only, no body
+        if ctx.not_modified {
+            let (status, mut headers) = assembler.build_not_modified_header(metadata)?;
+            self.header_rewriter.rewrite_response(&mut headers);
+            self.metrics.record_request_duration(start_time.elapsed());
+            info!("Slice request answered with 304 Not Modified: url={}", url);
+            return Ok((status, headers, SliceBody::empty(self, url, ctx, span)));
+        }
+        
+        // HEAD answered from cached metadata: a GET's headers, no body
+        if let Some(age) = ctx.head_age {
+            let (status, mut headers) = assembler.build_response_header(metadata, ctx.client_range())?;
+            headers.insert("x-cache-status", HeaderValue::from_static(ctx.cache_status().header_value()));
+            headers.insert(http::header::AGE, HeaderValue::from(age.as_secs()));
+            self.header_rewriter.rewrite_response(&mut headers);
+            self.metrics.record_request_duration(start_time.elapsed());
+            info!("Slice request answered as HEAD from cached metadata: url={}", url);
+            return Ok((status, headers, SliceBody::empty(self, url, ctx, span)));
+        }
+        
+        self.metrics
+            .record_slice_hit_mix(ctx.cached_slice_count(), ctx.uncached_slice_count());
+        
+        let (status, mut headers) = assembler.build_response_header(metadata, ctx.client_range())?;
+        headers.insert("x-cache-status", HeaderValue::from_static(ctx.cache_status().header_value()));
+        if ctx.cache_bypassed {
+            headers.insert("x-cache", HeaderValue::from_static("BYPASS"));
+        }
+        self.header_rewriter.rewrite_response(&mut headers);
         
         debug!(
             "Built response headers: status={}, content_length={}",
@@ -217,150 +707,308 @@ impl SliceProxy {
             ctx.slice_count()
         );
         
-        // Step 3: Fetch uncached slices concurrently (Requirements 5.1, 5.2, 5.3, 5.4, 5.5)
-        let fetch_start = Instant::now();
-        let fetch_results = if !slices_to_fetch.is_empty() {
-            let subrequest_mgr = SubrequestManager::new(
-                self.config.max_concurrent_subrequests,
-                self.config.max_retries,
-            );
-            
+        // Step 3: Start fetching uncached slices concurrently (Requirements 5.1, 5.2, 5.3, 5.4, 5.5)
+        let mut body = SliceBody::new(self, url, ctx, start_time, span);
+        body.assemble_span = Some(tracing::info_span!(
+            "assemble",
+            slices = ctx.slice_count(),
+            bytes = tracing::field::Empty,
+        ));
+        if !slices_to_fetch.is_empty() {
+            ctx.check_deadline()?;
             debug!(
                 "Fetching {} slices with max_concurrent={}",
                 slices_to_fetch.len(),
                 self.config.max_concurrent_subrequests
             );
             
-            match subrequest_mgr.fetch_slices(slices_to_fetch.clone(), url).await {
-                Ok(results) => {
-                    let fetch_duration = fetch_start.elapsed();
-                    info!(
-                        "Successfully fetched {} slices in {:?}",
-                        results.len(),
-                        fetch_duration
-                    );
-                    
-                    // Record subrequest metrics
-                    for _ in &results {
-                        self.metrics.record_subrequest(true);
-                    }
-                    self.metrics.record_subrequest_duration(fetch_duration);
-                    
-                    results
+            let whole = ctx.cache_mode == CacheMode::Whole;
+            let finish_in_flight = self.config.on_client_abort != ClientAbortPolicy::Abort;
+            let started = if ctx.served_stale {
+                // The origin already failed to provide the metadata
+                if body.serve_stale(&slices_to_fetch, self.stale_window()) {
+                    Ok(())
+                } else {
+                    Err(SliceError::CacheError(format!("Stale slices of {} were evicted", url)))
                 }
-                Err(e) => {
-                    warn!("Failed to fetch slices: {:?}", e);
-                    // Record failed subrequests
-                    for _ in &slices_to_fetch {
-                        self.metrics.record_subrequest(false);
-                    }
-                    return Err(e);
+            } else {
+                body.start_fetch(slices_to_fetch.clone(), whole, finish_in_flight);
+                // The first slice from origin decides whether the response is cached
+                body.receive().await
+            };
+            match started {
+                Ok(()) => {}
+                Err(SliceError::ClientAborted) => return Err(self.abort_slice_body(url, ctx, body).await),
+                // Nothing has come from the origin yet, so expired copies
+                // can stand in for all of it
+                Err(e)
+                    if e.is_origin_failure()
+                        && self.config.stale_if_error_secs > 0
+                        && body.serve_stale(&slices_to_fetch, self.stale_window()) =>
+                {
+                    warn!("Origin failed, serving stale slices: url={}, error={:?}", url, e);
+                    headers.insert("x-cache-status", HeaderValue::from_static(CacheStatus::Stale.header_value()));
                 }
+                Err(e) => return Err(e),
             }
         } else {
             debug!("All slices are cached, no fetching needed");
-            Vec::new()
-        };
+        }
         
-        // Step 4: Merge cached and newly fetched slices (Requirement 6.2)
-        let assembly_start = Instant::now();
-        let cache = crate::SliceCache::new(Duration::from_secs(self.config.cache_ttl));
-        let mut all_slices: BTreeMap<usize, Bytes> = BTreeMap::new();
-        
-        // Add cached slices
-        for (idx, slice_spec) in ctx.slices().iter().enumerate() {
-            if slice_spec.cached {
-                match cache.lookup_slice(url, &slice_spec.range).await {
-                    Ok(Some(data)) => {
-                        debug!(
-                            "Retrieved cached slice {}: range={}-{}, size={}",
-                            idx, slice_spec.range.start, slice_spec.range.end, data.len()
-                        );
-                        self.metrics.record_bytes_from_cache(data.len() as u64);
-                        all_slices.insert(idx, data);
-                    }
-                    Ok(None) => {
-                        warn!(
-                            "Cached slice {} not found in cache (marked as cached but missing)",
-                            idx
-                        );
-                        // This shouldn't happen, but we'll handle it gracefully
-                        // The slice should have been in slices_to_fetch if it wasn't cached
-                    }
-                    Err(e) => {
-                        warn!("Error retrieving cached slice {}: {:?}", idx, e);
-                        self.metrics.record_cache_error();
-                    }
-                }
+        // Per-user responses, and statuses left out of cache_admission,
+        // are served but never cached
+        let admission = body.admission.unwrap_or(Admission::Cache(None));
+        match admission {
+            Admission::SkipPrivate => {
+                info!("Origin response carries per-user headers, not caching: url={}", url);
+                self.metrics.record_private_skip();
+                headers.insert("x-cache", HeaderValue::from_static("SKIP-PRIVATE"));
+            }
+            Admission::SkipStatus | Admission::SkipMethod => {
+                info!("Origin response status is not admitted to the cache, not caching: url={}", url);
+                headers.insert("x-cache", HeaderValue::from_static("SKIP-STATUS"));
             }
+            Admission::Cache(_) => {}
         }
         
-        // Step 5: Add newly fetched slices and store them in cache (Requirements 7.1, 7.5)
-        for result in fetch_results {
-            let idx = result.slice_index;
-            let data = result.data.clone();
-            
-            debug!(
-                "Adding fetched slice {}: size={}",
-                idx,
-                data.len()
-            );
-            
-            self.metrics.record_bytes_from_origin(data.len() as u64);
-            all_slices.insert(idx, data.clone());
-            
-            // Store in cache
-            if let Some(slice_spec) = ctx.slices().get(idx) {
-                match cache.store_slice(url, &slice_spec.range, data).await {
-                    Ok(()) => {
-                        debug!(
-                            "Stored slice {} in cache: range={}-{}",
-                            idx, slice_spec.range.start, slice_spec.range.end
-                        );
-                    }
-                    Err(e) => {
-                        warn!(
-                            "Failed to store slice {} in cache: {:?}",
-                            idx, e
-                        );
-                        self.metrics.record_cache_error();
-                        // Continue processing even if cache storage fails
-                    }
-                }
-            }
+        // A range request from the start of an uncached file is likely the
+        // first of a sequential download: fetch what comes next ahead of it,
+        // unless the origin is failing
+        if self.config.prefetch_slices > 0
+            && self.config.enable_cache
+            && admission.is_cached()
+            && !body.served_stale()
+            && ctx.client_range().is_some()
+            && ctx.slices().first().is_some_and(|first| first.range.start == 0 && !first.cached)
+        {
+            self.prefetch_after(url, ctx, metadata.content_length);
         }
         
-        // Step 6: Validate that all slices are present (Requirement 6.2)
-        assembler.validate_completeness(&all_slices, ctx.slice_count())?;
+        // The response is as old as its oldest cached slice
+        if let Some(age) = self.cached_age(url, ctx).await.max(body.stale_age) {
+            headers.insert(http::header::AGE, HeaderValue::from(age.as_secs()));
+        }
         
-        debug!(
-            "All {} slices assembled successfully",
-            all_slices.len()
+        Ok((status, headers, body))
+    }
+    
+    /// Age of the oldest cached slice of the request, if any slice is
+    /// still cached
+    async fn cached_age(&self, url: &str, ctx: &SliceContext) -> Option<Duration> {
+        let mut age = None;
+        for slice in ctx.slices().iter().filter(|slice| slice.cached) {
+            age = age.max(self.cache.age(ctx.cache_key(url), &slice.range).await);
+        }
+        age
+    }
+    
+    /// Build a subrequest manager sharing this proxy's metrics, origin rate
+    /// limiter, concurrency ramp and upstream pool, using the configured fetch order and the
+    /// request's Vary headers
+    ///
+    /// The request's retry budget and deadline start with the manager.
+    fn subrequest_manager(&self, max_concurrent: usize, ctx: &SliceContext) -> SubrequestManager {
+        let mut manager = SubrequestManager::new(max_concurrent, self.config.max_retries)
+            .with_http_client(self.origin_client.clone())
+            .with_metrics(self.metrics_arc())
+            .with_fetch_order(self.config.fetch_order)
+            .with_request_headers(self.origin_headers(ctx.cache_variant.as_ref(), ctx));
+        if let Some(limiter) = &self.origin_limiter {
+            manager = manager.with_rate_limiter(limiter.clone());
+        }
+        if let Some(ramp) = &self.concurrency_ramp {
+            manager = manager.with_concurrency_ramp(ramp.clone());
+        }
+        if let Some(pool) = self.origin_pool(ctx) {
+            manager = manager.with_upstreams(pool.clone());
+        }
+        if let Some(metadata) = ctx.metadata() {
+            manager = manager.with_expected_size(metadata.content_length);
+        }
+        if let Some(retries) = self.config.request_retry_budget {
+            manager = manager.with_retry_budget(retries);
+        }
+        if let Some(ms) = self.config.request_deadline_ms {
+            manager = manager.with_deadline(Duration::from_millis(ms));
+        }
+        if let Some(signer) = &self.signer {
+            manager = manager.with_signer(signer.clone());
+        }
+        if let Some(failures) = self.config.whole_object_fallback_after {
+            manager = manager.with_failure_limit(failures);
+        }
+        manager
+    }
+    
+    /// Headers sent with every origin request for `variant`
+    ///
+    /// The variant's headers and the request's forwarding headers, rewritten
+    /// by the upstream request rules. Fills from a cluster peer are marked
+    /// with `X-Slice-Cluster-Hop` instead, as the peer applies its own rules.
+    fn origin_headers(&self, variant: Option<&CacheVariant>, ctx: &SliceContext) -> HeaderMap {
+        let mut base = variant.map(|v| v.headers.clone()).unwrap_or_default();
+        base.extend(ctx.forwarded_headers.clone());
+        if let (Some(_), Some(cluster)) = (&ctx.cluster_peer, &self.cluster) {
+            if let Ok(hop) = HeaderValue::from_str(cluster.self_peer()) {
+                base.insert(CLUSTER_HOP_HEADER, hop);
+            }
+            return base;
+        }
+        self.header_rewriter.request_headers(base)
+    }
+    
+    /// Cache TTL for slices of this request
+    fn slice_ttl(&self, ctx: &SliceContext) -> Duration {
+        ctx.cache_ttl()
+            .unwrap_or_else(|| Duration::from_secs(self.config.cache_ttl))
+    }
+    
+    /// Apply the `on_client_abort` policy after the client disconnected
+    ///
+    /// Slices that finished fetching are cached. With `complete_fill` the
+    /// remaining slices are fetched and cached by a background task.
+    ///
+    /// # Returns
+    /// The error to report for the aborted request
+    async fn handle_client_abort(
+        &self,
+        url: &str,
+        ctx: &SliceContext,
+        fetch: InterruptedFetch,
+    ) -> SliceError {
+        self.metrics.record_client_abort();
+        info!(
+            "Client disconnected: url={}, policy={:?}, fetched={}, remaining={}",
+            url,
+            self.config.on_client_abort,
+            fetch.results.len(),
+            fetch.remaining.len()
         );
         
-        // Step 7: Stream slices in order (Requirements 6.1, 6.2, 6.3)
-        let ordered_slices = assembler.stream_slices(all_slices);
-        
-        // Calculate total bytes sent
-        let total_bytes: u64 = ordered_slices.iter().map(|b| b.len() as u64).sum();
-        self.metrics.record_bytes_to_client(total_bytes);
-        
-        let assembly_duration = assembly_start.elapsed();
-        self.metrics.record_assembly_duration(assembly_duration);
+        let admission = self.cache_policy.admit_fill(&fetch.results);
+        if admission == Admission::SkipPrivate {
+            self.metrics.record_private_skip();
+        }
+        let ttl = admission.ttl().unwrap_or_else(|| self.slice_ttl(ctx));
+        for result in fetch.results {
+            self.metrics.record_subrequest(true);
+            self.metrics.record_bytes_from_origin(result.data.len() as u64);
+            if !self.config.enable_cache || !admission.is_cached() {
+                continue;
+            }
+            if let Some(slice_spec) = ctx.slices().get(result.slice_index) {
+                if let Err(e) = self
+                    .cache
+                    .store_with_ttl(ctx.cache_key(url), &slice_spec.range, result.data, ttl)
+                {
+                    warn!("Failed to store slice {} in cache: {:?}", result.slice_index, e);
+                    self.metrics.record_cache_error();
+                }
+            }
+        }
         
-        // Step 8: Record overall request metrics (Requirement 9.1, 9.2)
-        let total_duration = start_time.elapsed();
-        self.metrics.record_request_duration(total_duration);
+        if self.config.on_client_abort == ClientAbortPolicy::CompleteFill
+            && self.config.enable_cache
+            && admission.is_cached()
+            && !fetch.remaining.is_empty()
+        {
+            self.spawn_background_fill(url, ctx, fetch.remaining, BackgroundFill::ClientAbort);
+        }
         
-        info!(
-            "Slice request completed: url={}, slices={}, total_bytes={}, duration={:?}",
-            url,
-            ctx.slice_count(),
-            total_bytes,
-            total_duration
-        );
+        SliceError::ClientAborted
+    }
+    
+    /// Prefetch the `prefetch_slices` slices after the request's range that
+    /// are not cached yet
+    ///
+    /// Which of them are cached is checked in the fill task, so the
+    /// response is not held up by it.
+    fn prefetch_after(&self, url: &str, ctx: &SliceContext, file_size: u64) {
+        let Some(last) = ctx.slices().last() else {
+            return;
+        };
+        let slice_size = self.config.slice_size as u64;
+        let slices: Vec<SliceSpec> = (0..self.config.prefetch_slices as u64)
+            .map(|i| last.range.end + 1 + i * slice_size)
+            .take_while(|&start| start < file_size)
+            .filter_map(|start| ByteRange::new(start, (start + slice_size - 1).min(file_size - 1)).ok())
+            .enumerate()
+            .map(|(index, range)| SliceSpec::new(index, range))
+            .collect();
+        if !slices.is_empty() {
+            self.spawn_background_fill(url, ctx, slices, BackgroundFill::Prefetch);
+        }
+    }
+    
+    /// Fetch and cache `slices` in a detached task
+    ///
+    /// Slices already in the cache when the task runs are left out. Fills
+    /// are skipped when `max_background_fills` are already running.
+    fn spawn_background_fill(&self, url: &str, ctx: &SliceContext, slices: Vec<SliceSpec>, kind: BackgroundFill) {
+        let Ok(permit) = self.background_fills.clone().try_acquire_owned() else {
+            warn!("Too many background fills running, dropping fill: url={}", url);
+            return;
+        };
         
-        Ok((status, headers, ordered_slices))
+        let manager = self.subrequest_manager(self.config.background_fill_concurrency, ctx);
+        let cache = self.cache.clone();
+        let metrics = self.metrics_arc();
+        let ttl = self.slice_ttl(ctx);
+        let cache_key = ctx.cache_key(url).to_string();
+        let url = url.to_string();
+        let cache_policy = self.cache_policy.clone();
+        
+        tokio::spawn(async move {
+            let _permit = permit;
+            let mut missing = Vec::with_capacity(slices.len());
+            for slice in slices {
+                if cache.inspect(&cache.generate_cache_key(&cache_key, &slice.range)).await.is_none() {
+                    missing.push(slice);
+                }
+            }
+            let slices = missing;
+            if slices.is_empty() {
+                return;
+            }
+            debug!("Filling {} slices in the background: url={}, kind={:?}", slices.len(), url, kind);
+            
+            let results = match manager.fetch_slices(slices.clone(), &url).await {
+                Ok(results) => results,
+                Err(e) => {
+                    warn!("Background fill failed: url={}, error={:?}", url, e);
+                    return;
+                }
+            };
+            let ttl = match cache_policy.admit_fill(&results) {
+                Admission::Cache(status_ttl) => status_ttl.unwrap_or(ttl),
+                Admission::SkipPrivate => {
+                    info!("Origin response carries per-user headers, dropping background fill: url={}", url);
+                    metrics.record_private_skip();
+                    return;
+                }
+                Admission::SkipStatus | Admission::SkipMethod => {
+                    info!("Origin response status is not admitted to the cache, dropping background fill: url={}", url);
+                    return;
+                }
+            };
+            
+            for result in results {
+                let Some(slice_spec) = slices.iter().find(|s| s.index == result.slice_index) else {
+                    continue;
+                };
+                metrics.record_subrequest(true);
+                metrics.record_bytes_from_origin(result.data.len() as u64);
+                if let Err(e) = cache.store_with_ttl(&cache_key, &slice_spec.range, result.data, ttl) {
+                    warn!("Failed to store slice {} in cache: {:?}", result.slice_index, e);
+                    metrics.record_cache_error();
+                }
+            }
+            match kind {
+                BackgroundFill::ClientAbort => metrics.record_background_fill(),
+                BackgroundFill::Prefetch => metrics.record_prefetched_slices(slices.len() as u64),
+            }
+            info!("Background fill completed: url={}, slices={}, kind={:?}", url, slices.len(), kind);
+        }.in_current_span());
     }
     
     /// Request filter - determines if slicing should be enabled for this request
@@ -385,6 +1033,10 @@ impl SliceProxy {
     /// * `Ok(false)` - Slicing enabled, will handle response ourselves
     /// * `Err(SliceError)` - An error occurred during processing
     ///
+    /// The request's work is recorded under a `slice_request` span kept in
+    /// `ctx`, continuing the client's trace when built with the `otel`
+    /// feature and the request carries a `traceparent`.
+    ///
     /// # Requirements
     /// Validates: Requirements 2.1, 2.2, 2.3, 2.4, 3.1, 3.2, 3.3, 3.4, 3.5,
     ///            4.1, 4.2, 4.3, 4.4, 7.3
@@ -394,28 +1046,143 @@ impl SliceProxy {
         uri: &str,
         headers: &HeaderMap<HeaderValue>,
         ctx: &mut SliceContext,
+    ) -> Result<bool> {
+        let span = tracing::info_span!(
+            "slice_request",
+            method = %method,
+            url = %uri,
+            slices = tracing::field::Empty,
+        );
+        #[cfg(feature = "otel")]
+        crate::telemetry::set_parent_from_headers(&span, headers);
+        ctx.trace_span = Some(span.clone());
+        
+        self.filter_request(method, uri, headers, ctx).instrument(span).await
+    }
+    
+    /// Body of `request_filter`, run inside the request span
+    async fn filter_request(
+        &self,
+        method: &Method,
+        uri: &str,
+        headers: &HeaderMap<HeaderValue>,
+        ctx: &mut SliceContext,
     ) -> Result<bool> {
         info!("Processing request: method={}, uri={}", method, uri);
+        if ctx.deadline.is_none() {
+            ctx.deadline = self
+                .config
+                .request_timeout_secs
+                .map(|secs| Instant::now() + Duration::from_secs(secs));
+        }
         
-        // Step 1: Check if slicing should be enabled for this request
-        // Requirements: 2.1, 2.2, 2.3, 2.4
-        let analyzer = RequestAnalyzer::new(self.config_arc());
+        // Clients over their rate limit are turned away before any work is done
+        if let (Some(limiter), Some(client)) = (&self.client_limiter, ctx.client_addr.as_deref()) {
+            if let Err(wait) = limiter.check(client) {
+                let retry_after_secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+                warn!(
+                    "Client rate limit exceeded: client={}, uri={}, retry_after={}s",
+                    client, uri, retry_after_secs
+                );
+                self.metrics.record_throttled_request();
+                return Err(SliceError::RateLimited { retry_after_secs });
+            }
+        }
         
-        if !analyzer.should_slice(method, uri, headers) {
-            debug!(
-                "Slicing not applicable for request: method={}, uri={}",
-                method, uri
+        // Download limits apply to every response, sliced or not
+        ctx.pacer = ClientPacer::for_request(
+            self.config.client_max_bytes_per_sec,
+            self.config.trust_rate_limit_header,
+            headers,
+        );
+        
+        // Forwarding headers go to the origin whether the request is sliced
+        // or proxied
+        if !self.forwarded.is_empty() {
+            ctx.forwarded_headers = self.forwarded.build(
+                headers,
+                ctx.client_addr.as_deref(),
+                ctx.client_scheme.as_deref().unwrap_or("http"),
             );
-            // Record as non-sliced request
-            self.metrics.record_request(false);
+        }
+        
+        // A preserved Host sends every origin request to the client's virtual
+        // host, and keys the cache on that host
+        let preserved_host = match self.config.host_header {
+            HostHeaderMode::Preserve => headers.get(http::header::HOST).cloned(),
+            HostHeaderMode::Upstream => None,
+        };
+        if let Some(host) = &preserved_host {
+            ctx.forwarded_headers.insert(http::header::HOST, host.clone());
+        }
+        
+        // Bypass rules skip the cache entirely
+        let analyzer = &self.analyzer;
+        ctx.cache_mode = analyzer.cache_mode_for(uri).unwrap_or_default();
+        if ctx.cache_mode == CacheMode::Bypass {
+            debug!("Cache bypassed by cache_mode_rules: uri={}", uri);
+            self.metrics.record_request(false);
+            return Ok(true);
+        }
+        
+        // The bypass header skips lookups but still caches what is fetched
+        ctx.cache_bypassed = self.bypass_requested(headers);
+        if ctx.cache_bypassed {
+            debug!("Cache lookup bypassed by request header: uri={}", uri);
+        }
+        
+        // Step 1: Check if slicing should be enabled for this request
+        // Requirements: 2.1, 2.2, 2.3, 2.4
+        // HEAD requests for sliceable URLs are answered from cached metadata
+        let slice_method = if method == Method::HEAD { &Method::GET } else { method };
+        
+        if !analyzer.should_slice(slice_method, uri, headers) {
+            debug!(
+                "Slicing not applicable for request: method={}, uri={}",
+                method, uri
+            );
+            // Record as non-sliced request
+            self.metrics.record_request(false);
             // Continue with normal proxy mode
             return Ok(true);
         }
         
         debug!("Request eligible for slicing: uri={}", uri);
+        let normalized = match preserved_host.as_ref().and_then(|h| h.to_str().ok()) {
+            Some(host) => self.cache_keys.build(&rewrite_authority(uri, host)),
+            None => self.cache_keys.build(uri),
+        };
+        ctx.normalized_key = Some(SliceKey::new(&normalized).object_key());
+        if self.config.metrics_endpoint.as_ref().is_some_and(|m| m.pattern_labels) {
+            let label = analyzer.pattern_label(uri);
+            self.metrics.record_pattern_request(&label);
+            ctx.pattern_label = Some(label);
+        }
+        
+        // In cluster mode, objects owned by another node are forwarded to
+        // it, or with `local_copy` sliced here and filled from it
+        let cluster_peer = self
+            .cluster
+            .as_ref()
+            .and_then(|cluster| cluster.route(ctx.cache_key(uri), headers))
+            .map(str::to_string);
+        if let Some(peer) = cluster_peer {
+            if !self.cluster.as_ref().is_some_and(ClusterRouter::local_copy) {
+                return Ok(self.route_to_peer(&peer, uri, ctx));
+            }
+            debug!("Filling from owning cluster peer: uri={}, peer={}", uri, peer);
+            ctx.cluster_peer = Some(peer);
+        }
+        
+        // Pick the cache TTL from the matched pattern rule, if any
+        ctx.set_cache_ttl(analyzer.cache_ttl_for(uri));
         
         // Step 2: Extract client's Range header if present (Requirement 10.1)
-        ctx.set_client_range_opt(analyzer.extract_client_range(headers));
+        let client_range = analyzer.client_range(headers).inspect_err(|e| {
+            warn!("Refusing Range header for uri={}: {}", uri, e);
+            self.metrics.record_request(false);
+        })?;
+        ctx.set_client_range_opt(client_range);
         
         if let Some(range) = ctx.client_range() {
             debug!(
@@ -424,15 +1191,52 @@ impl SliceProxy {
             );
         }
         
-        // Step 3: Fetch file metadata from origin server
+        if method == Method::HEAD {
+            if ctx.cache_bypassed {
+                self.metrics.record_request(false);
+                return Ok(true);
+            }
+            return self.head_from_metadata(uri, headers, ctx);
+        }
+        
+        // Step 3: Fetch file metadata, from the metadata cache when possible
         // Requirements: 3.1, 3.2, 3.3, 3.4, 3.5
-        let metadata_fetcher = MetadataFetcher::new()
-            .map_err(|e| {
-                warn!("Failed to create metadata fetcher: {:?}", e);
-                e
-            })?;
+        let base_key = ctx.cache_key(uri).to_string();
+        if ctx.cache_bypassed {
+            self.metadata_cache.invalidate(&base_key);
+        }
+        let mut fetch_result = self
+            .cached_metadata(uri, &base_key, None, ctx)
+            .await;
         
-        let metadata = match metadata_fetcher.fetch_metadata(uri).await {
+        // When the origin varies its response, key the cache and metadata on
+        // the variant this client asked for
+        if let Ok(meta) = &fetch_result {
+            if !meta.vary.is_empty() {
+                match CacheVariant::from_vary(&base_key, &meta.vary, &self.config.vary_headers, headers) {
+                    Some(variant) => {
+                        debug!("Selected cache variant: uri={}, key={}", uri, variant.key);
+                        if ctx.cache_bypassed {
+                            self.metadata_cache.invalidate(&variant.key);
+                        }
+                        fetch_result = self
+                            .cached_metadata(uri, &variant.key, Some(&variant), ctx)
+                            .await;
+                        ctx.cache_variant = Some(variant);
+                    }
+                    None => {
+                        info!(
+                            "Origin varies on uncacheable headers for uri={} (vary={:?}), falling back to normal proxy",
+                            uri, meta.vary
+                        );
+                        self.metrics.record_request(false);
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+        
+        let metadata = match fetch_result {
             Ok(meta) => {
                 debug!(
                     "Fetched metadata: uri={}, size={}, supports_range={}",
@@ -440,20 +1244,29 @@ impl SliceProxy {
                 );
                 meta
             }
-            Err(e) => {
-                warn!(
-                    "Failed to fetch metadata for uri={}: {:?}",
-                    uri, e
-                );
-                // Record as non-sliced request
-                self.metrics.record_request(false);
-                // Fall back to normal proxy mode
-                return Ok(true);
-            }
+            Err(e) => match self.stale_metadata(&base_key, &e, headers, ctx) {
+                Some(meta) => {
+                    warn!("Failed to fetch metadata for uri={}, serving stale: {:?}", uri, e);
+                    ctx.served_stale = true;
+                    meta
+                }
+                None => {
+                    warn!(
+                        "Failed to fetch metadata for uri={}: {:?}",
+                        uri, e
+                    );
+                    // Record as non-sliced request
+                    self.metrics.record_request(false);
+                    // Fall back to normal proxy mode
+                    return Ok(true);
+                }
+            },
         };
         
         // Step 4: Check if origin supports Range requests (Requirement 3.3, 3.4)
-        if !metadata.supports_range {
+        // Whole-object requests never send one
+        let whole = ctx.cache_mode == CacheMode::Whole;
+        if !metadata.supports_range && !whole {
             info!(
                 "Origin does not support Range requests for uri={}, falling back to normal proxy",
                 uri
@@ -464,13 +1277,50 @@ impl SliceProxy {
             return Ok(true);
         }
         
+        // Small files are cheaper to fetch whole than in slices
+        if metadata.content_length < self.config.min_slice_file_size && !whole {
+            debug!(
+                "File below min_slice_file_size for uri={} ({} < {}), falling back to normal proxy",
+                uri, metadata.content_length, self.config.min_slice_file_size
+            );
+            self.metrics.record_request(false);
+            return Ok(true);
+        }
+        
+        // Answer conditional requests for an unchanged file with 304
+        let if_none_match = analyzer.extract_if_none_match(headers);
+        let if_modified_since = analyzer.extract_if_modified_since(headers);
+        if metadata.is_not_modified(if_none_match.as_deref(), if_modified_since.as_deref()) {
+            info!(
+                "Client copy is current, responding 304: uri={}, if_none_match={:?}, if_modified_since={:?}",
+                uri, if_none_match, if_modified_since
+            );
+            ctx.not_modified = true;
+            ctx.set_metadata(metadata);
+            ctx.enable_slicing();
+            self.metrics.record_request(true);
+            return Ok(false);
+        }
+        
+        // Only honor the client's Range if its If-Range validator still matches
+        self.apply_if_range(analyzer, headers, &metadata, ctx);
+        
+        // A range running past the end of the file is served up to the last byte
+        if let Some(range) = ctx.client_range() {
+            if range.start < metadata.content_length && range.end >= metadata.content_length {
+                ctx.set_client_range_opt(ByteRange::new(range.start, metadata.content_length - 1).ok());
+            }
+        }
+        
         // Step 5: Calculate slices (Requirements 4.1, 4.2, 4.3, 4.4)
-        let calculator = SliceCalculator::new(self.config.slice_size);
+        // Client ranges are fetched as whole, aligned slices so they share
+        // cache entries with full-file requests
+        let calculated = match ctx.client_range() {
+            Some(range) => self.calculator.calculate_aligned_slices(metadata.content_length, range),
+            None => self.calculator.calculate_slices(metadata.content_length, None),
+        };
         
-        let slices = match calculator.calculate_slices(
-            metadata.content_length,
-            ctx.client_range(),
-        ) {
+        let slices = match calculated {
             Ok(slices) => {
                 debug!(
                     "Calculated {} slices for uri={}, file_size={}",
@@ -500,12 +1350,30 @@ impl SliceProxy {
         }
         
         // Step 6: Check cache for existing slices (Requirement 7.3)
-        let cache = SliceCache::new(Duration::from_secs(self.config.cache_ttl));
-        
         // Extract ranges for cache lookup
         let ranges: Vec<ByteRange> = slices.iter().map(|s| s.range).collect();
         
-        let cached_slices = cache.lookup_multiple(uri, &ranges).await;
+        tracing::Span::current().record("slices", slices.len());
+        let cached_slices = if self.config.enable_cache && !ctx.cache_bypassed {
+            let span = tracing::info_span!(
+                "cache_lookup",
+                url = %uri,
+                cache_tier = "memory",
+                slices = ranges.len(),
+                hits = tracing::field::Empty,
+                bytes = tracing::field::Empty,
+            );
+            let cached = self
+                .cache
+                .lookup_multiple(ctx.cache_key(uri), &ranges)
+                .instrument(span.clone())
+                .await;
+            span.record("hits", cached.len());
+            span.record("bytes", cached.values().map(|data| data.len()).sum::<usize>());
+            cached
+        } else {
+            HashMap::new()
+        };
         
         debug!(
             "Cache lookup complete: uri={}, total_slices={}, cache_hits={}",
@@ -514,6 +1382,14 @@ impl SliceProxy {
             cached_slices.len()
         );
         
+        // With local_copy, requests the owning peer has to help with count
+        // as routed to it
+        if let Some(peer) = &ctx.cluster_peer {
+            if cached_slices.len() < slices.len() {
+                self.metrics.record_cluster_route(peer);
+            }
+        }
+        
         // Record cache hits and misses
         for _ in 0..cached_slices.len() {
             self.metrics.record_cache_hit();
@@ -521,6 +1397,13 @@ impl SliceProxy {
         for _ in 0..(slices.len() - cached_slices.len()) {
             self.metrics.record_cache_miss();
         }
+        if let Some(label) = &ctx.pattern_label {
+            self.metrics.record_pattern_cache(
+                label,
+                cached_slices.len() as u64,
+                (slices.len() - cached_slices.len()) as u64,
+            );
+        }
         
         // Mark which slices are cached
         let mut slices_with_cache_info = slices;
@@ -529,6 +1412,59 @@ impl SliceProxy {
                 slices_with_cache_info[idx].cached = true;
             }
         }
+        ctx.cache_expired = self.config.enable_cache
+            && slices_with_cache_info
+                .iter()
+                .any(|slice| !slice.cached && self.cache.is_expired(ctx.cache_key(uri), &slice.range));
+        
+        // Stale metadata is only of use if every slice it needs is still here
+        if ctx.served_stale
+            && slices_with_cache_info.iter().any(|slice| {
+                !slice.cached
+                    && self
+                        .cache
+                        .lookup_stale(ctx.cache_key(uri), &slice.range, self.stale_window())
+                        .is_none()
+            })
+        {
+            info!("No stale copy of every slice for uri={}, falling back to normal proxy", uri);
+            ctx.served_stale = false;
+            self.metrics.record_request(false);
+            return Ok(true);
+        }
+        
+        // Reserve buffer space for the slices to fetch; when the miss is too
+        // large or too many fills are in flight, proxy it without buffering
+        // or caching it. Stale slices are not fetched.
+        let fill_bytes: u64 = slices_with_cache_info
+            .iter()
+            .filter(|slice| !slice.cached && !ctx.served_stale)
+            .map(|slice| slice.range.size())
+            .sum();
+        if self.config.max_request_fill_bytes.is_some_and(|max| fill_bytes > max) {
+            info!("Cache fill too large, proxying uri={} uncached ({} bytes to fetch)", uri, fill_bytes);
+            self.metrics.record_fill_bypass();
+            self.metrics.record_request(false);
+            ctx.fill_too_large = true;
+            return Ok(true);
+        }
+        if fill_bytes > 0 {
+            match self.fill_limiter.try_acquire(fill_bytes) {
+                Some(guard) => ctx.fill_guard = Some(Arc::new(guard)),
+                None => {
+                    info!(
+                        "Cache fill limit reached, proxying uri={} uncached ({} bytes to fetch, {} buffered)",
+                        uri,
+                        fill_bytes,
+                        self.fill_limiter.buffered_bytes()
+                    );
+                    self.metrics.record_fill_bypass();
+                    self.metrics.record_request(false);
+                    ctx.fill_bypassed = true;
+                    return Ok(true);
+                }
+            }
+        }
         
         // Step 7: Update context and enable slicing
         ctx.set_metadata(metadata);
@@ -551,16 +1487,227 @@ impl SliceProxy {
         Ok(false)
     }
     
+    /// Origins a sliced request's metadata and slices are fetched from
+    ///
+    /// The owning cluster peer for objects filled from it with
+    /// `local_copy`, otherwise `upstream_pool` if configured.
+    fn origin_pool(&self, ctx: &SliceContext) -> Option<&Arc<UpstreamPool>> {
+        match (&ctx.cluster_peer, &self.cluster) {
+            (Some(peer), Some(cluster)) => cluster.fill_pool(peer),
+            _ => self.upstreams.as_ref(),
+        }
+    }
+    
+    /// Forward the request to the cluster peer that owns it
+    ///
+    /// # Returns
+    /// `true`, so the request continues in normal proxy mode towards `peer`
+    fn route_to_peer(&self, peer: &str, uri: &str, ctx: &mut SliceContext) -> bool {
+        info!("Routing request to owning cluster peer: uri={}, peer={}", uri, peer);
+        ctx.cluster_peer = Some(peer.to_string());
+        self.metrics.record_cluster_route(peer);
+        self.metrics.record_request(false);
+        true
+    }
+    
+    /// Fetch metadata for `uri` through the metadata cache
+    ///
+    /// The result is cached under `key`, the request's cache key. The probe
+    /// carries the variant's headers, if any, the request's forwarding
+    /// headers and the upstream request rules, and goes where the request's
+    /// slices are fetched from (see [`origin_pool`](Self::origin_pool)).
+    async fn cached_metadata(
+        &self,
+        uri: &str,
+        key: &str,
+        variant: Option<&CacheVariant>,
+        ctx: &SliceContext,
+    ) -> Result<FileMetadata> {
+        self.metadata_cache
+            .get_or_fetch(key, || async {
+                let metadata_fetcher = self
+                    .metadata_fetcher
+                    .clone()
+                    .with_request_headers(self.origin_headers(variant, ctx));
+                let Some(pool) = self.origin_pool(ctx) else {
+                    return metadata_fetcher.fetch_metadata(uri).await;
+                };
+                
+                // Try each peer of the pool at most once, within max_retries
+                let mut failed: Vec<usize> = Vec::new();
+                loop {
+                    let lease = pool.select_excluding(&failed).ok_or_else(|| {
+                        SliceError::InternalError("upstream pool has no peers".to_string())
+                    })?;
+                    if let Some(&previous) = failed.last() {
+                        self.metrics.record_upstream_failover(pool.address(previous), lease.address());
+                    }
+                    let result = metadata_fetcher
+                        .fetch_metadata(&rewrite_authority(uri, lease.address()))
+                        .await;
+                    let success = !matches!(&result, Err(e) if e.should_retry());
+                    self.metrics.record_upstream_request(lease.address(), success);
+                    if success {
+                        pool.record_success(lease.index());
+                        return result;
+                    }
+                    pool.record_failure(lease.index());
+                    failed.push(lease.index());
+                    if failed.len() >= pool.len() || failed.len() > self.config.max_retries {
+                        return result;
+                    }
+                }
+            })
+            .await
+    }
+    
+    /// How long past their TTL cached copies may stand in for a failing origin
+    fn stale_window(&self) -> Duration {
+        Duration::from_secs(self.config.stale_if_error_secs)
+    }
+    
+    /// Cached metadata to serve a request with after the origin failed to
+    /// provide it, if `stale_if_error_secs` allows
+    ///
+    /// Selects the request's cache variant when the stale metadata varies.
+    fn stale_metadata(
+        &self,
+        base_key: &str,
+        error: &SliceError,
+        headers: &HeaderMap<HeaderValue>,
+        ctx: &mut SliceContext,
+    ) -> Option<FileMetadata> {
+        if self.config.stale_if_error_secs == 0 || !error.is_origin_failure() {
+            return None;
+        }
+        if let Some(variant) = &ctx.cache_variant {
+            return self.metadata_cache.get_stale(&variant.key, self.stale_window());
+        }
+        let metadata = self.metadata_cache.get_stale(base_key, self.stale_window())?;
+        if metadata.vary.is_empty() {
+            return Some(metadata);
+        }
+        let variant = CacheVariant::from_vary(base_key, &metadata.vary, &self.config.vary_headers, headers)?;
+        let metadata = self.metadata_cache.get_stale(&variant.key, self.stale_window())?;
+        ctx.cache_variant = Some(variant);
+        Some(metadata)
+    }
+    
+    /// Whether the request asks to skip the cache with the `cache_bypass`
+    /// header, carrying the purge token if one is required
+    fn bypass_requested(&self, headers: &HeaderMap<HeaderValue>) -> bool {
+        let Some(bypass) = &self.config.cache_bypass else {
+            return false;
+        };
+        let requested = headers
+            .get(bypass.header.as_str())
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        if !requested || !bypass.require_token {
+            return requested;
+        }
+        let token = self.config.purge.as_ref().and_then(|p| p.auth_token.as_deref());
+        token.is_some_and(|token| headers_have_token(headers, token))
+    }
+    
+    /// Answer a HEAD request from the metadata cache
+    ///
+    /// The response carries the headers a GET would get, without a body and
+    /// without contacting the origin. Metadata that isn't cached, or a file
+    /// the origin won't serve in ranges, is left to the origin.
+    ///
+    /// # Returns
+    /// * `Ok(false)` - The request is answered by `handle_slice_request`
+    /// * `Ok(true)` - The request should be proxied to the origin
+    fn head_from_metadata(
+        &self,
+        uri: &str,
+        headers: &HeaderMap<HeaderValue>,
+        ctx: &mut SliceContext,
+    ) -> Result<bool> {
+        let base_key = ctx.cache_key(uri).to_string();
+        let cached = self.metadata_cache.get_with_age(&base_key).and_then(|(metadata, age)| {
+            if metadata.vary.is_empty() {
+                return Some((metadata, age, None));
+            }
+            let variant =
+                CacheVariant::from_vary(&base_key, &metadata.vary, &self.config.vary_headers, headers)?;
+            let (metadata, age) = self.metadata_cache.get_with_age(&variant.key)?;
+            Some((metadata, age, Some(variant)))
+        });
+        let Some((metadata, age, variant)) = cached.filter(|(metadata, ..)| metadata.supports_range) else {
+            debug!("No cached metadata for HEAD, proxying: uri={}", uri);
+            self.metrics.record_request(false);
+            return Ok(true);
+        };
+        
+        let analyzer = &self.analyzer;
+        let if_none_match = analyzer.extract_if_none_match(headers);
+        let if_modified_since = analyzer.extract_if_modified_since(headers);
+        ctx.not_modified = metadata.is_not_modified(if_none_match.as_deref(), if_modified_since.as_deref());
+        if !ctx.not_modified {
+            self.apply_if_range(analyzer, headers, &metadata, ctx);
+            if let Some(range) = ctx.client_range() {
+                if range.start >= metadata.content_length {
+                    self.metrics.record_request(false);
+                    return Err(SliceError::RangeNotSatisfiable { total_size: metadata.content_length });
+                }
+                if range.end >= metadata.content_length {
+                    ctx.set_client_range_opt(ByteRange::new(range.start, metadata.content_length - 1).ok());
+                }
+            }
+        }
+        
+        info!("Answering HEAD from cached metadata: uri={}, age={:?}", uri, age);
+        ctx.cache_variant = variant;
+        ctx.head_age = Some(age);
+        ctx.set_metadata(metadata);
+        ctx.enable_slicing();
+        self.metrics.record_request(true);
+        Ok(false)
+    }
+    
+    /// Apply the client's If-Range precondition to the requested range
+    ///
+    /// When the request carries both a Range and an If-Range header, the range
+    /// is kept only if the validator matches the current ETag/Last-Modified.
+    /// Otherwise the range is dropped so the full file is served with 200, as
+    /// required by RFC 7233.
+    fn apply_if_range(
+        &self,
+        analyzer: &RequestAnalyzer,
+        headers: &HeaderMap<HeaderValue>,
+        metadata: &FileMetadata,
+        ctx: &mut SliceContext,
+    ) {
+        if ctx.client_range().is_none() {
+            return;
+        }
+        
+        if let Some(if_range) = analyzer.extract_if_range(headers) {
+            if metadata.matches_if_range(&if_range) {
+                debug!("If-Range validator matches, serving requested range: {}", if_range);
+            } else {
+                info!(
+                    "If-Range validator does not match (if_range={}, etag={:?}, last_modified={:?}), serving full file",
+                    if_range, metadata.etag, metadata.last_modified
+                );
+                ctx.set_client_range_opt(None);
+            }
+        }
+    }
+    
     /// Get the upstream peer for normal proxy mode
     ///
     /// This method returns the upstream server configuration when slicing is not enabled.
     /// It is called by Pingora when the request_filter returns true (normal proxy mode).
+    /// Requests routed to another cluster node go to that peer instead.
     ///
     /// # Arguments
     /// * `ctx` - The request context
     ///
     /// # Returns
-    /// * `Ok(String)` - The upstream server address
+    /// * `Ok(String)` - The upstream server, pooled upstream or cluster peer address
     /// * `Err(SliceError)` - If slicing is enabled (this method shouldn't be called)
     ///
     /// # Requirements
@@ -575,10 +1722,180 @@ impl SliceProxy {
             ));
         }
         
+        if let Some(peer) = &ctx.cluster_peer {
+            debug!("Returning cluster peer: {}", peer);
+            return Ok(peer.clone());
+        }
+        
+        if let Some(peer) = self.upstreams.as_ref().and_then(|pool| pool.select()) {
+            debug!("Returning pooled upstream peer: {}", peer.address());
+            return Ok(peer.address().to_string());
+        }
+        
         debug!("Returning upstream peer: {}", self.config.upstream_address);
         Ok(self.config.upstream_address.clone())
     }
     
+    /// Adjust the request headers sent upstream in normal proxy mode
+    ///
+    /// Mirrors Pingora's `upstream_request_filter`. Requests forwarded to a
+    /// cluster peer are marked with `X-Slice-Cluster-Hop` so the peer serves
+    /// them itself instead of forwarding them again. Requests to the origin
+    /// get the upstream request header rules; the peer applies its own.
+    /// Both carry the request's forwarding headers, so a peer in append mode
+    /// extends them instead of recording this node as the client.
+    /// Fails with [`SliceError::DeadlineExceeded`] once the request's
+    /// deadline has passed.
+    ///
+    /// # Arguments
+    /// * `headers` - Headers of the upstream request
+    /// * `ctx` - The request context
+    pub fn upstream_request_filter(
+        &self,
+        headers: &mut HeaderMap<HeaderValue>,
+        ctx: &SliceContext,
+    ) -> Result<()> {
+        ctx.check_deadline()?;
+        headers.extend(ctx.forwarded_headers.clone());
+        if let (Some(_), Some(cluster)) = (&ctx.cluster_peer, &self.cluster) {
+            let hop = HeaderValue::from_str(cluster.self_peer()).map_err(|e| {
+                SliceError::InternalError(format!("Invalid cluster.self header value: {}", e))
+            })?;
+            headers.insert(CLUSTER_HOP_HEADER, hop);
+        } else {
+            self.header_rewriter.rewrite_request(headers);
+        }
+        Ok(())
+    }
+    
+    /// Adjust the response headers received from upstream in normal proxy mode
+    ///
+    /// Mirrors Pingora's `upstream_response_filter`, which runs before the
+    /// response is cached or sent on: applies the response header rules.
+    /// Responses relayed from a cluster peer already had them applied there.
+    /// Responses proxied here get `X-Cache-Status: BYPASS`, and misses sent
+    /// here by the fill limits are also tagged `X-Cache: BYPASS-BUSY`, or
+    /// `X-Cache: BYPASS-TOO-LARGE` when over `max_request_fill_bytes`.
+    /// Requests matching a `bypass` cache mode rule or sending the
+    /// `cache_bypass` header are tagged `X-Cache: BYPASS`.
+    ///
+    /// # Arguments
+    /// * `headers` - Headers of the upstream response
+    /// * `ctx` - The request context
+    pub fn upstream_response_filter(&self, headers: &mut HeaderMap<HeaderValue>, ctx: &SliceContext) {
+        if ctx.cluster_peer.is_none() {
+            headers.insert("x-cache-status", HeaderValue::from_static(CacheStatus::Bypass.header_value()));
+            self.header_rewriter.rewrite_response(headers);
+        }
+        if ctx.fill_bypassed {
+            headers.insert("x-cache", HeaderValue::from_static("BYPASS-BUSY"));
+        } else if ctx.fill_too_large {
+            headers.insert("x-cache", HeaderValue::from_static("BYPASS-TOO-LARGE"));
+        } else if ctx.cache_mode == CacheMode::Bypass || ctx.cache_bypassed {
+            headers.insert("x-cache", HeaderValue::from_static("BYPASS"));
+        }
+    }
+    
+    /// Pace a response body chunk to the client's download limit
+    ///
+    /// Mirrors Pingora's `response_body_filter`: the returned duration is how
+    /// long to wait before sending the next chunk. Large slices should be
+    /// split with [`ClientPacer::split`] first so the delays stay smooth.
+    /// Fails with [`SliceError::DeadlineExceeded`] once the request's
+    /// deadline has passed; the body should then be cut off.
+    ///
+    /// # Arguments
+    /// * `body` - Chunk about to be sent to the client
+    /// * `end_of_stream` - Whether this is the last chunk
+    /// * `ctx` - The request context
+    pub fn response_body_filter(
+        &self,
+        body: &Option<Bytes>,
+        end_of_stream: bool,
+        ctx: &mut SliceContext,
+    ) -> Result<Option<Duration>> {
+        ctx.check_deadline()?;
+        let (Some(pacer), Some(chunk)) = (ctx.pacer.as_mut(), body) else {
+            return Ok(None);
+        };
+        let delay = pacer.on_chunk(chunk.len());
+        // Nothing follows the last chunk, so there is nothing to hold back
+        Ok(if end_of_stream { None } else { delay })
+    }
+    
+    /// Build the response for a request that failed before anything was sent
+    ///
+    /// Called when slicing or the proxied request fails, e.g. the origin is
+    /// down and nothing is cached. Rate-limited requests get a `429` with
+    /// `Retry-After`. If `error_pages` has a page for the error's status, it
+    /// is returned with the response header rules applied. Requests past
+    /// their deadline otherwise get a `504` with a JSON body naming the
+    /// error, and unsatisfiable ranges a `416` with `Content-Range:
+    /// bytes */<size>`; anything else gets `None`, and the caller sends its
+    /// default error response.
+    ///
+    /// # Arguments
+    /// * `error` - Why the request failed
+    /// * `ctx` - The request context
+    pub fn fail_to_proxy(
+        &self,
+        error: &SliceError,
+        ctx: &mut SliceContext,
+    ) -> Option<(http::StatusCode, HeaderMap, Bytes)> {
+        // Throttled clients are told when to come back
+        if let SliceError::RateLimited { retry_after_secs } = error {
+            let mut headers = HeaderMap::new();
+            headers.insert("retry-after", HeaderValue::from(*retry_after_secs));
+            headers.insert("content-length", HeaderValue::from_static("0"));
+            self.header_rewriter.rewrite_response(&mut headers);
+            ctx.set_response(429, 0);
+            return Some((http::StatusCode::TOO_MANY_REQUESTS, headers, Bytes::new()));
+        }
+        
+        let deadline_passed = matches!(error, SliceError::DeadlineExceeded(_));
+        if deadline_passed {
+            self.metrics.record_deadline_abort();
+        }
+        
+        // An unsatisfiable range is answered with the file's size
+        let content_range = match error {
+            SliceError::RangeNotSatisfiable { total_size } => Some(
+                HeaderValue::try_from(format!("bytes */{}", total_size)).expect("digits are a valid header value"),
+            ),
+            _ => None,
+        };
+        
+        let Some((status, mut headers, body)) = self.error_pages.response(error.to_http_status()) else {
+            if let Some(content_range) = content_range {
+                let mut headers = HeaderMap::new();
+                headers.insert("content-range", content_range);
+                headers.insert("content-length", HeaderValue::from_static("0"));
+                self.header_rewriter.rewrite_response(&mut headers);
+                ctx.set_response(416, 0);
+                return Some((http::StatusCode::RANGE_NOT_SATISFIABLE, headers, Bytes::new()));
+            }
+            if !deadline_passed {
+                return None;
+            }
+            let body = Bytes::from(
+                serde_json::json!({ "error": error.error_type(), "message": error.to_string() }).to_string(),
+            );
+            let mut headers = HeaderMap::new();
+            headers.insert("content-type", HeaderValue::from_static("application/json"));
+            headers.insert("content-length", HeaderValue::from(body.len()));
+            self.header_rewriter.rewrite_response(&mut headers);
+            ctx.set_response(504, body.len() as u64);
+            return Some((http::StatusCode::GATEWAY_TIMEOUT, headers, body));
+        };
+        if let Some(content_range) = content_range {
+            headers.insert("content-range", content_range);
+        }
+        self.header_rewriter.rewrite_response(&mut headers);
+        info!("Serving error page: status={}, error={}", status, error);
+        ctx.set_response(status.as_u16(), body.len() as u64);
+        Some((status, headers, body))
+    }
+    
     /// Log request completion information
     ///
     /// This method logs detailed information about the request processing, including:
@@ -638,7 +1955,67 @@ impl SliceProxy {
                 duration_ms
             );
         }
+        
+        // Downloaded bytes count against the client's bandwidth budget
+        if let (Some(limiter), Some(client)) = (&self.client_limiter, ctx.client_addr.as_deref()) {
+            limiter.record_bytes(client, ctx.bytes_sent);
+        }
+        
+        if let Some(logger) = &self.access_logger {
+            logger.log(Self::access_record(method, uri, ctx, error, duration_ms));
+        }
     }
+    
+    /// Build the access log record for a completed request
+    ///
+    /// When no response status was recorded in the context, it is derived
+    /// from the error, or from whether the client asked for a range.
+    pub fn access_record(
+        method: &Method,
+        uri: &str,
+        ctx: &SliceContext,
+        error: Option<&SliceError>,
+        duration_ms: u64,
+    ) -> AccessRecord {
+        let status = ctx.response_status.unwrap_or_else(|| match error {
+            Some(err) => err.to_http_status(),
+            None if ctx.client_range().is_some() => 206,
+            None => 200,
+        });
+        let cache_status = ctx.is_slice_enabled().then(|| ctx.cache_status());
+        
+        AccessRecord {
+            timestamp: rfc3339_now(),
+            client_addr: ctx.client_addr.clone(),
+            method: method.to_string(),
+            url: uri.to_string(),
+            status,
+            bytes_sent: ctx.bytes_sent,
+            cache_status,
+            slices_total: ctx.slice_count(),
+            slices_cached: ctx.cached_slice_count(),
+            upstream_time_ms: ctx.upstream_time.map(|d| d.as_millis() as u64),
+            total_time_ms: duration_ms,
+            error_type: error.map(|e| e.error_type().to_string()),
+        }
+    }
+}
+
+/// Whether a slice response indicates the origin object differs from `metadata`
+///
+/// Compares the total size from Content-Range and the ETag, when present.
+fn origin_changed(metadata: &FileMetadata, headers: &HeaderMap) -> bool {
+    let total = headers
+        .get("content-range")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit_once('/'))
+        .and_then(|(_, total)| total.trim().parse::<u64>().ok());
+    if total.is_some_and(|total| total != metadata.content_length) {
+        return true;
+    }
+
+    let etag = headers.get("etag").and_then(|v| v.to_str().ok());
+    matches!((etag, metadata.etag.as_deref()), (Some(a), Some(b)) if a != b)
 }
 
 impl SliceContext {
@@ -732,6 +2109,55 @@ impl SliceContext {
         &mut self.slices
     }
     
+    /// Set the cache TTL for slices of this request
+    ///
+    /// # Arguments
+    /// * `ttl` - Time-to-live for stored slices
+    pub fn set_cache_ttl(&mut self, ttl: Duration) {
+        self.cache_ttl = Some(ttl);
+    }
+    
+    /// Get the cache TTL for slices of this request
+    ///
+    /// # Returns
+    /// The TTL chosen by the matched pattern rule, or None to use the global TTL
+    pub fn cache_ttl(&self) -> Option<Duration> {
+        self.cache_ttl
+    }
+    
+    /// Key the slices of `url` are cached under for this request
+    ///
+    /// This is the normalized URL, extended with the request's variant when
+    /// the origin varies its response.
+    pub fn cache_key<'a>(&'a self, url: &'a str) -> &'a str {
+        self.cache_variant
+            .as_ref()
+            .map(|v| v.key.as_str())
+            .or(self.normalized_key.as_deref())
+            .unwrap_or(url)
+    }
+    
+    /// Fail with [`SliceError::DeadlineExceeded`] once the request's
+    /// deadline has passed
+    pub fn check_deadline(&self) -> Result<()> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(SliceError::DeadlineExceeded(
+                "request_timeout_secs elapsed".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Record the response sent to the client
+    ///
+    /// # Arguments
+    /// * `status` - Status code of the response
+    /// * `bytes_sent` - Number of body bytes sent
+    pub fn set_response(&mut self, status: u16, bytes_sent: u64) {
+        self.response_status = Some(status);
+        self.bytes_sent = bytes_sent;
+    }
+    
     /// Get the number of slices
     ///
     /// # Returns
@@ -740,28 +2166,522 @@ impl SliceContext {
         self.slices.len()
     }
     
-    /// Check if there are any slices
-    ///
-    /// # Returns
-    /// true if there are slices, false otherwise
-    pub fn has_slices(&self) -> bool {
-        !self.slices.is_empty()
+    /// Check if there are any slices
+    ///
+    /// # Returns
+    /// true if there are slices, false otherwise
+    pub fn has_slices(&self) -> bool {
+        !self.slices.is_empty()
+    }
+    
+    /// Get the number of cached slices
+    ///
+    /// # Returns
+    /// The number of slices that are marked as cached
+    pub fn cached_slice_count(&self) -> usize {
+        self.slices.iter().filter(|s| s.cached).count()
+    }
+    
+    /// Get the number of uncached slices
+    ///
+    /// # Returns
+    /// The number of slices that need to be fetched from origin
+    pub fn uncached_slice_count(&self) -> usize {
+        self.slices.iter().filter(|s| !s.cached).count()
+    }
+
+    /// Get the cache status of a sliced request
+    ///
+    /// # Returns
+    /// `Stale` when expired copies stood in for a failing origin, `Expired`
+    /// when a slice is refetched because its cached copy expired, otherwise
+    /// the status derived from the slice counts
+    pub fn cache_status(&self) -> CacheStatus {
+        if self.head_age.is_some() {
+            CacheStatus::Hit
+        } else if self.cache_bypassed {
+            CacheStatus::Bypass
+        } else if self.served_stale {
+            CacheStatus::Stale
+        } else if self.cache_expired {
+            CacheStatus::Expired
+        } else {
+            CacheStatus::from_counts(self.slice_count(), self.cached_slice_count())
+        }
+    }
+}
+
+/// Body of a sliced response, produced one slice at a time in index order
+///
+/// Cached slices are read when their turn comes, and slices fetched from
+/// origin are held only until the slices before them have been produced,
+/// so a large response is never assembled in memory whole. A cached slice
+/// that is gone by the time it is read is fetched from origin instead.
+/// Dropping the body cancels the subrequests still in flight.
+pub struct SliceBody {
+    url: String,
+    cache_key: String,
+    metadata: Option<FileMetadata>,
+    /// Slices not produced yet, in index order
+    slices: VecDeque<SliceSpec>,
+    /// Fetched slices waiting for the slices before them
+    ready: BTreeMap<usize, Bytes>,
+    /// Slices requested from origin and not received yet
+    outstanding: BTreeMap<usize, SliceSpec>,
+    /// Slices arriving from origin, while a streamed fetch is running
+    fetches: Option<mpsc::UnboundedReceiver<Result<SubrequestResult>>>,
+    /// Whether fetches in flight complete after the client aborts
+    finish_in_flight: bool,
+    manager: SubrequestManager,
+    cache: Arc<TieredCache>,
+    cache_policy: Arc<CachePolicy>,
+    metrics: Arc<SliceMetrics>,
+    metadata_cache: Arc<MetadataCache>,
+    /// Whether fetched slices may be stored at all
+    cache_fills: bool,
+    /// TTL of fetched slices whose response does not set one
+    ttl: Duration,
+    /// Whether the first slice from origin may be cached
+    admission: Option<Admission>,
+    /// Age of the oldest expired slice standing in for the origin, if any
+    stale_age: Option<Duration>,
+    abort: AbortSignal,
+    /// Bytes to leave out before the client's range, then bytes left to produce
+    skip: u64,
+    remaining: u64,
+    produced: u64,
+    pattern_label: Option<String>,
+    /// Request span the body's work is recorded under
+    span: tracing::Span,
+    /// Open until the body is complete
+    assemble_span: Option<tracing::Span>,
+    started: Instant,
+    assembly_started: Instant,
+    fetch_started: Option<Instant>,
+    fetched: usize,
+    done: bool,
+    /// Keeps the request's fill budget reserved while slices are buffered
+    fill_guard: Option<Arc<FillGuard>>,
+    /// Budget for slices a whole-object GET buffers beyond `fill_guard`
+    fill_limiter: Arc<FillLimiter>,
+    fallback_guard: Option<FillGuard>,
+    /// Most bytes a whole-object fallback may read
+    whole_object_max_bytes: Option<u64>,
+}
+
+impl SliceBody {
+    /// Body producing every slice of `ctx`, none fetched yet
+    fn new(proxy: &SliceProxy, url: &str, ctx: &SliceContext, started: Instant, span: tracing::Span) -> Self {
+        let (skip, remaining) = match (ctx.client_range(), ctx.slices().first()) {
+            (Some(range), Some(first)) => (range.start.saturating_sub(first.range.start), range.size()),
+            _ => (0, ctx.slices().iter().map(|slice| slice.range.size()).sum()),
+        };
+        SliceBody {
+            url: url.to_string(),
+            cache_key: ctx.cache_key(url).to_string(),
+            metadata: ctx.metadata().cloned(),
+            slices: ctx.slices().iter().cloned().collect(),
+            ready: BTreeMap::new(),
+            outstanding: BTreeMap::new(),
+            fetches: None,
+            finish_in_flight: proxy.config.on_client_abort != ClientAbortPolicy::Abort,
+            manager: proxy.subrequest_manager(proxy.config.max_concurrent_subrequests, ctx),
+            cache: proxy.cache.clone(),
+            cache_policy: proxy.cache_policy.clone(),
+            metrics: proxy.metrics_arc(),
+            metadata_cache: proxy.metadata_cache.clone(),
+            cache_fills: proxy.config.enable_cache,
+            ttl: proxy.slice_ttl(ctx),
+            admission: None,
+            stale_age: None,
+            abort: ctx.client_abort.clone(),
+            skip,
+            remaining,
+            produced: 0,
+            pattern_label: ctx.pattern_label.clone(),
+            span,
+            assemble_span: None,
+            started,
+            assembly_started: Instant::now(),
+            fetch_started: None,
+            fetched: 0,
+            done: false,
+            fill_guard: ctx.fill_guard.clone(),
+            fill_limiter: proxy.fill_limiter.clone(),
+            fallback_guard: None,
+            whole_object_max_bytes: proxy.config.whole_object_fallback_max_bytes,
+        }
+    }
+    
+    /// Body of a response without one, such as a 304 or a HEAD
+    fn empty(proxy: &SliceProxy, url: &str, ctx: &SliceContext, span: tracing::Span) -> Self {
+        let mut body = Self::new(proxy, url, ctx, Instant::now(), span);
+        body.slices.clear();
+        body.remaining = 0;
+        body.done = true;
+        body
+    }
+    
+    /// Bytes the body has yet to produce
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+    
+    /// Whether expired cached slices stand in for a failing origin
+    pub fn served_stale(&self) -> bool {
+        self.stale_age.is_some()
+    }
+    
+    /// Next slice of the body, cut to the client's range, or `None` once
+    /// the body is complete
+    ///
+    /// Returns [`SliceError::ClientAborted`] if the request's abort signal
+    /// is raised while waiting for origin; pass the body to
+    /// [`SliceProxy::abort_slice_body`] to apply the `on_client_abort`
+    /// policy.
+    pub async fn next_slice(&mut self) -> Option<Result<Bytes>> {
+        let span = self.span.clone();
+        self.produce().instrument(span).await.transpose()
+    }
+    
+    async fn produce(&mut self) -> Result<Option<Bytes>> {
+        while let Some(slice) = self.slices.front().cloned() {
+            let data = if let Some(data) = self.ready.remove(&slice.index) {
+                data
+            } else if self.outstanding.contains_key(&slice.index) {
+                self.receive().await?;
+                continue;
+            } else if slice.cached {
+                self.read_cached(&slice).await?
+            } else {
+                return Err(SliceError::AssemblyError(format!(
+                    "Slice {} was neither cached nor fetched",
+                    slice.index
+                )));
+            };
+            self.slices.pop_front();
+            
+            // The first byte of the response is available once slice 0 is in hand
+            if slice.index == 0 {
+                self.metrics.record_ttfb(self.started.elapsed());
+            }
+            
+            // Cut the aligned slices down to the bytes the client asked for
+            let skip = self.skip.min(data.len() as u64) as usize;
+            self.skip -= skip as u64;
+            let end = (skip as u64 + self.remaining).min(data.len() as u64) as usize;
+            let part = data.slice(skip..end);
+            if part.is_empty() {
+                continue;
+            }
+            self.remaining -= part.len() as u64;
+            self.produced += part.len() as u64;
+            self.metrics.record_bytes_to_client(part.len() as u64);
+            if let Some(label) = &self.pattern_label {
+                self.metrics.record_pattern_bytes_to_client(label, part.len() as u64);
+            }
+            return Ok(Some(part));
+        }
+        self.finish();
+        Ok(None)
+    }
+    
+    /// Start fetching `slices` from origin
+    ///
+    /// In whole mode the object is fetched in one GET and cut into the
+    /// slices as it arrives; otherwise the slices are fetched concurrently.
+    /// Either way they are received as they complete.
+    fn start_fetch(&mut self, slices: Vec<SliceSpec>, whole: bool, finish_in_flight: bool) {
+        self.fetch_started = Some(Instant::now());
+        self.finish_in_flight = finish_in_flight;
+        self.outstanding = slices.iter().map(|slice| (slice.index, slice.clone())).collect();
+        self.fetches = Some(if whole {
+            self.manager
+                .fetch_whole_object_stream_until_abort(slices, &self.url, &self.abort, finish_in_flight)
+        } else {
+            self.manager
+                .fetch_slices_stream_until_abort(slices, &self.url, &self.abort, finish_in_flight)
+        });
+    }
+    
+    /// Wait for the next slice from origin, if any are outstanding
+    async fn receive(&mut self) -> Result<()> {
+        loop {
+            if self.outstanding.is_empty() {
+                return Ok(());
+            }
+            let received = match self.fetches.as_mut() {
+                // The fetch ends early once the client is gone, so check for
+                // that first
+                Some(fetches) => tokio::select! {
+                    biased;
+                    _ = self.abort.aborted() => return Err(SliceError::ClientAborted),
+                    received = fetches.recv() => received,
+                },
+                None => None,
+            };
+            match received {
+                Some(Ok(result)) => {
+                    self.accept(result);
+                    return Ok(());
+                }
+                // Range requests keep failing: fetch the rest of the object
+                // in one GET and slice it here
+                Some(Err(SliceError::SliceFailureLimit { failures })) => self.fall_back_to_whole(failures)?,
+                Some(Err(e)) => return Err(self.fail(e)),
+                None => {
+                    return Err(self.fail(SliceError::AssemblyError(format!(
+                        "Origin fetch ended without {} slices",
+                        self.outstanding.len()
+                    ))))
+                }
+            }
+        }
+    }
+    
+    /// Fetch the outstanding slices with one plain GET after `failures`
+    /// failed range requests
+    ///
+    /// Refused, failing the request, when the GET would read more than
+    /// `whole_object_fallback_max_bytes` or its slices do not fit in the
+    /// fill budget.
+    fn fall_back_to_whole(&mut self, failures: usize) -> Result<()> {
+        self.fetches = None;
+        let read = self.outstanding.values().map(|slice| slice.range.end + 1).max().unwrap_or(0);
+        if self.whole_object_max_bytes.is_some_and(|max| read > max) {
+            warn!(
+                "{} slice fetches failed, not falling back to a whole-object GET of {} bytes: url={}",
+                failures, read, self.url
+            );
+            return Err(self.fail(SliceError::SliceFailureLimit { failures }));
+        }
+        if !self.charge_whole_fetch() {
+            warn!(
+                "{} slice fetches failed, no fill budget for a whole-object GET: url={}",
+                failures, self.url
+            );
+            return Err(self.fail(SliceError::SliceFailureLimit { failures }));
+        }
+        warn!(
+            "{} slice fetches failed, falling back to a whole-object GET: url={}",
+            failures, self.url
+        );
+        self.metrics.record_whole_object_fallback();
+        let slices = self.outstanding.values().cloned().collect();
+        self.fetches = Some(self.manager.fetch_whole_object_stream_until_abort(
+            slices,
+            &self.url,
+            &self.abort,
+            self.finish_in_flight,
+        ));
+        Ok(())
+    }
+    
+    /// Reserve fill budget for the slices a whole-object GET will buffer
+    ///
+    /// The request's own reservation covers the slices it fetches; anything
+    /// beyond it is charged to the fill limiter here.
+    fn charge_whole_fetch(&mut self) -> bool {
+        let bytes: u64 = self.outstanding.values().map(|slice| slice.range.size()).sum();
+        let reserved = self.fill_guard.as_ref().map_or(0, |guard| guard.bytes());
+        if bytes <= reserved {
+            return true;
+        }
+        match self.fill_limiter.try_acquire(bytes - reserved) {
+            Some(guard) => {
+                self.fallback_guard = Some(guard);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Read a cached slice, or fetch it from origin if it is gone
+    async fn read_cached(&mut self, slice: &SliceSpec) -> Result<Bytes> {
+        let span = tracing::info_span!(
+            "cache_lookup",
+            url = %self.url,
+            cache_tier = "memory",
+            slice_index = slice.index,
+            range = %slice.range.to_header(),
+            bytes = tracing::field::Empty,
+        );
+        let lookup = self
+            .cache
+            .lookup_with_age(&self.cache_key, &slice.range)
+            .instrument(span.clone())
+            .await;
+        match lookup {
+            Ok(Some((data, _))) => {
+                span.record("bytes", data.len());
+                debug!(
+                    "Retrieved cached slice {}: range={}-{}, size={}",
+                    slice.index, slice.range.start, slice.range.end, data.len()
+                );
+                self.metrics.record_bytes_from_cache(data.len() as u64);
+                return Ok(data);
+            }
+            // Evicted or expired since the hit check
+            Ok(None) => debug!("Cached slice {} was evicted, fetching it from origin", slice.index),
+            Err(e) => {
+                warn!("Error retrieving cached slice {}, fetching it from origin: {:?}", slice.index, e);
+                self.metrics.record_cache_error();
+            }
+        }
+        
+        self.outstanding.insert(slice.index, slice.clone());
+        match self.manager.fetch_single_slice(slice, &self.url).await {
+            Ok(result) => {
+                self.accept(result);
+                Ok(self.ready.remove(&slice.index).unwrap_or_default())
+            }
+            Err(e) => Err(self.fail(e)),
+        }
+    }
+    
+    /// Produce `slices` from their expired cached copies instead of origin
+    ///
+    /// # Returns
+    /// `false`, leaving the body as it was, unless every slice has a copy
+    /// that expired less than `max_stale` ago
+    fn serve_stale(&mut self, slices: &[SliceSpec], max_stale: Duration) -> bool {
+        let mut stale = Vec::with_capacity(slices.len());
+        for slice in slices {
+            match self.cache.lookup_stale(&self.cache_key, &slice.range, max_stale) {
+                Some(copy) => stale.push((slice.index, copy)),
+                None => return false,
+            }
+        }
+        self.fetches = None;
+        self.outstanding.clear();
+        for (idx, (data, age)) in stale {
+            self.metrics.record_bytes_from_cache(data.len() as u64);
+            self.stale_age = self.stale_age.max(Some(age));
+            self.ready.insert(idx, data);
+        }
+        true
+    }
+    
+    /// Take a slice fetched from origin, storing it in cache (Requirements 7.1, 7.5)
+    fn accept(&mut self, result: SubrequestResult) {
+        let idx = result.slice_index;
+        let Some(slice_spec) = self.outstanding.remove(&idx) else {
+            warn!("Ignoring unexpected slice {} from origin", idx);
+            return;
+        };
+        debug!("Adding fetched slice {}: size={}", idx, result.data.len());
+        self.fetched += 1;
+        self.metrics.record_subrequest(true);
+        self.metrics.record_bytes_from_origin(result.data.len() as u64);
+        
+        // Drop cached metadata if the origin object changed underneath us
+        if self.metadata.as_ref().is_some_and(|metadata| origin_changed(metadata, &result.headers)) {
+            warn!(
+                "Slice response does not match cached metadata, invalidating: url={}",
+                self.url
+            );
+            self.metadata_cache.invalidate(&self.cache_key);
+        }
+        
+        // Per-user responses, and statuses left out of cache_admission,
+        // are served but never cached
+        let admission = self.cache_policy.admit_fill(std::slice::from_ref(&result));
+        self.admission.get_or_insert(admission);
+        if self.cache_fills && admission.is_cached() {
+            let span = tracing::info_span!(
+                "cache_store",
+                url = %self.url,
+                cache_tier = "memory",
+                slice_index = idx,
+                range = %slice_spec.range.to_header(),
+                bytes = result.data.len(),
+            );
+            let ttl = admission.ttl().unwrap_or(self.ttl);
+            let store = span.in_scope(|| {
+                self.cache.store_with_ttl(&self.cache_key, &slice_spec.range, result.data.clone(), ttl)
+            });
+            match store {
+                Ok(()) => {
+                    debug!(
+                        "Stored slice {} in cache: range={}-{}",
+                        idx, slice_spec.range.start, slice_spec.range.end
+                    );
+                }
+                Err(e) => {
+                    // Continue processing even if cache storage fails
+                    warn!("Failed to store slice {} in cache: {:?}", idx, e);
+                    self.metrics.record_cache_error();
+                }
+            }
+        }
+        self.ready.insert(idx, result.data);
+        
+        if self.outstanding.is_empty() {
+            if let Some(fetch_started) = self.fetch_started.take() {
+                let fetch_duration = fetch_started.elapsed();
+                info!("Successfully fetched {} slices in {:?}", self.fetched, fetch_duration);
+                self.metrics.record_subrequest_duration(fetch_duration);
+            }
+            self.fetches = None;
+        }
+    }
+    
+    /// Give up on the slices still outstanding after `e`
+    fn fail(&mut self, e: SliceError) -> SliceError {
+        warn!("Failed to fetch slices: {:?}", e);
+        self.fetches = None;
+        for _ in std::mem::take(&mut self.outstanding) {
+            self.metrics.record_subrequest(false);
+        }
+        // A slice failing every attempt may mean the object changed
+        // size; probe the origin again on the next request
+        if matches!(e, SliceError::SubrequestFailed { .. }) {
+            self.metadata_cache.expire(&self.cache_key);
+        }
+        e
     }
     
-    /// Get the number of cached slices
-    ///
-    /// # Returns
-    /// The number of slices that are marked as cached
-    pub fn cached_slice_count(&self) -> usize {
-        self.slices.iter().filter(|s| s.cached).count()
+    /// Stop fetching after the client went away, collecting the slices
+    /// still delivered and those never fetched
+    async fn interrupt(mut self) -> InterruptedFetch {
+        self.abort.abort();
+        self.done = true;
+        let mut fetch = InterruptedFetch {
+            aborted: true,
+            ..Default::default()
+        };
+        // The fetch stops starting subrequests and, unless the policy lets
+        // them finish, cancels those in flight
+        if let Some(mut fetches) = self.fetches.take() {
+            while let Some(received) = fetches.recv().await {
+                if let Ok(result) = received {
+                    if self.outstanding.remove(&result.slice_index).is_some() {
+                        fetch.results.push(result);
+                    }
+                }
+            }
+        }
+        fetch.results.sort_by_key(|r| r.slice_index);
+        fetch.remaining = std::mem::take(&mut self.outstanding).into_values().collect();
+        fetch
     }
     
-    /// Get the number of uncached slices
-    ///
-    /// # Returns
-    /// The number of slices that need to be fetched from origin
-    pub fn uncached_slice_count(&self) -> usize {
-        self.slices.iter().filter(|s| !s.cached).count()
+    /// Record the request's metrics once every slice is produced
+    /// (Requirements 9.1, 9.2)
+    fn finish(&mut self) {
+        if std::mem::replace(&mut self.done, true) {
+            return;
+        }
+        if let Some(span) = self.assemble_span.take() {
+            span.record("bytes", self.produced);
+        }
+        self.metrics.record_assembly_duration(self.assembly_started.elapsed());
+        let total_duration = self.started.elapsed();
+        self.metrics.record_request_duration(total_duration);
+        info!(
+            "Slice request completed: url={}, total_bytes={}, duration={:?}",
+            self.url, self.produced, total_duration
+        );
     }
 }
 
@@ -1032,6 +2952,61 @@ mod tests {
         );
     }
     
+    #[test]
+    fn test_access_record_from_context() {
+        let mut ctx = SliceContext::new();
+        ctx.enable_slicing();
+        let mut cached = SliceSpec::new(0, ByteRange::new(0, 1023).unwrap());
+        cached.cached = true;
+        let uncached = SliceSpec::new(1, ByteRange::new(1024, 2047).unwrap());
+        ctx.set_slices(vec![cached, uncached]);
+        ctx.client_addr = Some("192.0.2.1".to_string());
+        ctx.upstream_time = Some(Duration::from_millis(15));
+        ctx.set_response(200, 2048);
+        
+        let record = SliceProxy::access_record(&Method::GET, "/file.bin", &ctx, None, 40);
+        assert_eq!(record.status, 200);
+        assert_eq!(record.bytes_sent, 2048);
+        assert_eq!(record.cache_status, Some(CacheStatus::Partial));
+        assert_eq!((record.slices_total, record.slices_cached), (2, 1));
+        assert_eq!(record.upstream_time_ms, Some(15));
+        assert_eq!(record.client_addr.as_deref(), Some("192.0.2.1"));
+        
+        let error = SliceError::Timeout("origin".to_string());
+        let record = SliceProxy::access_record(&Method::GET, "/x", &SliceContext::new(), Some(&error), 5);
+        assert_eq!(record.status, 504);
+        assert_eq!(record.cache_status, None);
+        assert_eq!(record.error_type.as_deref(), Some("timeout"));
+    }
+    
+    #[tokio::test]
+    async fn test_logging_writes_access_log() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("access.log");
+        let logger = Arc::new(AccessLogger::from_config(&crate::AccessLogConfig {
+            enabled: true,
+            path: Some(path.to_string_lossy().into_owned()),
+            format: crate::AccessLogFormat::Json,
+            buffer_size: 16,
+        }).unwrap());
+        let proxy = SliceProxy::new(Arc::new(SliceConfig::default()))
+            .with_access_logger(logger.clone());
+        
+        proxy.logging(&Method::GET, "/a.bin", &SliceContext::new(), None, 10);
+        proxy.logging(&Method::HEAD, "/b.bin", &SliceContext::new(), None, 3);
+        logger.flush().await;
+        
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["url"], "/a.bin");
+        assert_eq!(lines[1]["method"], "HEAD");
+        assert_eq!(lines[1]["total_time_ms"], 3);
+    }
+    
     #[test]
     fn test_logging_with_error() {
         let config = Arc::new(SliceConfig::default());
@@ -1049,6 +3024,36 @@ mod tests {
             50,
         );
     }
+    
+    #[tokio::test]
+    async fn test_expiry_sweep_reclaims_unread_entries() {
+        let config = Arc::new(SliceConfig {
+            cache_sweep_interval_secs: Some(1),
+            ..Default::default()
+        });
+        let proxy = SliceProxy::new(config);
+        let cache = proxy.cache_arc();
+        for start in [0, 1024, 2048] {
+            let range = ByteRange::new(start, start + 1023).unwrap();
+            cache
+                .store_with_ttl("http://example.com/a.bin", &range, Bytes::from(vec![0; 1024]), Duration::from_millis(100))
+                .unwrap();
+        }
+        assert_eq!(cache.get_stats().l1_bytes, 3072);
+        
+        // The entries are never looked up again; only the sweep removes them
+        let sweep = proxy.spawn_expiry_sweep().unwrap();
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        let stats = cache.get_stats();
+        assert_eq!((stats.l1_entries, stats.l1_bytes), (0, 0));
+        let metrics = proxy.metrics().get_stats();
+        assert_eq!(metrics.expiry_sweeps, 1);
+        assert_eq!((metrics.expired_entries_swept, metrics.last_sweep_entries), (3, 3));
+        
+        sweep.abort();
+        assert!(sweep.await.unwrap_err().is_cancelled());
+        assert!(SliceProxy::new(Arc::new(SliceConfig::default())).spawn_expiry_sweep().is_none());
+    }
 }
 
 #[cfg(test)]
@@ -1088,7 +3093,10 @@ mod request_filter_tests {
     
     #[tokio::test]
     async fn test_request_filter_with_range_header() {
-        let proxy = create_test_proxy(vec![]);
+        let proxy = SliceProxy::new(Arc::new(SliceConfig {
+            slice_client_range_requests: false,
+            ..Default::default()
+        }));
         let mut ctx = SliceContext::new();
         let mut headers = HeaderMap::new();
         headers.insert("range", HeaderValue::from_static("bytes=0-1023"));
@@ -1163,6 +3171,68 @@ mod request_filter_tests {
         assert_eq!(ctx.slice_count(), 10);
     }
     
+    #[tokio::test]
+    async fn test_request_filter_caches_metadata() {
+        let mock_server = MockServer::start().await;
+        
+        // Only one HEAD request is expected across both requests
+        Mock::given(method("HEAD"))
+            .and(path("/file.bin"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Length", "10240")
+                    .insert_header("Accept-Ranges", "bytes")
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        
+        let proxy = create_test_proxy(vec![]);
+        let headers = HeaderMap::new();
+        let url = format!("{}/file.bin", mock_server.uri());
+        
+        for _ in 0..2 {
+            let mut ctx = SliceContext::new();
+            let result = proxy.request_filter(&Method::GET, &url, &headers, &mut ctx).await;
+            assert!(!result.unwrap());
+            assert_eq!(ctx.metadata().unwrap().content_length, 10240);
+        }
+        
+        mock_server.verify().await;
+    }
+    
+    #[tokio::test]
+    async fn test_request_filter_metadata_cache_disabled() {
+        let mock_server = MockServer::start().await;
+        
+        Mock::given(method("HEAD"))
+            .and(path("/file.bin"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Length", "10240")
+                    .insert_header("Accept-Ranges", "bytes")
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        
+        let proxy = SliceProxy::new(Arc::new(SliceConfig {
+            slice_size: 1024,
+            metadata_cache_ttl: Some(0),
+            ..Default::default()
+        }));
+        let headers = HeaderMap::new();
+        let url = format!("{}/file.bin", mock_server.uri());
+        
+        for _ in 0..2 {
+            let mut ctx = SliceContext::new();
+            let result = proxy.request_filter(&Method::GET, &url, &headers, &mut ctx).await;
+            assert!(!result.unwrap());
+        }
+        
+        mock_server.verify().await;
+    }
+    
     #[tokio::test]
     async fn test_request_filter_origin_no_range_support() {
         let mock_server = MockServer::start().await;
@@ -1196,6 +3266,50 @@ mod request_filter_tests {
         assert!(!ctx.is_slice_enabled());
     }
     
+    #[tokio::test]
+    async fn test_request_filter_min_slice_file_size() {
+        let mock_server = MockServer::start().await;
+        for (file, size) in [("/small.bin", "51200"), ("/large.bin", "102400")] {
+            Mock::given(method("HEAD"))
+                .and(path(file))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Length", size)
+                        .insert_header("Accept-Ranges", "bytes")
+                )
+                .mount(&mock_server)
+                .await;
+        }
+    
+        let headers = HeaderMap::new();
+        let small = format!("{}/small.bin", mock_server.uri());
+        let large = format!("{}/large.bin", mock_server.uri());
+    
+        let proxy = SliceProxy::new(Arc::new(SliceConfig {
+            slice_size: 1024,
+            min_slice_file_size: 64 * 1024,
+            ..Default::default()
+        }));
+    
+        // Below the threshold: proxied normally
+        let mut ctx = SliceContext::new();
+        assert!(proxy.request_filter(&Method::GET, &small, &headers, &mut ctx).await.unwrap());
+        assert!(!ctx.is_slice_enabled());
+        assert_eq!(proxy.metrics().get_stats().sliced_requests, 0);
+    
+        // Above the threshold: sliced
+        let mut ctx = SliceContext::new();
+        assert!(!proxy.request_filter(&Method::GET, &large, &headers, &mut ctx).await.unwrap());
+        assert!(ctx.is_slice_enabled());
+        assert_eq!(ctx.slice_count(), 100);
+    
+        // A threshold of 0 slices every file
+        let proxy = create_test_proxy(vec![]);
+        let mut ctx = SliceContext::new();
+        assert!(!proxy.request_filter(&Method::GET, &small, &headers, &mut ctx).await.unwrap());
+        assert!(ctx.is_slice_enabled());
+    }
+    
     #[tokio::test]
     async fn test_request_filter_metadata_fetch_failure() {
         let proxy = create_test_proxy(vec![]);
@@ -1342,6 +3456,60 @@ mod request_filter_tests {
         assert_eq!(result.unwrap(), true);
         assert!(!ctx.is_slice_enabled());
     }
+    
+    /// Send `total` bytes through `response_body_filter` in 64KB chunks
+    async fn send_body(proxy: &SliceProxy, ctx: &mut SliceContext, total: usize) -> usize {
+        let chunk = Bytes::from(vec![0u8; 64 * 1024]);
+        let chunks = total / chunk.len();
+        let mut delays = 0;
+        for i in 0..chunks {
+            let body = Some(chunk.clone());
+            if let Some(delay) = proxy.response_body_filter(&body, i + 1 == chunks, ctx).unwrap() {
+                delays += 1;
+                tokio::time::sleep(delay).await;
+            }
+        }
+        delays
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_client_download_paced() {
+        let proxy = SliceProxy::new(Arc::new(SliceConfig {
+            client_max_bytes_per_sec: Some(1024 * 1024),
+            ..Default::default()
+        }));
+        let mut ctx = SliceContext::new();
+        proxy
+            .request_filter(&Method::POST, "http://example.com/file.bin", &HeaderMap::new(), &mut ctx)
+            .await
+            .unwrap();
+        assert_eq!(ctx.pacer.as_ref().unwrap().bytes_per_sec(), 1024 * 1024);
+        
+        let start = tokio::time::Instant::now();
+        send_body(&proxy, &mut ctx, 10 * 1024 * 1024).await;
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(9700) && elapsed <= Duration::from_millis(10100),
+            "elapsed {:?}",
+            elapsed
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_client_download_unlimited() {
+        let proxy = create_test_proxy(vec![]);
+        let mut ctx = SliceContext::new();
+        let mut headers = HeaderMap::new();
+        // Ignored unless the header is trusted
+        headers.insert("x-ratelimit-bps", HeaderValue::from_static("1024"));
+        proxy
+            .request_filter(&Method::POST, "http://example.com/file.bin", &headers, &mut ctx)
+            .await
+            .unwrap();
+        assert!(ctx.pacer.is_none());
+        
+        assert_eq!(send_body(&proxy, &mut ctx, 10 * 1024 * 1024).await, 0);
+    }
 }
 
 #[cfg(test)]
@@ -1490,6 +3658,116 @@ mod handle_slice_request_tests {
         assert_eq!(stats.total_subrequests, 2);
         assert_eq!(stats.bytes_from_origin, 2048);
         assert_eq!(stats.bytes_to_client, 2048);
+        assert_eq!(stats.full_miss_requests, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_handle_slice_request_counts_partial_hits() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/file.bin"))
+            .and(header("range", "bytes=1024-2047"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("Content-Range", "bytes 1024-2047/2048")
+                    .set_body_bytes(vec![2u8; 1024])
+            )
+            .mount(&mock_server)
+            .await;
+        
+        let proxy = create_test_proxy();
+        let url = format!("{}/file.bin", mock_server.uri());
+        let range1 = ByteRange::new(0, 1023).unwrap();
+        let range2 = ByteRange::new(1024, 2047).unwrap();
+        proxy
+            .cache_arc()
+            .store(&url, &range1, Bytes::from(vec![1u8; 1024]))
+            .unwrap();
+        
+        // First slice cached, second fetched from origin
+        let mut ctx = SliceContext::new();
+        ctx.set_metadata(FileMetadata::new(2048, true));
+        let mut slice1 = SliceSpec::new(0, range1);
+        slice1.cached = true;
+        ctx.set_slices(vec![slice1, SliceSpec::new(1, range2)]);
+        ctx.enable_slicing();
+        
+        let (_, _, slices) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+        assert_eq!(slices.concat().len(), 2048);
+        
+        let stats = proxy.metrics().get_stats();
+        assert_eq!(stats.partial_hit_requests, 1);
+        assert_eq!(stats.full_hit_requests, 0);
+        assert_eq!(stats.full_miss_requests, 0);
+        
+        // Both slices cached now
+        let mut ctx = SliceContext::new();
+        ctx.set_metadata(FileMetadata::new(2048, true));
+        let slices = [range1, range2]
+            .into_iter()
+            .enumerate()
+            .map(|(i, range)| {
+                let mut slice = SliceSpec::new(i, range);
+                slice.cached = true;
+                slice
+            })
+            .collect();
+        ctx.set_slices(slices);
+        ctx.enable_slicing();
+        proxy.handle_slice_request(&url, &ctx).await.unwrap();
+        assert_eq!(proxy.metrics().get_stats().full_hit_requests, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_handle_slice_request_refetches_evicted_slice() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/file.bin"))
+            .and(header("range", "bytes=1024-2047"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("Content-Range", "bytes 1024-2047/2048")
+                    .set_body_bytes(vec![2u8; 1024])
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        
+        let proxy = create_test_proxy();
+        let url = format!("{}/file.bin", mock_server.uri());
+        let range1 = ByteRange::new(0, 1023).unwrap();
+        let range2 = ByteRange::new(1024, 2047).unwrap();
+        let cache = proxy.cache_arc();
+        cache.store(&url, &range1, Bytes::from(vec![1u8; 1024])).unwrap();
+        cache.store(&url, &range2, Bytes::from(vec![2u8; 1024])).unwrap();
+        
+        // Both slices were hits when the request was checked
+        let mut ctx = SliceContext::new();
+        ctx.set_metadata(FileMetadata::new(2048, true));
+        let slices = [range1, range2]
+            .into_iter()
+            .enumerate()
+            .map(|(i, range)| {
+                let mut slice = SliceSpec::new(i, range);
+                slice.cached = true;
+                slice
+            })
+            .collect();
+        ctx.set_slices(slices);
+        ctx.enable_slicing();
+        
+        // The second one is evicted before it is read
+        assert!(cache.purge(&url, &range2).await.unwrap());
+        
+        let (status, _, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.concat(), [vec![1u8; 1024], vec![2u8; 1024]].concat());
+        let stats = proxy.metrics().get_stats();
+        assert_eq!(stats.bytes_from_cache, 1024);
+        assert_eq!(stats.bytes_from_origin, 1024);
+        
+        // The refetched slice is cached again
+        assert!(cache.lookup(&url, &range2).await.unwrap().is_some());
     }
     
     #[tokio::test]
@@ -1662,4 +3940,138 @@ mod handle_slice_request_tests {
         let stats = proxy.metrics().get_stats();
         assert!(stats.failed_subrequests > 0);
     }
+    
+    async fn mount_two_slice_origin(mock_server: &MockServer) {
+        for (range, content_range, byte) in [
+            ("bytes=0-1023", "bytes 0-1023/2048", 1u8),
+            ("bytes=1024-2047", "bytes 1024-2047/2048", 2u8),
+        ] {
+            Mock::given(method("GET"))
+                .and(path("/file.bin"))
+                .and(header("range", range))
+                .respond_with(
+                    ResponseTemplate::new(206)
+                        .insert_header("Content-Range", content_range)
+                        .set_body_bytes(vec![byte; 1024])
+                )
+                .mount(mock_server)
+                .await;
+        }
+    }
+    
+    /// Build a context for a client `Range: bytes=1024-2047` request with `If-Range`
+    fn if_range_ctx(proxy: &SliceProxy, if_range: &str) -> SliceContext {
+        let metadata = FileMetadata::with_headers(
+            2048,
+            true,
+            None,
+            Some("\"v1\"".to_string()),
+            None,
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert("if-range", HeaderValue::from_str(if_range).unwrap());
+        
+        let mut ctx = SliceContext::new();
+        ctx.set_client_range(ByteRange::new(1024, 2047).unwrap());
+        
+        proxy.apply_if_range(&proxy.analyzer, &headers, &metadata, &mut ctx);
+        
+        let slices = SliceCalculator::new(1024)
+            .calculate_slices(metadata.content_length, ctx.client_range())
+            .unwrap();
+        ctx.set_metadata(metadata);
+        ctx.set_slices(slices);
+        ctx.enable_slicing();
+        ctx
+    }
+    
+    #[tokio::test]
+    async fn test_if_range_match_serves_partial_content() {
+        let mock_server = MockServer::start().await;
+        mount_two_slice_origin(&mock_server).await;
+        
+        let proxy = create_test_proxy();
+        let ctx = if_range_ctx(&proxy, "\"v1\"");
+        assert!(ctx.client_range().is_some());
+        
+        let url = format!("{}/file.bin", mock_server.uri());
+        let (status, headers, slices) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+        
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers.get("content-range").unwrap(), "bytes 1024-2047/2048");
+        assert_eq!(slices.concat(), vec![2u8; 1024]);
+    }
+    
+    #[tokio::test]
+    async fn test_if_range_mismatch_serves_full_file() {
+        let mock_server = MockServer::start().await;
+        mount_two_slice_origin(&mock_server).await;
+        
+        let proxy = create_test_proxy();
+        let ctx = if_range_ctx(&proxy, "\"v0\"");
+        assert!(ctx.client_range().is_none());
+        
+        let url = format!("{}/file.bin", mock_server.uri());
+        let (status, headers, slices) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+        
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get("content-range").is_none());
+        assert_eq!(slices.concat().len(), 2048);
+    }
+    
+    #[tokio::test]
+    async fn test_pattern_rule_ttl_applied_to_stored_slices() {
+        let mock_server = MockServer::start().await;
+        for file in ["/live.bin", "/vod.bin"] {
+            Mock::given(method("HEAD"))
+                .and(path(file))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Length", "1024")
+                        .insert_header("Accept-Ranges", "bytes")
+                )
+                .mount(&mock_server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path(file))
+                .respond_with(
+                    ResponseTemplate::new(206)
+                        .insert_header("Content-Range", "bytes 0-1023/1024")
+                        .set_body_bytes(vec![3u8; 1024])
+                )
+                .mount(&mock_server)
+                .await;
+        }
+        
+        let config = Arc::new(SliceConfig {
+            slice_size: 1024,
+            cache_ttl: 3600,
+            slice_patterns: vec!["*.bin".to_string()],
+            pattern_rules: vec![crate::config::PatternRule {
+                pattern: "*/live.bin".to_string(),
+                name: None,
+                cache_ttl: Some(1),
+            }],
+            ..Default::default()
+        });
+        let proxy = SliceProxy::new(config);
+        let range = ByteRange::new(0, 1023).unwrap();
+        
+        let live_url = format!("{}/live.bin", mock_server.uri());
+        let vod_url = format!("{}/vod.bin", mock_server.uri());
+        for url in [&live_url, &vod_url] {
+            let mut ctx = SliceContext::new();
+            proxy.request_filter(&Method::GET, url, &HeaderMap::new(), &mut ctx).await.unwrap();
+            proxy.handle_slice_request(url, &ctx).await.unwrap();
+        }
+        
+        let cache = proxy.cache_arc();
+        assert!(cache.lookup(&live_url, &range).await.unwrap().is_some());
+        
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        
+        // The short-TTL rule entry has expired; the global-TTL entry remains
+        assert!(cache.lookup(&live_url, &range).await.unwrap().is_none());
+        assert!(cache.lookup(&vod_url, &range).await.unwrap().is_some());
+    }
 }
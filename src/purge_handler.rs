@@ -6,17 +6,35 @@
 //! Supported PURGE methods:
 //! - PURGE /path/to/file - Purge specific URL
 //! - PURGE /* - Purge all cache (with X-Purge-All header)
+//! - PURGE /purge/batch - Purge every URL listed in the request body
 
+use crate::cache_key::CacheKeyBuilder;
 use crate::error::{Result, SliceError};
+use crate::metadata_cache::MetadataCache;
+use crate::models::SliceKey;
 use crate::purge_metrics::PurgeMetrics;
+use crate::slice_calculator::SliceCalculator;
 use crate::tiered_cache::TieredCache;
 use bytes::Bytes;
 use http::{Method, Request, Response, StatusCode};
-use http_body_util::Full;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Body;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Instant;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
+
+/// Path that accepts a list of URLs to purge in the request body
+pub const BATCH_PURGE_PATH: &str = "/purge/batch";
+
+/// Maximum number of URLs accepted in one batch purge
+const MAX_BATCH_URLS: usize = 10_000;
+
+/// Maximum batch purge request body size
+const MAX_BATCH_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// URLs purged between yields to the runtime during a batch purge
+const BATCH_YIELD_INTERVAL: usize = 64;
 
 /// PURGE request handler
 pub struct PurgeHandler {
@@ -27,6 +45,21 @@ pub struct PurgeHandler {
     auth_token: Option<String>,
     /// Prometheus metrics (optional)
     metrics: Option<Arc<PurgeMetrics>>,
+    /// File metadata cache to invalidate alongside slices (optional)
+    metadata_cache: Option<Arc<MetadataCache>>,
+    /// Cache key normalization shared with the proxy (optional)
+    cache_keys: Option<CacheKeyBuilder>,
+    /// Slice size used by the proxy, to enumerate a file's slices (optional)
+    slice_size: Option<usize>,
+}
+
+/// Entries removed by a PURGE, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurgeCounts {
+    /// Slice entries removed from L1 or L2
+    pub slices: usize,
+    /// File metadata entries removed from the metadata cache
+    pub metadata: usize,
 }
 
 /// PURGE response body
@@ -36,6 +69,43 @@ pub struct PurgeResponse {
     pub purged_count: usize,
     pub url: Option<String>,
     pub message: String,
+    /// Breakdown of `purged_count` plus metadata entries (URL purges only)
+    #[serde(default)]
+    pub purged: PurgeCounts,
+}
+
+/// Outcome of purging one URL in a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchPurgeStatus {
+    Purged,
+    NotFound,
+    Failed,
+}
+
+/// Per-URL result of a batch purge
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchPurgeItem {
+    pub url: String,
+    pub status: BatchPurgeStatus,
+    pub purged_count: usize,
+}
+
+/// Batch PURGE response body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchPurgeResponse {
+    pub success: bool,
+    pub total: usize,
+    pub purged: usize,
+    pub not_found: usize,
+    pub failed: usize,
+    pub results: Vec<BatchPurgeItem>,
+}
+
+/// JSON object form of a batch purge body (`{"urls": [...]}`)
+#[derive(Deserialize)]
+struct BatchPurgeRequest {
+    urls: Vec<String>,
 }
 
 impl PurgeHandler {
@@ -46,6 +116,9 @@ impl PurgeHandler {
             require_auth: false,
             auth_token: None,
             metrics: None,
+            metadata_cache: None,
+            cache_keys: None,
+            slice_size: None,
         }
     }
 
@@ -56,6 +129,9 @@ impl PurgeHandler {
             require_auth: true,
             auth_token: Some(auth_token),
             metrics: None,
+            metadata_cache: None,
+            cache_keys: None,
+            slice_size: None,
         }
     }
 
@@ -65,6 +141,68 @@ impl PurgeHandler {
         self
     }
 
+    /// Invalidate cached file metadata on purge
+    pub fn with_metadata_cache(mut self, metadata_cache: Arc<MetadataCache>) -> Self {
+        self.metadata_cache = Some(metadata_cache);
+        self
+    }
+
+    /// Purge the cache keys built by `keys` instead of the raw URL
+    ///
+    /// Use the same policy as the proxy so that a purge reaches the entries
+    /// clients hit. The request's query string then takes part in the key;
+    /// without a builder only the path is used.
+    pub fn with_cache_keys(mut self, keys: CacheKeyBuilder) -> Self {
+        self.cache_keys = Some(keys);
+        self
+    }
+
+    /// Find the slices of a purged URL from its cached metadata
+    ///
+    /// Without this, a URL purge only removes the slices still in L1; slices
+    /// evicted to L2 are only found through the file size in the metadata
+    /// cache. Pass the proxy's `slice_size` along with
+    /// [`with_metadata_cache`](Self::with_metadata_cache).
+    pub fn with_slice_size(mut self, slice_size: usize) -> Self {
+        self.slice_size = Some(slice_size);
+        self
+    }
+
+    /// Remove every slice of `url` and its file metadata, for all variants
+    async fn purge_url_entries(&self, url: &str) -> Result<PurgeCounts> {
+        let metadata = self
+            .metadata_cache
+            .as_ref()
+            .map(|cache| cache.take_variants(url))
+            .unwrap_or_default();
+        let mut counts = PurgeCounts {
+            slices: 0,
+            metadata: metadata.len(),
+        };
+
+        if let Some(slice_size) = self.slice_size {
+            for (key, metadata) in &metadata {
+                let ranges: Vec<_> = SliceCalculator::new(slice_size)
+                    .calculate_slices(metadata.content_length, None)?
+                    .into_iter()
+                    .map(|slice| slice.range)
+                    .collect();
+                counts.slices += self.cache.purge_slices(key, &ranges).await?;
+            }
+        }
+        // Anything left in L1, e.g. slices cached with another slice size
+        counts.slices += self.cache.purge_url(url).await?;
+        Ok(counts)
+    }
+
+    /// Cache key purged for `url`
+    fn purge_key(&self, url: String) -> String {
+        match &self.cache_keys {
+            Some(keys) => SliceKey::new(&keys.build(&url)).object_key(),
+            None => SliceKey::new(&url).object_key(),
+        }
+    }
+
     /// Handle HTTP PURGE request
     ///
     /// Supports:
@@ -96,26 +234,14 @@ impl PurgeHandler {
             }
         }
 
-        // Get the URL path
-        let path = req.uri().path();
-        let host = req
-            .headers()
-            .get("host")
-            .and_then(|h| h.to_str().ok())
-            .unwrap_or("localhost");
-
-        // Construct full URL
-        let scheme = if req
-            .headers()
-            .get("x-forwarded-proto")
-            .and_then(|h| h.to_str().ok())
-            == Some("https")
-        {
-            "https"
-        } else {
-            "http"
+        // Construct full URL from the request path
+        let url = match &self.cache_keys {
+            Some(_) => {
+                let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+                self.purge_key(format!("{}{}", base_url(&req), path))
+            }
+            None => self.purge_key(format!("{}{}", base_url(&req), req.uri().path())),
         };
-        let url = format!("{}://{}{}", scheme, host, path);
 
         // Check for special purge modes
         let purge_all = req
@@ -145,9 +271,13 @@ impl PurgeHandler {
         }
 
         // Execute purge operation
+        let mut purged = PurgeCounts::default();
         let (purged_count, message, success) = if purge_all {
             // Purge all cache
             info!("Purging all cache entries");
+            if let Some(metadata_cache) = &self.metadata_cache {
+                metadata_cache.clear();
+            }
             match self.cache.purge_all().await {
                 Ok(count) => {
                     info!("Purged all {} cache entries", count);
@@ -169,6 +299,9 @@ impl PurgeHandler {
             // Purge by pattern (currently only supports prefix)
             if pattern == "prefix" {
                 info!("Purging cache entries with prefix: {}", url);
+                if let Some(metadata_cache) = &self.metadata_cache {
+                    metadata_cache.invalidate_prefix(&url);
+                }
                 match self.cache.purge_url(&url).await {
                     Ok(count) => {
                         info!("Purged {} cache entries for URL: {}", count, url);
@@ -199,9 +332,14 @@ impl PurgeHandler {
         } else {
             // Purge specific URL
             info!("Purging cache for URL: {}", url);
-            match self.cache.purge_url(&url).await {
-                Ok(count) => {
-                    info!("Purged {} cache entries for URL: {}", count, url);
+            match self.purge_url_entries(&url).await {
+                Ok(counts) => {
+                    let count = counts.slices;
+                    purged = counts;
+                    info!(
+                        "Purged {} slices and {} metadata entries for URL: {}",
+                        counts.slices, counts.metadata, url
+                    );
                     if count > 0 {
                         (
                             count,
@@ -239,52 +377,150 @@ impl PurgeHandler {
             purged_count,
             url: if purge_all { None } else { Some(url) },
             message,
+            purged,
         };
 
         self.json_response(StatusCode::OK, &response)
     }
 
-    /// Check authentication
-    fn check_auth<B>(&self, req: &Request<B>) -> Result<()> {
-        if let Some(expected_token) = &self.auth_token {
-            // Check Authorization header
-            if let Some(auth_header) = req.headers().get("authorization") {
-                if let Ok(auth_str) = auth_header.to_str() {
-                    // Support both "Bearer <token>" and direct token
-                    let token = if auth_str.starts_with("Bearer ") {
-                        &auth_str[7..]
-                    } else {
-                        auth_str
-                    };
+    /// Whether a request should be handled by [`handle_batch_purge`](Self::handle_batch_purge)
+    pub fn is_batch_request<B>(req: &Request<B>) -> bool {
+        req.method().as_str() == "PURGE" && req.uri().path() == BATCH_PURGE_PATH
+    }
 
-                    if token == expected_token {
-                        return Ok(());
-                    }
+    /// Handle a batch PURGE request
+    ///
+    /// The body lists the URLs to purge, either as a JSON array, a JSON
+    /// object `{"urls": [...]}`, or one URL per line. Paths (starting with
+    /// `/`) are resolved against the request's host like a single PURGE.
+    /// The response reports whether each URL was purged or not found.
+    pub async fn handle_batch_purge<B>(&self, req: Request<B>) -> Result<Response<Full<Bytes>>>
+    where
+        B: Body,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let start_time = Instant::now();
+        let purge_method = "batch";
+
+        if req.method() != Method::from_bytes(b"PURGE").unwrap() {
+            return self.error_response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "Only PURGE method is allowed",
+            );
+        }
+
+        if self.require_auth {
+            if let Err(e) = self.check_auth(&req) {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_auth_failure("invalid_token");
                 }
+                return self.error_response(StatusCode::UNAUTHORIZED, &e.to_string());
             }
+        }
 
-            // Check X-Purge-Token header (alternative)
-            if let Some(token_header) = req.headers().get("x-purge-token") {
-                if let Ok(token) = token_header.to_str() {
-                    if token == expected_token {
-                        return Ok(());
-                    }
-                }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_request(purge_method);
+        }
+
+        let base = base_url(&req);
+        let body = match Limited::new(req.into_body(), MAX_BATCH_BODY_BYTES).collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) => {
+                return self.error_response(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    &format!("Failed to read request body: {}", e),
+                );
+            }
+        };
+        let urls = match parse_batch_urls(&body) {
+            Ok(urls) => urls,
+            Err(e) => return self.error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+        };
+        if urls.is_empty() {
+            return self.error_response(StatusCode::BAD_REQUEST, "No URLs to purge");
+        }
+        if urls.len() > MAX_BATCH_URLS {
+            return self.error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &format!("At most {} URLs can be purged per batch", MAX_BATCH_URLS),
+            );
+        }
+
+        info!("Batch purging {} URLs", urls.len());
+        let mut results = Vec::with_capacity(urls.len());
+        let mut purged_items = 0;
+        for (i, url) in urls.into_iter().enumerate() {
+            // Let other tasks run between chunks of a large batch
+            if i > 0 && i % BATCH_YIELD_INTERVAL == 0 {
+                tokio::task::yield_now().await;
             }
 
-            Err(SliceError::ConfigError(
-                "Invalid or missing authentication token".to_string(),
-            ))
+            let url = if url.starts_with('/') {
+                format!("{}{}", base, url)
+            } else {
+                url
+            };
+            let url = self.purge_key(url);
+            let (status, purged_count) = match self.purge_url_entries(&url).await {
+                Ok(PurgeCounts { slices: 0, .. }) => (BatchPurgeStatus::NotFound, 0),
+                Ok(counts) => (BatchPurgeStatus::Purged, counts.slices),
+                Err(e) => {
+                    warn!("Failed to purge URL {}: {}", url, e);
+                    (BatchPurgeStatus::Failed, 0)
+                }
+            };
+            debug!("Batch purge {}: {:?} ({} entries)", url, status, purged_count);
+            purged_items += purged_count;
+            results.push(BatchPurgeItem {
+                url,
+                status,
+                purged_count,
+            });
+        }
+
+        let count = |status| results.iter().filter(|r| r.status == status).count();
+        let response = BatchPurgeResponse {
+            success: count(BatchPurgeStatus::Failed) == 0,
+            total: results.len(),
+            purged: count(BatchPurgeStatus::Purged),
+            not_found: count(BatchPurgeStatus::NotFound),
+            failed: count(BatchPurgeStatus::Failed),
+            results,
+        };
+        info!(
+            "Batch purge complete: {} purged, {} not found, {} failed ({} entries)",
+            response.purged, response.not_found, response.failed, purged_items
+        );
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_result(purge_method, response.success);
+            metrics.record_purged_items(purge_method, purged_items);
+            metrics.record_duration(purge_method, start_time.elapsed().as_secs_f64());
+        }
+
+        self.json_response(StatusCode::OK, &response)
+    }
+
+    /// Check authentication
+    fn check_auth<B>(&self, req: &Request<B>) -> Result<()> {
+        if let Some(expected_token) = &self.auth_token {
+            if has_valid_token(req, expected_token) {
+                Ok(())
+            } else {
+                Err(SliceError::ConfigError(
+                    "Invalid or missing authentication token".to_string(),
+                ))
+            }
         } else {
             Ok(())
         }
     }
 
     /// Build JSON response
-    fn json_response(
+    fn json_response<T: Serialize>(
         &self,
         status: StatusCode,
-        body: &PurgeResponse,
+        body: &T,
     ) -> Result<Response<Full<Bytes>>> {
         let json = serde_json::to_string(body)
             .map_err(|e| SliceError::CacheError(format!("Failed to serialize response: {}", e)))?;
@@ -304,12 +540,84 @@ impl PurgeHandler {
             purged_count: 0,
             url: None,
             message: message.to_string(),
+            purged: PurgeCounts::default(),
         };
 
         self.json_response(status, &response)
     }
 }
 
+/// Scheme and host of the request, e.g. `https://example.com`
+fn base_url<B>(req: &Request<B>) -> String {
+    let host = req
+        .headers()
+        .get("host")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+
+    let scheme = if req
+        .headers()
+        .get("x-forwarded-proto")
+        .and_then(|h| h.to_str().ok())
+        == Some("https")
+    {
+        "https"
+    } else {
+        "http"
+    };
+    format!("{}://{}", scheme, host)
+}
+
+/// Parse a batch purge body: a JSON array, `{"urls": [...]}`, or one URL per line
+pub(crate) fn parse_batch_urls(body: &[u8]) -> Result<Vec<String>> {
+    let text = std::str::from_utf8(body)
+        .map_err(|e| SliceError::ParseError(format!("Request body is not UTF-8: {}", e)))?;
+    let trimmed = text.trim_start();
+
+    let urls = if trimmed.starts_with('[') {
+        serde_json::from_str::<Vec<String>>(trimmed)
+            .map_err(|e| SliceError::ParseError(format!("Invalid JSON URL list: {}", e)))?
+    } else if trimmed.starts_with('{') {
+        serde_json::from_str::<BatchPurgeRequest>(trimmed)
+            .map_err(|e| SliceError::ParseError(format!("Invalid JSON URL list: {}", e)))?
+            .urls
+    } else {
+        text.lines().map(str::to_string).collect()
+    };
+
+    Ok(urls
+        .into_iter()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect())
+}
+
+/// Check whether a request carries the expected token, either as
+/// `Authorization: Bearer <token>` (or a bare token) or as `X-Purge-Token`
+pub(crate) fn has_valid_token<B>(req: &Request<B>, expected_token: &str) -> bool {
+    headers_have_token(req.headers(), expected_token)
+}
+
+/// [`has_valid_token`] for a request's headers
+pub(crate) fn headers_have_token(headers: &http::HeaderMap, expected_token: &str) -> bool {
+    if let Some(auth_str) = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+    {
+        // Support both "Bearer <token>" and direct token
+        let token = auth_str.strip_prefix("Bearer ").unwrap_or(auth_str);
+        if token == expected_token {
+            return true;
+        }
+    }
+
+    // Check X-Purge-Token header (alternative)
+    headers
+        .get("x-purge-token")
+        .and_then(|h| h.to_str().ok())
+        == Some(expected_token)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -354,6 +662,80 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[tokio::test]
+    async fn test_purge_invalidates_metadata() {
+        let (handler, _temp_dir) = create_test_handler().await;
+        let metadata_cache = Arc::new(MetadataCache::new(Duration::from_secs(60), 100));
+        let handler = handler.with_metadata_cache(metadata_cache.clone());
+
+        let url = "http://example.com/test.dat";
+        metadata_cache.insert(url, crate::models::FileMetadata::new(1024, true));
+
+        let req = Request::builder()
+            .method(Method::from_bytes(b"PURGE").unwrap())
+            .uri("/test.dat")
+            .header("host", "example.com")
+            .body(())
+            .unwrap();
+
+        let response = handler.handle_purge(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(metadata_cache.get(url).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_every_slice_and_metadata() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // L1 only holds two of the four slices, the others live on disk
+        let cache = Arc::new(
+            TieredCache::new(Duration::from_secs(60), 2048, temp_dir.path())
+                .await
+                .unwrap(),
+        );
+        let metadata_cache = Arc::new(MetadataCache::new(Duration::from_secs(60), 100));
+        let handler = PurgeHandler::new(cache.clone())
+            .with_metadata_cache(metadata_cache.clone())
+            .with_slice_size(1024);
+
+        let url = "http://example.com/video.mp4";
+        metadata_cache.insert(url, crate::models::FileMetadata::new(4096, true));
+        let ranges: Vec<_> = (0..4)
+            .map(|i| ByteRange::new(i * 1024, i * 1024 + 1023).unwrap())
+            .collect();
+        for range in &ranges {
+            cache.store(url, range, Bytes::from(vec![7u8; 1024])).unwrap();
+        }
+        for range in &ranges {
+            // Disk writes are asynchronous
+            for _ in 0..100 {
+                if cache.lookup(url, range).await.unwrap().is_some() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert!(cache.lookup(url, range).await.unwrap().is_some());
+        }
+
+        let req = Request::builder()
+            .method(Method::from_bytes(b"PURGE").unwrap())
+            .uri("/video.mp4")
+            .header("host", "example.com")
+            .body(())
+            .unwrap();
+        let response = handler.handle_purge(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let result: PurgeResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.purged, PurgeCounts { slices: 4, metadata: 1 });
+        assert_eq!(result.purged_count, 4);
+
+        for range in &ranges {
+            assert!(cache.lookup(url, range).await.unwrap().is_none());
+        }
+        assert!(metadata_cache.get(url).is_none());
+    }
+
     #[tokio::test]
     async fn test_purge_all() {
         let (handler, _temp_dir) = create_test_handler().await;
@@ -437,4 +819,76 @@ mod tests {
         let response = handler.handle_purge(req).await.unwrap();
         assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
     }
+
+    fn batch_request(body: &str) -> Request<Full<Bytes>> {
+        Request::builder()
+            .method(Method::from_bytes(b"PURGE").unwrap())
+            .uri(BATCH_PURGE_PATH)
+            .header("host", "example.com")
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batch_purge_mixed_urls() {
+        let (handler, _temp_dir) = create_test_handler().await;
+        let registry = prometheus::Registry::new();
+        let metrics = Arc::new(PurgeMetrics::with_registry(&registry).unwrap());
+        let handler = handler.with_metrics(metrics);
+
+        let range = ByteRange::new(0, 1023).unwrap();
+        let data = Bytes::from(vec![1u8; 1024]);
+        handler.cache.store("http://example.com/a.dat", &range, data.clone()).unwrap();
+        handler.cache.store("http://example.com/b.dat", &range, data.clone()).unwrap();
+        handler.cache.store("http://example.com/keep.dat", &range, data).unwrap();
+
+        let req = batch_request(r#"["/a.dat", "http://example.com/b.dat", "/missing.dat"]"#);
+        assert!(PurgeHandler::is_batch_request(&req));
+        let response = handler.handle_batch_purge(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let result: BatchPurgeResponse = serde_json::from_slice(&body).unwrap();
+        assert!(result.success);
+        assert_eq!((result.total, result.purged, result.not_found), (3, 2, 1));
+        assert_eq!(result.results[0].url, "http://example.com/a.dat");
+        assert_eq!(result.results[0].status, BatchPurgeStatus::Purged);
+        assert_eq!(result.results[2].status, BatchPurgeStatus::NotFound);
+
+        assert!(handler.cache.lookup("http://example.com/a.dat", &range).await.unwrap().is_none());
+        assert!(handler.cache.lookup("http://example.com/keep.dat", &range).await.unwrap().is_some());
+
+        let families = registry.gather();
+        let items = families
+            .iter()
+            .find(|f| f.get_name() == "pingora_slice_purge_items_total")
+            .unwrap();
+        assert_eq!(items.get_metric()[0].get_counter().get_value(), 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_purge_body_formats() {
+        let (handler, _temp_dir) = create_test_handler().await;
+
+        let response = handler
+            .handle_batch_purge(batch_request("/a.dat\n\n/b.dat\n"))
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let result: BatchPurgeResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.total, 2);
+        assert_eq!(result.not_found, 2);
+
+        let response = handler
+            .handle_batch_purge(batch_request(r#"{"urls": ["/a.dat"]}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = handler.handle_batch_purge(batch_request("[1, 2]")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = handler.handle_batch_purge(batch_request("")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -0,0 +1,452 @@
+//! Rate limiting for origin subrequests and client requests
+//!
+//! A single [`OriginRateLimiter`] is shared by every subrequest sent to the
+//! origin so that cache-fill storms cannot saturate the origin's uplink.
+//! Permission is acquired before each subrequest is sent and before each
+//! chunk of a response body is read; cache hits never touch the limiter.
+//!
+//! A [`ClientRateLimiter`] keeps a token bucket per client IP and rejects
+//! requests from clients over their budget instead of delaying them.
+
+use crate::config::{ClientRateLimitConfig, SliceConfig};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// Fraction of a second's budget that may be spent in a single burst
+const BURST_FRACTION: f64 = 0.1;
+
+/// Window over which utilization is measured
+const UTILIZATION_WINDOW: Duration = Duration::from_secs(1);
+
+/// Mutable token bucket state
+#[derive(Debug)]
+struct BucketState {
+    /// Available tokens; negative while repaying an oversized acquisition
+    tokens: f64,
+    last_refill: Instant,
+    window_start: Instant,
+    window_used: f64,
+    last_utilization: f64,
+}
+
+/// Async-aware token bucket
+///
+/// Waiters are served in FIFO order, so concurrent callers share the budget
+/// fairly.
+#[derive(Debug)]
+struct TokenBucket {
+    /// Tokens added per second
+    rate: f64,
+    /// Maximum number of tokens that can accumulate
+    capacity: f64,
+    state: Mutex<BucketState>,
+    /// Queue of waiters; held while sleeping so later callers wait their turn
+    turn: tokio::sync::Mutex<()>,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        let rate = rate as f64;
+        let capacity = (rate * BURST_FRACTION).max(1.0);
+        let now = Instant::now();
+        TokenBucket {
+            rate,
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: now,
+                window_start: now,
+                window_used: 0.0,
+                last_utilization: 0.0,
+            }),
+            turn: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Wait until `amount` tokens may be taken, then take them
+    ///
+    /// Amounts larger than the bucket capacity are allowed once the bucket is
+    /// full; the resulting deficit is repaid before the next caller proceeds.
+    ///
+    /// # Returns
+    /// How long the caller was delayed
+    async fn acquire(&self, amount: f64) -> Duration {
+        let start = Instant::now();
+        let _turn = self.turn.lock().await;
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                self.refill(&mut state, now);
+
+                let needed = amount.min(self.capacity);
+                if state.tokens >= needed {
+                    state.tokens -= amount;
+                    self.record_use(&mut state, now, amount);
+                    return start.elapsed();
+                }
+                Duration::from_secs_f64((needed - state.tokens) / self.rate)
+            };
+            sleep(wait).await;
+        }
+    }
+
+    fn refill(&self, state: &mut BucketState, now: Instant) {
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.capacity);
+        state.last_refill = now;
+    }
+
+    fn record_use(&self, state: &mut BucketState, now: Instant, amount: f64) {
+        let elapsed = now.duration_since(state.window_start);
+        if elapsed >= UTILIZATION_WINDOW {
+            state.last_utilization = state.window_used / elapsed.as_secs_f64() / self.rate;
+            state.window_start = now;
+            state.window_used = 0.0;
+        }
+        state.window_used += amount;
+    }
+
+    /// Share of the configured rate used over the last measurement window
+    fn utilization(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+        let elapsed = state.window_start.elapsed();
+        if elapsed >= UTILIZATION_WINDOW * 2 {
+            // Nothing has been recorded for a whole window
+            0.0
+        } else if elapsed >= UTILIZATION_WINDOW {
+            state.window_used / elapsed.as_secs_f64() / self.rate
+        } else {
+            state.last_utilization
+        }
+    }
+}
+
+/// Process-wide limiter for origin bandwidth and request rate
+#[derive(Debug)]
+pub struct OriginRateLimiter {
+    bytes: Option<TokenBucket>,
+    requests: Option<TokenBucket>,
+}
+
+impl OriginRateLimiter {
+    /// Create a limiter
+    ///
+    /// # Arguments
+    /// * `max_bytes_per_sec` - Origin bandwidth cap (`None` for unlimited)
+    /// * `max_requests_per_sec` - Origin request rate cap (`None` for unlimited)
+    pub fn new(max_bytes_per_sec: Option<u64>, max_requests_per_sec: Option<u64>) -> Self {
+        OriginRateLimiter {
+            bytes: max_bytes_per_sec.filter(|&rate| rate > 0).map(TokenBucket::new),
+            requests: max_requests_per_sec.filter(|&rate| rate > 0).map(TokenBucket::new),
+        }
+    }
+
+    /// Create a limiter from `origin_max_bytes_per_sec` and
+    /// `origin_max_requests_per_sec`
+    ///
+    /// # Returns
+    /// `None` if neither limit is configured
+    pub fn from_config(config: &SliceConfig) -> Option<Self> {
+        let limiter = Self::new(
+            config.origin_max_bytes_per_sec,
+            config.origin_max_requests_per_sec,
+        );
+        (limiter.bytes.is_some() || limiter.requests.is_some()).then_some(limiter)
+    }
+
+    /// Wait for permission to send one request to the origin
+    ///
+    /// # Returns
+    /// How long the caller was delayed
+    pub async fn acquire_request(&self) -> Duration {
+        match &self.requests {
+            Some(bucket) => bucket.acquire(1.0).await,
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Wait for permission to read `bytes` bytes from the origin
+    ///
+    /// # Returns
+    /// How long the caller was delayed
+    pub async fn acquire_bytes(&self, bytes: u64) -> Duration {
+        match &self.bytes {
+            Some(bucket) if bytes > 0 => bucket.acquire(bytes as f64).await,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Share of the bandwidth cap used over the last second (0.0 when unlimited)
+    pub fn bandwidth_utilization(&self) -> f64 {
+        self.bytes.as_ref().map_or(0.0, TokenBucket::utilization)
+    }
+
+    /// Share of the request rate cap used over the last second (0.0 when unlimited)
+    pub fn request_utilization(&self) -> f64 {
+        self.requests.as_ref().map_or(0.0, TokenBucket::utilization)
+    }
+}
+
+/// Token buckets of one client
+#[derive(Debug)]
+struct ClientBucket {
+    /// Available requests
+    requests: f64,
+    /// Available bytes; negative while repaying a large response
+    bytes: f64,
+    last_refill: Instant,
+    /// Recency stamp, the key of this client in `ClientBuckets::recency`
+    stamp: u64,
+}
+
+/// Buckets of the tracked clients, bounded to the least recently seen
+#[derive(Debug, Default)]
+struct ClientBuckets {
+    clients: HashMap<String, ClientBucket>,
+    recency: BTreeMap<u64, String>,
+    next_stamp: u64,
+}
+
+/// Per-client-IP request and bandwidth limiter
+///
+/// Requests are admitted while the client has a request token and is not
+/// repaying downloaded bytes. Bytes are charged once the response is sent,
+/// so a large download delays the client's next request rather than
+/// cutting the current one short. At most `max_clients` buckets are kept;
+/// the least recently seen client is forgotten to make room, which resets
+/// its budget.
+#[derive(Debug)]
+pub struct ClientRateLimiter {
+    requests_per_sec: f64,
+    burst: f64,
+    bytes_per_sec: Option<f64>,
+    max_clients: usize,
+    buckets: Mutex<ClientBuckets>,
+}
+
+impl ClientRateLimiter {
+    /// Create a limiter from its configuration
+    pub fn new(config: &ClientRateLimitConfig) -> Self {
+        let requests_per_sec = config.requests_per_sec.max(1) as f64;
+        ClientRateLimiter {
+            requests_per_sec,
+            burst: config.burst.map_or(requests_per_sec, |burst| burst.max(1) as f64),
+            bytes_per_sec: config.bytes_per_sec.filter(|&rate| rate > 0).map(|rate| rate as f64),
+            max_clients: config.max_clients.max(1),
+            buckets: Mutex::new(ClientBuckets::default()),
+        }
+    }
+
+    /// Create a limiter from `client_rate_limit`
+    ///
+    /// # Returns
+    /// `None` if no client rate limit is configured
+    pub fn from_config(config: &SliceConfig) -> Option<Self> {
+        config.client_rate_limit.as_ref().map(Self::new)
+    }
+
+    /// Take a request token for `client`
+    ///
+    /// # Returns
+    /// * `Ok(())` if the request may proceed
+    /// * `Err(wait)` with how long until the client may try again
+    pub fn check(&self, client: &str) -> std::result::Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = self.bucket(&mut buckets, client);
+
+        if bucket.bytes < 0.0 {
+            let rate = self.bytes_per_sec.unwrap_or(1.0);
+            return Err(Duration::from_secs_f64(-bucket.bytes / rate));
+        }
+        if bucket.requests < 1.0 {
+            return Err(Duration::from_secs_f64(
+                (1.0 - bucket.requests) / self.requests_per_sec,
+            ));
+        }
+        bucket.requests -= 1.0;
+        Ok(())
+    }
+
+    /// Charge `bytes` sent to `client` against its bandwidth budget
+    pub fn record_bytes(&self, client: &str, bytes: u64) {
+        if self.bytes_per_sec.is_none() || bytes == 0 {
+            return;
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = self.bucket(&mut buckets, client);
+        bucket.bytes -= bytes as f64;
+    }
+
+    /// Number of clients currently tracked
+    pub fn tracked_clients(&self) -> usize {
+        self.buckets.lock().unwrap().clients.len()
+    }
+
+    /// Refilled bucket of `client`, created (evicting the least recently
+    /// seen client if full) when missing
+    ///
+    /// Clients given as `ip:port` share the bucket of their IP.
+    fn bucket<'a>(&self, buckets: &'a mut ClientBuckets, client: &str) -> &'a mut ClientBucket {
+        let client = &client
+            .parse::<SocketAddr>()
+            .map_or_else(|_| client.to_string(), |addr| addr.ip().to_string());
+        let now = Instant::now();
+        let stamp = buckets.next_stamp;
+        buckets.next_stamp += 1;
+
+        let previous = buckets.clients.get(client).map(|bucket| bucket.stamp);
+        match previous {
+            Some(old) => {
+                buckets.recency.remove(&old);
+            }
+            None => {
+                if buckets.clients.len() >= self.max_clients {
+                    if let Some((_, evicted)) = buckets.recency.pop_first() {
+                        buckets.clients.remove(&evicted);
+                    }
+                }
+                buckets.clients.insert(
+                    client.to_string(),
+                    ClientBucket {
+                        requests: self.burst,
+                        bytes: self.bytes_per_sec.unwrap_or(0.0),
+                        last_refill: now,
+                        stamp,
+                    },
+                );
+            }
+        }
+        buckets.recency.insert(stamp, client.to_string());
+
+        let bucket = buckets.clients.get_mut(client).unwrap();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.requests = (bucket.requests + elapsed * self.requests_per_sec).min(self.burst);
+        if let Some(rate) = self.bytes_per_sec {
+            bucket.bytes = (bucket.bytes + elapsed * rate).min(rate);
+        }
+        bucket.last_refill = now;
+        bucket.stamp = stamp;
+        bucket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_request_rate_is_capped() {
+        let limiter = OriginRateLimiter::new(None, Some(10));
+        let start = Instant::now();
+
+        // The first request uses the burst allowance, the rest wait 100ms each
+        for _ in 0..21 {
+            limiter.acquire_request().await;
+        }
+
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_secs(2) && elapsed < Duration::from_millis(2050),
+            "elapsed {:?}",
+            elapsed
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_oversized_acquire_is_repaid() {
+        let limiter = OriginRateLimiter::new(Some(1000), None);
+
+        // Larger than the 100 byte burst: allowed immediately, then repaid
+        assert_eq!(limiter.acquire_bytes(600).await, Duration::ZERO);
+        let waited = limiter.acquire_bytes(100).await;
+        assert!(
+            waited >= Duration::from_millis(600) && waited < Duration::from_millis(610),
+            "waited {:?}",
+            waited
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unlimited_never_waits() {
+        let limiter = OriginRateLimiter::new(None, None);
+        assert_eq!(limiter.acquire_bytes(u64::MAX).await, Duration::ZERO);
+        assert_eq!(limiter.acquire_request().await, Duration::ZERO);
+        assert_eq!(limiter.bandwidth_utilization(), 0.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_utilization_tracks_usage() {
+        let limiter = OriginRateLimiter::new(Some(1000), None);
+        for _ in 0..30 {
+            limiter.acquire_bytes(100).await;
+        }
+
+        let utilization = limiter.bandwidth_utilization();
+        assert!(utilization > 0.9 && utilization <= 1.1, "utilization {}", utilization);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = SliceConfig::default();
+        assert!(OriginRateLimiter::from_config(&config).is_none());
+
+        config.origin_max_bytes_per_sec = Some(1024);
+        let limiter = OriginRateLimiter::from_config(&config).unwrap();
+        assert!(limiter.bytes.is_some());
+        assert!(limiter.requests.is_none());
+    }
+
+    fn client_config(requests_per_sec: u64, bytes_per_sec: Option<u64>) -> ClientRateLimitConfig {
+        ClientRateLimitConfig {
+            requests_per_sec,
+            burst: None,
+            bytes_per_sec,
+            max_clients: 2,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_client_requests_refill() {
+        let limiter = ClientRateLimiter::new(&client_config(2, None));
+        assert!(limiter.check("192.0.2.1").is_ok());
+        assert!(limiter.check("192.0.2.1").is_ok());
+        assert_eq!(limiter.check("192.0.2.1"), Err(Duration::from_millis(500)));
+
+        // Other clients have their own budget; ports share the IP's
+        assert!(limiter.check("192.0.2.2").is_ok());
+        assert!(limiter.check("192.0.2.1:40000").is_err());
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert!(limiter.check("192.0.2.1").is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_client_bytes_are_repaid() {
+        let limiter = ClientRateLimiter::new(&client_config(100, Some(1000)));
+        assert!(limiter.check("192.0.2.1").is_ok());
+        limiter.record_bytes("192.0.2.1", 3000);
+        assert_eq!(limiter.check("192.0.2.1"), Err(Duration::from_secs(2)));
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert!(limiter.check("192.0.2.1").is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_least_recently_seen_client_is_forgotten() {
+        let limiter = ClientRateLimiter::new(&client_config(1, None));
+        assert!(limiter.check("192.0.2.1").is_ok());
+        assert!(limiter.check("192.0.2.2").is_ok());
+        assert!(limiter.check("192.0.2.1").is_err());
+
+        // .2 was seen least recently and makes room for .3
+        assert!(limiter.check("192.0.2.3").is_ok());
+        assert_eq!(limiter.tracked_clients(), 2);
+        assert!(limiter.check("192.0.2.1").is_err());
+        assert!(limiter.check("192.0.2.2").is_ok());
+    }
+}
@@ -1,20 +1,94 @@
 //! Request analysis for determining if slicing should be enabled
 
-use crate::config::SliceConfig;
+use crate::config::{CacheMode, PatternRule, SliceConfig};
+use crate::error::{Result, SliceError};
 use crate::models::ByteRange;
 use http::{Method, HeaderMap, HeaderValue};
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::debug;
 
 /// Analyzes incoming requests to determine if slicing should be applied
+///
+/// The configured URL patterns are compiled when the analyzer is created,
+/// and clones share them, so one analyzer can be built per proxy and used
+/// by every request.
+#[derive(Debug, Clone)]
 pub struct RequestAnalyzer {
     config: Arc<SliceConfig>,
+    patterns: Arc<CompiledPatterns>,
+}
+
+/// `slice_patterns`, `pattern_rules` and `cache_mode_rules`, in
+/// configuration order
+#[derive(Debug)]
+struct CompiledPatterns {
+    slice_patterns: Vec<Pattern>,
+    rules: Vec<Pattern>,
+    cache_modes: Vec<Pattern>,
+}
+
+/// A URL pattern, split at its wildcards
+///
+/// Supports simple glob-style patterns:
+/// - `*` matches any sequence of characters
+/// - Without a wildcard, the URI must equal or start with the pattern
+#[derive(Debug)]
+enum Pattern {
+    Prefix(String),
+    Glob(Vec<String>),
+}
+
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        if pattern.contains('*') {
+            Pattern::Glob(pattern.split('*').map(str::to_string).collect())
+        } else {
+            Pattern::Prefix(pattern.to_string())
+        }
+    }
+
+    /// Check if the pattern matches the URI
+    fn matches(&self, uri: &str) -> bool {
+        let parts = match self {
+            // Exact match or prefix match
+            Pattern::Prefix(prefix) => return uri.starts_with(prefix.as_str()),
+            Pattern::Glob(parts) => parts,
+        };
+        let (first, last) = (&parts[0], &parts[parts.len() - 1]);
+
+        // Check if URI starts with first part and ends with last part
+        if !uri.starts_with(first.as_str()) || !uri.ends_with(last.as_str()) {
+            return false;
+        }
+
+        // For middle parts, check if they appear in order
+        let mut current_pos = first.len();
+        for part in &parts[1..parts.len() - 1] {
+            if part.is_empty() {
+                continue;
+            }
+            match uri[current_pos..].find(part.as_str()) {
+                Some(pos) => current_pos += pos + part.len(),
+                None => return false,
+            }
+        }
+        true
+    }
 }
 
 impl RequestAnalyzer {
     /// Create a new RequestAnalyzer with the given configuration
     pub fn new(config: Arc<SliceConfig>) -> Self {
-        RequestAnalyzer { config }
+        let patterns = CompiledPatterns {
+            slice_patterns: config.slice_patterns.iter().map(|p| Pattern::new(p)).collect(),
+            rules: config.pattern_rules.iter().map(|rule| Pattern::new(&rule.pattern)).collect(),
+            cache_modes: config.cache_mode_rules.iter().map(|rule| Pattern::new(&rule.pattern)).collect(),
+        };
+        RequestAnalyzer {
+            config,
+            patterns: Arc::new(patterns),
+        }
     }
 
     /// Determine if slicing should be enabled for this request
@@ -30,8 +104,12 @@ impl RequestAnalyzer {
     /// # Logic
     /// Slicing is enabled when:
     /// 1. Request method is GET
-    /// 2. Request does NOT already contain a Range header
-    /// 3. URL matches one of the configured slice patterns (or patterns list is empty)
+    /// 2. Request has no Range header, or a single byte range while
+    ///    `slice_client_range_requests` is enabled (a range ending before it
+    ///    starts is taken too, so that it can be refused with 400)
+    /// 3. URL matches one of the configured slice patterns, pattern rules or
+    ///    `slice`/`whole` cache mode rules (or neither of the first two lists
+    ///    has entries)
     pub fn should_slice(&self, method: &Method, uri: &str, headers: &HeaderMap<HeaderValue>) -> bool {
         // Check 1: Must be GET request
         if method != Method::GET {
@@ -42,24 +120,36 @@ impl RequestAnalyzer {
             return false;
         }
 
-        // Check 2: Must NOT have Range header (pass through Range requests)
+        // Check 2: Range requests are sliced only when enabled and the
+        // header names a single range we can serve
         if headers.contains_key("range") || headers.contains_key("Range") {
-            debug!(
-                "Slicing not applicable: Range header present for uri={}",
-                uri
-            );
-            return false;
+            if !self.config.slice_client_range_requests {
+                debug!(
+                    "Slicing not applicable: Range header present for uri={}",
+                    uri
+                );
+                return false;
+            }
+            if !self.has_single_range(headers) {
+                debug!(
+                    "Slicing not applicable: unsupported Range header for uri={}",
+                    uri
+                );
+                return false;
+            }
         }
 
         // Check 3: URL must match configured patterns
         // If no patterns configured, slice all requests
-        if self.config.slice_patterns.is_empty() {
+        if self.config.slice_patterns.is_empty() && self.config.pattern_rules.is_empty() {
             debug!("Slicing enabled: no patterns configured, slicing all GET requests for uri={}", uri);
             return true;
         }
 
         // Check if URI matches any of the configured patterns
-        let matches = self.matches_pattern(uri);
+        let matches = self.matches_pattern(uri)
+            || self.match_rule(uri).is_some()
+            || matches!(self.cache_mode_for(uri), Some(CacheMode::Slice | CacheMode::Whole));
         if matches {
             debug!("Slicing enabled: uri={} matches configured patterns", uri);
         } else {
@@ -104,80 +194,161 @@ impl RequestAnalyzer {
         }
     }
 
-    /// Check if the URI matches any of the configured patterns
+    /// Extract the client's Range header, refusing a malformed single range
     ///
     /// # Arguments
-    /// * `uri` - Request URI to check
+    /// * `headers` - Request headers
     ///
     /// # Returns
-    /// `true` if URI matches any pattern, `false` otherwise
-    fn matches_pattern(&self, uri: &str) -> bool {
-        // For now, use simple string matching
-        // In a production implementation, you might want to use regex
-        for pattern in &self.config.slice_patterns {
-            if self.pattern_matches(pattern, uri) {
-                return true;
+    /// * `Ok(Some(ByteRange))` if a valid Range header is present
+    /// * `Ok(None)` if no Range header is present
+    /// * `Err(SliceError::ParseError)` if the header cannot be parsed or
+    ///   its range ends before it starts
+    pub fn client_range(&self, headers: &HeaderMap<HeaderValue>) -> Result<Option<ByteRange>> {
+        let Some(value) = headers.get("range") else {
+            return Ok(None);
+        };
+        let value = value
+            .to_str()
+            .map_err(|_| SliceError::ParseError("Range header is not valid text".to_string()))?;
+        match ByteRange::from_header(value) {
+            Ok(range) => Ok(Some(range)),
+            Err(SliceError::InvalidRange(msg)) => {
+                Err(SliceError::ParseError(format!("Malformed Range header '{}': {}", value, msg)))
             }
+            Err(e) => Err(e),
         }
-        false
     }
 
-    /// Check if a single pattern matches the URI
+    /// Whether the Range header names a single `bytes=start-end` range
+    fn has_single_range(&self, headers: &HeaderMap<HeaderValue>) -> bool {
+        let Some(range) = headers
+            .get("range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().strip_prefix("bytes="))
+        else {
+            return false;
+        };
+        range
+            .split_once('-')
+            .is_some_and(|(start, end)| start.trim().parse::<u64>().is_ok() && end.trim().parse::<u64>().is_ok())
+    }
+
+    /// Extract the client's If-Range header if present
     ///
-    /// Supports simple glob-style patterns:
-    /// - `*` matches any sequence of characters
-    /// - Exact string matching otherwise
+    /// # Arguments
+    /// * `headers` - Request headers
+    ///
+    /// # Returns
+    /// * `Some(String)` with the validator (an entity tag or HTTP-date)
+    /// * `None` if no If-Range header is present
+    pub fn extract_if_range(&self, headers: &HeaderMap<HeaderValue>) -> Option<String> {
+        headers
+            .get("if-range")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    }
+
+    /// Extract the client's If-None-Match header if present
     ///
     /// # Arguments
-    /// * `pattern` - Pattern to match against
-    /// * `uri` - URI to check
+    /// * `headers` - Request headers
     ///
     /// # Returns
-    /// `true` if pattern matches, `false` otherwise
-    fn pattern_matches(&self, pattern: &str, uri: &str) -> bool {
-        // Simple wildcard matching
-        if pattern.contains('*') {
-            let parts: Vec<&str> = pattern.split('*').collect();
-            
-            if parts.is_empty() {
-                return true;
-            }
+    /// * `Some(String)` with the entity tag list or `*`
+    /// * `None` if no If-None-Match header is present
+    pub fn extract_if_none_match(&self, headers: &HeaderMap<HeaderValue>) -> Option<String> {
+        headers
+            .get("if-none-match")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    }
 
-            // Check if URI starts with first part
-            if !parts[0].is_empty() && !uri.starts_with(parts[0]) {
-                return false;
-            }
+    /// Extract the client's If-Modified-Since header if present
+    ///
+    /// # Arguments
+    /// * `headers` - Request headers
+    ///
+    /// # Returns
+    /// * `Some(String)` with the HTTP-date
+    /// * `None` if no If-Modified-Since header is present
+    pub fn extract_if_modified_since(&self, headers: &HeaderMap<HeaderValue>) -> Option<String> {
+        headers
+            .get("if-modified-since")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    }
 
-            // Check if URI ends with last part
-            if parts.len() > 1 && !parts[parts.len() - 1].is_empty() && !uri.ends_with(parts[parts.len() - 1]) {
-                return false;
-            }
+    /// Find the first configured pattern rule matching the URI
+    ///
+    /// # Arguments
+    /// * `uri` - Request URI to check
+    ///
+    /// # Returns
+    /// * `Some(&PatternRule)` for the first rule whose pattern matches
+    /// * `None` if no rule matches
+    pub fn match_rule(&self, uri: &str) -> Option<&PatternRule> {
+        let idx = self.patterns.rules.iter().position(|p| p.matches(uri))?;
+        self.config.pattern_rules.get(idx)
+    }
 
-            // For middle parts, check if they appear in order
-            let mut current_pos = parts[0].len();
-            for i in 1..parts.len() - 1 {
-                if parts[i].is_empty() {
-                    continue;
-                }
-                if let Some(pos) = uri[current_pos..].find(parts[i]) {
-                    current_pos += pos + parts[i].len();
-                } else {
-                    return false;
-                }
-            }
+    /// Cache mode of the first configured cache mode rule matching the URI
+    ///
+    /// # Returns
+    /// * `Some(CacheMode)` for the first rule whose pattern matches
+    /// * `None` if no rule matches
+    pub fn cache_mode_for(&self, uri: &str) -> Option<CacheMode> {
+        let idx = self.patterns.cache_modes.iter().position(|p| p.matches(uri))?;
+        self.config.cache_mode_rules.get(idx).map(|rule| rule.mode)
+    }
 
-            true
-        } else {
-            // Exact match or prefix match
-            uri == pattern || uri.starts_with(pattern)
+    /// Cache TTL to use for slices of the given URI
+    ///
+    /// Returns the TTL of the first matching pattern rule that sets one,
+    /// falling back to the global `cache_ttl`.
+    pub fn cache_ttl_for(&self, uri: &str) -> Duration {
+        let ttl = self
+            .match_rule(uri)
+            .and_then(|rule| rule.cache_ttl)
+            .unwrap_or(self.config.cache_ttl);
+        Duration::from_secs(ttl)
+    }
+
+    /// Label of the configured pattern matching the URI, for metrics
+    ///
+    /// Returns the name (or pattern) of the first matching pattern rule,
+    /// else the first matching `slice_patterns` entry, else `"default"`.
+    /// Labels only ever come from the configuration, never from the URI,
+    /// so their number stays bounded.
+    pub fn pattern_label(&self, uri: &str) -> String {
+        if let Some(rule) = self.match_rule(uri) {
+            return rule.name.clone().unwrap_or_else(|| rule.pattern.clone());
         }
+        self.config
+            .slice_patterns
+            .iter()
+            .zip(&self.patterns.slice_patterns)
+            .find(|(_, compiled)| compiled.matches(uri))
+            .map(|(pattern, _)| pattern.clone())
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    /// Check if the URI matches any of the configured patterns
+    ///
+    /// # Arguments
+    /// * `uri` - Request URI to check
+    ///
+    /// # Returns
+    /// `true` if URI matches any pattern, `false` otherwise
+    fn matches_pattern(&self, uri: &str) -> bool {
+        self.patterns.slice_patterns.iter().any(|p| p.matches(uri))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::SliceConfig;
+    use crate::config::{CacheModeRule, SliceConfig};
 
     fn create_test_config(patterns: Vec<String>) -> Arc<SliceConfig> {
         Arc::new(SliceConfig {
@@ -186,6 +357,10 @@ mod tests {
         })
     }
 
+    fn matches(pattern: &str, uri: &str) -> bool {
+        Pattern::new(pattern).matches(uri)
+    }
+
     fn create_headers_with_range(range: &str) -> HeaderMap<HeaderValue> {
         let mut headers = HeaderMap::new();
         headers.insert("range", HeaderValue::from_str(range).unwrap());
@@ -211,11 +386,49 @@ mod tests {
     }
 
     #[test]
-    fn test_should_not_slice_with_range_header() {
+    fn test_should_slice_with_single_range_header() {
         let config = create_test_config(vec![]);
         let analyzer = RequestAnalyzer::new(config);
         let headers = create_headers_with_range("bytes=0-1023");
 
+        assert!(analyzer.should_slice(&Method::GET, "/test.bin", &headers));
+    }
+
+    #[test]
+    fn test_should_not_slice_multi_range_header() {
+        let config = create_test_config(vec![]);
+        let analyzer = RequestAnalyzer::new(config);
+
+        for range in ["bytes=0-99,200-299", "bytes=100-", "bytes=-500"] {
+            let headers = create_headers_with_range(range);
+            assert!(!analyzer.should_slice(&Method::GET, "/test.bin", &headers), "{}", range);
+        }
+    }
+
+    #[test]
+    fn test_reversed_range_refused() {
+        let analyzer = RequestAnalyzer::new(create_test_config(vec![]));
+        let headers = create_headers_with_range("bytes=500-100");
+
+        // Taken for slicing so that the proxy answers it, with a 400
+        assert!(analyzer.should_slice(&Method::GET, "/test.bin", &headers));
+        let error = analyzer.client_range(&headers).unwrap_err();
+        assert_eq!(error.to_http_status(), 400);
+
+        let headers = create_headers_with_range("bytes=100-500");
+        assert_eq!(analyzer.client_range(&headers).unwrap(), Some(ByteRange::new(100, 500).unwrap()));
+        assert_eq!(analyzer.client_range(&HeaderMap::new()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_should_not_slice_range_header_when_disabled() {
+        let config = Arc::new(SliceConfig {
+            slice_client_range_requests: false,
+            ..Default::default()
+        });
+        let analyzer = RequestAnalyzer::new(config);
+        let headers = create_headers_with_range("bytes=0-1023");
+
         assert!(!analyzer.should_slice(&Method::GET, "/test.bin", &headers));
     }
 
@@ -272,41 +485,178 @@ mod tests {
 
     #[test]
     fn test_pattern_matches_exact() {
-        let config = create_test_config(vec![]);
+        assert!(matches("/test", "/test"));
+        assert!(matches("/test", "/test/file.bin"));
+        assert!(!matches("/test", "/other"));
+    }
+
+    #[test]
+    fn test_pattern_matches_wildcard_suffix() {
+        assert!(matches("*.bin", "/file.bin"));
+        assert!(matches("*.bin", "/path/to/file.bin"));
+        assert!(!matches("*.bin", "/file.txt"));
+    }
+
+    #[test]
+    fn test_pattern_matches_wildcard_prefix() {
+        assert!(matches("/downloads/*", "/downloads/file.bin"));
+        assert!(matches("/downloads/*", "/downloads/"));
+        assert!(!matches("/downloads/*", "/uploads/file.bin"));
+    }
+
+    #[test]
+    fn test_pattern_matches_multiple_wildcards() {
+        assert!(matches("/*/files/*.bin", "/user/files/test.bin"));
+        assert!(matches("/*/files/*.bin", "/admin/files/data.bin"));
+        assert!(!matches("/*/files/*.bin", "/user/docs/test.txt"));
+    }
+
+    #[test]
+    fn test_pattern_rule_enables_slicing() {
+        let config = Arc::new(SliceConfig {
+            slice_patterns: vec!["/downloads/*".to_string()],
+            pattern_rules: vec![PatternRule {
+                pattern: "/api/*".to_string(),
+                name: None,
+                cache_ttl: Some(60),
+            }],
+            ..Default::default()
+        });
         let analyzer = RequestAnalyzer::new(config);
+        let headers = HeaderMap::new();
 
-        assert!(analyzer.pattern_matches("/test", "/test"));
-        assert!(analyzer.pattern_matches("/test", "/test/file.bin"));
-        assert!(!analyzer.pattern_matches("/test", "/other"));
+        assert!(analyzer.should_slice(&Method::GET, "/api/data.bin", &headers));
+        assert!(analyzer.should_slice(&Method::GET, "/downloads/file.bin", &headers));
+        assert!(!analyzer.should_slice(&Method::GET, "/other/file.bin", &headers));
     }
 
     #[test]
-    fn test_pattern_matches_wildcard_suffix() {
-        let config = create_test_config(vec![]);
+    fn test_cache_mode_rules() {
+        let config = Arc::new(SliceConfig {
+            slice_patterns: vec!["/downloads/*".to_string()],
+            cache_mode_rules: vec![
+                CacheModeRule {
+                    pattern: "/reports/*".to_string(),
+                    mode: CacheMode::Whole,
+                },
+                CacheModeRule {
+                    pattern: "/downloads/live/".to_string(),
+                    mode: CacheMode::Bypass,
+                },
+            ],
+            ..Default::default()
+        });
         let analyzer = RequestAnalyzer::new(config);
+        let headers = HeaderMap::new();
 
-        assert!(analyzer.pattern_matches("*.bin", "/file.bin"));
-        assert!(analyzer.pattern_matches("*.bin", "/path/to/file.bin"));
-        assert!(!analyzer.pattern_matches("*.bin", "/file.txt"));
+        assert_eq!(analyzer.cache_mode_for("/reports/q3.json"), Some(CacheMode::Whole));
+        assert_eq!(analyzer.cache_mode_for("/downloads/live/feed.ts"), Some(CacheMode::Bypass));
+        assert_eq!(analyzer.cache_mode_for("/downloads/file.bin"), None);
+
+        // A whole-object rule makes the URL eligible without a slice pattern
+        assert!(analyzer.should_slice(&Method::GET, "/reports/q3.json", &headers));
+        assert!(analyzer.should_slice(&Method::GET, "/downloads/file.bin", &headers));
+        assert!(!analyzer.should_slice(&Method::GET, "/other/file.bin", &headers));
     }
 
     #[test]
-    fn test_pattern_matches_wildcard_prefix() {
-        let config = create_test_config(vec![]);
+    fn test_cache_ttl_for_matched_rule() {
+        let config = Arc::new(SliceConfig {
+            cache_ttl: 3600,
+            pattern_rules: vec![
+                PatternRule {
+                    pattern: "/live/*".to_string(),
+                    name: None,
+                    cache_ttl: Some(5),
+                },
+                PatternRule {
+                    pattern: "/live/archive/*".to_string(),
+                    name: None,
+                    cache_ttl: Some(86400),
+                },
+                PatternRule {
+                    pattern: "*.mp4".to_string(),
+                    name: None,
+                    cache_ttl: None,
+                },
+            ],
+            ..Default::default()
+        });
         let analyzer = RequestAnalyzer::new(config);
 
-        assert!(analyzer.pattern_matches("/downloads/*", "/downloads/file.bin"));
-        assert!(analyzer.pattern_matches("/downloads/*", "/downloads/"));
-        assert!(!analyzer.pattern_matches("/downloads/*", "/uploads/file.bin"));
+        assert_eq!(analyzer.cache_ttl_for("/live/stream.ts"), Duration::from_secs(5));
+        // First matching rule wins
+        assert_eq!(analyzer.cache_ttl_for("/live/archive/a.ts"), Duration::from_secs(5));
+        // Matching rule without a TTL uses the global value
+        assert_eq!(analyzer.cache_ttl_for("/video.mp4"), Duration::from_secs(3600));
+        assert_eq!(analyzer.cache_ttl_for("/other.bin"), Duration::from_secs(3600));
+        assert!(analyzer.match_rule("/other.bin").is_none());
     }
 
     #[test]
-    fn test_pattern_matches_multiple_wildcards() {
-        let config = create_test_config(vec![]);
+    fn test_pattern_label() {
+        let config = Arc::new(SliceConfig {
+            slice_patterns: vec!["/downloads/*".to_string()],
+            pattern_rules: vec![
+                PatternRule {
+                    pattern: "/live/*".to_string(),
+                    name: Some("live".to_string()),
+                    cache_ttl: None,
+                },
+                PatternRule {
+                    pattern: "*.mp4".to_string(),
+                    name: None,
+                    cache_ttl: None,
+                },
+            ],
+            ..Default::default()
+        });
         let analyzer = RequestAnalyzer::new(config);
 
-        assert!(analyzer.pattern_matches("/*/files/*.bin", "/user/files/test.bin"));
-        assert!(analyzer.pattern_matches("/*/files/*.bin", "/admin/files/data.bin"));
-        assert!(!analyzer.pattern_matches("/*/files/*.bin", "/user/docs/test.txt"));
+        assert_eq!(analyzer.pattern_label("/live/stream.ts"), "live");
+        assert_eq!(analyzer.pattern_label("/video.mp4"), "*.mp4");
+        assert_eq!(analyzer.pattern_label("/downloads/file.bin"), "/downloads/*");
+        assert_eq!(analyzer.pattern_label("/other.bin"), "default");
+    }
+
+    /// Compares a shared analyzer with one compiled per request. Timings
+    /// depend on the machine, so it only reports them.
+    #[test]
+    #[ignore = "timing benchmark; run with --ignored --nocapture"]
+    fn bench_shared_analyzer_against_per_request_analyzer() {
+        let patterns = (0..48).map(|i| format!("/tenant{}/*/media/*.bin", i)).collect();
+        let config = create_test_config(patterns);
+        let headers = HeaderMap::new();
+        let uri = "/tenant47/videos/media/clip.bin";
+        let rounds = 2000;
+
+        // Best of several runs, so a busy machine skews less
+        let best = |per_request: bool| {
+            let shared = RequestAnalyzer::new(config.clone());
+            (0..5)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    for _ in 0..rounds {
+                        let analyzer = if per_request {
+                            RequestAnalyzer::new(config.clone())
+                        } else {
+                            shared.clone()
+                        };
+                        assert!(analyzer.should_slice(&Method::GET, uri, &headers));
+                    }
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let per_request = best(true);
+        let shared = best(false);
+        println!(
+            "{} requests, 48 patterns: shared {:?}, per request {:?} ({:.1}x)",
+            rounds,
+            shared,
+            per_request,
+            per_request.as_secs_f64() / shared.as_secs_f64().max(f64::EPSILON)
+        );
     }
 }
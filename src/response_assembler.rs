@@ -103,6 +103,32 @@ impl ResponseAssembler {
             HeaderValue::from_static("bytes"),
         );
 
+        Self::insert_validators(&mut headers, metadata)?;
+
+        debug!(
+            "Built response headers: status={}, content_length={:?}",
+            status,
+            headers.get("content-length").and_then(|v| v.to_str().ok())
+        );
+
+        Ok((status, headers))
+    }
+
+    /// Build headers for a `304 Not Modified` response
+    ///
+    /// Only the validators and Vary are sent; there is no body and no
+    /// Content-Length.
+    ///
+    /// # Arguments
+    /// * `metadata` - File metadata from the origin server
+    pub fn build_not_modified_header(&self, metadata: &FileMetadata) -> Result<(StatusCode, HeaderMap)> {
+        let mut headers = HeaderMap::new();
+        Self::insert_validators(&mut headers, metadata)?;
+        Ok((StatusCode::NOT_MODIFIED, headers))
+    }
+
+    /// Add ETag, Last-Modified and Vary from `metadata`
+    fn insert_validators(headers: &mut HeaderMap, metadata: &FileMetadata) -> Result<()> {
         // Set ETag if available
         if let Some(etag) = &metadata.etag {
             headers.insert(
@@ -121,13 +147,16 @@ impl ResponseAssembler {
             );
         }
 
-        debug!(
-            "Built response headers: status={}, content_length={:?}",
-            status,
-            headers.get("content-length").and_then(|v| v.to_str().ok())
-        );
+        // Pass Vary through so downstream caches keep variants apart
+        if !metadata.vary.is_empty() {
+            headers.insert(
+                "vary",
+                HeaderValue::from_str(&metadata.vary.join(", "))
+                    .map_err(|e| SliceError::AssemblyError(format!("Invalid header value: {}", e)))?,
+            );
+        }
 
-        Ok((status, headers))
+        Ok(())
     }
 
     /// Assemble slices into ordered data ready for streaming
@@ -180,6 +209,42 @@ impl ResponseAssembler {
         assembled_slices.into_values().collect()
     }
 
+    /// Trim ordered slice data to a byte window
+    ///
+    /// Used when slice-aligned slices were fetched for a client range: the
+    /// first `skip` bytes and everything after the next `length` bytes are
+    /// dropped. Slices are split without copying.
+    ///
+    /// # Arguments
+    /// * `slices` - Slice data in order
+    /// * `skip` - Bytes to drop from the front
+    /// * `length` - Bytes to keep after `skip`
+    ///
+    /// # Returns
+    /// The non-empty parts of `slices` inside the window
+    pub fn trim_to_range(&self, slices: Vec<Bytes>, skip: u64, length: u64) -> Vec<Bytes> {
+        let mut skip = skip as usize;
+        let mut remaining = length as usize;
+        let mut trimmed = Vec::with_capacity(slices.len());
+
+        for data in slices {
+            if remaining == 0 {
+                break;
+            }
+            if skip >= data.len() {
+                skip -= data.len();
+                continue;
+            }
+            let end = std::cmp::min(data.len(), skip + remaining);
+            let part = data.slice(skip..end);
+            remaining -= part.len();
+            skip = 0;
+            trimmed.push(part);
+        }
+
+        trimmed
+    }
+
     /// Validate that all expected slices are present
     ///
     /// # Arguments
@@ -292,6 +357,18 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_build_not_modified_header() {
+        let assembler = ResponseAssembler::new();
+        let metadata = create_test_metadata();
+
+        let (status, headers) = assembler.build_not_modified_header(&metadata).unwrap();
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(headers.get("etag").unwrap(), "\"abc123\"");
+        assert_eq!(headers.get("last-modified").unwrap(), "Wed, 21 Oct 2015 07:28:00 GMT");
+        assert!(headers.get("content-length").is_none());
+    }
+
     #[test]
     fn test_assemble_slices_ordered() {
         let assembler = ResponseAssembler::new();
@@ -375,6 +452,23 @@ mod tests {
         assert_eq!(streamed[2], Bytes::from("slice2"));
     }
 
+    #[test]
+    fn test_trim_to_range() {
+        let assembler = ResponseAssembler::new();
+        let slices = vec![
+            Bytes::from("slice0"),
+            Bytes::from("slice1"),
+            Bytes::from("slice2"),
+        ];
+
+        let trimmed = assembler.trim_to_range(slices.clone(), 4, 10);
+        assert_eq!(trimmed, vec![Bytes::from("e0"), Bytes::from("slice1"), Bytes::from("sl")]);
+
+        // A window inside one slice
+        let trimmed = assembler.trim_to_range(slices, 7, 3);
+        assert_eq!(trimmed, vec![Bytes::from("lic")]);
+    }
+
     #[test]
     fn test_validate_completeness_success() {
         let assembler = ResponseAssembler::new();
@@ -0,0 +1,1052 @@
+//! Standalone HTTP server running a [`SliceProxy`]
+//!
+//! [`SliceServer`] drives the proxy's request hooks from a hyper HTTP/1
+//! server so the crate can run without a hand-written `main`: sliced
+//! requests are streamed from [`SliceProxy::stream_slice_request`], anything
+//! else is forwarded to [`SliceProxy::upstream_peer`]. With `purge.enabled`
+//! the PURGE, cache admin and cache warm endpoints are served as well, on
+//! the proxy's cache. [`run_slice_server`]
+//! loads a configuration file and runs the proxy, plus the metrics endpoint
+//! and its health checks if enabled, until SIGINT or SIGTERM, reloading the
+//! file on SIGHUP.
+
+use crate::access_log::AccessLogger;
+use crate::cache_admin::CacheAdminHandler;
+use crate::cache_key::CacheKeyBuilder;
+use crate::cache_warmer::{CacheWarmer, WarmHandler};
+use crate::client_pacer::ClientPacer;
+use crate::config::{HostHeaderMode, SliceConfig, SocketConfig};
+use crate::error::{Result, SliceError};
+use crate::health::HealthChecker;
+use crate::metrics_endpoint::MetricsEndpoint;
+use crate::proxy::{SliceBody, SliceContext, SliceProxy};
+use crate::purge_handler::PurgeHandler;
+use crate::tiered_cache::TieredCache;
+use bytes::{Bytes, BytesMut};
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::{Method, Request, Response, StatusCode};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Body, Frame, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::graceful::GracefulShutdown;
+use socket2::{SockRef, TcpKeepalive};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, warn};
+
+/// Body of the responses sent by [`SliceServer`]
+pub type ServerBody = BoxBody<Bytes, Infallible>;
+
+/// How long in-flight requests may run after shutdown is requested
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+/// How long a request with `Expect: 100-continue` waits for the upstream's
+/// `100 Continue` before its body is sent anyway
+const EXPECT_CONTINUE_WAIT: Duration = Duration::from_secs(1);
+
+/// How long opening an upstream connection of our own may take
+const UPSTREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Headers that only apply to a single connection and are not forwarded
+const HOP_BY_HOP: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "upgrade",
+];
+
+/// HTTP server answering client requests through a [`SliceProxy`]
+pub struct SliceServer {
+    current: Arc<RwLock<Arc<Handler>>>,
+    listener: TcpListener,
+    socket: SocketConfig,
+}
+
+impl SliceServer {
+    /// Bind the server to `addr`
+    ///
+    /// Use port 0 for an ephemeral port and [`local_addr`](Self::local_addr)
+    /// to find out which one was picked. The proxy's `socket` options apply
+    /// to the listener and every accepted connection.
+    pub async fn bind(proxy: SliceProxy, addr: &str) -> Result<Self> {
+        let socket = proxy.config().socket.clone();
+        let listener = listen(addr, &socket)
+            .await
+            .map_err(|e| SliceError::ConfigError(format!("Failed to listen on {}: {}", addr, e)))?;
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| SliceError::InternalError(format!("Failed to create HTTP client: {}", e)))?;
+        let warmer = Arc::new(
+            CacheWarmer::new(Arc::new(proxy.config().clone()), proxy.cache_arc()).with_metrics(proxy.metrics_arc()),
+        );
+        let admin = AdminEndpoints::from_proxy(&proxy, &warmer);
+        Ok(SliceServer {
+            current: Arc::new(RwLock::new(Arc::new(Handler { proxy, client, warmer, admin }))),
+            listener,
+            socket,
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener
+            .local_addr()
+            .map_err(|e| SliceError::InternalError(format!("Failed to read listen address: {}", e)))
+    }
+
+    /// The proxy new requests are answered with
+    pub fn proxy(&self) -> SliceProxy {
+        self.reload_handle().proxy()
+    }
+
+    /// Warmer filling the proxy's cache, shared with the `/admin/warm` endpoints
+    pub fn warmer(&self) -> Arc<CacheWarmer> {
+        self.reload_handle().handler().warmer.clone()
+    }
+
+    /// Handle for swapping the proxy's configuration while serving
+    pub fn reload_handle(&self) -> ReloadHandle {
+        ReloadHandle {
+            current: self.current.clone(),
+        }
+    }
+
+    /// Serve requests until `shutdown` completes
+    ///
+    /// Once it does, no new connections are accepted and in-flight requests
+    /// get up to 30 seconds to finish. The cache expiry sweep, if
+    /// configured, runs for as long as the server does.
+    pub async fn serve_until(self, shutdown: impl Future<Output = ()>) -> Result<()> {
+        let graceful = GracefulShutdown::new();
+        let expiry_sweep = self.proxy().spawn_expiry_sweep();
+        tokio::pin!(shutdown);
+
+        loop {
+            let (stream, client_addr) = tokio::select! {
+                accepted = self.listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+            if let Err(e) = apply_socket_options(&stream, &self.socket) {
+                warn!("Failed to set socket options for {}: {}", client_addr, e);
+            }
+
+            // Each request runs on the proxy current when it arrived
+            let current = self.current.clone();
+            let service = service_fn(move |req| {
+                let handler = current.read().unwrap_or_else(|e| e.into_inner()).clone();
+                async move { Ok::<_, Infallible>(handler.handle(req, client_addr).await) }
+            });
+            let conn = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            let conn = graceful.watch(conn);
+            tokio::spawn(async move {
+                if let Err(e) = conn.await {
+                    debug!("Error serving connection from {}: {:?}", client_addr, e);
+                }
+            });
+        }
+
+        info!("Shutting down, waiting for in-flight requests");
+        if tokio::time::timeout(SHUTDOWN_GRACE, graceful.shutdown()).await.is_err() {
+            warn!("Shutdown grace period elapsed with requests still in flight");
+        }
+        if let Some(sweep) = expiry_sweep {
+            sweep.abort();
+        }
+        Ok(())
+    }
+}
+
+/// Swaps the configuration a [`SliceServer`] answers new requests with
+///
+/// Requests already in flight finish on the configuration they started with.
+#[derive(Clone)]
+pub struct ReloadHandle {
+    current: Arc<RwLock<Arc<Handler>>>,
+}
+
+impl ReloadHandle {
+    /// The proxy new requests are answered with
+    pub fn proxy(&self) -> SliceProxy {
+        self.handler().proxy.clone()
+    }
+
+    /// Answer new requests with `config`
+    ///
+    /// See [`SliceProxy::reloaded`]: fields that need a restart keep their
+    /// running values, and the names of those that changed are returned.
+    ///
+    /// # Errors
+    /// Returns a configuration error, and keeps serving with the running
+    /// configuration, if `config` is invalid
+    pub fn reload(&self, config: SliceConfig) -> Result<Vec<&'static str>> {
+        let running = self.handler();
+        let (proxy, ignored) = running.proxy.reloaded(config)?;
+        let handler = Arc::new(Handler {
+            admin: AdminEndpoints::from_proxy(&proxy, &running.warmer),
+            proxy,
+            client: running.client.clone(),
+            warmer: running.warmer.clone(),
+        });
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = handler;
+        Ok(ignored)
+    }
+
+    fn handler(&self) -> Arc<Handler> {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Per-configuration state shared by every connection
+struct Handler {
+    proxy: SliceProxy,
+    client: reqwest::Client,
+    /// Kept over reloads so a running warm job carries on
+    warmer: Arc<CacheWarmer>,
+    admin: Option<AdminEndpoints>,
+}
+
+/// PURGE, cache admin and cache warm endpoints, served with `purge.enabled`
+///
+/// All three act on the proxy's cache and require `purge.auth_token` when
+/// one is set.
+struct AdminEndpoints {
+    purge: PurgeHandler,
+    cache_admin: CacheAdminHandler,
+    warm: WarmHandler,
+    /// Host purged URLs are keyed under, unless the client's is preserved
+    upstream_host: Option<HeaderValue>,
+}
+
+impl AdminEndpoints {
+    /// Endpoints for `proxy`, if its configuration enables them
+    fn from_proxy(proxy: &SliceProxy, warmer: &Arc<CacheWarmer>) -> Option<Self> {
+        let config = proxy.config();
+        let purge_config = config.purge.as_ref().filter(|purge| purge.enabled)?;
+        let shared_config = Arc::new(config.clone());
+        let cache = proxy.cache_arc();
+        let (purge, cache_admin, warm) = match &purge_config.auth_token {
+            Some(token) => (
+                PurgeHandler::with_auth(cache.clone(), token.clone()),
+                CacheAdminHandler::with_auth(cache, token.clone()),
+                WarmHandler::with_auth(warmer.clone(), token.clone()),
+            ),
+            None => (
+                PurgeHandler::new(cache.clone()),
+                CacheAdminHandler::new(cache),
+                WarmHandler::new(warmer.clone()),
+            ),
+        };
+        let upstream_host = match config.host_header {
+            HostHeaderMode::Preserve => None,
+            HostHeaderMode::Upstream => HeaderValue::from_str(&config.upstream_address).ok(),
+        };
+        Some(AdminEndpoints {
+            purge: purge
+                .with_metadata_cache(proxy.metadata_cache_arc())
+                .with_cache_keys(CacheKeyBuilder::from_config(config))
+                .with_slice_size(config.slice_size),
+            cache_admin: cache_admin.with_config(shared_config),
+            warm,
+            upstream_host,
+        })
+    }
+
+    /// Whether `parts` is a request for one of the endpoints
+    fn matches(parts: &http::request::Parts) -> bool {
+        parts.method.as_str() == "PURGE"
+            || CacheAdminHandler::matches(parts.uri.path())
+            || WarmHandler::matches(parts.uri.path())
+    }
+
+    async fn handle(&self, mut req: Request<Incoming>) -> Response<ServerBody> {
+        let result = if req.method().as_str() == "PURGE" {
+            // Purge the key the proxy cached the URL under
+            if let Some(host) = &self.upstream_host {
+                req.headers_mut().insert(header::HOST, host.clone());
+                req.headers_mut().remove("x-forwarded-proto");
+            }
+            if PurgeHandler::is_batch_request(&req) {
+                self.purge.handle_batch_purge(req).await
+            } else {
+                self.purge.handle_purge(req).await
+            }
+        } else if CacheAdminHandler::matches(req.uri().path()) {
+            self.cache_admin.handle_request(req).await
+        } else {
+            self.warm.handle_request(req).await
+        };
+        match result {
+            Ok(response) => response.map(|body| body.boxed()),
+            Err(e) => {
+                error!("Admin request failed: {}", e);
+                let mut headers = HeaderMap::new();
+                headers.insert(header::CONTENT_LENGTH, HeaderValue::from(0));
+                response(StatusCode::INTERNAL_SERVER_ERROR, headers, Full::new(Bytes::new()).boxed())
+            }
+        }
+    }
+}
+
+/// A response whose body is still being produced
+struct Reply {
+    status: StatusCode,
+    headers: HeaderMap,
+    chunks: Chunks,
+}
+
+/// Source of a response body
+enum Chunks {
+    /// Slices assembled by the proxy, in index order as they are ready
+    Slices(Box<SliceBody>),
+    /// Read from a proxied upstream response
+    Upstream(UpstreamBody),
+}
+
+/// Body of a proxied upstream response
+enum UpstreamBody {
+    /// Received through the shared client
+    Client(reqwest::Response),
+    /// Received on a connection of its own
+    Connection(Incoming),
+}
+
+impl UpstreamBody {
+    /// Next chunk of the body, or `None` once it is complete
+    async fn chunk(&mut self) -> std::result::Result<Option<Bytes>, String> {
+        match self {
+            UpstreamBody::Client(response) => response.chunk().await.map_err(|e| e.to_string()),
+            UpstreamBody::Connection(body) => loop {
+                match body.frame().await {
+                    Some(Ok(frame)) => {
+                        if let Ok(data) = frame.into_data() {
+                            return Ok(Some(data));
+                        }
+                    }
+                    Some(Err(e)) => return Err(e.to_string()),
+                    None => return Ok(None),
+                }
+            },
+        }
+    }
+}
+
+impl Handler {
+    async fn handle(self: Arc<Self>, req: Request<Incoming>, client_addr: SocketAddr) -> Response<ServerBody> {
+        let start = Instant::now();
+        let (parts, body) = req.into_parts();
+        let path = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+        let uri = format!("http://{}{}", self.proxy.config().upstream_address, path);
+
+        let mut ctx = SliceContext::new();
+        ctx.client_addr = Some(client_addr.to_string());
+
+        // Refuse oversized requests before any cache or origin work
+        if let Some(status) = self.oversized(&parts) {
+            warn!("Rejecting request from {} with {}: uri={}", client_addr, status, uri);
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(0));
+            headers.insert(header::CONNECTION, HeaderValue::from_static("close"));
+            ctx.set_response(status.as_u16(), 0);
+            self.proxy.logging(&parts.method, &uri, &ctx, None, start.elapsed().as_millis() as u64);
+            return response(status, headers, Full::new(Bytes::new()).boxed());
+        }
+
+        if let Some(admin) = self.admin.as_ref().filter(|_| AdminEndpoints::matches(&parts)) {
+            return admin.handle(Request::from_parts(parts, body)).await;
+        }
+
+        ctx.deadline = self
+            .proxy
+            .config()
+            .request_timeout_secs
+            .map(|secs| start + Duration::from_secs(secs));
+        let deadline = ctx.deadline;
+
+        // Dropping the work on timeout also cancels its slice fetches
+        let work = async {
+            match self
+                .proxy
+                .request_filter(&parts.method, &uri, &parts.headers, &mut ctx)
+                .await
+            {
+                Ok(false) => self.proxy.stream_slice_request(&uri, &ctx).await.map(|(status, headers, body)| {
+                    // Stale copies may have stood in for slices the origin failed
+                    ctx.served_stale |= body.served_stale();
+                    Reply {
+                        status,
+                        headers,
+                        chunks: Chunks::Slices(Box::new(body)),
+                    }
+                }),
+                Ok(true) => self.forward(&parts.method, path, parts.headers, body, &ctx).await,
+                Err(e) => Err(e),
+            }
+        };
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), work)
+                .await
+                .unwrap_or_else(|_| Err(SliceError::DeadlineExceeded("request_timeout_secs elapsed".to_string()))),
+            None => work.await,
+        };
+
+        let reply = match result {
+            Ok(reply) => reply,
+            Err(e) => {
+                let (status, headers, body) = self.proxy.fail_to_proxy(&e, &mut ctx).unwrap_or_else(|| {
+                    let status = StatusCode::from_u16(e.to_http_status()).unwrap_or(StatusCode::BAD_GATEWAY);
+                    let mut headers = HeaderMap::new();
+                    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(0));
+                    ctx.set_response(status.as_u16(), 0);
+                    (status, headers, Bytes::new())
+                });
+                let elapsed = start.elapsed().as_millis() as u64;
+                self.proxy.logging(&parts.method, &uri, &ctx, Some(&e), elapsed);
+                return response(status, headers, Full::new(body).boxed());
+            }
+        };
+
+        // The body is sent from a task so the pacer can hold it back
+        let (tx, rx) = mpsc::channel(4);
+        let Reply { status, headers, chunks } = reply;
+        let handler = self.clone();
+        tokio::spawn(async move {
+            let sent = handler.send_body(&uri, chunks, &tx, &mut ctx).await;
+            ctx.set_response(status.as_u16(), sent);
+            let elapsed = start.elapsed().as_millis() as u64;
+            handler.proxy.logging(&parts.method, &uri, &ctx, None, elapsed);
+        });
+        response(status, headers, ChannelBody { rx }.boxed())
+    }
+
+    /// Status refusing a request over `max_uri_bytes` or
+    /// `max_request_header_bytes`, if it is
+    fn oversized(&self, parts: &http::request::Parts) -> Option<StatusCode> {
+        let config = self.proxy.config();
+        if config
+            .max_uri_bytes
+            .is_some_and(|max| parts.uri.to_string().len() > max)
+        {
+            return Some(StatusCode::URI_TOO_LONG);
+        }
+        // Counted as sent: `name: value\r\n`
+        let header_bytes = || {
+            parts
+                .headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len() + 4)
+                .sum::<usize>()
+        };
+        if config
+            .max_request_header_bytes
+            .is_some_and(|max| header_bytes() > max)
+        {
+            return Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+        }
+        None
+    }
+
+    /// Forward a request the proxy does not slice to the upstream
+    async fn forward(
+        &self,
+        method: &Method,
+        path: &str,
+        mut headers: HeaderMap,
+        body: Incoming,
+        ctx: &SliceContext,
+    ) -> Result<Reply> {
+        let peer = self.proxy.upstream_peer(ctx)?;
+        strip_hop_by_hop(&mut headers);
+        headers.remove(header::HOST);
+        self.proxy.upstream_request_filter(&mut headers, ctx)?;
+
+        let upstream_start = Instant::now();
+        let (status, mut headers, body) = if expects_continue(&headers) {
+            self.forward_expect_continue(method, &peer, path, headers, body).await?
+        } else {
+            // The body is passed on as it arrives, not held in memory
+            let response = self
+                .client
+                .request(method.clone(), format!("http://{}{}", peer, path))
+                .headers(headers)
+                .body(reqwest::Body::wrap(body))
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_timeout() {
+                        SliceError::Timeout(format!("Upstream {} timed out: {}", peer, e))
+                    } else {
+                        SliceError::HttpError(format!("Upstream {} failed: {}", peer, e))
+                    }
+                })?;
+            (response.status(), response.headers().clone(), UpstreamBody::Client(response))
+        };
+        debug!("Upstream responded in {:?}", upstream_start.elapsed());
+
+        strip_hop_by_hop(&mut headers);
+        headers.remove(header::TRANSFER_ENCODING);
+        self.proxy.upstream_response_filter(&mut headers, ctx);
+        Ok(Reply {
+            status,
+            headers,
+            chunks: Chunks::Upstream(body),
+        })
+    }
+
+    /// Forward a request carrying `Expect: 100-continue`
+    ///
+    /// reqwest sends the body straight after the headers and hides interim
+    /// responses, so these requests get an upstream connection of their
+    /// own. The client body is only read, which is what makes hyper send
+    /// the client its `100 Continue`, once the upstream answers 100 or
+    /// [`EXPECT_CONTINUE_WAIT`] passes without an answer. A final status
+    /// sent instead is relayed without the body being read at all.
+    async fn forward_expect_continue(
+        &self,
+        method: &Method,
+        peer: &str,
+        path: &str,
+        headers: HeaderMap,
+        body: Incoming,
+    ) -> Result<(StatusCode, HeaderMap, UpstreamBody)> {
+        let failed = |e: &dyn std::fmt::Display| SliceError::HttpError(format!("Upstream {} failed: {}", peer, e));
+        let stream = tokio::time::timeout(UPSTREAM_CONNECT_TIMEOUT, TcpStream::connect(peer))
+            .await
+            .map_err(|_| SliceError::Timeout(format!("Connecting to upstream {} timed out", peer)))?
+            .map_err(|e| failed(&e))?;
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|e| failed(&e))?;
+        let peer_name = peer.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = conn.await {
+                debug!("Error on upstream connection to {}: {:?}", peer_name, e);
+            }
+        });
+
+        let (body_tx, body_rx) = mpsc::channel(4);
+        let mut request = Request::builder()
+            .method(method.clone())
+            .uri(path)
+            .body(ChannelBody { rx: body_rx })
+            .map_err(|e| SliceError::InternalError(format!("Failed to build upstream request: {}", e)))?;
+        *request.headers_mut() = headers;
+        let host = HeaderValue::from_str(peer).map_err(|e| failed(&e))?;
+        request.headers_mut().insert(header::HOST, host);
+
+        let (continue_tx, continue_rx) = oneshot::channel();
+        let continue_tx = std::sync::Mutex::new(Some(continue_tx));
+        hyper::ext::on_informational(&mut request, move |response| {
+            if response.status() == StatusCode::CONTINUE {
+                if let Some(tx) = continue_tx.lock().unwrap().take() {
+                    let _ = tx.send(());
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            // hyper drops the callback, and with it the sender, once a
+            // final status arrives: the body is then never read
+            if let Ok(Err(_)) = tokio::time::timeout(EXPECT_CONTINUE_WAIT, continue_rx).await {
+                return;
+            }
+            let mut body = body;
+            while let Some(frame) = body.frame().await {
+                match frame {
+                    Ok(frame) => {
+                        if let Ok(data) = frame.into_data() {
+                            if body_tx.send(data).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to read request body: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let response = sender.send_request(request).await.map_err(|e| failed(&e))?;
+        let (parts, body) = response.into_parts();
+        Ok((parts.status, parts.headers, UpstreamBody::Connection(body)))
+    }
+
+    /// Send a body to the client, paced to its download limit
+    ///
+    /// # Returns
+    /// The number of bytes sent
+    async fn send_body(&self, uri: &str, chunks: Chunks, tx: &mpsc::Sender<Bytes>, ctx: &mut SliceContext) -> u64 {
+        let mut sent = 0;
+        match chunks {
+            Chunks::Slices(mut body) => {
+                while let Some(slice) = body.next_slice().await {
+                    let slice = match slice {
+                        Ok(slice) => slice,
+                        Err(e) => {
+                            warn!("Cutting off sliced body after {} bytes: {}", sent, e);
+                            return sent;
+                        }
+                    };
+                    let mut chunks = ClientPacer::split(&slice).peekable();
+                    while let Some(chunk) = chunks.next() {
+                        let last = chunks.peek().is_none() && body.remaining() == 0;
+                        match self.send_chunk(chunk, last, tx, ctx).await {
+                            Some(len) => sent += len,
+                            None => {
+                                if ctx.client_abort.is_aborted() {
+                                    self.proxy.abort_slice_body(uri, ctx, *body).await;
+                                }
+                                return sent;
+                            }
+                        }
+                    }
+                }
+            }
+            Chunks::Upstream(mut response) => {
+                let config = self.proxy.config();
+                let mut buffer = config.response_buffer_size.map(|size| {
+                    ResponseBuffer::new(size, Duration::from_millis(config.response_buffer_flush_ms))
+                });
+                let request_deadline = ctx.deadline.map(tokio::time::Instant::from_std);
+                loop {
+                    let flush_deadline = buffer.as_ref().and_then(|buffer| buffer.deadline);
+                    let next = match flush_deadline.into_iter().chain(request_deadline).min() {
+                        Some(deadline) => tokio::time::timeout_at(deadline, response.chunk()).await,
+                        None => Ok(response.chunk().await),
+                    };
+                    if let Err(e) = ctx.check_deadline() {
+                        warn!("Cutting off upstream body after {} bytes: {}", sent, e);
+                        self.proxy.metrics().record_deadline_abort();
+                        return sent;
+                    }
+                    let (writes, done) = match (next, buffer.as_mut()) {
+                        // Held bytes waited long enough for more
+                        (Err(_), buffer) => (buffer.map(ResponseBuffer::take).unwrap_or_default(), false),
+                        (Ok(Ok(Some(chunk))), Some(buffer)) => (buffer.push(chunk), false),
+                        (Ok(Ok(Some(chunk))), None) => (vec![chunk], false),
+                        (Ok(Ok(None)), buffer) => (buffer.map(ResponseBuffer::take).unwrap_or_default(), true),
+                        (Ok(Err(e)), buffer) => {
+                            warn!("Upstream body failed after {} bytes: {}", sent, e);
+                            (buffer.map(ResponseBuffer::take).unwrap_or_default(), true)
+                        }
+                    };
+                    for chunk in writes {
+                        match self.send_chunk(chunk, false, tx, ctx).await {
+                            Some(len) => sent += len,
+                            None => return sent,
+                        }
+                    }
+                    if done {
+                        break;
+                    }
+                }
+            }
+        }
+        sent
+    }
+
+    /// Send one chunk, then wait as long as the pacer asks
+    ///
+    /// # Returns
+    /// The chunk length, or `None` if the client went away or the
+    /// request's deadline passed
+    async fn send_chunk(
+        &self,
+        chunk: Bytes,
+        last: bool,
+        tx: &mpsc::Sender<Bytes>,
+        ctx: &mut SliceContext,
+    ) -> Option<u64> {
+        let len = chunk.len() as u64;
+        let delay = match self.proxy.response_body_filter(&Some(chunk.clone()), last, ctx) {
+            Ok(delay) => delay,
+            Err(e) => {
+                warn!("Cutting off response body: {}", e);
+                self.proxy.metrics().record_deadline_abort();
+                return None;
+            }
+        };
+        if tx.send(chunk).await.is_err() {
+            debug!("Client disconnected before the response completed");
+            ctx.client_abort.abort();
+            return None;
+        }
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        Some(len)
+    }
+}
+
+/// Joins small upstream chunks into client writes of up to `capacity` bytes
+///
+/// Bytes are held until the buffer fills, the body ends, or `flush_after`
+/// has passed since the first held byte, so a slow stream still reaches
+/// the client promptly. Chunks of `capacity` bytes or more are never
+/// copied: the held bytes are written, then the chunk as is.
+struct ResponseBuffer {
+    capacity: usize,
+    flush_after: Duration,
+    held: BytesMut,
+    /// When the held bytes must be written, if any are held
+    deadline: Option<tokio::time::Instant>,
+}
+
+impl ResponseBuffer {
+    fn new(capacity: usize, flush_after: Duration) -> Self {
+        ResponseBuffer {
+            capacity,
+            flush_after,
+            held: BytesMut::new(),
+            deadline: None,
+        }
+    }
+
+    /// Add `chunk`, returning the writes due now
+    fn push(&mut self, chunk: Bytes) -> Vec<Bytes> {
+        if chunk.len() >= self.capacity {
+            let mut writes = self.take();
+            writes.push(chunk);
+            return writes;
+        }
+        if self.held.is_empty() {
+            self.held.reserve(self.capacity);
+            self.deadline = Some(tokio::time::Instant::now() + self.flush_after);
+        }
+        self.held.extend_from_slice(&chunk);
+        if self.held.len() >= self.capacity {
+            self.take()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Take the held bytes as one write, if there are any
+    fn take(&mut self) -> Vec<Bytes> {
+        self.deadline = None;
+        if self.held.is_empty() {
+            return Vec::new();
+        }
+        vec![self.held.split().freeze()]
+    }
+}
+
+/// Body streamed from the task producing it
+struct ChannelBody {
+    rx: mpsc::Receiver<Bytes>,
+}
+
+impl Body for ChannelBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, Infallible>>> {
+        self.rx.poll_recv(cx).map(|chunk| chunk.map(|data| Ok(Frame::data(data))))
+    }
+}
+
+/// Listen on `addr`, with SO_REUSEADDR as configured
+async fn listen(addr: &str, socket: &SocketConfig) -> std::io::Result<TcpListener> {
+    let addr = tokio::net::lookup_host(addr)
+        .await?
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "address resolved to nothing"))?;
+    let listener = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    listener.set_reuseaddr(socket.so_reuseaddr)?;
+    listener.bind(addr)?;
+    listener.listen(1024)
+}
+
+/// Apply the configured options to an accepted connection
+fn apply_socket_options(stream: &TcpStream, socket: &SocketConfig) -> std::io::Result<()> {
+    stream.set_nodelay(socket.tcp_nodelay)?;
+    let sock = SockRef::from(stream);
+    if let Some(size) = socket.send_buffer_size {
+        sock.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = socket.recv_buffer_size {
+        sock.set_recv_buffer_size(size)?;
+    }
+    if let Some(secs) = socket.keepalive_secs {
+        sock.set_tcp_keepalive(&TcpKeepalive::new().with_time(Duration::from_secs(secs)))?;
+    }
+    Ok(())
+}
+
+fn response(status: StatusCode, headers: HeaderMap, body: ServerBody) -> Response<ServerBody> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    response
+}
+
+/// Whether the client waits for `100 Continue` before sending its body
+fn expects_continue(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::EXPECT)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"100-continue"))
+}
+
+/// Remove hop-by-hop headers, including those named in `Connection`
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in named {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP {
+        headers.remove(name);
+    }
+}
+
+/// Wait for SIGINT, or SIGTERM on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+    info!("Shutdown signal received");
+}
+
+/// Reload `config_path` into `handle` on every SIGHUP
+///
+/// A file that fails to load or validate is logged and the running
+/// configuration stays in place.
+#[cfg(unix)]
+async fn reload_on_sighup(config_path: String, handle: ReloadHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        info!("SIGHUP received, reloading {}", config_path);
+        match SliceConfig::load(&config_path).and_then(|config| handle.reload(config)) {
+            Ok(ignored) => {
+                for field in ignored {
+                    warn!("{} changed but only takes effect after a restart, keeping the running value", field);
+                }
+                info!("Configuration reloaded");
+            }
+            Err(e) => error!("Failed to reload {}, keeping the running configuration: {}", config_path, e),
+        }
+    }
+}
+
+/// Load `config_path` and run the proxy server until SIGINT or SIGTERM
+///
+/// Forks into the background first when `daemon` is set, then builds a
+/// runtime with `threads` workers, writes `pid_file` if set, serves
+/// the proxy on `listen_address` and, when `metrics_endpoint` is enabled,
+/// the metrics endpoint on its own address. On SIGHUP the file is loaded
+/// again and new requests use it (see [`ReloadHandle::reload`]).
+pub fn run_slice_server(config_path: &str) -> Result<()> {
+    run_slice_server_warming(config_path, Vec::new())
+}
+
+/// [`run_slice_server`], warming the proxy's cache with `warm_urls` once
+/// it is listening
+///
+/// The job runs on the server's [`CacheWarmer`](SliceServer::warmer), so
+/// its progress shows up on `/admin/warm/status` when `purge.enabled` is set.
+pub fn run_slice_server_warming(config_path: &str, warm_urls: Vec<String>) -> Result<()> {
+    let config = Arc::new(SliceConfig::load(config_path)?);
+
+    // Fork before the runtime starts any threads
+    if config.daemon {
+        daemonize()?;
+    }
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    if let Some(threads) = config.threads {
+        runtime.worker_threads(threads);
+    }
+    let runtime = runtime
+        .enable_all()
+        .build()
+        .map_err(|e| SliceError::InternalError(format!("Failed to start runtime: {}", e)))?;
+
+    if let Some(pid_file) = &config.pid_file {
+        std::fs::write(pid_file, format!("{}\n", std::process::id()))
+            .map_err(|e| SliceError::ConfigError(format!("Failed to write pid_file {}: {}", pid_file, e)))?;
+    }
+
+    let result = runtime.block_on(async {
+        let cache = Arc::new(TieredCache::from_config(&config).await?);
+        let mut proxy = SliceProxy::with_cache(config.clone(), cache.clone());
+        if let Some(access_log) = config.access_log.as_ref().filter(|log| log.enabled) {
+            proxy = proxy.with_access_logger(Arc::new(AccessLogger::from_config(access_log)?));
+        }
+
+        if let Some(endpoint) = config.metrics_endpoint.as_ref().filter(|m| m.enabled) {
+            let addr = endpoint.address.parse().map_err(|e| {
+                SliceError::ConfigError(format!("Invalid metrics_endpoint.address: {}", e))
+            })?;
+            let mut health = HealthChecker::new(&config, proxy.metrics_arc()).with_cache(cache.clone());
+            if config.enable_l2_cache {
+                health = health.with_disk_dir(&config.l2_cache_dir);
+            }
+            let health = Arc::new(health);
+            health.spawn_probe();
+            let mut metrics = MetricsEndpoint::new(proxy.metrics_arc(), addr)
+                .with_path(&endpoint.path)
+                .with_health_checker(health);
+            if let Some(auth) = &endpoint.basic_auth {
+                metrics = metrics.with_basic_auth(&auth.username, &auth.password);
+            }
+            tokio::spawn(async move {
+                if let Err(e) = metrics.start().await {
+                    error!("Metrics endpoint failed: {}", e);
+                }
+            });
+        }
+
+        let server = SliceServer::bind(proxy, &config.listen_address).await?;
+        info!("Slice proxy listening on http://{}", server.local_addr()?);
+        if !warm_urls.is_empty() {
+            info!("Warming {} URLs", warm_urls.len());
+            server.warmer().start(warm_urls)?;
+        }
+        #[cfg(unix)]
+        tokio::spawn(reload_on_sighup(config_path.to_string(), server.reload_handle()));
+        server.serve_until(shutdown_signal()).await
+    });
+
+    if let Some(pid_file) = &config.pid_file {
+        let _ = std::fs::remove_file(pid_file);
+    }
+    result
+}
+
+/// Detach from the terminal, keeping the working directory so relative paths
+/// in the configuration still resolve
+#[cfg(unix)]
+fn daemonize() -> Result<()> {
+    let cwd = std::env::current_dir()
+        .map_err(|e| SliceError::InternalError(format!("Failed to read working directory: {}", e)))?;
+    daemonize::Daemonize::new()
+        .working_directory(cwd)
+        .umask(0o022)
+        .start()
+        .map_err(|e| SliceError::InternalError(format!("Failed to daemonize: {}", e)))
+}
+
+#[cfg(not(unix))]
+fn daemonize() -> Result<()> {
+    Err(SliceError::ConfigError("daemon is only supported on Unix".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_buffer_coalesces_small_chunks() {
+        let mut buffer = ResponseBuffer::new(10, Duration::from_millis(10));
+        assert!(buffer.push(Bytes::from_static(b"abcd")).is_empty());
+        assert!(buffer.deadline.is_some());
+        assert!(buffer.push(Bytes::from_static(b"efgh")).is_empty());
+        assert_eq!(buffer.push(Bytes::from_static(b"ijkl")), [Bytes::from_static(b"abcdefghijkl")]);
+        assert!(buffer.deadline.is_none());
+
+        // Large chunks pass through after the held bytes
+        assert!(buffer.push(Bytes::from_static(b"m")).is_empty());
+        let large = Bytes::from(vec![b'n'; 10]);
+        assert_eq!(buffer.push(large.clone()), [Bytes::from_static(b"m"), large]);
+        assert!(buffer.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_socket_options_applied_to_accepted_connection() {
+        let socket = SocketConfig {
+            tcp_nodelay: true,
+            so_reuseaddr: true,
+            send_buffer_size: Some(64 * 1024),
+            recv_buffer_size: Some(64 * 1024),
+            keepalive_secs: Some(30),
+        };
+        let listener = listen("127.0.0.1:0", &socket).await.unwrap();
+        assert!(SockRef::from(&listener).reuse_address().unwrap());
+
+        let addr = listener.local_addr().unwrap();
+        let (_client, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        let (stream, _) = accepted.unwrap();
+        apply_socket_options(&stream, &socket).unwrap();
+
+        let sock = SockRef::from(&stream);
+        assert!(stream.nodelay().unwrap());
+        assert!(sock.keepalive().unwrap());
+        assert_eq!(sock.tcp_keepalive_time().unwrap(), Duration::from_secs(30));
+        // The kernel may round buffer sizes up
+        assert!(sock.send_buffer_size().unwrap() >= 64 * 1024);
+        assert!(sock.recv_buffer_size().unwrap() >= 64 * 1024);
+
+        let defaults = SocketConfig {
+            tcp_nodelay: false,
+            ..SocketConfig::default()
+        };
+        apply_socket_options(&stream, &defaults).unwrap();
+        assert!(!stream.nodelay().unwrap());
+    }
+
+    #[test]
+    fn test_strip_hop_by_hop() {
+        let mut headers = HeaderMap::new();
+        headers.insert("connection", HeaderValue::from_static("close, x-session"));
+        headers.insert("x-session", HeaderValue::from_static("abc"));
+        headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        headers.insert("range", HeaderValue::from_static("bytes=0-1"));
+        strip_hop_by_hop(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key("range"));
+    }
+}
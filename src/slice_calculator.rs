@@ -7,6 +7,7 @@ use crate::models::{ByteRange, SliceSpec};
 use tracing::debug;
 
 /// Calculator for splitting files into slices
+#[derive(Debug, Clone)]
 pub struct SliceCalculator {
     /// Size of each slice in bytes
     slice_size: usize,
@@ -51,12 +52,18 @@ impl SliceCalculator {
     /// - If `client_range` is Some, calculates only the slices needed for that range
     /// - Each slice (except possibly the last) will be `slice_size` bytes
     /// - The last slice will cover remaining bytes to the end of the requested range
+    /// - A range ending past the file is clamped to its last byte; one starting
+    ///   at or past the end fails with [`SliceError::RangeNotSatisfiable`]
     pub fn calculate_slices(
         &self,
         file_size: u64,
         client_range: Option<ByteRange>,
     ) -> Result<Vec<SliceSpec>> {
         if file_size == 0 {
+            // No byte of an empty file can be asked for
+            if client_range.is_some() {
+                return Err(SliceError::RangeNotSatisfiable { total_size: 0 });
+            }
             debug!("File size is 0, returning empty slice list");
             return Ok(Vec::new());
         }
@@ -75,10 +82,7 @@ impl SliceCalculator {
                         "Invalid range: start {} is beyond file size {}",
                         range.start, file_size
                     );
-                    return Err(SliceError::InvalidRange(format!(
-                        "Range start {} is beyond file size {}",
-                        range.start, file_size
-                    )));
+                    return Err(SliceError::RangeNotSatisfiable { total_size: file_size });
                 }
                 
                 // Clamp the end to file size - 1
@@ -126,6 +130,48 @@ impl SliceCalculator {
 
         Ok(slices)
     }
+
+    /// Calculate the slice-aligned slices covering a client range
+    ///
+    /// Unlike [`calculate_slices`](Self::calculate_slices), slices start on
+    /// multiples of `slice_size`, so they share cache entries with requests
+    /// for the whole file. The first and last slices may extend past the
+    /// range; the caller trims them.
+    ///
+    /// # Arguments
+    /// * `file_size` - Total size of the file in bytes
+    /// * `client_range` - Byte range requested by the client
+    ///
+    /// # Returns
+    /// The slices overlapping `client_range`, indexed from 0
+    pub fn calculate_aligned_slices(
+        &self,
+        file_size: u64,
+        client_range: ByteRange,
+    ) -> Result<Vec<SliceSpec>> {
+        if client_range.start >= file_size {
+            return Err(SliceError::RangeNotSatisfiable { total_size: file_size });
+        }
+
+        let slice_size = self.slice_size as u64;
+        let range_end = std::cmp::min(client_range.end, file_size - 1);
+        let first = client_range.start / slice_size;
+        let last = range_end / slice_size;
+
+        let mut slices = Vec::new();
+        for (index, slice) in (first..=last).enumerate() {
+            let start = slice * slice_size;
+            let end = std::cmp::min(start + slice_size - 1, file_size - 1);
+            slices.push(SliceSpec::new(index, ByteRange::new(start, end)?));
+        }
+
+        debug!(
+            "Calculated {} aligned slices (slices {}-{}) for range {}-{} (file_size={}, slice_size={})",
+            slices.len(), first, last, client_range.start, range_end, file_size, self.slice_size
+        );
+
+        Ok(slices)
+    }
 }
 
 #[cfg(test)]
@@ -203,8 +249,10 @@ mod tests {
         let calculator = SliceCalculator::new(1024);
         let client_range = ByteRange::new(5000, 10000).unwrap();
         let result = calculator.calculate_slices(4000, Some(client_range));
+        assert!(matches!(result, Err(SliceError::RangeNotSatisfiable { total_size: 4000 })));
         
-        assert!(result.is_err());
+        let result = calculator.calculate_aligned_slices(4000, ByteRange::new(4000, 4000).unwrap());
+        assert!(matches!(result, Err(SliceError::RangeNotSatisfiable { total_size: 4000 })));
     }
 
     #[test]
@@ -222,6 +270,9 @@ mod tests {
         let calculator = SliceCalculator::new(1024);
         let slices = calculator.calculate_slices(0, None).unwrap();
         assert_eq!(slices.len(), 0);
+        
+        let result = calculator.calculate_slices(0, Some(ByteRange::new(0, 0).unwrap()));
+        assert!(matches!(result, Err(SliceError::RangeNotSatisfiable { total_size: 0 })));
     }
 
     #[test]
@@ -259,4 +310,26 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_calculate_aligned_slices() {
+        let calculator = SliceCalculator::new(1024);
+        let client_range = ByteRange::new(1500, 2500).unwrap();
+        let slices = calculator.calculate_aligned_slices(10000, client_range).unwrap();
+
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].index, 0);
+        assert_eq!((slices[0].range.start, slices[0].range.end), (1024, 2047));
+        assert_eq!(slices[1].index, 1);
+        assert_eq!((slices[1].range.start, slices[1].range.end), (2048, 3071));
+
+        // The last slice stops at the end of the file
+        let client_range = ByteRange::new(9500, 20000).unwrap();
+        let slices = calculator.calculate_aligned_slices(10000, client_range).unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!((slices[0].range.start, slices[0].range.end), (9216, 9999));
+
+        let client_range = ByteRange::new(10000, 10001).unwrap();
+        assert!(calculator.calculate_aligned_slices(10000, client_range).is_err());
+    }
 }
@@ -0,0 +1,219 @@
+//! Concurrency slow-start for origin subrequests
+//!
+//! On a cold cache every request misses, and firing `max_concurrent_subrequests`
+//! at the origin straight away can stampede it. [`ConcurrencyRamp`] is a
+//! process-wide gate that starts with a small number of in-flight subrequests
+//! and raises the limit as subrequests succeed or as the warm-up window passes,
+//! halving it again whenever one fails.
+
+use crate::config::SliceConfig;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+#[derive(Debug)]
+struct RampState {
+    /// Limit earned through successes (additive increase, multiplicative decrease)
+    limit: usize,
+    /// Successes counted towards the next increase
+    successes: usize,
+    /// Limit the warm-up window ramps from
+    window_base: usize,
+    /// When the current warm-up window started
+    window_start: Instant,
+    in_flight: usize,
+}
+
+/// Process-wide gate that ramps subrequest concurrency up to a maximum
+#[derive(Debug)]
+pub struct ConcurrencyRamp {
+    initial: usize,
+    max: usize,
+    warmup: Duration,
+    state: Mutex<RampState>,
+    released: Notify,
+}
+
+/// Permission to send one subrequest; released on drop
+#[derive(Debug)]
+pub struct RampPermit {
+    ramp: Arc<ConcurrencyRamp>,
+}
+
+impl Drop for RampPermit {
+    fn drop(&mut self) {
+        self.ramp.state.lock().unwrap().in_flight -= 1;
+        self.ramp.released.notify_waiters();
+    }
+}
+
+impl ConcurrencyRamp {
+    /// Create a ramp
+    ///
+    /// # Arguments
+    /// * `initial` - Concurrency allowed on cold start
+    /// * `max` - Concurrency the ramp grows towards
+    /// * `warmup` - Time after which `max` is allowed regardless of successes
+    ///   (zero to ramp on successes only)
+    pub fn new(initial: usize, max: usize, warmup: Duration) -> Self {
+        let max = max.max(1);
+        let initial = initial.clamp(1, max);
+        ConcurrencyRamp {
+            initial,
+            max,
+            warmup,
+            state: Mutex::new(RampState {
+                limit: initial,
+                successes: 0,
+                window_base: initial,
+                window_start: Instant::now(),
+                in_flight: 0,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    /// Create a ramp from the `slow_start` section
+    ///
+    /// # Returns
+    /// `None` if slow start is not enabled
+    pub fn from_config(config: &SliceConfig) -> Option<Self> {
+        let slow_start = config.slow_start.as_ref().filter(|s| s.enabled)?;
+        Some(Self::new(
+            slow_start.initial_concurrency,
+            config.max_concurrent_subrequests,
+            Duration::from_secs(slow_start.warmup_secs),
+        ))
+    }
+
+    /// Current number of subrequests allowed in flight
+    pub fn current_limit(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        self.effective_limit(&state)
+    }
+
+    fn effective_limit(&self, state: &RampState) -> usize {
+        let mut limit = state.limit;
+        if !self.warmup.is_zero() {
+            let progress =
+                (state.window_start.elapsed().as_secs_f64() / self.warmup.as_secs_f64()).min(1.0);
+            let floor = state.window_base as f64
+                + (self.max - state.window_base) as f64 * progress;
+            limit = limit.max(floor as usize);
+        }
+        limit.min(self.max)
+    }
+
+    /// Wait until another subrequest may be sent
+    pub async fn acquire(self: &Arc<Self>) -> RampPermit {
+        loop {
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.in_flight < self.effective_limit(&state) {
+                    state.in_flight += 1;
+                    return RampPermit { ramp: self.clone() };
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// Record a successful subrequest
+    ///
+    /// The limit grows by one after as many consecutive successes as the
+    /// current limit, so it rises steadily over successive batches.
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.successes += 1;
+        if state.successes >= state.limit && state.limit < self.max {
+            state.limit += 1;
+            state.successes = 0;
+            drop(state);
+            self.released.notify_waiters();
+        }
+    }
+
+    /// Record a failed subrequest, halving the limit
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let current = self.effective_limit(&state);
+        state.limit = (current / 2).max(self.initial);
+        state.successes = 0;
+        state.window_base = state.limit;
+        state.window_start = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_additive_increase() {
+        let ramp = ConcurrencyRamp::new(1, 4, Duration::ZERO);
+        assert_eq!(ramp.current_limit(), 1);
+
+        ramp.record_success();
+        assert_eq!(ramp.current_limit(), 2);
+        ramp.record_success();
+        assert_eq!(ramp.current_limit(), 2);
+        ramp.record_success();
+        assert_eq!(ramp.current_limit(), 3);
+
+        for _ in 0..10 {
+            ramp.record_success();
+        }
+        assert_eq!(ramp.current_limit(), 4);
+    }
+
+    #[test]
+    fn test_failure_halves_limit() {
+        let ramp = ConcurrencyRamp::new(2, 16, Duration::ZERO);
+        for _ in 0..200 {
+            ramp.record_success();
+        }
+        assert_eq!(ramp.current_limit(), 16);
+
+        ramp.record_failure();
+        assert_eq!(ramp.current_limit(), 8);
+        ramp.record_failure();
+        ramp.record_failure();
+        ramp.record_failure();
+        assert_eq!(ramp.current_limit(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_warmup_window_raises_limit() {
+        let ramp = ConcurrencyRamp::new(1, 9, Duration::from_secs(8));
+        assert_eq!(ramp.current_limit(), 1);
+
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert_eq!(ramp.current_limit(), 5);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(ramp.current_limit(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_release() {
+        let ramp = Arc::new(ConcurrencyRamp::new(1, 4, Duration::ZERO));
+        let permit = ramp.acquire().await;
+
+        let waiter = tokio::spawn({
+            let ramp = ramp.clone();
+            async move {
+                let _permit = ramp.acquire().await;
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(permit);
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
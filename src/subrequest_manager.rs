@@ -1,12 +1,27 @@
 //! Subrequest manager for fetching slices from origin server
 
+use crate::config::FetchOrder;
 use crate::error::{Result, SliceError};
+use crate::metrics::SliceMetrics;
 use crate::models::{ByteRange, SliceSpec};
-use bytes::Bytes;
+use crate::origin_signing::{send_signed, RequestSigner};
+use crate::rate_limiter::OriginRateLimiter;
+use crate::slow_start::ConcurrencyRamp;
+use crate::upstream::{rewrite_authority, UpstreamPool};
+use bytes::{Bytes, BytesMut};
 use http::HeaderMap;
 use reqwest::Client;
-use std::time::Duration;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, Notify, Semaphore};
+use tokio::task::JoinSet;
 use tokio::time::sleep;
+use tracing::Instrument;
+
+/// Bytes of origin bandwidth requested from the rate limiter per body read
+const RATE_LIMIT_CHUNK: u64 = 16 * 1024;
 
 /// Result of a subrequest for a single slice
 #[derive(Debug, Clone)]
@@ -21,6 +36,61 @@ pub struct SubrequestResult {
     pub headers: HeaderMap,
 }
 
+/// Signal raised when the client goes away mid-response
+///
+/// Clones share the same state, so the signal can be raised from the code
+/// writing to the client and observed by the code fetching slices.
+#[derive(Debug, Clone, Default)]
+pub struct AbortSignal {
+    inner: Arc<AbortInner>,
+}
+
+#[derive(Debug, Default)]
+struct AbortInner {
+    aborted: AtomicBool,
+    notify: Notify,
+}
+
+impl AbortSignal {
+    /// Create a signal that has not been raised
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Raise the signal, waking everything waiting on it
+    pub fn abort(&self) {
+        self.inner.aborted.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Whether the signal has been raised
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the signal is raised
+    pub async fn aborted(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_aborted() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Result of a fetch that may be interrupted by an [`AbortSignal`]
+#[derive(Debug, Default)]
+pub struct InterruptedFetch {
+    /// Slices fetched successfully, in slice order
+    pub results: Vec<SubrequestResult>,
+    /// Slices that were not fetched because of the abort, in slice order
+    pub remaining: Vec<SliceSpec>,
+    /// Whether the abort signal was raised before the fetch completed
+    pub aborted: bool,
+}
+
 /// Retry policy for failed subrequests
 #[derive(Debug, Clone)]
 pub struct RetryPolicy {
@@ -60,6 +130,48 @@ impl RetryPolicy {
     }
 }
 
+/// Slot reserved for the lowest slice of a request that is still outstanding
+///
+/// Positions are indices into the request's slices sorted by index.
+struct CriticalSlot {
+    outstanding: Mutex<BTreeSet<usize>>,
+    lowest: watch::Sender<Option<usize>>,
+}
+
+impl CriticalSlot {
+    fn new(count: usize) -> Self {
+        let (lowest, _) = watch::channel((count > 0).then_some(0));
+        CriticalSlot {
+            outstanding: Mutex::new((0..count).collect()),
+            lowest,
+        }
+    }
+
+    /// Resolves once `position` is the lowest outstanding slice
+    async fn turn(&self, position: usize) {
+        let mut lowest = self.lowest.subscribe();
+        // The sender lives as long as `self`
+        let _ = lowest.wait_for(|lowest| *lowest == Some(position)).await;
+    }
+
+    /// Mark `position` done, passing the slot to the next outstanding slice
+    fn release(&self, position: usize) {
+        let mut outstanding = self.outstanding.lock().unwrap();
+        outstanding.remove(&position);
+        self.lowest.send_replace(outstanding.first().copied());
+    }
+}
+
+/// Releases a slice's claim on the critical slot when its task ends or is
+/// cancelled
+struct CriticalRelease<'a>(&'a CriticalSlot, usize);
+
+impl Drop for CriticalRelease<'_> {
+    fn drop(&mut self) {
+        self.0.release(self.1);
+    }
+}
+
 /// Manager for handling subrequests to fetch slices
 pub struct SubrequestManager {
     /// HTTP client for making requests
@@ -68,6 +180,29 @@ pub struct SubrequestManager {
     max_concurrent: usize,
     /// Retry policy
     retry_policy: RetryPolicy,
+    /// Metrics collector for per-slice latency (optional)
+    metrics: Option<Arc<SliceMetrics>>,
+    /// Origin rate limiter shared with other managers (optional)
+    rate_limiter: Option<Arc<OriginRateLimiter>>,
+    /// Concurrency slow-start shared with other managers (optional)
+    concurrency_ramp: Option<Arc<ConcurrencyRamp>>,
+    /// Order in which slices are started
+    fetch_order: FetchOrder,
+    /// Extra headers sent with every subrequest
+    request_headers: HeaderMap,
+    /// Origins subrequests are spread over (optional)
+    upstreams: Option<Arc<UpstreamPool>>,
+    /// Object size every Content-Range total must match (optional)
+    expected_size: Option<u64>,
+    /// Retries left for all slices together (optional)
+    retry_budget: Option<Arc<AtomicUsize>>,
+    /// When every fetch must have finished (optional)
+    deadline: Option<Instant>,
+    /// Signs every subrequest (optional)
+    signer: Option<Arc<RequestSigner>>,
+    /// Failed attempts allowed for all slices together, and the count so far
+    /// (optional)
+    failure_limit: Option<(usize, Arc<AtomicUsize>)>,
 }
 
 impl SubrequestManager {
@@ -91,9 +226,259 @@ impl SubrequestManager {
             http_client,
             max_concurrent,
             retry_policy: RetryPolicy::new(max_retries),
+            metrics: None,
+            rate_limiter: None,
+            concurrency_ramp: None,
+            fetch_order: FetchOrder::default(),
+            request_headers: HeaderMap::new(),
+            upstreams: None,
+            expected_size: None,
+            retry_budget: None,
+            deadline: None,
+            signer: None,
+            failure_limit: None,
         }
     }
 
+    /// Send subrequests with `client`, sharing its connection pool
+    ///
+    /// See [`origin_client`](crate::origin_client::origin_client).
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Record per-slice fetch latency and retries into `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<SliceMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Throttle origin requests and body reads through `limiter`
+    ///
+    /// The limiter is meant to be shared by every manager in the process so
+    /// that the configured caps apply to all origin traffic combined.
+    pub fn with_rate_limiter(mut self, limiter: Arc<OriginRateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Gate subrequests through a concurrency slow-start
+    ///
+    /// Like the rate limiter, the ramp is meant to be shared process-wide.
+    pub fn with_concurrency_ramp(mut self, ramp: Arc<ConcurrencyRamp>) -> Self {
+        self.concurrency_ramp = Some(ramp);
+        self
+    }
+
+    /// Start slices in the given order instead of all at once
+    pub fn with_fetch_order(mut self, order: FetchOrder) -> Self {
+        self.fetch_order = order;
+        self
+    }
+
+    /// Send `headers` with every subrequest, e.g. the request headers the
+    /// origin varies its response on
+    pub fn with_request_headers(mut self, headers: HeaderMap) -> Self {
+        self.request_headers = headers;
+        self
+    }
+
+    /// Sign every subrequest with `signer`, after all other headers are set
+    pub fn with_signer(mut self, signer: Arc<RequestSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Send subrequests to peers picked from `pool` instead of the URL's host
+    ///
+    /// Retries of a slice go to a peer that has not failed it yet.
+    pub fn with_upstreams(mut self, pool: Arc<UpstreamPool>) -> Self {
+        self.upstreams = Some(pool);
+        self
+    }
+
+    /// Reject slice responses whose Content-Range total is not `size`
+    ///
+    /// Catches an origin object that changed size since its metadata was
+    /// fetched, so slices of two versions are never assembled together.
+    pub fn with_expected_size(mut self, size: u64) -> Self {
+        self.expected_size = Some(size);
+        self
+    }
+
+    /// Share `retries` retries between every slice fetched by this manager
+    ///
+    /// A failed attempt finding the budget used up fails with
+    /// [`SliceError::DeadlineExceeded`] instead of retrying, so many slices
+    /// can't each spend their own `max_retries`.
+    pub fn with_retry_budget(mut self, retries: usize) -> Self {
+        self.retry_budget = Some(Arc::new(AtomicUsize::new(retries)));
+        self
+    }
+
+    /// Give up on every slice once `failures` attempts have failed in total
+    ///
+    /// The attempt reaching the limit fails with
+    /// [`SliceError::SliceFailureLimit`] instead of retrying, telling the
+    /// caller to stop slicing and use
+    /// [`fetch_whole_object`](Self::fetch_whole_object).
+    pub fn with_failure_limit(mut self, failures: usize) -> Self {
+        self.failure_limit = Some((failures, Arc::new(AtomicUsize::new(0))));
+        self
+    }
+
+    /// Fail fetches not finished `timeout` from now with
+    /// [`SliceError::DeadlineExceeded`]
+    pub fn with_deadline(self, timeout: Duration) -> Self {
+        self.with_deadline_at(Instant::now() + timeout)
+    }
+
+    /// Fail fetches not finished by `deadline`, or by an earlier deadline
+    /// already set
+    pub fn with_deadline_at(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(self.deadline.map_or(deadline, |current| current.min(deadline)));
+        self
+    }
+
+    /// Take a retry from the budget, if there is one
+    ///
+    /// # Returns
+    /// Whether the retry may go ahead
+    fn take_retry(&self) -> bool {
+        match &self.retry_budget {
+            Some(budget) => budget
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+                .is_ok(),
+            None => true,
+        }
+    }
+
+    /// Count a failed attempt against the failure limit
+    ///
+    /// # Returns
+    /// The limit, once this failure reaches it
+    fn record_failure(&self) -> Option<usize> {
+        let (limit, failures) = self.failure_limit.as_ref()?;
+        (failures.fetch_add(1, Ordering::SeqCst) + 1 >= *limit).then_some(*limit)
+    }
+
+    /// Fetch a slice once from a peer of the upstream pool, if configured
+    ///
+    /// Peers in `failed` are avoided; the peer used is added to it when the
+    /// attempt fails with a retryable error.
+    async fn try_fetch_slice_pooled(
+        &self,
+        slice: &SliceSpec,
+        url: &str,
+        failed: &mut Vec<usize>,
+    ) -> Result<SubrequestResult> {
+        let Some(pool) = &self.upstreams else {
+            return self.try_fetch_slice_ramped(slice, url).await;
+        };
+        let Some(lease) = pool.select_excluding(failed) else {
+            return self.try_fetch_slice_ramped(slice, url).await;
+        };
+        if let (Some(metrics), Some(&previous)) = (&self.metrics, failed.last()) {
+            if previous != lease.index() {
+                metrics.record_upstream_failover(pool.address(previous), lease.address());
+            }
+        }
+
+        tracing::Span::current().record("upstream", lease.address());
+        let result = self
+            .try_fetch_slice_ramped(slice, &rewrite_authority(url, lease.address()))
+            .await;
+        let success = !matches!(&result, Err(e) if e.should_retry());
+        if success {
+            pool.record_success(lease.index());
+        } else {
+            pool.record_failure(lease.index());
+            failed.push(lease.index());
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_upstream_request(lease.address(), success);
+        }
+        result
+    }
+
+    /// Fetch a slice once, holding a slow-start permit if configured
+    async fn try_fetch_slice_ramped(&self, slice: &SliceSpec, url: &str) -> Result<SubrequestResult> {
+        let Some(ramp) = &self.concurrency_ramp else {
+            return self.try_fetch_slice(slice, url).await;
+        };
+
+        let _permit = ramp.acquire().await;
+        let result = self.try_fetch_slice(slice, url).await;
+        match &result {
+            Ok(_) => ramp.record_success(),
+            // Only back off on errors that suggest the origin is struggling
+            Err(e) if e.should_retry() => ramp.record_failure(),
+            Err(_) => {}
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.set_effective_concurrency(ramp.current_limit());
+        }
+        result
+    }
+
+    /// Record rate limiter wait time and utilization into `metrics`
+    fn record_throttle(&self, limiter: &OriginRateLimiter, waited: Duration) {
+        if let Some(metrics) = &self.metrics {
+            if !waited.is_zero() {
+                metrics.record_origin_throttle(waited);
+            }
+            metrics.set_origin_utilization(
+                limiter.bandwidth_utilization(),
+                limiter.request_utilization(),
+            );
+        }
+    }
+
+    /// Read a response body, acquiring origin bandwidth before each chunk
+    ///
+    /// Chunks larger than the bandwidth acquired for them are paid for before
+    /// the next read, so the long-run read rate stays within the cap.
+    async fn read_body_limited(
+        &self,
+        mut response: reqwest::Response,
+        limiter: &OriginRateLimiter,
+        expected_len: u64,
+    ) -> Result<Bytes> {
+        let mut body = BytesMut::with_capacity(expected_len as usize);
+        let mut credit = 0u64;
+
+        loop {
+            if credit == 0 {
+                let remaining = expected_len.saturating_sub(body.len() as u64);
+                let amount = remaining.clamp(1, RATE_LIMIT_CHUNK);
+                let waited = limiter.acquire_bytes(amount).await;
+                self.record_throttle(limiter, waited);
+                credit = amount;
+            }
+
+            let chunk = response
+                .chunk()
+                .await
+                .map_err(|e| SliceError::HttpError(format!("Failed to read response body: {}", e)))?;
+            let Some(chunk) = chunk else {
+                break;
+            };
+
+            let len = chunk.len() as u64;
+            if len > credit {
+                let waited = limiter.acquire_bytes(len - credit).await;
+                self.record_throttle(limiter, waited);
+                credit = 0;
+            } else {
+                credit -= len;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(body.freeze())
+    }
+
     /// Build a Range request for a specific byte range
     ///
     /// # Arguments
@@ -104,9 +489,13 @@ impl SubrequestManager {
     /// A reqwest::RequestBuilder configured with the Range header
     fn build_range_request(&self, url: &str, range: &ByteRange) -> reqwest::RequestBuilder {
         let range_header = range.to_header();
+        let headers = self.request_headers.clone();
+        #[cfg(feature = "otel")]
+        let headers = crate::telemetry::with_traceparent(headers);
         
         self.http_client
             .get(url)
+            .headers(headers)
             .header("Range", range_header)
     }
 
@@ -120,12 +509,19 @@ impl SubrequestManager {
     /// * `Ok(SubrequestResult)` if the request succeeds
     /// * `Err(SliceError)` if the request fails
     async fn try_fetch_slice(&self, slice: &SliceSpec, url: &str) -> Result<SubrequestResult> {
+        if let Some(limiter) = &self.rate_limiter {
+            let waited = limiter.acquire_request().await;
+            self.record_throttle(limiter, waited);
+        }
+
         let request = self.build_range_request(url, &slice.range);
         
-        let response = request
-            .send()
+        let response = send_signed(request, self.signer.as_deref())
             .await
             .map_err(|e| SliceError::HttpError(format!("Request failed: {}", e)))?;
+        if let Some(metrics) = &self.metrics {
+            metrics.record_subrequest_version(response.version());
+        }
 
         let status = response.status().as_u16();
         let headers = response.headers().clone();
@@ -145,11 +541,14 @@ impl SubrequestManager {
                 .map_err(|e| SliceError::ParseError(format!("Invalid Content-Range header: {}", e)))?;
             
             // Parse Content-Range header (format: "bytes start-end/total")
-            if !Self::validate_content_range(content_range_str, &slice.range)? {
-                return Err(SliceError::HttpError(format!(
-                    "Content-Range mismatch: expected {}-{}, got {}",
-                    slice.range.start, slice.range.end, content_range_str
-                )));
+            if !Self::validate_content_range(content_range_str, &slice.range, self.expected_size)? {
+                let total = self
+                    .expected_size
+                    .map_or_else(|| "*".to_string(), |size| size.to_string());
+                return Err(SliceError::ContentRangeMismatch {
+                    expected: format!("bytes {}-{}/{}", slice.range.start, slice.range.end, total),
+                    actual: content_range_str.to_string(),
+                });
             }
         } else {
             return Err(SliceError::HttpError(
@@ -158,10 +557,16 @@ impl SubrequestManager {
         }
 
         // Read the response body
-        let data = response
-            .bytes()
-            .await
-            .map_err(|e| SliceError::HttpError(format!("Failed to read response body: {}", e)))?;
+        let data = match &self.rate_limiter {
+            Some(limiter) => {
+                self.read_body_limited(response, limiter, slice.range.size())
+                    .await?
+            }
+            None => response
+                .bytes()
+                .await
+                .map_err(|e| SliceError::HttpError(format!("Failed to read response body: {}", e)))?,
+        };
 
         Ok(SubrequestResult {
             slice_index: slice.index,
@@ -176,12 +581,17 @@ impl SubrequestManager {
     /// # Arguments
     /// * `content_range` - The Content-Range header value (e.g., "bytes 0-1023/10240")
     /// * `expected_range` - The expected byte range
+    /// * `expected_total` - The expected object size; an unknown total (`*`) is accepted
     ///
     /// # Returns
-    /// * `Ok(true)` if the range matches
-    /// * `Ok(false)` if the range doesn't match
+    /// * `Ok(true)` if the range and total match
+    /// * `Ok(false)` if the range or total doesn't match
     /// * `Err(SliceError)` if parsing fails
-    fn validate_content_range(content_range: &str, expected_range: &ByteRange) -> Result<bool> {
+    fn validate_content_range(
+        content_range: &str,
+        expected_range: &ByteRange,
+        expected_total: Option<u64>,
+    ) -> Result<bool> {
         // Expected format: "bytes start-end/total"
         let content_range = content_range.trim();
         
@@ -222,7 +632,20 @@ impl SubrequestManager {
             .parse::<u64>()
             .map_err(|e| SliceError::ParseError(format!("Invalid end value: {}", e)))?;
 
-        Ok(start == expected_range.start && end == expected_range.end)
+        let total = match parts[1].trim() {
+            "*" => None,
+            total => Some(
+                total
+                    .parse::<u64>()
+                    .map_err(|e| SliceError::ParseError(format!("Invalid total value: {}", e)))?,
+            ),
+        };
+        let total_matches = match (total, expected_total) {
+            (Some(total), Some(expected)) => total == expected,
+            _ => true,
+        };
+
+        Ok(start == expected_range.start && end == expected_range.end && total_matches)
     }
 
     /// Fetch a single slice with retry logic
@@ -233,14 +656,61 @@ impl SubrequestManager {
     ///
     /// # Returns
     /// * `Ok(SubrequestResult)` if the request succeeds (possibly after retries)
-    /// * `Err(SliceError)` if all retry attempts fail
+    /// * `Err(SliceError)` if all retry attempts fail, or the deadline or
+    ///   retry budget runs out
     pub async fn fetch_single_slice(&self, slice: &SliceSpec, url: &str) -> Result<SubrequestResult> {
+        let Some(deadline) = self.deadline else {
+            return self.fetch_with_retries(slice, url).await;
+        };
+        tokio::time::timeout_at(deadline.into(), self.fetch_with_retries(slice, url))
+            .await
+            .unwrap_or_else(|_| {
+                Err(SliceError::DeadlineExceeded(format!(
+                    "slice {} not fetched in time",
+                    slice.index
+                )))
+            })
+    }
+
+    /// Fetch a single slice, retrying as the policy and budget allow
+    async fn fetch_with_retries(&self, slice: &SliceSpec, url: &str) -> Result<SubrequestResult> {
         let mut attempt = 0;
+        let mut failed_upstreams = Vec::new();
+        let start = Instant::now();
 
         loop {
-            match self.try_fetch_slice(slice, url).await {
-                Ok(result) => return Ok(result),
+            let span = tracing::info_span!(
+                "subrequest",
+                url = %url,
+                slice_index = slice.index,
+                range = %slice.range.to_header(),
+                attempt = attempt + 1,
+                upstream = tracing::field::Empty,
+                bytes = tracing::field::Empty,
+            );
+            let attempt_result = self
+                .try_fetch_slice_pooled(slice, url, &mut failed_upstreams)
+                .instrument(span.clone())
+                .await;
+            match attempt_result {
+                Ok(result) => {
+                    span.record("bytes", result.data.len());
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_subrequest_latency(start.elapsed());
+                    }
+                    return Ok(result);
+                }
                 Err(e) => {
+                    if let Some(failures) = self.record_failure() {
+                        tracing::warn!(
+                            "Slice {} failed ({}), {} failed attempts reached the failure limit",
+                            slice.index,
+                            e,
+                            failures
+                        );
+                        return Err(SliceError::SliceFailureLimit { failures });
+                    }
+
                     if !self.retry_policy.should_retry(attempt, &e) {
                         // All retries exhausted, return the final error
                         return Err(SliceError::SubrequestFailed {
@@ -249,8 +719,21 @@ impl SubrequestManager {
                         });
                     }
 
-                    // Wait before retrying
+                    if !self.take_retry() {
+                        return Err(SliceError::DeadlineExceeded(format!(
+                            "retry budget used up, slice {} failed: {}",
+                            slice.index, e
+                        )));
+                    }
+
+                    // Wait before retrying, unless the deadline passes first
                     let backoff = self.retry_policy.backoff_duration(attempt);
+                    if self.deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline) {
+                        return Err(SliceError::DeadlineExceeded(format!(
+                            "no time left to retry slice {}: {}",
+                            slice.index, e
+                        )));
+                    }
                     tracing::warn!(
                         "Subrequest failed for slice {} (attempt {}), retrying after {:?}: {}",
                         slice.index,
@@ -260,14 +743,200 @@ impl SubrequestManager {
                     );
                     sleep(backoff).await;
                     
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_subrequest_retry();
+                    }
                     attempt += 1;
                 }
             }
         }
     }
 
+    /// Fetch the whole object with one plain GET and cut it into `slices`
+    ///
+    /// Collects [`fetch_whole_object_stream_until_abort`](Self::fetch_whole_object_stream_until_abort).
+    ///
+    /// # Arguments
+    /// * `slices` - Slices to return the bytes of
+    /// * `url` - The URL to fetch from
+    pub async fn fetch_whole_object(&self, slices: &[SliceSpec], url: &str) -> Result<Vec<SubrequestResult>> {
+        let mut stream = self.fetch_whole_object_stream_until_abort(slices.to_vec(), url, &AbortSignal::default(), true);
+        let mut results = Vec::with_capacity(slices.len());
+        while let Some(result) = stream.recv().await {
+            results.push(result?);
+        }
+        results.sort_by_key(|r| r.slice_index);
+        Ok(results)
+    }
+
+    /// Fetch the whole object with one plain GET, sending each of `slices`
+    /// as soon as the body has reached its last byte
+    ///
+    /// The fallback for origins whose range handling keeps failing. The
+    /// response must be a 200 with the whole object (`expected_size` bytes,
+    /// if set); each slice is sent as if fetched on its own. Only the slice
+    /// being cut is held here, and the body is not read past the last
+    /// slice. Not retried: the first error is sent and ends the stream.
+    ///
+    /// Once `abort` is raised the GET is cancelled, unless
+    /// `finish_in_flight` is set.
+    ///
+    /// # Arguments
+    /// * `slices` - Slices to send the bytes of
+    /// * `url` - The URL to fetch from
+    /// * `abort` - Signal raised when the client disconnects
+    /// * `finish_in_flight` - Whether the GET completes after an abort
+    pub fn fetch_whole_object_stream_until_abort(
+        &self,
+        slices: Vec<SliceSpec>,
+        url: &str,
+        abort: &AbortSignal,
+        finish_in_flight: bool,
+    ) -> mpsc::UnboundedReceiver<Result<SubrequestResult>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let manager = self.clone_for_task();
+        let url = url.to_string();
+        let abort = abort.clone();
+
+        tokio::spawn(async move {
+            let fetch = manager.stream_whole_object(slices, &url, &tx);
+            let result = if finish_in_flight {
+                fetch.await
+            } else {
+                tokio::select! {
+                    result = fetch => result,
+                    _ = abort.aborted() => Ok(()),
+                }
+            };
+            if let Err(e) = result {
+                let _ = tx.send(Err(e));
+            }
+        });
+
+        rx
+    }
+
+    /// Run one whole-object GET against an upstream from the pool, if any
+    async fn stream_whole_object(
+        &self,
+        slices: Vec<SliceSpec>,
+        url: &str,
+        tx: &mpsc::UnboundedSender<Result<SubrequestResult>>,
+    ) -> Result<()> {
+        let lease = self.upstreams.as_ref().and_then(|pool| pool.select_excluding(&[]));
+        let target = match &lease {
+            Some(lease) => rewrite_authority(url, lease.address()),
+            None => url.to_string(),
+        };
+
+        let fetch = self.try_fetch_whole(slices, &target, tx);
+        let result = match self.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), fetch).await.unwrap_or_else(|_| {
+                Err(SliceError::DeadlineExceeded("whole object not fetched in time".to_string()))
+            }),
+            None => fetch.await,
+        };
+        if let (Some(pool), Some(lease)) = (&self.upstreams, &lease) {
+            let success = !matches!(&result, Err(e) if e.should_retry());
+            if success {
+                pool.record_success(lease.index());
+            } else {
+                pool.record_failure(lease.index());
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.record_upstream_request(lease.address(), success);
+            }
+        }
+        result
+    }
+
+    /// Send one GET without a Range header and cut `slices` from its body
+    async fn try_fetch_whole(
+        &self,
+        mut slices: Vec<SliceSpec>,
+        url: &str,
+        tx: &mpsc::UnboundedSender<Result<SubrequestResult>>,
+    ) -> Result<()> {
+        if let Some(limiter) = &self.rate_limiter {
+            let waited = limiter.acquire_request().await;
+            self.record_throttle(limiter, waited);
+        }
+
+        let headers = self.request_headers.clone();
+        #[cfg(feature = "otel")]
+        let headers = crate::telemetry::with_traceparent(headers);
+        let request = self.http_client.get(url).headers(headers);
+        let mut response = send_signed(request, self.signer.as_deref())
+            .await
+            .map_err(|e| SliceError::HttpError(format!("Request failed: {}", e)))?;
+
+        let status = response.status().as_u16();
+        if status != 200 {
+            return Err(SliceError::from_http_status(status, "whole object fetch failed"));
+        }
+        if let (Some(expected), Some(actual)) = (self.expected_size, response.content_length()) {
+            if expected != actual {
+                return Err(SliceError::ContentRangeMismatch {
+                    expected: format!("{} bytes", expected),
+                    actual: format!("{} bytes", actual),
+                });
+            }
+        }
+        let headers = response.headers().clone();
+
+        slices.sort_by_key(|slice| slice.range.start);
+        let mut pending: VecDeque<SliceSpec> = slices.into();
+        let mut buffer = BytesMut::new();
+        let mut read = 0u64;
+        while let Some(slice) = pending.front() {
+            let chunk = response
+                .chunk()
+                .await
+                .map_err(|e| SliceError::HttpError(format!("Failed to read response body: {}", e)))?;
+            let Some(chunk) = chunk else {
+                return Err(SliceError::AssemblyError(format!(
+                    "whole object is {} bytes, slice {} ends at byte {}",
+                    read, slice.index, slice.range.end
+                )));
+            };
+            if let Some(limiter) = &self.rate_limiter {
+                let waited = limiter.acquire_bytes(chunk.len() as u64).await;
+                self.record_throttle(limiter, waited);
+            }
+
+            // Keep the parts of the chunk that fall in pending slices
+            let chunk_start = read;
+            read += chunk.len() as u64;
+            while let Some(slice) = pending.front() {
+                if slice.range.start >= read {
+                    break;
+                }
+                let from = slice.range.start.max(chunk_start) - chunk_start;
+                let to = (slice.range.end + 1).min(read) - chunk_start;
+                buffer.extend_from_slice(&chunk[from as usize..to as usize]);
+                if slice.range.end >= read {
+                    break;
+                }
+                let result = SubrequestResult {
+                    slice_index: slice.index,
+                    data: buffer.split().freeze(),
+                    status,
+                    headers: headers.clone(),
+                };
+                pending.pop_front();
+                if tx.send(Ok(result)).is_err() {
+                    // Nobody is waiting for the rest
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Fetch multiple slices concurrently
     ///
+    /// Slices are started according to the manager's `FetchOrder`.
+    ///
     /// # Arguments
     /// * `slices` - Vector of slice specifications to fetch
     /// * `url` - The URL to fetch from
@@ -276,34 +945,14 @@ impl SubrequestManager {
     /// * `Ok(Vec<SubrequestResult>)` if all slices are fetched successfully
     /// * `Err(SliceError)` if any slice fails after all retries
     pub async fn fetch_slices(&self, slices: Vec<SliceSpec>, url: &str) -> Result<Vec<SubrequestResult>> {
-        use tokio::sync::Semaphore;
-        use std::sync::Arc;
-
-        let semaphore = Arc::new(Semaphore::new(self.max_concurrent));
-        let mut tasks = Vec::new();
-
-        for slice in slices {
-            let sem = semaphore.clone();
-            let url = url.to_string();
-            let manager = self.clone_for_task();
-
-            let task = tokio::spawn(async move {
-                // Acquire semaphore permit to limit concurrency
-                let _permit = sem.acquire().await.expect("Semaphore closed");
-                
-                manager.fetch_single_slice(&slice, &url).await
-            });
-
-            tasks.push(task);
-        }
+        let mut tasks = self.spawn_fetches(slices, url, &AbortSignal::default());
 
         // Wait for all tasks to complete
         let mut results = Vec::new();
-        for task in tasks {
+        while let Some(task) = tasks.join_next().await {
             let result = task
-                .await
                 .map_err(|e| SliceError::HttpError(format!("Task join error: {}", e)))??;
-            results.push(result);
+            results.extend(result);
         }
 
         // Sort results by slice index to maintain order
@@ -312,12 +961,237 @@ impl SubrequestManager {
         Ok(results)
     }
 
+    /// Fetch multiple slices concurrently, stopping early if `abort` is raised
+    ///
+    /// Once the signal is raised no new subrequests are started. Subrequests
+    /// already in flight are cancelled, or allowed to complete when
+    /// `finish_in_flight` is set.
+    ///
+    /// # Arguments
+    /// * `slices` - Vector of slice specifications to fetch
+    /// * `url` - The URL to fetch from
+    /// * `abort` - Signal raised when the client disconnects
+    /// * `finish_in_flight` - Whether in-flight subrequests complete after an abort
+    ///
+    /// # Returns
+    /// * `Ok(InterruptedFetch)` with the fetched and unfetched slices
+    /// * `Err(SliceError)` if any slice fails after all retries before an abort
+    pub async fn fetch_slices_until_abort(
+        &self,
+        slices: Vec<SliceSpec>,
+        url: &str,
+        abort: &AbortSignal,
+        finish_in_flight: bool,
+    ) -> Result<InterruptedFetch> {
+        let mut pending: HashMap<usize, SliceSpec> =
+            slices.iter().map(|slice| (slice.index, slice.clone())).collect();
+        let mut tasks = self.spawn_fetches(slices, url, abort);
+
+        let mut outcome = InterruptedFetch::default();
+        loop {
+            tokio::select! {
+                joined = tasks.join_next() => match joined {
+                    None => break,
+                    Some(Ok(Ok(Some(result)))) => {
+                        pending.remove(&result.slice_index);
+                        outcome.results.push(result);
+                    }
+                    // Skipped because of the abort
+                    Some(Ok(Ok(None))) => {}
+                    Some(Ok(Err(e))) => {
+                        if !outcome.aborted {
+                            tasks.abort_all();
+                            return Err(e);
+                        }
+                    }
+                    Some(Err(e)) if e.is_cancelled() => {}
+                    Some(Err(e)) => {
+                        return Err(SliceError::HttpError(format!("Task join error: {}", e)));
+                    }
+                },
+                _ = abort.aborted(), if !outcome.aborted => {
+                    outcome.aborted = true;
+                    if !finish_in_flight {
+                        tasks.abort_all();
+                    }
+                }
+            }
+        }
+
+        outcome.results.sort_by_key(|r| r.slice_index);
+        outcome.remaining = pending.into_values().collect();
+        outcome.remaining.sort_by_key(|s| s.index);
+        Ok(outcome)
+    }
+
+    /// Fetch multiple slices, delivering each one as soon as it is ready
+    ///
+    /// Results arrive in completion order, which follows the configured
+    /// `FetchOrder`. The channel closes after the last slice or after the
+    /// first error; dropping the receiver cancels outstanding subrequests.
+    /// Must be called from within a Tokio runtime.
+    pub fn fetch_slices_stream(
+        &self,
+        slices: Vec<SliceSpec>,
+        url: &str,
+    ) -> mpsc::UnboundedReceiver<Result<SubrequestResult>> {
+        self.fetch_slices_stream_until_abort(slices, url, &AbortSignal::default(), false)
+    }
+
+    /// Fetch multiple slices as [`fetch_slices_stream`](Self::fetch_slices_stream)
+    /// does, stopping early if `abort` is raised
+    ///
+    /// Once the signal is raised no new subrequests are started. Subrequests
+    /// already in flight are cancelled, or allowed to complete and delivered
+    /// when `finish_in_flight` is set; errors after the abort are dropped.
+    pub fn fetch_slices_stream_until_abort(
+        &self,
+        slices: Vec<SliceSpec>,
+        url: &str,
+        abort: &AbortSignal,
+        finish_in_flight: bool,
+    ) -> mpsc::UnboundedReceiver<Result<SubrequestResult>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut tasks = self.spawn_fetches(slices, url, abort);
+        let abort = abort.clone();
+
+        tokio::spawn(async move {
+            let mut aborted = false;
+            loop {
+                let joined = tokio::select! {
+                    joined = tasks.join_next() => joined,
+                    _ = abort.aborted(), if !aborted => {
+                        aborted = true;
+                        if !finish_in_flight {
+                            tasks.abort_all();
+                        }
+                        continue;
+                    }
+                    _ = tx.closed() => break,
+                };
+                let result = match joined {
+                    None => break,
+                    Some(Ok(Ok(Some(result)))) => Ok(result),
+                    // Skipped because of the abort
+                    Some(Ok(Ok(None))) => continue,
+                    Some(Ok(Err(_))) if aborted => continue,
+                    Some(Ok(Err(e))) => Err(e),
+                    Some(Err(e)) if e.is_cancelled() => continue,
+                    Some(Err(e)) => Err(SliceError::HttpError(format!("Task join error: {}", e))),
+                };
+                let failed = result.is_err();
+                if tx.send(result).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// Spawn one task per slice, gated by `max_concurrent` and the fetch order
+    ///
+    /// One of the `max_concurrent` slots is reserved for the lowest slice not
+    /// yet fetched, so the start of the response never waits behind later
+    /// slices. The others are shared by the remaining slices.
+    ///
+    /// Tasks return `None` for slices skipped because `abort` was raised
+    /// before they started.
+    fn spawn_fetches(
+        &self,
+        mut slices: Vec<SliceSpec>,
+        url: &str,
+        abort: &AbortSignal,
+    ) -> JoinSet<Result<Option<SubrequestResult>>> {
+        slices.sort_by_key(|s| s.index);
+        let semaphore = Arc::new(Semaphore::new(self.max_concurrent.saturating_sub(1)));
+        let critical = Arc::new(CriticalSlot::new(slices.len()));
+        let dispatched = Instant::now();
+        let mut tasks = JoinSet::new();
+
+        // Each gate opens when its sender is dropped: after the slot is
+        // taken in priority mode, after the fetch in sequential mode
+        let mut gate: Option<watch::Receiver<()>> = None;
+
+        for (position, slice) in slices.into_iter().enumerate() {
+            let wait = gate.clone();
+            let (started, finished) = match self.fetch_order {
+                FetchOrder::Parallel => (None, None),
+                FetchOrder::Priority => {
+                    let (tx, rx) = watch::channel(());
+                    gate = Some(rx);
+                    (Some(tx), None)
+                }
+                FetchOrder::SequentialFirstN(n) if position < n => {
+                    let (tx, rx) = watch::channel(());
+                    gate = Some(rx);
+                    (None, Some(tx))
+                }
+                FetchOrder::SequentialFirstN(_) => (None, None),
+            };
+
+            let sem = semaphore.clone();
+            let critical = critical.clone();
+            let url = url.to_string();
+            let manager = self.clone_for_task();
+            let abort = abort.clone();
+
+            tasks.spawn(async move {
+                let _done = CriticalRelease(&critical, position);
+                if let Some(mut wait) = wait {
+                    // Errors once the gate's sender is dropped
+                    let _ = wait.changed().await;
+                }
+                let mut permit = tokio::select! {
+                    biased;
+                    _ = critical.turn(position) => None,
+                    permit = sem.acquire_owned() => Some(permit.expect("Semaphore closed")),
+                };
+                drop(started);
+                if abort.is_aborted() {
+                    return Ok(None);
+                }
+
+                // A slice that becomes the lowest outstanding one moves to the
+                // reserved slot and hands its permit to the next slice
+                let fetch = manager.fetch_single_slice(&slice, &url);
+                tokio::pin!(fetch);
+                let result = loop {
+                    tokio::select! {
+                        result = &mut fetch => break result,
+                        _ = critical.turn(position), if permit.is_some() => permit = None,
+                    }
+                };
+                if position == 0 && result.is_ok() {
+                    if let Some(metrics) = &manager.metrics {
+                        metrics.record_first_slice(dispatched.elapsed());
+                    }
+                }
+                drop(finished);
+                result.map(Some)
+            }.in_current_span());
+        }
+
+        tasks
+    }
+
     /// Clone the necessary fields for use in async tasks
     fn clone_for_task(&self) -> Self {
         SubrequestManager {
             http_client: self.http_client.clone(),
             max_concurrent: self.max_concurrent,
             retry_policy: self.retry_policy.clone(),
+            metrics: self.metrics.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            concurrency_ramp: self.concurrency_ramp.clone(),
+            fetch_order: self.fetch_order,
+            request_headers: self.request_headers.clone(),
+            upstreams: self.upstreams.clone(),
+            expected_size: self.expected_size,
+            retry_budget: self.retry_budget.clone(),
+            deadline: self.deadline,
+            signer: self.signer.clone(),
+            failure_limit: self.failure_limit.clone(),
         }
     }
 }
@@ -360,7 +1234,7 @@ mod tests {
     #[test]
     fn test_validate_content_range_valid() {
         let range = ByteRange::new(0, 1023).unwrap();
-        let result = SubrequestManager::validate_content_range("bytes 0-1023/10240", &range);
+        let result = SubrequestManager::validate_content_range("bytes 0-1023/10240", &range, None);
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
@@ -368,7 +1242,7 @@ mod tests {
     #[test]
     fn test_validate_content_range_mismatch() {
         let range = ByteRange::new(0, 1023).unwrap();
-        let result = SubrequestManager::validate_content_range("bytes 0-2047/10240", &range);
+        let result = SubrequestManager::validate_content_range("bytes 0-2047/10240", &range, None);
         assert!(result.is_ok());
         assert!(!result.unwrap());
     }
@@ -376,10 +1250,20 @@ mod tests {
     #[test]
     fn test_validate_content_range_invalid_format() {
         let range = ByteRange::new(0, 1023).unwrap();
-        let result = SubrequestManager::validate_content_range("invalid", &range);
+        let result = SubrequestManager::validate_content_range("invalid", &range, None);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_content_range_total() {
+        let range = ByteRange::new(0, 1023).unwrap();
+        let validate = |header| SubrequestManager::validate_content_range(header, &range, Some(10240));
+        assert!(validate("bytes 0-1023/10240").unwrap());
+        assert!(validate("bytes 0-1023/*").unwrap());
+        assert!(!validate("bytes 0-1023/20480").unwrap());
+        assert!(validate("bytes 0-1023/abc").is_err());
+    }
+
     #[test]
     fn test_subrequest_manager_new() {
         let manager = SubrequestManager::new(4, 3);
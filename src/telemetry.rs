@@ -0,0 +1,107 @@
+//! OpenTelemetry export of request spans (`otel` feature)
+//!
+//! The proxy always records its work as `tracing` spans: one `slice_request`
+//! span per client request, with `fetch_metadata`, `cache_lookup`,
+//! `subrequest`, `cache_store` and `assemble` spans below it. This module
+//! exports those spans over OTLP and carries the W3C `traceparent` header
+//! through the proxy, so client and origin traces link up with ours.
+//!
+//! ```rust,no_run
+//! use pingora_slice::{telemetry, SliceConfig};
+//! use tracing_subscriber::layer::SubscriberExt;
+//! use tracing_subscriber::util::SubscriberInitExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = SliceConfig::from_file("pingora_slice.yaml")?;
+//! let provider = telemetry::tracer_provider(&config.tracing.unwrap_or_default())?;
+//! tracing_subscriber::registry()
+//!     .with(telemetry::layer(&provider))
+//!     .init();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::TracingConfig;
+use crate::error::{Result, SliceError};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Build a tracer provider exporting to the configured OTLP endpoint
+///
+/// New traces are sampled at `sampling_ratio`; requests that arrive with a
+/// `traceparent` keep the caller's sampling decision. Must be called from
+/// within a Tokio runtime.
+pub fn tracer_provider(config: &TracingConfig) -> Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(config.otlp_endpoint.clone())
+        .build()
+        .map_err(|e| SliceError::ConfigError(format!("Failed to create OTLP exporter: {}", e)))?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sampling_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build())
+}
+
+/// Subscriber layer that turns `tracing` spans into spans of `provider`
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("pingora-slice"))
+}
+
+/// Make `span` a child of the trace named by the request's `traceparent`
+///
+/// Requests without a valid `traceparent` start a new trace.
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    // Fails only once the span has started, which it has not yet
+    let _ = span.set_parent(parent);
+}
+
+/// Add a `traceparent` for the current span to outgoing request `headers`
+pub fn with_traceparent(mut headers: HeaderMap) -> HeaderMap {
+    let context = Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(&mut headers));
+    headers
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
@@ -7,45 +7,201 @@
 //! Features:
 //! - Automatic promotion of frequently accessed items to L1
 //! - Asynchronous write-behind to L2 for minimal latency impact
-//! - LRU eviction for L1 when memory limit is reached
+//! - LRU, LFU, TTL-first or size-weighted eviction for L1 when the memory
+//!   limit is reached
+//! - Optional background sweep of expired L1 entries, on-demand shrinking
+//!   under memory pressure and L1 admission policies
 //! - Persistent storage survives restarts
 //! - Configurable cache sizes and TTL
+//! - Falls back to memory-only mode while the disk is failing
+//! - Optional per-origin byte quotas for L1
+//! - Pluggable L2 storage through the [`CacheBackend`] trait
 
+use crate::cache::origin_host;
+use crate::cache_backend::{BackendEntry, CacheBackend, CacheBackendStats, EntryFormat, FileBackend};
+use crate::config::{L2DeltaConfig, OriginQuotaConfig, SliceConfig};
+use crate::delta_backend::DeltaBackend;
 use crate::error::{Result, SliceError};
-use crate::models::ByteRange;
+use crate::models::{ByteRange, SliceKey};
 use bytes::Bytes;
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::fs;
-use tokio::io::AsyncWriteExt;
-use tokio::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, error, info, warn};
 
+/// Consecutive disk errors before L2 is bypassed (default)
+pub const DEFAULT_DISK_ERROR_THRESHOLD: u64 = 5;
+
+/// How often a degraded L2 is probed for recovery (default)
+pub const DEFAULT_DISK_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of counters in the L1 frequency sketch
+const SKETCH_COUNTERS: usize = 4096;
+
+/// Counters incremented per key in the L1 frequency sketch
+const SKETCH_HASHES: u64 = 4;
+
+/// Saturation value of a frequency sketch counter
+const SKETCH_MAX_COUNT: u8 = 15;
+
+/// L1 eviction victims picked per scan of the entries
+const EVICTION_BATCH: usize = 16;
+
 /// Message for async disk write operations
 #[derive(Debug)]
 enum DiskWriteMessage {
+    /// Write the pending entry for `key`, if it is still the one queued
+    /// as `seq`
     Write {
         key: String,
-        data: Bytes,
-        expires_at: SystemTime,
+        seq: u64,
     },
     Delete {
         key: String,
     },
+    PurgeAll,
+    /// Flush the backend once the messages before it are handled
+    Flush {
+        done: oneshot::Sender<Result<()>>,
+    },
     Shutdown,
 }
 
+/// An L2 write waiting for the disk writer
+///
+/// Lookups are served from it until the write lands, so an entry is
+/// readable as soon as it is stored even if L1 did not keep it.
+#[derive(Debug)]
+struct PendingWrite {
+    seq: u64,
+    data: Bytes,
+    expires_at: Instant,
+    queued_at: Instant,
+}
+
+/// L2 writes queued for the disk writer, by key
+type PendingWrites = Arc<Mutex<HashMap<String, PendingWrite>>>;
+
+/// Held by the disk writer while a write is in flight
+type L2WriteLock = Arc<tokio::sync::Mutex<()>>;
+
 /// L1 cache entry with access tracking
+///
+/// Expiry and last access are monotonic, so a wall clock step neither
+/// expires every entry at once nor reorders eviction; `stored_at` is wall
+/// clock time for reporting.
 #[derive(Clone)]
 struct L1Entry {
     data: Bytes,
-    expires_at: SystemTime,
-    last_accessed: SystemTime,
+    stored_at: SystemTime,
+    expires_at: Instant,
+    last_accessed: Instant,
     access_count: u64,
 }
 
+/// Which entries are admitted into L1
+///
+/// Entries rejected from L1 are still written to L2.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L1AdmissionPolicy {
+    /// Admit every entry
+    #[default]
+    Always,
+    /// Admit only entries smaller than this many bytes
+    SizeBelow(usize),
+    /// Admit an entry the second time it is stored or promoted, so
+    /// one-off objects do not displace the working set
+    Frequency,
+}
+
+/// Which L1 entries are evicted first when L1 is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L1EvictionPolicy {
+    /// Least recently used
+    #[default]
+    Lru,
+    /// Least often looked up since stored, least recently used among equals
+    Lfu,
+    /// Closest to expiry
+    TtlFirst,
+    /// Largest size times time since last use, so that large cold entries
+    /// go first and fewer evictions free the space needed
+    SizeWeighted,
+}
+
+impl L1EvictionPolicy {
+    /// Rank of `entry` at `now`; lower ranks are evicted first
+    fn rank(self, entry: &L1Entry, now: Instant) -> u128 {
+        let idle = now.saturating_duration_since(entry.last_accessed);
+        match self {
+            L1EvictionPolicy::Lru => u128::MAX - idle.as_nanos(),
+            L1EvictionPolicy::Lfu => {
+                ((entry.access_count as u128) << 64) | (u64::MAX as u128 - (idle.as_nanos() & u64::MAX as u128))
+            }
+            L1EvictionPolicy::TtlFirst => entry.expires_at.saturating_duration_since(now).as_nanos(),
+            L1EvictionPolicy::SizeWeighted => {
+                u128::MAX - (entry.data.len() as u128 + 1) * (idle.as_millis() + 1)
+            }
+        }
+    }
+}
+
+/// Counting Bloom filter estimating how often keys were offered to L1
+///
+/// All counters are halved after every `sample_size` increments so that
+/// past popularity fades.
+struct FrequencySketch {
+    counters: Vec<u8>,
+    additions: usize,
+    sample_size: usize,
+}
+
+impl FrequencySketch {
+    fn new() -> Self {
+        Self {
+            counters: vec![0; SKETCH_COUNTERS],
+            additions: 0,
+            sample_size: SKETCH_COUNTERS * 10,
+        }
+    }
+
+    /// Record an access to `key` and return its estimated access count
+    fn increment(&mut self, key: &str) -> u8 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+
+        let mut estimate = SKETCH_MAX_COUNT;
+        for i in 0..SKETCH_HASHES {
+            let index = (h1.wrapping_add(i.wrapping_mul(h2)) % SKETCH_COUNTERS as u64) as usize;
+            let counter = &mut self.counters[index];
+            if *counter < SKETCH_MAX_COUNT {
+                *counter += 1;
+            }
+            estimate = estimate.min(*counter);
+        }
+
+        self.additions += 1;
+        if self.additions >= self.sample_size {
+            for counter in &mut self.counters {
+                *counter /= 2;
+            }
+            self.additions = 0;
+        }
+        estimate
+    }
+}
+
 /// L2 disk cache metadata
 #[derive(Clone)]
 #[allow(dead_code)]
@@ -55,15 +211,285 @@ struct L2Metadata {
 }
 
 /// Cache statistics
-#[derive(Debug, Clone, Default)]
+///
+/// Serialized field names are part of the `/admin/cache/stats` schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TieredCacheStats {
     pub l1_entries: usize,
     pub l1_bytes: usize,
     pub l1_hits: u64,
+    /// Fraction of lookups served from L1 (0.0 to 1.0)
+    pub l1_hit_ratio: f64,
+    /// L1 entries removed because their TTL passed
+    pub l1_evictions_ttl: u64,
+    /// L1 entries displaced to stay within the L1 size limit
+    pub l1_evictions_size: u64,
+    /// Whether an expiry sweep of L1 is running now
+    pub l1_sweep_running: bool,
+    /// Expiry sweeps skipped because another was already running
+    pub l1_sweeps_coalesced: u64,
+    /// L1 entries evicted by [`TieredCache::shrink_l1_to`]
+    pub l1_evictions_shrink: u64,
+    /// Policy the size and shrink evictions followed
+    pub l1_eviction_policy: L1EvictionPolicy,
+    /// Entries kept out of L1 by the admission policy
+    pub l1_admission_rejected: u64,
     pub l2_hits: u64,
+    /// Whether L2 is currently bypassed because of disk errors
+    pub l2_degraded: bool,
+    /// Statistics reported by the L2 backend (absent without L2)
+    pub l2_backend: Option<CacheBackendStats>,
     pub misses: u64,
+    /// Fraction of lookups served from either tier (0.0 to 1.0)
+    pub overall_hit_ratio: f64,
     pub disk_writes: u64,
     pub disk_errors: u64,
+    /// L2 writes queued but not yet written
+    pub pending_writes: usize,
+    /// Bytes of the queued L2 writes
+    pub pending_write_bytes: usize,
+    /// How long the oldest queued L2 write has been waiting, in milliseconds
+    pub oldest_pending_write_ms: u64,
+}
+
+/// Lets at most one L1 expiry sweep run at a time
+///
+/// The janitor, the server's expiry sweep and admin calls can all start a
+/// sweep; one arriving while another runs is coalesced into it, since the
+/// running sweep removes the same entries.
+#[derive(Default)]
+struct SweepGuard {
+    running: AtomicBool,
+    coalesced: AtomicU64,
+    /// Sweeps running now and the most ever seen at once
+    #[cfg(test)]
+    active: (AtomicU64, AtomicU64),
+}
+
+/// Tracks consecutive L2 failures and whether L2 is bypassed
+struct DiskHealth {
+    consecutive_errors: AtomicU64,
+    degraded: AtomicBool,
+    error_threshold: u64,
+}
+
+impl DiskHealth {
+    fn new(error_threshold: u64) -> Self {
+        Self {
+            consecutive_errors: AtomicU64::new(0),
+            degraded: AtomicBool::new(false),
+            error_threshold: error_threshold.max(1),
+        }
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    fn record_success(&self) {
+        self.consecutive_errors.store(0, Ordering::Relaxed);
+    }
+
+    fn record_error(&self) {
+        let errors = self.consecutive_errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if errors >= self.error_threshold && !self.degraded.swap(true, Ordering::Relaxed) {
+            error!(
+                "L2 disk cache failed {} consecutive times; switching to memory-only mode until the disk recovers",
+                errors
+            );
+        }
+    }
+
+    fn recover(&self) {
+        self.consecutive_errors.store(0, Ordering::Relaxed);
+        if self.degraded.swap(false, Ordering::Relaxed) {
+            warn!("L2 disk cache recovered; re-enabling disk tier");
+        }
+    }
+}
+
+/// Tier that holds a cache entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheTier {
+    L1,
+    L2,
+}
+
+/// Metadata about a single cache entry, without its body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntryInfo {
+    pub tier: CacheTier,
+    pub size_bytes: usize,
+    /// Slices are stored uncompressed
+    pub compressed: bool,
+    /// Stored checksum (entries are stored without one)
+    pub checksum: Option<String>,
+    /// When the entry was stored, in seconds since the Unix epoch
+    pub stored_at_secs: u64,
+    pub ttl_remaining_secs: u64,
+    /// Offset of the entry in the backing store (L2 uses one file per entry)
+    pub offset: Option<u64>,
+    pub access_count: u64,
+}
+
+/// Tiers a single entry was removed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemovedTiers {
+    pub l1: bool,
+    pub l2: bool,
+}
+
+impl RemovedTiers {
+    /// Whether the entry was removed from any tier
+    pub fn any(&self) -> bool {
+        self.l1 || self.l2
+    }
+}
+
+/// One page of cache keys
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheKeyPage {
+    pub keys: Vec<String>,
+    /// Pass as `after` to fetch the next page (absent on the last page)
+    pub next: Option<String>,
+}
+
+/// Iterator over cache keys returned by [`TieredCache::keys`]
+///
+/// Keys are fetched a page at a time with [`TieredCache::list_keys`], so
+/// the L1 lock is only held while a page is collected, never for the
+/// whole walk. Every key present for the whole walk is returned exactly
+/// once; keys stored or removed meanwhile may or may not be.
+pub struct CacheKeys<'a> {
+    cache: &'a TieredCache,
+    prefix: String,
+    page_size: usize,
+    page: std::vec::IntoIter<String>,
+    /// Cursor of the next page, `None` once the last page was fetched
+    next: Option<Option<String>>,
+}
+
+impl Iterator for CacheKeys<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if let Some(key) = self.page.next() {
+                return Some(key);
+            }
+            let after = self.next.take()?;
+            let page = self.cache.list_keys(&self.prefix, after.as_deref(), self.page_size);
+            self.next = page.next.map(Some);
+            self.page = page.keys.into_iter();
+        }
+    }
+}
+
+/// What [`TieredCache::top_entries`] ranks entries by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TopBy {
+    /// Largest first
+    Size,
+    /// Oldest first
+    Age,
+    /// Most looked up first (L1 only)
+    Hits,
+}
+
+/// An entry in a [`TopTier`] report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopEntry {
+    /// Cache key in L1; in L2, the name the backend stores the entry under
+    pub key: String,
+    pub size_bytes: u64,
+    /// When the entry was stored, in seconds since the Unix epoch
+    pub stored_at_secs: u64,
+    /// Last lookup, in seconds since the Unix epoch (L1 only)
+    pub last_access_secs: Option<u64>,
+    /// Lookups served (L1 only)
+    pub access_count: Option<u64>,
+}
+
+/// Totals of one tier and its top entries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopTier {
+    pub total_entries: u64,
+    pub total_bytes: u64,
+    pub entries: Vec<TopEntry>,
+}
+
+/// Report of [`TieredCache::top_entries`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopEntries {
+    pub by: TopBy,
+    pub l1: TopTier,
+    /// Absent without an L2 backend able to list its entries
+    pub l2: Option<TopTier>,
+}
+
+/// A [`TopEntry`] ordered by its score, ties broken by key
+#[derive(PartialEq, Eq)]
+struct Ranked {
+    score: u64,
+    entry: TopEntry,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.score, Reverse(&self.entry.key)).cmp(&(other.score, Reverse(&other.entry.key)))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keeps the `limit` highest-ranked entries seen and the totals of all
+struct TopCollector {
+    by: TopBy,
+    limit: usize,
+    top: BinaryHeap<Reverse<Ranked>>,
+    tier: TopTier,
+}
+
+impl TopCollector {
+    fn new(by: TopBy, limit: usize) -> Self {
+        TopCollector {
+            by,
+            limit,
+            top: BinaryHeap::with_capacity(limit + 1),
+            tier: TopTier::default(),
+        }
+    }
+
+    fn push(&mut self, entry: TopEntry) {
+        self.tier.total_entries += 1;
+        self.tier.total_bytes += entry.size_bytes;
+        let score = match self.by {
+            TopBy::Size => entry.size_bytes,
+            TopBy::Age => u64::MAX - entry.stored_at_secs,
+            TopBy::Hits => entry.access_count.unwrap_or(0),
+        };
+        self.top.push(Reverse(Ranked { score, entry }));
+        if self.top.len() > self.limit {
+            self.top.pop();
+        }
+    }
+
+    fn finish(mut self) -> TopTier {
+        // Ascending order of `Reverse` is descending rank
+        self.tier.entries = self
+            .top
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| ranked.entry)
+            .collect();
+        self.tier
+    }
 }
 
 /// Two-tier cache with memory (L1) and disk (L2) storage
@@ -72,19 +498,30 @@ pub struct TieredCache {
     l1_storage: Arc<RwLock<HashMap<String, L1Entry>>>,
     l1_max_size_bytes: usize,
     l1_current_size: Arc<RwLock<usize>>,
+    l1_admission: L1AdmissionPolicy,
+    l1_eviction: L1EvictionPolicy,
+    l1_frequency: Mutex<FrequencySketch>,
+    origin_quotas: Option<OriginQuotaConfig>,
     
-    // L2: Disk cache
-    l2_base_path: PathBuf,
-    l2_enabled: bool,
+    // L2: Pluggable backend (disk by default)
+    l2: Option<Arc<dyn CacheBackend>>,
     
     // Configuration
     ttl: Duration,
+    stale_window: Duration,
     
     // Statistics
     stats: Arc<RwLock<TieredCacheStats>>,
+    l1_sweep: Arc<SweepGuard>,
+    
+    // L2 health (bypass L2 while the disk is failing)
+    disk_health: Arc<DiskHealth>,
     
     // Async disk writer
     disk_writer_tx: Option<mpsc::UnboundedSender<DiskWriteMessage>>,
+    pending_writes: PendingWrites,
+    l2_write_lock: L2WriteLock,
+    write_seq: AtomicU64,
 }
 
 impl TieredCache {
@@ -99,13 +536,64 @@ impl TieredCache {
         l1_max_size_bytes: usize,
         l2_base_path: impl AsRef<Path>,
     ) -> Result<Self> {
-        let l2_base_path = l2_base_path.as_ref().to_path_buf();
-        
+        Self::with_disk_health(
+            ttl,
+            l1_max_size_bytes,
+            l2_base_path,
+            DEFAULT_DISK_ERROR_THRESHOLD,
+            DEFAULT_DISK_PROBE_INTERVAL,
+        )
+        .await
+    }
+    
+    /// Create a new two-tier cache with custom disk failure handling
+    ///
+    /// After `disk_error_threshold` consecutive L2 errors the cache serves
+    /// from L1 only (misses go to origin) and probes the disk every
+    /// `probe_interval` until it is writable again.
+    ///
+    /// # Arguments
+    /// * `ttl` - Time-to-live for cached items
+    /// * `l1_max_size_bytes` - Maximum L1 (memory) cache size
+    /// * `l2_base_path` - Base directory for L2 (disk) cache
+    /// * `disk_error_threshold` - Consecutive disk errors before L2 is bypassed
+    /// * `probe_interval` - How often a bypassed L2 is probed for recovery
+    pub async fn with_disk_health(
+        ttl: Duration,
+        l1_max_size_bytes: usize,
+        l2_base_path: impl AsRef<Path>,
+        disk_error_threshold: u64,
+        probe_interval: Duration,
+    ) -> Result<Self> {
+        Self::open_disk(
+            ttl,
+            l1_max_size_bytes,
+            l2_base_path.as_ref(),
+            disk_error_threshold,
+            probe_interval,
+            None,
+        )
+        .await
+    }
+    
+    /// Open the L2 directory, wrapped in a [`DeltaBackend`] if `delta` is
+    /// given
+    async fn open_disk(
+        ttl: Duration,
+        l1_max_size_bytes: usize,
+        l2_base_path: &Path,
+        disk_error_threshold: u64,
+        probe_interval: Duration,
+        delta: Option<&L2DeltaConfig>,
+    ) -> Result<Self> {
         // Create L2 directory if it doesn't exist
-        if let Err(e) = fs::create_dir_all(&l2_base_path).await {
-            warn!("Failed to create L2 cache directory: {}", e);
-            return Ok(Self::memory_only(ttl, l1_max_size_bytes));
-        }
+        let backend = match FileBackend::new(l2_base_path).await {
+            Ok(backend) => backend,
+            Err(e) => {
+                warn!("{}", e);
+                return Ok(Self::memory_only(ttl, l1_max_size_bytes));
+            }
+        };
         
         info!(
             "Initializing two-tier cache: L1={}MB, L2={:?}",
@@ -113,26 +601,117 @@ impl TieredCache {
             l2_base_path
         );
         
+        // Refuse a directory written by a dedup backend, purge entries
+        // stored under an older key format or entry encoding and load the
+        // entry index, deleting old files it does not list, then clean up
+        // after writes interrupted by the last shutdown and check what is
+        // left
+        backend.verify_layout().await?;
+        backend.verify_key_version(SliceKey::FORMAT_VERSION).await?;
+        let format = if delta.is_some() { EntryFormat::Delta } else { EntryFormat::Raw };
+        backend.verify_entry_format(format).await?;
+        if let Err(e) = backend.load_index().await {
+            warn!("{}", e);
+        }
+        if let Err(e) = backend.recover().await {
+            warn!("{}", e);
+        }
+        if let Err(e) = backend.check_consistency().await {
+            warn!("{}", e);
+        }
+        
+        let backend: Arc<dyn CacheBackend> = match delta {
+            Some(delta) => Arc::new(
+                DeltaBackend::new(Arc::new(backend))
+                    .with_min_size(delta.min_size_bytes)
+                    .with_max_delta_ratio(delta.max_delta_ratio),
+            ),
+            None => Arc::new(backend),
+        };
+        Ok(Self::with_backend_and_health(
+            ttl,
+            l1_max_size_bytes,
+            backend,
+            disk_error_threshold,
+            probe_interval,
+        ))
+    }
+    
+    /// Create a two-tier cache with a custom L2 backend
+    ///
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Arguments
+    /// * `ttl` - Time-to-live for cached items
+    /// * `l1_max_size_bytes` - Maximum L1 (memory) cache size
+    /// * `backend` - Storage for the L2 tier
+    pub fn with_backend(
+        ttl: Duration,
+        l1_max_size_bytes: usize,
+        backend: Arc<dyn CacheBackend>,
+    ) -> Self {
+        Self::with_backend_and_health(
+            ttl,
+            l1_max_size_bytes,
+            backend,
+            DEFAULT_DISK_ERROR_THRESHOLD,
+            DEFAULT_DISK_PROBE_INTERVAL,
+        )
+    }
+    
+    /// Create a two-tier cache with a custom L2 backend and failure handling
+    ///
+    /// After `error_threshold` consecutive backend errors the cache serves
+    /// from L1 only and calls [`CacheBackend::health`] every
+    /// `probe_interval` until it succeeds. Must be called from within a
+    /// Tokio runtime.
+    pub fn with_backend_and_health(
+        ttl: Duration,
+        l1_max_size_bytes: usize,
+        backend: Arc<dyn CacheBackend>,
+        error_threshold: u64,
+        probe_interval: Duration,
+    ) -> Self {
         // Start async disk writer
         let (tx, rx) = mpsc::unbounded_channel();
-        let l2_path_clone = l2_base_path.clone();
-        let stats_clone = Arc::new(RwLock::new(TieredCacheStats::default()));
-        let stats_for_writer = stats_clone.clone();
-        
-        tokio::spawn(async move {
-            Self::disk_writer_task(rx, l2_path_clone, stats_for_writer).await;
-        });
+        let stats = Arc::new(RwLock::new(TieredCacheStats::default()));
+        let disk_health = Arc::new(DiskHealth::new(error_threshold));
+        let pending_writes = PendingWrites::default();
+        let l2_write_lock = L2WriteLock::default();
+        
+        tokio::spawn(Self::disk_writer_task(
+            rx,
+            backend.clone(),
+            stats.clone(),
+            disk_health.clone(),
+            pending_writes.clone(),
+            l2_write_lock.clone(),
+        ));
+        tokio::spawn(Self::disk_probe_task(
+            Arc::downgrade(&disk_health),
+            backend.clone(),
+            probe_interval,
+        ));
         
-        Ok(TieredCache {
+        TieredCache {
             l1_storage: Arc::new(RwLock::new(HashMap::new())),
             l1_max_size_bytes,
             l1_current_size: Arc::new(RwLock::new(0)),
-            l2_base_path,
-            l2_enabled: true,
+            l1_admission: L1AdmissionPolicy::Always,
+            l1_eviction: L1EvictionPolicy::Lru,
+            l1_frequency: Mutex::new(FrequencySketch::new()),
+            origin_quotas: None,
+            l2: Some(backend),
             ttl,
-            stats: stats_clone,
+            stale_window: Duration::ZERO,
+            stats,
+            l1_sweep: Arc::default(),
+            disk_health,
             disk_writer_tx: Some(tx),
-        })
+            pending_writes,
+            l2_write_lock,
+            write_seq: AtomicU64::new(0),
+        }
     }
     
     /// Create a memory-only cache (L2 disabled)
@@ -143,29 +722,135 @@ impl TieredCache {
             l1_storage: Arc::new(RwLock::new(HashMap::new())),
             l1_max_size_bytes,
             l1_current_size: Arc::new(RwLock::new(0)),
-            l2_base_path: PathBuf::new(),
-            l2_enabled: false,
+            l1_admission: L1AdmissionPolicy::Always,
+            l1_eviction: L1EvictionPolicy::Lru,
+            l1_frequency: Mutex::new(FrequencySketch::new()),
+            origin_quotas: None,
+            l2: None,
             ttl,
+            stale_window: Duration::ZERO,
             stats: Arc::new(RwLock::new(TieredCacheStats::default())),
+            l1_sweep: Arc::default(),
+            disk_health: Arc::new(DiskHealth::new(DEFAULT_DISK_ERROR_THRESHOLD)),
             disk_writer_tx: None,
+            pending_writes: PendingWrites::default(),
+            l2_write_lock: L2WriteLock::default(),
+            write_seq: AtomicU64::new(0),
+        }
+    }
+    
+    /// Create the cache described by `config`
+    ///
+    /// L2 is kept in `l2_cache_dir` when `enable_l2_cache` is set, and
+    /// bypassed as `l2_health` describes when it fails, and stores updates
+    /// as diffs with `l2_delta`; if the directory cannot be created the
+    /// cache runs memory-only. L1 holds up to
+    /// `l1_cache_size_bytes`, with `origin_quotas` applied, and keeps
+    /// expired entries for `stale_if_error_secs`.
+    pub async fn from_config(config: &SliceConfig) -> Result<Self> {
+        let ttl = Duration::from_secs(config.cache_ttl);
+        let cache = if config.enable_l2_cache {
+            Self::open_disk(
+                ttl,
+                config.l1_cache_size_bytes,
+                Path::new(&config.l2_cache_dir),
+                config.l2_health.error_threshold,
+                Duration::from_secs(config.l2_health.probe_interval_secs),
+                Some(&config.l2_delta).filter(|delta| delta.enabled),
+            )
+            .await?
+        } else {
+            Self::memory_only(ttl, config.l1_cache_size_bytes)
         }
+        .with_stale_window(Duration::from_secs(config.stale_if_error_secs));
+        Ok(match &config.origin_quotas {
+            Some(quotas) => cache.with_origin_quotas(quotas.clone()),
+            None => cache,
+        })
+    }
+    
+    /// Limit how many L1 bytes each origin host may occupy
+    ///
+    /// A store that takes a host over its quota evicts that host's entries
+    /// in eviction policy order, leaving other hosts' entries alone. L2 is
+    /// not limited.
+    pub fn with_origin_quotas(mut self, quotas: OriginQuotaConfig) -> Self {
+        self.origin_quotas = Some(quotas);
+        self
+    }
+    
+    /// Set which entries are admitted into L1 (default: all)
+    pub fn with_l1_admission(mut self, policy: L1AdmissionPolicy) -> Self {
+        self.l1_admission = policy;
+        self
+    }
+    
+    /// Set which entries are evicted first when L1 is full (default: LRU)
+    pub fn with_l1_eviction(mut self, policy: L1EvictionPolicy) -> Self {
+        self.l1_eviction = policy;
+        self
+    }
+    
+    /// Keep expired L1 entries for `window` past their TTL
+    ///
+    /// Sweeps leave them in place for that long so that
+    /// [`lookup_stale`](Self::lookup_stale) can serve them while the
+    /// origin is failing. Set it before starting the janitor.
+    pub fn with_stale_window(mut self, window: Duration) -> Self {
+        self.stale_window = window;
+        self
+    }
+    
+    /// Sweep expired L1 entries every `interval` in the background
+    ///
+    /// Without a sweep, expired entries stay in memory until they are
+    /// displaced or [`evict_expired_l1`](Self::evict_expired_l1) runs. Must be called from within a Tokio runtime; the task
+    /// exits when the cache is dropped.
+    pub fn with_l1_janitor(self, interval: Duration) -> Self {
+        tokio::spawn(Self::l1_janitor_task(
+            Arc::downgrade(&self.l1_storage),
+            self.l1_current_size.clone(),
+            self.stats.clone(),
+            self.l1_sweep.clone(),
+            self.stale_window,
+            interval,
+        ));
+        self
     }
     
     /// Generate cache key from URL and byte range
+    ///
+    /// Keys match [`SliceCache::generate_cache_key`](crate::SliceCache::generate_cache_key),
+    /// so both caches name a slice the same way.
     pub fn generate_cache_key(&self, url: &str, range: &ByteRange) -> String {
-        format!("{}:{}:{}", url, range.start, range.end)
+        SliceKey::from_object_key(url).with_range(*range).slice_key()
     }
     
     /// Lookup a slice in the cache (checks L1 then L2)
     pub async fn lookup(&self, url: &str, range: &ByteRange) -> Result<Option<Bytes>> {
+        Ok(self.lookup_with_age(url, range).await?.map(|(data, _)| data))
+    }
+    
+    /// Lookup a slice along with its age (checks L1 then L2)
+    ///
+    /// The age is the time since the slice was stored, for the `Age`
+    /// response header; it carries over when an L2 entry is promoted.
+    /// Expired L1 entries are left in place, so that
+    /// [`is_expired`](Self::is_expired) can tell a refetch from a first
+    /// fetch, until they are swept or displaced.
+    #[tracing::instrument(
+        name = "cache_lookup",
+        skip_all,
+        fields(url = %url, range = %range.to_header(), cache_tier = "miss", bytes = tracing::field::Empty)
+    )]
+    pub async fn lookup_with_age(&self, url: &str, range: &ByteRange) -> Result<Option<(Bytes, Duration)>> {
         let key = self.generate_cache_key(url, range);
-        let now = SystemTime::now();
+        let now = Instant::now();
         
         // Try L1 first
         {
             let mut storage = self.l1_storage.write().unwrap();
             if let Some(entry) = storage.get_mut(&key) {
-                // Check expiration
                 if entry.expires_at > now {
                     // Update access tracking
                     entry.last_accessed = now;
@@ -173,31 +858,41 @@ impl TieredCache {
                     
                     // Record L1 hit
                     self.stats.write().unwrap().l1_hits += 1;
+                    record_hit("l1", entry.data.len());
                     
                     debug!("L1 cache hit: {}", key);
-                    return Ok(Some(entry.data.clone()));
-                } else {
-                    // Expired, remove from L1
-                    let removed = storage.remove(&key);
-                    if let Some(removed_entry) = removed {
-                        let mut size = self.l1_current_size.write().unwrap();
-                        *size = size.saturating_sub(removed_entry.data.len());
-                    }
+                    let age = SystemTime::now().duration_since(entry.stored_at).unwrap_or_default();
+                    return Ok(Some((entry.data.clone(), age)));
                 }
             }
         }
         
-        // Try L2 if enabled
-        if self.l2_enabled {
-            if let Some(data) = self.lookup_l2(&key).await? {
-                // Promote to L1
-                self.store_l1(&key, data.clone(), now + self.ttl);
+        // Then L2 writes that have not landed yet
+        if let Some((data, age)) = self.lookup_pending(&key, now) {
+            self.stats.write().unwrap().l2_hits += 1;
+            record_hit("l2", data.len());
+            debug!("L2 write buffer hit: {}", key);
+            return Ok(Some((data, age)));
+        }
+        
+        // Try L2 if enabled and healthy
+        if self.l2_active() {
+            if let Some(data) = self.lookup_l2(&key).await {
+                // Promote to L1 for what is left of the entry's TTL
+                let entry = self.l2_entry(&key).await;
+                let ttl = entry.as_ref().map_or(self.ttl, |entry| entry.ttl_remaining);
+                let stored_at = entry.map_or_else(SystemTime::now, |entry| {
+                    UNIX_EPOCH + Duration::from_secs(entry.stored_at_secs)
+                });
+                self.store_l1_at(&key, data.clone(), now + ttl, stored_at);
                 
                 // Record L2 hit
                 self.stats.write().unwrap().l2_hits += 1;
+                record_hit("l2", data.len());
                 
                 debug!("L2 cache hit (promoted to L1): {}", key);
-                return Ok(Some(data));
+                let age = SystemTime::now().duration_since(stored_at).unwrap_or_default();
+                return Ok(Some((data, age)));
             }
         }
         
@@ -207,32 +902,187 @@ impl TieredCache {
         Ok(None)
     }
     
+    /// Time since a cached slice was stored, without reading it
+    ///
+    /// Like [`inspect`](Self::inspect), this leaves access tracking and
+    /// statistics alone and does not promote L2 entries.
+    ///
+    /// # Returns
+    /// `None` if the slice is not cached
+    pub async fn age(&self, url: &str, range: &ByteRange) -> Option<Duration> {
+        let key = self.generate_cache_key(url, range);
+        let now = Instant::now();
+        {
+            let storage = self.l1_storage.read().unwrap();
+            if let Some(entry) = storage.get(&key).filter(|entry| entry.expires_at > now) {
+                return Some(SystemTime::now().duration_since(entry.stored_at).unwrap_or_default());
+            }
+        }
+        if let Some((_, age)) = self.lookup_pending(&key, now) {
+            return Some(age);
+        }
+        if !self.l2_active() {
+            return None;
+        }
+        let entry = self.l2_entry(&key).await?;
+        let stored_at = UNIX_EPOCH + Duration::from_secs(entry.stored_at_secs);
+        Some(SystemTime::now().duration_since(stored_at).unwrap_or_default())
+    }
+    
+    /// Lookup an expired slice, to serve while the origin is failing
+    ///
+    /// Only L1 keeps expired entries, for the window set with
+    /// [`with_stale_window`](Self::with_stale_window). Statistics and access
+    /// tracking are left alone.
+    ///
+    /// # Returns
+    /// The slice and the time since it was stored, or `None` if it is
+    /// fresh, not cached or expired more than `max_stale` ago
+    pub fn lookup_stale(&self, url: &str, range: &ByteRange, max_stale: Duration) -> Option<(Bytes, Duration)> {
+        let key = self.generate_cache_key(url, range);
+        let now = Instant::now();
+        let storage = self.l1_storage.read().unwrap();
+        let entry = storage
+            .get(&key)
+            .filter(|entry| entry.expires_at <= now && entry.expires_at + max_stale > now)?;
+        let age = SystemTime::now().duration_since(entry.stored_at).unwrap_or_default();
+        Some((entry.data.clone(), age))
+    }
+    
+    /// Whether L1 holds an expired copy of a slice
+    ///
+    /// Expired entries stay in place until swept or overwritten, which
+    /// tells a refetch of a known slice apart from a first fetch.
+    pub fn is_expired(&self, url: &str, range: &ByteRange) -> bool {
+        let key = self.generate_cache_key(url, range);
+        let now = Instant::now();
+        self.l1_storage
+            .read()
+            .unwrap()
+            .get(&key)
+            .is_some_and(|entry| entry.expires_at <= now)
+    }
+    
     /// Store a slice in the cache (L1 + async L2)
     pub fn store(&self, url: &str, range: &ByteRange, data: Bytes) -> Result<()> {
+        self.store_with_ttl(url, range, data, self.ttl)
+    }
+    
+    /// Store a slice in the cache with a per-entry TTL
+    ///
+    /// The TTL is kept with the entry in both tiers, including when it is
+    /// promoted from L2 back to L1.
+    #[tracing::instrument(
+        name = "cache_store",
+        skip_all,
+        fields(url = %url, range = %range.to_header(), cache_tier = "l1", bytes = data.len())
+    )]
+    pub fn store_with_ttl(&self, url: &str, range: &ByteRange, data: Bytes, ttl: Duration) -> Result<()> {
         let key = self.generate_cache_key(url, range);
-        let expires_at = SystemTime::now() + self.ttl;
+        let expires_at = Instant::now() + ttl;
         
         // Store in L1
         self.store_l1(&key, data.clone(), expires_at);
         
-        // Async store in L2
-        if self.l2_enabled {
+        // Async store in L2 (skipped while the disk is failing)
+        if self.l2_active() {
             if let Some(tx) = &self.disk_writer_tx {
-                let _ = tx.send(DiskWriteMessage::Write {
-                    key,
-                    data,
-                    expires_at,
-                });
+                let seq = self.write_seq.fetch_add(1, Ordering::Relaxed);
+                self.pending_writes.lock().unwrap().insert(
+                    key.clone(),
+                    PendingWrite {
+                        seq,
+                        data,
+                        expires_at,
+                        queued_at: Instant::now(),
+                    },
+                );
+                let _ = tx.send(DiskWriteMessage::Write { key, seq });
             }
         }
         
         Ok(())
     }
     
-    /// Store in L1 cache with LRU eviction
-    fn store_l1(&self, key: &str, data: Bytes, expires_at: SystemTime) {
+    /// Unexpired data queued for L2 under `key`, with its age
+    fn lookup_pending(&self, key: &str, now: Instant) -> Option<(Bytes, Duration)> {
+        let pending = self.pending_writes.lock().unwrap();
+        pending
+            .get(key)
+            .filter(|write| write.expires_at > now)
+            .map(|write| (write.data.clone(), now.saturating_duration_since(write.queued_at)))
+    }
+    
+    /// Drop queued L2 writes for `keys`, so they are neither served nor
+    /// written
+    fn cancel_pending<'a>(&self, keys: impl IntoIterator<Item = &'a String>) {
+        let mut pending = self.pending_writes.lock().unwrap();
+        for key in keys {
+            pending.remove(key);
+        }
+    }
+    
+    /// Write every queued L2 entry and flush the backend
+    ///
+    /// Queued writes are lost if the process exits before the disk writer
+    /// gets to them, so call this on shutdown. Also makes the entries
+    /// durable with backends that defer syncing, like [`FileBackend`] with
+    /// [`FsyncPolicy::Interval`](crate::FsyncPolicy::Interval).
+    pub async fn flush(&self) -> Result<()> {
+        let Some(tx) = &self.disk_writer_tx else {
+            return Ok(());
+        };
+        let (done, flushed) = oneshot::channel();
+        let stopped = || SliceError::CacheError("L2 disk writer is not running".to_string());
+        tx.send(DiskWriteMessage::Flush { done }).map_err(|_| stopped())?;
+        flushed.await.map_err(|_| stopped())?
+    }
+    
+    /// Whether L2 is enabled and not bypassed because of disk errors
+    fn l2_active(&self) -> bool {
+        self.l2.is_some() && !self.disk_health.is_degraded()
+    }
+    
+    /// Whether L2 is currently bypassed because of disk errors
+    pub fn is_l2_degraded(&self) -> bool {
+        self.l2.is_some() && self.disk_health.is_degraded()
+    }
+    
+    /// Whether L2 is configured, degraded or not
+    pub fn has_l2(&self) -> bool {
+        self.l2.is_some()
+    }
+    
+    /// Check that the L1 locks can still be taken
+    ///
+    /// # Returns
+    /// `false` if a thread panicked while holding one, leaving the cache
+    /// unusable
+    pub fn is_accessible(&self) -> bool {
+        self.l1_storage.read().is_ok()
+            && self.l1_current_size.read().is_ok()
+            && self.stats.read().is_ok()
+    }
+    
+    /// Whether the admission policy lets an entry into L1
+    fn admit_l1(&self, key: &str, size: usize) -> bool {
+        match self.l1_admission {
+            L1AdmissionPolicy::Always => true,
+            L1AdmissionPolicy::SizeBelow(limit) => size < limit,
+            L1AdmissionPolicy::Frequency => self.l1_frequency.lock().unwrap().increment(key) >= 2,
+        }
+    }
+    
+    /// Store in L1 cache, evicting entries as the eviction policy says
+    fn store_l1(&self, key: &str, data: Bytes, expires_at: Instant) {
+        self.store_l1_at(key, data, expires_at, SystemTime::now());
+    }
+    
+    /// Store in L1 cache an entry first stored at `stored_at`
+    fn store_l1_at(&self, key: &str, data: Bytes, expires_at: Instant, stored_at: SystemTime) {
         let data_size = data.len();
-        let now = SystemTime::now();
+        let now = Instant::now();
+        let admitted = self.admit_l1(key, data_size);
         
         let mut storage = self.l1_storage.write().unwrap();
         let mut current_size = self.l1_current_size.write().unwrap();
@@ -242,21 +1092,33 @@ impl TieredCache {
             *current_size = current_size.saturating_sub(old_entry.data.len());
         }
         
-        // Evict LRU entries if needed
-        while *current_size + data_size > self.l1_max_size_bytes && !storage.is_empty() {
-            // Find LRU entry
-            if let Some((lru_key, _)) = storage
-                .iter()
-                .min_by_key(|(_, entry)| entry.last_accessed)
-                .map(|(k, v)| (k.clone(), v.clone()))
-            {
-                if let Some(removed) = storage.remove(&lru_key) {
-                    *current_size = current_size.saturating_sub(removed.data.len());
-                    debug!("Evicted LRU entry from L1: {}", lru_key);
-                }
-            } else {
-                break;
-            }
+        if !admitted {
+            self.stats.write().unwrap().l1_admission_rejected += 1;
+            debug!("L1 admission rejected: {} ({} bytes)", key, data_size);
+            return;
+        }
+        
+        // Keep the origin within its quota by evicting its own entries,
+        // then the whole of L1 within its size limit
+        let host = origin_host(key);
+        let mut evicted = 0;
+        if let Some(quota) = self.origin_quota(host) {
+            evicted += evict_l1_host(
+                self.l1_eviction,
+                &mut storage,
+                &mut current_size,
+                host,
+                quota.saturating_sub(data_size),
+            );
+        }
+        evicted += evict_l1(
+            self.l1_eviction,
+            &mut storage,
+            &mut current_size,
+            self.l1_max_size_bytes.saturating_sub(data_size),
+        );
+        if evicted > 0 {
+            self.stats.write().unwrap().l1_evictions_size += evicted as u64;
         }
         
         // Insert new entry
@@ -264,6 +1126,7 @@ impl TieredCache {
             key.to_string(),
             L1Entry {
                 data,
+                stored_at,
                 expires_at,
                 last_accessed: now,
                 access_count: 0,
@@ -274,68 +1137,102 @@ impl TieredCache {
         debug!("Stored in L1: {} ({} bytes)", key, data_size);
     }
     
-    /// Lookup in L2 disk cache
-    async fn lookup_l2(&self, key: &str) -> Result<Option<Bytes>> {
-        let file_path = self.get_l2_file_path(key);
-        
-        match fs::read(&file_path).await {
-            Ok(data) => {
-                // Check if file is expired (first 8 bytes = timestamp)
-                if data.len() < 8 {
-                    let _ = fs::remove_file(&file_path).await;
-                    return Ok(None);
-                }
-                
-                let timestamp_bytes: [u8; 8] = data[0..8].try_into().unwrap();
-                let expires_at_secs = u64::from_le_bytes(timestamp_bytes);
-                let expires_at = UNIX_EPOCH + Duration::from_secs(expires_at_secs);
-                
-                if expires_at <= SystemTime::now() {
-                    // Expired, delete file
-                    let _ = fs::remove_file(&file_path).await;
-                    return Ok(None);
-                }
-                
-                // Return data (skip first 8 bytes)
-                Ok(Some(Bytes::from(data[8..].to_vec())))
+    /// Lookup in the L2 backend
+    ///
+    /// Backend errors count towards degrading L2 and are reported as a miss.
+    async fn lookup_l2(&self, key: &str) -> Option<Bytes> {
+        match self.l2.as_ref()?.lookup(key).await {
+            Ok(Some(data)) => {
+                self.disk_health.record_success();
+                Some(data)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!("L2 lookup failed for {}: {}", key, e);
+                self.stats.write().unwrap().disk_errors += 1;
+                self.disk_health.record_error();
+                None
             }
-            Err(_) => Ok(None),
         }
     }
     
+    /// Metadata of the L2 entry under `key`, if the backend reports it
+    async fn l2_entry(&self, key: &str) -> Option<BackendEntry> {
+        self.l2.as_ref()?.entry(key).await.ok()?
+    }
+    
+    /// L1 byte quota for an origin host, if it has one
+    fn origin_quota(&self, host: &str) -> Option<usize> {
+        let quotas = self.origin_quotas.as_ref()?;
+        quotas.hosts.get(host).copied().or(quotas.default_bytes)
+    }
+    
     /// Async disk writer task
     async fn disk_writer_task(
         mut rx: mpsc::UnboundedReceiver<DiskWriteMessage>,
-        base_path: PathBuf,
+        backend: Arc<dyn CacheBackend>,
         stats: Arc<RwLock<TieredCacheStats>>,
+        disk_health: Arc<DiskHealth>,
+        pending: PendingWrites,
+        write_lock: L2WriteLock,
     ) {
         info!("Disk writer task started");
         
         while let Some(msg) = rx.recv().await {
             match msg {
-                DiskWriteMessage::Write {
-                    key,
-                    data,
-                    expires_at,
-                } => {
-                    if let Err(e) = Self::write_to_disk(&base_path, &key, &data, expires_at).await
-                    {
+                DiskWriteMessage::Write { key, seq } => {
+                    let _writing = write_lock.lock().await;
+                    // Skip writes purged or superseded while queued
+                    let queued = pending
+                        .lock()
+                        .unwrap()
+                        .get(&key)
+                        .filter(|write| write.seq == seq)
+                        .map(|write| (write.data.clone(), write.expires_at));
+                    let Some((data, expires_at)) = queued else {
+                        continue;
+                    };
+                    let ttl = expires_at.saturating_duration_since(Instant::now());
+                    if let Err(e) = backend.store(&key, data, ttl).await {
                         error!("Failed to write to L2 cache: {}", e);
                         stats.write().unwrap().disk_errors += 1;
+                        disk_health.record_error();
                     } else {
                         stats.write().unwrap().disk_writes += 1;
+                        disk_health.record_success();
+                    }
+                    
+                    let cancelled = {
+                        let mut pending = pending.lock().unwrap();
+                        match pending.get(&key) {
+                            Some(write) if write.seq == seq => {
+                                pending.remove(&key);
+                                false
+                            }
+                            Some(_) => false,
+                            None => true,
+                        }
+                    };
+                    // Purged while being written: the removal may have run
+                    // before the file landed
+                    if cancelled {
+                        if let Err(e) = backend.remove(&key).await {
+                            warn!("Failed to delete from L2 cache: {}", e);
+                        }
                     }
                 }
                 DiskWriteMessage::Delete { key } => {
-                    let file_path = Self::get_l2_file_path_static(&base_path, &key);
-                    if let Err(e) = fs::remove_file(&file_path).await {
-                        if e.kind() != std::io::ErrorKind::NotFound {
-                            warn!("Failed to delete L2 cache file {}: {}", file_path.display(), e);
-                        }
-                    } else {
-                        debug!("Deleted from L2: {}", key);
+                    if let Err(e) = backend.remove(&key).await {
+                        warn!("Failed to delete from L2 cache: {}", e);
                     }
                 }
+                DiskWriteMessage::PurgeAll => match backend.purge_all().await {
+                    Ok(removed) => info!("Purged {} entries from L2", removed),
+                    Err(e) => warn!("Failed to purge L2 cache: {}", e),
+                },
+                DiskWriteMessage::Flush { done } => {
+                    let _ = done.send(backend.flush().await);
+                }
                 DiskWriteMessage::Shutdown => {
                     info!("Disk writer task shutting down");
                     break;
@@ -344,75 +1241,52 @@ impl TieredCache {
         }
     }
     
-    /// Write data to disk
-    async fn write_to_disk(
-        base_path: &Path,
-        key: &str,
-        data: &Bytes,
-        expires_at: SystemTime,
-    ) -> Result<()> {
-        let file_path = Self::get_l2_file_path_static(base_path, key);
-        
-        // Create parent directory if needed
-        if let Some(parent) = file_path.parent() {
-            fs::create_dir_all(parent).await.map_err(|e| {
-                SliceError::CacheError(format!("Failed to create cache directory: {}", e))
-            })?;
+    /// Periodically remove expired L1 entries
+    ///
+    /// Exits when the owning cache is dropped.
+    async fn l1_janitor_task(
+        storage: Weak<RwLock<HashMap<String, L1Entry>>>,
+        current_size: Arc<RwLock<usize>>,
+        stats: Arc<RwLock<TieredCacheStats>>,
+        sweep: Arc<SweepGuard>,
+        stale_window: Duration,
+        interval: Duration,
+    ) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(storage) = storage.upgrade() else {
+                break;
+            };
+            
+            let expired = remove_expired_l1(&storage, &current_size, &stats, &sweep, stale_window);
+            if expired > 0 {
+                debug!("L1 janitor removed {} expired entries", expired);
+            }
         }
-        
-        // Write timestamp + data
-        let expires_at_secs = expires_at
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        
-        let mut file = fs::File::create(&file_path).await.map_err(|e| {
-            SliceError::CacheError(format!("Failed to create cache file: {}", e))
-        })?;
-        
-        file.write_all(&expires_at_secs.to_le_bytes())
-            .await
-            .map_err(|e| SliceError::CacheError(format!("Failed to write timestamp: {}", e)))?;
-        
-        file.write_all(data).await.map_err(|e| {
-            SliceError::CacheError(format!("Failed to write data: {}", e))
-        })?;
-        
-        file.sync_all().await.map_err(|e| {
-            SliceError::CacheError(format!("Failed to sync file: {}", e))
-        })?;
-        
-        debug!("Wrote to L2: {} ({} bytes)", key, data.len());
-        Ok(())
-    }
-    
-    /// Get L2 file path for a key
-    fn get_l2_file_path(&self, key: &str) -> PathBuf {
-        Self::get_l2_file_path_static(&self.l2_base_path, key)
     }
     
-    /// Get L2 file path (static version)
-    fn get_l2_file_path_static(base_path: &Path, key: &str) -> PathBuf {
-        // Use hash to create subdirectories (avoid too many files in one dir)
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        let hash = hasher.finish();
-        
-        // Create 2-level directory structure: XX/YY/filename
-        let dir1 = format!("{:02x}", (hash >> 8) & 0xFF);
-        let dir2 = format!("{:02x}", hash & 0xFF);
-        
-        // Sanitize key for filename
-        let filename = key
-            .replace('/', "_")
-            .replace(':', "_")
-            .replace('?', "_")
-            .replace('&', "_");
-        
-        base_path.join(dir1).join(dir2).join(filename)
+    /// Periodically probe a degraded L2 and re-enable it once healthy
+    ///
+    /// Exits when the owning cache is dropped.
+    async fn disk_probe_task(
+        health: Weak<DiskHealth>,
+        backend: Arc<dyn CacheBackend>,
+        interval: Duration,
+    ) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(health) = health.upgrade() else {
+                break;
+            };
+            if !health.is_degraded() {
+                continue;
+            }
+            
+            match backend.health().await {
+                Ok(()) => health.recover(),
+                Err(e) => debug!("L2 health probe failed: {}", e),
+            }
+        }
     }
     
     /// Get cache statistics
@@ -422,10 +1296,233 @@ impl TieredCache {
         let storage = self.l1_storage.read().unwrap();
         stats.l1_entries = storage.len();
         stats.l1_bytes = *self.l1_current_size.read().unwrap();
+        stats.l1_eviction_policy = self.l1_eviction;
+        stats.l1_sweep_running = self.l1_sweep.running.load(Ordering::Relaxed);
+        stats.l1_sweeps_coalesced = self.l1_sweep.coalesced.load(Ordering::Relaxed);
+        stats.l2_degraded = self.is_l2_degraded();
+        stats.l2_backend = self.l2.as_ref().map(|backend| backend.stats());
+        
+        let pending = self.pending_writes.lock().unwrap();
+        stats.pending_writes = pending.len();
+        stats.pending_write_bytes = pending.values().map(|write| write.data.len()).sum();
+        stats.oldest_pending_write_ms = pending
+            .values()
+            .map(|write| write.queued_at.elapsed().as_millis() as u64)
+            .max()
+            .unwrap_or(0);
+        
+        let lookups = stats.l1_hits + stats.l2_hits + stats.misses;
+        if lookups > 0 {
+            stats.l1_hit_ratio = stats.l1_hits as f64 / lookups as f64;
+            stats.overall_hit_ratio = (stats.l1_hits + stats.l2_hits) as f64 / lookups as f64;
+        }
         
         stats
     }
     
+    /// Remove expired entries from L1 now
+    ///
+    /// Entries still within the stale window are kept. At most one sweep
+    /// runs at a time; a call arriving while one runs, here or in the
+    /// janitor, is coalesced into it.
+    ///
+    /// # Returns
+    /// The number of entries removed, 0 when coalesced
+    pub fn evict_expired_l1(&self) -> usize {
+        remove_expired_l1(&self.l1_storage, &self.l1_current_size, &self.stats, &self.l1_sweep, self.stale_window)
+    }
+    
+    /// Evict L1 entries in eviction policy order until L1 holds at most
+    /// `target_bytes`
+    ///
+    /// For reacting to memory pressure, e.g. from an admin endpoint or a
+    /// cgroup memory watcher. L2 is not affected, and the L1 size limit is
+    /// unchanged, so L1 can grow back afterwards.
+    ///
+    /// # Returns
+    /// The number of entries evicted
+    pub fn shrink_l1_to(&self, target_bytes: usize) -> usize {
+        let evicted = {
+            let mut storage = self.l1_storage.write().unwrap();
+            let mut current_size = self.l1_current_size.write().unwrap();
+            if *current_size <= target_bytes {
+                return 0;
+            }
+            
+            evict_l1(self.l1_eviction, &mut storage, &mut current_size, target_bytes)
+        };
+        
+        self.stats.write().unwrap().l1_evictions_shrink += evicted as u64;
+        info!("Shrunk L1 to {} bytes: {} entries evicted", target_bytes, evicted);
+        evicted
+    }
+    
+    /// Inspect a cache entry by its cache key without returning the body
+    ///
+    /// Unlike [`lookup`](Self::lookup), this does not update access tracking,
+    /// hit/miss statistics, or promote L2 entries into L1.
+    pub async fn inspect(&self, key: &str) -> Option<CacheEntryInfo> {
+        let now = Instant::now();
+
+        {
+            let storage = self.l1_storage.read().unwrap();
+            if let Some(entry) = storage.get(key) {
+                if entry.expires_at > now {
+                    return Some(CacheEntryInfo {
+                        tier: CacheTier::L1,
+                        size_bytes: entry.data.len(),
+                        compressed: false,
+                        checksum: None,
+                        stored_at_secs: unix_secs(entry.stored_at),
+                        ttl_remaining_secs: entry.expires_at.saturating_duration_since(now).as_secs(),
+                        offset: None,
+                        access_count: entry.access_count,
+                    });
+                }
+            }
+        }
+
+        let entry = self.l2.as_ref()?.entry(key).await.ok()??;
+        Some(CacheEntryInfo {
+            tier: CacheTier::L2,
+            size_bytes: entry.size_bytes,
+            compressed: false,
+            checksum: None,
+            stored_at_secs: entry.stored_at_secs,
+            ttl_remaining_secs: entry.ttl_remaining.as_secs(),
+            offset: entry.offset,
+            access_count: 0,
+        })
+    }
+
+    /// List cache keys starting with `prefix`, in key order
+    ///
+    /// Only L1 keys are listed: L2 file names are derived from keys lossily
+    /// and cannot be mapped back to them.
+    ///
+    /// # Arguments
+    /// * `prefix` - Only keys starting with this prefix are returned
+    /// * `after` - Return keys strictly after this one (from a previous page's `next`)
+    /// * `limit` - Maximum number of keys to return
+    pub fn list_keys(&self, prefix: &str, after: Option<&str>, limit: usize) -> CacheKeyPage {
+        let now = Instant::now();
+        let mut keys: Vec<String> = {
+            let storage = self.l1_storage.read().unwrap();
+            storage
+                .iter()
+                .filter(|(k, entry)| {
+                    k.starts_with(prefix)
+                        && entry.expires_at > now
+                        && after.is_none_or(|after| k.as_str() > after)
+                })
+                .map(|(k, _)| k.clone())
+                .collect()
+        };
+        keys.sort_unstable();
+
+        let next = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned()
+        } else {
+            None
+        };
+        CacheKeyPage { keys, next }
+    }
+
+    /// Iterate over all cache keys starting with `prefix`, in key order
+    ///
+    /// Keys are read `page_size` at a time (at least one); see
+    /// [`CacheKeys`]. Like [`list_keys`](Self::list_keys), only L1 keys are
+    /// listed.
+    pub fn keys(&self, prefix: &str, page_size: usize) -> CacheKeys<'_> {
+        CacheKeys {
+            cache: self,
+            prefix: prefix.to_string(),
+            page_size: page_size.max(1),
+            page: Vec::new().into_iter(),
+            next: Some(None),
+        }
+    }
+
+    /// Report what fills the cache: the `limit` entries of each tier
+    /// ranking highest `by` size, age or hits, with the tier's totals
+    ///
+    /// L1 entries are ranked in one pass under the L1 read lock, keeping
+    /// only the top `limit` in a bounded heap, so lookups wait for at most
+    /// that pass. L2 entries come from [`CacheBackend::visit_entries`],
+    /// which for [`FileBackend`] walks the directory without holding any
+    /// cache lock; they are named by file, not key. L2 keeps no hit
+    /// counts, so ranking by hits lists no L2 entries, only totals.
+    /// Like [`inspect`](Self::inspect), this does not count as access.
+    pub async fn top_entries(&self, by: TopBy, limit: usize) -> Result<TopEntries> {
+        let (now, wall_now) = (Instant::now(), SystemTime::now());
+        let mut l1 = TopCollector::new(by, limit);
+        {
+            let storage = self.l1_storage.read().unwrap();
+            for (key, entry) in storage.iter().filter(|(_, entry)| entry.expires_at > now) {
+                l1.push(TopEntry {
+                    key: key.clone(),
+                    size_bytes: entry.data.len() as u64,
+                    stored_at_secs: unix_secs(entry.stored_at),
+                    last_access_secs: Some(unix_secs(wall_now - now.saturating_duration_since(entry.last_accessed))),
+                    access_count: Some(entry.access_count),
+                });
+            }
+        }
+
+        let l2 = match &self.l2 {
+            Some(backend) => {
+                let l2_limit = if by == TopBy::Hits { 0 } else { limit };
+                let mut l2 = TopCollector::new(by, l2_limit);
+                let listed = backend
+                    .visit_entries(&mut |name, entry| {
+                        l2.push(TopEntry {
+                            key: name,
+                            size_bytes: entry.size_bytes as u64,
+                            stored_at_secs: entry.stored_at_secs,
+                            last_access_secs: None,
+                            access_count: None,
+                        })
+                    })
+                    .await?;
+                listed.then(|| l2.finish())
+            }
+            None => None,
+        };
+
+        Ok(TopEntries { by, l1: l1.finish(), l2 })
+    }
+
+    /// Remove a single entry by its cache key from both tiers
+    ///
+    /// Unlike [`purge`](Self::purge), the L2 file is removed before returning
+    /// so the result reports which tiers actually held the entry.
+    pub async fn remove_entry(&self, key: &str) -> Result<RemovedTiers> {
+        let queued = self.pending_writes.lock().unwrap().remove(key).is_some();
+        let l1 = {
+            let mut storage = self.l1_storage.write().unwrap();
+            if let Some(entry) = storage.remove(key) {
+                let mut size = self.l1_current_size.write().unwrap();
+                *size = size.saturating_sub(entry.data.len());
+                true
+            } else {
+                false
+            }
+        };
+
+        let l2 = match &self.l2 {
+            Some(backend) => {
+                // A write of the entry in flight lands before the removal
+                let _writing = self.l2_write_lock.lock().await;
+                backend.remove(key).await? || queued
+            }
+            None => false,
+        };
+
+        info!("Removed cache entry: {} (L1: {}, L2: {})", key, l1, l2);
+        Ok(RemovedTiers { l1, l2 })
+    }
+
     /// Batch lookup multiple slices
     pub async fn lookup_multiple(
         &self,
@@ -468,10 +1565,9 @@ impl TieredCache {
         };
         
         // Remove from L2 (async)
-        if self.l2_enabled {
-            if let Some(tx) = &self.disk_writer_tx {
-                let _ = tx.send(DiskWriteMessage::Delete { key: key.clone() });
-            }
+        self.cancel_pending([&key]);
+        if let Some(tx) = &self.disk_writer_tx {
+            let _ = tx.send(DiskWriteMessage::Delete { key: key.clone() });
         }
         
         info!("Purged cache entry: {} (L1: {})", key, removed_from_l1);
@@ -480,7 +1576,8 @@ impl TieredCache {
     
     /// Purge all cached slices for a specific URL
     ///
-    /// This removes all cache entries whose keys start with the given URL.
+    /// This removes the slices of every variant of the URL, as parsed by
+    /// [`SliceKey::parse`].
     ///
     /// # Arguments
     /// * `url` - The URL of the resource to purge
@@ -488,7 +1585,8 @@ impl TieredCache {
     /// # Returns
     /// The number of entries purged from L1
     pub async fn purge_url(&self, url: &str) -> Result<usize> {
-        let url_prefix = format!("{}:", url);
+        let target = SliceKey::from_object_key(url);
+        let matches = |key: &String| SliceKey::parse(key).is_ok_and(|key| key.same_url(&target));
         let mut purged_count = 0;
         
         // Collect keys to remove (to avoid holding lock during iteration)
@@ -496,11 +1594,25 @@ impl TieredCache {
             let storage = self.l1_storage.read().unwrap();
             storage
                 .keys()
-                .filter(|k| k.starts_with(&url_prefix))
+                .filter(|k| matches(k))
                 .cloned()
                 .collect()
         };
         
+        // Writes still queued for L2 may not be in L1
+        let queued: Vec<String> = {
+            let mut pending = self.pending_writes.lock().unwrap();
+            let keys: Vec<String> = pending
+                .keys()
+                .filter(|k| matches(k) && !keys_to_remove.contains(k))
+                .cloned()
+                .collect();
+            for key in &keys {
+                pending.remove(key);
+            }
+            keys
+        };
+        
         // Remove from L1
         {
             let mut storage = self.l1_storage.write().unwrap();
@@ -516,11 +1628,10 @@ impl TieredCache {
         }
         
         // Remove from L2 (async)
-        if self.l2_enabled {
-            if let Some(tx) = &self.disk_writer_tx {
-                for key in keys_to_remove {
-                    let _ = tx.send(DiskWriteMessage::Delete { key });
-                }
+        self.cancel_pending(&keys_to_remove);
+        if let Some(tx) = &self.disk_writer_tx {
+            for key in keys_to_remove.into_iter().chain(queued) {
+                let _ = tx.send(DiskWriteMessage::Delete { key });
             }
         }
         
@@ -528,34 +1639,48 @@ impl TieredCache {
         Ok(purged_count)
     }
     
+    /// Purge the slices of `url` covering `ranges` from both L1 and L2
+    ///
+    /// Unlike [`purge_url`](Self::purge_url), which can only find keys in
+    /// L1, this reaches slices that were evicted to L2, given their ranges
+    /// (e.g. from the file's metadata). L2 files are removed before returning.
+    ///
+    /// # Returns
+    /// The number of slices found in either tier
+    pub async fn purge_slices(&self, url: &str, ranges: &[ByteRange]) -> Result<usize> {
+        let mut purged_count = 0;
+        for range in ranges {
+            let key = self.generate_cache_key(url, range);
+            if self.remove_entry(&key).await?.any() {
+                purged_count += 1;
+            }
+        }
+        
+        info!("Purged {} of {} slices for URL: {}", purged_count, ranges.len(), url);
+        Ok(purged_count)
+    }
+    
     /// Purge all cached entries from both L1 and L2
     ///
+    /// L2 is purged asynchronously, after any writes queued before the call.
+    ///
     /// # Returns
     /// The number of entries purged from L1
     pub async fn purge_all(&self) -> Result<usize> {
-        // Collect all keys
-        let all_keys: Vec<String> = {
-            let storage = self.l1_storage.read().unwrap();
-            storage.keys().cloned().collect()
-        };
-        
-        let purged_count = all_keys.len();
-        
         // Clear L1
-        {
+        let purged_count = {
             let mut storage = self.l1_storage.write().unwrap();
+            let count = storage.len();
             storage.clear();
             let mut size = self.l1_current_size.write().unwrap();
             *size = 0;
-        }
+            count
+        };
         
         // Remove from L2 (async)
-        if self.l2_enabled {
-            if let Some(tx) = &self.disk_writer_tx {
-                for key in all_keys {
-                    let _ = tx.send(DiskWriteMessage::Delete { key });
-                }
-            }
+        self.pending_writes.lock().unwrap().clear();
+        if let Some(tx) = &self.disk_writer_tx {
+            let _ = tx.send(DiskWriteMessage::PurgeAll);
         }
         
         info!("Purged all cache entries: {} total", purged_count);
@@ -563,6 +1688,155 @@ impl TieredCache {
     }
 }
 
+/// Evict L1 entries in `policy` order until `current_size` is at most `target`
+///
+/// Each scan of the entries keeps the [`EVICTION_BATCH`] lowest-ranked in a
+/// bounded heap, so freeing space costs O(n log batch) per batch instead of
+/// sorting every entry.
+///
+/// # Returns
+/// The number of entries evicted
+fn evict_l1(
+    policy: L1EvictionPolicy,
+    storage: &mut HashMap<String, L1Entry>,
+    current_size: &mut usize,
+    target: usize,
+) -> usize {
+    let now = Instant::now();
+    let mut evicted = 0;
+    while *current_size > target && !storage.is_empty() {
+        let mut victims: BinaryHeap<(u128, &String)> = BinaryHeap::with_capacity(EVICTION_BATCH + 1);
+        for (key, entry) in storage.iter() {
+            victims.push((policy.rank(entry, now), key));
+            if victims.len() > EVICTION_BATCH {
+                victims.pop();
+            }
+        }
+        let batch: Vec<String> = victims
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(_, key)| key.clone())
+            .collect();
+        
+        for key in batch {
+            if *current_size <= target {
+                break;
+            }
+            if let Some(entry) = storage.remove(&key) {
+                *current_size = current_size.saturating_sub(entry.data.len());
+                evicted += 1;
+                debug!("Evicted L1 entry ({:?}): {}", policy, key);
+            }
+        }
+    }
+    evicted
+}
+
+/// Evict `host`'s L1 entries in `policy` order until they hold at most
+/// `target` bytes
+///
+/// Only called for hosts with a quota, so the scan of L1 it needs is only
+/// paid when quotas are configured.
+///
+/// # Returns
+/// The number of entries evicted
+fn evict_l1_host(
+    policy: L1EvictionPolicy,
+    storage: &mut HashMap<String, L1Entry>,
+    current_size: &mut usize,
+    host: &str,
+    target: usize,
+) -> usize {
+    let now = Instant::now();
+    let mut entries: Vec<(u128, String, usize)> = storage
+        .iter()
+        .filter(|(key, _)| origin_host(key) == host)
+        .map(|(key, entry)| (policy.rank(entry, now), key.clone(), entry.data.len()))
+        .collect();
+    let mut used: usize = entries.iter().map(|(_, _, size)| size).sum();
+    if used <= target {
+        return 0;
+    }
+    
+    entries.sort_unstable_by_key(|(rank, _, _)| *rank);
+    let mut evicted = 0;
+    for (_, key, size) in entries {
+        if used <= target {
+            break;
+        }
+        storage.remove(&key);
+        *current_size = current_size.saturating_sub(size);
+        used -= size;
+        evicted += 1;
+        debug!("Evicted L1 entry over its origin quota ({:?}): {}", policy, key);
+    }
+    evicted
+}
+
+/// Mark the current `cache_lookup` span as a hit in `tier`
+fn record_hit(tier: &str, bytes: usize) {
+    let span = tracing::Span::current();
+    span.record("cache_tier", tier);
+    span.record("bytes", bytes);
+}
+
+/// Remove expired entries from L1, returning how many were removed
+fn remove_expired_l1(
+    storage: &RwLock<HashMap<String, L1Entry>>,
+    current_size: &RwLock<usize>,
+    stats: &RwLock<TieredCacheStats>,
+    sweep: &SweepGuard,
+    stale_window: Duration,
+) -> usize {
+    if sweep.running.swap(true, Ordering::Acquire) {
+        sweep.coalesced.fetch_add(1, Ordering::Relaxed);
+        return 0;
+    }
+    #[cfg(test)]
+    {
+        let (active, peak) = &sweep.active;
+        let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+        peak.fetch_max(now, Ordering::SeqCst);
+    }
+    let expired = sweep_expired_l1(storage, current_size, stats, stale_window);
+    #[cfg(test)]
+    sweep.active.0.fetch_sub(1, Ordering::SeqCst);
+    sweep.running.store(false, Ordering::Release);
+    expired
+}
+
+fn sweep_expired_l1(
+    storage: &RwLock<HashMap<String, L1Entry>>,
+    current_size: &RwLock<usize>,
+    stats: &RwLock<TieredCacheStats>,
+    stale_window: Duration,
+) -> usize {
+    let now = Instant::now();
+    let mut storage = storage.write().unwrap();
+    let before = storage.len();
+    let mut freed = 0;
+    storage.retain(|_, entry| {
+        let live = entry.expires_at + stale_window > now;
+        if !live {
+            freed += entry.data.len();
+        }
+        live
+    });
+    
+    let expired = before - storage.len();
+    if expired > 0 {
+        let mut size = current_size.write().unwrap();
+        *size = size.saturating_sub(freed);
+        stats.write().unwrap().l1_evictions_ttl += expired as u64;
+    }
+    expired
+}
+
+/// Seconds since the Unix epoch
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
 impl Drop for TieredCache {
     fn drop(&mut self) {
         // Send shutdown signal to disk writer
@@ -642,6 +1916,218 @@ mod tests {
         assert_eq!(stats.l2_hits, 1);
     }
     
+    #[tokio::test]
+    async fn test_old_key_format_purged_on_open() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let ttl = Duration::from_secs(60);
+        let old_key = "http://example.com/file:0:999";
+        {
+            // A directory written before keys were versioned
+            let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+            backend.verify_layout().await.unwrap();
+            backend.load_index().await.unwrap();
+            backend.store(old_key, Bytes::from(vec![4u8; 1000]), ttl).await.unwrap();
+            backend.store("http://example.com/other:0:999", Bytes::from(vec![5u8; 1000]), ttl)
+                .await
+                .unwrap();
+        }
+
+        let cache = TieredCache::new(ttl, 1024 * 1024, temp_dir.path()).await.unwrap();
+        assert_eq!(cache.get_stats().l2_backend.unwrap().entries, Some(0));
+        let version = std::fs::read_to_string(temp_dir.path().join(".key-version")).unwrap();
+        assert_eq!(version, SliceKey::FORMAT_VERSION.to_string());
+
+        let backend = FileBackend::new(temp_dir.path()).await.unwrap();
+        assert_eq!(backend.lookup(old_key).await.unwrap(), None);
+        assert_eq!(backend.verify_key_version(SliceKey::FORMAT_VERSION).await.unwrap(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_l2_delta_encoding_recorded_on_open() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = |enabled| SliceConfig {
+            l2_cache_dir: temp_dir.path().to_string_lossy().into_owned(),
+            l2_delta: L2DeltaConfig { enabled, ..Default::default() },
+            ..Default::default()
+        };
+        let range = ByteRange::new(0, 131_071).unwrap();
+        let url = "http://example.com/backup.tar";
+        let v1: Vec<u8> = (0..131_072u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        let mut v2 = v1.clone();
+        v2[1000..1010].copy_from_slice(b"0123456789");
+        let format = || std::fs::read_to_string(temp_dir.path().join(".entry-format")).unwrap();
+
+        // Raw entries written before delta storage was enabled are purged
+        {
+            let cache = TieredCache::from_config(&config(false)).await.unwrap();
+            cache.store(url, &range, Bytes::from(v1.clone())).unwrap();
+            cache.flush().await.unwrap();
+        }
+        assert_eq!(format(), "raw");
+        {
+            let cache = TieredCache::from_config(&config(true)).await.unwrap();
+            assert_eq!(cache.lookup(url, &range).await.unwrap(), None);
+            cache.store(url, &range, Bytes::from(v1.clone())).unwrap();
+            cache.flush().await.unwrap();
+            cache.store(url, &range, Bytes::from(v2.clone())).unwrap();
+            cache.flush().await.unwrap();
+        }
+        assert_eq!(format(), "delta");
+
+        // Diffs are applied after a restart
+        {
+            let cache = TieredCache::from_config(&config(true)).await.unwrap();
+            assert_eq!(cache.lookup(url, &range).await.unwrap(), Some(Bytes::from(v2)));
+        }
+
+        // And purged once delta storage is turned off again
+        let cache = TieredCache::from_config(&config(false)).await.unwrap();
+        assert_eq!(cache.lookup(url, &range).await.unwrap(), None);
+        assert_eq!(format(), "raw");
+    }
+    
+    #[tokio::test]
+    async fn test_inspect_l2_entry() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let range = ByteRange::new(0, 999).unwrap();
+        let key = {
+            let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+                .await
+                .unwrap();
+            cache.store("http://example.com/file", &range, Bytes::from(vec![3u8; 1000])).unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cache.generate_cache_key("http://example.com/file", &range)
+        };
+
+        let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap();
+        let info = cache.inspect(&key).await.unwrap();
+        assert_eq!(info.tier, CacheTier::L2);
+        assert_eq!(info.size_bytes, 1000);
+        assert!(!info.compressed);
+
+        // Inspection must not promote or count as a hit
+        assert_eq!(cache.get_stats().l1_entries, 0);
+        assert_eq!(cache.get_stats().l2_hits, 0);
+        assert!(cache.inspect("missing").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_degrades_to_memory_only_on_disk_errors() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let l2_path = temp_dir.path().join("l2");
+        let cache = TieredCache::with_disk_health(
+            Duration::from_secs(60),
+            1024 * 1024,
+            &l2_path,
+            2,
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap();
+        
+        // Replace the L2 directory with a regular file so every disk write fails
+        std::fs::remove_dir_all(&l2_path).unwrap();
+        std::fs::write(&l2_path, b"not a directory").unwrap();
+        
+        let url = "http://example.com/file";
+        for i in 0..3u64 {
+            let range = ByteRange::new(i * 100, i * 100 + 99).unwrap();
+            cache.store(url, &range, Bytes::from(vec![i as u8; 100])).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        
+        assert!(cache.is_l2_degraded());
+        let stats = cache.get_stats();
+        assert!(stats.l2_degraded);
+        assert!(stats.disk_errors >= 2);
+        
+        // Still serves from L1, and misses fall through to origin instead of failing
+        let range = ByteRange::new(100, 199).unwrap();
+        assert_eq!(cache.lookup(url, &range).await.unwrap().unwrap()[0], 1);
+        let missing = ByteRange::new(1000, 1099).unwrap();
+        assert!(cache.lookup(url, &missing).await.unwrap().is_none());
+        
+        // Once the disk is usable again the probe re-enables L2
+        std::fs::remove_file(&l2_path).unwrap();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(!cache.is_l2_degraded());
+        
+        cache.store(url, &missing, Bytes::from(vec![7u8; 100])).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cache.inspect(&cache.generate_cache_key(url, &missing)).await.unwrap().tier, CacheTier::L1);
+        assert!(cache.get_stats().disk_writes >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_keys_paged() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024);
+        for i in 0..5u64 {
+            let range = ByteRange::new(i * 100, i * 100 + 99).unwrap();
+            cache.store("http://example.com/a", &range, Bytes::from(vec![0u8; 100])).unwrap();
+        }
+        let range = ByteRange::new(0, 99).unwrap();
+        cache.store("http://example.com/b", &range, Bytes::from(vec![0u8; 100])).unwrap();
+
+        let page = cache.list_keys("http://example.com/a:", None, 3);
+        assert_eq!(page.keys.len(), 3);
+        assert!(page.keys.windows(2).all(|w| w[0] < w[1]));
+        let next = page.next.unwrap();
+
+        let page = cache.list_keys("http://example.com/a:", Some(&next), 3);
+        assert_eq!(page.keys.len(), 2);
+        assert!(page.next.is_none());
+        assert_eq!(cache.list_keys("", None, 100).keys.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_keys_iterates_every_key_once() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024);
+        for url in ["http://example.com/a", "http://example.com/b", "http://example.org/c"] {
+            for i in 0..7u64 {
+                let range = ByteRange::new(i * 100, i * 100 + 99).unwrap();
+                cache.store(url, &range, Bytes::from(vec![0u8; 100])).unwrap();
+            }
+        }
+
+        let mut expected = cache.list_keys("", None, 100).keys;
+        expected.sort();
+        for page_size in [1, 3, 7, 21, 100] {
+            let keys: Vec<String> = cache.keys("", page_size).collect();
+            assert_eq!(keys, expected, "page_size {}", page_size);
+        }
+        assert_eq!(cache.keys("http://example.com/", 4).count(), 14);
+        assert_eq!(cache.keys("http://example.net/", 4).count(), 0);
+
+        // Keys removed mid-walk are skipped, the rest still come once
+        let mut keys = cache.keys("", 5);
+        let first: Vec<String> = keys.by_ref().take(5).collect();
+        cache.remove_entry(&expected[10]).await.unwrap();
+        let rest: Vec<String> = keys.collect();
+        assert_eq!(first.len() + rest.len(), 20);
+        assert!(!rest.contains(&expected[10]));
+    }
+
+    #[tokio::test]
+    async fn test_remove_entry_reports_tiers() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap();
+        let range = ByteRange::new(0, 999).unwrap();
+        cache.store("http://example.com/file", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let key = cache.generate_cache_key("http://example.com/file", &range);
+
+        let removed = cache.remove_entry(&key).await.unwrap();
+        assert_eq!(removed, RemovedTiers { l1: true, l2: true });
+        assert!(cache.inspect(&key).await.is_none());
+
+        let removed = cache.remove_entry(&key).await.unwrap();
+        assert!(!removed.any());
+    }
+
     #[tokio::test]
     async fn test_purge_single_entry() {
         let temp_dir = tempfile::TempDir::new().unwrap();
@@ -753,4 +2239,562 @@ mod tests {
         assert_eq!(stats.l1_entries, 0);
         assert_eq!(stats.l1_bytes, 0);
     }
+    
+    #[tokio::test]
+    async fn test_janitor_removes_expired_without_lookups() {
+        let cache = TieredCache::memory_only(Duration::from_millis(50), 1024 * 1024)
+            .with_l1_janitor(Duration::from_millis(10));
+        let range = ByteRange::new(0, 999).unwrap();
+        cache.store("http://example.com/file1", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        cache.store("http://example.com/file2", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        assert_eq!(cache.get_stats().l1_entries, 2);
+        
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_entries, 0);
+        assert_eq!(stats.l1_bytes, 0);
+        assert_eq!(stats.l1_evictions_ttl, 2);
+        assert_eq!(stats.misses, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_shrink_l1_to_evicts_lru() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024);
+        let range = ByteRange::new(0, 999).unwrap();
+        for i in 0..4 {
+            let url = format!("http://example.com/file{}", i);
+            cache.store(&url, &range, Bytes::from(vec![1u8; 1000])).unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        // file0 becomes the most recently used
+        cache.lookup("http://example.com/file0", &range).await.unwrap();
+        
+        assert_eq!(cache.shrink_l1_to(5000), 0);
+        assert_eq!(cache.shrink_l1_to(2500), 2);
+        
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_entries, 2);
+        assert_eq!(stats.l1_bytes, 2000);
+        assert_eq!(stats.l1_evictions_shrink, 2);
+        assert_eq!(stats.l1_evictions_size, 0);
+        assert!(cache.lookup("http://example.com/file0", &range).await.unwrap().is_some());
+        assert!(cache.lookup("http://example.com/file3", &range).await.unwrap().is_some());
+        assert!(cache.lookup("http://example.com/file1", &range).await.unwrap().is_none());
+        
+        assert_eq!(cache.shrink_l1_to(0), 2);
+        assert_eq!(cache.get_stats().l1_bytes, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_size_evictions_counted() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 2000);
+        let range = ByteRange::new(0, 999).unwrap();
+        for i in 0..3 {
+            let url = format!("http://example.com/file{}", i);
+            cache.store(&url, &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        }
+        
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_entries, 2);
+        assert_eq!(stats.l1_evictions_size, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_size_below_admission() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024)
+            .with_l1_admission(L1AdmissionPolicy::SizeBelow(1000));
+        let small = ByteRange::new(0, 998).unwrap();
+        let large = ByteRange::new(0, 999).unwrap();
+        cache.store("http://example.com/small", &small, Bytes::from(vec![1u8; 999])).unwrap();
+        cache.store("http://example.com/large", &large, Bytes::from(vec![1u8; 1000])).unwrap();
+        
+        assert!(cache.lookup("http://example.com/small", &small).await.unwrap().is_some());
+        assert!(cache.lookup("http://example.com/large", &large).await.unwrap().is_none());
+        assert_eq!(cache.get_stats().l1_admission_rejected, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_frequency_admission() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap()
+            .with_l1_admission(L1AdmissionPolicy::Frequency);
+        let range = ByteRange::new(0, 999).unwrap();
+        let url = "http://example.com/file";
+        
+        // First access goes to L2 only
+        cache.store(url, &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_entries, 0);
+        assert_eq!(stats.l1_admission_rejected, 1);
+        
+        // Second access (promotion from L2) is admitted
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(cache.lookup(url, &range).await.unwrap().is_some());
+        assert!(cache.lookup(url, &range).await.unwrap().is_some());
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_entries, 1);
+        assert_eq!(stats.l2_hits, 1);
+        assert_eq!(stats.l1_hits, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_lookup_reads_queued_write() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap()
+            .with_l1_admission(L1AdmissionPolicy::SizeBelow(1));
+        let range = ByteRange::new(0, 999).unwrap();
+        let data = Bytes::from(vec![4u8; 1000]);
+        
+        // Not in L1 and not on disk yet, but readable right away
+        cache.store("http://example.com/file", &range, data.clone()).unwrap();
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_entries, 0);
+        assert_eq!(stats.pending_writes, 1);
+        assert_eq!(stats.pending_write_bytes, 1000);
+        assert_eq!(cache.lookup("http://example.com/file", &range).await.unwrap(), Some(data.clone()));
+        
+        cache.flush().await.unwrap();
+        let stats = cache.get_stats();
+        assert_eq!(stats.pending_writes, 0);
+        assert_eq!(stats.oldest_pending_write_ms, 0);
+        assert_eq!(stats.disk_writes, 1);
+        assert_eq!(cache.lookup("http://example.com/file", &range).await.unwrap(), Some(data));
+        assert_eq!(cache.get_stats().l2_hits, 2);
+        
+        // A write purged while queued is never served or written
+        cache.store("http://example.com/gone", &range, Bytes::from(vec![5u8; 1000])).unwrap();
+        cache.purge_url("http://example.com/gone").await.unwrap();
+        assert_eq!(cache.lookup("http://example.com/gone", &range).await.unwrap(), None);
+        cache.flush().await.unwrap();
+        assert_eq!(cache.lookup("http://example.com/gone", &range).await.unwrap(), None);
+        assert_eq!(cache.get_stats().disk_writes, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_idle_write_synced_within_interval() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = Arc::new(
+            FileBackend::new(temp_dir.path())
+                .await
+                .unwrap()
+                .with_fsync_policy(crate::FsyncPolicy::Interval(Duration::from_millis(50))),
+        );
+        let cache = TieredCache::with_backend(Duration::from_secs(60), 1024 * 1024, backend.clone());
+        let range = ByteRange::new(0, 999).unwrap();
+        
+        // No further write comes along to trigger the sync
+        cache.store("http://example.com/a", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(backend.fsync_count(), 1);
+        
+        // Flushing writes and syncs without waiting for the interval
+        cache.store("http://example.com/b", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        cache.flush().await.unwrap();
+        assert_eq!(backend.fsync_count(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_per_entry_ttl_survives_promotion() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let range = ByteRange::new(0, 999).unwrap();
+        let data = Bytes::from(vec![6u8; 1000]);
+        {
+            let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+                .await
+                .unwrap();
+            cache
+                .store_with_ttl("http://example.com/manifest", &range, data.clone(), Duration::from_secs(2))
+                .unwrap();
+            cache.store("http://example.com/image", &range, data.clone()).unwrap();
+            cache.flush().await.unwrap();
+        }
+        
+        // After a restart both come from L2 into L1, each with its own TTL
+        let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap();
+        assert!(cache.lookup("http://example.com/manifest", &range).await.unwrap().is_some());
+        assert!(cache.lookup("http://example.com/image", &range).await.unwrap().is_some());
+        assert_eq!(cache.get_stats().l2_hits, 2);
+        
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        assert_eq!(cache.lookup("http://example.com/manifest", &range).await.unwrap(), None);
+        assert_eq!(cache.lookup("http://example.com/image", &range).await.unwrap(), Some(data));
+    }
+    
+    #[tokio::test]
+    async fn test_promoted_entry_keeps_its_age() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let range = ByteRange::new(0, 999).unwrap();
+        {
+            let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+                .await
+                .unwrap();
+            cache.store("http://example.com/a", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+            cache.flush().await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        
+        // Age is measured from the original store, not the promotion
+        let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap();
+        let (_, age) = cache.lookup_with_age("http://example.com/a", &range).await.unwrap().unwrap();
+        assert!(age >= Duration::from_secs(1));
+        let (_, age) = cache.lookup_with_age("http://example.com/a", &range).await.unwrap().unwrap();
+        assert!(age >= Duration::from_secs(1));
+        assert_eq!((cache.get_stats().l2_hits, cache.get_stats().l1_hits), (1, 1));
+    }
+    
+    #[tokio::test]
+    async fn test_expired_l1_entry_kept_until_swept() {
+        let cache = TieredCache::memory_only(Duration::from_millis(100), 1024 * 1024);
+        let range = ByteRange::new(0, 999).unwrap();
+        cache.store("http://example.com/a", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        assert!(!cache.is_expired("http://example.com/a", &range));
+        
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(cache.lookup("http://example.com/a", &range).await.unwrap(), None);
+        assert!(cache.is_expired("http://example.com/a", &range));
+        
+        assert_eq!(cache.evict_expired_l1(), 1);
+        assert!(!cache.is_expired("http://example.com/a", &range));
+    }
+    
+    #[tokio::test]
+    async fn test_stale_entry_served_within_window() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024)
+            .with_stale_window(Duration::from_millis(200));
+        let range = ByteRange::new(0, 999).unwrap();
+        cache
+            .store_with_ttl("http://example.com/a", &range, Bytes::from(vec![1u8; 1000]), Duration::from_millis(50))
+            .unwrap();
+        
+        // Fresh entries are not stale
+        let window = Duration::from_millis(200);
+        assert!(cache.lookup_stale("http://example.com/a", &range, window).is_none());
+        
+        // Expired within the window: kept by the sweep and served stale
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(cache.evict_expired_l1(), 0);
+        let (data, _) = cache.lookup_stale("http://example.com/a", &range, window).unwrap();
+        assert_eq!(data.len(), 1000);
+        assert!(cache.lookup_stale("http://example.com/a", &range, Duration::from_millis(10)).is_none());
+        
+        // Past the window
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(cache.lookup_stale("http://example.com/a", &range, window).is_none());
+        assert_eq!(cache.evict_expired_l1(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_expiry_sweep_coalesced_while_running() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024);
+        let range = ByteRange::new(0, 9).unwrap();
+        cache.store_with_ttl("http://example.com/a", &range, Bytes::from(vec![1u8; 10]), Duration::ZERO).unwrap();
+        
+        // A sweep is already running: the call is folded into it
+        cache.l1_sweep.running.store(true, Ordering::SeqCst);
+        assert!(cache.get_stats().l1_sweep_running);
+        assert_eq!(cache.evict_expired_l1(), 0);
+        assert_eq!(cache.get_stats().l1_sweeps_coalesced, 1);
+        
+        cache.l1_sweep.running.store(false, Ordering::SeqCst);
+        assert_eq!(cache.evict_expired_l1(), 1);
+        assert!(!cache.get_stats().l1_sweep_running);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_expiry_sweeps_never_overlap() {
+        let cache = Arc::new(
+            TieredCache::memory_only(Duration::from_secs(60), 64 * 1024 * 1024)
+                .with_l1_janitor(Duration::from_millis(1)),
+        );
+        let range = ByteRange::new(0, 9).unwrap();
+        for i in 0..20_000 {
+            let url = format!("http://example.com/{}", i);
+            cache.store_with_ttl(&url, &range, Bytes::from_static(b"0123456789"), Duration::ZERO).unwrap();
+        }
+        
+        // Admin calls and server sweeps racing the janitor
+        let tasks: Vec<_> = (0..16)
+            .map(|_| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    for _ in 0..20 {
+                        cache.evict_expired_l1();
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        cache.evict_expired_l1();
+        
+        let (active, peak) = &cache.l1_sweep.active;
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert_eq!(active.load(Ordering::SeqCst), 0);
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_entries, 0);
+        assert_eq!(stats.l1_bytes, 0);
+        assert_eq!(stats.l1_evictions_ttl, 20_000);
+    }
+    
+    #[tokio::test]
+    async fn test_origin_quota_evicts_within_host() {
+        let quotas = OriginQuotaConfig {
+            default_bytes: None,
+            hosts: HashMap::from([("busy.example.com".to_string(), 2000)]),
+        };
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024).with_origin_quotas(quotas);
+        let data = Bytes::from(vec![1u8; 1000]);
+        cache.store("http://quiet.example.com/a", &ByteRange::new(0, 999).unwrap(), data.clone()).unwrap();
+        for i in 0..3u64 {
+            let range = ByteRange::new(i * 1000, i * 1000 + 999).unwrap();
+            cache.store("http://busy.example.com/a", &range, data.clone()).unwrap();
+        }
+        
+        // The busy host stays within its quota; the other host is untouched
+        let stats = cache.get_stats();
+        assert_eq!((stats.l1_entries, stats.l1_bytes), (3, 3000));
+        assert!(cache.lookup("http://busy.example.com/a", &ByteRange::new(0, 999).unwrap()).await.unwrap().is_none());
+        assert!(cache.lookup("http://quiet.example.com/a", &ByteRange::new(0, 999).unwrap()).await.unwrap().is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_restart_loads_index_and_removes_orphans() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let url = "http://example.com/video.mp4";
+        {
+            let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+                .await
+                .unwrap();
+            for i in 0..3u64 {
+                let range = ByteRange::new(i * 1000, i * 1000 + 999).unwrap();
+                cache.store(url, &range, Bytes::from(vec![i as u8; 1000])).unwrap();
+            }
+            cache.flush().await.unwrap();
+        }
+        
+        // A file no write recorded, left long enough to count as an orphan
+        let orphan = temp_dir.path().join("00").join("00").join("bogus");
+        std::fs::create_dir_all(orphan.parent().unwrap()).unwrap();
+        std::fs::write(&orphan, vec![0u8; 108]).unwrap();
+        let file = std::fs::File::options().write(true).open(&orphan).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(3600)).unwrap();
+        
+        let cache = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap();
+        let backend = cache.get_stats().l2_backend.unwrap();
+        assert_eq!((backend.entries, backend.bytes), (Some(3), Some(3000)));
+        let index = backend.index.unwrap();
+        assert_eq!((index.recovered_entries, index.recovered_bytes), (3, 3000));
+        assert_eq!((index.orphans_removed, index.orphan_bytes), (1, 108));
+        assert!(!orphan.exists());
+        
+        // Every slice is served from L2 without going back to the origin
+        for i in 0..3u64 {
+            let range = ByteRange::new(i * 1000, i * 1000 + 999).unwrap();
+            assert_eq!(cache.lookup(url, &range).await.unwrap().unwrap()[0], i as u8);
+        }
+        let stats = cache.get_stats();
+        assert_eq!((stats.l2_hits, stats.misses), (3, 0));
+    }
+    
+    #[test]
+    fn test_admission_policy_config() {
+        let policy: L1AdmissionPolicy = serde_yaml::from_str("!size_below 1048576").unwrap();
+        assert_eq!(policy, L1AdmissionPolicy::SizeBelow(1048576));
+        let policy: L1AdmissionPolicy = serde_yaml::from_str("frequency").unwrap();
+        assert_eq!(policy, L1AdmissionPolicy::Frequency);
+    }
+    
+    #[test]
+    fn test_eviction_policy_config() {
+        let policy: L1EvictionPolicy = serde_yaml::from_str("ttl_first").unwrap();
+        assert_eq!(policy, L1EvictionPolicy::TtlFirst);
+        let policy: L1EvictionPolicy = serde_yaml::from_str("size_weighted").unwrap();
+        assert_eq!(policy, L1EvictionPolicy::SizeWeighted);
+    }
+    
+    /// Which of `urls` are still in L1
+    async fn cached(cache: &TieredCache, urls: &[&str]) -> Vec<bool> {
+        let range = ByteRange::new(0, 999).unwrap();
+        let mut found = Vec::new();
+        for url in urls {
+            found.push(cache.inspect(&cache.generate_cache_key(url, &range)).await.is_some());
+        }
+        found
+    }
+    
+    #[tokio::test]
+    async fn test_lfu_eviction() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 3000)
+            .with_l1_eviction(L1EvictionPolicy::Lfu);
+        let range = ByteRange::new(0, 999).unwrap();
+        let urls = ["http://example.com/a", "http://example.com/b", "http://example.com/c"];
+        for url in urls {
+            cache.store(url, &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        }
+        // b is the most recently used but the least often
+        for (url, lookups) in [(urls[0], 3), (urls[2], 2), (urls[1], 1)] {
+            for _ in 0..lookups {
+                cache.lookup(url, &range).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+        }
+        
+        cache.store("http://example.com/d", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        assert_eq!(cached(&cache, &urls).await, vec![true, false, true]);
+        
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_evictions_size, 1);
+        assert_eq!(stats.l1_eviction_policy, L1EvictionPolicy::Lfu);
+    }
+    
+    #[tokio::test]
+    async fn test_ttl_first_eviction() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 3000)
+            .with_l1_eviction(L1EvictionPolicy::TtlFirst);
+        let range = ByteRange::new(0, 999).unwrap();
+        let urls = ["http://example.com/a", "http://example.com/b", "http://example.com/c"];
+        for (url, ttl) in urls.iter().zip([600, 30, 120]) {
+            cache
+                .store_with_ttl(url, &range, Bytes::from(vec![1u8; 1000]), Duration::from_secs(ttl))
+                .unwrap();
+        }
+        cache.lookup(urls[1], &range).await.unwrap();
+        
+        cache.store("http://example.com/d", &range, Bytes::from(vec![1u8; 1000])).unwrap();
+        assert_eq!(cached(&cache, &urls).await, vec![true, false, true]);
+        
+        // d got the default 60s TTL, so it goes before c
+        assert_eq!(cache.shrink_l1_to(2000), 1);
+        assert_eq!(cached(&cache, &urls).await, vec![true, false, true]);
+        assert_eq!(cached(&cache, &["http://example.com/d"]).await, vec![false]);
+    }
+    
+    #[tokio::test]
+    async fn test_size_weighted_eviction() {
+        let cache = TieredCache::memory_only(Duration::from_secs(60), 3000)
+            .with_l1_eviction(L1EvictionPolicy::SizeWeighted);
+        let small = ByteRange::new(0, 499).unwrap();
+        let large = ByteRange::new(0, 1999).unwrap();
+        cache.store("http://example.com/a", &small, Bytes::from(vec![1u8; 500])).unwrap();
+        cache.store("http://example.com/b", &small, Bytes::from(vec![1u8; 500])).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cache.store("http://example.com/large", &large, Bytes::from(vec![1u8; 2000])).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        
+        // LRU would drop both small entries; the large one frees enough alone
+        cache.store("http://example.com/c", &small, Bytes::from(vec![1u8; 1000])).unwrap();
+        let stats = cache.get_stats();
+        assert_eq!(stats.l1_evictions_size, 1);
+        assert_eq!(stats.l1_entries, 3);
+        assert!(cache.lookup("http://example.com/large", &large).await.unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_eviction_scans_in_batches() {
+        let now = Instant::now();
+        let mut storage: HashMap<String, L1Entry> = (0..100u64)
+            .map(|i| {
+                let entry = L1Entry {
+                    data: Bytes::from(vec![0u8; 10]),
+                    stored_at: SystemTime::now(),
+                    expires_at: now,
+                    last_accessed: now - Duration::from_secs(100 - i),
+                    access_count: 0,
+                };
+                (format!("key{}", i), entry)
+            })
+            .collect();
+        let mut size = 1000;
+        
+        // More victims than one batch, oldest first
+        assert_eq!(evict_l1(L1EvictionPolicy::Lru, &mut storage, &mut size, 600), 40);
+        assert_eq!(size, 600);
+        assert!((0..40).all(|i| !storage.contains_key(&format!("key{}", i))));
+        assert!((40..100).all(|i| storage.contains_key(&format!("key{}", i))));
+    }
+    
+    /// Keys of the top entries of `tier`
+    fn top_keys(tier: &TopTier) -> Vec<&str> {
+        tier.entries.iter().map(|entry| entry.key.as_str()).collect()
+    }
+    
+    #[tokio::test]
+    async fn test_top_entries_ordering() {
+        let cache = TieredCache::memory_only(Duration::from_secs(3600), 1024 * 1024);
+        let url = "http://example.com/file";
+        // Size, age in seconds and lookups of each entry
+        let entries = [(100, 300, 1), (300, 100, 0), (200, 200, 3)];
+        let mut keys = Vec::new();
+        for (i, (size, _, hits)) in entries.iter().enumerate() {
+            let range = ByteRange::new(i as u64 * 1000, i as u64 * 1000 + 999).unwrap();
+            cache.store(url, &range, Bytes::from(vec![0u8; *size])).unwrap();
+            for _ in 0..*hits {
+                cache.lookup(url, &range).await.unwrap();
+            }
+            keys.push(cache.generate_cache_key(url, &range));
+        }
+        {
+            let mut storage = cache.l1_storage.write().unwrap();
+            for (key, (_, age, _)) in keys.iter().zip(entries) {
+                storage.get_mut(key).unwrap().stored_at -= Duration::from_secs(age);
+            }
+        }
+        let (a, b, c) = (keys[0].as_str(), keys[1].as_str(), keys[2].as_str());
+        
+        let top = cache.top_entries(TopBy::Size, 10).await.unwrap();
+        assert_eq!(top_keys(&top.l1), [b, c, a]);
+        assert_eq!((top.l1.total_entries, top.l1.total_bytes), (3, 600));
+        assert_eq!(top.l2, None);
+        
+        let top = cache.top_entries(TopBy::Age, 10).await.unwrap();
+        assert_eq!(top_keys(&top.l1), [a, c, b]);
+        
+        let top = cache.top_entries(TopBy::Hits, 2).await.unwrap();
+        assert_eq!(top_keys(&top.l1), [c, a]);
+        assert_eq!(top.l1.entries[0].access_count, Some(3));
+        assert_eq!(top.l1.total_entries, 3);
+        
+        // Reporting is not an access
+        let top = cache.top_entries(TopBy::Hits, 2).await.unwrap();
+        assert_eq!(top.l1.entries[0].access_count, Some(3));
+    }
+    
+    #[tokio::test]
+    async fn test_top_entries_lists_l2_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = TieredCache::new(Duration::from_secs(3600), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap();
+        let url = "http://example.com/file";
+        for (i, size) in [100usize, 300, 200].into_iter().enumerate() {
+            let range = ByteRange::new(i as u64 * 1000, i as u64 * 1000 + 999).unwrap();
+            cache.store(url, &range, Bytes::from(vec![0u8; size])).unwrap();
+        }
+        cache.flush().await.unwrap();
+        
+        let top = cache.top_entries(TopBy::Size, 2).await.unwrap();
+        let l2 = top.l2.unwrap();
+        assert_eq!((l2.total_entries, l2.total_bytes), (3, 600));
+        let sizes: Vec<u64> = l2.entries.iter().map(|entry| entry.size_bytes).collect();
+        assert_eq!(sizes, [300, 200]);
+        assert!(l2.entries.iter().all(|entry| entry.access_count.is_none()));
+        
+        // No hit counts in L2: totals only
+        let l2 = cache.top_entries(TopBy::Hits, 2).await.unwrap().l2.unwrap();
+        assert_eq!(l2.total_entries, 3);
+        assert!(l2.entries.is_empty());
+    }
 }
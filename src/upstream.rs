@@ -0,0 +1,292 @@
+//! Pool of origin servers with health-aware selection
+//!
+//! With `upstream_pool` configured, pass-through requests and slice
+//! subrequests are spread over several origins instead of going to the
+//! single `upstream_address`. A peer that fails `max_failures` times in a
+//! row is taken out of rotation for `cooldown_secs`; after that it gets
+//! traffic again and a single success brings it fully back.
+
+use crate::config::{SliceConfig, UpstreamPolicy};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// One origin of the pool and its health state
+#[derive(Debug)]
+struct Peer {
+    address: String,
+    active: AtomicUsize,
+    consecutive_failures: AtomicU32,
+    down_until: Mutex<Option<Instant>>,
+}
+
+impl Peer {
+    fn is_available(&self, now: Instant) -> bool {
+        self.down_until.lock().unwrap().is_none_or(|until| now >= until)
+    }
+}
+
+/// Origins requests are spread over
+#[derive(Debug)]
+pub struct UpstreamPool {
+    peers: Vec<Peer>,
+    policy: UpstreamPolicy,
+    max_failures: u32,
+    cooldown: Duration,
+    next: AtomicUsize,
+}
+
+/// A peer picked from the pool, counted as in flight until dropped
+#[derive(Debug)]
+pub struct UpstreamLease<'a> {
+    pool: &'a UpstreamPool,
+    index: usize,
+}
+
+impl UpstreamLease<'_> {
+    /// Address of the picked peer
+    pub fn address(&self) -> &str {
+        &self.pool.peers[self.index].address
+    }
+
+    /// Position of the picked peer in the pool
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Drop for UpstreamLease<'_> {
+    fn drop(&mut self) {
+        self.pool.peers[self.index].active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl UpstreamPool {
+    /// Create a pool over `peers`
+    ///
+    /// # Arguments
+    /// * `peers` - Origin addresses (`host:port`)
+    /// * `policy` - How the next peer is picked
+    /// * `max_failures` - Consecutive failures before a peer is taken out of rotation
+    /// * `cooldown` - How long a failed peer stays out of rotation
+    pub fn new(peers: &[String], policy: UpstreamPolicy, max_failures: u32, cooldown: Duration) -> Self {
+        UpstreamPool {
+            peers: peers
+                .iter()
+                .map(|address| Peer {
+                    address: address.clone(),
+                    active: AtomicUsize::new(0),
+                    consecutive_failures: AtomicU32::new(0),
+                    down_until: Mutex::new(None),
+                })
+                .collect(),
+            policy,
+            max_failures,
+            cooldown,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Build the pool from `upstream_pool`, or `None` if it is not set
+    pub fn from_config(config: &SliceConfig) -> Option<Self> {
+        let pool = config.upstream_pool.as_ref()?;
+        Some(Self::new(
+            &pool.peers,
+            pool.policy,
+            pool.max_failures,
+            Duration::from_secs(pool.cooldown_secs),
+        ))
+    }
+
+    /// Number of peers in the pool
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Whether the pool has no peers
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Pick a peer according to the policy
+    pub fn select(&self) -> Option<UpstreamLease<'_>> {
+        self.select_excluding(&[])
+    }
+
+    /// Pick a peer, avoiding the ones at `excluded` (e.g. peers a retry
+    /// already failed on)
+    ///
+    /// Peers out of rotation are skipped while any other peer is left. When
+    /// every candidate is down or excluded the pool still returns one, since
+    /// trying a failed origin beats failing the request outright.
+    pub fn select_excluding(&self, excluded: &[usize]) -> Option<UpstreamLease<'_>> {
+        if self.peers.is_empty() {
+            return None;
+        }
+
+        let now = Instant::now();
+        let all: Vec<usize> = (0..self.peers.len()).collect();
+        let not_excluded: Vec<usize> = all.iter().copied().filter(|i| !excluded.contains(i)).collect();
+        let available: Vec<usize> = not_excluded
+            .iter()
+            .copied()
+            .filter(|&i| self.peers[i].is_available(now))
+            .collect();
+        let candidates = if !available.is_empty() {
+            available
+        } else if !not_excluded.is_empty() {
+            not_excluded
+        } else {
+            all
+        };
+
+        let index = match self.policy {
+            UpstreamPolicy::RoundRobin => {
+                candidates[self.next.fetch_add(1, Ordering::Relaxed) % candidates.len()]
+            }
+            UpstreamPolicy::LeastConnections => {
+                // Start the scan at a rotating offset so ties are spread out
+                let offset = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..candidates.len())
+                    .map(|i| candidates[(offset + i) % candidates.len()])
+                    .min_by_key(|&i| self.peers[i].active.load(Ordering::Relaxed))
+                    .unwrap()
+            }
+            UpstreamPolicy::Failover => candidates[0],
+        };
+
+        self.peers[index].active.fetch_add(1, Ordering::Relaxed);
+        Some(UpstreamLease { pool: self, index })
+    }
+
+    /// Record a successful request to the peer at `index`
+    pub fn record_success(&self, index: usize) {
+        let peer = &self.peers[index];
+        peer.consecutive_failures.store(0, Ordering::Relaxed);
+        *peer.down_until.lock().unwrap() = None;
+    }
+
+    /// Record a failed request to the peer at `index`
+    ///
+    /// Takes the peer out of rotation for the cooldown once it has failed
+    /// `max_failures` times in a row. A peer that fails again after its
+    /// cooldown is taken out straight away.
+    pub fn record_failure(&self, index: usize) {
+        let peer = &self.peers[index];
+        let failures = peer.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.max_failures {
+            let until = Instant::now() + self.cooldown;
+            *peer.down_until.lock().unwrap() = Some(until);
+            tracing::warn!(
+                "Upstream {} failed {} times in a row, out of rotation for {:?}",
+                peer.address,
+                failures,
+                self.cooldown
+            );
+        }
+    }
+
+    /// Address of the peer at `index`
+    pub fn address(&self, index: usize) -> &str {
+        &self.peers[index].address
+    }
+
+    /// Whether the peer at `index` is currently in rotation
+    pub fn is_available(&self, index: usize) -> bool {
+        self.peers[index].is_available(Instant::now())
+    }
+}
+
+/// Replace the authority (`host[:port]`) of `url` with `authority`
+///
+/// URLs without a scheme are returned unchanged.
+pub fn rewrite_authority(url: &str, authority: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let path_start = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    format!("{}://{}{}", scheme, authority, &rest[path_start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(policy: UpstreamPolicy) -> UpstreamPool {
+        let peers = vec!["a:80".to_string(), "b:80".to_string(), "c:80".to_string()];
+        UpstreamPool::new(&peers, policy, 2, Duration::from_secs(60))
+    }
+
+    #[test]
+    fn test_round_robin_rotates() {
+        let pool = pool(UpstreamPolicy::RoundRobin);
+        let picked: Vec<String> = (0..6)
+            .map(|_| pool.select().unwrap().address().to_string())
+            .collect();
+        assert_eq!(picked, ["a:80", "b:80", "c:80", "a:80", "b:80", "c:80"]);
+    }
+
+    #[test]
+    fn test_least_connections_prefers_idle_peer() {
+        let pool = pool(UpstreamPolicy::LeastConnections);
+        let first = pool.select().unwrap();
+        let second = pool.select().unwrap();
+        let third = pool.select().unwrap();
+        assert_ne!(first.index(), second.index());
+        assert_ne!(second.index(), third.index());
+        assert_ne!(first.index(), third.index());
+
+        let freed = second.index();
+        drop(second);
+        assert_eq!(pool.select().unwrap().index(), freed);
+    }
+
+    #[test]
+    fn test_failover_uses_primary_until_it_fails() {
+        let pool = pool(UpstreamPolicy::Failover);
+        assert_eq!(pool.select().unwrap().address(), "a:80");
+
+        pool.record_failure(0);
+        assert_eq!(pool.select().unwrap().address(), "a:80");
+        pool.record_failure(0);
+        assert!(!pool.is_available(0));
+        assert_eq!(pool.select().unwrap().address(), "b:80");
+
+        pool.record_success(0);
+        assert_eq!(pool.select().unwrap().address(), "a:80");
+    }
+
+    #[test]
+    fn test_failed_peer_returns_after_cooldown() {
+        let peers = vec!["a:80".to_string(), "b:80".to_string()];
+        let pool = UpstreamPool::new(&peers, UpstreamPolicy::Failover, 1, Duration::ZERO);
+        pool.record_failure(0);
+        assert!(pool.is_available(0));
+        assert_eq!(pool.select().unwrap().address(), "a:80");
+    }
+
+    #[test]
+    fn test_select_excluding() {
+        let pool = pool(UpstreamPolicy::Failover);
+        assert_eq!(pool.select_excluding(&[0]).unwrap().address(), "b:80");
+
+        // Every peer excluded or down still yields a peer
+        pool.record_failure(2);
+        pool.record_failure(2);
+        assert_eq!(pool.select_excluding(&[0, 1]).unwrap().address(), "c:80");
+        assert!(pool.select_excluding(&[0, 1, 2]).is_some());
+        assert!(UpstreamPool::new(&[], UpstreamPolicy::RoundRobin, 1, Duration::ZERO)
+            .select()
+            .is_none());
+    }
+
+    #[test]
+    fn test_rewrite_authority() {
+        assert_eq!(
+            rewrite_authority("http://origin.example.com/a/b?x=1", "10.0.0.1:8080"),
+            "http://10.0.0.1:8080/a/b?x=1"
+        );
+        assert_eq!(rewrite_authority("https://h:443", "b:80"), "https://b:80");
+        assert_eq!(rewrite_authority("/relative", "b:80"), "/relative");
+    }
+}
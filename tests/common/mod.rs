@@ -0,0 +1,144 @@
+//! Fixtures shared by the integration tests
+//!
+//! Each test binary includes this module with `mod common;` and uses only
+//! part of it.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+type FileFn = Arc<dyn Fn(&Request) -> Vec<u8> + Send + Sync>;
+
+/// Mock origin serving one file to `HEAD` and ranged `GET` requests
+///
+/// `HEAD` answers with the file's `Content-Length` and `Accept-Ranges:
+/// bytes`; `GET` with `Range: bytes=a-b` answers `206` with that part of
+/// the file, the end clamped to its size.
+pub struct RangeOrigin {
+    file: FileFn,
+    headers: Vec<(String, String)>,
+    required: Option<(String, String)>,
+    delay: Arc<dyn Fn(usize) -> Duration + Send + Sync>,
+    on_get: Arc<dyn Fn(&Request) + Send + Sync>,
+}
+
+impl RangeOrigin {
+    /// A file of `size` bytes of `x`
+    pub fn new(size: usize) -> Self {
+        Self::from_fn(size, |_| b'x')
+    }
+
+    /// A file of `size` bytes, `byte(i)` at offset `i`
+    pub fn from_fn(size: usize, byte: impl Fn(usize) -> u8 + Send + Sync + 'static) -> Self {
+        Self::with_file(move |_| (0..size).map(&byte).collect())
+    }
+
+    /// A file picked per request, e.g. by its headers
+    pub fn with_file(file: impl Fn(&Request) -> Vec<u8> + Send + Sync + 'static) -> Self {
+        RangeOrigin {
+            file: Arc::new(file),
+            headers: Vec::new(),
+            required: None,
+            delay: Arc::new(|_| Duration::ZERO),
+            on_get: Arc::new(|_| {}),
+        }
+    }
+
+    /// Send `name: value` with every response
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Only answer requests carrying `name: value`; others get a 404
+    pub fn require_header(mut self, name: &str, value: &str) -> Self {
+        self.required = Some((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Hold each `GET` response back by `delay(range start)`
+    pub fn delay(mut self, delay: impl Fn(usize) -> Duration + Send + Sync + 'static) -> Self {
+        self.delay = Arc::new(delay);
+        self
+    }
+
+    /// Call `f` with every `GET` request as it arrives
+    pub fn on_get(mut self, f: impl Fn(&Request) + Send + Sync + 'static) -> Self {
+        self.on_get = Arc::new(f);
+        self
+    }
+
+    /// Start the origin
+    pub async fn start(self) -> MockServer {
+        let server = MockServer::start().await;
+        let head = Mock::given(method("HEAD"));
+        let get = Mock::given(method("GET"));
+        let (head, get) = match &self.required {
+            Some((name, value)) => (
+                head.and(header(name.as_str(), value.as_str())),
+                get.and(header(name.as_str(), value.as_str())),
+            ),
+            None => (head, get),
+        };
+
+        let (file, headers) = (self.file.clone(), self.headers.clone());
+        head.respond_with(move |req: &Request| {
+            let response = ResponseTemplate::new(200)
+                .insert_header("Content-Length", file(req).len().to_string().as_str())
+                .insert_header("Accept-Ranges", "bytes");
+            with_headers(response, &headers)
+        })
+        .mount(&server)
+        .await;
+
+        let RangeOrigin {
+            file,
+            headers,
+            delay,
+            on_get,
+            ..
+        } = self;
+        get.respond_with(move |req: &Request| {
+            on_get(req);
+            let body = file(req);
+            let (start, end) = requested_range(req);
+            let end = end.min(body.len() - 1);
+            let response = ResponseTemplate::new(206)
+                .insert_header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", start, end, body.len()).as_str(),
+                )
+                .set_body_bytes(body[start..=end].to_vec())
+                .set_delay(delay(start));
+            with_headers(response, &headers)
+        })
+        .mount(&server)
+        .await;
+        server
+    }
+}
+
+/// Start an origin serving a file of `size` bytes of `x`
+pub async fn start_origin(size: usize) -> MockServer {
+    RangeOrigin::new(size).start().await
+}
+
+/// Start and end of the request's `Range: bytes=a-b` header
+pub fn requested_range(req: &Request) -> (usize, usize) {
+    let range = req.headers.get(&"range".into()).unwrap().last().as_str();
+    let (start, end) = range
+        .trim_start_matches("bytes=")
+        .split_once('-')
+        .unwrap();
+    (start.parse().unwrap(), end.parse().unwrap())
+}
+
+fn with_headers(mut response: ResponseTemplate, headers: &[(String, String)]) -> ResponseTemplate {
+    for (name, value) in headers {
+        response = response.insert_header(name.as_str(), value.as_str());
+    }
+    response
+}
@@ -4,6 +4,7 @@
 // Property: For any 4xx status code returned by the origin server for a HEAD request,
 // the same status code should be returned to the client without retry
 
+use pingora_slice::config::MetadataProbe;
 use pingora_slice::error::SliceError;
 use pingora_slice::metadata_fetcher::MetadataFetcher;
 use proptest::prelude::*;
@@ -46,7 +47,7 @@ proptest! {
                 .await;
             
             // Create metadata fetcher
-            let fetcher = MetadataFetcher::new().unwrap();
+            let fetcher = MetadataFetcher::new().unwrap().with_probe(MetadataProbe::Head);
             
             // Attempt to fetch metadata (should fail with 4xx error)
             let url = format!("{}/test-file", mock_server.uri());
@@ -147,7 +148,7 @@ proptest! {
                 .mount(&mock_server)
                 .await;
             
-            let fetcher = MetadataFetcher::new().unwrap();
+            let fetcher = MetadataFetcher::new().unwrap().with_probe(MetadataProbe::Head);
             let url = format!("{}/test-file", mock_server.uri());
             let result = fetcher.fetch_metadata(&url).await;
             
@@ -203,7 +204,7 @@ proptest! {
                 .mount(&mock_server)
                 .await;
             
-            let fetcher = MetadataFetcher::new().unwrap();
+            let fetcher = MetadataFetcher::new().unwrap().with_probe(MetadataProbe::Head);
             let url = format!("{}/test-file", mock_server.uri());
             let result = fetcher.fetch_metadata(&url).await;
             
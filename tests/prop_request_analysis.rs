@@ -1,8 +1,9 @@
 // Feature: pingora-slice, Property 2: Range 请求透传
 // **Validates: Requirements 2.3**
 //
-// Property: For any client request containing a Range header, the request 
-// should bypass the slicing logic and be passed through to the origin server directly
+// Property: With `slice_client_range_requests` disabled, any client request
+// containing a Range header should bypass the slicing logic and be passed
+// through to the origin server directly. When enabled, single ranges are sliced.
 
 use pingora_slice::config::SliceConfig;
 use pingora_slice::request_analyzer::RequestAnalyzer;
@@ -10,6 +11,14 @@ use http::{Method, HeaderMap, HeaderValue};
 use proptest::prelude::*;
 use std::sync::Arc;
 
+/// Config that proxies Range requests instead of slicing them
+fn passthrough_config() -> SliceConfig {
+    SliceConfig {
+        slice_client_range_requests: false,
+        ..Default::default()
+    }
+}
+
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(100))]
 
@@ -44,7 +53,7 @@ proptest! {
         );
         
         // Create config with no patterns (would normally slice everything)
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         // Test with GET method (which would normally enable slicing)
@@ -84,7 +93,7 @@ proptest! {
             "/downloads/*".to_string(),
         ];
         
-        let mut config = SliceConfig::default();
+        let mut config = passthrough_config();
         config.slice_patterns = patterns.clone();
         let config = Arc::new(config);
         let analyzer = RequestAnalyzer::new(config);
@@ -146,7 +155,7 @@ proptest! {
         let mut headers = HeaderMap::new();
         headers.insert("range", HeaderValue::from_str(&range_value).unwrap());
         
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         // should_slice should return false for any method with Range header
@@ -186,7 +195,7 @@ proptest! {
         let mut headers = HeaderMap::new();
         headers.insert("range", HeaderValue::from_str(&range_value).unwrap());
         
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         let result = analyzer.should_slice(&Method::GET, "/test.bin", &headers);
@@ -214,7 +223,7 @@ proptest! {
         };
         
         let range_value = format!("bytes={}-{}", start, end);
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         // Test lowercase "range"
@@ -237,6 +246,27 @@ proptest! {
             "should_slice() must return false for capitalized 'Range' header"
         );
     }
+
+    /// Property 2 (enabled): Single ranges are sliced
+    ///
+    /// With the default config, a GET with one `bytes=start-end` range is
+    /// eligible for slicing, while a multi-range header is still proxied.
+    #[test]
+    fn prop_single_range_sliced_when_enabled(
+        start in 0u64..=1_000_000u64,
+        len in 0u64..=1_000_000u64,
+    ) {
+        let analyzer = RequestAnalyzer::new(Arc::new(SliceConfig::default()));
+
+        let mut headers = HeaderMap::new();
+        let single = format!("bytes={}-{}", start, start + len);
+        headers.insert("range", HeaderValue::from_str(&single).unwrap());
+        prop_assert!(analyzer.should_slice(&Method::GET, "/test.bin", &headers));
+
+        let multi = format!("bytes=0-{},{}-{}", start, start + 1, start + len + 1);
+        headers.insert("range", HeaderValue::from_str(&multi).unwrap());
+        prop_assert!(!analyzer.should_slice(&Method::GET, "/test.bin", &headers));
+    }
 }
 
 #[cfg(test)]
@@ -245,7 +275,7 @@ mod unit_tests {
 
     #[test]
     fn test_range_header_prevents_slicing() {
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         let mut headers = HeaderMap::new();
@@ -257,7 +287,7 @@ mod unit_tests {
 
     #[test]
     fn test_range_header_case_insensitive() {
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         // Test lowercase
@@ -273,7 +303,7 @@ mod unit_tests {
 
     #[test]
     fn test_range_header_overrides_pattern_match() {
-        let mut config = SliceConfig::default();
+        let mut config = passthrough_config();
         config.slice_patterns = vec!["/large-files/".to_string()];
         let config = Arc::new(config);
         let analyzer = RequestAnalyzer::new(config);
@@ -288,7 +318,7 @@ mod unit_tests {
 
     #[test]
     fn test_no_range_header_allows_slicing() {
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         let headers = HeaderMap::new();
@@ -300,7 +330,7 @@ mod unit_tests {
 
     #[test]
     fn test_range_header_with_post_method() {
-        let config = Arc::new(SliceConfig::default());
+        let config = Arc::new(passthrough_config());
         let analyzer = RequestAnalyzer::new(config);
         
         let mut headers = HeaderMap::new();
@@ -0,0 +1,213 @@
+//! Integration tests for pluggable L2 backends
+//!
+//! A mock backend stands in for a third-party store (S3, Redis, ...) and
+//! records every call the tiered cache and the PURGE handler make to it.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{Method, Request, StatusCode};
+use pingora_slice::purge_handler::PurgeHandler;
+use pingora_slice::{ByteRange, CacheBackend, CacheBackendStats, SliceError, TieredCache};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Call {
+    Store { key: String, ttl: Duration },
+    Lookup(String),
+    Remove(String),
+    PurgeAll,
+    Health,
+}
+
+#[derive(Default)]
+struct MockBackend {
+    entries: Mutex<HashMap<String, Bytes>>,
+    calls: Mutex<Vec<Call>>,
+    failing: AtomicBool,
+}
+
+impl MockBackend {
+    fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: Call) -> pingora_slice::Result<()> {
+        self.calls.lock().unwrap().push(call);
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(SliceError::CacheError("backend unavailable".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MockBackend {
+    async fn store(&self, key: &str, data: Bytes, ttl: Duration) -> pingora_slice::Result<()> {
+        self.record(Call::Store { key: key.to_string(), ttl })?;
+        self.entries.lock().unwrap().insert(key.to_string(), data);
+        Ok(())
+    }
+
+    async fn lookup(&self, key: &str) -> pingora_slice::Result<Option<Bytes>> {
+        self.record(Call::Lookup(key.to_string()))?;
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    async fn remove(&self, key: &str) -> pingora_slice::Result<bool> {
+        self.record(Call::Remove(key.to_string()))?;
+        Ok(self.entries.lock().unwrap().remove(key).is_some())
+    }
+
+    async fn purge_all(&self) -> pingora_slice::Result<usize> {
+        self.record(Call::PurgeAll)?;
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.clear();
+        Ok(count)
+    }
+
+    fn stats(&self) -> CacheBackendStats {
+        let entries = self.entries.lock().unwrap();
+        CacheBackendStats {
+            name: "mock".to_string(),
+            entries: Some(entries.len() as u64),
+            bytes: Some(entries.values().map(|data| data.len() as u64).sum()),
+            ..Default::default()
+        }
+    }
+
+    async fn health(&self) -> pingora_slice::Result<()> {
+        self.record(Call::Health)
+    }
+}
+
+/// Wait for the cache's background writer to make `condition` true
+async fn eventually(condition: impl Fn() -> bool) {
+    for _ in 0..100 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("condition not reached");
+}
+
+fn range(index: u64) -> ByteRange {
+    ByteRange::new(index * 100, index * 100 + 99).unwrap()
+}
+
+#[tokio::test]
+async fn test_store_and_lookup_go_through_backend() {
+    let backend = Arc::new(MockBackend::default());
+    let cache = TieredCache::with_backend(TTL, 1024 * 1024, backend.clone());
+    let url = "http://example.com/file";
+    let key = cache.generate_cache_key(url, &range(0));
+
+    cache.store(url, &range(0), Bytes::from(vec![1u8; 100])).unwrap();
+    eventually(|| backend.entries.lock().unwrap().contains_key(&key)).await;
+    match &backend.calls()[0] {
+        Call::Store { key: stored, ttl } => {
+            assert_eq!(stored, &key);
+            assert!(*ttl <= TTL && *ttl > TTL - Duration::from_secs(5));
+        }
+        call => panic!("unexpected call {:?}", call),
+    }
+
+    // An entry only the backend holds is an L2 hit and is promoted to L1
+    let other = cache.generate_cache_key(url, &range(1));
+    backend.entries.lock().unwrap().insert(other.clone(), Bytes::from(vec![2u8; 100]));
+    assert_eq!(cache.lookup(url, &range(1)).await.unwrap().unwrap()[0], 2);
+    assert_eq!(cache.lookup(url, &range(1)).await.unwrap().unwrap()[0], 2);
+    let lookups = backend.calls().iter().filter(|c| **c == Call::Lookup(other.clone())).count();
+    assert_eq!(lookups, 1);
+
+    let stats = cache.get_stats();
+    assert_eq!(stats.l2_hits, 1);
+    assert_eq!(stats.disk_writes, 1);
+    let backend_stats = stats.l2_backend.unwrap();
+    assert_eq!(backend_stats.name, "mock");
+    assert_eq!(backend_stats.entries, Some(2));
+    assert_eq!(backend_stats.bytes, Some(200));
+}
+
+#[tokio::test]
+async fn test_remove_and_purge_reach_backend() {
+    let backend = Arc::new(MockBackend::default());
+    let cache = TieredCache::with_backend(TTL, 1024 * 1024, backend.clone());
+    let url = "http://example.com/file";
+    for i in 0..3 {
+        cache.store(url, &range(i), Bytes::from(vec![0u8; 100])).unwrap();
+    }
+    eventually(|| backend.entries.lock().unwrap().len() == 3).await;
+
+    let key = cache.generate_cache_key(url, &range(0));
+    let removed = cache.remove_entry(&key).await.unwrap();
+    assert!(removed.l1 && removed.l2);
+    assert!(backend.calls().contains(&Call::Remove(key)));
+
+    let key = cache.generate_cache_key(url, &range(1));
+    assert!(cache.purge(url, &range(1)).await.unwrap());
+    eventually(|| backend.calls().contains(&Call::Remove(key.clone()))).await;
+
+    assert_eq!(cache.purge_all().await.unwrap(), 1);
+    eventually(|| backend.calls().contains(&Call::PurgeAll)).await;
+    assert!(backend.entries.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_purge_handler_purges_custom_backend() {
+    let backend = Arc::new(MockBackend::default());
+    let cache = Arc::new(TieredCache::with_backend(TTL, 1024 * 1024, backend.clone()));
+    let url = "http://example.com/video.mp4";
+    for i in 0..2 {
+        cache.store(url, &range(i), Bytes::from(vec![0u8; 100])).unwrap();
+    }
+    eventually(|| backend.entries.lock().unwrap().len() == 2).await;
+
+    let handler = PurgeHandler::new(cache.clone());
+    let req = Request::builder()
+        .method(Method::from_bytes(b"PURGE").unwrap())
+        .uri("/video.mp4")
+        .header("host", "example.com")
+        .body(())
+        .unwrap();
+    let response = handler.handle_purge(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    eventually(|| backend.entries.lock().unwrap().is_empty()).await;
+    assert!(cache.lookup(url, &range(0)).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_failing_backend_is_bypassed_until_healthy() {
+    let backend = Arc::new(MockBackend::default());
+    let cache = TieredCache::with_backend_and_health(
+        TTL,
+        1024 * 1024,
+        backend.clone(),
+        2,
+        Duration::from_millis(20),
+    );
+    let url = "http://example.com/file";
+
+    backend.failing.store(true, Ordering::SeqCst);
+    for i in 0..2 {
+        cache.store(url, &range(i), Bytes::from(vec![0u8; 100])).unwrap();
+    }
+    eventually(|| cache.is_l2_degraded()).await;
+    assert_eq!(cache.get_stats().disk_errors, 2);
+
+    // Misses skip the backend while it is bypassed
+    let calls = backend.calls().len();
+    assert!(cache.lookup(url, &range(5)).await.unwrap().is_none());
+    assert!(!backend.calls()[calls..].iter().any(|c| matches!(c, Call::Lookup(_))));
+
+    backend.failing.store(false, Ordering::SeqCst);
+    eventually(|| !cache.is_l2_degraded()).await;
+    assert!(backend.calls().contains(&Call::Health));
+}
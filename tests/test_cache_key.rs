@@ -0,0 +1,93 @@
+//! Integration tests for cache key normalization
+//!
+//! With `strip_query`, URLs that differ only in tracking parameters share
+//! one cache entry in the proxy and the warmed cache, the origin still sees
+//! the URL the client sent, and one PURGE clears the shared entry.
+
+mod common;
+
+use common::start_origin;
+use http::{HeaderMap, Method, Request, StatusCode};
+use pingora_slice::{
+    ByteRange, CacheKeyBuilder, CacheKeyPolicy, CacheWarmer, SliceConfig, SliceContext, SliceProxy,
+    TieredCache,
+};
+use pingora_slice::purge_handler::PurgeHandler;
+use std::sync::Arc;
+use std::time::Duration;
+
+const FILE_SIZE: usize = 2048;
+
+fn config() -> SliceConfig {
+    SliceConfig {
+        slice_size: 1024,
+        cache_key_policy: CacheKeyPolicy {
+            strip_query: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_stripped_params_share_cache_entry() {
+    let origin = start_origin(FILE_SIZE).await;
+    let proxy = SliceProxy::new(Arc::new(config()));
+
+    for source in ["newsletter", "social"] {
+        let url = format!("{}/file.bin?utm_source={}", origin.uri(), source);
+        let mut ctx = SliceContext::new();
+        let passthrough = proxy
+            .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+            .await
+            .unwrap();
+        assert!(!passthrough);
+        let (_, _, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+        assert_eq!(body.concat().len(), FILE_SIZE);
+    }
+
+    // Metadata and slices were fetched once, for the first URL as sent
+    let requests = origin.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    for req in &requests {
+        assert_eq!(req.url.query(), Some("utm_source=newsletter"));
+    }
+    let stats = proxy.metrics().get_stats();
+    assert_eq!(stats.cache_hits, 2);
+}
+
+#[tokio::test]
+async fn test_single_purge_clears_shared_entry() {
+    let origin = start_origin(FILE_SIZE).await;
+    let config = Arc::new(config());
+    let cache = Arc::new(TieredCache::memory_only(Duration::from_secs(60), 1024 * 1024));
+    let warmer = CacheWarmer::new(config.clone(), cache.clone());
+
+    let urls = vec![
+        format!("{}/file.bin?utm_source=a", origin.uri()),
+        format!("{}/file.bin?utm_source=b", origin.uri()),
+    ];
+    let status = warmer.warm(urls.clone()).await.unwrap();
+    assert_eq!(status.urls[0].slices_fetched, 2);
+    assert_eq!(status.urls[1].slices_cached, 2);
+
+    let keys = CacheKeyBuilder::from_config(&config);
+    let range = ByteRange::new(0, 1023).unwrap();
+    for url in &urls {
+        assert!(cache.lookup(&keys.build(url), &range).await.unwrap().is_some());
+    }
+
+    let handler = PurgeHandler::new(cache.clone()).with_cache_keys(keys.clone());
+    let req = Request::builder()
+        .method(Method::from_bytes(b"PURGE").unwrap())
+        .uri("/file.bin?utm_source=c")
+        .header("host", origin.address().to_string())
+        .body(())
+        .unwrap();
+    let response = handler.handle_purge(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for url in &urls {
+        assert!(cache.lookup(&keys.build(url), &range).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,194 @@
+//! Integration tests for the `slice-cachectl` commands
+//!
+//! The commands run in-process against an L2 directory written by
+//! `FileBackend`, the way the binary runs them against a node's cache.
+
+use bytes::Bytes;
+use pingora_slice::cache_backend::{CacheBackend, FileBackend};
+use pingora_slice::cachectl::{self, EXIT_CORRUPT, EXIT_FAILED, EXIT_HEALTHY, EXIT_UNUSABLE};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Run `slice-cachectl <dir> <command>`, returning the exit code and output
+async fn cachectl(dir: &Path, command: &str) -> (i32, String, String) {
+    let mut args = vec![dir.display().to_string()];
+    args.extend(command.split_whitespace().map(str::to_string));
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    let code = cachectl::run(&args, &mut out, &mut err).await;
+    (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+}
+
+/// A cache with two entries under `a/` and one under `b/`
+async fn populated_cache(dedup: bool) -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let backend = FileBackend::new(temp_dir.path()).await.unwrap().with_dedup(dedup);
+    backend.verify_layout().await.unwrap();
+    backend.load_index().await.unwrap();
+    for (key, data) in [("a/1", "first"), ("a/2", "second"), ("b/1", "first")] {
+        backend
+            .store(key, Bytes::from(data), Duration::from_secs(3600))
+            .await
+            .unwrap();
+    }
+    backend.sync().await.unwrap();
+    temp_dir
+}
+
+#[tokio::test]
+async fn test_info_ls_and_get() {
+    let temp_dir = populated_cache(false).await;
+    let dir = temp_dir.path();
+
+    let (code, out, _) = cachectl(dir, "info").await;
+    assert_eq!(code, EXIT_HEALTHY);
+    assert!(out.contains("layout: plain"), "{}", out);
+    assert!(out.contains("index: 3 records"), "{}", out);
+    assert!(out.contains("entries: 3 (0 expired)"), "{}", out);
+
+    let (code, out, _) = cachectl(dir, "ls --prefix a/").await;
+    assert_eq!(code, EXIT_HEALTHY);
+    let keys: Vec<&str> = out.lines().map(|line| line.split('\t').next().unwrap()).collect();
+    assert_eq!(keys, ["a/1", "a/2"]);
+
+    let (code, out, _) = cachectl(dir, "get a/2").await;
+    assert_eq!((code, out.as_str()), (EXIT_HEALTHY, "second"));
+
+    let output = dir.join("..").join(format!("{}.out", dir.file_name().unwrap().to_string_lossy()));
+    let (code, _, _) = cachectl(dir, &format!("get b/1 -o {}", output.display())).await;
+    assert_eq!(code, EXIT_HEALTHY);
+    assert_eq!(std::fs::read(&output).unwrap(), b"first");
+    std::fs::remove_file(output).unwrap();
+
+    let (code, _, err) = cachectl(dir, "get c/1").await;
+    assert_eq!(code, EXIT_FAILED);
+    assert!(err.contains("not cached"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_read_only_commands_leave_the_directory_alone() {
+    let temp_dir = populated_cache(false).await;
+    let dir = temp_dir.path();
+    let backend = FileBackend::open_readonly(dir).await.unwrap();
+    assert!(backend.is_read_only());
+    assert!(backend.store("c/1", Bytes::from("x"), Duration::from_secs(60)).await.is_err());
+    assert!(backend.remove("a/1").await.is_err());
+
+    let index_before = std::fs::read(dir.join(".index")).unwrap();
+    for command in ["info", "ls", "get a/1", "verify", "stats", "stats --json"] {
+        assert_eq!(cachectl(dir, command).await.0, EXIT_HEALTHY, "{}", command);
+    }
+    assert_eq!(std::fs::read(dir.join(".index")).unwrap(), index_before);
+}
+
+#[tokio::test]
+async fn test_verify_reports_and_repairs_corruption() {
+    let temp_dir = populated_cache(false).await;
+    let dir = temp_dir.path();
+    assert_eq!(cachectl(dir, "verify").await.0, EXIT_HEALTHY);
+
+    // A truncated entry and a write that never completed
+    let records = FileBackend::open_readonly(dir)
+        .await
+        .unwrap()
+        .read_index()
+        .await
+        .unwrap()
+        .unwrap();
+    let truncated = dir.join(&records[0].file);
+    std::fs::write(&truncated, b"abc").unwrap();
+    std::fs::write(truncated.with_file_name("x.~partial.0"), b"partial").unwrap();
+
+    let (code, out, _) = cachectl(dir, "verify").await;
+    assert_eq!(code, EXIT_CORRUPT);
+    assert!(out.contains("problem: 1 corrupt entries"), "{}", out);
+    assert!(out.contains("problem: 1 temporary files"), "{}", out);
+    // Without --repair nothing changed
+    assert_eq!(cachectl(dir, "verify").await.0, EXIT_CORRUPT);
+
+    let (code, out, _) = cachectl(dir, "verify --repair").await;
+    assert_eq!(code, EXIT_HEALTHY, "{}", out);
+    assert!(out.contains("1 corrupt entries removed, 1 temporary files removed"), "{}", out);
+    assert!(out.ends_with("healthy\n"), "{}", out);
+
+    let (_, out, _) = cachectl(dir, "ls").await;
+    assert_eq!(out.lines().count(), 2);
+    assert_eq!(cachectl(dir, "verify").await.0, EXIT_HEALTHY);
+}
+
+#[tokio::test]
+async fn test_verify_repairs_dedup_reference_counts() {
+    let temp_dir = populated_cache(true).await;
+    let dir = temp_dir.path();
+    let (code, out, _) = cachectl(dir, "info").await;
+    assert_eq!(code, EXIT_HEALTHY);
+    assert!(out.contains("layout: dedup"), "{}", out);
+
+    // Dropping one of the two references to "first" leaves its count stale
+    let records = FileBackend::open_readonly(dir)
+        .await
+        .unwrap()
+        .read_index()
+        .await
+        .unwrap()
+        .unwrap();
+    let b1 = records.iter().find(|record| record.key == "b/1").unwrap();
+    std::fs::remove_file(dir.join(&b1.file)).unwrap();
+
+    let (code, out, _) = cachectl(dir, "verify").await;
+    assert_eq!(code, EXIT_CORRUPT);
+    assert!(out.contains("index records without an entry file"), "{}", out);
+    assert!(out.contains("wrong reference counts"), "{}", out);
+
+    let (code, out, _) = cachectl(dir, "verify --repair").await;
+    assert_eq!(code, EXIT_HEALTHY, "{}", out);
+    let (code, out, _) = cachectl(dir, "get a/1").await;
+    assert_eq!((code, out.as_str()), (EXIT_HEALTHY, "first"));
+}
+
+#[tokio::test]
+async fn test_rebuild_metadata_and_stats() {
+    let temp_dir = populated_cache(false).await;
+    let dir = temp_dir.path();
+    std::fs::write(dir.join(".index"), b"garbage").unwrap();
+
+    let (code, out, _) = cachectl(dir, "verify").await;
+    assert_eq!(code, EXIT_CORRUPT);
+    assert!(out.contains("index unreadable"), "{}", out);
+
+    let (code, out, _) = cachectl(dir, "rebuild-metadata").await;
+    assert_eq!(code, EXIT_HEALTHY);
+    assert!(out.contains("3 entries adopted"), "{}", out);
+    assert_eq!(cachectl(dir, "verify").await.0, EXIT_HEALTHY);
+
+    let (code, out, _) = cachectl(dir, "stats --json").await;
+    assert_eq!(code, EXIT_HEALTHY);
+    let stats: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(stats["layout"], "plain");
+    assert_eq!(stats["entries"], 3);
+    assert_eq!(stats["index_records"], 3);
+    // Data plus the 8 byte expiry of each entry
+    assert_eq!(stats["indexed_bytes"], 16);
+    assert!(stats["file_bytes"].as_u64().unwrap() >= 16 + 3 * 8);
+
+    let (code, out, _) = cachectl(dir, "stats").await;
+    assert_eq!(code, EXIT_HEALTHY);
+    assert!(out.contains("slack bytes:"), "{}", out);
+}
+
+#[tokio::test]
+async fn test_unusable_directory_and_bad_arguments() {
+    let temp_dir = TempDir::new().unwrap();
+    let missing = temp_dir.path().join("missing");
+    let (code, _, err) = cachectl(&missing, "info").await;
+    assert_eq!(code, EXIT_UNUSABLE);
+    assert!(err.contains("cannot open cache"), "{}", err);
+    assert!(!missing.exists());
+
+    std::fs::write(temp_dir.path().join(".layout"), b"striped").unwrap();
+    assert_eq!(cachectl(temp_dir.path(), "info").await.0, EXIT_UNUSABLE);
+
+    let (code, _, err) = cachectl(temp_dir.path(), "verify --force").await;
+    assert_eq!(code, EXIT_FAILED);
+    assert!(err.contains("usage: slice-cachectl"), "{}", err);
+}
@@ -0,0 +1,119 @@
+//! Integration tests for client disconnect handling
+//!
+//! The mock origin raises the request's abort signal while serving the first
+//! slice, simulating a client that goes away mid-download, and the tests
+//! check how many origin requests each `on_client_abort` policy makes.
+
+mod common;
+
+use common::RangeOrigin;
+use pingora_slice::{
+    AbortSignal, ByteRange, ClientAbortPolicy, FileMetadata, SliceConfig, SliceContext,
+    SliceError, SliceProxy, SliceSpec,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::MockServer;
+
+const SLICE_SIZE: u64 = 1024;
+const SLICE_COUNT: u64 = 4;
+
+/// Start an origin that counts range requests and raises `abort` on the first
+async fn start_aborting_origin(abort: AbortSignal, requests: Arc<AtomicUsize>) -> MockServer {
+    RangeOrigin::from_fn((SLICE_SIZE * SLICE_COUNT) as usize, |_| 0x42)
+        .on_get(move |_| {
+            if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                abort.abort();
+            }
+        })
+        .delay(|_| Duration::from_millis(100))
+        .start()
+        .await
+}
+
+fn slice_range(index: u64) -> ByteRange {
+    ByteRange::new(index * SLICE_SIZE, (index + 1) * SLICE_SIZE - 1).unwrap()
+}
+
+struct AbortedRequest {
+    proxy: SliceProxy,
+    url: String,
+    requests: Arc<AtomicUsize>,
+    // Kept alive so background fills can still reach the origin
+    _origin: MockServer,
+}
+
+/// Run one request under `policy` that the client abandons during the first slice
+async fn run_aborted_request(policy: ClientAbortPolicy) -> AbortedRequest {
+    let config = Arc::new(SliceConfig {
+        slice_size: SLICE_SIZE as usize,
+        max_concurrent_subrequests: 1,
+        max_retries: 0,
+        on_client_abort: policy,
+        ..Default::default()
+    });
+    let proxy = SliceProxy::new(config);
+
+    let mut ctx = SliceContext::new();
+    ctx.set_metadata(FileMetadata::new(SLICE_SIZE * SLICE_COUNT, true));
+    ctx.set_slices(
+        (0..SLICE_COUNT)
+            .map(|i| SliceSpec::new(i as usize, slice_range(i)))
+            .collect(),
+    );
+    ctx.enable_slicing();
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let origin = start_aborting_origin(ctx.client_abort.clone(), requests.clone()).await;
+    let url = format!("{}/video.mp4", origin.uri());
+
+    let result = proxy.handle_slice_request(&url, &ctx).await;
+    assert!(matches!(result, Err(SliceError::ClientAborted)));
+    assert_eq!(proxy.metrics().get_stats().client_aborts, 1);
+
+    AbortedRequest { proxy, url, requests, _origin: origin }
+}
+
+#[tokio::test]
+async fn test_abort_cancels_fill() {
+    let AbortedRequest { proxy, url, requests, _origin } =
+        run_aborted_request(ClientAbortPolicy::Abort).await;
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert!(proxy.cache_arc().lookup(&url, &slice_range(0)).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_complete_current_slice_stores_in_flight_slice() {
+    let AbortedRequest { proxy, url, requests, _origin } =
+        run_aborted_request(ClientAbortPolicy::CompleteCurrentSlice).await;
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let cache = proxy.cache_arc();
+    assert!(cache.lookup(&url, &slice_range(0)).await.unwrap().is_some());
+    assert!(cache.lookup(&url, &slice_range(1)).await.unwrap().is_none());
+    assert_eq!(proxy.metrics().get_stats().background_fills, 0);
+}
+
+#[tokio::test]
+async fn test_complete_fill_continues_in_background() {
+    let AbortedRequest { proxy, url, requests, _origin } =
+        run_aborted_request(ClientAbortPolicy::CompleteFill).await;
+
+    let mut waited = Duration::ZERO;
+    while proxy.metrics().get_stats().background_fills == 0 {
+        assert!(waited < Duration::from_secs(5), "background fill did not finish");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        waited += Duration::from_millis(20);
+    }
+
+    assert_eq!(requests.load(Ordering::SeqCst), SLICE_COUNT as usize);
+    let cache = proxy.cache_arc();
+    for i in 0..SLICE_COUNT {
+        assert!(cache.lookup(&url, &slice_range(i)).await.unwrap().is_some());
+    }
+}
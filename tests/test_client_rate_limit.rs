@@ -0,0 +1,80 @@
+//! Integration tests for per-client rate limiting
+//!
+//! A client bursting past its request budget gets 429s with `Retry-After`
+//! while other clients are still served.
+
+mod common;
+
+use common::start_origin;
+use http::{HeaderMap, Method, StatusCode};
+use pingora_slice::{ClientRateLimitConfig, SliceConfig, SliceContext, SliceError, SliceProxy};
+use std::sync::Arc;
+
+fn proxy() -> SliceProxy {
+    SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        client_rate_limit: Some(ClientRateLimitConfig {
+            requests_per_sec: 1,
+            burst: Some(3),
+            bytes_per_sec: None,
+            max_clients: 100,
+        }),
+        ..Default::default()
+    }))
+}
+
+async fn request(proxy: &SliceProxy, url: &str, client: &str) -> (Result<bool, SliceError>, SliceContext) {
+    let mut ctx = SliceContext::new();
+    ctx.client_addr = Some(client.to_string());
+    let result = proxy
+        .request_filter(&Method::GET, url, &HeaderMap::new(), &mut ctx)
+        .await;
+    (result, ctx)
+}
+
+#[tokio::test]
+async fn test_burst_past_limit_gets_429() {
+    let origin = start_origin(2048).await;
+    let url = format!("{}/file.bin", origin.uri());
+    let proxy = proxy();
+
+    let mut throttled = 0;
+    for _ in 0..6 {
+        let (result, mut ctx) = request(&proxy, &url, "198.51.100.7").await;
+        match result {
+            Ok(passthrough) => assert!(!passthrough),
+            Err(error) => {
+                assert!(matches!(error, SliceError::RateLimited { .. }));
+                let (status, headers, body) = proxy.fail_to_proxy(&error, &mut ctx).unwrap();
+                assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+                assert_eq!(headers.get("retry-after").unwrap(), "1");
+                assert!(body.is_empty());
+                assert_eq!(ctx.response_status, Some(429));
+                throttled += 1;
+            }
+        }
+    }
+    assert_eq!(throttled, 3);
+
+    // Another client has its own budget
+    let (result, _) = request(&proxy, &url, "198.51.100.8").await;
+    assert!(!result.unwrap());
+
+    assert_eq!(proxy.metrics().get_stats().throttled_requests, 3);
+}
+
+#[tokio::test]
+async fn test_unlimited_without_config() {
+    let origin = start_origin(2048).await;
+    let url = format!("{}/file.bin", origin.uri());
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        ..Default::default()
+    }));
+
+    for _ in 0..10 {
+        let (result, _) = request(&proxy, &url, "198.51.100.7").await;
+        assert!(!result.unwrap());
+    }
+    assert_eq!(proxy.metrics().get_stats().throttled_requests, 0);
+}
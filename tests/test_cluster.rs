@@ -0,0 +1,151 @@
+//! Integration tests for cluster mode
+//!
+//! Two proxies share a peer list; a request for an object owned by the first
+//! node that arrives at the second must be forwarded to the first, or with
+//! `local_copy` be filled from it.
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, HeaderValue, Method};
+use pingora_slice::{
+    ClusterConfig, HashRing, SliceConfig, SliceContext, SliceProxy, CLUSTER_HOP_HEADER,
+};
+use std::sync::Arc;
+use wiremock::MockServer;
+
+const NODE_A: &str = "10.0.0.1:8080";
+const NODE_B: &str = "10.0.0.2:8080";
+const FILE_SIZE: usize = 4096;
+
+async fn start_origin() -> MockServer {
+    RangeOrigin::from_fn(FILE_SIZE, |_| 0x61).start().await
+}
+
+fn node(self_peer: &str, local_copy: bool) -> SliceProxy {
+    SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        cluster: Some(ClusterConfig {
+            peers: vec![NODE_A.to_string(), NODE_B.to_string()],
+            self_peer: self_peer.to_string(),
+            local_copy,
+        }),
+        ..Default::default()
+    }))
+}
+
+/// A URL on `origin` that the ring assigns to `owner`
+fn url_owned_by(origin: &MockServer, owner: &str) -> String {
+    let ring = HashRing::new(&[NODE_A.to_string(), NODE_B.to_string()]);
+    (0..)
+        .map(|i| format!("{}/videos/{}.mp4", origin.uri(), i))
+        .find(|url| ring.owner(url) == Some(owner))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_request_routed_to_owning_peer() {
+    let origin = start_origin().await;
+    let url = url_owned_by(&origin, NODE_A);
+    let node_a = node(NODE_A, false);
+    let node_b = node(NODE_B, false);
+
+    // Node B does not own the object and forwards it to node A
+    let mut ctx = SliceContext::new();
+    let passthrough = node_b
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(passthrough);
+    assert_eq!(ctx.cluster_peer.as_deref(), Some(NODE_A));
+    assert_eq!(node_b.upstream_peer(&ctx).unwrap(), NODE_A);
+
+    let mut upstream_headers = HeaderMap::new();
+    node_b.upstream_request_filter(&mut upstream_headers, &ctx).unwrap();
+    assert_eq!(upstream_headers.get(CLUSTER_HOP_HEADER).unwrap(), NODE_B);
+    assert_eq!(node_b.metrics().get_stats().cluster_routed.get(NODE_A), Some(&1));
+
+    // Node A owns the object and fills it from the origin
+    let mut ctx = SliceContext::new();
+    let passthrough = node_a
+        .request_filter(&Method::GET, &url, &upstream_headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    assert!(ctx.cluster_peer.is_none());
+    let (_, _, body) = node_a.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(body.concat().len(), FILE_SIZE);
+    assert!(node_a.metrics().get_stats().cluster_routed.is_empty());
+}
+
+#[tokio::test]
+async fn test_forwarded_request_is_not_bounced_again() {
+    let origin = start_origin().await;
+    let url = url_owned_by(&origin, NODE_A);
+    let node_b = node(NODE_B, false);
+
+    // A request that already hopped once is served locally, even if misrouted
+    let mut headers = HeaderMap::new();
+    headers.insert(CLUSTER_HOP_HEADER, HeaderValue::from_static(NODE_A));
+    let mut ctx = SliceContext::new();
+    let passthrough = node_b
+        .request_filter(&Method::GET, &url, &headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    assert!(ctx.cluster_peer.is_none());
+}
+
+#[tokio::test]
+async fn test_local_copy_fills_from_owning_peer() {
+    // The owner is stood in for by a mock answering like the origin; the
+    // origin itself must not be contacted
+    let origin = MockServer::start().await;
+    let owner = start_origin().await;
+    let owner_addr = owner.address().to_string();
+    let peers = vec![owner_addr.clone(), NODE_B.to_string()];
+    let ring = HashRing::new(&peers);
+    let url = (0..)
+        .map(|i| format!("{}/videos/{}.mp4", origin.uri(), i))
+        .find(|url| ring.owner(url) == Some(owner_addr.as_str()))
+        .unwrap();
+    let node_b = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        cluster: Some(ClusterConfig {
+            peers,
+            self_peer: NODE_B.to_string(),
+            local_copy: true,
+        }),
+        ..Default::default()
+    }));
+
+    // Node B slices the request itself and fills it from the owner
+    let mut ctx = SliceContext::new();
+    assert!(!node_b
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap());
+    assert_eq!(ctx.cluster_peer.as_deref(), Some(owner_addr.as_str()));
+    let (_, _, body) = node_b.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(body.concat().len(), FILE_SIZE);
+
+    let requests = owner.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 5);
+    assert!(requests
+        .iter()
+        .all(|req| req.headers.get(&CLUSTER_HOP_HEADER.into()).unwrap().as_str() == NODE_B));
+    assert!(origin.received_requests().await.unwrap().is_empty());
+    assert_eq!(node_b.metrics().get_stats().cluster_routed.get(&owner_addr), Some(&1));
+
+    // The copy it kept answers the next request without asking the owner
+    let mut ctx = SliceContext::new();
+    assert!(!node_b
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap());
+    assert_eq!(ctx.cached_slice_count(), ctx.slice_count());
+    node_b.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(owner.received_requests().await.unwrap().len(), 5);
+    assert_eq!(node_b.metrics().get_stats().cluster_routed.get(&owner_addr), Some(&1));
+}
@@ -0,0 +1,98 @@
+//! Integration tests for conditional requests
+//!
+//! The mock origin serves a 2KB file with a fixed ETag and Last-Modified.
+//! Requests carrying matching validators must get an empty 304 with the
+//! validators, and anything else the full file.
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use pingora_slice::{SliceConfig, SliceContext, SliceProxy};
+use std::sync::Arc;
+use wiremock::MockServer;
+
+const FILE_SIZE: usize = 2048;
+const ETAG: &str = "\"v1\"";
+const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+async fn start_origin() -> MockServer {
+    RangeOrigin::new(FILE_SIZE)
+        .header("ETag", ETAG)
+        .header("Last-Modified", LAST_MODIFIED)
+        .start()
+        .await
+}
+
+/// Run one request with `headers` through the proxy
+async fn fetch(headers: &[(&'static str, &'static str)]) -> (StatusCode, HeaderMap, Vec<u8>) {
+    let origin = start_origin().await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        ..Default::default()
+    }));
+    let url = format!("{}/file.bin", origin.uri());
+
+    let mut request_headers = HeaderMap::new();
+    for (name, value) in headers {
+        request_headers.insert(*name, HeaderValue::from_static(value));
+    }
+
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, &url, &request_headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+
+    let (status, headers, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+    (status, headers, body.concat())
+}
+
+#[tokio::test]
+async fn test_matching_etag_returns_not_modified() {
+    let (status, headers, body) = fetch(&[("if-none-match", ETAG)]).await;
+
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert!(body.is_empty());
+    assert_eq!(headers.get("etag").unwrap(), ETAG);
+    assert_eq!(headers.get("last-modified").unwrap(), LAST_MODIFIED);
+    assert!(headers.get("content-length").is_none());
+}
+
+#[tokio::test]
+async fn test_mismatched_etag_returns_full_body() {
+    let (status, headers, body) = fetch(&[("if-none-match", "\"v0\"")]).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers.get("etag").unwrap(), ETAG);
+    assert_eq!(body.len(), FILE_SIZE);
+}
+
+#[tokio::test]
+async fn test_weak_etag_and_wildcard_match() {
+    for if_none_match in ["W/\"v1\"", "\"v0\", W/\"v1\"", "*"] {
+        let (status, _, body) = fetch(&[("if-none-match", if_none_match)]).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED, "If-None-Match: {}", if_none_match);
+        assert!(body.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_if_modified_since() {
+    let (status, _, body) = fetch(&[("if-modified-since", LAST_MODIFIED)]).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert!(body.is_empty());
+
+    let (status, _, body) = fetch(&[("if-modified-since", "Tue, 20 Oct 2015 07:28:00 GMT")]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.len(), FILE_SIZE);
+
+    // If-None-Match wins over a matching If-Modified-Since
+    let (status, _, _) = fetch(&[
+        ("if-none-match", "\"v0\""),
+        ("if-modified-since", LAST_MODIFIED),
+    ])
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -58,3 +58,21 @@ fn test_load_nonexistent_file() {
     let config = SliceConfig::from_file("nonexistent.yaml");
     assert!(config.is_err(), "Should fail when file doesn't exist");
 }
+
+#[test]
+fn test_load_rejects_unknown_key() {
+    let yaml = r#"
+slice_size: 1048576
+enable_cahce: false
+"#;
+
+    std::fs::write("test_unknown_key.yaml", yaml).unwrap();
+
+    let err = SliceConfig::load("test_unknown_key.yaml").unwrap_err().to_string();
+    assert!(err.contains("test_unknown_key.yaml"), "{}", err);
+    assert!(err.contains("unknown field `enable_cahce`"), "{}", err);
+    assert!(err.contains("line 3"), "{}", err);
+
+    // Cleanup
+    std::fs::remove_file("test_unknown_key.yaml").unwrap();
+}
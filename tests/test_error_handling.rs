@@ -218,3 +218,13 @@ fn test_subrequest_failed_not_retryable() {
     };
     assert!(!error.should_retry(), "SubrequestFailed should not be retried (already exhausted)");
 }
+
+#[test]
+fn test_origin_failures_allow_stale() {
+    // Only a failing origin lets an expired copy stand in
+    assert!(SliceError::origin_server_error(503, "Service Unavailable").is_origin_failure());
+    assert!(SliceError::Timeout("Connection timeout".to_string()).is_origin_failure());
+    assert!(SliceError::SubrequestFailed { slice_index: 0, attempts: 3 }.is_origin_failure());
+    assert!(!SliceError::origin_client_error(404, "Not Found").is_origin_failure());
+    assert!(!SliceError::ClientAborted.is_origin_failure());
+}
@@ -0,0 +1,75 @@
+//! Integration tests for custom error pages
+//!
+//! The origin answers the metadata probe and then goes away, so every slice
+//! fetch fails and nothing is cached.
+
+use http::{HeaderMap, Method, StatusCode};
+use pingora_slice::{ErrorPageConfig, SliceConfig, SliceContext, SliceProxy};
+use std::io::Write;
+use std::sync::Arc;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PAGE: &str = "<html><body>Origin unavailable</body></html>";
+
+fn config(error_pages: Vec<ErrorPageConfig>) -> SliceConfig {
+    SliceConfig {
+        slice_size: 1024,
+        max_retries: 0,
+        error_pages,
+        ..Default::default()
+    }
+}
+
+/// Run a sliced request against an origin that goes down after the HEAD
+async fn fail_request(proxy: &SliceProxy) -> (pingora_slice::SliceError, SliceContext) {
+    let origin = MockServer::builder().start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", "2048")
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&origin)
+        .await;
+    let url = format!("{}/file.bin", origin.uri());
+
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    drop(origin);
+
+    let error = proxy.handle_slice_request(&url, &ctx).await.unwrap_err();
+    (error, ctx)
+}
+
+#[tokio::test]
+async fn test_origin_down_serves_error_page() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(PAGE.as_bytes()).unwrap();
+    let proxy = SliceProxy::new(Arc::new(config(vec![ErrorPageConfig {
+        status: 502,
+        path: file.path().to_string_lossy().into_owned(),
+        content_type: "text/html; charset=utf-8".to_string(),
+    }])));
+
+    let (error, mut ctx) = fail_request(&proxy).await;
+    let (status, headers, body) = proxy.fail_to_proxy(&error, &mut ctx).unwrap();
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(headers.get("content-type").unwrap(), "text/html; charset=utf-8");
+    assert_eq!(body, PAGE.as_bytes());
+    assert_eq!(ctx.response_status, Some(502));
+    assert_eq!(ctx.bytes_sent, PAGE.len() as u64);
+}
+
+#[tokio::test]
+async fn test_no_error_page_configured() {
+    let proxy = SliceProxy::new(Arc::new(config(Vec::new())));
+
+    let (error, mut ctx) = fail_request(&proxy).await;
+    assert_eq!(error.to_http_status(), 502);
+    assert!(proxy.fail_to_proxy(&error, &mut ctx).is_none());
+}
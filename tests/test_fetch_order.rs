@@ -0,0 +1,114 @@
+//! Integration tests for slice fetch ordering
+//!
+//! The mock origin answers the first slice slowly and later slices quickly,
+//! so the order in which slices become available shows which were started
+//! first.
+
+mod common;
+
+use common::RangeOrigin;
+use pingora_slice::{ByteRange, FetchOrder, SliceMetrics, SliceSpec, SubrequestManager};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::MockServer;
+
+const SLICE_SIZE: u64 = 1024;
+const SLICE_COUNT: u64 = 4;
+
+/// Start an origin where slice 0 takes 200ms and every other slice 10ms
+async fn start_origin() -> MockServer {
+    start_origin_with(SLICE_COUNT, |start| if start == 0 { 200 } else { 10 }).await
+}
+
+/// Start an origin serving `slice_count` slices, each delayed by
+/// `delay_ms(range start)`
+async fn start_origin_with(slice_count: u64, delay_ms: fn(u64) -> u64) -> MockServer {
+    RangeOrigin::from_fn((SLICE_SIZE * slice_count) as usize, |_| 0x33)
+        .delay(move |start| Duration::from_millis(delay_ms(start as u64)))
+        .start()
+        .await
+}
+
+fn slices() -> Vec<SliceSpec> {
+    slices_of(SLICE_COUNT)
+}
+
+fn slices_of(count: u64) -> Vec<SliceSpec> {
+    (0..count)
+        .map(|i| {
+            let start = i * SLICE_SIZE;
+            SliceSpec::new(i as usize, ByteRange::new(start, start + SLICE_SIZE - 1).unwrap())
+        })
+        .collect()
+}
+
+/// Fetch all slices and return their indices in the order they became available
+async fn arrival_order(order: FetchOrder, max_concurrent: usize) -> Vec<usize> {
+    let origin = start_origin().await;
+    let manager = SubrequestManager::new(max_concurrent, 0).with_fetch_order(order);
+    let url = format!("{}/movie.mp4", origin.uri());
+
+    let mut rx = manager.fetch_slices_stream(slices(), &url);
+    let mut arrived = Vec::new();
+    while let Some(result) = rx.recv().await {
+        arrived.push(result.unwrap().slice_index);
+    }
+    arrived
+}
+
+#[tokio::test]
+async fn test_parallel_delivers_fast_slices_first() {
+    let arrived = arrival_order(FetchOrder::Parallel, 4).await;
+    assert_eq!(arrived.len(), SLICE_COUNT as usize);
+    assert_eq!(*arrived.last().unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_sequential_first_slice_available_first() {
+    let arrived = arrival_order(FetchOrder::SequentialFirstN(1), 4).await;
+    assert_eq!(arrived.len(), SLICE_COUNT as usize);
+    assert_eq!(arrived[0], 0);
+}
+
+#[tokio::test]
+async fn test_priority_starts_slices_in_order() {
+    let arrived = arrival_order(FetchOrder::Priority, 1).await;
+    assert_eq!(arrived, vec![0, 1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_fetch_slices_returns_sorted_results() {
+    let origin = start_origin().await;
+    let manager = SubrequestManager::new(4, 0).with_fetch_order(FetchOrder::SequentialFirstN(2));
+    let url = format!("{}/movie.mp4", origin.uri());
+
+    let results = manager.fetch_slices(slices(), &url).await.unwrap();
+    let indices: Vec<usize> = results.iter().map(|r| r.slice_index).collect();
+    assert_eq!(indices, vec![0, 1, 2, 3]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_first_slice_never_waits_behind_later_slices() {
+    const COUNT: u64 = 24;
+    let origin = start_origin_with(COUNT, |start| if start == 0 { 20 } else { 300 }).await;
+    let metrics = Arc::new(SliceMetrics::new());
+    let manager = SubrequestManager::new(2, 0).with_metrics(metrics.clone());
+    let url = format!("{}/movie.mp4", origin.uri());
+
+    let mut rx = manager.fetch_slices_stream(slices_of(COUNT), &url);
+    assert_eq!(rx.recv().await.unwrap().unwrap().slice_index, 0);
+    drop(rx);
+
+    // Slice 0 was sent before later slices could fill both slots...
+    let requests = origin.received_requests().await.unwrap();
+    let first = requests
+        .iter()
+        .position(|r| r.headers.get(&"range".into()).unwrap().last().as_str() == "bytes=0-1023")
+        .unwrap();
+    assert!(first < 2, "slice 0 was request {}", first);
+
+    // ...so it arrived after one slice round trip, not after a 300ms one
+    let first_slice = metrics.get_stats().first_slice_latency;
+    assert_eq!(first_slice.count, 1);
+    assert!(first_slice.p50_ms() <= 250.0, "{}ms", first_slice.p50_ms());
+}
@@ -0,0 +1,150 @@
+//! Integration tests for cache fill limits
+//!
+//! The mock origin answers slice requests slowly, so the fills admitted
+//! first are still in flight when the later misses arrive.
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, Method};
+use pingora_slice::{SliceConfig, SliceContext, SliceProxy};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::MockServer;
+
+const FILE_SIZE: usize = 2048;
+
+async fn start_slow_origin() -> MockServer {
+    RangeOrigin::new(FILE_SIZE)
+        .delay(|_| Duration::from_millis(300))
+        .start()
+        .await
+}
+
+/// Request `files` concurrently
+///
+/// # Returns
+/// The `X-Cache` header each request would be proxied with, `None` for
+/// requests served sliced
+async fn concurrent_requests(
+    proxy: &SliceProxy,
+    origin: &MockServer,
+    files: &[usize],
+) -> Vec<Option<String>> {
+    let tasks: Vec<_> = files
+        .iter()
+        .map(|i| {
+            let proxy = proxy.clone();
+            let url = format!("{}/file{}.bin", origin.uri(), i);
+            tokio::spawn(async move {
+                let mut ctx = SliceContext::new();
+                let passthrough = proxy
+                    .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+                    .await
+                    .unwrap();
+                if passthrough {
+                    let mut headers = HeaderMap::new();
+                    proxy.upstream_response_filter(&mut headers, &ctx);
+                    return headers
+                        .get("x-cache")
+                        .map(|v| v.to_str().unwrap().to_string());
+                }
+                let (_, _, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+                assert_eq!(body.concat().len(), FILE_SIZE);
+                None
+            })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for task in tasks {
+        results.push(task.await.unwrap());
+    }
+    results
+}
+
+#[tokio::test]
+async fn test_misses_over_fill_limit_bypass() {
+    let origin = start_slow_origin().await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        max_concurrent_fills: Some(2),
+        ..Default::default()
+    }));
+
+    let results = concurrent_requests(&proxy, &origin, &[0, 1, 2, 3, 4]).await;
+    let bypassed = results.iter().flatten().collect::<Vec<_>>();
+    assert_eq!(bypassed.len(), 3);
+    assert!(bypassed.iter().all(|v| *v == "BYPASS-BUSY"));
+    assert_eq!(proxy.metrics().get_stats().fill_bypasses, 3);
+
+    // Every admitted fill returned its budget
+    let limiter = proxy.fill_limiter();
+    assert_eq!(limiter.active_fills(), Some(0));
+    assert_eq!(limiter.buffered_bytes(), 0);
+}
+
+#[tokio::test]
+async fn test_fill_buffer_budget() {
+    let origin = start_slow_origin().await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        max_fill_buffer_bytes: Some(3 * FILE_SIZE as u64),
+        ..Default::default()
+    }));
+
+    let results = concurrent_requests(&proxy, &origin, &[0, 1, 2, 3]).await;
+    assert_eq!(results.iter().flatten().count(), 1);
+    assert_eq!(proxy.fill_limiter().buffered_bytes(), 0);
+
+    // Once the fills are done, the cached files are served without taking
+    // any budget
+    let filled: Vec<usize> = (0..4).filter(|&i| results[i].is_none()).collect();
+    let results = concurrent_requests(&proxy, &origin, &filled).await;
+    assert!(results.iter().all(Option::is_none));
+    assert_eq!(proxy.metrics().get_stats().fill_bypasses, 1);
+}
+
+#[tokio::test]
+async fn test_large_fill_is_assembled_and_released() {
+    const LARGE_SIZE: usize = 4 * 1024 * 1024;
+    let content: Vec<u8> = (0..LARGE_SIZE).map(|i| (i % 251) as u8).collect();
+
+    let body = content.clone();
+    let origin = RangeOrigin::with_file(move |_| body.clone()).start().await;
+
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 256 * 1024,
+        ..Default::default()
+    }));
+    let url = format!("{}/large.bin", origin.uri());
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    assert_eq!(proxy.metrics().get_stats().fill_buffered_bytes, LARGE_SIZE as u64);
+
+    let (_, _, slices) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(slices.concat(), content);
+
+    drop(ctx);
+    assert_eq!(proxy.fill_limiter().buffered_bytes(), 0);
+    assert_eq!(proxy.metrics().get_stats().fill_buffered_bytes, 0);
+}
+
+#[tokio::test]
+async fn test_miss_over_request_fill_limit_bypasses() {
+    let origin = start_slow_origin().await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        max_request_fill_bytes: Some(FILE_SIZE as u64 - 1),
+        ..Default::default()
+    }));
+
+    let results = concurrent_requests(&proxy, &origin, &[0]).await;
+    assert_eq!(results, vec![Some("BYPASS-TOO-LARGE".to_string())]);
+    assert_eq!(proxy.metrics().get_stats().fill_bypasses, 1);
+    assert_eq!(proxy.metrics().get_stats().fill_buffered_bytes, 0);
+}
@@ -0,0 +1,114 @@
+//! Integration tests for the upstream User-Agent and forwarding headers
+//!
+//! The mock origin records every request, so the tests can check what the
+//! metadata probe and slice subrequests carried.
+
+mod common;
+
+use common::start_origin;
+use http::{HeaderMap, HeaderValue, Method};
+use pingora_slice::{ForwardedHeadersConfig, ForwardedMode, SliceConfig, SliceContext, SliceProxy};
+use std::sync::Arc;
+use wiremock::Request;
+
+const FILE_SIZE: usize = 2048;
+
+fn config(mode: ForwardedMode) -> SliceConfig {
+    SliceConfig {
+        slice_size: 1024,
+        upstream_user_agent: Some("pingora-slice/test".to_string()),
+        forwarded_headers: ForwardedHeadersConfig {
+            x_forwarded_for: true,
+            x_forwarded_proto: true,
+            forwarded: true,
+            mode,
+        },
+        ..Default::default()
+    }
+}
+
+fn header(req: &Request, name: &str) -> String {
+    req.headers.get(&name.into()).unwrap().iter().map(|v| v.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+#[tokio::test]
+async fn test_origin_requests_carry_client_address() {
+    let origin = start_origin(FILE_SIZE).await;
+    let proxy = SliceProxy::new(Arc::new(config(ForwardedMode::Append)));
+    let url = format!("{}/file.bin", origin.uri());
+
+    let mut ctx = SliceContext::new();
+    ctx.client_addr = Some("198.51.100.7:40000".to_string());
+    ctx.client_scheme = Some("https".to_string());
+    let passthrough = proxy
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    let (_, _, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(body.concat().len(), FILE_SIZE);
+
+    // HEAD + 2 slices
+    let requests = origin.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    for req in &requests {
+        assert_eq!(header(req, "user-agent"), "pingora-slice/test");
+        assert_eq!(header(req, "x-forwarded-for"), "198.51.100.7");
+        assert_eq!(header(req, "x-forwarded-proto"), "https");
+        assert_eq!(header(req, "forwarded"), "for=198.51.100.7;proto=https");
+    }
+}
+
+#[tokio::test]
+async fn test_chained_proxy_headers_appended() {
+    let origin = start_origin(FILE_SIZE).await;
+    let proxy = SliceProxy::new(Arc::new(config(ForwardedMode::Append)));
+    let url = format!("{}/file.bin", origin.uri());
+
+    // A load balancer in front of this proxy already added its client
+    let mut request = HeaderMap::new();
+    request.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.9"));
+    request.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+    request.insert("forwarded", HeaderValue::from_static("for=203.0.113.9;proto=https"));
+
+    let mut ctx = SliceContext::new();
+    ctx.client_addr = Some("10.0.0.2".to_string());
+    proxy
+        .request_filter(&Method::GET, &url, &request, &mut ctx)
+        .await
+        .unwrap();
+    proxy.handle_slice_request(&url, &ctx).await.unwrap();
+
+    for req in &origin.received_requests().await.unwrap() {
+        assert_eq!(header(req, "x-forwarded-for"), "203.0.113.9, 10.0.0.2");
+        assert_eq!(header(req, "x-forwarded-proto"), "https");
+        assert_eq!(
+            header(req, "forwarded"),
+            "for=203.0.113.9;proto=https, for=10.0.0.2;proto=http"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_proxied_request_headers() {
+    let proxy = SliceProxy::new(Arc::new(config(ForwardedMode::Set)));
+
+    // Spoofed by the client, replaced in set mode
+    let mut request = HeaderMap::new();
+    request.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4"));
+    request.insert("user-agent", HeaderValue::from_static("curl/8.0"));
+
+    let mut ctx = SliceContext::new();
+    ctx.client_addr = Some("198.51.100.7".to_string());
+    let passthrough = proxy
+        .request_filter(&Method::POST, "http://origin/upload", &request, &mut ctx)
+        .await
+        .unwrap();
+    assert!(passthrough);
+
+    proxy.upstream_request_filter(&mut request, &ctx).unwrap();
+    assert_eq!(request.get_all("x-forwarded-for").iter().count(), 1);
+    assert_eq!(request.get("x-forwarded-for").unwrap(), "198.51.100.7");
+    assert_eq!(request.get("x-forwarded-proto").unwrap(), "http");
+    assert_eq!(request.get("user-agent").unwrap(), "pingora-slice/test");
+}
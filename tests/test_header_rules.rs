@@ -0,0 +1,95 @@
+//! Integration tests for header rewrite rules
+//!
+//! The mock origin only answers requests carrying the auth header that an
+//! upstream request rule adds, and sends a cookie that a response rule strips.
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, HeaderValue, Method};
+use pingora_slice::{
+    HeaderAction, HeaderRule, HeaderTarget, SliceConfig, SliceContext, SliceProxy,
+};
+use std::sync::Arc;
+use wiremock::MockServer;
+
+const FILE_SIZE: usize = 2048;
+const TOKEN: &str = "Bearer secret";
+
+/// Start an origin that only answers requests carrying the `TOKEN` rule header
+async fn start_origin() -> MockServer {
+    RangeOrigin::new(FILE_SIZE).require_header("authorization", TOKEN).start().await
+}
+
+fn config() -> SliceConfig {
+    SliceConfig {
+        slice_size: 1024,
+        header_rules: vec![
+            HeaderRule {
+                apply_to: HeaderTarget::UpstreamRequest,
+                action: HeaderAction::Set,
+                name: "authorization".to_string(),
+                value: Some(TOKEN.to_string()),
+            },
+            HeaderRule {
+                apply_to: HeaderTarget::Response,
+                action: HeaderAction::Remove,
+                name: "set-cookie".to_string(),
+                value: None,
+            },
+            HeaderRule {
+                apply_to: HeaderTarget::Response,
+                action: HeaderAction::Add,
+                name: "x-served-by".to_string(),
+                value: Some("pingora-slice".to_string()),
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_auth_header_added_to_origin_requests() {
+    let origin = start_origin().await;
+    let proxy = SliceProxy::new(Arc::new(config()));
+    let url = format!("{}/file.bin", origin.uri());
+
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    let (_, headers, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(body.concat().len(), FILE_SIZE);
+    assert_eq!(headers.get("x-served-by").unwrap(), "pingora-slice");
+
+    // HEAD + 2 slices, all authorized
+    let requests = origin.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    for req in &requests {
+        assert_eq!(req.headers.get(&"authorization".into()).unwrap().last().as_str(), TOKEN);
+    }
+}
+
+#[tokio::test]
+async fn test_proxied_request_and_response_rewritten() {
+    let proxy = SliceProxy::new(Arc::new(config()));
+    let ctx = SliceContext::new();
+
+    let mut request = HeaderMap::new();
+    request.insert("authorization", HeaderValue::from_static("Basic client"));
+    proxy.upstream_request_filter(&mut request, &ctx).unwrap();
+    assert_eq!(request.get_all("authorization").iter().count(), 1);
+    assert_eq!(request.get("authorization").unwrap(), TOKEN);
+
+    // The cookie is gone before the response can be cached or sent
+    let mut response = HeaderMap::new();
+    response.append("set-cookie", HeaderValue::from_static("session=abc"));
+    response.append("set-cookie", HeaderValue::from_static("id=1"));
+    response.insert("content-type", HeaderValue::from_static("video/mp4"));
+    proxy.upstream_response_filter(&mut response, &ctx);
+    assert!(!response.contains_key("set-cookie"));
+    assert_eq!(response.get("content-type").unwrap(), "video/mp4");
+    assert_eq!(response.get("x-served-by").unwrap(), "pingora-slice");
+}
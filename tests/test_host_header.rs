@@ -0,0 +1,104 @@
+//! Integration tests for the Host header of origin requests
+//!
+//! The mock origin serves a different file per virtual host, so a probe and
+//! slice requests that disagree on the Host would disagree on the size too.
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, HeaderValue, Method};
+use pingora_slice::{HostHeaderMode, SliceConfig, SliceContext, SliceProxy};
+use std::sync::Arc;
+use wiremock::{MockServer, Request};
+
+/// Size and fill byte of the file each virtual host serves
+fn virtual_file(req: &Request) -> (usize, u8) {
+    match req.headers.get(&"host".into()).map(|v| v.last().as_str()) {
+        Some("a.example") => (3000, b'a'),
+        Some("b.example") => (1500, b'b'),
+        _ => (500, b'u'),
+    }
+}
+
+async fn start_origin() -> MockServer {
+    RangeOrigin::with_file(|req| {
+        let (size, fill) = virtual_file(req);
+        vec![fill; size]
+    })
+    .start()
+    .await
+}
+
+fn proxy(host_header: HostHeaderMode) -> SliceProxy {
+    SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        host_header,
+        ..Default::default()
+    }))
+}
+
+/// Fetch `url` through the proxy as a client of `host`
+async fn fetch(proxy: &SliceProxy, url: &str, host: &'static str) -> Vec<u8> {
+    let mut headers = HeaderMap::new();
+    headers.insert("host", HeaderValue::from_static(host));
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, url, &headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    let (_, _, body) = proxy.handle_slice_request(url, &ctx).await.unwrap();
+    body.concat()
+}
+
+#[tokio::test]
+async fn test_preserved_host_reaches_virtual_host() {
+    let origin = start_origin().await;
+    let proxy = proxy(HostHeaderMode::Preserve);
+    let url = format!("{}/file.bin", origin.uri());
+
+    assert_eq!(fetch(&proxy, &url, "a.example").await, vec![b'a'; 3000]);
+    assert_eq!(fetch(&proxy, &url, "b.example").await, vec![b'b'; 1500]);
+
+    // The probe and every slice request carried the client's Host
+    let requests = origin.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2 + 3 + 2);
+    for req in &requests {
+        assert_ne!(virtual_file(req).1, b'u');
+    }
+
+    // Each host is cached under its own key
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 5);
+    assert_eq!(fetch(&proxy, &url, "a.example").await, vec![b'a'; 3000]);
+    assert_eq!(origin.received_requests().await.unwrap().len(), 7);
+}
+
+#[tokio::test]
+async fn test_upstream_host_by_default() {
+    let origin = start_origin().await;
+    let proxy = proxy(HostHeaderMode::default());
+    let url = format!("{}/file.bin", origin.uri());
+
+    // Both clients get the file of the upstream address's host
+    assert_eq!(fetch(&proxy, &url, "a.example").await, vec![b'u'; 500]);
+    assert_eq!(fetch(&proxy, &url, "b.example").await, vec![b'u'; 500]);
+    assert_eq!(origin.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_preserved_host_on_proxied_requests() {
+    let proxy = proxy(HostHeaderMode::Preserve);
+    let mut headers = HeaderMap::new();
+    headers.insert("host", HeaderValue::from_static("a.example"));
+
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::POST, "http://origin/upload", &headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(passthrough);
+
+    let mut upstream = HeaderMap::new();
+    proxy.upstream_request_filter(&mut upstream, &ctx).unwrap();
+    assert_eq!(upstream.get("host").unwrap(), "a.example");
+}
@@ -106,3 +106,162 @@ async fn test_metadata_fetcher_default() {
         }
     }
 }
+
+mod range_probe {
+    use pingora_slice::{MetadataFetcher, MetadataProbe, SliceError};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_head_405_falls_back_to_ranged_get_206() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/file.bin"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/file.bin"))
+            .and(header("range", "bytes=0-0"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 0-0/10485760")
+                    .insert_header("content-type", "application/octet-stream")
+                    .insert_header("etag", "\"abc\"")
+                    .set_body_bytes(vec![0u8]),
+            )
+            .mount(&server)
+            .await;
+
+        let fetcher = MetadataFetcher::new().unwrap();
+        let metadata = fetcher
+            .fetch_metadata(&format!("{}/file.bin", server.uri()))
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.content_length, 10485760);
+        assert!(metadata.supports_range);
+        assert_eq!(metadata.content_type.as_deref(), Some("application/octet-stream"));
+        assert_eq!(metadata.etag.as_deref(), Some("\"abc\""));
+    }
+
+    #[tokio::test]
+    async fn test_head_405_falls_back_to_ranged_get_200() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/file.bin"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/file.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 2048]))
+            .mount(&server)
+            .await;
+
+        let fetcher = MetadataFetcher::new().unwrap();
+        let metadata = fetcher
+            .fetch_metadata(&format!("{}/file.bin", server.uri()))
+            .await
+            .unwrap();
+
+        // 200 to a ranged GET means the origin does not support ranges
+        assert_eq!(metadata.content_length, 2048);
+        assert!(!metadata.supports_range);
+    }
+
+    #[tokio::test]
+    async fn test_auto_prefers_successful_head() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/file.bin"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-length", "4096")
+                    .insert_header("accept-ranges", "bytes"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/file.bin"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let fetcher = MetadataFetcher::new().unwrap().with_probe(MetadataProbe::Auto);
+        let metadata = fetcher
+            .fetch_metadata(&format!("{}/file.bin", server.uri()))
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.content_length, 4096);
+        assert!(metadata.supports_range);
+    }
+
+    #[tokio::test]
+    async fn test_failed_fallback_reports_get_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/file.bin"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/file.bin"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let fetcher = MetadataFetcher::new().unwrap();
+        let result = fetcher
+            .fetch_metadata(&format!("{}/file.bin", server.uri()))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(SliceError::OriginClientError { status: 404, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_head_mode_does_not_fall_back() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/file.bin"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+
+        let fetcher = MetadataFetcher::new().unwrap().with_probe(MetadataProbe::Head);
+        let result = fetcher
+            .fetch_metadata(&format!("{}/file.bin", server.uri()))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(SliceError::OriginClientError { status: 405, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_range_mode_skips_head() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/file.bin"))
+            .respond_with(
+                ResponseTemplate::new(206).insert_header("content-range", "bytes 0-0/512"),
+            )
+            .mount(&server)
+            .await;
+
+        let fetcher = MetadataFetcher::new().unwrap().with_probe(MetadataProbe::GetRange);
+        let metadata = fetcher
+            .fetch_metadata(&format!("{}/file.bin", server.uri()))
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.content_length, 512);
+        assert!(metadata.supports_range);
+    }
+}
@@ -256,3 +256,96 @@ async fn test_metrics_update_reflected_in_endpoint() {
         assert!(body.contains("pingora_slice_cache_hits_total 2"));
     }
 }
+
+/// Start `endpoint` on a free port and return its address
+async fn spawn_endpoint(endpoint: impl FnOnce(std::net::SocketAddr) -> MetricsEndpoint) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let endpoint = endpoint(addr);
+    tokio::spawn(async move { endpoint.start().await });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    addr
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_basic_auth() {
+    let metrics = Arc::new(SliceMetrics::new());
+    metrics.record_request(true);
+    let addr = spawn_endpoint(|addr| {
+        MetricsEndpoint::new(metrics, addr)
+            .with_path("/internal/metrics")
+            .with_basic_auth("prometheus", "s3cret")
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/internal/metrics", addr);
+
+    // No or wrong credentials are refused with a challenge
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Basic realm=\"metrics\"");
+    let response = client.get(&url).basic_auth("prometheus", Some("wrong")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = client.get(&url).basic_auth("prometheus", Some("s3cret")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("pingora_slice_requests_total 1"));
+    assert!(body.contains("pingora_slice_uptime_seconds"));
+    if cfg!(target_os = "linux") {
+        assert!(body.contains("process_resident_memory_bytes"));
+        assert!(body.contains("process_open_fds"));
+    }
+
+    // The old path is gone and health probes need no credentials
+    let response = client.get(format!("http://{}/metrics", addr)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    let response = client.get(format!("http://{}/health", addr)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_gzip() {
+    let metrics = Arc::new(SliceMetrics::new());
+    metrics.record_request(true);
+    metrics.record_bytes_from_origin(1000);
+    let addr = spawn_endpoint(|addr| MetricsEndpoint::new(metrics, addr)).await;
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/metrics", addr);
+
+    let plain = client.get(&url).send().await.unwrap();
+    assert!(plain.headers().get("content-encoding").is_none());
+    let plain = plain.text().await.unwrap();
+
+    let response = client.get(&url).header("accept-encoding", "gzip").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(response.headers()["vary"], "Accept-Encoding");
+    let compressed = response.bytes().await.unwrap();
+    assert!(compressed.len() < plain.len() / 3, "{} of {} bytes", compressed.len(), plain.len());
+
+    // A single gzip member: 10-byte header, deflate data, 8-byte trailer
+    assert_eq!(compressed[..2], [0x1f, 0x8b]);
+    let member = &compressed[10..compressed.len() - 8];
+    let body = String::from_utf8(miniz_oxide::inflate::decompress_to_vec(member).unwrap()).unwrap();
+    assert!(body.contains("pingora_slice_requests_total 1\n"));
+    assert!(body.contains("pingora_slice_bytes_from_origin_total 1000\n"));
+    assert!(body.ends_with("\n\n"));
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_registry() {
+    let registry = prometheus::Registry::new();
+    let purge_metrics = pingora_slice::purge_metrics::PurgeMetrics::with_registry(&registry).unwrap();
+    purge_metrics.record_request("PURGE");
+    let metrics = Arc::new(SliceMetrics::new());
+    metrics.record_request(true);
+    let addr = spawn_endpoint(|addr| MetricsEndpoint::new(metrics, addr).with_registry(registry)).await;
+
+    let body = reqwest::get(format!("http://{}/metrics", addr)).await.unwrap().text().await.unwrap();
+    assert!(body.contains("pingora_slice_requests_total 1\n"));
+    assert!(body.contains("# TYPE pingora_slice_purge_requests_total counter"));
+    assert!(body.contains("pingora_slice_purge_requests_total{method=\"PURGE\"} 1"));
+}
@@ -0,0 +1,124 @@
+//! Integration tests for the connection pool of slice subrequests
+//!
+//! The mock origin speaks HTTP/1.1 and cleartext HTTP/2 on the same port
+//! and counts the connections it accepts, so the tests can tell how many
+//! connections a set of slices was fetched over.
+
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response, StatusCode, Version};
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use pingora_slice::{OriginProtocol, SliceConfig, SliceContext, SliceProxy};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+const FILE_SIZE: usize = 4096;
+
+#[derive(Default)]
+struct Origin {
+    connections: AtomicUsize,
+    /// Version of every ranged GET received
+    get_versions: Mutex<Vec<Version>>,
+}
+
+async fn respond(origin: Arc<Origin>, req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.method() == Method::HEAD {
+        return Ok(Response::builder()
+            .header("content-length", FILE_SIZE)
+            .header("accept-ranges", "bytes")
+            .body(Full::default())
+            .unwrap());
+    }
+    origin.get_versions.lock().unwrap().push(req.version());
+    let range = req.headers()["range"].to_str().unwrap();
+    let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+    let start: usize = start.parse().unwrap();
+    let end: usize = end.parse::<usize>().unwrap().min(FILE_SIZE - 1);
+    Ok(Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header("content-range", format!("bytes {}-{}/{}", start, end, FILE_SIZE))
+        .body(Full::new(Bytes::from(vec![b'x'; end - start + 1])))
+        .unwrap())
+}
+
+/// Start an origin accepting HTTP/1.1 and HTTP/2 with prior knowledge
+async fn start_origin() -> (String, Arc<Origin>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let origin = Arc::new(Origin::default());
+
+    let state = origin.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            state.connections.fetch_add(1, Ordering::SeqCst);
+            let state = state.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| respond(state.clone(), req));
+                let _ = auto::Builder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    (base, origin)
+}
+
+fn proxy(origin_protocol: OriginProtocol, max_concurrent_subrequests: usize) -> SliceProxy {
+    SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        max_concurrent_subrequests,
+        origin_protocol,
+        ..Default::default()
+    }))
+}
+
+async fn fetch(proxy: &SliceProxy, url: &str) {
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    let (_, _, body) = proxy.handle_slice_request(url, &ctx).await.unwrap();
+    assert_eq!(body.concat().len(), FILE_SIZE);
+}
+
+#[tokio::test]
+async fn test_http2_multiplexes_slices_over_one_connection() {
+    let (base, origin) = start_origin().await;
+    let proxy = proxy(OriginProtocol::Http2, 4);
+
+    fetch(&proxy, &format!("{}/a.bin", base)).await;
+    fetch(&proxy, &format!("{}/b.bin", base)).await;
+
+    // Both probes and all eight slices over the one connection
+    let versions = origin.get_versions.lock().unwrap().clone();
+    assert_eq!(versions, vec![Version::HTTP_2; 8]);
+    let stats = proxy.metrics().get_stats();
+    assert_eq!(stats.total_subrequests, 8);
+    assert_eq!((stats.http1_subrequests, stats.http2_subrequests), (0, 8));
+    assert_eq!(stats.origin_connections, 1);
+    assert_eq!(stats.origin_connection_reuse_rate(), 87.5);
+    assert_eq!(origin.connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_http1_connections_kept_alive() {
+    let (base, origin) = start_origin().await;
+    let proxy = proxy(OriginProtocol::Http1, 1);
+
+    fetch(&proxy, &format!("{}/a.bin", base)).await;
+    fetch(&proxy, &format!("{}/b.bin", base)).await;
+
+    let versions = origin.get_versions.lock().unwrap().clone();
+    assert_eq!(versions, vec![Version::HTTP_11; 8]);
+    let stats = proxy.metrics().get_stats();
+    assert_eq!((stats.http1_subrequests, stats.http2_subrequests), (8, 0));
+    assert_eq!(stats.origin_connections, 1);
+    assert_eq!(origin.connections.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,124 @@
+//! Integration tests for origin rate limiting
+//!
+//! These tests run slice subrequests against a mock origin with a small
+//! bandwidth budget and check the measured fill rate against the cap.
+
+mod common;
+
+use common::RangeOrigin;
+use pingora_slice::{
+    ByteRange, FileMetadata, OriginRateLimiter, SliceConfig, SliceContext, SliceProxy, SliceSpec,
+    SubrequestManager,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wiremock::MockServer;
+
+const SLICE_SIZE: u64 = 64 * 1024;
+const BYTES_PER_SEC: u64 = 256 * 1024;
+
+/// Start an origin that answers any `Range: bytes=a-b` request for a file of
+/// `file_size` bytes
+async fn start_range_origin(file_size: u64) -> MockServer {
+    RangeOrigin::from_fn(file_size as usize, |_| 0x5A).start().await
+}
+
+fn slices_for(file_size: u64) -> Vec<SliceSpec> {
+    (0..file_size / SLICE_SIZE)
+        .map(|i| {
+            let start = i * SLICE_SIZE;
+            SliceSpec::new(i as usize, ByteRange::new(start, start + SLICE_SIZE - 1).unwrap())
+        })
+        .collect()
+}
+
+fn assert_rate_near_cap(bytes: u64, elapsed: Duration) {
+    let rate = bytes as f64 / elapsed.as_secs_f64();
+    let cap = BYTES_PER_SEC as f64;
+    assert!(
+        rate >= cap * 0.9 && rate <= cap * 1.1,
+        "fill rate {:.0} B/s not within 10% of cap {:.0} B/s",
+        rate,
+        cap
+    );
+}
+
+#[tokio::test]
+async fn test_fill_rate_stays_within_cap() {
+    let file_size = 12 * SLICE_SIZE;
+    let origin = start_range_origin(file_size).await;
+
+    let config = Arc::new(SliceConfig {
+        slice_size: SLICE_SIZE as usize,
+        max_concurrent_subrequests: 4,
+        origin_max_bytes_per_sec: Some(BYTES_PER_SEC),
+        ..Default::default()
+    });
+    let proxy = SliceProxy::new(config);
+    assert!(proxy.origin_limiter().is_some());
+
+    let mut ctx = SliceContext::new();
+    ctx.set_metadata(FileMetadata::new(file_size, true));
+    ctx.set_slices(slices_for(file_size));
+    ctx.enable_slicing();
+
+    let url = format!("{}/big.bin", origin.uri());
+    let start = Instant::now();
+    let (_, _, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+    let elapsed = start.elapsed();
+
+    assert_eq!(body.iter().map(|b| b.len() as u64).sum::<u64>(), file_size);
+    assert_rate_near_cap(file_size, elapsed);
+
+    let stats = proxy.metrics().get_stats();
+    assert!(stats.origin_throttle_wait_us > 0);
+    assert!(stats.origin_bandwidth_utilization > 0);
+
+    // Cache hits are served without waiting on the limiter
+    let mut cached = slices_for(file_size);
+    for slice in &mut cached {
+        slice.cached = true;
+    }
+    ctx.set_slices(cached);
+    let start = Instant::now();
+    let (_, _, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(body.iter().map(|b| b.len() as u64).sum::<u64>(), file_size);
+    assert!(start.elapsed() < Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn test_concurrent_fills_share_budget() {
+    let file_size = 6 * SLICE_SIZE;
+    let origin = start_range_origin(file_size).await;
+    let limiter = Arc::new(OriginRateLimiter::new(Some(BYTES_PER_SEC), None));
+
+    let start = Instant::now();
+    let mut handles = Vec::new();
+    for name in ["a.bin", "b.bin"] {
+        let manager = SubrequestManager::new(4, 0).with_rate_limiter(limiter.clone());
+        let url = format!("{}/{}", origin.uri(), name);
+        handles.push(tokio::spawn(async move {
+            let results = manager.fetch_slices(slices_for(file_size), &url).await.unwrap();
+            assert_eq!(results.len(), 6);
+            start.elapsed()
+        }));
+    }
+
+    let mut finished = Vec::new();
+    for handle in handles {
+        finished.push(handle.await.unwrap());
+    }
+    let total = start.elapsed();
+
+    assert_rate_near_cap(2 * file_size, total);
+
+    // Neither request is starved: both finish close to the end of the fill
+    for elapsed in finished {
+        assert!(
+            elapsed.as_secs_f64() >= total.as_secs_f64() * 0.85,
+            "request finished after {:?} of {:?}",
+            elapsed,
+            total
+        );
+    }
+}
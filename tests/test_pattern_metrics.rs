@@ -0,0 +1,85 @@
+//! Integration tests for per-pattern metrics
+//!
+//! Requests matching different pattern rules are counted under the rule's
+//! name, and nothing is labeled unless `pattern_labels` is enabled.
+
+mod common;
+
+use common::start_origin;
+use http::{HeaderMap, Method};
+use pingora_slice::{MetricsEndpointConfig, PatternRule, SliceConfig, SliceContext, SliceProxy};
+use std::sync::Arc;
+
+const FILE_SIZE: usize = 2048;
+
+fn config(pattern_labels: bool) -> SliceConfig {
+    SliceConfig {
+        slice_size: 1024,
+        pattern_rules: vec![
+            PatternRule {
+                pattern: "*/videos/*".to_string(),
+                name: Some("video".to_string()),
+                cache_ttl: None,
+            },
+            PatternRule {
+                pattern: "*.iso".to_string(),
+                name: None,
+                cache_ttl: None,
+            },
+        ],
+        metrics_endpoint: Some(MetricsEndpointConfig {
+            pattern_labels,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+async fn fetch(proxy: &SliceProxy, url: &str) {
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    let (_, _, body) = proxy.handle_slice_request(url, &ctx).await.unwrap();
+    assert_eq!(body.concat().len(), FILE_SIZE);
+}
+
+#[tokio::test]
+async fn test_requests_counted_per_matched_rule() {
+    let origin = start_origin(FILE_SIZE).await;
+    let proxy = SliceProxy::new(Arc::new(config(true)));
+
+    fetch(&proxy, &format!("{}/videos/a.mp4", origin.uri())).await;
+    fetch(&proxy, &format!("{}/videos/a.mp4", origin.uri())).await;
+    fetch(&proxy, &format!("{}/images/disk.iso", origin.uri())).await;
+
+    let stats = proxy.metrics().get_stats();
+    assert_eq!(stats.pattern_stats.len(), 2);
+
+    let video = stats.pattern_stats["video"];
+    assert_eq!(video.requests, 2);
+    assert_eq!(video.cache_misses, 2);
+    assert_eq!(video.cache_hits, 2);
+    assert_eq!(video.bytes_to_client, 2 * FILE_SIZE as u64);
+
+    // Unnamed rules are labeled with their pattern, never the URL
+    let iso = stats.pattern_stats["*.iso"];
+    assert_eq!(iso.requests, 1);
+    assert_eq!(iso.cache_misses, 2);
+    assert_eq!(iso.cache_hits, 0);
+    assert_eq!(iso.bytes_to_client, FILE_SIZE as u64);
+}
+
+#[tokio::test]
+async fn test_pattern_labels_disabled_by_default() {
+    let origin = start_origin(FILE_SIZE).await;
+    let proxy = SliceProxy::new(Arc::new(config(false)));
+
+    fetch(&proxy, &format!("{}/videos/a.mp4", origin.uri())).await;
+
+    let stats = proxy.metrics().get_stats();
+    assert_eq!(stats.total_requests, 1);
+    assert!(stats.pattern_stats.is_empty());
+}
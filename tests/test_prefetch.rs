@@ -0,0 +1,122 @@
+//! Integration tests for prefetching slices ahead of sequential downloads
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, HeaderValue, Method};
+use pingora_slice::{ByteRange, SliceConfig, SliceContext, SliceProxy};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::MockServer;
+
+const SLICE_SIZE: u64 = 1024;
+const FILE_SIZE: u64 = SLICE_SIZE * 8;
+
+/// Start an origin that counts range requests
+async fn start_origin(requests: Arc<AtomicUsize>) -> MockServer {
+    RangeOrigin::from_fn(FILE_SIZE as usize, |i| (i as u64 / SLICE_SIZE) as u8)
+        .on_get(move |_| {
+            requests.fetch_add(1, Ordering::SeqCst);
+        })
+        .start()
+        .await
+}
+
+fn slice_range(index: u64) -> ByteRange {
+    ByteRange::new(index * SLICE_SIZE, (index + 1) * SLICE_SIZE - 1).unwrap()
+}
+
+/// Serve `range` of the file through `proxy`, returning the request context
+async fn get_range(proxy: &SliceProxy, url: &str, range: &str) -> SliceContext {
+    let mut headers = HeaderMap::new();
+    headers.insert("range", HeaderValue::from_str(range).unwrap());
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, url, &headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    proxy.handle_slice_request(url, &ctx).await.unwrap();
+    ctx
+}
+
+async fn wait_for_prefetch(proxy: &SliceProxy, slices: u64) {
+    let mut waited = Duration::ZERO;
+    while proxy.metrics().get_stats().prefetched_slices < slices {
+        assert!(waited < Duration::from_secs(5), "prefetch did not finish");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        waited += Duration::from_millis(20);
+    }
+}
+
+fn prefetching_proxy() -> SliceProxy {
+    SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: SLICE_SIZE as usize,
+        prefetch_slices: 3,
+        ..Default::default()
+    }))
+}
+
+#[tokio::test]
+async fn test_slices_prefetched_ahead_of_sequential_download() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let origin = start_origin(requests.clone()).await;
+    let proxy = prefetching_proxy();
+    let url = format!("{}/video.mp4", origin.uri());
+
+    get_range(&proxy, &url, "bytes=0-1023").await;
+    wait_for_prefetch(&proxy, 3).await;
+
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+    let cache = proxy.cache_arc();
+    for i in 1..=3 {
+        assert!(cache.lookup(&url, &slice_range(i)).await.unwrap().is_some());
+    }
+    assert!(cache.lookup(&url, &slice_range(4)).await.unwrap().is_none());
+
+    // The next range is served from cache without going to the origin
+    let ctx = get_range(&proxy, &url, "bytes=1024-3071").await;
+    assert_eq!(ctx.uncached_slice_count(), 0);
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn test_no_prefetch_past_start_or_when_disabled() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let origin = start_origin(requests.clone()).await;
+
+    // Ranges that do not start the file are not sequential downloads
+    let proxy = prefetching_proxy();
+    get_range(&proxy, &format!("{}/a.bin", origin.uri()), "bytes=2048-3071").await;
+
+    let disabled = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: SLICE_SIZE as usize,
+        ..Default::default()
+    }));
+    get_range(&disabled, &format!("{}/b.bin", origin.uri()), "bytes=0-1023").await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+    assert_eq!(proxy.metrics().get_stats().prefetched_slices, 0);
+}
+
+#[tokio::test]
+async fn test_cached_slices_are_not_prefetched() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let origin = start_origin(requests.clone()).await;
+    let proxy = prefetching_proxy();
+    let url = format!("{}/video.mp4", origin.uri());
+    proxy
+        .cache_arc()
+        .store(&url, &slice_range(2), vec![2u8; SLICE_SIZE as usize].into())
+        .unwrap();
+
+    get_range(&proxy, &url, "bytes=0-1023").await;
+    wait_for_prefetch(&proxy, 2).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Only slices 1 and 3 are fetched ahead
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+    assert_eq!(proxy.metrics().get_stats().prefetched_slices, 2);
+}
@@ -0,0 +1,1136 @@
+//! Integration tests for the standalone proxy server
+//!
+//! The server is bound to an ephemeral port in front of a mock origin and
+//! queried over HTTP like a real client would.
+
+mod common;
+
+use common::{requested_range, RangeOrigin};
+use pingora_slice::config::PurgeConfig;
+use pingora_slice::{
+    CacheAdmissionConfig, CacheBypassConfig, CacheMode, CacheModeRule, PatternRule, SliceConfig,
+    SliceProxy, SliceServer,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+const FILE_SIZE: usize = 4096;
+
+fn file_byte(i: usize) -> u8 {
+    (i % 251) as u8
+}
+
+async fn start_origin() -> MockServer {
+    let server = RangeOrigin::from_fn(FILE_SIZE, file_byte).start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Start a server in front of `origin`
+///
+/// # Returns
+/// The server's base URL, the proxy it runs and the shutdown trigger
+async fn start_server(origin: &MockServer) -> (String, SliceProxy, oneshot::Sender<()>) {
+    start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        ..Default::default()
+    })
+    .await
+}
+
+async fn start_server_with(config: SliceConfig) -> (String, SliceProxy, oneshot::Sender<()>) {
+    let proxy = SliceProxy::new(Arc::new(config));
+    let server = SliceServer::bind(proxy.clone(), "127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", server.local_addr().unwrap());
+
+    let (stop, stopped) = oneshot::channel();
+    tokio::spawn(server.serve_until(async {
+        let _ = stopped.await;
+    }));
+    (base, proxy, stop)
+}
+
+#[tokio::test]
+async fn test_proxies_and_caches_sliced_file() {
+    let origin = start_origin().await;
+    let (base, proxy, _stop) = start_server(&origin).await;
+    let client = reqwest::Client::new();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap(), expected);
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 4);
+
+    // The second request is served from cache: no more GETs reach the origin
+    let response = client
+        .get(format!("{}/video.mp4", base))
+        .header("range", "bytes=1000-1099")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.bytes().await.unwrap(), expected[1000..1100]);
+
+    let gets = origin
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|r| r.method == wiremock::http::Method::Get)
+        .count();
+    assert_eq!(gets, 4);
+}
+
+#[tokio::test]
+async fn test_streams_slices_before_later_ones_arrive() {
+    // The last slice takes far longer than the others
+    let origin = RangeOrigin::from_fn(FILE_SIZE, file_byte)
+        .delay(|start| if start + 1024 >= FILE_SIZE { Duration::from_millis(1500) } else { Duration::ZERO })
+        .start()
+        .await;
+    let (base, _proxy, _stop) = start_server(&origin).await;
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+
+    let start = Instant::now();
+    let mut response = reqwest::get(format!("{}/video.mp4", base)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let first = response.chunk().await.unwrap().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(1000), "first bytes waited for the last slice");
+    assert_eq!(first, expected[..first.len()]);
+
+    let mut body = first.to_vec();
+    while let Some(chunk) = response.chunk().await.unwrap() {
+        body.extend_from_slice(&chunk);
+    }
+    assert!(start.elapsed() >= Duration::from_millis(1500));
+    assert_eq!(body, expected);
+}
+
+#[tokio::test]
+async fn test_forwards_unsliced_requests() {
+    let origin = start_origin().await;
+    let (base, _proxy, _stop) = start_server(&origin).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/upload", base))
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.text().await.unwrap(), "created");
+
+    let requests = origin.received_requests().await.unwrap();
+    let post = requests.iter().find(|r| r.method == wiremock::http::Method::Post).unwrap();
+    assert_eq!(post.body, b"payload");
+}
+
+#[tokio::test]
+async fn test_cache_status_and_age_headers() {
+    let origin = start_origin().await;
+    let rule = |pattern: &str, cache_ttl| PatternRule {
+        pattern: pattern.to_string(),
+        name: None,
+        cache_ttl,
+    };
+    let (base, _proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        pattern_rules: vec![rule("*/live/*", Some(1)), rule("*", None)],
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let get = |path: &str| client.get(format!("{}{}", base, path)).send();
+    let header = |response: &reqwest::Response, name: &str| {
+        response.headers().get(name).map(|v| v.to_str().unwrap().to_string())
+    };
+
+    for path in ["/video.mp4", "/live/a.ts"] {
+        let response = get(path).await.unwrap();
+        assert_eq!(header(&response, "x-cache-status").as_deref(), Some("MISS"));
+        assert_eq!(header(&response, "age"), None);
+    }
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    // Age counts from when the slices were stored
+    let response = get("/video.mp4").await.unwrap();
+    assert_eq!(header(&response, "x-cache-status").as_deref(), Some("HIT"));
+    assert_eq!(header(&response, "age").as_deref(), Some("1"));
+
+    // Slices past their TTL are refetched, then served fresh
+    let response = get("/live/a.ts").await.unwrap();
+    assert_eq!(header(&response, "x-cache-status").as_deref(), Some("EXPIRED"));
+    assert_eq!(header(&response, "age"), None);
+    // Refetched slices are cached as the body streams
+    response.bytes().await.unwrap();
+    let response = get("/live/a.ts").await.unwrap();
+    assert_eq!(header(&response, "x-cache-status").as_deref(), Some("HIT"));
+    assert_eq!(header(&response, "age").as_deref(), Some("0"));
+
+    let response = client.post(format!("{}/upload", base)).send().await.unwrap();
+    assert_eq!(header(&response, "x-cache-status").as_deref(), Some("BYPASS"));
+}
+
+#[tokio::test]
+async fn test_serves_stale_slices_while_origin_fails() {
+    let origin = start_origin().await;
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        max_retries: 0,
+        cache_ttl: 1,
+        metadata_cache_ttl: Some(60),
+        stale_if_error_secs: 60,
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let get = |path: &str| client.get(format!("{}{}", base, path)).send();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+
+    let response = get("/video.mp4").await.unwrap();
+    assert_eq!(response.bytes().await.unwrap(), expected);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    origin.reset().await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&origin)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&origin)
+        .await;
+
+    // The expired slices stand in when refetching them fails, and then
+    // when the metadata probe fails too
+    for _ in 0..2 {
+        let response = get("/video.mp4").await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["x-cache-status"], "STALE");
+        assert!(response.headers()["age"].to_str().unwrap().parse::<u64>().unwrap() >= 1);
+        assert_eq!(response.bytes().await.unwrap(), expected);
+    }
+    let requests = origin.received_requests().await.unwrap();
+    assert!(requests.iter().any(|r| r.method == wiremock::http::Method::Get));
+    assert!(requests.iter().any(|r| r.method == wiremock::http::Method::Head));
+
+    // Nothing to serve stale for a file never cached
+    let response = get("/other.mp4").await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 4);
+}
+
+#[tokio::test]
+async fn test_head_answered_from_cached_metadata() {
+    let origin = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", "2048")
+                .insert_header("Content-Type", "video/mp4")
+                .insert_header("ETag", "\"v1\"")
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .expect(2)
+        .mount(&origin)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(|req: &Request| {
+            let (start, end) = requested_range(req);
+            ResponseTemplate::new(206)
+                .insert_header("Content-Range", format!("bytes {}-{}/2048", start, end).as_str())
+                .set_body_bytes(vec![0u8; end - start + 1])
+        })
+        .expect(2)
+        .mount(&origin)
+        .await;
+    let (base, _proxy, _stop) = start_server(&origin).await;
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.bytes().await.unwrap().len(), 2048);
+
+    // The cached metadata answers HEAD without another origin request
+    let response = client.head(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let headers = response.headers();
+    assert_eq!(headers["content-length"], "2048");
+    assert_eq!(headers["content-type"], "video/mp4");
+    assert_eq!(headers["etag"], "\"v1\"");
+    assert_eq!(headers["x-cache-status"], "HIT");
+    assert_eq!(headers["age"], "0");
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    let response = client
+        .head(format!("{}/video.mp4", base))
+        .header("range", "bytes=100-199")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.headers()["content-range"], "bytes 100-199/2048");
+
+    let response = client
+        .head(format!("{}/video.mp4", base))
+        .header("if-none-match", "\"v1\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 304);
+
+    // Uncached metadata is left to the origin
+    let response = client.head(format!("{}/other.mp4", base)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-length"], "2048");
+    origin.verify().await;
+}
+
+#[tokio::test]
+async fn test_falls_back_to_whole_object_when_slices_fail() {
+    let origin = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", FILE_SIZE.to_string().as_str())
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&origin)
+        .await;
+    // Range requests always fail; a plain GET returns the whole file
+    Mock::given(method("GET"))
+        .respond_with(|req: &Request| match req.headers.get(&"range".into()) {
+            Some(_) => ResponseTemplate::new(500),
+            None => ResponseTemplate::new(200).set_body_bytes((0..FILE_SIZE).map(file_byte).collect::<Vec<_>>()),
+        })
+        .mount(&origin)
+        .await;
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        whole_object_fallback_after: Some(2),
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap(), expected);
+    assert_eq!(proxy.metrics().get_stats().whole_object_fallbacks, 1);
+
+    // The object was sliced locally and cached
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 4);
+    let response = client
+        .get(format!("{}/video.mp4", base))
+        .header("range", "bytes=1000-1099")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.bytes().await.unwrap(), expected[1000..1100]);
+
+    let requests = origin.received_requests().await.unwrap();
+    let gets: Vec<_> = requests.iter().filter(|r| r.method == wiremock::http::Method::Get).collect();
+    assert_eq!(gets.iter().filter(|r| !r.headers.contains_key(&"range".into())).count(), 1);
+    assert!(gets.iter().filter(|r| r.headers.contains_key(&"range".into())).count() >= 2);
+}
+
+#[tokio::test]
+async fn test_whole_object_fallback_is_capped() {
+    let origin = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", FILE_SIZE.to_string().as_str())
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&origin)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(|req: &Request| match req.headers.get(&"range".into()) {
+            Some(_) => ResponseTemplate::new(500),
+            None => ResponseTemplate::new(200).set_body_bytes((0..FILE_SIZE).map(file_byte).collect::<Vec<_>>()),
+        })
+        .mount(&origin)
+        .await;
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        whole_object_fallback_after: Some(2),
+        whole_object_fallback_max_bytes: Some(2048),
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+
+    // A range within the first slice only reads that far
+    let response = client
+        .get(format!("{}/video.mp4", base))
+        .header("range", "bytes=0-99")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.bytes().await.unwrap(), expected[..100]);
+    assert_eq!(proxy.metrics().get_stats().whole_object_fallbacks, 1);
+
+    // The whole file is over the cap, so the fallback is refused
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.status(), 502);
+    assert_eq!(proxy.metrics().get_stats().whole_object_fallbacks, 1);
+
+    let requests = origin.received_requests().await.unwrap();
+    let plain_gets = requests
+        .iter()
+        .filter(|r| r.method == wiremock::http::Method::Get && !r.headers.contains_key(&"range".into()))
+        .count();
+    assert_eq!(plain_gets, 1);
+}
+
+#[tokio::test]
+async fn test_cache_mode_rules() {
+    let origin = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", FILE_SIZE.to_string().as_str())
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&origin)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(|req: &Request| match req.headers.get(&"range".into()) {
+            Some(range) => {
+                let (start, end) = range.last().as_str().trim_start_matches("bytes=").split_once('-').unwrap();
+                let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+                ResponseTemplate::new(206)
+                    .insert_header("Content-Range", format!("bytes {}-{}/{}", start, end, FILE_SIZE).as_str())
+                    .set_body_bytes((start..=end).map(file_byte).collect::<Vec<_>>())
+            }
+            None => ResponseTemplate::new(200).set_body_bytes((0..FILE_SIZE).map(file_byte).collect::<Vec<_>>()),
+        })
+        .mount(&origin)
+        .await;
+    let rule = |pattern: &str, mode| CacheModeRule {
+        pattern: pattern.to_string(),
+        mode,
+    };
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        slice_patterns: vec!["*.mp4".to_string()],
+        cache_mode_rules: vec![
+            rule("*/reports/*", CacheMode::Whole),
+            rule("*/live/*", CacheMode::Bypass),
+            rule("*/video.mp4", CacheMode::Slice),
+        ],
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+    let header = |response: &reqwest::Response, name: &str| {
+        response.headers().get(name).map(|v| v.to_str().unwrap().to_string())
+    };
+
+    for (path, cache_status, x_cache) in [
+        ("/video.mp4", "MISS", None),
+        ("/video.mp4", "HIT", None),
+        ("/reports/q3.json", "MISS", None),
+        ("/reports/q3.json", "HIT", None),
+        ("/live/feed.mp4", "BYPASS", Some("BYPASS")),
+        ("/live/feed.mp4", "BYPASS", Some("BYPASS")),
+        // No rule and no slice pattern: proxied as before
+        ("/other.bin", "BYPASS", None),
+    ] {
+        let response = client.get(format!("{}{}", base, path)).send().await.unwrap();
+        assert_eq!(response.status(), 200, "{}", path);
+        assert_eq!(header(&response, "x-cache-status").as_deref(), Some(cache_status), "{}", path);
+        assert_eq!(header(&response, "x-cache").as_deref(), x_cache, "{}", path);
+        assert_eq!(response.bytes().await.unwrap(), expected, "{}", path);
+    }
+    // Slices of /video.mp4 and /reports/q3.json
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 8);
+
+    let requests = origin.received_requests().await.unwrap();
+    let gets = |path: &str, ranged: bool| {
+        requests
+            .iter()
+            .filter(|r| r.method == wiremock::http::Method::Get && r.url.path() == path)
+            .filter(|r| r.headers.contains_key(&"range".into()) == ranged)
+            .count()
+    };
+    assert_eq!((gets("/video.mp4", true), gets("/video.mp4", false)), (4, 0));
+    // Whole-object mode never sends a range request
+    assert_eq!((gets("/reports/q3.json", true), gets("/reports/q3.json", false)), (0, 1));
+    // Bypass skips the metadata probe and the cache
+    assert_eq!(gets("/live/feed.mp4", false), 2);
+    assert!(!requests.iter().any(|r| r.method == wiremock::http::Method::Head && r.url.path() == "/live/feed.mp4"));
+}
+
+#[tokio::test]
+async fn test_responses_with_set_cookie_are_not_cached() {
+    let origin = RangeOrigin::from_fn(FILE_SIZE, file_byte)
+        .header("Set-Cookie", "session=alice")
+        .start()
+        .await;
+    let (base, proxy, _stop) = start_server(&origin).await;
+    let client = reqwest::Client::new();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+
+    for _ in 0..2 {
+        let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["x-cache-status"], "MISS");
+        assert_eq!(response.headers()["x-cache"], "SKIP-PRIVATE");
+        assert!(response.headers().get("set-cookie").is_none());
+        assert_eq!(response.bytes().await.unwrap(), expected);
+    }
+
+    // Nothing was cached, so the second request went back to the origin
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 0);
+    assert_eq!(proxy.metrics().get_stats().private_skips, 2);
+    let gets = origin
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|r| r.method == wiremock::http::Method::Get)
+        .count();
+    assert_eq!(gets, 8);
+}
+
+#[tokio::test]
+async fn test_cache_admission_by_status() {
+    let origin = start_origin().await;
+    // Slices come back as 206, which this configuration does not admit
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        cache_admission: CacheAdmissionConfig {
+            statuses: vec![200],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+
+    for _ in 0..2 {
+        let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["x-cache"], "SKIP-STATUS");
+        assert_eq!(response.bytes().await.unwrap(), expected);
+    }
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 0);
+    assert_eq!(proxy.metrics().get_stats().private_skips, 0);
+    let gets = origin
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|r| r.method == wiremock::http::Method::Get)
+        .count();
+    assert_eq!(gets, 8);
+}
+
+#[tokio::test]
+async fn test_redirects_pass_through_uncached() {
+    let origin = MockServer::start().await;
+    Mock::given(wiremock::matchers::path("/moved.mp4"))
+        .respond_with(ResponseTemplate::new(301).insert_header("Location", "/video.mp4"))
+        .mount(&origin)
+        .await;
+    let (base, proxy, _stop) = start_server(&origin).await;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    for _ in 0..2 {
+        let response = client.get(format!("{}/moved.mp4", base)).send().await.unwrap();
+        assert_eq!(response.status(), 301);
+        assert_eq!(response.headers()["location"], "/video.mp4");
+    }
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 0);
+    let gets = origin
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|r| r.method == wiremock::http::Method::Get)
+        .count();
+    assert_eq!(gets, 2);
+}
+
+#[tokio::test]
+async fn test_unsatisfiable_and_malformed_ranges() {
+    let origin = start_origin().await;
+    let (base, _proxy, _stop) = start_server(&origin).await;
+    let client = reqwest::Client::new();
+    let get = |range: &'static str| client.get(format!("{}/video.mp4", base)).header("range", range).send();
+
+    let response = get("bytes=5000-5100").await.unwrap();
+    assert_eq!(response.status(), 416);
+    assert_eq!(response.headers()["content-range"], "bytes */4096");
+
+    // A range running past the end is cut to the last byte
+    let response = get("bytes=4000-9999").await.unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.headers()["content-range"], "bytes 4000-4095/4096");
+    assert_eq!(response.bytes().await.unwrap().len(), 96);
+
+    let response = get("bytes=100-50").await.unwrap();
+    assert_eq!(response.status(), 400);
+
+    // No byte of an empty file can be asked for
+    let empty = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", "0")
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&empty)
+        .await;
+    let (base, _proxy, _stop) = start_server(&empty).await;
+    let response = client
+        .get(format!("{}/empty.bin", base))
+        .header("range", "bytes=0-10")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 416);
+    assert_eq!(response.headers()["content-range"], "bytes */0");
+}
+
+#[tokio::test]
+async fn test_stops_accepting_after_shutdown() {
+    let origin = start_origin().await;
+    let (base, _proxy, stop) = start_server(&origin).await;
+
+    stop.send(()).unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(reqwest::get(format!("{}/video.mp4", base)).await.is_err());
+}
+
+/// Start an origin answering every request with a chunked body
+///
+/// The body is 100 chunks of 10 bytes written at once, then, after
+/// `pause`, a final `tail` chunk.
+async fn start_trickling_origin(pause: Duration) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).await;
+                let mut head = b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n".to_vec();
+                for i in 0..100u8 {
+                    head.extend_from_slice(b"a\r\n");
+                    head.extend_from_slice(&[i; 10]);
+                    head.extend_from_slice(b"\r\n");
+                }
+                stream.write_all(&head).await.unwrap();
+                tokio::time::sleep(pause).await;
+                let _ = stream.write_all(b"4\r\ntail\r\n0\r\n\r\n").await;
+            });
+        }
+    });
+    address
+}
+
+#[tokio::test]
+async fn test_buffers_small_upstream_chunks() {
+    let pause = Duration::from_millis(300);
+    let upstream_address = start_trickling_origin(pause).await;
+    let (base, _proxy, _stop) = start_server_with(SliceConfig {
+        upstream_address,
+        response_buffer_size: Some(64 * 1024),
+        response_buffer_flush_ms: 10,
+        ..Default::default()
+    })
+    .await;
+
+    let start = Instant::now();
+    let mut response = reqwest::Client::new()
+        .post(format!("{}/stream", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // The hundred small chunks are joined, and the flush timer sends them
+    // without waiting for the rest of the body
+    let first = response.chunk().await.unwrap().unwrap();
+    assert!(start.elapsed() < pause);
+    let expected: Vec<u8> = (0..100u8).flat_map(|i| [i; 10]).collect();
+    assert_eq!(first, expected);
+
+    let mut rest = Vec::new();
+    while let Some(chunk) = response.chunk().await.unwrap() {
+        rest.extend_from_slice(&chunk);
+    }
+    assert_eq!(rest, b"tail");
+}
+
+#[tokio::test]
+async fn test_request_timeout_answers_504() {
+    // The origin answers the probe at once but takes far too long for slices
+    let origin = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", FILE_SIZE.to_string().as_str())
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&origin)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(206).set_delay(Duration::from_secs(5)))
+        .mount(&origin)
+        .await;
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        request_timeout_secs: Some(1),
+        // No idle origin connections, so every task ends with the request
+        origin_pool_size: 0,
+        ..Default::default()
+    })
+    .await;
+    let tasks_before = tokio::runtime::Handle::current().metrics().num_alive_tasks();
+
+    let client = reqwest::Client::new();
+    let start = Instant::now();
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert!(start.elapsed() < Duration::from_millis(1500));
+    assert_eq!(response.status(), 504);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let body: serde_json::Value = serde_json::from_slice(&response.bytes().await.unwrap()).unwrap();
+    assert_eq!(body["error"], "deadline_exceeded");
+    assert_eq!(proxy.metrics().get_stats().deadline_aborts, 1);
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 0);
+
+    // The slice fetches were cancelled, not left running in the background
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(tokio::runtime::Handle::current().metrics().num_alive_tasks(), tasks_before);
+}
+
+#[tokio::test]
+async fn test_rejects_oversized_uri_and_headers() {
+    let origin = start_origin().await;
+    let (base, _proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        max_uri_bytes: Some(64),
+        max_request_header_bytes: Some(512),
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/video.mp4?pad={}", base, "a".repeat(64)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 414);
+
+    let response = client
+        .get(format!("{}/video.mp4", base))
+        .header("x-padding", "a".repeat(512))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 431);
+
+    // Neither reached the origin
+    assert!(origin.received_requests().await.unwrap().is_empty());
+
+    let response = client
+        .get(format!("{}/video.mp4", base))
+        .header("x-padding", "a".repeat(64))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap().len(), FILE_SIZE);
+}
+
+#[tokio::test]
+async fn test_cache_bypass_header_forces_origin_fetch() {
+    let origin = start_origin().await;
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        cache_bypass: Some(CacheBypassConfig {
+            require_token: true,
+            ..Default::default()
+        }),
+        purge: Some(PurgeConfig {
+            enabled: true,
+            auth_token: Some("secret".to_string()),
+            enable_metrics: false,
+        }),
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let expected: Vec<u8> = (0..FILE_SIZE).map(file_byte).collect();
+    let gets = || async {
+        origin
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|r| r.method == wiremock::http::Method::Get)
+            .count()
+    };
+
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.bytes().await.unwrap(), expected);
+    assert_eq!(gets().await, 4);
+
+    // Without the token the header is ignored
+    let response = client
+        .get(format!("{}/video.mp4", base))
+        .header("x-cache-bypass", "1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-cache-status"], "HIT");
+    assert!(response.headers().get("x-cache").is_none());
+    assert_eq!(gets().await, 4);
+
+    let response = client
+        .get(format!("{}/video.mp4", base))
+        .header("x-cache-bypass", "1")
+        .header("x-purge-token", "secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-cache"], "BYPASS");
+    assert_eq!(response.headers()["x-cache-status"], "BYPASS");
+    assert_eq!(response.bytes().await.unwrap(), expected);
+    assert_eq!(gets().await, 8);
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 4);
+
+    // The refetched slices were cached again
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.headers()["x-cache-status"], "HIT");
+    assert_eq!(gets().await, 8);
+}
+
+/// Read an HTTP message head, up to and including the blank line
+async fn read_head(stream: &mut tokio::net::TcpStream) -> String {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(stream.read_u8().await.unwrap());
+    }
+    String::from_utf8(head).unwrap().to_lowercase()
+}
+
+/// Start an origin for uploads sent with `Expect: 100-continue`
+///
+/// When `accept` is set it answers `100 Continue`, reads the body and
+/// answers 201; otherwise it answers 413 without reading the body.
+///
+/// # Returns
+/// The origin's address and the request heads and bodies it received
+async fn start_continue_origin(accept: bool) -> (String, tokio::sync::mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let head = read_head(&mut stream).await;
+                if !accept {
+                    stream
+                        .write_all(b"HTTP/1.1 413 Payload Too Large\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                        .await
+                        .unwrap();
+                    let _ = tx.send((head, Vec::new()));
+                    return;
+                }
+                stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await.unwrap();
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                let mut body = vec![0u8; length];
+                stream.read_exact(&mut body).await.unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 201 Created\r\ncontent-length: 6\r\n\r\nstored")
+                    .await
+                    .unwrap();
+                let _ = tx.send((head, body));
+            });
+        }
+    });
+    (address, rx)
+}
+
+#[tokio::test]
+async fn test_relays_expect_continue_uploads() {
+    let (upstream_address, mut received) = start_continue_origin(true).await;
+    let (base, _proxy, _stop) = start_server_with(SliceConfig {
+        upstream_address,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = tokio::net::TcpStream::connect(base.trim_start_matches("http://")).await.unwrap();
+    client
+        .write_all(b"PUT /upload HTTP/1.1\r\nhost: example.com\r\ncontent-length: 7\r\nexpect: 100-continue\r\n\r\n")
+        .await
+        .unwrap();
+
+    // The body is only sent once the origin's 100 Continue is relayed,
+    // well before the proxy would stop waiting for it
+    let start = Instant::now();
+    let interim = read_head(&mut client).await;
+    assert!(interim.starts_with("http/1.1 100 continue"), "{}", interim);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    client.write_all(b"payload").await.unwrap();
+
+    let head = read_head(&mut client).await;
+    assert!(head.starts_with("http/1.1 201"), "{}", head);
+    let mut body = [0u8; 6];
+    client.read_exact(&mut body).await.unwrap();
+    assert_eq!(&body, b"stored");
+
+    let (origin_head, origin_body) = received.recv().await.unwrap();
+    assert!(origin_head.starts_with("put /upload http/1.1"), "{}", origin_head);
+    assert!(origin_head.contains("expect: 100-continue"), "{}", origin_head);
+    assert_eq!(origin_body, b"payload");
+}
+
+#[tokio::test]
+async fn test_relays_final_status_instead_of_continue() {
+    let (upstream_address, mut received) = start_continue_origin(false).await;
+    let (base, _proxy, _stop) = start_server_with(SliceConfig {
+        upstream_address,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = tokio::net::TcpStream::connect(base.trim_start_matches("http://")).await.unwrap();
+    client
+        .write_all(b"POST /upload HTTP/1.1\r\nhost: example.com\r\ncontent-length: 1048576\r\nexpect: 100-continue\r\n\r\n")
+        .await
+        .unwrap();
+
+    // The rejection arrives without a 100 Continue and before any body
+    let head = tokio::time::timeout(Duration::from_secs(5), read_head(&mut client))
+        .await
+        .unwrap();
+    assert!(head.starts_with("http/1.1 413"), "{}", head);
+    let (_, origin_body) = received.recv().await.unwrap();
+    assert!(origin_body.is_empty());
+}
+
+#[tokio::test]
+async fn test_streams_upload_bodies_to_origin() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream_address = listener.local_addr().unwrap().to_string();
+    let (first_tx, first_rx) = oneshot::channel();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_head(&mut stream).await;
+        let mut first = [0u8; 4];
+        stream.read_exact(&mut first).await.unwrap();
+        let _ = first_tx.send(first);
+        let mut rest = [0u8; 4];
+        stream.read_exact(&mut rest).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 201 Created\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+    });
+    let (base, _proxy, _stop) = start_server_with(SliceConfig {
+        upstream_address,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = tokio::net::TcpStream::connect(base.trim_start_matches("http://")).await.unwrap();
+    client
+        .write_all(b"PUT /upload HTTP/1.1\r\nhost: example.com\r\ncontent-length: 8\r\n\r\nabcd")
+        .await
+        .unwrap();
+
+    // The origin sees the start of the body before the client sends the rest
+    let first = tokio::time::timeout(Duration::from_secs(5), first_rx).await.unwrap().unwrap();
+    assert_eq!(&first, b"abcd");
+    client.write_all(b"efgh").await.unwrap();
+    let head = read_head(&mut client).await;
+    assert!(head.starts_with("http/1.1 201"), "{}", head);
+}
+
+#[tokio::test]
+async fn test_reload_applies_to_new_requests() {
+    let origin = start_origin().await;
+    // Reloads are validated, so unlike the other tests this one needs a
+    // valid slice size
+    let config = SliceConfig {
+        slice_size: 64 * 1024,
+        upstream_address: origin.address().to_string(),
+        ..Default::default()
+    };
+    let proxy = SliceProxy::new(Arc::new(config.clone()));
+    let server = SliceServer::bind(proxy.clone(), "127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", server.local_addr().unwrap());
+    let reload = server.reload_handle();
+    let (_stop, stopped) = oneshot::channel::<()>();
+    tokio::spawn(server.serve_until(async {
+        let _ = stopped.await;
+    }));
+    let client = reqwest::Client::new();
+    let long_uri = format!("{}/video.mp4?pad={}", base, "a".repeat(64));
+
+    let response = client.get(&long_uri).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap().len(), FILE_SIZE);
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.headers()["x-cache-status"], "MISS");
+
+    let ignored = reload
+        .reload(SliceConfig {
+            max_uri_bytes: Some(64),
+            slice_size: 128 * 1024,
+            ..config.clone()
+        })
+        .unwrap();
+    assert_eq!(ignored, ["slice_size"]);
+    assert_eq!(reload.proxy().config().max_uri_bytes, Some(64));
+    assert_eq!(reload.proxy().config().slice_size, 64 * 1024);
+    // The running proxy's configuration is untouched
+    assert_eq!(proxy.config().max_uri_bytes, None);
+
+    let response = client.get(&long_uri).send().await.unwrap();
+    assert_eq!(response.status(), 414);
+
+    // The cache survives the reload
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.headers()["x-cache-status"], "HIT");
+    assert!(Arc::ptr_eq(&reload.proxy().cache_arc(), &proxy.cache_arc()));
+
+    // An invalid configuration leaves the running one in place
+    assert!(reload
+        .reload(SliceConfig {
+            max_concurrent_subrequests: 0,
+            ..config
+        })
+        .is_err());
+    assert_eq!(reload.proxy().config().max_uri_bytes, Some(64));
+}
+
+#[tokio::test]
+async fn test_serves_purge_and_admin_endpoints() {
+    let origin = start_origin().await;
+    let (base, proxy, _stop) = start_server_with(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        purge: Some(PurgeConfig {
+            enabled: true,
+            auth_token: Some("secret".to_string()),
+            enable_metrics: false,
+        }),
+        ..Default::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let purge = reqwest::Method::from_bytes(b"PURGE").unwrap();
+
+    client.get(format!("{}/video.mp4", base)).send().await.unwrap().bytes().await.unwrap();
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 4);
+
+    let response = client.get(format!("{}/admin/cache/keys", base)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client.request(purge.clone(), format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 4);
+
+    let response = client
+        .get(format!("{}/admin/cache/keys", base))
+        .bearer_auth("secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.text().await.unwrap().contains("/video.mp4"));
+
+    // The PURGE reaches the entries the proxy cached, keyed by the upstream
+    let response = client
+        .request(purge, format!("{}/video.mp4", base))
+        .bearer_auth("secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 0);
+    let response = client.get(format!("{}/video.mp4", base)).send().await.unwrap();
+    assert_eq!(response.headers()["x-cache-status"], "MISS");
+}
+
+#[tokio::test]
+async fn test_admin_paths_are_proxied_without_purge() {
+    let origin = start_origin().await;
+    let (base, _proxy, _stop) = start_server(&origin).await;
+    let response = reqwest::Client::new()
+        .post(format!("{}/admin/warm", base))
+        .body("[]")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+}
+
+#[tokio::test]
+async fn test_warmer_fills_the_proxy_cache() {
+    let origin = start_origin().await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        upstream_address: origin.address().to_string(),
+        ..Default::default()
+    }));
+    let server = SliceServer::bind(proxy.clone(), "127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", server.local_addr().unwrap());
+
+    let status = server
+        .warmer()
+        .warm(vec![format!("{}/video.mp4", origin.uri())])
+        .await
+        .unwrap();
+    assert_eq!(status.bytes_fetched, FILE_SIZE as u64);
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 4);
+
+    let (_stop, stopped) = oneshot::channel::<()>();
+    tokio::spawn(server.serve_until(async {
+        let _ = stopped.await;
+    }));
+    let response = reqwest::get(format!("{}/video.mp4", base)).await.unwrap();
+    assert_eq!(response.headers()["x-cache-status"], "HIT");
+}
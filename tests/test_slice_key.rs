@@ -0,0 +1,106 @@
+//! Integration tests for cache keys built through `SliceKey`
+//!
+//! The slice cache, the tiered cache, the metadata cache and PURGE must all
+//! name an object, its variants and its slices the same way.
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use pingora_slice::purge_handler::{PurgeCounts, PurgeHandler, PurgeResponse};
+use pingora_slice::{
+    ByteRange, CacheVariant, FileMetadata, MetadataCache, SliceCache, SliceKey, TieredCache,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SLICE_SIZE: u64 = 1024;
+
+fn ranges(file_size: u64) -> Vec<ByteRange> {
+    (0..file_size / SLICE_SIZE)
+        .map(|i| ByteRange::new(i * SLICE_SIZE, (i + 1) * SLICE_SIZE - 1).unwrap())
+        .collect()
+}
+
+fn variant(base_key: &str, encoding: &str) -> CacheVariant {
+    let mut headers = HeaderMap::new();
+    headers.insert("accept-encoding", HeaderValue::from_str(encoding).unwrap());
+    let vary = vec!["accept-encoding".to_string()];
+    CacheVariant::from_vary(base_key, &vary, &vary, &headers).unwrap()
+}
+
+#[tokio::test]
+async fn test_caches_agree_on_slice_keys() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let slices = SliceCache::new(Duration::from_secs(60));
+    let tiered = TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+        .await
+        .unwrap();
+    let range = ByteRange::new(0, 1023).unwrap();
+
+    for url in [
+        "http://example.com/a.bin",
+        "http://example.com/a|b.bin?x=1:slice:2",
+        "/relative:slice:0:1",
+    ] {
+        let base_key = SliceKey::new(url).object_key();
+        for key in [base_key.clone(), variant(&base_key, "gzip").key] {
+            let expected = SliceKey::parse(&key).unwrap().with_range(range);
+            assert_eq!(slices.generate_cache_key(&key, &range), expected.slice_key());
+            assert_eq!(tiered.generate_cache_key(&key, &range), expected.slice_key());
+            assert_eq!(SliceKey::parse(&expected.slice_key()).unwrap(), expected);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_purge_removes_every_variant() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let cache = Arc::new(
+        TieredCache::new(Duration::from_secs(60), 1024 * 1024, temp_dir.path())
+            .await
+            .unwrap(),
+    );
+    let metadata_cache = Arc::new(MetadataCache::new(Duration::from_secs(60), 100));
+    let handler = PurgeHandler::new(cache.clone())
+        .with_metadata_cache(metadata_cache.clone())
+        .with_slice_size(SLICE_SIZE as usize);
+
+    // Cached the way the proxy caches an object served with Vary
+    let base_key = SliceKey::new("http://example.com/video.mp4").object_key();
+    let keys = [
+        base_key.clone(),
+        variant(&base_key, "gzip").key,
+        variant(&base_key, "br").key,
+    ];
+    for key in &keys {
+        metadata_cache.insert(key, FileMetadata::new(2 * SLICE_SIZE, true));
+        for range in ranges(2 * SLICE_SIZE) {
+            cache.store(key, &range, Bytes::from(vec![1u8; SLICE_SIZE as usize])).unwrap();
+        }
+    }
+    // Another object sharing the URL as a prefix must survive
+    let other = SliceKey::new("http://example.com/video.mp4.bak").object_key();
+    let range = ByteRange::new(0, SLICE_SIZE - 1).unwrap();
+    cache.store(&other, &range, Bytes::from(vec![2u8; SLICE_SIZE as usize])).unwrap();
+
+    let req = Request::builder()
+        .method(Method::from_bytes(b"PURGE").unwrap())
+        .uri("/video.mp4")
+        .header("host", "example.com")
+        .body(())
+        .unwrap();
+    let response = handler.handle_purge(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: PurgeResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(result.purged, PurgeCounts { slices: 6, metadata: 3 });
+
+    for key in &keys {
+        assert!(metadata_cache.get(key).is_none());
+        for range in ranges(2 * SLICE_SIZE) {
+            assert!(cache.lookup(key, &range).await.unwrap().is_none());
+        }
+    }
+    assert!(cache.lookup(&other, &range).await.unwrap().is_some());
+}
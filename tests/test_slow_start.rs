@@ -0,0 +1,96 @@
+//! Integration tests for subrequest concurrency slow-start
+//!
+//! These tests simulate a cold start against a slow mock origin and check
+//! that concurrency grows over successive batches of slice requests.
+
+mod common;
+
+use common::RangeOrigin;
+use pingora_slice::{
+    ByteRange, FileMetadata, SliceConfig, SliceContext, SliceProxy, SliceSpec, SlowStartConfig,
+};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wiremock::MockServer;
+
+const SLICE_SIZE: u64 = 1024;
+const SLICES_PER_BATCH: u64 = 8;
+const ORIGIN_DELAY: Duration = Duration::from_millis(100);
+
+/// Start an origin that answers every range request after `ORIGIN_DELAY`,
+/// recording when each request arrived
+async fn start_slow_origin(arrivals: Arc<Mutex<Vec<Instant>>>) -> MockServer {
+    RangeOrigin::from_fn((SLICE_SIZE * SLICES_PER_BATCH) as usize, |_| 0)
+        .on_get(move |_| arrivals.lock().unwrap().push(Instant::now()))
+        .delay(|_| ORIGIN_DELAY)
+        .start()
+        .await
+}
+
+/// Largest number of requests that arrived within one origin delay of each other
+fn peak_concurrency(arrivals: &[Instant]) -> usize {
+    let window = ORIGIN_DELAY - Duration::from_millis(20);
+    arrivals
+        .iter()
+        .map(|&t| {
+            arrivals
+                .iter()
+                .filter(|&&other| other >= t && other - t < window)
+                .count()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn test_concurrency_ramps_over_cold_start_batches() {
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+    let origin = start_slow_origin(arrivals.clone()).await;
+
+    let config = Arc::new(SliceConfig {
+        slice_size: SLICE_SIZE as usize,
+        max_concurrent_subrequests: 8,
+        slow_start: Some(SlowStartConfig {
+            enabled: true,
+            initial_concurrency: 1,
+            warmup_secs: 0,
+        }),
+        ..Default::default()
+    });
+    let proxy = SliceProxy::new(config);
+    let ramp = proxy.concurrency_ramp().unwrap();
+    assert_eq!(ramp.current_limit(), 1);
+
+    let mut peaks = Vec::new();
+    let mut limits = Vec::new();
+    for batch in 0..3 {
+        arrivals.lock().unwrap().clear();
+
+        let mut ctx = SliceContext::new();
+        ctx.set_metadata(FileMetadata::new(SLICE_SIZE * SLICES_PER_BATCH, true));
+        ctx.set_slices(
+            (0..SLICES_PER_BATCH)
+                .map(|i| {
+                    let start = i * SLICE_SIZE;
+                    SliceSpec::new(i as usize, ByteRange::new(start, start + SLICE_SIZE - 1).unwrap())
+                })
+                .collect(),
+        );
+        ctx.enable_slicing();
+
+        let url = format!("{}/batch{}.bin", origin.uri(), batch);
+        proxy.handle_slice_request(&url, &ctx).await.unwrap();
+
+        peaks.push(peak_concurrency(&arrivals.lock().unwrap()));
+        limits.push(proxy.metrics().get_stats().effective_concurrency);
+    }
+
+    // The first batch starts with a single request in flight, not the maximum
+    assert!(peaks[0] < 8, "first batch peak {:?}", peaks);
+    assert!(peaks[2] > peaks[0], "peaks did not grow: {:?}", peaks);
+    assert!(peaks.iter().all(|&p| p <= 8), "peaks above max: {:?}", peaks);
+
+    // Effective concurrency grows batch over batch
+    assert!(limits.windows(2).all(|w| w[1] > w[0]), "limits: {:?}", limits);
+    assert!(limits[2] <= 8);
+}
@@ -1,10 +1,54 @@
 //! Integration tests for SubrequestManager
 //!
-//! Note: These tests require a server that supports Range requests.
+//! Note: The httpbin.org tests require a server that supports Range requests.
 //! httpbin.org does not support Range requests, so these tests are ignored by default.
 //! To run these tests, set up a local server that supports Range requests.
+//! The Content-Range validation tests use a local mock origin and always run.
 
-use pingora_slice::{ByteRange, SliceSpec, SubrequestManager};
+mod common;
+
+use common::requested_range;
+use http::{HeaderMap, Method};
+use pingora_slice::{
+    ByteRange, SliceConfig, SliceContext, SliceError, SliceProxy, SliceSpec, SubrequestManager,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Origin whose HEAD reports `size` and whose range responses always
+/// carry `content_range`
+async fn start_origin(size: usize, content_range: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", size.to_string().as_str())
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(206)
+                .insert_header("Content-Range", content_range)
+                .set_body_bytes(vec![0u8; 1024]),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn count(server: &MockServer, method: wiremock::http::Method) -> usize {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|req| req.method == method)
+        .count()
+}
 
 #[tokio::test]
 #[ignore = "Requires a server that supports Range requests"]
@@ -118,7 +162,6 @@ async fn test_subrequest_manager_concurrent_limit() {
 #[test]
 fn test_retry_policy_exponential_backoff() {
     use pingora_slice::RetryPolicy;
-    use std::time::Duration;
     
     let policy = RetryPolicy::new(4);
     
@@ -128,3 +171,186 @@ fn test_retry_policy_exponential_backoff() {
     assert_eq!(policy.backoff_duration(2), Duration::from_millis(400));
     assert_eq!(policy.backoff_duration(3), Duration::from_millis(800));
 }
+
+#[tokio::test]
+async fn test_mismatched_content_range_is_retried_then_fails() {
+    let origin = start_origin(4096, "bytes 0-1023/4096").await;
+    let manager = SubrequestManager::new(4, 1);
+    let slice = SliceSpec::new(1, ByteRange::new(1024, 2047).unwrap());
+
+    let result = manager
+        .fetch_single_slice(&slice, &format!("{}/file.bin", origin.uri()))
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(SliceError::SubrequestFailed { slice_index: 1, attempts: 2 })
+    ));
+    assert_eq!(count(&origin, wiremock::http::Method::Get).await, 2);
+}
+
+#[tokio::test]
+async fn test_content_range_total_must_match_expected_size() {
+    let origin = start_origin(4096, "bytes 0-1023/8192").await;
+    let url = format!("{}/file.bin", origin.uri());
+    let slice = SliceSpec::new(0, ByteRange::new(0, 1023).unwrap());
+
+    // Without an expected size only the range is checked
+    let manager = SubrequestManager::new(4, 0);
+    assert!(manager.fetch_single_slice(&slice, &url).await.is_ok());
+
+    let manager = SubrequestManager::new(4, 0).with_expected_size(4096);
+    assert!(manager.fetch_single_slice(&slice, &url).await.is_err());
+}
+
+#[tokio::test]
+async fn test_proxy_rejects_slices_of_resized_object() {
+    // Metadata says 2KB, but the slices come from an 8KB object
+    let origin = start_origin(2048, "bytes 0-1023/8192").await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        max_retries: 0,
+        ..Default::default()
+    }));
+    let url = format!("{}/file.bin", origin.uri());
+
+    for _ in 0..2 {
+        let mut ctx = SliceContext::new();
+        let passthrough = proxy
+            .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+            .await
+            .unwrap();
+        assert!(!passthrough);
+        let result = proxy.handle_slice_request(&url, &ctx).await;
+        assert!(matches!(result, Err(SliceError::SubrequestFailed { .. })));
+    }
+
+    // The failed fetch dropped the cached metadata, so it was probed again
+    assert_eq!(count(&origin, wiremock::http::Method::Head).await, 2);
+}
+
+/// Origin of a 2KB file whose slice at `failing_range` always fails with 503
+async fn start_failing_origin(failing_range: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Length", "2048")
+                .insert_header("Accept-Ranges", "bytes"),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(header("range", failing_range))
+        .respond_with(ResponseTemplate::new(503))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(|req: &wiremock::Request| {
+            let (start, end) = requested_range(req);
+            ResponseTemplate::new(206)
+                .insert_header("Content-Range", format!("bytes {}-{}/2048", start, end).as_str())
+                .set_body_bytes(vec![0u8; end - start + 1])
+        })
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_deadline_aborts_request_before_retries_run_out() {
+    let origin = start_failing_origin("bytes=1024-2047").await;
+    let slices = vec![
+        SliceSpec::new(0, ByteRange::new(0, 1023).unwrap()),
+        SliceSpec::new(1, ByteRange::new(1024, 2047).unwrap()),
+    ];
+
+    // Five retries would back off for 3.1s in total
+    let manager = SubrequestManager::new(4, 5).with_deadline(Duration::from_millis(500));
+    let start = Instant::now();
+    let result = manager
+        .fetch_slices(slices, &format!("{}/file.bin", origin.uri()))
+        .await;
+
+    assert!(matches!(result, Err(SliceError::DeadlineExceeded(_))));
+    assert!(start.elapsed() < Duration::from_millis(600));
+    assert!(count(&origin, wiremock::http::Method::Get).await < 1 + 6);
+}
+
+#[tokio::test]
+async fn test_retry_budget_is_shared_by_slices() {
+    let origin = start_failing_origin("bytes=0-1023").await;
+    let slices = vec![SliceSpec::new(0, ByteRange::new(0, 1023).unwrap())];
+    let manager = SubrequestManager::new(4, 3).with_retry_budget(1);
+
+    let result = manager
+        .fetch_slices(slices, &format!("{}/file.bin", origin.uri()))
+        .await;
+
+    // One retry instead of three
+    assert!(matches!(result, Err(SliceError::DeadlineExceeded(_))));
+    assert_eq!(count(&origin, wiremock::http::Method::Get).await, 2);
+}
+
+#[tokio::test]
+async fn test_proxy_applies_request_deadline() {
+    let origin = start_failing_origin("bytes=1024-2047").await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        max_retries: 5,
+        request_deadline_ms: Some(300),
+        ..Default::default()
+    }));
+    let url = format!("{}/file.bin", origin.uri());
+
+    let mut ctx = SliceContext::new();
+    proxy
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    let start = Instant::now();
+    let error = proxy.handle_slice_request(&url, &ctx).await.unwrap_err();
+
+    assert!(matches!(error, SliceError::DeadlineExceeded(_)));
+    assert_eq!(error.to_http_status(), 504);
+    assert!(start.elapsed() < Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn test_whole_object_fetch_streams_slices() {
+    let server = MockServer::start().await;
+    let body: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+        .mount(&server)
+        .await;
+    let url = format!("{}/file", server.uri());
+    let slices = vec![
+        SliceSpec::new(2, ByteRange::new(2048, 3071).unwrap()),
+        SliceSpec::new(1, ByteRange::new(1024, 2047).unwrap()),
+    ];
+
+    // Slices arrive in body order, each cut to its range
+    let manager = SubrequestManager::new(4, 3).with_expected_size(4096);
+    let mut stream =
+        manager.fetch_whole_object_stream_until_abort(slices.clone(), &url, &Default::default(), true);
+    let first = stream.recv().await.unwrap().unwrap();
+    assert_eq!(first.slice_index, 1);
+    assert_eq!(first.status, 200);
+    assert_eq!(first.data, body[1024..2048]);
+    let second = stream.recv().await.unwrap().unwrap();
+    assert_eq!(second.slice_index, 2);
+    assert_eq!(second.data, body[2048..3072]);
+    assert!(stream.recv().await.is_none());
+
+    // An object of another size is rejected before any slice is cut
+    let manager = SubrequestManager::new(4, 3).with_expected_size(8192);
+    let result = manager.fetch_whole_object(&slices, &url).await;
+    assert!(matches!(result, Err(SliceError::ContentRangeMismatch { .. })));
+
+    // As is a body ending before the last slice
+    let slices = vec![SliceSpec::new(4, ByteRange::new(4096, 5119).unwrap())];
+    let result = SubrequestManager::new(4, 3).fetch_whole_object(&slices, &url).await;
+    assert!(matches!(result, Err(SliceError::AssemblyError(_))));
+}
@@ -0,0 +1,150 @@
+//! Integration tests for request tracing (`otel` feature)
+//!
+//! Spans are collected with an in-memory exporter while a 3-slice request
+//! runs through the proxy, then checked for the expected tree shape and for
+//! trace context propagation in both directions.
+
+#![cfg(feature = "otel")]
+
+mod common;
+
+use common::start_origin;
+use http::{HeaderMap, HeaderValue, Method};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use pingora_slice::{telemetry, SliceConfig, SliceContext, SliceProxy};
+use std::sync::Arc;
+use tracing_subscriber::layer::SubscriberExt;
+
+const FILE_SIZE: usize = 3072;
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const CLIENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+fn attribute(span: &SpanData, key: &str) -> Option<String> {
+    span.attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| kv.value.to_string())
+}
+
+#[tokio::test]
+async fn test_span_tree_for_sliced_request() {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry().with(telemetry::layer(&provider));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let origin = start_origin(FILE_SIZE).await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        ..Default::default()
+    }));
+    let url = format!("{}/file.bin", origin.uri());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "traceparent",
+        HeaderValue::from_str(&format!("00-{}-{}-01", TRACE_ID, CLIENT_SPAN_ID)).unwrap(),
+    );
+
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, &url, &headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+    let (_, _, body) = proxy.handle_slice_request(&url, &ctx).await.unwrap();
+    assert_eq!(body.concat().len(), FILE_SIZE);
+    drop(ctx);
+    provider.force_flush().unwrap();
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let root = spans.iter().find(|s| s.name == "slice_request").unwrap();
+    assert_eq!(root.span_context.trace_id().to_string(), TRACE_ID);
+    assert_eq!(root.parent_span_id.to_string(), CLIENT_SPAN_ID);
+    assert_eq!(attribute(root, "slices").as_deref(), Some("3"));
+
+    let root_id = root.span_context.span_id();
+    let mut children: Vec<&str> = spans
+        .iter()
+        .filter(|s| s.parent_span_id == root_id)
+        .map(|s| s.name.as_ref())
+        .collect();
+    children.sort();
+    assert_eq!(
+        children,
+        [
+            "assemble",
+            "cache_lookup",
+            "cache_store",
+            "cache_store",
+            "cache_store",
+            "fetch_metadata",
+            "subrequest",
+            "subrequest",
+            "subrequest",
+        ]
+    );
+
+    let subrequests: Vec<&SpanData> = spans.iter().filter(|s| s.name == "subrequest").collect();
+    let mut indices: Vec<String> = subrequests
+        .iter()
+        .map(|s| attribute(s, "slice_index").unwrap())
+        .collect();
+    indices.sort();
+    assert_eq!(indices, ["0", "1", "2"]);
+    for span in &subrequests {
+        assert_eq!(attribute(span, "bytes").as_deref(), Some("1024"));
+        assert_eq!(attribute(span, "attempt").as_deref(), Some("1"));
+        assert!(attribute(span, "range").unwrap().starts_with("bytes="));
+    }
+
+    // Every origin request carries the trace, parented to the span that sent it
+    let span_ids: Vec<String> = spans
+        .iter()
+        .filter(|s| s.name == "subrequest" || s.name == "fetch_metadata")
+        .map(|s| s.span_context.span_id().to_string())
+        .collect();
+    let requests = origin.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 4);
+    for req in requests {
+        let traceparent = req.headers.get(&"traceparent".into()).unwrap().last().to_string();
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(parts[1], TRACE_ID);
+        assert!(span_ids.iter().any(|id| id == parts[2]), "{}", traceparent);
+    }
+}
+
+#[tokio::test]
+async fn test_request_without_traceparent_starts_new_trace() {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry().with(telemetry::layer(&provider));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let origin = start_origin(FILE_SIZE).await;
+    let proxy = SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        ..Default::default()
+    }));
+    let url = format!("{}/file.bin", origin.uri());
+
+    let mut ctx = SliceContext::new();
+    proxy
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    drop(ctx);
+    provider.force_flush().unwrap();
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let root = spans.iter().find(|s| s.name == "slice_request").unwrap();
+    assert_ne!(root.span_context.trace_id().to_string(), TRACE_ID);
+    assert!(spans
+        .iter()
+        .filter(|s| s.name == "fetch_metadata")
+        .all(|s| s.span_context.trace_id() == root.span_context.trace_id()));
+}
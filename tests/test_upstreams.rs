@@ -0,0 +1,153 @@
+//! Integration tests for the upstream pool
+//!
+//! Requests go to a URL whose host is never resolved; the pool rewrites it
+//! to one of the mock origins, which both serve the same 4KB file.
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, Method};
+use pingora_slice::{SliceConfig, SliceContext, SliceProxy, UpstreamPolicy, UpstreamPoolConfig};
+use std::net::TcpListener;
+use std::sync::Arc;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const FILE_SIZE: usize = 4096;
+const URL: &str = "http://origin.invalid/file.bin";
+
+async fn start_origin() -> MockServer {
+    RangeOrigin::from_fn(FILE_SIZE, |i| i as u8).start().await
+}
+
+/// Address nothing is listening on
+fn closed_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn proxy(peers: Vec<String>, policy: UpstreamPolicy, max_failures: u32) -> SliceProxy {
+    SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: 1024,
+        upstream_pool: Some(UpstreamPoolConfig {
+            peers,
+            policy,
+            max_failures,
+            cooldown_secs: 60,
+        }),
+        ..Default::default()
+    }))
+}
+
+async fn fetch(proxy: &SliceProxy) -> Vec<u8> {
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, URL, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough);
+
+    let (status, _, body) = proxy.handle_slice_request(URL, &ctx).await.unwrap();
+    assert!(status.is_success());
+    body.concat()
+}
+
+async fn get_count(server: &MockServer) -> usize {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|req| req.method == wiremock::http::Method::Get)
+        .count()
+}
+
+fn expected_body() -> Vec<u8> {
+    (0..FILE_SIZE).map(|i| i as u8).collect()
+}
+
+#[tokio::test]
+async fn test_round_robin_spreads_slices() {
+    let first = start_origin().await;
+    let second = start_origin().await;
+    let proxy = proxy(
+        vec![first.address().to_string(), second.address().to_string()],
+        UpstreamPolicy::RoundRobin,
+        3,
+    );
+
+    assert_eq!(fetch(&proxy).await, expected_body());
+
+    let (first_gets, second_gets) = (get_count(&first).await, get_count(&second).await);
+    assert_eq!(first_gets + second_gets, FILE_SIZE / 1024);
+    assert!(first_gets > 0 && second_gets > 0);
+
+    let stats = proxy.metrics().get_stats();
+    assert_eq!(stats.upstream_requests.values().sum::<u64>(), 5); // HEAD + 4 slices
+    assert!(stats.upstream_failures.is_empty());
+}
+
+#[tokio::test]
+async fn test_failover_when_primary_is_down() {
+    let backup = start_origin().await;
+    let primary = closed_address();
+    let proxy = proxy(
+        vec![primary.clone(), backup.address().to_string()],
+        UpstreamPolicy::Failover,
+        1,
+    );
+
+    assert_eq!(fetch(&proxy).await, expected_body());
+    assert_eq!(get_count(&backup).await, FILE_SIZE / 1024);
+
+    // The primary is out of rotation after its first failure
+    let stats = proxy.metrics().get_stats();
+    assert_eq!(stats.upstream_failures.get(&primary), Some(&1));
+    assert_eq!(stats.upstream_requests.get(&primary), Some(&1));
+    assert_eq!(proxy.upstream_peer(&SliceContext::new()).unwrap(), backup.address().to_string());
+}
+
+#[tokio::test]
+async fn test_slice_retry_uses_another_peer() {
+    let backup = start_origin().await;
+    let primary = closed_address();
+    // The primary stays in rotation, so every slice fails there first
+    let proxy = proxy(
+        vec![primary.clone(), backup.address().to_string()],
+        UpstreamPolicy::Failover,
+        100,
+    );
+
+    assert_eq!(fetch(&proxy).await, expected_body());
+
+    let stats = proxy.metrics().get_stats();
+    assert_eq!(stats.upstream_failures.get(&primary), Some(&(1 + FILE_SIZE as u64 / 1024)));
+    assert_eq!(stats.retried_subrequests, FILE_SIZE as u64 / 1024);
+}
+
+#[tokio::test]
+async fn test_failover_from_unavailable_origin() {
+    let failing = MockServer::start().await;
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&failing)
+        .await;
+    let healthy = start_origin().await;
+    let (failing_addr, healthy_addr) = (failing.address().to_string(), healthy.address().to_string());
+    // Fails the probe and each of the four slices once, then leaves rotation
+    let proxy = proxy(
+        vec![failing_addr.clone(), healthy_addr.clone()],
+        UpstreamPolicy::Failover,
+        5,
+    );
+
+    assert_eq!(fetch(&proxy).await, expected_body());
+    assert_eq!(get_count(&healthy).await, FILE_SIZE / 1024);
+
+    // One failover for the metadata probe and one for each slice
+    let stats = proxy.metrics().get_stats();
+    let pair = (failing_addr.clone(), healthy_addr.clone());
+    assert_eq!(stats.upstream_failovers.get(&pair), Some(&(1 + FILE_SIZE as u64 / 1024)));
+    assert_eq!(stats.upstream_failovers.len(), 1);
+    assert_eq!(stats.upstream_failures.get(&failing_addr), Some(&(1 + FILE_SIZE as u64 / 1024)));
+    assert!(!proxy.upstream_pool().unwrap().is_available(0));
+}
@@ -0,0 +1,103 @@
+//! Integration tests for Vary-aware slice caching
+//!
+//! The mock origin serves a different body per `Accept-Encoding` and answers
+//! with `Vary: Accept-Encoding`, so each encoding must get its own cache
+//! entries.
+
+mod common;
+
+use common::RangeOrigin;
+use http::{HeaderMap, HeaderValue, Method};
+use pingora_slice::{SliceConfig, SliceContext, SliceProxy};
+use std::sync::Arc;
+use wiremock::{MockServer, Request};
+
+const SLICE_SIZE: usize = 1024;
+
+/// Body served for `accept_encoding`: gzip gets 2KB of `g`, others 3KB of `i`
+fn variant_body(accept_encoding: Option<&str>) -> Vec<u8> {
+    match accept_encoding {
+        Some("gzip") => vec![b'g'; 2048],
+        _ => vec![b'i'; 3072],
+    }
+}
+
+fn accept_encoding(req: &Request) -> Option<String> {
+    req.headers
+        .get(&"accept-encoding".into())
+        .map(|v| v.last().as_str().to_string())
+}
+
+async fn start_vary_origin(vary: &'static str) -> MockServer {
+    RangeOrigin::with_file(|req| variant_body(accept_encoding(req).as_deref()))
+        .header("Vary", vary)
+        .start()
+        .await
+}
+
+fn create_proxy() -> SliceProxy {
+    SliceProxy::new(Arc::new(SliceConfig {
+        slice_size: SLICE_SIZE,
+        ..Default::default()
+    }))
+}
+
+/// Run one request through the proxy and return the context and body
+async fn fetch(proxy: &SliceProxy, url: &str, encoding: &'static str) -> (SliceContext, Vec<u8>) {
+    let mut headers = HeaderMap::new();
+    headers.insert("accept-encoding", HeaderValue::from_static(encoding));
+
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, url, &headers, &mut ctx)
+        .await
+        .unwrap();
+    assert!(!passthrough, "request should be sliced");
+
+    let (_, response_headers, body) = proxy.handle_slice_request(url, &ctx).await.unwrap();
+    assert_eq!(response_headers.get("vary").unwrap(), "accept-encoding");
+    (ctx, body.concat())
+}
+
+#[tokio::test]
+async fn test_each_encoding_gets_its_own_variant() {
+    let origin = start_vary_origin("Accept-Encoding").await;
+    let url = format!("{}/video.mp4", origin.uri());
+    let proxy = create_proxy();
+
+    let (ctx, body) = fetch(&proxy, &url, "gzip").await;
+    assert_eq!(body, variant_body(Some("gzip")));
+    assert_eq!(ctx.cached_slice_count(), 0);
+
+    // Same URL, different encoding: must not be served the gzip entry
+    let (ctx, body) = fetch(&proxy, &url, "identity").await;
+    assert_eq!(body, variant_body(Some("identity")));
+    assert_eq!(ctx.cached_slice_count(), 0);
+
+    // Both variants are now cached under separate keys
+    let (ctx, body) = fetch(&proxy, &url, "gzip").await;
+    assert_eq!(body, variant_body(Some("gzip")));
+    assert_eq!(ctx.cached_slice_count(), ctx.slice_count());
+
+    let (ctx, body) = fetch(&proxy, &url, "identity").await;
+    assert_eq!(body, variant_body(Some("identity")));
+    assert_eq!(ctx.cached_slice_count(), ctx.slice_count());
+    assert_ne!(ctx.cache_key(&url), url);
+
+    assert_eq!(proxy.cache_arc().get_stats().l1_entries, 2 + 3);
+}
+
+#[tokio::test]
+async fn test_unlisted_vary_header_is_not_sliced() {
+    let origin = start_vary_origin("Cookie").await;
+    let url = format!("{}/video.mp4", origin.uri());
+    let proxy = create_proxy();
+
+    let mut ctx = SliceContext::new();
+    let passthrough = proxy
+        .request_filter(&Method::GET, &url, &HeaderMap::new(), &mut ctx)
+        .await
+        .unwrap();
+    assert!(passthrough);
+    assert!(!ctx.is_slice_enabled());
+}